@@ -0,0 +1,57 @@
+// Helpers shared by the examples, each example pulls this in with `mod common;`.
+#![allow(dead_code)]
+
+use std::{cell::RefCell, future::Future, rc::Rc};
+
+use rad::prelude::*;
+
+pub const SPRITE_WGSL: &str = include_str!("sprite.wgsl");
+
+/// Runs an example app inside the tokio runtime the asset loaders expect.
+pub fn run<A, F, Fut>(factory: F) -> anyhow::Result<()>
+where
+    A: RadApp + 'static,
+    F: Fn(Rc<RefCell<RenderWindow>>) -> Fut,
+    Fut: Future<Output = anyhow::Result<A>>,
+{
+    env_logger::init();
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(Radium::start(factory))
+}
+
+/// Flat colored screen space sprites, see sprite.wgsl. Drawn depth tested but without
+/// writing depth so they can go in the same pass as 3D geometry.
+pub fn sprite_shader(window: &RenderWindow) -> Shader {
+    let device = window.device();
+    let camera_layout = window.camera().layout();
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Example Sprite Pipeline Layout"),
+        bind_group_layouts: &[Some(&camera_layout)],
+        immediate_size: 0,
+    });
+    Shader::new(
+        device,
+        wgpu::ShaderModuleDescriptor {
+            label: Some("Example Sprite Shader"),
+            source: wgpu::ShaderSource::Wgsl(SPRITE_WGSL.into()),
+        },
+        layout,
+        window.surface_config().format,
+        Some(Texture::DEPTH_FORMAT),
+        &[SpriteVertex::buffer_layout()],
+        PipelineOptions::default().with_depth(DepthMode::ReadOnly),
+        None,
+    )
+}
+
+/// A QuadBuffer whose default material is sprite_shader.
+pub fn sprite_buffer(window: &RenderWindow, capacity: usize) -> QuadBuffer {
+    let shader = sprite_shader(window);
+    let mut quads = QuadBuffer::new(window.device(), capacity);
+    quads.set_default_material(
+        QuadMaterial::from_shader(&shader).with_bind_group(0, window.camera_bind_group()),
+    );
+    quads
+}
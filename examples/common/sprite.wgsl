@@ -0,0 +1,47 @@
+// Screen space sprites for the examples. Positions are in pixels with the origin at the
+// bottom left of the window, converted to clip space with the resolution from Globals.
+
+struct CameraUniform {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+};
+
+struct Globals {
+    resolution: vec2<f32>,
+    cursor: vec2<f32>,
+    time: f32,
+    delta_time: f32,
+    frame: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+@group(0) @binding(1)
+var<uniform> globals: Globals;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    let ndc = in.position.xy / max(globals.resolution, vec2<f32>(1.0)) * 2.0 - 1.0;
+    out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+    out.tex_coords = in.tex_coords;
+    out.color = in.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
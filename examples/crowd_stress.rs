@@ -0,0 +1,239 @@
+// cargo run --release --example crowd_stress [-- <character count>]
+//
+// Walks 10k (or the given count of) vertex animated figures around a grid in a single
+// instanced draw, each with its own clip and time offset. Far figures update their
+// animation less often, see CrowdLod. Frame times and animation updates are logged once a
+// second, run with RUST_LOG=info to see them.
+//   WASD/Space/Shift + mouse drag: move the camera, scroll: zoom
+//   L: toggle animation LOD
+
+mod common;
+
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use cgmath::{Deg, Quaternion, Rotation3, Vector3};
+use rad::{
+    gfx::{
+        crowd::{Crowd, CrowdInstance, CrowdLod, VertexAnimationData},
+        wgpu::vertex::Vertex3D,
+    },
+    prelude::*,
+    sys::{
+        fs::{compute_tangents, load_model},
+        rand::Rng,
+    },
+};
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+const DEFAULT_CHARACTERS: usize = 10_000;
+const SPACING: f32 = 2.5;
+const SEGMENTS: u32 = 8;
+const HEIGHT: f32 = 1.8;
+
+struct CrowdStress {
+    window: Rc<RefCell<RenderWindow>>,
+    mesh: Mesh,
+    material: Material,
+    crowd: Crowd,
+    lod: bool,
+    frames: u32,
+    updated: usize,
+    elapsed: Duration,
+}
+
+impl CrowdStress {
+    async fn new(window: Rc<RefCell<RenderWindow>>, count: usize) -> anyhow::Result<Self> {
+        let (device, queue, texture_layout, camera_layout, light_layout, format) = {
+            let window = window.borrow();
+            let format = window.surface_config().format;
+            (
+                window.device().clone(),
+                window.device_queue().clone(),
+                window.texture_bind_group_layout().clone(),
+                window.camera().layout(),
+                window.light_bind_group_layout(),
+                format,
+            )
+        };
+        let material = load_model("cube.obj", &device, &queue, &texture_layout)
+            .await?
+            .materials
+            .remove(0);
+
+        let (vertices, indices) = figure();
+        let mesh = Mesh::new(&device, "Crowd Figure", &vertices, &indices, 0);
+        let mut animation = VertexAnimationData::new(vertices.len());
+        let walk = animation.bake_clip("walk", 30.0, true, 30, &vertices, |time, [x, y, z]| {
+            let phase = time * std::f32::consts::TAU;
+            let sway = phase.sin() * 0.15 * (y / HEIGHT);
+            [x + sway, y + (phase * 2.0).sin().abs() * 0.08, z]
+        })?;
+        let wave = animation.bake_clip("wave", 30.0, true, 45, &vertices, |time, [x, y, z]| {
+            let phase = time / 1.5 * std::f32::consts::TAU;
+            let bend = (y / HEIGHT).powi(2) * phase.sin() * 0.4;
+            [x, y, z + bend]
+        })?;
+        let mut crowd = Crowd::new(
+            &device,
+            &queue,
+            format,
+            [&texture_layout, &camera_layout, &light_layout],
+            &animation,
+            CrowdLod::default(),
+        )?;
+
+        let mut rng = Rng::new(0xc0ffee);
+        let side = (count as f32).sqrt().ceil() as usize;
+        *crowd.instances_mut() = (0..count)
+            .map(|i| {
+                let (x, z) = ((i % side) as f32, (i / side) as f32);
+                let position =
+                    Vector3::new((x - side as f32 * 0.5) * SPACING, 0.0, -z * SPACING - 4.0);
+                let rotation = Quaternion::from_angle_y(Deg(rng.range_f32(0.0..360.0)));
+                let clip = if rng.next_f32() < 0.8 { walk } else { wave };
+                CrowdInstance::new(position, rotation, clip)
+                    .with_time_offset(rng.range_f32(0.0..2.0))
+                    .with_speed(rng.range_f32(0.8..1.2))
+            })
+            .collect();
+
+        log::info!("crowd_stress => {} characters", count);
+        Ok(Self {
+            window,
+            mesh,
+            material,
+            crowd,
+            lod: true,
+            frames: 0,
+            updated: 0,
+            elapsed: Duration::ZERO,
+        })
+    }
+}
+
+/// A box figure split into SEGMENTS rings up its height so it bends smoothly.
+fn figure() -> (Vec<Vertex3D>, Vec<u32>) {
+    let (w, d) = (0.25, 0.15);
+    let sides: [([f32; 3], [f32; 3], [f32; 3]); 4] = [
+        ([-w, 0.0, d], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([w, 0.0, d], [0.0, 0.0, -1.0], [1.0, 0.0, 0.0]),
+        ([w, 0.0, -d], [-1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        ([-w, 0.0, -d], [0.0, 0.0, 1.0], [-1.0, 0.0, 0.0]),
+    ];
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for (corner, along, normal) in sides {
+        let width = if along[0] != 0.0 { w * 2.0 } else { d * 2.0 };
+        let base = vertices.len() as u32;
+        for ring in 0..=SEGMENTS {
+            let v = ring as f32 / SEGMENTS as f32;
+            for u in [0.0, 1.0] {
+                vertices.push(Vertex3D {
+                    position: [
+                        corner[0] + along[0] * width * u,
+                        v * HEIGHT,
+                        corner[2] + along[2] * width * u,
+                    ],
+                    tex_coords: [u, 1.0 - v],
+                    normal,
+                    ..Default::default()
+                });
+            }
+        }
+        for ring in 0..SEGMENTS {
+            let i = base + ring * 2;
+            indices.extend([i, i + 1, i + 3, i, i + 3, i + 2]);
+        }
+    }
+    let top = vertices.len() as u32;
+    for [x, z] in [[-w, d], [w, d], [w, -d], [-w, -d]] {
+        vertices.push(Vertex3D {
+            position: [x, HEIGHT, z],
+            tex_coords: [(x + w) / (2.0 * w), (z + d) / (2.0 * d)],
+            normal: [0.0, 1.0, 0.0],
+            ..Default::default()
+        });
+    }
+    indices.extend([top, top + 1, top + 2, top, top + 2, top + 3]);
+    compute_tangents(&mut vertices, &indices);
+    (vertices, indices)
+}
+
+impl RadApp for CrowdStress {
+    fn frame_update(&mut self, dt: Duration) {
+        let viewer = self.window.borrow().camera_uniform().view_position();
+        self.crowd.animate(dt, viewer);
+
+        self.frames += 1;
+        self.updated += self.crowd.updated();
+        self.elapsed += dt;
+        if self.elapsed >= Duration::from_secs(1) {
+            log::info!(
+                "crowd_stress => {} fps, {:.2} ms/frame, {} animation updates/frame",
+                self.frames,
+                self.elapsed.as_secs_f64() * 1000.0 / self.frames as f64,
+                self.updated / self.frames as usize
+            );
+            self.frames = 0;
+            self.updated = 0;
+            self.elapsed = Duration::ZERO;
+        }
+    }
+
+    fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
+        self.window
+            .borrow_mut()
+            .camera_mut()
+            .process_mouse(mouse_dx, mouse_dy);
+    }
+
+    fn handle_window_events(&mut self, event: &WindowEvent) -> InputEventStatus {
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key),
+                        state,
+                        ..
+                    },
+                ..
+            } => {
+                if *state == ElementState::Pressed && *key == KeyCode::KeyL {
+                    self.lod = !self.lod;
+                    self.crowd.set_lod(if self.lod {
+                        CrowdLod::default()
+                    } else {
+                        CrowdLod::full()
+                    });
+                    log::info!("crowd_stress => animation LOD {}", self.lod);
+                    return InputEventStatus::Processing;
+                }
+                self.window
+                    .borrow_mut()
+                    .camera_mut()
+                    .process_keyboard(*key, *state)
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.window.borrow_mut().camera_mut().process_scroll(delta);
+                InputEventStatus::Processing
+            }
+            _ => InputEventStatus::Done,
+        }
+    }
+
+    fn draw_frame(&mut self, ctx: &mut DrawCtx) -> Result<(), SurfaceError> {
+        self.crowd.draw(ctx, &self.mesh, &self.material);
+        Ok(())
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let count = match std::env::args().nth(1) {
+        Some(arg) => arg.parse()?,
+        None => DEFAULT_CHARACTERS,
+    };
+    common::run(move |window| CrowdStress::new(window, count))
+}
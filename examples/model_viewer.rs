@@ -0,0 +1,187 @@
+// cargo run --release --example model_viewer [-- <model in public/>]
+//
+// Loads an obj model (teapot_small.obj by default) and draws a 3x3 grid of it, each copy
+// turning at its own speed while the scene light orbits around them.
+//   WASD/Space/Shift + mouse drag: move the camera, scroll: zoom
+//   L: pause the light, C: cycle the light color, F: toggle fog
+
+mod common;
+
+use std::{cell::RefCell, rc::Rc, sync::Arc, time::Duration};
+
+use cgmath::{Deg, Quaternion, Rotation3, Vector3};
+use rad::{
+    gfx::{
+        environment::{Environment, Fog},
+        light::LightUniform,
+        wgpu::buffer::Instance,
+    },
+    prelude::*,
+    sys::fs::load_model,
+};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+const LIGHT_COLORS: [[f32; 4]; 3] = [
+    [1.0, 1.0, 1.0, 0.0],
+    [1.0, 0.6, 0.3, 0.0],
+    [0.3, 0.6, 1.0, 0.0],
+];
+const LIGHT_RADIUS: f32 = 4.0;
+const SPACING: f32 = 4.0;
+
+struct ModelViewer {
+    window: Rc<RefCell<RenderWindow>>,
+    model: Model,
+    light_model: Model,
+    instances: Vec<Instance>,
+    instance_buffer: Arc<wgpu::Buffer>,
+    light_angle: f32,
+    light_paused: bool,
+    light_color: usize,
+    fog: bool,
+}
+
+impl ModelViewer {
+    async fn new(window: Rc<RefCell<RenderWindow>>, name: String) -> anyhow::Result<Self> {
+        let (model, light_model, instances, instance_buffer) = {
+            // wgpu handles are cheap to clone, so the window isn't borrowed across the loads.
+            let (device, queue, layout) = {
+                let window = window.borrow();
+                (
+                    window.device().clone(),
+                    window.device_queue().clone(),
+                    window.texture_bind_group_layout().clone(),
+                )
+            };
+            let model = load_model(&name, &device, &queue, &layout).await?;
+            let light_model = load_model("cube.obj", &device, &queue, &layout).await?;
+
+            let instances = (-1..=1)
+                .flat_map(|z| {
+                    (-1..=1).map(move |x| Instance {
+                        position: Vector3::new(x as f32 * SPACING, 0.0, z as f32 * SPACING),
+                        rotation: Quaternion::from_angle_y(Deg(0.0)),
+                    })
+                })
+                .collect::<Vec<_>>();
+            let raw = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+            let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Model Viewer Instance Buffer"),
+                contents: bytemuck::cast_slice(&raw),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            });
+            (model, light_model, instances, Arc::new(instance_buffer))
+        };
+
+        Ok(Self {
+            window,
+            model,
+            light_model,
+            instances,
+            instance_buffer,
+            light_angle: 0.0,
+            light_paused: false,
+            light_color: 0,
+            fog: false,
+        })
+    }
+
+    fn on_key(&mut self, key: KeyCode) -> bool {
+        let mut window = self.window.borrow_mut();
+        match key {
+            KeyCode::KeyL => self.light_paused = !self.light_paused,
+            KeyCode::KeyC => self.light_color = (self.light_color + 1) % LIGHT_COLORS.len(),
+            KeyCode::KeyF => {
+                self.fog = !self.fog;
+                let environment = if self.fog {
+                    Environment::default()
+                        .with_fog(Fog::Exponential { density: 0.08 }, [0.1, 0.2, 0.3])
+                } else {
+                    Environment::default()
+                };
+                window.set_environment(environment);
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl RadApp for ModelViewer {
+    fn frame_update(&mut self, dt: Duration) {
+        let dt_secs = dt.as_secs_f32();
+        if !self.light_paused {
+            self.light_angle += 60.0 * dt_secs;
+        }
+
+        for (i, instance) in self.instances.iter_mut().enumerate() {
+            let speed = 20.0 + i as f32 * 5.0;
+            instance.rotation = Quaternion::from_angle_y(Deg(speed * dt_secs)) * instance.rotation;
+        }
+        let raw = self
+            .instances
+            .iter()
+            .map(Instance::to_raw)
+            .collect::<Vec<_>>();
+
+        let mut window = self.window.borrow_mut();
+        window.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&raw));
+        let (sin, cos) = self.light_angle.to_radians().sin_cos();
+        window.set_light(LightUniform {
+            position: [cos * LIGHT_RADIUS, 2.5, sin * LIGHT_RADIUS, 0.0],
+            color: LIGHT_COLORS[self.light_color],
+        });
+    }
+
+    fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
+        self.window
+            .borrow_mut()
+            .camera_mut()
+            .process_mouse(mouse_dx, mouse_dy);
+    }
+
+    fn handle_window_events(&mut self, event: &WindowEvent) -> InputEventStatus {
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key),
+                        state,
+                        ..
+                    },
+                ..
+            } => {
+                if *state == ElementState::Pressed && self.on_key(*key) {
+                    return InputEventStatus::Processing;
+                }
+                self.window
+                    .borrow_mut()
+                    .camera_mut()
+                    .process_keyboard(*key, *state)
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.window.borrow_mut().camera_mut().process_scroll(delta);
+                InputEventStatus::Processing
+            }
+            _ => InputEventStatus::Done,
+        }
+    }
+
+    fn draw_frame(&mut self, ctx: &mut DrawCtx) -> Result<(), SurfaceError> {
+        ctx.draw_light_model(&self.light_model);
+        ctx.set_vertex_buffer(1, self.instance_buffer.clone());
+        ctx.draw_model_instanced(&self.model, 0..self.instances.len() as u32);
+        Ok(())
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let name = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "teapot_small.obj".to_string());
+    common::run(move |window| ModelViewer::new(window, name.clone()))
+}
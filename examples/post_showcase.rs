@@ -0,0 +1,253 @@
+// cargo run --release --example post_showcase
+//
+// A grid of cubes run through a post stack of color grading, a custom vignette/wave
+// effect and FXAA, ahead of the built in transition effect.
+//   1: toggle the warm LUT grade, 2: toggle the custom effect, 3: toggle FXAA
+//   T: fade in from black, WASD/Space/Shift + mouse drag: move the camera
+
+mod common;
+
+use std::{cell::RefCell, rc::Rc, sync::Arc, time::Duration};
+
+use cgmath::{Deg, InnerSpace, Quaternion, Rotation3, Vector3, Zero};
+use rad::{
+    eng::transition::{TransitionKind, TRANSITION_EFFECT},
+    gfx::{
+        post::{
+            aa::{AntiAliasMode, AntiAliasing, FxaaSettings},
+            custom::CustomEffect,
+            grade::{ColorGrade, ColorGradeMode, Lut},
+        },
+        wgpu::buffer::Instance,
+    },
+    prelude::*,
+    sys::fs::load_model,
+};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+const GRID: i32 = 10;
+const SPACING: f32 = 3.0;
+const CUSTOM_EFFECT: &str = "vignette_wave";
+
+/// Darkens the corners and wobbles the image sideways, see CustomEffect for the interface.
+const VIGNETTE_WAVE_WGSL: &str = r#"
+struct VignetteParams { strength: f32, radius: f32, wave: f32, _pad: f32 };
+@group(1) @binding(1)
+var<uniform> params: VignetteParams;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let offset = sin(in.uv.y * 40.0 + frame.time * 3.0) * params.wave / frame.resolution.x;
+    let color = textureSample(t_input, s_input, in.uv + vec2<f32>(offset, 0.0));
+    let d = distance(in.uv, vec2<f32>(0.5));
+    let vignette = 1.0 - params.strength * smoothstep(params.radius, 0.75, d);
+    return vec4<f32>(color.rgb * vignette, color.a);
+}
+"#;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct VignetteParams {
+    strength: f32,
+    radius: f32,
+    wave: f32,
+    _pad: f32,
+}
+
+/// Identity LUT pushed towards orange highlights and teal shadows.
+fn warm_lut() -> Lut {
+    let mut lut = Lut::identity(ColorGrade::DEFAULT_LUT_SIZE);
+    for texel in lut.data.iter_mut() {
+        let [r, g, b, a] = texel.map(|c| c as f32 / 255.0);
+        let luma = 0.3 * r + 0.59 * g + 0.11 * b;
+        let graded = [r * 1.1 + 0.05 * luma, g, b * 0.8 + 0.1 * (1.0 - luma), a];
+        *texel = graded.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    }
+    lut
+}
+
+struct PostShowcase {
+    window: Rc<RefCell<RenderWindow>>,
+    model: Model,
+    instance_count: u32,
+    instance_buffer: Arc<wgpu::Buffer>,
+    grade: ColorGrade,
+    aa: AntiAliasing,
+    custom: CustomEffect,
+    custom_enabled: bool,
+}
+
+impl PostShowcase {
+    async fn new(window: Rc<RefCell<RenderWindow>>) -> anyhow::Result<Self> {
+        let (device, queue, layout) = {
+            let window = window.borrow();
+            (
+                window.device().clone(),
+                window.device_queue().clone(),
+                window.texture_bind_group_layout().clone(),
+            )
+        };
+        let model = load_model("cube.obj", &device, &queue, &layout).await?;
+        let (device, queue) = (&device, &queue);
+        let instances = (0..GRID)
+            .flat_map(|z| {
+                (0..GRID).map(move |x| {
+                    let position = Vector3::new(
+                        SPACING * (x - GRID / 2) as f32,
+                        0.0,
+                        SPACING * (z - GRID / 2) as f32,
+                    );
+                    let rotation = if position.is_zero() {
+                        Quaternion::from_angle_y(Deg(0.0))
+                    } else {
+                        Quaternion::from_axis_angle(position.normalize(), Deg(45.0))
+                    };
+                    Instance { position, rotation }.to_raw()
+                })
+            })
+            .collect::<Vec<_>>();
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Post Showcase Instance Buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let mut grade = ColorGrade::new(device, queue);
+        grade.set_lut(device, queue, &warm_lut());
+        grade.set_mode(queue, ColorGradeMode::Lut { strength: 1.0 });
+        let aa = AntiAliasing::new(device);
+        let params = VignetteParams {
+            strength: 0.6,
+            radius: 0.35,
+            wave: 2.0,
+            _pad: 0.0,
+        };
+        let custom = CustomEffect::new(
+            device,
+            CUSTOM_EFFECT,
+            VIGNETTE_WAVE_WGSL,
+            bytemuck::bytes_of(&params),
+        )?;
+
+        {
+            let window = window.borrow();
+            let mut post = window.post_processor().borrow_mut();
+            let effects = [
+                grade.effect(device, &post),
+                custom.effect(device, &post),
+                aa.effect(device, &post),
+            ];
+            for effect in effects {
+                post.insert_before(TRANSITION_EFFECT, effect)?;
+            }
+        }
+
+        let mut showcase = Self {
+            window: window.clone(),
+            model,
+            instance_count: instances.len() as u32,
+            instance_buffer: Arc::new(instance_buffer),
+            grade,
+            aa,
+            custom,
+            custom_enabled: true,
+        };
+        showcase.toggle_aa();
+        Ok(showcase)
+    }
+
+    fn toggle_grade(&mut self) {
+        let window = self.window.borrow();
+        let mode = match self.grade.mode() {
+            ColorGradeMode::Passthrough => ColorGradeMode::Lut { strength: 1.0 },
+            _ => ColorGradeMode::Passthrough,
+        };
+        self.grade.set_mode(window.device_queue(), mode);
+    }
+
+    fn toggle_custom(&mut self) {
+        self.custom_enabled = !self.custom_enabled;
+        let window = self.window.borrow();
+        window
+            .post_processor()
+            .borrow_mut()
+            .set_enabled(CUSTOM_EFFECT, self.custom_enabled);
+    }
+
+    fn toggle_aa(&mut self) {
+        let window = self.window.borrow();
+        let mode = match self.aa.mode() {
+            AntiAliasMode::Off => AntiAliasMode::Fxaa(FxaaSettings::HIGH),
+            AntiAliasMode::Fxaa(_) => AntiAliasMode::Off,
+        };
+        let mut post = window.post_processor().borrow_mut();
+        self.aa.set_mode(window.device_queue(), &mut post, mode);
+        log::info!("post_showcase => anti-aliasing {:?}", mode);
+    }
+
+    fn on_key(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Digit1 => self.toggle_grade(),
+            KeyCode::Digit2 => self.toggle_custom(),
+            KeyCode::Digit3 => self.toggle_aa(),
+            KeyCode::KeyT => self.window.borrow_mut().start_transition(
+                TransitionKind::FadeIn(wgpu::Color::BLACK),
+                Duration::from_secs(1),
+            ),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl RadApp for PostShowcase {
+    fn frame_update(&mut self, dt: Duration) {
+        let window = self.window.borrow();
+        let post = window.post_processor().borrow();
+        self.custom.frame_update(window.device_queue(), &post, dt);
+    }
+
+    fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
+        self.window
+            .borrow_mut()
+            .camera_mut()
+            .process_mouse(mouse_dx, mouse_dy);
+    }
+
+    fn handle_window_events(&mut self, event: &WindowEvent) -> InputEventStatus {
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key),
+                        state,
+                        ..
+                    },
+                ..
+            } => {
+                if *state == ElementState::Pressed && self.on_key(*key) {
+                    return InputEventStatus::Processing;
+                }
+                self.window
+                    .borrow_mut()
+                    .camera_mut()
+                    .process_keyboard(*key, *state)
+            }
+            _ => InputEventStatus::Done,
+        }
+    }
+
+    fn draw_frame(&mut self, ctx: &mut DrawCtx) -> Result<(), SurfaceError> {
+        ctx.set_vertex_buffer(1, self.instance_buffer.clone());
+        ctx.draw_model_instanced(&self.model, 0..self.instance_count);
+        Ok(())
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    common::run(PostShowcase::new)
+}
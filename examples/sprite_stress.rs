@@ -0,0 +1,117 @@
+// cargo run --release --example sprite_stress [-- <sprite count>]
+//
+// Bounces 100k (or the given count of) rotating sprites around the window, rebuilding
+// and uploading the whole QuadBuffer every frame. Frame times are logged once a second,
+// run with RUST_LOG=info to see them.
+
+mod common;
+
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use rad::{prelude::*, sys::rand::Rng};
+
+const DEFAULT_SPRITES: usize = 100_000;
+const SPRITE_SIZE: f32 = 6.0;
+
+struct Particle {
+    position: [f32; 2],
+    velocity: [f32; 2],
+    rotation: f32,
+    spin: f32,
+    color: [f32; 4],
+}
+
+struct SpriteStress {
+    window: Rc<RefCell<RenderWindow>>,
+    particles: Vec<Particle>,
+    quads: QuadBuffer,
+    frames: u32,
+    elapsed: Duration,
+}
+
+impl SpriteStress {
+    fn new(window: Rc<RefCell<RenderWindow>>, count: usize) -> Self {
+        let (quads, width, height) = {
+            let window = window.borrow();
+            let quads = common::sprite_buffer(&window, count);
+            let size = window.size();
+            (quads, size.width as f32, size.height as f32)
+        };
+
+        let mut rng = Rng::new(0x5eed);
+        let particles = (0..count)
+            .map(|_| {
+                let direction = rng.on_unit_circle() * rng.range_f32(40.0..240.0);
+                Particle {
+                    position: [rng.range_f32(0.0..width), rng.range_f32(0.0..height)],
+                    velocity: [direction.x, direction.y],
+                    rotation: rng.range_f32(0.0..std::f32::consts::TAU),
+                    spin: rng.range_f32(-4.0..4.0),
+                    color: [rng.next_f32(), rng.next_f32(), rng.next_f32(), 1.0],
+                }
+            })
+            .collect();
+
+        log::info!("sprite_stress => {} sprites", count);
+        Self {
+            window,
+            particles,
+            quads,
+            frames: 0,
+            elapsed: Duration::ZERO,
+        }
+    }
+}
+
+impl RadApp for SpriteStress {
+    fn frame_update(&mut self, dt: Duration) {
+        let window = self.window.borrow();
+        let size = window.size();
+        let (width, height) = (size.width as f32, size.height as f32);
+        let dt_secs = dt.as_secs_f32();
+
+        self.quads.clear();
+        for p in self.particles.iter_mut() {
+            for axis in 0..2 {
+                let max = if axis == 0 { width } else { height };
+                p.position[axis] += p.velocity[axis] * dt_secs;
+                if p.position[axis] < 0.0 || p.position[axis] > max {
+                    p.velocity[axis] = -p.velocity[axis];
+                    p.position[axis] = p.position[axis].clamp(0.0, max);
+                }
+            }
+            p.rotation += p.spin * dt_secs;
+            self.quads.push_sprite(
+                &Sprite::new(p.position, [SPRITE_SIZE; 2])
+                    .with_rotation(p.rotation)
+                    .with_color(p.color),
+            );
+        }
+        self.quads.upload(window.device(), window.device_queue());
+
+        self.frames += 1;
+        self.elapsed += dt;
+        if self.elapsed >= Duration::from_secs(1) {
+            log::info!(
+                "sprite_stress => {} fps, {:.2} ms/frame",
+                self.frames,
+                self.elapsed.as_secs_f64() * 1000.0 / self.frames as f64
+            );
+            self.frames = 0;
+            self.elapsed = Duration::ZERO;
+        }
+    }
+
+    fn draw_frame(&mut self, ctx: &mut DrawCtx) -> Result<(), SurfaceError> {
+        self.quads.draw(ctx);
+        Ok(())
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let count = match std::env::args().nth(1) {
+        Some(arg) => arg.parse()?,
+        None => DEFAULT_SPRITES,
+    };
+    common::run(move |window| async move { Ok(SpriteStress::new(window, count)) })
+}
@@ -0,0 +1,337 @@
+// cargo run --release --example text_ui
+//
+// A small UI panel drawn entirely with QuadBuffer: an editable text field driven by
+// TextEditState (selection, clipboard shortcuts, caret), hoverable buttons and a status
+// line. Text uses a tiny 3x5 bitmap font built from one quad per lit pixel, enough to
+// show layout and input handling until the engine has a glyph renderer.
+
+mod common;
+
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+use rad::{
+    eng::input::text::{LocalClipboard, TextEditState},
+    prelude::*,
+};
+use winit::event::{ElementState, MouseButton, WindowEvent};
+
+const MAX_CHARS: usize = 32;
+/// Size of one font pixel in screen pixels.
+const SCALE: f32 = 4.0;
+const GLYPH_ADVANCE: f32 = 4.0 * SCALE;
+const GLYPH_HEIGHT: f32 = 5.0 * SCALE;
+
+const PANEL: [f32; 4] = [0.12, 0.13, 0.16, 1.0];
+const FIELD: [f32; 4] = [0.05, 0.05, 0.07, 1.0];
+const SELECTION: [f32; 4] = [0.2, 0.35, 0.7, 1.0];
+const TEXT: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const DIM: [f32; 4] = [0.5, 0.5, 0.55, 1.0];
+const BUTTON: [f32; 4] = [0.25, 0.27, 0.33, 1.0];
+const BUTTON_HOVER: [f32; 4] = [0.35, 0.4, 0.55, 1.0];
+
+/// 3x5 glyphs, rows top to bottom, the leftmost pixel is the highest bit of each row.
+const FONT: &[(char, u16)] = &[
+    ('A', 0b010_101_111_101_101),
+    ('B', 0b110_101_110_101_110),
+    ('C', 0b011_100_100_100_011),
+    ('D', 0b110_101_101_101_110),
+    ('E', 0b111_100_110_100_111),
+    ('F', 0b111_100_110_100_100),
+    ('G', 0b011_100_101_101_011),
+    ('H', 0b101_101_111_101_101),
+    ('I', 0b111_010_010_010_111),
+    ('J', 0b001_001_001_101_010),
+    ('K', 0b101_101_110_101_101),
+    ('L', 0b100_100_100_100_111),
+    ('M', 0b101_111_111_101_101),
+    ('N', 0b110_101_101_101_101),
+    ('O', 0b010_101_101_101_010),
+    ('P', 0b110_101_110_100_100),
+    ('Q', 0b010_101_101_110_011),
+    ('R', 0b110_101_110_101_101),
+    ('S', 0b011_100_010_001_110),
+    ('T', 0b111_010_010_010_010),
+    ('U', 0b101_101_101_101_111),
+    ('V', 0b101_101_101_101_010),
+    ('W', 0b101_101_111_111_101),
+    ('X', 0b101_101_010_101_101),
+    ('Y', 0b101_101_010_010_010),
+    ('Z', 0b111_001_010_100_111),
+    ('0', 0b111_101_101_101_111),
+    ('1', 0b010_110_010_010_111),
+    ('2', 0b110_001_010_100_111),
+    ('3', 0b110_001_010_001_110),
+    ('4', 0b101_101_111_001_001),
+    ('5', 0b111_100_110_001_110),
+    ('6', 0b011_100_111_101_111),
+    ('7', 0b111_001_010_010_010),
+    ('8', 0b111_101_111_101_111),
+    ('9', 0b111_101_111_001_110),
+    (' ', 0),
+    ('.', 0b000_000_000_000_010),
+    (',', 0b000_000_000_010_100),
+    ('!', 0b010_010_010_000_010),
+    ('?', 0b110_001_010_000_010),
+    ('-', 0b000_000_111_000_000),
+    (':', 0b000_010_000_010_000),
+    ('/', 0b001_001_010_100_100),
+    ('\'', 0b010_010_000_000_000),
+];
+
+/// Unknown characters draw as a filled box.
+fn glyph(c: char) -> u16 {
+    let c = c.to_ascii_uppercase();
+    FONT.iter()
+        .find(|(g, _)| *g == c)
+        .map_or(0b111_111_111_111_111, |(_, bits)| *bits)
+}
+
+/// Pushes `text` with its first glyph's bottom left corner at `origin`.
+fn push_text(quads: &mut QuadBuffer, text: &str, origin: [f32; 2], layer: i32, color: [f32; 4]) {
+    for (i, c) in text.chars().enumerate() {
+        let bits = glyph(c);
+        for row in 0..5 {
+            for col in 0..3 {
+                if bits & (1 << (14 - (row * 3 + col))) == 0 {
+                    continue;
+                }
+                let x = origin[0] + i as f32 * GLYPH_ADVANCE + col as f32 * SCALE;
+                let y = origin[1] + (4 - row) as f32 * SCALE;
+                quads.push_sprite(
+                    &Sprite::new([x, y], [SCALE; 2])
+                        .with_pivot([0.0, 0.0])
+                        .with_color(color)
+                        .with_layer(layer, 0.0),
+                );
+            }
+        }
+    }
+}
+
+fn text_width(chars: usize) -> f32 {
+    chars as f32 * GLYPH_ADVANCE
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    min: [f32; 2],
+    size: [f32; 2],
+}
+
+impl Rect {
+    fn contains(&self, p: [f32; 2]) -> bool {
+        (0..2).all(|i| p[i] >= self.min[i] && p[i] <= self.min[i] + self.size[i])
+    }
+
+    fn push(&self, quads: &mut QuadBuffer, layer: i32, color: [f32; 4]) {
+        quads.push_sprite(
+            &Sprite::new(self.min, self.size)
+                .with_pivot([0.0, 0.0])
+                .with_color(color)
+                .with_layer(layer, 0.0),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Clear,
+    Shout,
+}
+
+struct Button {
+    label: &'static str,
+    action: Action,
+    rect: Rect,
+}
+
+struct TextUi {
+    window: Rc<RefCell<RenderWindow>>,
+    quads: QuadBuffer,
+    field: TextEditState,
+    clipboard: LocalClipboard,
+    buttons: Vec<Button>,
+    cursor: [f32; 2],
+    time: f32,
+}
+
+impl TextUi {
+    fn new(window: Rc<RefCell<RenderWindow>>) -> Self {
+        let quads = common::sprite_buffer(&window.borrow(), 4096);
+        let buttons = [("CLEAR", Action::Clear), ("SHOUT", Action::Shout)]
+            .into_iter()
+            .map(|(label, action)| Button {
+                label,
+                action,
+                rect: Rect {
+                    min: [0.0; 2],
+                    size: [0.0; 2],
+                },
+            })
+            .collect();
+        Self {
+            window,
+            quads,
+            field: TextEditState::with_text("Hello radium").with_max_len(MAX_CHARS),
+            clipboard: LocalClipboard::default(),
+            buttons,
+            cursor: [-1.0; 2],
+            time: 0.0,
+        }
+    }
+
+    fn apply(&mut self, action: Action) {
+        match action {
+            Action::Clear => self.field.set_text(""),
+            Action::Shout => {
+                let text = self.field.text().to_uppercase() + "!";
+                self.field.set_text(&text);
+            }
+        }
+    }
+
+    /// Lays out and pushes the whole UI, centered in a `width` x `height` window.
+    fn build(&mut self, width: f32, height: f32) {
+        let padding = 24.0;
+        let field_width = text_width(MAX_CHARS) + 2.0 * SCALE * 2.0;
+        let panel = Rect {
+            min: [
+                (width - field_width) * 0.5 - padding,
+                (height - 240.0) * 0.5,
+            ],
+            size: [field_width + 2.0 * padding, 240.0],
+        };
+        let [left, bottom] = panel.min;
+        let top = bottom + panel.size[1];
+        let quads = &mut self.quads;
+        quads.clear();
+        panel.push(quads, 0, PANEL);
+        push_text(
+            quads,
+            "TEXT AND UI DEMO",
+            [left + padding, top - padding - GLYPH_HEIGHT],
+            3,
+            TEXT,
+        );
+
+        // Text field with selection and a blinking caret.
+        let field = Rect {
+            min: [left + padding, top - 2.0 * padding - GLYPH_HEIGHT - 44.0],
+            size: [field_width, 44.0],
+        };
+        field.push(quads, 1, FIELD);
+        let text_origin = [
+            field.min[0] + 2.0 * SCALE,
+            field.min[1] + (field.size[1] - GLYPH_HEIGHT) * 0.5,
+        ];
+        let text = self.field.text();
+        if let Some(selection) = self.field.selection() {
+            let start = text[..selection.start].chars().count();
+            let len = text[selection].chars().count();
+            Rect {
+                min: [
+                    text_origin[0] + text_width(start) - SCALE * 0.5,
+                    text_origin[1] - SCALE,
+                ],
+                size: [text_width(len), GLYPH_HEIGHT + 2.0 * SCALE],
+            }
+            .push(quads, 2, SELECTION);
+        }
+        push_text(quads, text, text_origin, 3, TEXT);
+        if self.time.fract() < 0.5 {
+            Rect {
+                min: [
+                    text_origin[0] + text_width(self.field.cursor_char()) - SCALE,
+                    text_origin[1] - SCALE,
+                ],
+                size: [SCALE * 0.5, GLYPH_HEIGHT + 2.0 * SCALE],
+            }
+            .push(quads, 4, TEXT);
+        }
+
+        // Buttons along the bottom, highlighted under the mouse.
+        let mut x = left + padding;
+        for button in self.buttons.iter_mut() {
+            button.rect = Rect {
+                min: [x, bottom + padding + 28.0],
+                size: [text_width(button.label.len()) + 2.0 * padding, 40.0],
+            };
+            x += button.rect.size[0] + padding * 0.5;
+            let color = if button.rect.contains(self.cursor) {
+                BUTTON_HOVER
+            } else {
+                BUTTON
+            };
+            button.rect.push(quads, 1, color);
+            push_text(
+                quads,
+                button.label,
+                [
+                    button.rect.min[0] + padding,
+                    button.rect.min[1] + (40.0 - GLYPH_HEIGHT) * 0.5,
+                ],
+                3,
+                TEXT,
+            );
+        }
+
+        let status = format!("{}/{} CHARS  CTRL-A/C/X/V", text.chars().count(), MAX_CHARS);
+        push_text(
+            quads,
+            &status,
+            [left + padding, bottom + padding * 0.5],
+            3,
+            DIM,
+        );
+    }
+}
+
+impl RadApp for TextUi {
+    fn frame_update(&mut self, dt: Duration) {
+        self.time += dt.as_secs_f32();
+        let window = self.window.clone();
+        let window = window.borrow();
+        let size = window.size();
+        self.build(size.width as f32, size.height as f32);
+        self.quads.upload(window.device(), window.device_queue());
+    }
+
+    fn handle_window_events(&mut self, event: &WindowEvent) -> InputEventStatus {
+        if self.field.handle_event(event, &mut self.clipboard) {
+            self.time = 0.0;
+            return InputEventStatus::Processing;
+        }
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let height = self.window.borrow().size().height as f32;
+                self.cursor = [position.x as f32, height - position.y as f32];
+                InputEventStatus::Processing
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let clicked = self
+                    .buttons
+                    .iter()
+                    .find(|b| b.rect.contains(self.cursor))
+                    .map(|b| b.action);
+                if let Some(action) = clicked {
+                    self.apply(action);
+                }
+                InputEventStatus::Processing
+            }
+            _ => InputEventStatus::Done,
+        }
+    }
+
+    fn draw_frame(&mut self, ctx: &mut DrawCtx) -> Result<(), SurfaceError> {
+        self.quads.draw(ctx);
+        Ok(())
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    common::run(|window| async move { Ok(TextUi::new(window)) })
+}
@@ -0,0 +1,5 @@
+pub mod noise;
+
+use core::f32::consts::FRAC_PI_2;
+
+pub const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
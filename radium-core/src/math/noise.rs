@@ -0,0 +1,300 @@
+use libm::{floorf, roundf};
+
+use crate::rand::SplitMix64;
+
+/// Seeded gradient noise. Every sample is in roughly -1..1 and the same seed always
+/// produces the same field, so noise can be regenerated instead of stored.
+#[derive(Debug, Clone)]
+pub struct Noise {
+    seed: u64,
+    perm: [u8; 512],
+}
+
+impl Default for Noise {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        let mut table: [u8; 256] = core::array::from_fn(|i| i as u8);
+        // Fisher-Yates driven by splitmix64 so seeds close together still differ.
+        let mut sm = SplitMix64::new(seed);
+        for i in (1..table.len()).rev() {
+            table.swap(i, (sm.next_u64() % (i as u64 + 1)) as usize);
+        }
+        Self {
+            seed,
+            perm: core::array::from_fn(|i| table[i & 255]),
+        }
+    }
+
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    #[inline]
+    fn hash(&self, i: i32) -> usize {
+        self.perm[(i & 255) as usize] as usize
+    }
+
+    #[inline]
+    fn hash2(&self, x: i32, y: i32) -> usize {
+        self.perm[self.hash(x) + (y & 255) as usize] as usize
+    }
+
+    #[inline]
+    fn hash3(&self, x: i32, y: i32, z: i32) -> usize {
+        self.perm[self.hash2(x, y) + (z & 255) as usize] as usize
+    }
+
+    pub fn perlin2(&self, x: f32, y: f32) -> f32 {
+        let (xi, yi) = (floorf(x) as i32, floorf(y) as i32);
+        let (xf, yf) = (x - floorf(x), y - floorf(y));
+        let (u, v) = (fade(xf), fade(yf));
+
+        let n00 = grad2(self.hash2(xi, yi), xf, yf);
+        let n10 = grad2(self.hash2(xi + 1, yi), xf - 1.0, yf);
+        let n01 = grad2(self.hash2(xi, yi + 1), xf, yf - 1.0);
+        let n11 = grad2(self.hash2(xi + 1, yi + 1), xf - 1.0, yf - 1.0);
+        lerp(lerp(n00, n10, u), lerp(n01, n11, u), v)
+    }
+
+    pub fn perlin3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let (xi, yi, zi) = (floorf(x) as i32, floorf(y) as i32, floorf(z) as i32);
+        let (xf, yf, zf) = (x - floorf(x), y - floorf(y), z - floorf(z));
+        let (u, v, w) = (fade(xf), fade(yf), fade(zf));
+
+        let g = |dx: i32, dy: i32, dz: i32| {
+            grad3(
+                self.hash3(xi + dx, yi + dy, zi + dz),
+                xf - dx as f32,
+                yf - dy as f32,
+                zf - dz as f32,
+            )
+        };
+        let x00 = lerp(g(0, 0, 0), g(1, 0, 0), u);
+        let x10 = lerp(g(0, 1, 0), g(1, 1, 0), u);
+        let x01 = lerp(g(0, 0, 1), g(1, 0, 1), u);
+        let x11 = lerp(g(0, 1, 1), g(1, 1, 1), u);
+        lerp(lerp(x00, x10, v), lerp(x01, x11, v), w)
+    }
+
+    pub fn simplex2(&self, x: f32, y: f32) -> f32 {
+        const F2: f32 = 0.366_025_42; // (sqrt(3) - 1) / 2
+        const G2: f32 = 0.211_324_87; // (3 - sqrt(3)) / 6
+
+        let s = (x + y) * F2;
+        let (i, j) = (floorf(x + s) as i32, floorf(y + s) as i32);
+        let t = (i + j) as f32 * G2;
+        let (x0, y0) = (x - (i as f32 - t), y - (j as f32 - t));
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+
+        let corners = [
+            (0, 0, x0, y0),
+            (i1, j1, x0 - i1 as f32 + G2, y0 - j1 as f32 + G2),
+            (1, 1, x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2),
+        ];
+        let sum: f32 = corners
+            .iter()
+            .map(|&(di, dj, cx, cy)| {
+                let t = 0.5 - cx * cx - cy * cy;
+                if t < 0.0 {
+                    0.0
+                } else {
+                    let t = t * t;
+                    t * t * grad2(self.hash2(i + di, j + dj), cx, cy)
+                }
+            })
+            .sum();
+        70.0 * sum
+    }
+
+    pub fn simplex3(&self, x: f32, y: f32, z: f32) -> f32 {
+        const F3: f32 = 1.0 / 3.0;
+        const G3: f32 = 1.0 / 6.0;
+
+        let s = (x + y + z) * F3;
+        let (i, j, k) = (
+            floorf(x + s) as i32,
+            floorf(y + s) as i32,
+            floorf(z + s) as i32,
+        );
+        let t = (i + j + k) as f32 * G3;
+        let (x0, y0, z0) = (x - (i as f32 - t), y - (j as f32 - t), z - (k as f32 - t));
+
+        // Which of the six tetrahedra of the skewed cube the point is in.
+        let ((i1, j1, k1), (i2, j2, k2)) = if x0 >= y0 {
+            if y0 >= z0 {
+                ((1, 0, 0), (1, 1, 0))
+            } else if x0 >= z0 {
+                ((1, 0, 0), (1, 0, 1))
+            } else {
+                ((0, 0, 1), (1, 0, 1))
+            }
+        } else if y0 < z0 {
+            ((0, 0, 1), (0, 1, 1))
+        } else if x0 < z0 {
+            ((0, 1, 0), (0, 1, 1))
+        } else {
+            ((0, 1, 0), (1, 1, 0))
+        };
+
+        let corner = |di: i32, dj: i32, dk: i32, g: f32| {
+            let (cx, cy, cz) = (x0 - di as f32 + g, y0 - dj as f32 + g, z0 - dk as f32 + g);
+            let t = 0.6 - cx * cx - cy * cy - cz * cz;
+            if t < 0.0 {
+                0.0
+            } else {
+                let t = t * t;
+                t * t * grad3(self.hash3(i + di, j + dj, k + dk), cx, cy, cz)
+            }
+        };
+        32.0 * (corner(0, 0, 0, 0.0)
+            + corner(i1, j1, k1, G3)
+            + corner(i2, j2, k2, 2.0 * G3)
+            + corner(1, 1, 1, 3.0 * G3))
+    }
+
+    pub fn sample2(&self, kind: NoiseKind, x: f32, y: f32) -> f32 {
+        match kind {
+            NoiseKind::Perlin => self.perlin2(x, y),
+            NoiseKind::Simplex => self.simplex2(x, y),
+        }
+    }
+
+    pub fn sample3(&self, kind: NoiseKind, x: f32, y: f32, z: f32) -> f32 {
+        match kind {
+            NoiseKind::Perlin => self.perlin3(x, y, z),
+            NoiseKind::Simplex => self.simplex3(x, y, z),
+        }
+    }
+
+    /// Fractal sum of `fractal.octaves` layers, normalized back to -1..1.
+    pub fn fbm2(&self, fractal: &Fractal, x: f32, y: f32) -> f32 {
+        fractal.sum(|f| self.sample2(fractal.kind, x * f, y * f))
+    }
+
+    pub fn fbm3(&self, fractal: &Fractal, x: f32, y: f32, z: f32) -> f32 {
+        fractal.sum(|f| self.sample3(fractal.kind, x * f, y * f, z * f))
+    }
+
+    /// Fbm of folded noise, sharp crests useful for mountain ridges. Returns 0..1.
+    pub fn ridged2(&self, fractal: &Fractal, x: f32, y: f32) -> f32 {
+        fractal.ridged(|f| self.sample2(fractal.kind, x * f, y * f))
+    }
+
+    pub fn ridged3(&self, fractal: &Fractal, x: f32, y: f32, z: f32) -> f32 {
+        fractal.ridged(|f| self.sample3(fractal.kind, x * f, y * f, z * f))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum NoiseKind {
+    #[default]
+    Perlin,
+    Simplex,
+}
+
+/// Octave settings shared by fbm and ridged noise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fractal {
+    pub kind: NoiseKind,
+    pub octaves: u32,
+    /// Frequency of the first octave.
+    pub frequency: f32,
+    /// Frequency multiplier between octaves.
+    pub lacunarity: f32,
+    /// Amplitude multiplier between octaves.
+    pub gain: f32,
+}
+
+impl Default for Fractal {
+    fn default() -> Self {
+        Self {
+            kind: NoiseKind::Perlin,
+            octaves: 5,
+            frequency: 1.0,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+
+impl Fractal {
+    fn sum(&self, mut sample: impl FnMut(f32) -> f32) -> f32 {
+        let (mut freq, mut amp) = (self.frequency, 1.0);
+        let (mut total, mut norm) = (0.0, 0.0);
+        for _ in 0..self.octaves.max(1) {
+            total += sample(freq) * amp;
+            norm += amp;
+            freq *= self.lacunarity;
+            amp *= self.gain;
+        }
+        total / norm
+    }
+
+    fn ridged(&self, mut sample: impl FnMut(f32) -> f32) -> f32 {
+        let (mut freq, mut amp) = (self.frequency, 1.0);
+        let (mut total, mut norm) = (0.0, 0.0);
+        // Each octave is weighted by the previous one so detail gathers along the ridges.
+        let mut weight = 1.0;
+        for _ in 0..self.octaves.max(1) {
+            let n = 1.0 - sample(freq).abs().min(1.0);
+            let n = n * n * weight;
+            weight = n.clamp(0.0, 1.0);
+            total += n * amp;
+            norm += amp;
+            freq *= self.lacunarity;
+            amp *= self.gain;
+        }
+        total / norm
+    }
+}
+
+#[inline]
+pub fn to_unorm8(v: f32) -> u8 {
+    roundf((v.clamp(-1.0, 1.0) * 0.5 + 0.5) * 255.0) as u8
+}
+
+#[inline]
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+#[inline]
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[inline]
+fn grad2(hash: usize, x: f32, y: f32) -> f32 {
+    let (gx, gy) = match hash & 3 {
+        0 => (1.0, 1.0),
+        1 => (-1.0, 1.0),
+        2 => (1.0, -1.0),
+        _ => (-1.0, -1.0),
+    };
+    gx * x + gy * y
+}
+
+#[inline]
+fn grad3(hash: usize, x: f32, y: f32, z: f32) -> f32 {
+    // The 12 cube edge directions, padded to 16 so a mask picks one.
+    match hash & 15 {
+        0 | 12 => x + y,
+        1 | 14 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x + z,
+        5 => -x + z,
+        6 => x - z,
+        7 => -x - z,
+        8 => y + z,
+        9 | 13 => -y + z,
+        10 => y - z,
+        _ => -y - z,
+    }
+}
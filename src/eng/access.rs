@@ -0,0 +1,494 @@
+use std::sync::{Arc, Mutex};
+
+use accesskit::{
+    Action, ActionData, ActionHandler, ActionRequest, ActivationHandler, DeactivationHandler, Node,
+    NodeId, Rect, Role, Toggled, Tree, TreeUpdate,
+};
+use winit::{event::WindowEvent, event_loop::ActiveEventLoop, window::Window};
+
+use super::determinism::StateHasher;
+
+/// Moves focus to the next focusable widget, registered by the engine on Tab.
+pub const FOCUS_NEXT: &str = "access.focus_next";
+/// Moves focus to the previous focusable widget, registered by the engine on Shift+Tab.
+pub const FOCUS_PREV: &str = "access.focus_prev";
+/// Clicks the focused widget, registered by the engine on Enter.
+pub const ACTIVATE: &str = "access.activate";
+/// Steps the focused slider up, registered by the engine on Ctrl+Right.
+pub const INCREMENT: &str = "access.increment";
+/// Steps the focused slider down, registered by the engine on Ctrl+Left.
+pub const DECREMENT: &str = "access.decrement";
+
+/// Node id in the accessibility tree. Widgets rebuild their nodes every frame, so ids
+/// come from names rather than allocation order to keep focus on the same widget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AccessId(pub u64);
+
+impl AccessId {
+    /// The window, parent of every top level widget.
+    pub const ROOT: Self = Self(0);
+
+    pub fn from_name(name: &str) -> Self {
+        let mut hasher = StateHasher::new();
+        hasher.write_bytes(name.as_bytes());
+        Self(hasher.finish())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessRole {
+    Group,
+    Label,
+    Button,
+    CheckBox,
+    Slider,
+    Image,
+}
+
+impl AccessRole {
+    /// Roles a user can tab to.
+    pub fn is_focusable(self) -> bool {
+        matches!(self, Self::Button | Self::CheckBox | Self::Slider)
+    }
+
+    fn role(self) -> Role {
+        match self {
+            Self::Group => Role::Group,
+            Self::Label => Role::Label,
+            Self::Button => Role::Button,
+            Self::CheckBox => Role::CheckBox,
+            Self::Slider => Role::Slider,
+            Self::Image => Role::Image,
+        }
+    }
+}
+
+/// Numeric value of a slider, min and max may be in either order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccessRange {
+    pub value: f64,
+    pub min: f64,
+    pub max: f64,
+    /// Amount INCREMENT and DECREMENT move the value by.
+    pub step: f64,
+}
+
+/// A widget as seen by screen readers, pushed to an AccessTree every frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessNode {
+    pub id: AccessId,
+    pub parent: AccessId,
+    pub role: AccessRole,
+    pub label: String,
+    /// Value read out after the label, e.g. a slider's formatted value.
+    pub value: Option<String>,
+    pub range: Option<AccessRange>,
+    pub toggled: Option<bool>,
+    /// Left, top, width and height in window pixels, y growing downwards.
+    pub rect: [f32; 4],
+    pub focusable: bool,
+}
+
+impl AccessNode {
+    pub fn new(id: AccessId, role: AccessRole, label: &str) -> Self {
+        Self {
+            id,
+            parent: AccessId::ROOT,
+            role,
+            label: String::from(label),
+            value: None,
+            range: None,
+            toggled: None,
+            rect: [0.0; 4],
+            focusable: role.is_focusable(),
+        }
+    }
+
+    pub fn with_parent(mut self, parent: AccessId) -> Self {
+        self.parent = parent;
+        self
+    }
+
+    pub fn with_rect(mut self, rect: [f32; 4]) -> Self {
+        self.rect = rect;
+        self
+    }
+
+    pub fn with_value(mut self, value: String) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    pub fn with_range(mut self, range: AccessRange) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    pub fn with_toggled(mut self, toggled: bool) -> Self {
+        self.toggled = Some(toggled);
+        self
+    }
+
+    pub fn with_focusable(mut self, focusable: bool) -> Self {
+        self.focusable = focusable;
+        self
+    }
+
+    fn node(&self, children: Vec<NodeId>) -> Node {
+        let mut node = Node::new(self.role.role());
+        node.set_label(self.label.as_str());
+        if let Some(value) = &self.value {
+            node.set_value(value.as_str());
+        }
+        if let Some(range) = self.range {
+            node.set_numeric_value(range.value);
+            node.set_min_numeric_value(range.min.min(range.max));
+            node.set_max_numeric_value(range.min.max(range.max));
+            node.set_numeric_value_step(range.step);
+            node.add_action(Action::Increment);
+            node.add_action(Action::Decrement);
+            node.add_action(Action::SetValue);
+        }
+        if let Some(toggled) = self.toggled {
+            node.set_toggled(if toggled {
+                Toggled::True
+            } else {
+                Toggled::False
+            });
+        }
+        if matches!(self.role, AccessRole::Button | AccessRole::CheckBox) {
+            node.add_action(Action::Click);
+        }
+        if self.focusable {
+            node.add_action(Action::Focus);
+        }
+        let [x, y, w, h] = self.rect.map(f64::from);
+        node.set_bounds(Rect::new(x, y, x + w, y + h));
+        if !children.is_empty() {
+            node.set_children(children);
+        }
+        node
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccessActionKind {
+    Click,
+    Increment,
+    Decrement,
+    SetValue(f64),
+}
+
+/// Request from a screen reader or the access shortcuts, applied by the widget that
+/// owns `target` during the frame after it arrived.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccessAction {
+    pub target: AccessId,
+    pub kind: AccessActionKind,
+}
+
+impl AccessAction {
+    /// None for requests widgets don't handle, focus requests move focus directly.
+    fn from_request(request: &ActionRequest) -> Option<Self> {
+        let kind = match (request.action, &request.data) {
+            (Action::Click, _) => AccessActionKind::Click,
+            (Action::Increment, _) => AccessActionKind::Increment,
+            (Action::Decrement, _) => AccessActionKind::Decrement,
+            (Action::SetValue, Some(ActionData::NumericValue(value))) => {
+                AccessActionKind::SetValue(*value)
+            }
+            _ => return None,
+        };
+        Some(Self {
+            target: AccessId(request.target.0),
+            kind,
+        })
+    }
+}
+
+/// Widgets that describe themselves to screen readers, e.g. TweakPanel.
+pub trait Accessible {
+    /// Pushes this frame's nodes, top level ones parented to `parent`.
+    fn push_access_nodes(&self, tree: &mut AccessTree, parent: AccessId);
+}
+
+/// Accessibility tree rebuilt from the widgets every frame, with keyboard focus kept
+/// across frames by id. Tab order is the order focusable nodes were pushed in.
+#[derive(Debug, Clone)]
+pub struct AccessTree {
+    title: String,
+    nodes: Vec<AccessNode>,
+    /// Focusable nodes of the last finished frame, focus moves within these.
+    order: Vec<AccessId>,
+    focus: Option<AccessId>,
+    actions: Vec<AccessAction>,
+}
+
+impl AccessTree {
+    /// `title` labels the root window node.
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: String::from(title),
+            nodes: Vec::new(),
+            order: Vec::new(),
+            focus: None,
+            actions: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn nodes(&self) -> &[AccessNode] {
+        &self.nodes
+    }
+
+    #[inline]
+    pub fn focus(&self) -> Option<AccessId> {
+        self.focus
+    }
+
+    #[inline]
+    pub fn is_focused(&self, id: AccessId) -> bool {
+        self.focus == Some(id)
+    }
+
+    pub fn set_focus(&mut self, focus: Option<AccessId>) {
+        self.focus = focus;
+    }
+
+    /// Actions queued for this frame.
+    #[inline]
+    pub fn actions(&self) -> &[AccessAction] {
+        &self.actions
+    }
+
+    pub fn queue(&mut self, action: AccessAction) {
+        self.actions.push(action);
+    }
+
+    /// Queues `kind` on the focused widget, does nothing without focus.
+    pub fn act_on_focus(&mut self, kind: AccessActionKind) {
+        if let Some(target) = self.focus {
+            self.queue(AccessAction { target, kind });
+        }
+    }
+
+    /// Wraps around to the first widget, starts there without focus.
+    pub fn focus_next(&mut self) {
+        self.step_focus(1);
+    }
+
+    /// Wraps around to the last widget, starts there without focus.
+    pub fn focus_prev(&mut self) {
+        self.step_focus(-1);
+    }
+
+    fn step_focus(&mut self, step: isize) {
+        if self.order.is_empty() {
+            return;
+        }
+        let len = self.order.len() as isize;
+        let current = self
+            .focus
+            .and_then(|focus| self.order.iter().position(|id| *id == focus));
+        let next = match current {
+            Some(i) => (i as isize + step).rem_euclid(len),
+            None if step > 0 => 0,
+            None => len - 1,
+        };
+        self.focus = Some(self.order[next as usize]);
+    }
+
+    pub fn push(&mut self, node: AccessNode) {
+        self.nodes.push(node);
+    }
+
+    /// Clears last frame's nodes before widgets push this frame's.
+    pub fn begin_frame(&mut self) {
+        self.nodes.clear();
+    }
+
+    /// Drops the frame's actions and focus on widgets that weren't pushed this frame.
+    pub fn end_frame(&mut self) {
+        self.actions.clear();
+        self.order = self
+            .nodes
+            .iter()
+            .filter(|node| node.focusable)
+            .map(|node| node.id)
+            .collect();
+        if let Some(focus) = self.focus {
+            if !self.order.contains(&focus) {
+                self.focus = None;
+            }
+        }
+    }
+
+    /// Full tree for AccessKit, nodes whose parent wasn't pushed are left out.
+    pub fn tree_update(&self) -> TreeUpdate {
+        let children_of = |parent: AccessId| {
+            self.nodes
+                .iter()
+                .filter(|node| node.parent == parent && node.id != parent)
+                .map(|node| NodeId(node.id.0))
+                .collect::<Vec<_>>()
+        };
+
+        let mut root = Node::new(Role::Window);
+        root.set_label(self.title.as_str());
+        root.set_children(children_of(AccessId::ROOT));
+        let mut nodes = vec![(NodeId(AccessId::ROOT.0), root)];
+
+        let mut stack = vec![AccessId::ROOT];
+        while let Some(parent) = stack.pop() {
+            for node in self.nodes.iter().filter(|node| node.parent == parent) {
+                if node.id == parent || nodes.iter().any(|(id, _)| id.0 == node.id.0) {
+                    continue;
+                }
+                nodes.push((NodeId(node.id.0), node.node(children_of(node.id))));
+                stack.push(node.id);
+            }
+        }
+
+        let mut tree = Tree::new(NodeId(AccessId::ROOT.0));
+        tree.toolkit_name = Some(String::from("radium"));
+        tree.toolkit_version = Some(String::from(env!("CARGO_PKG_VERSION")));
+        TreeUpdate {
+            nodes,
+            tree: Some(tree),
+            focus: NodeId(self.focus.unwrap_or(AccessId::ROOT).0),
+        }
+    }
+
+    /// Focus requests move focus right away, the rest are queued for the widgets.
+    fn handle_request(&mut self, request: &ActionRequest) {
+        if request.action == Action::Focus {
+            self.focus = Some(AccessId(request.target.0));
+        } else if let Some(action) = AccessAction::from_request(request) {
+            self.queue(action);
+        }
+    }
+}
+
+/// Requests from AccessKit, which may call in from any thread.
+#[derive(Debug, Clone, Default)]
+struct QueuedRequests(Arc<Mutex<Vec<ActionRequest>>>);
+
+impl ActionHandler for QueuedRequests {
+    fn do_action(&mut self, request: ActionRequest) {
+        self.0.lock().unwrap().push(request);
+    }
+}
+
+/// The tree is sent on the first frame after activation instead, and there is nothing to
+/// tear down on deactivation.
+struct NoInitialTree;
+
+impl ActivationHandler for NoInitialTree {
+    fn request_initial_tree(&mut self) -> Option<TreeUpdate> {
+        None
+    }
+}
+
+impl DeactivationHandler for NoInitialTree {
+    fn deactivate_accessibility(&mut self) {}
+}
+
+/// Exposes an AccessTree to the platform screen reader through AccessKit, see
+/// RenderWindow::screen_reader. Without an adapter the tree and focus still work, so
+/// keyboard navigation behaves the same.
+pub struct ScreenReader {
+    adapter: Option<accesskit_winit::Adapter>,
+    requests: QueuedRequests,
+    tree: AccessTree,
+}
+
+impl std::fmt::Debug for ScreenReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScreenReader")
+            .field("connected", &self.adapter.is_some())
+            .field("tree", &self.tree)
+            .finish()
+    }
+}
+
+impl ScreenReader {
+    /// Connects to the platform adapter, `window` must not have been shown yet.
+    pub fn new(event_loop: &ActiveEventLoop, window: &Window, title: &str) -> Self {
+        let requests = QueuedRequests::default();
+        let adapter = accesskit_winit::Adapter::with_direct_handlers(
+            event_loop,
+            window,
+            NoInitialTree,
+            requests.clone(),
+            NoInitialTree,
+        );
+        Self {
+            adapter: Some(adapter),
+            requests,
+            tree: AccessTree::new(title),
+        }
+    }
+
+    /// For windows created outside of RenderWindow::with_config, which may already be
+    /// visible.
+    pub fn disconnected(title: &str) -> Self {
+        Self {
+            adapter: None,
+            requests: QueuedRequests::default(),
+            tree: AccessTree::new(title),
+        }
+    }
+
+    #[inline]
+    pub fn is_connected(&self) -> bool {
+        self.adapter.is_some()
+    }
+
+    #[inline]
+    pub fn tree(&self) -> &AccessTree {
+        &self.tree
+    }
+
+    #[inline]
+    pub fn tree_mut(&mut self) -> &mut AccessTree {
+        &mut self.tree
+    }
+
+    /// Must see every window event before the app does.
+    pub fn process_event(&mut self, window: &Window, event: &WindowEvent) {
+        if let Some(adapter) = &mut self.adapter {
+            adapter.process_event(window, event);
+        }
+    }
+
+    /// Applies a fired access shortcut, returns false for other actions.
+    pub fn handle_shortcut(&mut self, action: &str) -> bool {
+        match action {
+            FOCUS_NEXT => self.tree.focus_next(),
+            FOCUS_PREV => self.tree.focus_prev(),
+            ACTIVATE => self.tree.act_on_focus(AccessActionKind::Click),
+            INCREMENT => self.tree.act_on_focus(AccessActionKind::Increment),
+            DECREMENT => self.tree.act_on_focus(AccessActionKind::Decrement),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Queues the screen reader's requests and clears the tree for the widgets.
+    pub fn begin_frame(&mut self) {
+        let requests = std::mem::take(&mut *self.requests.0.lock().unwrap());
+        for request in requests.iter() {
+            self.tree.handle_request(request);
+        }
+        self.tree.begin_frame();
+    }
+
+    /// Sends the tree the widgets pushed this frame, if a screen reader is listening.
+    pub fn end_frame(&mut self) {
+        self.tree.end_frame();
+        let tree = &self.tree;
+        if let Some(adapter) = &mut self.adapter {
+            adapter.update_if_active(|| tree.tree_update());
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::{any::Any, collections::HashMap, time::Duration};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Status {
+    Success,
+    Failure,
+    Running,
+}
+
+/// Values shared between the nodes of a tree, keyed by name.
+#[derive(Default)]
+pub struct Blackboard {
+    values: HashMap<String, Box<dyn Any>>,
+}
+
+impl Blackboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set<T: 'static>(&mut self, key: &str, value: T) {
+        self.values.insert(String::from(key), Box::new(value));
+    }
+
+    /// None if the key is missing or holds another type.
+    pub fn get<T: 'static>(&self, key: &str) -> Option<&T> {
+        self.values.get(key).and_then(|v| v.downcast_ref())
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, key: &str) -> Option<&mut T> {
+        self.values.get_mut(key).and_then(|v| v.downcast_mut())
+    }
+
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.values.remove(key).is_some()
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+}
+
+/// A behavior tree node. reset is called when a running node is abandoned or its tree
+/// restarts, so it should drop any progress it kept.
+pub trait Behavior<C> {
+    fn tick(&mut self, ctx: &mut C, bb: &mut Blackboard, dt: Duration) -> Status;
+    fn reset(&mut self) {}
+}
+
+pub type Node<C> = Box<dyn Behavior<C>>;
+
+/// Root node and blackboard, tick once per frame_update.
+pub struct BehaviorTree<C> {
+    root: Node<C>,
+    pub blackboard: Blackboard,
+}
+
+impl<C> BehaviorTree<C> {
+    pub fn new(root: Node<C>) -> Self {
+        Self {
+            root,
+            blackboard: Blackboard::new(),
+        }
+    }
+
+    /// Runs the tree, which restarts from the root once it succeeds or fails.
+    pub fn tick(&mut self, ctx: &mut C, dt: Duration) -> Status {
+        let status = self.root.tick(ctx, &mut self.blackboard, dt);
+        if status != Status::Running {
+            self.root.reset();
+        }
+        status
+    }
+
+    pub fn reset(&mut self) {
+        self.root.reset();
+    }
+}
+
+/// Runs children in order until one fails.
+pub struct Sequence<C> {
+    children: Vec<Node<C>>,
+    current: usize,
+}
+
+/// Runs children in order until one succeeds.
+pub struct Selector<C> {
+    children: Vec<Node<C>>,
+    current: usize,
+}
+
+macro_rules! composite {
+    ($ty:ident, $continue_on:expr) => {
+        impl<C> Behavior<C> for $ty<C> {
+            fn tick(&mut self, ctx: &mut C, bb: &mut Blackboard, dt: Duration) -> Status {
+                while let Some(child) = self.children.get_mut(self.current) {
+                    match child.tick(ctx, bb, dt) {
+                        Status::Running => return Status::Running,
+                        s if s == $continue_on => self.current += 1,
+                        s => {
+                            self.reset();
+                            return s;
+                        }
+                    }
+                }
+                self.reset();
+                $continue_on
+            }
+
+            fn reset(&mut self) {
+                for child in self.children.iter_mut() {
+                    child.reset();
+                }
+                self.current = 0;
+            }
+        }
+    };
+}
+
+composite!(Sequence, Status::Success);
+composite!(Selector, Status::Failure);
+
+pub struct Action<F>(F);
+
+impl<C, F: FnMut(&mut C, &mut Blackboard, Duration) -> Status> Behavior<C> for Action<F> {
+    fn tick(&mut self, ctx: &mut C, bb: &mut Blackboard, dt: Duration) -> Status {
+        (self.0)(ctx, bb, dt)
+    }
+}
+
+pub struct Condition<F>(F);
+
+impl<C, F: FnMut(&C, &Blackboard) -> bool> Behavior<C> for Condition<F> {
+    fn tick(&mut self, ctx: &mut C, bb: &mut Blackboard, _dt: Duration) -> Status {
+        if (self.0)(ctx, bb) {
+            Status::Success
+        } else {
+            Status::Failure
+        }
+    }
+}
+
+/// Runs for a duration, then succeeds.
+pub struct Wait {
+    duration: Duration,
+    elapsed: Duration,
+}
+
+impl<C> Behavior<C> for Wait {
+    fn tick(&mut self, _ctx: &mut C, _bb: &mut Blackboard, dt: Duration) -> Status {
+        self.elapsed += dt;
+        if self.elapsed >= self.duration {
+            self.elapsed = Duration::ZERO;
+            Status::Success
+        } else {
+            Status::Running
+        }
+    }
+
+    fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecoratorKind {
+    /// Swaps success and failure.
+    Invert,
+    /// Always succeeds once the child is done.
+    Succeed,
+    /// Runs the child until it has succeeded n times, failing if it fails.
+    Repeat(u32),
+    /// Runs the child until it fails, then succeeds.
+    UntilFail,
+    /// Fails without running the child until the duration has passed since it last finished.
+    Cooldown(Duration),
+}
+
+pub struct Decorator<C> {
+    kind: DecoratorKind,
+    child: Node<C>,
+    count: u32,
+    since_done: Option<Duration>,
+}
+
+impl<C> Behavior<C> for Decorator<C> {
+    fn tick(&mut self, ctx: &mut C, bb: &mut Blackboard, dt: Duration) -> Status {
+        if let DecoratorKind::Cooldown(cooldown) = self.kind {
+            if let Some(since) = self.since_done.as_mut() {
+                *since += dt;
+                if *since < cooldown {
+                    return Status::Failure;
+                }
+            }
+        }
+
+        let status = self.child.tick(ctx, bb, dt);
+        if status == Status::Running {
+            return Status::Running;
+        }
+        self.child.reset();
+        match self.kind {
+            DecoratorKind::Invert => match status {
+                Status::Success => Status::Failure,
+                _ => Status::Success,
+            },
+            DecoratorKind::Succeed => Status::Success,
+            DecoratorKind::Repeat(n) => match status {
+                Status::Failure => {
+                    self.count = 0;
+                    Status::Failure
+                }
+                _ => {
+                    self.count += 1;
+                    if self.count >= n {
+                        self.count = 0;
+                        Status::Success
+                    } else {
+                        Status::Running
+                    }
+                }
+            },
+            DecoratorKind::UntilFail => match status {
+                Status::Failure => Status::Success,
+                _ => Status::Running,
+            },
+            DecoratorKind::Cooldown(_) => {
+                self.since_done = Some(Duration::ZERO);
+                status
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.child.reset();
+        self.count = 0;
+    }
+}
+
+pub fn sequence<C: 'static>(children: Vec<Node<C>>) -> Node<C> {
+    Box::new(Sequence {
+        children,
+        current: 0,
+    })
+}
+
+pub fn selector<C: 'static>(children: Vec<Node<C>>) -> Node<C> {
+    Box::new(Selector {
+        children,
+        current: 0,
+    })
+}
+
+pub fn action<C: 'static>(
+    f: impl FnMut(&mut C, &mut Blackboard, Duration) -> Status + 'static,
+) -> Node<C> {
+    Box::new(Action(f))
+}
+
+pub fn condition<C: 'static>(f: impl FnMut(&C, &Blackboard) -> bool + 'static) -> Node<C> {
+    Box::new(Condition(f))
+}
+
+pub fn wait<C: 'static>(duration: Duration) -> Node<C> {
+    Box::new(Wait {
+        duration,
+        elapsed: Duration::ZERO,
+    })
+}
+
+pub fn decorate<C: 'static>(kind: DecoratorKind, child: Node<C>) -> Node<C> {
+    Box::new(Decorator {
+        kind,
+        child,
+        count: 0,
+        since_done: None,
+    })
+}
+
+pub fn invert<C: 'static>(child: Node<C>) -> Node<C> {
+    decorate(DecoratorKind::Invert, child)
+}
+
+pub fn repeat<C: 'static>(times: u32, child: Node<C>) -> Node<C> {
+    decorate(DecoratorKind::Repeat(times), child)
+}
+
+pub fn cooldown<C: 'static>(duration: Duration, child: Node<C>) -> Node<C> {
+    decorate(DecoratorKind::Cooldown(duration), child)
+}
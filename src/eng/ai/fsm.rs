@@ -0,0 +1,162 @@
+use std::{collections::HashMap, hash::Hash, time::Duration};
+
+/// A state of a StateMachine over context `C`, keyed by `K`. update returns the state to
+/// switch to, if any. Closures `FnMut(&mut C, Duration) -> Option<K>` are states too.
+pub trait State<K, C> {
+    fn enter(&mut self, _ctx: &mut C) {}
+    fn exit(&mut self, _ctx: &mut C) {}
+    fn update(&mut self, ctx: &mut C, dt: Duration) -> Option<K>;
+}
+
+impl<K, C, F: FnMut(&mut C, Duration) -> Option<K>> State<K, C> for F {
+    fn update(&mut self, ctx: &mut C, dt: Duration) -> Option<K> {
+        self(ctx, dt)
+    }
+}
+
+struct Node<K, C> {
+    state: Box<dyn State<K, C>>,
+    parent: Option<K>,
+    initial_child: Option<K>,
+}
+
+/// Hierarchical state machine. States may have children, entering a parent also enters
+/// its initial child, and transitions only exit and enter the states that change.
+pub struct StateMachine<K, C> {
+    nodes: HashMap<K, Node<K, C>>,
+    /// Active states from the outermost parent down to the leaf.
+    active: Vec<K>,
+}
+
+impl<K: Copy + Eq + Hash + std::fmt::Debug, C> Default for StateMachine<K, C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Copy + Eq + Hash + std::fmt::Debug, C> StateMachine<K, C> {
+    pub fn new() -> Self {
+        Self {
+            nodes: HashMap::new(),
+            active: Vec::new(),
+        }
+    }
+
+    /// Adds a top level state.
+    pub fn add(&mut self, key: K, state: impl State<K, C> + 'static) -> &mut Self {
+        self.insert(key, None, Box::new(state));
+        self
+    }
+
+    /// Adds a child of `parent`, the first child added is the initial one.
+    pub fn add_child(&mut self, parent: K, key: K, state: impl State<K, C> + 'static) -> &mut Self {
+        self.insert(key, Some(parent), Box::new(state));
+        if let Some(p) = self.nodes.get_mut(&parent) {
+            p.initial_child.get_or_insert(key);
+        }
+        self
+    }
+
+    pub fn set_initial_child(&mut self, parent: K, child: K) -> &mut Self {
+        if let Some(p) = self.nodes.get_mut(&parent) {
+            p.initial_child = Some(child);
+        }
+        self
+    }
+
+    fn insert(&mut self, key: K, parent: Option<K>, state: Box<dyn State<K, C>>) {
+        self.nodes.insert(
+            key,
+            Node {
+                state,
+                parent,
+                initial_child: None,
+            },
+        );
+    }
+
+    /// Innermost active state.
+    #[inline]
+    pub fn current(&self) -> Option<K> {
+        self.active.last().copied()
+    }
+
+    /// True if `key` or one of its children is active.
+    pub fn is_in(&self, key: K) -> bool {
+        self.active.contains(&key)
+    }
+
+    pub fn active(&self) -> &[K] {
+        &self.active
+    }
+
+    /// Enters `key` from nothing, exiting whatever was active first.
+    pub fn start(&mut self, ctx: &mut C, key: K) {
+        self.stop(ctx);
+        self.transition(ctx, key);
+    }
+
+    /// Exits every active state, innermost first.
+    pub fn stop(&mut self, ctx: &mut C) {
+        while let Some(key) = self.active.pop() {
+            if let Some(node) = self.nodes.get_mut(&key) {
+                node.state.exit(ctx);
+            }
+        }
+    }
+
+    /// Switches to `key`, exiting states up to the common parent and entering down to
+    /// `key` and then its initial children. Unknown keys are ignored.
+    pub fn transition(&mut self, ctx: &mut C, key: K) {
+        let Some(path) = self.path_to(key) else {
+            log::warn!("StateMachine::transition => Unknown state {:?}", key);
+            return;
+        };
+        // A transition to an already active state re-enters it.
+        let mut shared = self
+            .active
+            .iter()
+            .zip(path.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        if shared == path.len() {
+            shared -= 1;
+        }
+        while self.active.len() > shared {
+            let k = self.active.pop().unwrap();
+            self.nodes.get_mut(&k).unwrap().state.exit(ctx);
+        }
+        for &k in path[shared..].iter() {
+            self.active.push(k);
+            self.nodes.get_mut(&k).unwrap().state.enter(ctx);
+        }
+        while let Some(child) = self.current().and_then(|k| self.nodes[&k].initial_child) {
+            self.active.push(child);
+            self.nodes.get_mut(&child).unwrap().state.enter(ctx);
+        }
+    }
+
+    /// Updates the active states from the outermost in, so a parent can take over before
+    /// its children run. The first requested transition is taken and ends the update.
+    pub fn update(&mut self, ctx: &mut C, dt: Duration) {
+        for i in 0..self.active.len() {
+            let key = self.active[i];
+            let next = self.nodes.get_mut(&key).unwrap().state.update(ctx, dt);
+            if let Some(next) = next {
+                self.transition(ctx, next);
+                return;
+            }
+        }
+    }
+
+    fn path_to(&self, key: K) -> Option<Vec<K>> {
+        let mut path = vec![key];
+        let mut node = self.nodes.get(&key)?;
+        while let Some(parent) = node.parent {
+            path.push(parent);
+            node = self.nodes.get(&parent)?;
+        }
+        path.reverse();
+        Some(path)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod bt;
+pub mod fsm;
+pub mod path;
@@ -0,0 +1,425 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    hash::Hash,
+};
+
+use cgmath::InnerSpace;
+
+use crate::sys::geom::Vec2;
+
+/// A* over any graph. `neighbors` pushes (node, step cost) pairs, `heuristic` must not
+/// overestimate the remaining cost. Returns the path from start to goal and its cost.
+pub fn astar<N: Copy + Eq + Hash>(
+    start: N,
+    goal: N,
+    mut neighbors: impl FnMut(N, &mut Vec<(N, f32)>),
+    heuristic: impl Fn(N) -> f32,
+) -> Option<(Vec<N>, f32)> {
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<N, N> = HashMap::new();
+    let mut cost: HashMap<N, f32> = HashMap::new();
+    let mut buf = Vec::new();
+
+    cost.insert(start, 0.0);
+    open.push(OpenNode {
+        priority: heuristic(start),
+        cost: 0.0,
+        node: start,
+    });
+    while let Some(OpenNode { cost: g, node, .. }) = open.pop() {
+        if node == goal {
+            let mut path = vec![goal];
+            let mut cur = goal;
+            while let Some(&prev) = came_from.get(&cur) {
+                path.push(prev);
+                cur = prev;
+            }
+            path.reverse();
+            return Some((path, g));
+        }
+        // Stale entry, a cheaper route to this node was already expanded.
+        if cost.get(&node).is_some_and(|&best| g > best) {
+            continue;
+        }
+        buf.clear();
+        neighbors(node, &mut buf);
+        for &(next, step) in buf.iter() {
+            let next_cost = g + step;
+            if cost.get(&next).is_none_or(|&c| next_cost < c) {
+                cost.insert(next, next_cost);
+                came_from.insert(next, node);
+                open.push(OpenNode {
+                    priority: next_cost + heuristic(next),
+                    cost: next_cost,
+                    node: next,
+                });
+            }
+        }
+    }
+    None
+}
+
+struct OpenNode<N> {
+    priority: f32,
+    cost: f32,
+    node: N,
+}
+
+impl<N> PartialEq for OpenNode<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<N> Eq for OpenNode<N> {}
+
+impl<N> PartialOrd for OpenNode<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N> Ord for OpenNode<N> {
+    /// Reversed so BinaryHeap pops the lowest priority, ties go to the deeper node.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .priority
+            .total_cmp(&self.priority)
+            .then(self.cost.total_cmp(&other.cost))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Diagonal {
+    Never,
+    /// Diagonal steps are allowed when both adjacent orthogonal tiles are walkable.
+    #[default]
+    NoCornerCutting,
+    Always,
+}
+
+/// Grid of tile costs, None is blocked. Costs multiply the step length, so 1 is normal
+/// ground and 3 is e.g. mud.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridMap {
+    width: u32,
+    height: u32,
+    costs: Vec<Option<f32>>,
+}
+
+impl GridMap {
+    /// Every tile walkable with cost 1.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            costs: vec![Some(1.0); (width * height) as usize],
+        }
+    }
+
+    pub fn from_fn(width: u32, height: u32, mut cost: impl FnMut(u32, u32) -> Option<f32>) -> Self {
+        let mut map = Self::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                map.set_cost(x, y, cost(x, y));
+            }
+        }
+        map
+    }
+
+    /// Builds the walkable layer from a row major tile layer, `cost` maps a tile to its
+    /// cost or None for walls.
+    pub fn from_layer<T>(
+        width: u32,
+        height: u32,
+        tiles: &[T],
+        mut cost: impl FnMut(&T) -> Option<f32>,
+    ) -> Self {
+        debug_assert_eq!(tiles.len(), (width * height) as usize);
+        Self {
+            width,
+            height,
+            costs: tiles.iter().map(&mut cost).collect(),
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        (x >= 0 && y >= 0 && (x as u32) < self.width && (y as u32) < self.height)
+            .then(|| (y as u32 * self.width + x as u32) as usize)
+    }
+
+    /// None for blocked tiles and tiles outside the map.
+    pub fn cost(&self, x: i32, y: i32) -> Option<f32> {
+        self.index(x, y).and_then(|i| self.costs[i])
+    }
+
+    pub fn set_cost(&mut self, x: u32, y: u32, cost: Option<f32>) {
+        if let Some(i) = self.index(x as i32, y as i32) {
+            self.costs[i] = cost.map(|c| c.max(0.0));
+        }
+    }
+
+    #[inline]
+    pub fn is_walkable(&self, x: i32, y: i32) -> bool {
+        self.cost(x, y).is_some()
+    }
+
+    /// Cheapest path between two tiles, both included.
+    pub fn find_path(
+        &self,
+        start: (i32, i32),
+        goal: (i32, i32),
+        diagonal: Diagonal,
+    ) -> Option<Vec<(i32, i32)>> {
+        if !self.is_walkable(start.0, start.1) || !self.is_walkable(goal.0, goal.1) {
+            return None;
+        }
+        let min_cost = self
+            .costs
+            .iter()
+            .flatten()
+            .copied()
+            .fold(f32::MAX, f32::min);
+        let neighbors = |(x, y): (i32, i32), out: &mut Vec<((i32, i32), f32)>| {
+            for (dx, dy) in NEIGHBORS {
+                let diag = dx != 0 && dy != 0;
+                let allowed = match (diag, diagonal) {
+                    (false, _) | (true, Diagonal::Always) => true,
+                    (true, Diagonal::Never) => false,
+                    (true, Diagonal::NoCornerCutting) => {
+                        self.is_walkable(x + dx, y) && self.is_walkable(x, y + dy)
+                    }
+                };
+                if !allowed {
+                    continue;
+                }
+                if let Some(c) = self.cost(x + dx, y + dy) {
+                    let len = if diag { std::f32::consts::SQRT_2 } else { 1.0 };
+                    out.push(((x + dx, y + dy), len * c));
+                }
+            }
+        };
+        let heuristic = |(x, y): (i32, i32)| {
+            let (dx, dy) = ((goal.0 - x).abs() as f32, (goal.1 - y).abs() as f32);
+            let dist = if diagonal == Diagonal::Never {
+                dx + dy
+            } else {
+                // Octile distance.
+                dx.max(dy) + (std::f32::consts::SQRT_2 - 1.0) * dx.min(dy)
+            };
+            dist * min_cost
+        };
+        astar(start, goal, neighbors, heuristic).map(|(path, _)| path)
+    }
+
+    /// True if every tile the segment between the tile centers passes through is walkable.
+    pub fn line_of_sight(&self, from: (i32, i32), to: (i32, i32)) -> bool {
+        let (mut x, mut y) = from;
+        let (dx, dy) = ((to.0 - x).abs(), (to.1 - y).abs());
+        let (sx, sy) = ((to.0 - x).signum(), (to.1 - y).signum());
+        let mut err = dx - dy;
+        loop {
+            if !self.is_walkable(x, y) {
+                return false;
+            }
+            if (x, y) == to {
+                return true;
+            }
+            let e2 = 2 * err;
+            // Stepping through a corner touches both tiles around it.
+            if e2 > -dy && e2 < dx && !(self.is_walkable(x + sx, y) && self.is_walkable(x, y + sy))
+            {
+                return false;
+            }
+            if e2 > -dy {
+                err -= dy;
+                x += sx;
+            }
+            if e2 < dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Drops waypoints that can be skipped in a straight line. Only use on uniform cost
+    /// maps, shortcuts ignore tile costs.
+    pub fn smooth_path(&self, path: &[(i32, i32)]) -> Vec<(i32, i32)> {
+        let Some(&first) = path.first() else {
+            return Vec::new();
+        };
+        let mut out = vec![first];
+        let mut anchor = 0;
+        for i in 1..path.len() {
+            if i + 1 == path.len() || !self.line_of_sight(path[anchor], path[i + 1]) {
+                out.push(path[i]);
+                anchor = i;
+            }
+        }
+        out
+    }
+}
+
+const NEIGHBORS: [(i32, i32); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+/// Navmesh-lite: convex polygons that are connected where they share an edge.
+/// Polygons are counter clockwise, vertices of shared edges must match exactly.
+#[derive(Debug, Clone, Default)]
+pub struct RegionGraph {
+    regions: Vec<Vec<Vec2>>,
+    /// Per region, (neighbor, portal left, portal right) as seen from inside the region.
+    links: Vec<Vec<(usize, Vec2, Vec2)>>,
+}
+
+impl RegionGraph {
+    pub fn new(regions: Vec<Vec<Vec2>>) -> Self {
+        let mut links = vec![Vec::new(); regions.len()];
+        for (i, a) in regions.iter().enumerate() {
+            for (j, b) in regions.iter().enumerate().skip(i + 1) {
+                for (a0, a1) in edges(a) {
+                    if edges(b).any(|(b0, b1)| b0 == a1 && b1 == a0) {
+                        // Walking out of a CCW polygon, a1 is on the left.
+                        links[i].push((j, a1, a0));
+                        links[j].push((i, a0, a1));
+                    }
+                }
+            }
+        }
+        Self { regions, links }
+    }
+
+    pub fn regions(&self) -> &[Vec<Vec2>] {
+        &self.regions
+    }
+
+    /// Region containing `p`, the first one if it is on a shared edge.
+    pub fn region_at(&self, p: Vec2) -> Option<usize> {
+        self.regions.iter().position(|poly| {
+            edges(poly).all(|(a, b)| {
+                let (e, d) = (b - a, p - a);
+                e.x * d.y - e.y * d.x >= -1e-5
+            })
+        })
+    }
+
+    /// Shortest corridor through the regions, pulled tight around the corners with the
+    /// funnel algorithm. Includes `start` and `goal`.
+    pub fn find_path(&self, start: Vec2, goal: Vec2) -> Option<Vec<Vec2>> {
+        let from = self.region_at(start)?;
+        let to = self.region_at(goal)?;
+        if from == to {
+            return Some(vec![start, goal]);
+        }
+
+        // Regions are connected centroid to centroid through the portal midpoints.
+        let (corridor, _) = astar(
+            from,
+            to,
+            |r, out| {
+                let here = self.centroid(r);
+                for &(n, l, rt) in self.links[r].iter() {
+                    let mid = (l + rt) * 0.5;
+                    out.push((
+                        n,
+                        (mid - here).magnitude() + (self.centroid(n) - mid).magnitude(),
+                    ));
+                }
+            },
+            |r| (self.centroid(to) - self.centroid(r)).magnitude(),
+        )?;
+
+        let mut portals = vec![(start, start)];
+        for pair in corridor.windows(2) {
+            let &(_, l, r) = self.links[pair[0]].iter().find(|(n, _, _)| *n == pair[1])?;
+            portals.push((l, r));
+        }
+        portals.push((goal, goal));
+        Some(funnel(&portals))
+    }
+
+    fn centroid(&self, region: usize) -> Vec2 {
+        let poly = &self.regions[region];
+        poly.iter().fold(Vec2::new(0.0, 0.0), |a, p| a + p) / poly.len() as f32
+    }
+}
+
+fn edges(poly: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    (0..poly.len()).map(move |i| (poly[i], poly[(i + 1) % poly.len()]))
+}
+
+#[inline]
+fn cross(o: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let (a, b) = (a - o, b - o);
+    a.x * b.y - a.y * b.x
+}
+
+/// Simple stupid funnel algorithm over (left, right) portals.
+fn funnel(portals: &[(Vec2, Vec2)]) -> Vec<Vec2> {
+    let mut path = vec![portals[0].0];
+    let (mut apex, mut left, mut right) = (portals[0].0, portals[0].0, portals[0].1);
+    let (mut left_i, mut right_i) = (0, 0);
+
+    let mut i = 1;
+    while i < portals.len() {
+        let (pl, pr) = portals[i];
+
+        if cross(apex, right, pr) >= 0.0 {
+            if apex == right || cross(apex, left, pr) < 0.0 {
+                right = pr;
+                right_i = i;
+            } else {
+                // Right crossed over left, left becomes a corner of the path.
+                path.push(left);
+                apex = left;
+                let apex_i = left_i;
+                (left, right) = (apex, apex);
+                (left_i, right_i) = (apex_i, apex_i);
+                i = apex_i + 1;
+                continue;
+            }
+        }
+
+        if cross(apex, left, pl) <= 0.0 {
+            if apex == left || cross(apex, right, pl) > 0.0 {
+                left = pl;
+                left_i = i;
+            } else {
+                path.push(right);
+                apex = right;
+                let apex_i = right_i;
+                (left, right) = (apex, apex);
+                (left_i, right_i) = (apex_i, apex_i);
+                i = apex_i + 1;
+                continue;
+            }
+        }
+        i += 1;
+    }
+
+    let goal = portals[portals.len() - 1].0;
+    if path.last() != Some(&goal) {
+        path.push(goal);
+    }
+    path
+}
@@ -0,0 +1,341 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use cgmath::{InnerSpace, Quaternion, Vector3, VectorSpace};
+
+use crate::gfx::transform::Transform;
+
+/// Property a track writes to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "scene", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrackProperty {
+    Position,
+    Rotation,
+    Scale,
+    SpriteFrame,
+    Color,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "scene", derive(serde::Serialize, serde::Deserialize))]
+pub enum TrackValue {
+    Vec3([f32; 3]),
+    /// Quaternion as [x, y, z, w].
+    Quat([f32; 4]),
+    Frame(u32),
+    Color([f32; 4]),
+}
+
+impl TrackValue {
+    /// Blends towards `other` by `t`. Frames and mismatched value kinds switch at the halfway point.
+    pub fn blend(&self, other: &TrackValue, t: f32) -> TrackValue {
+        match (self, other) {
+            (TrackValue::Vec3(a), TrackValue::Vec3(b)) => {
+                TrackValue::Vec3(Vector3::from(*a).lerp(Vector3::from(*b), t).into())
+            }
+            (TrackValue::Quat(a), TrackValue::Quat(b)) => {
+                let (a, b) = (Quaternion::from(*a), Quaternion::from(*b));
+                // Take the short way around.
+                let b = if a.dot(b) < 0.0 { -b } else { b };
+                TrackValue::Quat(a.nlerp(b, t).into())
+            }
+            (TrackValue::Color(a), TrackValue::Color(b)) => {
+                TrackValue::Color(std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t))
+            }
+            _ if t < 0.5 => *self,
+            _ => *other,
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "scene", derive(serde::Serialize, serde::Deserialize))]
+pub enum Interpolation {
+    Step,
+    #[default]
+    Linear,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "scene", derive(serde::Serialize, serde::Deserialize))]
+pub struct Keyframe {
+    /// Seconds from the start of the clip.
+    pub time: f32,
+    pub value: TrackValue,
+}
+
+/// Keyframes for one property of one named target.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "scene", derive(serde::Serialize, serde::Deserialize))]
+pub struct Track {
+    /// Name of the entity the track animates, matched by AnimationTarget::apply.
+    pub target: String,
+    pub property: TrackProperty,
+    #[cfg_attr(feature = "scene", serde(default))]
+    pub interpolation: Interpolation,
+    /// Sorted by time.
+    pub keys: Vec<Keyframe>,
+}
+
+impl Track {
+    pub fn new(
+        target: impl Into<String>,
+        property: TrackProperty,
+        mut keys: Vec<Keyframe>,
+    ) -> Self {
+        keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self {
+            target: target.into(),
+            property,
+            interpolation: Interpolation::default(),
+            keys,
+        }
+    }
+
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Value at `time`, holding the first and last keys outside of their range.
+    pub fn sample(&self, time: f32) -> Option<TrackValue> {
+        let first = self.keys.first()?;
+        let next = self.keys.partition_point(|k| k.time <= time);
+        if next == 0 {
+            return Some(first.value);
+        }
+        let prev = &self.keys[next - 1];
+        let Some(next) = self.keys.get(next) else {
+            return Some(prev.value);
+        };
+        match self.interpolation {
+            Interpolation::Step => Some(prev.value),
+            Interpolation::Linear => {
+                let span = next.time - prev.time;
+                let t = if span > 0.0 {
+                    (time - prev.time) / span
+                } else {
+                    1.0
+                };
+                Some(prev.value.blend(&next.value, t))
+            }
+        }
+    }
+}
+
+/// A set of tracks played together.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "scene", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnimationClip {
+    pub name: String,
+    /// Length in seconds, defaults to the time of the last key.
+    pub duration: f32,
+    #[cfg_attr(feature = "scene", serde(default))]
+    pub looping: bool,
+    pub tracks: Vec<Track>,
+}
+
+impl AnimationClip {
+    pub fn new(name: impl Into<String>, tracks: Vec<Track>) -> Self {
+        let duration = tracks
+            .iter()
+            .filter_map(|t| t.keys.last())
+            .map(|k| k.time)
+            .fold(0.0, f32::max);
+        Self {
+            name: name.into(),
+            duration,
+            looping: false,
+            tracks,
+        }
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    fn local_time(&self, time: f32) -> f32 {
+        if self.looping && self.duration > 0.0 {
+            time.rem_euclid(self.duration)
+        } else {
+            time.min(self.duration)
+        }
+    }
+
+    /// Samples every track at `time` into `out`, keyed by target and property.
+    fn sample_into(&self, time: f32, out: &mut HashMap<(String, TrackProperty), TrackValue>) {
+        let time = self.local_time(time);
+        for track in &self.tracks {
+            if let Some(value) = track.sample(time) {
+                out.insert((track.target.clone(), track.property), value);
+            }
+        }
+    }
+
+    #[cfg(feature = "scene")]
+    pub fn from_ron(source: &str) -> anyhow::Result<Self> {
+        Ok(ron::from_str(source)?)
+    }
+
+    #[cfg(feature = "scene")]
+    pub fn to_ron(&self) -> anyhow::Result<String> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+}
+
+/// Something a ClipPlayer can write sampled values into.
+pub trait AnimationTarget {
+    fn apply(&mut self, target: &str, property: TrackProperty, value: &TrackValue);
+}
+
+/// A single Transform takes every transform track regardless of the target name.
+impl AnimationTarget for Transform {
+    fn apply(&mut self, _target: &str, property: TrackProperty, value: &TrackValue) {
+        match (property, value) {
+            (TrackProperty::Position, TrackValue::Vec3(v)) => self.set_position((*v).into()),
+            (TrackProperty::Rotation, TrackValue::Quat(q)) => {
+                self.set_rotation(Quaternion::from(*q).normalize())
+            }
+            (TrackProperty::Scale, TrackValue::Vec3(v)) => self.set_size((*v).into()),
+            _ => {}
+        }
+    }
+}
+
+/// Transform tracks move entities with a matching name, sprite frame and color tracks
+/// are written to their "frame" and "color" properties.
+#[cfg(feature = "scene")]
+impl AnimationTarget for super::scene::Scene {
+    fn apply(&mut self, target: &str, property: TrackProperty, value: &TrackValue) {
+        use super::scene::PropertyValue;
+
+        for entity in self.entities.iter_mut().filter(|e| e.name == target) {
+            let (name, value) = match (property, value) {
+                (TrackProperty::SpriteFrame, TrackValue::Frame(f)) => {
+                    ("frame", PropertyValue::Float(*f as f32))
+                }
+                (TrackProperty::Color, TrackValue::Color([r, g, b, _])) => {
+                    ("color", PropertyValue::Vec3([*r, *g, *b]))
+                }
+                _ => {
+                    entity.transform.apply(target, property, value);
+                    continue;
+                }
+            };
+            match entity.properties.iter_mut().find(|(n, _)| n == name) {
+                Some((_, current)) => *current = value,
+                None => entity.properties.push((name.to_string(), value)),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Playing {
+    clip: Rc<AnimationClip>,
+    time: f32,
+}
+
+/// Plays clips onto an AnimationTarget, call frame_update from RadApp::frame_update.
+/// Starting a clip with a fade blends from the previous clip over the fade duration.
+#[derive(Debug, Clone)]
+pub struct ClipPlayer {
+    current: Option<Playing>,
+    /// Clip being faded out, with the elapsed and total fade time.
+    previous: Option<(Playing, f32, f32)>,
+    pub speed: f32,
+    pub paused: bool,
+}
+
+impl Default for ClipPlayer {
+    fn default() -> Self {
+        Self {
+            current: None,
+            previous: None,
+            speed: 1.0,
+            paused: false,
+        }
+    }
+}
+
+impl ClipPlayer {
+    pub fn play(&mut self, clip: Rc<AnimationClip>) {
+        self.current = Some(Playing { clip, time: 0.0 });
+        self.previous = None;
+    }
+
+    /// Starts `clip`, blending out of the current clip over `fade`.
+    pub fn crossfade(&mut self, clip: Rc<AnimationClip>, fade: Duration) {
+        self.previous = self.current.take().map(|p| (p, 0.0, fade.as_secs_f32()));
+        self.current = Some(Playing { clip, time: 0.0 });
+    }
+
+    pub fn stop(&mut self) {
+        self.current = None;
+        self.previous = None;
+    }
+
+    #[inline]
+    pub fn clip(&self) -> Option<&Rc<AnimationClip>> {
+        self.current.as_ref().map(|p| &p.clip)
+    }
+
+    /// Seconds into the current clip.
+    #[inline]
+    pub fn time(&self) -> f32 {
+        self.current.as_ref().map_or(0.0, |p| p.time)
+    }
+
+    /// True once a non looping clip reached its end.
+    pub fn is_finished(&self) -> bool {
+        self.current
+            .as_ref()
+            .is_none_or(|p| !p.clip.looping && p.time >= p.clip.duration)
+    }
+
+    /// Advances playback by `dt` and writes the blended values into `target`.
+    pub fn frame_update(&mut self, dt: Duration, target: &mut impl AnimationTarget) {
+        let dt = if self.paused {
+            0.0
+        } else {
+            dt.as_secs_f32() * self.speed
+        };
+        let Some(current) = self.current.as_mut() else {
+            return;
+        };
+        current.time += dt;
+
+        let mut values = HashMap::new();
+        current.clip.sample_into(current.time, &mut values);
+
+        if let Some((previous, elapsed, fade)) = self.previous.as_mut() {
+            previous.time += dt;
+            *elapsed += dt;
+            let weight = if *fade > 0.0 {
+                (*elapsed / *fade).min(1.0)
+            } else {
+                1.0
+            };
+            if weight >= 1.0 {
+                self.previous = None;
+            } else {
+                let mut from = HashMap::new();
+                previous.clip.sample_into(previous.time, &mut from);
+                for (key, old) in from {
+                    let blended = match values.get(&key) {
+                        Some(new) => old.blend(new, weight),
+                        None => old,
+                    };
+                    values.insert(key, blended);
+                }
+            }
+        }
+
+        for ((name, property), value) in &values {
+            target.apply(name, *property, value);
+        }
+    }
+}
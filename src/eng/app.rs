@@ -1,33 +1,332 @@
-use std::{cell::RefCell, future::Future, rc::Rc, time::Duration};
+use std::{
+    cell::RefCell,
+    future::Future,
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 use winit::{
-    event::{
-        DeviceEvent, ElementState, Event, KeyboardInput, MouseScrollDelta, VirtualKeyCode,
-        WindowEvent,
-    },
-    event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    application::ApplicationHandler,
+    event::{DeviceEvent, DeviceId, ElementState, KeyEvent, MouseScrollDelta, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    keyboard::{KeyCode, PhysicalKey},
+    window::{Window, WindowAttributes, WindowId, WindowLevel},
 };
 
-use crate::gfx;
+use crate::{
+    gfx::{
+        self,
+        post::accessibility::AccessibilitySettings,
+        tweak::TweakValues,
+        wgpu::surface::{SurfaceError, SurfaceOptions},
+    },
+    sys::{
+        fs::{self, UserDir},
+        lightmap::{parse_bake_quality, BakeQuality},
+    },
+};
 
-use super::{command::RenderPassOp, render::RenderWindow};
+#[cfg(feature = "audio")]
+use super::audio::bus::AudioSettings;
+#[cfg(feature = "scripting")]
+use super::script::ScriptCommand;
+use super::{
+    command::RenderPassOp,
+    context::EngineContext,
+    input::{mouse::MouseEvent, shortcut::TOGGLE_FULLSCREEN},
+    render::RenderWindow,
+};
 
 pub trait RadApp {
-    fn process_keyboard(&mut self, key: VirtualKeyCode, state: ElementState) -> InputEventStatus {
+    fn process_keyboard(&mut self, key: KeyCode, state: ElementState) -> InputEventStatus {
         InputEventStatus::Done
     }
 
     fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {}
+    /// Presses, clicks and drags as they happen, also queryable through RenderWindow::mouse.
+    fn process_mouse_event(&mut self, _event: &MouseEvent) {}
+    /// Called for every shortcut action fired, the key press itself is not passed on.
+    fn process_shortcut(&mut self, _action: &str) {}
+    /// Called with each command a playing cutscene reaches, before frame_update.
+    #[cfg(feature = "scripting")]
+    fn process_script_command(&mut self, _command: &ScriptCommand) {}
     fn process_scroll(&mut self, delta: &MouseScrollDelta) {}
-    fn draw_frame(&mut self, ctx: &mut gfx::draw::DrawCtx) -> Result<(), wgpu::SurfaceError>;
+    fn draw_frame(&mut self, ctx: &mut gfx::draw::DrawCtx) -> Result<(), SurfaceError>;
     fn frame_update(&mut self, dt: Duration);
+    /// Called zero or more times per frame with EngineContext::fixed_timestep, before frame_update.
+    fn fixed_update(&mut self, _dt: Duration) {}
 
     fn handle_window_events(&mut self, event: &WindowEvent) -> InputEventStatus {
         InputEventStatus::Done
     }
 }
 
+/// App driven by the headless loop, see Radium::start_headless. There is no window,
+/// surface or GPU device, only the update loop.
+pub trait HeadlessApp {
+    fn frame_update(&mut self, dt: Duration);
+    fn fixed_update(&mut self, _dt: Duration) {}
+
+    /// Checked after every update, the loop exits once this returns true.
+    fn should_exit(&self) -> bool {
+        false
+    }
+}
+
+/// Options read at startup that decide how Radium runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineConfig {
+    /// Names the app's config, data and cache directories, see sys::fs::user_dir.
+    pub app_name: String,
+    /// Run without creating a window or initializing wgpu, e.g. for a dedicated server.
+    pub headless: bool,
+    /// Updates per second of the headless loop.
+    pub tick_rate: u32,
+    /// Mixer bus settings applied to EngineContext::audio on launch.
+    #[cfg(feature = "audio")]
+    pub audio: AudioSettings,
+    /// Surface format, alpha mode and view formats, `surface.*` keys in the config file.
+    pub surface: SurfaceOptions,
+    /// `window.*` keys in the config file.
+    pub window: WindowOptions,
+    /// Persist the driver's pipeline cache in cache_dir where the backend supports it.
+    pub pipeline_cache: bool,
+    /// Request wgpu immediates (push constants) where the adapter has them, see
+    /// gfx::wgpu::immediate::Immediates.
+    pub immediates: bool,
+    /// Frames longer than this many milliseconds log a diagnostic dump, 0 disables it.
+    pub watchdog_ms: u64,
+    /// Quality of lightmaps baked at load time, see sys::lightmap::BakeSettings.
+    pub lightmap_quality: BakeQuality,
+    /// Multiplier on the platform scale factor for UI and text, see gfx::scale::DisplayScale.
+    pub ui_scale: f32,
+    /// Color vision filter and reduced flash mode, `accessibility.*` keys in the config file.
+    pub accessibility: AccessibilitySettings,
+    /// Values of the tweak panel, `tweak.*` keys in the config file.
+    pub tweaks: TweakValues,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        Self {
+            app_name: String::from("radium"),
+            headless: false,
+            tick_rate: 60,
+            #[cfg(feature = "audio")]
+            audio: AudioSettings::default(),
+            surface: SurfaceOptions::default(),
+            window: WindowOptions::default(),
+            pipeline_cache: true,
+            immediates: true,
+            watchdog_ms: 250,
+            lightmap_quality: BakeQuality::default(),
+            ui_scale: 1.0,
+            accessibility: AccessibilitySettings::default(),
+            tweaks: TweakValues::default(),
+        }
+    }
+}
+
+impl EngineConfig {
+    pub const HEADLESS_VAR: &'static str = "RADIUM_HEADLESS";
+    pub const TICK_RATE_VAR: &'static str = "RADIUM_TICK_RATE";
+
+    /// Reads RADIUM_HEADLESS (1/true) and RADIUM_TICK_RATE, falling back to the defaults.
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+        config.apply_env();
+        config
+    }
+
+    fn apply_env(&mut self) {
+        if let Ok(headless) = std::env::var(Self::HEADLESS_VAR) {
+            self.headless = matches!(headless.trim(), "1" | "true" | "TRUE" | "True");
+        }
+        if let Some(tick_rate) = std::env::var(Self::TICK_RATE_VAR)
+            .ok()
+            .and_then(|t| t.trim().parse().ok())
+        {
+            self.tick_rate = tick_rate;
+        }
+    }
+
+    /// Parses `key = value` lines, blank lines and lines starting with # are skipped.
+    /// Keys missing from `source` keep their defaults.
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        let mut config = Self::default();
+        for (number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                anyhow::bail!(
+                    "EngineConfig::parse => line {} is not key = value",
+                    number + 1
+                );
+            };
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "app_name" => config.app_name = String::from(value),
+                "headless" => config.headless = value.parse()?,
+                "tick_rate" => config.tick_rate = value.parse()?,
+                "pipeline_cache" => config.pipeline_cache = value.parse()?,
+                "immediates" => config.immediates = value.parse()?,
+                "watchdog_ms" => config.watchdog_ms = value.parse()?,
+                "lightmap_quality" => config.lightmap_quality = parse_bake_quality(value)?,
+                "ui_scale" => config.ui_scale = value.parse()?,
+                #[cfg(feature = "audio")]
+                _ if config.audio.set(key, value)? => {}
+                _ if config.surface.set(key, value)? => {}
+                _ if config.window.set(key, value)? => {}
+                _ if config.accessibility.set(key, value)? => {}
+                _ if config.tweaks.set(key, value)? => {}
+                _ => log::warn!("EngineConfig::parse => unknown key {}", key),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Reads the config file at `path`, then applies environment overrides like from_env.
+    pub fn load(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let mut config = Self::parse(&std::fs::read_to_string(path)?)?;
+        config.apply_env();
+        Ok(config)
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
+        fs::write_atomic(path, self.to_string().as_bytes())
+    }
+
+    /// The app's config directory, created if missing.
+    pub fn config_dir(&self) -> anyhow::Result<PathBuf> {
+        fs::ensure_dir(fs::user_dir(UserDir::Config, &self.app_name)?)
+    }
+
+    /// The app's data directory, created if missing.
+    pub fn data_dir(&self) -> anyhow::Result<PathBuf> {
+        fs::ensure_dir(fs::user_dir(UserDir::Data, &self.app_name)?)
+    }
+
+    /// The app's cache directory, created if missing.
+    pub fn cache_dir(&self) -> anyhow::Result<PathBuf> {
+        fs::ensure_dir(fs::user_dir(UserDir::Cache, &self.app_name)?)
+    }
+
+    #[inline]
+    pub fn tick_duration(&self) -> Duration {
+        Duration::from_secs(1) / self.tick_rate.max(1)
+    }
+}
+
+/// Writes the `key = value` format read by EngineConfig::parse.
+impl std::fmt::Display for EngineConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "app_name = {}", self.app_name)?;
+        writeln!(f, "headless = {}", self.headless)?;
+        writeln!(f, "tick_rate = {}", self.tick_rate)?;
+        writeln!(f, "pipeline_cache = {}", self.pipeline_cache)?;
+        writeln!(f, "immediates = {}", self.immediates)?;
+        writeln!(f, "watchdog_ms = {}", self.watchdog_ms)?;
+        writeln!(f, "lightmap_quality = {}", self.lightmap_quality.name())?;
+        writeln!(f, "ui_scale = {}", self.ui_scale)?;
+        let mut entries = Vec::new();
+        #[cfg(feature = "audio")]
+        entries.extend(self.audio.entries());
+        entries.extend(self.surface.entries());
+        entries.extend(self.window.entries());
+        entries.extend(self.accessibility.entries());
+        entries.extend(self.tweaks.entries());
+        for (key, value) in entries {
+            writeln!(f, "{} = {}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// How the window is created, mostly for overlay and widget style apps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowOptions {
+    pub title: String,
+    /// Lets the desktop show through where the frame has alpha below 1. The window is
+    /// cleared with alpha 0 and the surface uses a compositing alpha mode when one is
+    /// supported and EngineConfig::surface leaves the alpha mode on auto.
+    pub transparent: bool,
+    pub always_on_top: bool,
+    /// Title bar and borders, false for a borderless window.
+    pub decorations: bool,
+    /// Mouse input passes through to whatever is behind the window, where the platform
+    /// supports it. winit only allows this for the whole window, see
+    /// RenderWindow::set_click_through to toggle it at runtime.
+    pub click_through: bool,
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        Self {
+            title: String::from("radium"),
+            transparent: false,
+            always_on_top: false,
+            decorations: true,
+            click_through: false,
+        }
+    }
+}
+
+impl WindowOptions {
+    /// Applies a `window.*` config key, returns false for keys of other sections.
+    pub fn set(&mut self, key: &str, value: &str) -> anyhow::Result<bool> {
+        let Some(field) = key.strip_prefix("window.") else {
+            return Ok(false);
+        };
+        match field {
+            "title" => self.title = String::from(value),
+            "transparent" => self.transparent = value.parse()?,
+            "always_on_top" => self.always_on_top = value.parse()?,
+            "decorations" => self.decorations = value.parse()?,
+            "click_through" => self.click_through = value.parse()?,
+            _ => anyhow::bail!("WindowOptions::set => unknown window setting {}", field),
+        }
+        Ok(true)
+    }
+
+    /// `key = value` pairs read back by set.
+    pub fn entries(&self) -> Vec<(String, String)> {
+        vec![
+            (String::from("window.title"), self.title.clone()),
+            (
+                String::from("window.transparent"),
+                self.transparent.to_string(),
+            ),
+            (
+                String::from("window.always_on_top"),
+                self.always_on_top.to_string(),
+            ),
+            (
+                String::from("window.decorations"),
+                self.decorations.to_string(),
+            ),
+            (
+                String::from("window.click_through"),
+                self.click_through.to_string(),
+            ),
+        ]
+    }
+
+    pub fn attributes(&self) -> WindowAttributes {
+        Window::default_attributes()
+            .with_title(self.title.clone())
+            .with_transparent(self.transparent)
+            .with_decorations(self.decorations)
+            .with_window_level(if self.always_on_top {
+                WindowLevel::AlwaysOnTop
+            } else {
+                WindowLevel::Normal
+            })
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum InputEventStatus {
     Processing,
@@ -52,91 +351,347 @@ impl From<InputEventStatus> for bool {
 
 pub struct Radium;
 impl Radium {
-    pub async fn start<A, F, Fut>(factory: F) -> anyhow::Result<()>
+    /// Starts either the windowed or headless loop depending on config.headless. Only the
+    /// matching factory is called, so a server build never touches the window or GPU.
+    pub async fn launch<A, F, Fut, H, HF, HFut>(
+        config: EngineConfig,
+        factory: F,
+        headless_factory: HF,
+    ) -> anyhow::Result<()>
     where
         A: RadApp + 'static,
         F: Fn(Rc<RefCell<RenderWindow>>) -> Fut,
         Fut: Future<Output = anyhow::Result<A>>,
+        H: HeadlessApp,
+        HF: FnOnce(Rc<EngineContext>) -> HFut,
+        HFut: Future<Output = anyhow::Result<H>>,
     {
-        let event_loop = EventLoop::new();
-        let window = WindowBuilder::new().build(&event_loop)?;
-        let render_window = Rc::new(RefCell::new(RenderWindow::from_winit(window, None).await?));
+        if config.headless {
+            Self::start_headless(config, headless_factory).await
+        } else {
+            #[cfg(feature = "audio")]
+            let audio = config.audio;
+            Self::start_with_config(config, move |window: Rc<RefCell<RenderWindow>>| {
+                #[cfg(feature = "audio")]
+                window
+                    .borrow()
+                    .engine()
+                    .audio()
+                    .lock()
+                    .apply_settings(&audio);
+                factory(window)
+            })
+            .await
+        }
+    }
 
+    /// Runs the update loop at config.tick_rate without a window, surface or wgpu device.
+    /// Await it inside a tokio runtime with the timer enabled, ticks sleep on tokio's timer.
+    pub async fn start_headless<H, F, Fut>(config: EngineConfig, factory: F) -> anyhow::Result<()>
+    where
+        H: HeadlessApp,
+        F: FnOnce(Rc<EngineContext>) -> Fut,
+        Fut: Future<Output = anyhow::Result<H>>,
+    {
+        let engine = Rc::new(EngineContext::new());
+        #[cfg(feature = "audio")]
+        engine.audio().lock().apply_settings(&config.audio);
+        let mut app = factory(engine.clone()).await?;
+        let tick = config.tick_duration();
         let mut last_dt = std::time::Instant::now();
 
-        let mut app = factory(render_window.clone()).await?;
-
-        event_loop.run(move |event, _, control_flow| {
-            *control_flow = ControlFlow::Poll;
-
-            match event {
-                Event::WindowEvent {
-                    ref event,
-                    window_id,
-                } if window_id == render_window.borrow().window_id() => {
-                    match app.handle_window_events(event) {
-                        InputEventStatus::Processing => {}
-                        InputEventStatus::Done => match event {
-                            WindowEvent::CloseRequested
-                            | WindowEvent::KeyboardInput {
-                                input:
-                                    KeyboardInput {
-                                        state: ElementState::Pressed,
-                                        virtual_keycode: Some(VirtualKeyCode::Escape),
-                                        ..
-                                    },
-                                ..
-                            } => {
-                                *control_flow = ControlFlow::Exit;
-                            }
-                            WindowEvent::Resized(physical_size) => {
-                                render_window.borrow_mut().resize(*physical_size);
-                            }
-                            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                                render_window.borrow_mut().resize(**new_inner_size);
-                            }
-                            _ => {}
-                        },
-                    }
+        while !app.should_exit() {
+            let now = std::time::Instant::now();
+            let dt = now - last_dt;
+            last_dt = now;
+
+            if let Some(dt) = engine.begin_frame(dt) {
+                for _ in 0..engine.fixed_steps(dt) {
+                    app.fixed_update(engine.fixed_timestep());
+                }
+                app.frame_update(dt);
+            }
+
+            // Awaited rather than std::thread::sleep so other tasks on the runtime keep running.
+            let elapsed = now.elapsed();
+            if elapsed < tick {
+                tokio::time::sleep(tick - elapsed).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs `factory`'s app in a window. The window and app are created once winit
+    /// resumes the event loop, errors from either are returned after the loop exits.
+    pub async fn start<A, F, Fut>(factory: F) -> anyhow::Result<()>
+    where
+        A: RadApp + 'static,
+        F: Fn(Rc<RefCell<RenderWindow>>) -> Fut,
+        Fut: Future<Output = anyhow::Result<A>>,
+    {
+        Self::start_with_config(EngineConfig::default(), factory).await
+    }
+
+    /// Same as Radium::start, with `config` deciding how the window and device are created.
+    pub async fn start_with_config<A, F, Fut>(
+        config: EngineConfig,
+        factory: F,
+    ) -> anyhow::Result<()>
+    where
+        A: RadApp + 'static,
+        F: Fn(Rc<RefCell<RenderWindow>>) -> Fut,
+        Fut: Future<Output = anyhow::Result<A>>,
+    {
+        let event_loop = EventLoop::new()?;
+        event_loop.set_control_flow(ControlFlow::Poll);
+
+        let mut handler = AppHandler {
+            config,
+            factory,
+            running: None,
+            last_dt: Instant::now(),
+            error: None,
+        };
+        event_loop.run_app(&mut handler)?;
+        handler.error.map_or(Ok(()), Err)
+    }
+}
+
+/// Feeds winit's events to a RadApp, see Radium::start.
+struct AppHandler<A, F> {
+    config: EngineConfig,
+    factory: F,
+    /// Window and app, created on the first resumed event.
+    running: Option<(Rc<RefCell<RenderWindow>>, A)>,
+    last_dt: Instant,
+    error: Option<anyhow::Error>,
+}
+
+impl<A, F, Fut> AppHandler<A, F>
+where
+    A: RadApp,
+    F: Fn(Rc<RefCell<RenderWindow>>) -> Fut,
+    Fut: Future<Output = anyhow::Result<A>>,
+{
+    async fn init(
+        &self,
+        event_loop: &ActiveEventLoop,
+    ) -> anyhow::Result<(Rc<RefCell<RenderWindow>>, A)> {
+        let render_window = Rc::new(RefCell::new(
+            RenderWindow::with_config(event_loop, &self.config).await?,
+        ));
+        #[cfg(feature = "audio-output")]
+        if let Err(e) = render_window.borrow().engine().open_audio_output() {
+            log::warn!("AppHandler::init => no audio output: {}", e);
+        }
+        let app = (self.factory)(render_window.clone()).await?;
+        // Pipelines built while the app loaded are saved now, later ones on exit.
+        render_window.borrow().save_pipeline_cache();
+        Ok((render_window, app))
+    }
+}
+
+impl<A, F, Fut> ApplicationHandler for AppHandler<A, F>
+where
+    A: RadApp,
+    F: Fn(Rc<RefCell<RenderWindow>>) -> Fut,
+    Fut: Future<Output = anyhow::Result<A>>,
+{
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.running.is_some() {
+            return;
+        }
+        match pollster::block_on(self.init(event_loop)) {
+            Ok(running) => {
+                self.running = Some(running);
+                self.last_dt = Instant::now();
+            }
+            Err(e) => {
+                self.error = Some(e);
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        let Self {
+            running: Some((render_window, app)),
+            last_dt,
+            ..
+        } = self
+        else {
+            return;
+        };
+        if window_id != render_window.borrow().window_id() {
+            return;
+        }
+
+        #[cfg(feature = "accesskit")]
+        render_window.borrow_mut().process_access_event(&event);
+        if let WindowEvent::CursorMoved { position, .. } = event {
+            render_window
+                .borrow_mut()
+                .set_cursor_position(position.x as f32, position.y as f32);
+        }
+        let mouse_events = render_window.borrow_mut().mouse_mut().handle_event(&event);
+        for mouse_event in mouse_events.iter() {
+            app.process_mouse_event(mouse_event);
+        }
+        let engine = render_window.borrow().engine().clone();
+        let shortcut = engine.shortcuts().borrow_mut().handle_event(&event);
+        if let Some(hit) = shortcut {
+            if !hit.repeat {
+                if hit.action == TOGGLE_FULLSCREEN {
+                    render_window.borrow().toggle_fullscreen();
+                }
+                #[cfg(feature = "renderdoc")]
+                if hit.action == crate::eng::capture::CAPTURE_FRAME {
+                    render_window.borrow_mut().capture_mut().capture_next_frame();
+                }
+                #[cfg(feature = "accesskit")]
+                render_window
+                    .borrow_mut()
+                    .screen_reader_mut()
+                    .handle_shortcut(&hit.action);
+                app.process_shortcut(&hit.action);
+            }
+            return;
+        }
+
+        if let WindowEvent::RedrawRequested = event {
+            let now = Instant::now();
+            let dt = now - *last_dt;
+            *last_dt = now;
+
+            let watchdog = render_window.borrow().watchdog().clone();
+            watchdog.begin_frame();
+            render_window.borrow_mut().apply_pending_resize();
+
+            render_window.borrow_mut().update_globals(dt);
+            render_window.borrow_mut().update_camera(dt);
+            render_window.borrow_mut().update_transition(dt);
+            render_window.borrow_mut().update_accessibility(dt);
+            #[cfg(feature = "accesskit")]
+            render_window.borrow_mut().screen_reader_mut().begin_frame();
+            let engine = render_window.borrow().engine().clone();
+            if let Some(dt) = engine.begin_frame(dt) {
+                let _scope = watchdog.scope("update");
+                for _ in 0..engine.fixed_steps(dt) {
+                    app.fixed_update(engine.fixed_timestep());
                 }
-                Event::RedrawRequested(window_id)
-                    if window_id == render_window.borrow().window_id() =>
+                #[cfg(feature = "scripting")]
                 {
-                    let now = std::time::Instant::now();
-                    let dt = now - last_dt;
-                    last_dt = now;
-
-                    render_window.borrow_mut().update_camera(dt);
-                    app.frame_update(dt);
-
-                    let mut ctx = render_window.borrow().create_draw_context();
-                    ctx.begin_render_pass(RenderPassOp::CLEAR_BLACK);
-
-                    app.draw_frame(&mut ctx)
-                        .expect("Error occured while drawing frame");
-
-                    if let Err(error) = ctx.submit() {
-                        match error {
-                            wgpu::SurfaceError::Lost => render_window
-                                .borrow_mut()
-                                .resize(render_window.borrow().size()),
-                            wgpu::SurfaceError::OutOfMemory => *control_flow = ControlFlow::Exit,
-                            _ => eprintln!("{:?}", error),
-                        };
+                    let commands = engine.scripts().borrow_mut().update(dt.as_secs_f32());
+                    for command in commands.iter() {
+                        app.process_script_command(command);
                     }
                 }
-                Event::MainEventsCleared => {
-                    render_window.borrow().handle().request_redraw();
+                app.frame_update(dt);
+            }
+            watchdog.set_counter("pending tasks", engine.tasks().pending());
+
+            #[cfg(feature = "renderdoc")]
+            render_window.borrow_mut().capture_mut().begin_frame();
+            let mut ctx = render_window.borrow().create_draw_context();
+            let clear_color = render_window.borrow().clear_color();
+            ctx.begin_render_pass(RenderPassOp::Clear(clear_color));
+
+            {
+                let _scope = watchdog.scope("draw");
+                app.draw_frame(&mut ctx)
+                    .expect("Error occured while drawing frame");
+            }
+            // Formatting the summary every frame is wasted work, only long frames dump it.
+            if watchdog.is_armed() {
+                watchdog.record_commands(ctx.command_summary(4));
+            }
+            #[cfg(feature = "accesskit")]
+            render_window.borrow_mut().screen_reader_mut().end_frame();
+
+            let submitted = {
+                let _scope = watchdog.scope("submit");
+                ctx.submit()
+            };
+            watchdog.end_frame();
+            render_window.borrow_mut().mouse_mut().end_frame();
+            engine.shortcuts().borrow_mut().end_frame();
+            #[cfg(feature = "renderdoc")]
+            {
+                let mut render_window = render_window.borrow_mut();
+                render_window.capture_mut().end_frame();
+                // Outdated and mismatched attachments are routine while resizing.
+                if let Err(error @ (SurfaceError::Lost | SurfaceError::Validation)) = &submitted {
+                    let reason = format!("surface error {:?}", error);
+                    render_window.capture_mut().capture_on_error(&reason);
                 }
-                Event::DeviceEvent {
-                    event: DeviceEvent::MouseMotion { delta },
+            }
+            if let Err(error) = submitted {
+                match error {
+                    SurfaceError::Lost | SurfaceError::Outdated | SurfaceError::AttachmentSize => {
+                        let size = render_window.borrow().handle().inner_size();
+                        render_window.borrow_mut().request_resize(size)
+                    }
+                    SurfaceError::Timeout | SurfaceError::Occluded => {}
+                    _ => eprintln!("{:?}", error),
+                };
+            }
+            return;
+        }
+
+        match app.handle_window_events(&event) {
+            InputEventStatus::Processing => {}
+            InputEventStatus::Done => match event {
+                WindowEvent::CloseRequested
+                | WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            state: ElementState::Pressed,
+                            physical_key: PhysicalKey::Code(KeyCode::Escape),
+                            ..
+                        },
                     ..
-                } => match render_window.borrow().mouse_state() {
-                    MouseState::Pressed => app.process_mouse(delta.0, delta.1),
-                    _ => {}
-                },
+                } => {
+                    event_loop.exit();
+                }
+                WindowEvent::Resized(physical_size) => {
+                    render_window.borrow_mut().request_resize(physical_size);
+                }
+                WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                    let size = render_window.borrow().handle().inner_size();
+                    let mut render_window = render_window.borrow_mut();
+                    render_window.set_scale_factor(scale_factor);
+                    render_window.request_resize(size);
+                }
                 _ => {}
+            },
+        }
+    }
+
+    fn device_event(&mut self, _: &ActiveEventLoop, _: DeviceId, event: DeviceEvent) {
+        let Some((render_window, app)) = self.running.as_mut() else {
+            return;
+        };
+        if let DeviceEvent::MouseMotion { delta } = event {
+            if let MouseState::Pressed = render_window.borrow().mouse_state() {
+                app.process_mouse(delta.0, delta.1);
             }
-        });
+        }
+    }
+
+    fn about_to_wait(&mut self, _: &ActiveEventLoop) {
+        if let Some((render_window, _)) = self.running.as_ref() {
+            render_window.borrow().handle().request_redraw();
+        }
+    }
+
+    fn exiting(&mut self, _: &ActiveEventLoop) {
+        if let Some((render_window, _)) = self.running.as_ref() {
+            render_window.borrow().save_pipeline_cache();
+        }
     }
 }
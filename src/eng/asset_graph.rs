@@ -0,0 +1,342 @@
+use std::{
+    any::Any,
+    collections::{HashMap, HashSet},
+    fmt,
+    rc::Rc,
+};
+
+use anyhow::{bail, ensure};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AssetId(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssetType {
+    Model,
+    Mesh,
+    Material,
+    Texture,
+    Sampler,
+    Shader,
+    Other,
+}
+
+/// Bytes an asset keeps in CPU and GPU memory, estimated by whoever adds it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AssetSize {
+    pub cpu: u64,
+    pub gpu: u64,
+}
+
+impl AssetSize {
+    pub const fn cpu(bytes: u64) -> Self {
+        Self { cpu: bytes, gpu: 0 }
+    }
+
+    pub const fn gpu(bytes: u64) -> Self {
+        Self { cpu: 0, gpu: bytes }
+    }
+}
+
+struct AssetNode {
+    name: String,
+    ty: AssetType,
+    size: AssetSize,
+    /// Assets this one needs, e.g. a material's textures.
+    dependencies: Vec<AssetId>,
+    /// References from scenes and the app, see AssetGraph::acquire.
+    roots: u32,
+    value: Option<Rc<dyn Any>>,
+}
+
+/// An asset dropped by AssetGraph::unload_unused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreedAsset {
+    pub name: String,
+    pub ty: AssetType,
+    pub size: AssetSize,
+    /// Something outside the graph still holds the value, so its memory wasn't freed.
+    pub still_held: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct UnloadReport {
+    pub freed: Vec<FreedAsset>,
+}
+
+impl UnloadReport {
+    /// Memory actually released, assets still held elsewhere are left out.
+    pub fn freed_size(&self) -> AssetSize {
+        self.freed
+            .iter()
+            .filter(|a| !a.still_held)
+            .fold(AssetSize::default(), |total, a| AssetSize {
+                cpu: total.cpu + a.size.cpu,
+                gpu: total.gpu + a.size.gpu,
+            })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.freed.is_empty()
+    }
+}
+
+impl fmt::Display for UnloadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = self.freed_size();
+        writeln!(
+            f,
+            "unloaded {} assets, {} KiB cpu, {} KiB gpu",
+            self.freed.len(),
+            size.cpu / 1024,
+            size.gpu / 1024
+        )?;
+        for asset in self.freed.iter() {
+            write!(
+                f,
+                "  {:?} {} ({} KiB cpu, {} KiB gpu)",
+                asset.ty,
+                asset.name,
+                asset.size.cpu / 1024,
+                asset.size.gpu / 1024
+            )?;
+            if asset.still_held {
+                write!(f, " still held outside the graph")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Loaded assets by name with what each depends on (model -> materials -> textures ->
+/// samplers) and how many scenes or systems hold them. unload_unused drops every asset
+/// that can't be reached from a held one, freeing its CPU and GPU memory.
+#[derive(Default)]
+pub struct AssetGraph {
+    nodes: HashMap<AssetId, AssetNode>,
+    names: HashMap<String, AssetId>,
+    /// Assets each loaded scene holds, released together by unload_scene.
+    scenes: HashMap<String, Vec<AssetId>>,
+    next_id: u32,
+}
+
+impl AssetGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an asset owning `value`, or returns the asset already loaded under `name`.
+    pub fn insert<T: 'static>(
+        &mut self,
+        name: &str,
+        ty: AssetType,
+        size: AssetSize,
+        value: Rc<T>,
+    ) -> AssetId {
+        if let Some(&id) = self.names.get(name) {
+            return id;
+        }
+        let id = AssetId(self.next_id);
+        self.next_id += 1;
+        self.nodes.insert(
+            id,
+            AssetNode {
+                name: String::from(name),
+                ty,
+                size,
+                dependencies: Vec::new(),
+                roots: 0,
+                value: Some(value),
+            },
+        );
+        self.names.insert(String::from(name), id);
+        id
+    }
+
+    #[inline]
+    pub fn find(&self, name: &str) -> Option<AssetId> {
+        self.names.get(name).copied()
+    }
+
+    /// The value of `id`, None if it was unloaded or isn't a `T`.
+    pub fn get<T: 'static>(&self, id: AssetId) -> Option<Rc<T>> {
+        let value = self.nodes.get(&id)?.value.clone()?;
+        value.downcast().ok()
+    }
+
+    pub fn name(&self, id: AssetId) -> Option<&str> {
+        self.nodes.get(&id).map(|n| n.name.as_str())
+    }
+
+    pub fn contains(&self, id: AssetId) -> bool {
+        self.nodes.contains_key(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Records that `asset` needs `dependency` loaded, refusing edges that form a cycle.
+    pub fn add_dependency(&mut self, asset: AssetId, dependency: AssetId) -> anyhow::Result<()> {
+        ensure!(
+            self.contains(asset) && self.contains(dependency),
+            "AssetGraph::add_dependency => unknown asset"
+        );
+        if self.depends_on(dependency, asset) {
+            bail!(
+                "AssetGraph::add_dependency => {} -> {} would form a cycle",
+                self.nodes[&asset].name,
+                self.nodes[&dependency].name
+            );
+        }
+        let node = self.nodes.get_mut(&asset).unwrap();
+        if !node.dependencies.contains(&dependency) {
+            node.dependencies.push(dependency);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn dependencies(&self, id: AssetId) -> &[AssetId] {
+        self.nodes
+            .get(&id)
+            .map_or(&[], |n| n.dependencies.as_slice())
+    }
+
+    /// Assets that directly depend on `id`.
+    pub fn dependents(&self, id: AssetId) -> Vec<AssetId> {
+        let mut dependents = self
+            .nodes
+            .iter()
+            .filter(|(_, n)| n.dependencies.contains(&id))
+            .map(|(&i, _)| i)
+            .collect::<Vec<_>>();
+        dependents.sort();
+        dependents
+    }
+
+    /// True if `dependency` is reachable from `asset`, directly or through other assets.
+    pub fn depends_on(&self, asset: AssetId, dependency: AssetId) -> bool {
+        let mut stack = vec![asset];
+        let mut seen = HashSet::new();
+        while let Some(id) = stack.pop() {
+            if id == dependency {
+                return true;
+            }
+            if seen.insert(id) {
+                stack.extend_from_slice(self.dependencies(id));
+            }
+        }
+        false
+    }
+
+    /// Holds `id` and everything it depends on loaded until a matching release.
+    pub fn acquire(&mut self, id: AssetId) {
+        if let Some(node) = self.nodes.get_mut(&id) {
+            node.roots += 1;
+        }
+    }
+
+    pub fn release(&mut self, id: AssetId) {
+        if let Some(node) = self.nodes.get_mut(&id) {
+            node.roots = node.roots.saturating_sub(1);
+        }
+    }
+
+    /// Times `id` was acquired plus the assets depending on it.
+    pub fn ref_count(&self, id: AssetId) -> u32 {
+        let roots = self.nodes.get(&id).map_or(0, |n| n.roots);
+        roots + self.dependents(id).len() as u32
+    }
+
+    /// Replaces the assets scene `scene` holds, acquiring the new ones before releasing
+    /// the old so assets shared between the two stay loaded.
+    pub fn set_scene_assets(&mut self, scene: &str, assets: &[AssetId]) {
+        for &id in assets {
+            self.acquire(id);
+        }
+        if let Some(old) = self.scenes.insert(String::from(scene), assets.to_vec()) {
+            for id in old {
+                self.release(id);
+            }
+        }
+    }
+
+    /// Releases everything `scene` held, call unload_unused afterwards to free it.
+    pub fn unload_scene(&mut self, scene: &str) {
+        for id in self.scenes.remove(scene).unwrap_or_default() {
+            self.release(id);
+        }
+    }
+
+    /// Assets reachable from an acquired one.
+    pub fn live(&self) -> HashSet<AssetId> {
+        let mut stack = self
+            .nodes
+            .iter()
+            .filter(|(_, n)| n.roots > 0)
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        let mut live = HashSet::new();
+        while let Some(id) = stack.pop() {
+            if live.insert(id) {
+                stack.extend_from_slice(self.dependencies(id));
+            }
+        }
+        live
+    }
+
+    /// Drops every asset unreachable from an acquired one, dependents before their
+    /// dependencies, and reports what was freed.
+    pub fn unload_unused(&mut self) -> UnloadReport {
+        let live = self.live();
+        let mut dead = self
+            .nodes
+            .keys()
+            .filter(|id| !live.contains(id))
+            .copied()
+            .collect::<Vec<_>>();
+        // Newer assets are usually the dependents, which keeps the report top down.
+        dead.sort_by(|a, b| b.cmp(a));
+
+        let mut report = UnloadReport::default();
+        for id in dead {
+            let Some(mut node) = self.nodes.remove(&id) else {
+                continue;
+            };
+            self.names.remove(&node.name);
+            let still_held = node
+                .value
+                .take()
+                .is_some_and(|value| Rc::strong_count(&value) > 1);
+            report.freed.push(FreedAsset {
+                name: node.name,
+                ty: node.ty,
+                size: node.size,
+                still_held,
+            });
+        }
+        for assets in self.scenes.values_mut() {
+            assets.retain(|id| self.nodes.contains_key(id));
+        }
+        if !report.is_empty() {
+            log::info!("AssetGraph::unload_unused => {}", report);
+        }
+        report
+    }
+
+    /// Memory held by every loaded asset.
+    pub fn total_size(&self) -> AssetSize {
+        self.nodes
+            .values()
+            .fold(AssetSize::default(), |total, n| AssetSize {
+                cpu: total.cpu + n.size.cpu,
+                gpu: total.gpu + n.size.gpu,
+            })
+    }
+}
@@ -0,0 +1,299 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    gfx::{
+        model::Mesh,
+        wgpu::texture::{Texture, TextureType},
+    },
+    sys::{
+        baked::{BakedMaterial, BakedModel, BakedTexture},
+        fs::upload_baked_meshes,
+    },
+};
+
+use super::{render::DeviceSurface, tasks::Tasks};
+
+/// Which loads are decoded and uploaded first, Visible before Prefetch.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LoadPriority {
+    /// Needed on screen now.
+    Visible,
+    /// Likely needed soon, e.g. the next area.
+    #[default]
+    Prefetch,
+}
+
+/// Identifies a queued load, cancel it when the asset is no longer needed.
+#[derive(Debug, Clone)]
+pub struct LoadTicket {
+    id: u64,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl LoadTicket {
+    #[inline]
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Drops the load wherever it is. Queued loads never start, running decodes are
+    /// discarded once they finish and the upload is skipped.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+}
+
+type UploadFn<C> = Box<dyn FnOnce(&C)>;
+type PollFn<C> = Box<dyn FnMut() -> DecodeState<C>>;
+type StartFn<C> = Box<dyn FnOnce(&Tasks) -> PollFn<C>>;
+
+enum DecodeState<C> {
+    Running,
+    /// The upload for the decoded value, None if the load was cancelled or decode panicked.
+    Finished(Option<UploadFn<C>>),
+}
+
+struct Queued<C> {
+    priority: LoadPriority,
+    ticket: LoadTicket,
+    start: StartFn<C>,
+}
+
+struct Decoding<C> {
+    priority: LoadPriority,
+    ticket: LoadTicket,
+    poll: PollFn<C>,
+}
+
+struct Decoded<C> {
+    priority: LoadPriority,
+    ticket: LoadTicket,
+    upload: UploadFn<C>,
+}
+
+/// Counts after an AssetLoader::update.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct AssetLoaderStats {
+    pub queued: usize,
+    pub decoding: usize,
+    /// Decoded and waiting for upload budget.
+    pub decoded: usize,
+    pub uploaded: usize,
+    /// Loads dropped this update because they were cancelled.
+    pub cancelled: usize,
+}
+
+/// Decodes assets on the Tasks blocking pool and uploads them on the main thread. Decodes
+/// start in priority order with at most `max_decoding` running at once, and uploads are
+/// spread over frames by a time budget so a burst of finished loads doesn't hitch.
+/// `C` is whatever the uploads need, usually DeviceSurface.
+pub struct AssetLoader<C> {
+    next_id: u64,
+    max_decoding: usize,
+    upload_budget: Duration,
+    queued: Vec<Queued<C>>,
+    decoding: Vec<Decoding<C>>,
+    decoded: Vec<Decoded<C>>,
+}
+
+impl<C> Default for AssetLoader<C> {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_DECODING)
+    }
+}
+
+impl<C> AssetLoader<C> {
+    pub const DEFAULT_MAX_DECODING: usize = 4;
+    pub const DEFAULT_UPLOAD_BUDGET: Duration = Duration::from_millis(4);
+
+    pub fn new(max_decoding: usize) -> Self {
+        Self {
+            next_id: 0,
+            max_decoding: max_decoding.max(1),
+            upload_budget: Self::DEFAULT_UPLOAD_BUDGET,
+            queued: Vec::new(),
+            decoding: Vec::new(),
+            decoded: Vec::new(),
+        }
+    }
+
+    /// Time AssetLoader::update may spend uploading, at least one upload always runs.
+    pub fn with_upload_budget(mut self, budget: Duration) -> Self {
+        self.upload_budget = budget;
+        self
+    }
+
+    /// Queues a load. `decode` runs on a worker thread, `upload` runs with its result on the
+    /// main thread during AssetLoader::update unless the load was cancelled.
+    pub fn load<T, D, U>(&mut self, priority: LoadPriority, decode: D, upload: U) -> LoadTicket
+    where
+        T: Send + 'static,
+        D: FnOnce() -> T + Send + 'static,
+        U: FnOnce(&C, T) + 'static,
+        C: 'static,
+    {
+        let ticket = LoadTicket {
+            id: self.next_id,
+            cancelled: Arc::new(AtomicBool::new(false)),
+        };
+        self.next_id += 1;
+
+        let cancelled = ticket.cancelled.clone();
+        let start: StartFn<C> = Box::new(move |tasks: &Tasks| {
+            // Cancelled while waiting for a worker, skip the decode.
+            let mut handle =
+                tasks.spawn_blocking(move || (!cancelled.load(Ordering::Acquire)).then(decode));
+            // The upload isn't Send, it stays here and is paired with the value once back.
+            let mut upload = Some(upload);
+            Box::new(move || match handle.try_take() {
+                Some(value) => {
+                    DecodeState::Finished(value.zip(upload.take()).map(|(value, upload)| {
+                        Box::new(move |ctx: &C| upload(ctx, value)) as UploadFn<C>
+                    }))
+                }
+                None if handle.is_finished() => DecodeState::Finished(None),
+                None => DecodeState::Running,
+            })
+        });
+        self.queued.push(Queued {
+            priority,
+            ticket: ticket.clone(),
+            start,
+        });
+        ticket
+    }
+
+    /// Moves a load to another priority, e.g. a prefetched asset that just came into view.
+    pub fn set_priority(&mut self, ticket: &LoadTicket, priority: LoadPriority) {
+        let id = ticket.id;
+        self.queued
+            .iter_mut()
+            .filter(|l| l.ticket.id == id)
+            .for_each(|l| l.priority = priority);
+        self.decoding
+            .iter_mut()
+            .filter(|l| l.ticket.id == id)
+            .for_each(|l| l.priority = priority);
+        self.decoded
+            .iter_mut()
+            .filter(|l| l.ticket.id == id)
+            .for_each(|l| l.priority = priority);
+    }
+
+    /// Loads that haven't been uploaded or cancelled yet.
+    pub fn len(&self) -> usize {
+        self.queued.len() + self.decoding.len() + self.decoded.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Starts decodes, collects finished ones and uploads within the budget, call once per frame.
+    pub fn update(&mut self, tasks: &Tasks, ctx: &C) -> AssetLoaderStats {
+        let mut stats = AssetLoaderStats::default();
+        let before = self.len();
+        self.queued.retain(|l| !l.ticket.is_cancelled());
+
+        // Highest priority first, oldest first within a priority.
+        self.queued.sort_by_key(|l| (l.priority, l.ticket.id));
+        let free = self.max_decoding.saturating_sub(self.decoding.len());
+        for load in self.queued.drain(..free.min(self.queued.len())) {
+            self.decoding.push(Decoding {
+                priority: load.priority,
+                ticket: load.ticket,
+                poll: (load.start)(tasks),
+            });
+        }
+
+        let mut i = 0;
+        while i < self.decoding.len() {
+            match (self.decoding[i].poll)() {
+                DecodeState::Running => i += 1,
+                DecodeState::Finished(upload) => {
+                    let load = self.decoding.swap_remove(i);
+                    if let Some(upload) = upload.filter(|_| !load.ticket.is_cancelled()) {
+                        self.decoded.push(Decoded {
+                            priority: load.priority,
+                            ticket: load.ticket,
+                            upload,
+                        });
+                    }
+                }
+            }
+        }
+
+        self.decoded.retain(|l| !l.ticket.is_cancelled());
+        self.decoded.sort_by_key(|l| (l.priority, l.ticket.id));
+        let start = Instant::now();
+        while !self.decoded.is_empty() {
+            let load = self.decoded.remove(0);
+            (load.upload)(ctx);
+            stats.uploaded += 1;
+            if start.elapsed() >= self.upload_budget {
+                break;
+            }
+        }
+
+        stats.queued = self.queued.len();
+        stats.decoding = self.decoding.len();
+        stats.decoded = self.decoded.len();
+        stats.cancelled = before - self.len() - stats.uploaded;
+        stats
+    }
+}
+
+impl AssetLoader<DeviceSurface> {
+    /// Decodes an encoded image and generates its mips on a worker, then uploads it.
+    pub fn load_texture(
+        &mut self,
+        priority: LoadPriority,
+        bytes: Vec<u8>,
+        ty: TextureType,
+        label: impl Into<String>,
+        done: impl FnOnce(anyhow::Result<Texture>) + 'static,
+    ) -> LoadTicket {
+        let label = label.into();
+        self.load(
+            priority,
+            move || image::load_from_memory(&bytes).map(|img| BakedTexture::from_image(&img)),
+            move |ds: &DeviceSurface, baked| {
+                done(baked.map_err(Into::into).map(|baked| {
+                    Texture::from_baked(&ds.device, &ds.queue, &baked, ty, Some(&label))
+                }))
+            },
+        )
+    }
+
+    /// Parses a baked model (see BakedModel::write) on a worker, then uploads its meshes.
+    /// Its materials are returned by name so their textures can be loaded separately.
+    pub fn load_baked_meshes(
+        &mut self,
+        priority: LoadPriority,
+        bytes: Vec<u8>,
+        done: impl FnOnce(anyhow::Result<(Vec<Mesh>, Vec<BakedMaterial>)>) + 'static,
+    ) -> LoadTicket {
+        self.load(
+            priority,
+            move || BakedModel::from_bytes(&bytes),
+            move |ds: &DeviceSurface, model| {
+                done(model.map(|model| {
+                    let meshes = upload_baked_meshes(&ds.device, &model.view());
+                    (meshes, model.materials)
+                }))
+            },
+        )
+    }
+}
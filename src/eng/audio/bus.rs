@@ -0,0 +1,274 @@
+/// Mixer bus a voice is routed through. Music and Sfx feed into Master.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Bus {
+    Master,
+    Music,
+    #[default]
+    Sfx,
+}
+
+impl Bus {
+    pub const ALL: [Bus; 3] = [Bus::Master, Bus::Music, Bus::Sfx];
+
+    #[inline]
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Bus::Master => "master",
+            Bus::Music => "music",
+            Bus::Sfx => "sfx",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.name() == name)
+    }
+}
+
+/// Runtime adjustable settings of a bus.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BusSettings {
+    pub gain: f32,
+    pub muted: bool,
+    /// Cutoff in Hz of a one pole low-pass filter, None bypasses it.
+    pub low_pass: Option<f32>,
+    /// Amount of the bus sent to the shared reverb, 0 disables the send.
+    pub reverb_send: f32,
+}
+
+impl Default for BusSettings {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            muted: false,
+            low_pass: None,
+            reverb_send: 0.0,
+        }
+    }
+}
+
+/// Settings of every bus, stored in EngineConfig so they persist with the config file.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct AudioSettings {
+    pub buses: [BusSettings; 3],
+}
+
+impl AudioSettings {
+    #[inline]
+    pub fn bus(&self, bus: Bus) -> &BusSettings {
+        &self.buses[bus.index()]
+    }
+
+    #[inline]
+    pub fn bus_mut(&mut self, bus: Bus) -> &mut BusSettings {
+        &mut self.buses[bus.index()]
+    }
+
+    /// Applies an `audio.<bus>.<field>` config entry, returns false if the key isn't an audio setting.
+    pub fn set(&mut self, key: &str, value: &str) -> anyhow::Result<bool> {
+        let mut parts = key.split('.');
+        let (Some("audio"), Some(bus), Some(field), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Ok(false);
+        };
+        let Some(bus) = Bus::from_name(bus) else {
+            anyhow::bail!("AudioSettings::set => unknown bus {}", bus);
+        };
+        let settings = self.bus_mut(bus);
+        match field {
+            "gain" => settings.gain = value.parse()?,
+            "muted" => settings.muted = value.parse()?,
+            "low_pass" => {
+                settings.low_pass = match value {
+                    "none" => None,
+                    hz => Some(hz.parse()?),
+                }
+            }
+            "reverb_send" => settings.reverb_send = value.parse()?,
+            _ => anyhow::bail!("AudioSettings::set => unknown bus setting {}", field),
+        }
+        Ok(true)
+    }
+
+    /// `key = value` lines read back by AudioSettings::set.
+    pub fn entries(&self) -> Vec<(String, String)> {
+        Bus::ALL
+            .into_iter()
+            .flat_map(|bus| {
+                let s = self.bus(bus);
+                let key = |field: &str| format!("audio.{}.{}", bus.name(), field);
+                [
+                    (key("gain"), s.gain.to_string()),
+                    (key("muted"), s.muted.to_string()),
+                    (
+                        key("low_pass"),
+                        s.low_pass.map_or("none".to_string(), |hz| hz.to_string()),
+                    ),
+                    (key("reverb_send"), s.reverb_send.to_string()),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// One pole low-pass filter per channel.
+#[derive(Debug, Default, Copy, Clone)]
+struct LowPass {
+    state: [f32; 2],
+}
+
+impl LowPass {
+    fn process(&mut self, buffer: &mut [f32], cutoff: f32, sample_rate: u32) {
+        let a = 1.0 - (-std::f32::consts::TAU * cutoff / sample_rate as f32).exp();
+        for frame in buffer.chunks_exact_mut(2) {
+            for (sample, state) in frame.iter_mut().zip(self.state.iter_mut()) {
+                *state += a * (*sample - *state);
+                *sample = *state;
+            }
+        }
+    }
+}
+
+/// State and scratch buffer of a bus inside the Mixer. Its effects run wherever Mixer::mix
+/// is called, on the device callback thread with the audio-output feature.
+#[derive(Debug, Default, Clone)]
+pub(super) struct BusState {
+    pub settings: BusSettings,
+    pub buffer: Vec<f32>,
+    low_pass: LowPass,
+}
+
+impl BusState {
+    pub fn clear(&mut self, len: usize) {
+        self.buffer.clear();
+        self.buffer.resize(len, 0.0);
+    }
+
+    /// Runs the bus effects, adds the reverb send to `reverb` and the bus output to `out`.
+    pub fn finish(&mut self, sample_rate: u32, reverb: Option<&mut [f32]>, out: &mut [f32]) {
+        if let Some(cutoff) = self.settings.low_pass {
+            self.low_pass.process(&mut self.buffer, cutoff, sample_rate);
+        }
+        let gain = if self.settings.muted {
+            0.0
+        } else {
+            self.settings.gain
+        };
+        for (sample, o) in self.buffer.iter().zip(out.iter_mut()) {
+            *o += sample * gain;
+        }
+        let send = self.settings.reverb_send * gain;
+        if let Some(reverb) = reverb.filter(|_| send > 0.0) {
+            for (sample, r) in self.buffer.iter().zip(reverb.iter_mut()) {
+                *r += sample * send;
+            }
+        }
+    }
+}
+
+/// Feedback comb filter, the building block of the reverb.
+#[derive(Debug, Clone)]
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    feedback: f32,
+    damp: f32,
+    filter: f32,
+}
+
+impl Comb {
+    fn new(len: usize) -> Self {
+        Self {
+            buffer: vec![0.0; len.max(1)],
+            index: 0,
+            feedback: 0.8,
+            damp: 0.2,
+            filter: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let out = self.buffer[self.index];
+        self.filter = out * (1.0 - self.damp) + self.filter * self.damp;
+        self.buffer[self.index] = input + self.filter * self.feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        out
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AllPass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl AllPass {
+    fn new(len: usize) -> Self {
+        Self {
+            buffer: vec![0.0; len.max(1)],
+            index: 0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.index];
+        let out = delayed - input;
+        self.buffer[self.index] = input + delayed * 0.5;
+        self.index = (self.index + 1) % self.buffer.len();
+        out
+    }
+}
+
+/// Small Schroeder reverb shared by every bus send, wet signal only.
+#[derive(Debug, Clone)]
+pub(super) struct Reverb {
+    combs: [Vec<Comb>; 2],
+    allpasses: [Vec<AllPass>; 2],
+}
+
+impl Reverb {
+    /// Freeverb's delay lengths at 44.1kHz, the right channel is slightly detuned.
+    const COMBS: [usize; 4] = [1116, 1188, 1277, 1356];
+    const ALLPASSES: [usize; 2] = [556, 441];
+    const STEREO_SPREAD: usize = 23;
+
+    pub fn new(sample_rate: u32) -> Self {
+        let scale = |len: usize, spread: usize| (len + spread) * sample_rate as usize / 44_100;
+        let channel = |spread: usize| {
+            (
+                Self::COMBS.map(|l| Comb::new(scale(l, spread))).to_vec(),
+                Self::ALLPASSES
+                    .map(|l| AllPass::new(scale(l, spread)))
+                    .to_vec(),
+            )
+        };
+        let (left_combs, left_allpasses) = channel(0);
+        let (right_combs, right_allpasses) = channel(Self::STEREO_SPREAD);
+        Self {
+            combs: [left_combs, right_combs],
+            allpasses: [left_allpasses, right_allpasses],
+        }
+    }
+
+    /// Replaces the dry send in `buffer` with the reverb output.
+    pub fn process(&mut self, buffer: &mut [f32]) {
+        let scale = 1.0 / Self::COMBS.len() as f32;
+        for frame in buffer.chunks_exact_mut(2) {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let input = *sample * scale;
+                let mut out: f32 = self.combs[channel]
+                    .iter_mut()
+                    .map(|c| c.process(input))
+                    .sum();
+                for allpass in &mut self.allpasses[channel] {
+                    out = allpass.process(out);
+                }
+                *sample = out;
+            }
+        }
+    }
+}
@@ -0,0 +1,416 @@
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use cgmath::{Point3, Vector3};
+
+use self::{
+    bus::{AudioSettings, Bus, BusSettings, BusState, Reverb},
+    spatial::{Doppler, Listener, Spatial},
+    stream::MusicPlayer,
+};
+
+pub mod bus;
+#[cfg(feature = "audio-output")]
+pub mod output;
+pub mod spatial;
+pub mod stream;
+
+/// Decoded PCM samples, interleaved when stereo.
+#[derive(Debug, Clone)]
+pub struct Sound {
+    samples: Arc<[f32]>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl Sound {
+    pub fn new(samples: Vec<f32>, channels: u16, sample_rate: u32) -> anyhow::Result<Self> {
+        if !(1..=2).contains(&channels) {
+            anyhow::bail!(
+                "Sound::new => only mono and stereo are supported, got {} channels",
+                channels
+            );
+        }
+        if sample_rate == 0 {
+            anyhow::bail!("Sound::new => sample rate must be non zero");
+        }
+        Ok(Self {
+            samples: samples.into(),
+            channels,
+            sample_rate,
+        })
+    }
+
+    #[inline]
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    #[inline]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    #[inline]
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frames() as f64 / self.sample_rate as f64)
+    }
+
+    /// Left and right sample of `frame`, mono is duplicated to both sides.
+    fn frame(&self, frame: usize) -> (f32, f32) {
+        match self.channels {
+            1 => {
+                let s = self.samples[frame];
+                (s, s)
+            }
+            _ => (self.samples[frame * 2], self.samples[frame * 2 + 1]),
+        }
+    }
+
+    /// Linearly interpolated frame at a fractional position.
+    fn sample(&self, position: f64, looping: bool) -> (f32, f32) {
+        let frames = self.frames();
+        let index = position as usize;
+        let next = if index + 1 < frames {
+            index + 1
+        } else if looping {
+            0
+        } else {
+            index
+        };
+        let t = (position - index as f64) as f32;
+        let (l0, r0) = self.frame(index);
+        let (l1, r1) = self.frame(next);
+        (l0 + (l1 - l0) * t, r0 + (r1 - r0) * t)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct VoiceId(u64);
+
+/// How a sound is played, see Mixer::play.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VoiceParams {
+    pub gain: f32,
+    /// Playback rate, 2.0 plays an octave up.
+    pub pitch: f32,
+    pub looping: bool,
+    pub spatial: Spatial,
+    pub bus: Bus,
+}
+
+impl Default for VoiceParams {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            pitch: 1.0,
+            looping: false,
+            spatial: Spatial::default(),
+            bus: Bus::default(),
+        }
+    }
+}
+
+impl VoiceParams {
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    pub fn with_gain(mut self, gain: f32) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    pub fn with_spatial(mut self, spatial: Spatial) -> Self {
+        self.spatial = spatial;
+        self
+    }
+
+    pub fn on_bus(mut self, bus: Bus) -> Self {
+        self.bus = bus;
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Voice {
+    id: VoiceId,
+    sound: Sound,
+    /// Position in source frames.
+    cursor: f64,
+    params: VoiceParams,
+}
+
+/// Software mixer producing interleaved stereo f32 samples. It does not open an output
+/// device itself: with the audio-output feature output::AudioOutput pulls from it with
+/// Mixer::mix on the device's callback, otherwise the embedder has to call Mixer::mix
+/// from its own audio callback.
+#[derive(Debug)]
+pub struct Mixer {
+    sample_rate: u32,
+    voices: Vec<Voice>,
+    next_id: u64,
+    listener: Listener,
+    follow_camera: bool,
+    /// Pitch shift from relative motion, disabled by default.
+    pub doppler: Option<Doppler>,
+    buses: [BusState; 3],
+    reverb: Reverb,
+    reverb_buffer: Vec<f32>,
+    music: MusicPlayer,
+}
+
+impl Mixer {
+    pub const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            voices: Vec::new(),
+            next_id: 0,
+            listener: Listener::default(),
+            follow_camera: true,
+            doppler: None,
+            buses: Default::default(),
+            reverb: Reverb::new(sample_rate),
+            reverb_buffer: Vec::new(),
+            music: MusicPlayer::new(sample_rate),
+        }
+    }
+
+    #[inline]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Changes the output rate, e.g. to the one the output device opened with. Voices and
+    /// music are resampled to it, the reverb starts over with an empty tail.
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        if sample_rate == self.sample_rate || sample_rate == 0 {
+            return;
+        }
+        self.sample_rate = sample_rate;
+        self.reverb = Reverb::new(sample_rate);
+        self.music.set_sample_rate(sample_rate);
+    }
+
+    pub fn play(&mut self, sound: &Sound, params: VoiceParams) -> VoiceId {
+        let id = VoiceId(self.next_id);
+        self.next_id += 1;
+        self.voices.push(Voice {
+            id,
+            sound: sound.clone(),
+            cursor: 0.0,
+            params,
+        });
+        id
+    }
+
+    pub fn stop(&mut self, id: VoiceId) {
+        self.voices.retain(|v| v.id != id);
+    }
+
+    pub fn is_playing(&self, id: VoiceId) -> bool {
+        self.voices.iter().any(|v| v.id == id)
+    }
+
+    pub fn params_mut(&mut self, id: VoiceId) -> Option<&mut VoiceParams> {
+        self.voices
+            .iter_mut()
+            .find(|v| v.id == id)
+            .map(|v| &mut v.params)
+    }
+
+    /// Moves a positional voice, flat voices are left untouched.
+    pub fn set_emitter(&mut self, id: VoiceId, position: Point3<f32>, velocity: Vector3<f32>) {
+        if let Some(Spatial::Positional {
+            position: p,
+            velocity: v,
+            ..
+        }) = self.params_mut(id).map(|p| &mut p.spatial)
+        {
+            *p = position;
+            *v = velocity;
+        }
+    }
+
+    #[inline]
+    pub fn listener(&self) -> &Listener {
+        &self.listener
+    }
+
+    /// Places the listener manually, it stops following the camera until
+    /// Mixer::set_follow_camera turns it back on.
+    pub fn set_listener(&mut self, listener: Listener) {
+        self.listener = listener;
+        self.follow_camera = false;
+    }
+
+    pub fn set_follow_camera(&mut self, follow: bool) {
+        self.follow_camera = follow;
+    }
+
+    #[inline]
+    pub fn follows_camera(&self) -> bool {
+        self.follow_camera
+    }
+
+    #[inline]
+    pub fn bus(&self, bus: Bus) -> &BusSettings {
+        &self.buses[bus.index()].settings
+    }
+
+    pub fn set_bus(&mut self, bus: Bus, settings: BusSettings) {
+        self.buses[bus.index()].settings = settings;
+    }
+
+    pub fn set_bus_gain(&mut self, bus: Bus, gain: f32) {
+        self.buses[bus.index()].settings.gain = gain;
+    }
+
+    pub fn set_bus_muted(&mut self, bus: Bus, muted: bool) {
+        self.buses[bus.index()].settings.muted = muted;
+    }
+
+    /// Current bus settings, e.g. to store them in EngineConfig::audio.
+    pub fn settings(&self) -> AudioSettings {
+        AudioSettings {
+            buses: self.buses.each_ref().map(|b| b.settings),
+        }
+    }
+
+    pub fn apply_settings(&mut self, settings: &AudioSettings) {
+        for bus in Bus::ALL {
+            self.set_bus(bus, *settings.bus(bus));
+        }
+    }
+
+    /// Streamed music, mixed into the Music bus.
+    #[inline]
+    pub fn music(&mut self) -> &mut MusicPlayer {
+        &mut self.music
+    }
+
+    /// Preallocates the bus and reverb scratch buffers for `frames` stereo frames, so the
+    /// output callback running the bus effects doesn't allocate for buffers up to that size.
+    pub fn reserve(&mut self, frames: usize) {
+        let len = frames * 2;
+        for buffer in self
+            .buses
+            .iter_mut()
+            .map(|b| &mut b.buffer)
+            .chain([&mut self.reverb_buffer])
+        {
+            buffer.reserve(len.saturating_sub(buffer.len()));
+        }
+    }
+
+    /// Overwrites `out` with the next `out.len() / 2` interleaved stereo frames.
+    /// Voices that reach the end of a non looping sound are removed.
+    pub fn mix(&mut self, out: &mut [f32]) {
+        out.fill(0.0);
+        for bus in &mut self.buses {
+            bus.clear(out.len());
+        }
+        let buses = &mut self.buses;
+        let listener = self.listener;
+        let doppler = self.doppler;
+        let out_rate = self.sample_rate as f64;
+
+        self.voices.retain_mut(|voice| {
+            let (gain_l, gain_r) = listener.gains(&voice.params.spatial);
+            let mut pitch = voice.params.pitch;
+            if let (
+                Some(doppler),
+                Spatial::Positional {
+                    position, velocity, ..
+                },
+            ) = (doppler, voice.params.spatial)
+            {
+                pitch *= doppler.pitch(&listener, position, velocity);
+            }
+            let step = pitch.max(0.0) as f64 * voice.sound.sample_rate as f64 / out_rate;
+            let frames = voice.sound.frames() as f64;
+            let positional = matches!(voice.params.spatial, Spatial::Positional { .. });
+
+            let bus = &mut buses[voice.params.bus.index()].buffer;
+            for frame in bus.chunks_exact_mut(2) {
+                if voice.cursor >= frames {
+                    if !voice.params.looping || frames == 0.0 {
+                        return false;
+                    }
+                    voice.cursor %= frames;
+                }
+                let (mut l, mut r) = voice.sound.sample(voice.cursor, voice.params.looping);
+                if positional {
+                    // Positional voices are panned as mono.
+                    let mono = (l + r) * 0.5;
+                    (l, r) = (mono, mono);
+                }
+                frame[0] += l * gain_l * voice.params.gain;
+                frame[1] += r * gain_r * voice.params.gain;
+                voice.cursor += step;
+            }
+            true
+        });
+
+        self.music.mix(&mut self.buses[Bus::Music.index()].buffer);
+
+        self.reverb_buffer.clear();
+        self.reverb_buffer.resize(out.len(), 0.0);
+        let [master, music, sfx] = &mut self.buses;
+        for bus in [music, sfx] {
+            bus.finish(
+                self.sample_rate,
+                Some(&mut self.reverb_buffer),
+                &mut master.buffer,
+            );
+        }
+        // Always run so the tail keeps ringing after the sends go quiet.
+        self.reverb.process(&mut self.reverb_buffer);
+        for (sample, wet) in master.buffer.iter_mut().zip(&self.reverb_buffer) {
+            *sample += wet;
+        }
+        master.finish(self.sample_rate, None, out);
+    }
+}
+
+/// Mixer shared between the main thread and the audio callback, see EngineContext::audio.
+#[derive(Debug, Clone)]
+pub struct Audio(Arc<Mutex<Mixer>>);
+
+impl Default for Audio {
+    fn default() -> Self {
+        Self::new(Mixer::DEFAULT_SAMPLE_RATE)
+    }
+}
+
+impl Audio {
+    pub fn new(sample_rate: u32) -> Self {
+        Self(Arc::new(Mutex::new(Mixer::new(sample_rate))))
+    }
+
+    /// Locks the mixer, recovering it if a panicking thread poisoned the lock.
+    pub fn lock(&self) -> MutexGuard<'_, Mixer> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn play(&self, sound: &Sound, params: VoiceParams) -> VoiceId {
+        self.lock().play(sound, params)
+    }
+
+    /// Called by the main loop with the camera every frame, moves the listener if it follows the camera.
+    pub fn follow_camera(&self, position: Point3<f32>, forward: Vector3<f32>, dt: Duration) {
+        let mut mixer = self.lock();
+        if mixer.follow_camera {
+            mixer.listener.follow(position, forward, dt);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    FromSample, SampleFormat, SizedSample,
+};
+
+use super::Audio;
+
+/// Frames the mixer reserves for when the device doesn't report its largest buffer.
+const DEFAULT_BUFFER_FRAMES: usize = 4096;
+
+/// Sample formats tried in order when the device offers the mixer's rate in several.
+const FORMATS: [SampleFormat; 4] = [
+    SampleFormat::F32,
+    SampleFormat::I16,
+    SampleFormat::U16,
+    SampleFormat::I32,
+];
+
+/// Stream on the default output device whose callback locks Audio and pulls every buffer
+/// from Mixer::mix. Sound stops when it is dropped, see EngineContext::open_audio_output.
+pub struct AudioOutput {
+    stream: cpal::Stream,
+    device: String,
+    config: cpal::StreamConfig,
+}
+
+impl std::fmt::Debug for AudioOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AudioOutput")
+            .field("device", &self.device)
+            .field("channels", &self.config.channels)
+            .field("sample_rate", &self.config.sample_rate.0)
+            .finish()
+    }
+}
+
+impl AudioOutput {
+    /// Opens the default output device at the mixer's sample rate if it supports it,
+    /// otherwise at the device's own rate, which the mixer is switched to.
+    pub fn open(audio: &Audio) -> anyhow::Result<Self> {
+        let host = cpal::default_host();
+        let device = host
+            .default_output_device()
+            .ok_or_else(|| anyhow::anyhow!("AudioOutput::open => no output device"))?;
+        let name = device.name().unwrap_or_else(|_| "unknown".to_string());
+
+        let rate = cpal::SampleRate(audio.lock().sample_rate());
+        let supported = device
+            .supported_output_configs()?
+            .filter(|c| c.min_sample_rate() <= rate && rate <= c.max_sample_rate())
+            .filter_map(|c| {
+                let format = FORMATS.iter().position(|&f| f == c.sample_format())?;
+                // Stereo first, then the fewest channels.
+                let channels = (c.channels() != 2, c.channels());
+                Some(((format, channels), c.with_sample_rate(rate)))
+            })
+            .min_by_key(|(key, _)| *key)
+            .map(|(_, c)| c);
+        let supported = match supported {
+            Some(c) => c,
+            None => device.default_output_config()?,
+        };
+        let format = supported.sample_format();
+        let max_frames = match supported.buffer_size() {
+            cpal::SupportedBufferSize::Range { max, .. } => {
+                (*max as usize).min(DEFAULT_BUFFER_FRAMES * 4)
+            }
+            cpal::SupportedBufferSize::Unknown => DEFAULT_BUFFER_FRAMES,
+        };
+        let mut config = supported.config();
+        config.buffer_size = cpal::BufferSize::Default;
+        {
+            let mut mixer = audio.lock();
+            mixer.set_sample_rate(config.sample_rate.0);
+            mixer.reserve(max_frames);
+        }
+
+        let stream = match format {
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, max_frames, audio.clone()),
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, max_frames, audio.clone()),
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, max_frames, audio.clone()),
+            SampleFormat::I32 => build_stream::<i32>(&device, &config, max_frames, audio.clone()),
+            format => anyhow::bail!(
+                "AudioOutput::open => '{}' has unsupported sample format {}",
+                name,
+                format
+            ),
+        }?;
+        stream.play()?;
+        log::info!(
+            "AudioOutput::open => '{}', {} channels at {}Hz, {}",
+            name,
+            config.channels,
+            config.sample_rate.0,
+            format
+        );
+
+        Ok(Self {
+            stream,
+            device: name,
+            config,
+        })
+    }
+
+    #[inline]
+    pub fn device_name(&self) -> &str {
+        &self.device
+    }
+
+    #[inline]
+    pub fn config(&self) -> &cpal::StreamConfig {
+        &self.config
+    }
+
+    /// Stops pulling from the mixer without closing the device.
+    pub fn pause(&self) -> anyhow::Result<()> {
+        Ok(self.stream.pause()?)
+    }
+
+    pub fn resume(&self) -> anyhow::Result<()> {
+        Ok(self.stream.play()?)
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    max_frames: usize,
+    audio: Audio,
+) -> anyhow::Result<cpal::Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels.max(1) as usize;
+    // Mixer output in stereo. The whole bus chain, low-pass and reverb included, runs in
+    // Mixer::mix on this callback's thread, nothing allocates unless the device asks for
+    // more than `max_frames`.
+    let mut stereo = Vec::with_capacity(max_frames * 2);
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _| {
+            let frames = data.len() / channels;
+            stereo.resize(frames * 2, 0.0);
+            audio.lock().mix(&mut stereo);
+            for (out, lr) in data.chunks_exact_mut(channels).zip(stereo.chunks_exact(2)) {
+                if channels == 1 {
+                    out[0] = T::from_sample((lr[0] + lr[1]) * 0.5);
+                    continue;
+                }
+                // Left and right go to the first two channels, the rest stay silent.
+                out[0] = T::from_sample(lr[0]);
+                out[1] = T::from_sample(lr[1]);
+                out[2..].fill(T::EQUILIBRIUM);
+            }
+        },
+        |e| log::error!("AudioOutput => stream error: {}", e),
+        None,
+    )?;
+    Ok(stream)
+}
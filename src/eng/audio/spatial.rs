@@ -0,0 +1,178 @@
+use std::time::Duration;
+
+use cgmath::{InnerSpace, Point3, Vector3, Zero};
+
+/// How a voice is placed in the stereo field.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Spatial {
+    /// Fixed balance, -1 is full left, 1 full right.
+    Flat { pan: f32 },
+    /// Panned and attenuated relative to the Listener.
+    Positional {
+        position: Point3<f32>,
+        /// Units per second, only used for doppler.
+        velocity: Vector3<f32>,
+        attenuation: Attenuation,
+    },
+}
+
+impl Default for Spatial {
+    fn default() -> Self {
+        Spatial::Flat { pan: 0.0 }
+    }
+}
+
+impl Spatial {
+    pub fn at(position: Point3<f32>) -> Self {
+        Spatial::Positional {
+            position,
+            velocity: Vector3::zero(),
+            attenuation: Attenuation::default(),
+        }
+    }
+}
+
+/// Inverse distance rolloff, full volume inside min_distance and silent past max_distance.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Attenuation {
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub rolloff: f32,
+}
+
+impl Default for Attenuation {
+    fn default() -> Self {
+        Self {
+            min_distance: 1.0,
+            max_distance: 100.0,
+            rolloff: 1.0,
+        }
+    }
+}
+
+impl Attenuation {
+    pub fn gain(&self, distance: f32) -> f32 {
+        if distance >= self.max_distance {
+            return 0.0;
+        }
+        let min = self.min_distance.max(f32::EPSILON);
+        min / (min + self.rolloff * (distance.max(min) - min))
+    }
+}
+
+/// The ear positional voices are heard from. Follows the active camera unless
+/// Mixer::set_listener was called, see Audio::follow_camera.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Listener {
+    pub position: Point3<f32>,
+    /// Normalized facing direction.
+    pub forward: Vector3<f32>,
+    pub up: Vector3<f32>,
+    pub velocity: Vector3<f32>,
+}
+
+impl Default for Listener {
+    fn default() -> Self {
+        Self {
+            position: Point3::new(0.0, 0.0, 0.0),
+            forward: -Vector3::unit_z(),
+            up: Vector3::unit_y(),
+            velocity: Vector3::zero(),
+        }
+    }
+}
+
+impl Listener {
+    /// Listener for 2D games, looking down -Z at `position` so +X pans right.
+    pub fn flat(x: f32, y: f32) -> Self {
+        Self {
+            position: Point3::new(x, y, 0.0),
+            ..Default::default()
+        }
+    }
+
+    /// Moves to `position` facing `forward`, deriving the velocity from the last position.
+    pub fn follow(&mut self, position: Point3<f32>, forward: Vector3<f32>, dt: Duration) {
+        let dt = dt.as_secs_f32();
+        self.velocity = if dt > 0.0 {
+            (position - self.position) / dt
+        } else {
+            Vector3::zero()
+        };
+        self.position = position;
+        self.forward = forward.normalize();
+    }
+
+    /// Stereo position of `point`, -1 left to 1 right.
+    pub fn pan(&self, point: Point3<f32>) -> f32 {
+        let to = point - self.position;
+        if to.magnitude2() < f32::EPSILON {
+            return 0.0;
+        }
+        let right = self.forward.cross(self.up).normalize();
+        to.normalize().dot(right).clamp(-1.0, 1.0)
+    }
+
+    /// Left and right gain of a voice, including distance attenuation.
+    pub fn gains(&self, spatial: &Spatial) -> (f32, f32) {
+        match spatial {
+            Spatial::Flat { pan } => balance_gains(*pan),
+            Spatial::Positional {
+                position,
+                attenuation,
+                ..
+            } => {
+                let gain = attenuation.gain((position - self.position).magnitude());
+                let (l, r) = pan_gains(self.pan(*position));
+                (l * gain, r * gain)
+            }
+        }
+    }
+}
+
+/// Constant power pan of a mono signal, both sides are at -3dB in the center.
+pub fn pan_gains(pan: f32) -> (f32, f32) {
+    let angle = (pan.clamp(-1.0, 1.0) + 1.0) * std::f32::consts::FRAC_PI_4;
+    (angle.cos(), angle.sin())
+}
+
+/// Balance of a stereo signal, the center leaves both channels untouched.
+pub fn balance_gains(pan: f32) -> (f32, f32) {
+    let pan = pan.clamp(-1.0, 1.0);
+    ((1.0 - pan).min(1.0), (1.0 + pan).min(1.0))
+}
+
+/// Pitch shift from the relative motion of a source and the listener.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Doppler {
+    /// In world units per second.
+    pub speed_of_sound: f32,
+    /// Scales the effect, 0 disables it.
+    pub factor: f32,
+}
+
+impl Default for Doppler {
+    fn default() -> Self {
+        Self {
+            speed_of_sound: 343.0,
+            factor: 1.0,
+        }
+    }
+}
+
+impl Doppler {
+    /// Playback rate multiplier for a source at `position` moving with `velocity`.
+    pub fn pitch(&self, listener: &Listener, position: Point3<f32>, velocity: Vector3<f32>) -> f32 {
+        let to = position - listener.position;
+        if to.magnitude2() < f32::EPSILON || self.factor <= 0.0 {
+            return 1.0;
+        }
+        let dir = to.normalize();
+        let c = self.speed_of_sound;
+        // Clamp below the speed of sound so the ratio stays finite.
+        let limit = c * 0.9;
+        let listener_speed = (listener.velocity.dot(dir) * self.factor).clamp(-limit, limit);
+        let source_speed = (velocity.dot(dir) * self.factor).clamp(-limit, limit);
+        (c + listener_speed) / (c + source_speed)
+    }
+}
@@ -0,0 +1,552 @@
+use std::time::Duration;
+
+use super::Sound;
+
+/// Audio decoded incrementally while it plays, e.g. a long music track.
+pub trait StreamSource: Send {
+    /// 1 or 2, sources with more channels have to downmix.
+    fn channels(&self) -> u16;
+    fn sample_rate(&self) -> u32;
+    /// Fills `out` with interleaved samples and returns the number of frames written.
+    /// Returns 0 once the end of the stream is reached.
+    fn read(&mut self, out: &mut [f32]) -> anyhow::Result<usize>;
+    /// Moves to an absolute frame, the next read starts exactly there.
+    fn seek(&mut self, frame: u64) -> anyhow::Result<()>;
+}
+
+/// Streams an already decoded Sound, mostly useful for short stingers and tests.
+#[derive(Debug, Clone)]
+pub struct SoundStream {
+    sound: Sound,
+    frame: usize,
+}
+
+impl SoundStream {
+    pub fn new(sound: Sound) -> Self {
+        Self { sound, frame: 0 }
+    }
+}
+
+impl StreamSource for SoundStream {
+    fn channels(&self) -> u16 {
+        self.sound.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sound.sample_rate
+    }
+
+    fn read(&mut self, out: &mut [f32]) -> anyhow::Result<usize> {
+        let channels = self.sound.channels as usize;
+        let frames = (out.len() / channels).min(self.sound.frames() - self.frame);
+        let start = self.frame * channels;
+        out[..frames * channels]
+            .copy_from_slice(&self.sound.samples[start..start + frames * channels]);
+        self.frame += frames;
+        Ok(frames)
+    }
+
+    fn seek(&mut self, frame: u64) -> anyhow::Result<()> {
+        self.frame = (frame as usize).min(self.sound.frames());
+        Ok(())
+    }
+}
+
+/// Loop region in source frames. Playback jumps from `end` (or the end of the stream)
+/// back to `start` without dropping or repeating a frame.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct LoopPoints {
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+/// Options for MusicPlayer::play and MusicPlayer::crossfade.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct MusicOptions {
+    pub looping: Option<LoopPoints>,
+    pub fade_in: Duration,
+}
+
+impl MusicOptions {
+    /// Loops the whole track.
+    pub fn looping(mut self) -> Self {
+        self.looping = Some(LoopPoints::default());
+        self
+    }
+
+    pub fn with_loop_points(mut self, start: u64, end: Option<u64>) -> Self {
+        self.looping = Some(LoopPoints { start, end });
+        self
+    }
+
+    pub fn with_fade_in(mut self, fade_in: Duration) -> Self {
+        self.fade_in = fade_in;
+        self
+    }
+}
+
+/// Linear volume ramp, measured in output frames.
+#[derive(Debug, Copy, Clone)]
+struct Fade {
+    from: f32,
+    to: f32,
+    elapsed: u64,
+    length: u64,
+}
+
+impl Fade {
+    fn new(from: f32, to: f32, length: u64) -> Self {
+        Self {
+            from,
+            to,
+            elapsed: 0,
+            length,
+        }
+    }
+
+    fn next(&mut self) -> f32 {
+        if self.elapsed >= self.length {
+            return self.to;
+        }
+        let t = self.elapsed as f32 / self.length as f32;
+        self.elapsed += 1;
+        self.from + (self.to - self.from) * t
+    }
+
+    fn is_done(&self) -> bool {
+        self.elapsed >= self.length
+    }
+}
+
+struct MusicTrack {
+    source: Box<dyn StreamSource>,
+    looping: Option<LoopPoints>,
+    /// Source frame the next call to next_frame returns.
+    position: u64,
+    chunk: Vec<f32>,
+    chunk_pos: usize,
+    chunk_len: usize,
+    /// Resampler state, the two source frames around the output position.
+    frames: [(f32, f32); 2],
+    frac: f64,
+    primed: bool,
+    fade: Fade,
+    finished: bool,
+}
+
+impl MusicTrack {
+    /// Decoded frames read from the source at a time.
+    const CHUNK_FRAMES: usize = 4096;
+
+    fn new(source: Box<dyn StreamSource>, options: MusicOptions, sample_rate: u32) -> Self {
+        let channels = source.channels().clamp(1, 2) as usize;
+        let fade_len = (options.fade_in.as_secs_f64() * sample_rate as f64) as u64;
+        Self {
+            source,
+            looping: options.looping,
+            position: 0,
+            chunk: vec![0.0; Self::CHUNK_FRAMES * channels],
+            chunk_pos: 0,
+            chunk_len: 0,
+            frames: [(0.0, 0.0); 2],
+            frac: 0.0,
+            primed: false,
+            fade: Fade::new(if fade_len > 0 { 0.0 } else { 1.0 }, 1.0, fade_len),
+            finished: false,
+        }
+    }
+
+    fn channels(&self) -> usize {
+        self.source.channels().clamp(1, 2) as usize
+    }
+
+    fn jump(&mut self, frame: u64) -> anyhow::Result<()> {
+        self.source.seek(frame)?;
+        self.position = frame;
+        self.chunk_pos = 0;
+        self.chunk_len = 0;
+        Ok(())
+    }
+
+    /// Next source frame, following loop points. None once a non looping track ends.
+    fn next_frame(&mut self) -> anyhow::Result<Option<(f32, f32)>> {
+        if let Some(LoopPoints {
+            start,
+            end: Some(end),
+        }) = self.looping
+        {
+            if self.position >= end {
+                self.jump(start)?;
+            }
+        }
+        if self.chunk_pos >= self.chunk_len {
+            let mut frames = self.source.read(&mut self.chunk)?;
+            if frames == 0 {
+                let Some(LoopPoints { start, .. }) = self.looping else {
+                    return Ok(None);
+                };
+                self.jump(start)?;
+                frames = self.source.read(&mut self.chunk)?;
+                if frames == 0 {
+                    return Ok(None);
+                }
+            }
+            self.chunk_pos = 0;
+            self.chunk_len = frames * self.channels();
+        }
+        let frame = match self.channels() {
+            1 => {
+                let s = self.chunk[self.chunk_pos];
+                (s, s)
+            }
+            _ => (self.chunk[self.chunk_pos], self.chunk[self.chunk_pos + 1]),
+        };
+        self.chunk_pos += self.channels();
+        self.position += 1;
+        Ok(Some(frame))
+    }
+
+    /// Adds the track into interleaved stereo `out`, resampling to `sample_rate`.
+    fn mix(&mut self, out: &mut [f32], sample_rate: u32, gain: f32) {
+        let step = self.source.sample_rate() as f64 / sample_rate as f64;
+        for frame in out.chunks_exact_mut(2) {
+            if self.finished {
+                return;
+            }
+            if let Err(e) = self.advance(step) {
+                log::warn!("MusicTrack::mix => stream error, stopping track: {:#}", e);
+                self.finished = true;
+                return;
+            }
+            if self.finished {
+                return;
+            }
+            let [(l0, r0), (l1, r1)] = self.frames;
+            let t = self.frac as f32;
+            let gain = self.fade.next() * gain;
+            frame[0] += (l0 + (l1 - l0) * t) * gain;
+            frame[1] += (r0 + (r1 - r0) * t) * gain;
+        }
+    }
+
+    /// Moves the resampler forward by `step` source frames.
+    fn advance(&mut self, step: f64) -> anyhow::Result<()> {
+        if !self.primed {
+            self.primed = true;
+            let first = self.next_frame()?;
+            let second = self.next_frame()?.or(first);
+            match (first, second) {
+                (Some(a), Some(b)) => self.frames = [a, b],
+                _ => self.finished = true,
+            }
+            return Ok(());
+        }
+        self.frac += step;
+        while self.frac >= 1.0 {
+            self.frac -= 1.0;
+            match self.next_frame()? {
+                Some(next) => self.frames = [self.frames[1], next],
+                None => {
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Streams music onto the Music bus, see Mixer::music. Decoding happens in
+/// Mixer::mix, so only a small chunk of each track is in memory at a time.
+pub struct MusicPlayer {
+    current: Option<MusicTrack>,
+    /// Tracks fading out after a crossfade or stop.
+    outgoing: Vec<MusicTrack>,
+    sample_rate: u32,
+    pub paused: bool,
+    pub gain: f32,
+}
+
+impl std::fmt::Debug for MusicPlayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MusicPlayer")
+            .field("playing", &self.is_playing())
+            .field("outgoing", &self.outgoing.len())
+            .field("paused", &self.paused)
+            .field("gain", &self.gain)
+            .finish()
+    }
+}
+
+impl MusicPlayer {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            current: None,
+            outgoing: Vec::new(),
+            sample_rate,
+            paused: false,
+            gain: 1.0,
+        }
+    }
+
+    /// Fades already running keep their length in frames.
+    pub(super) fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// Replaces whatever is playing with `source`.
+    pub fn play(&mut self, source: Box<dyn StreamSource>, options: MusicOptions) {
+        self.outgoing.clear();
+        self.current = Some(MusicTrack::new(source, options, self.sample_rate));
+        self.paused = false;
+    }
+
+    /// Fades the current track out while `source` fades in over `duration`.
+    pub fn crossfade(
+        &mut self,
+        source: Box<dyn StreamSource>,
+        options: MusicOptions,
+        duration: Duration,
+    ) {
+        self.fade_out_current(duration);
+        let options = MusicOptions {
+            fade_in: duration,
+            ..options
+        };
+        self.current = Some(MusicTrack::new(source, options, self.sample_rate));
+        self.paused = false;
+    }
+
+    /// Stops the current track, fading it out over `fade`.
+    pub fn stop(&mut self, fade: Duration) {
+        self.fade_out_current(fade);
+    }
+
+    fn fade_out_current(&mut self, duration: Duration) {
+        if let Some(mut track) = self.current.take() {
+            let length = (duration.as_secs_f64() * self.sample_rate as f64) as u64;
+            // Start from the track's current level so an unfinished fade in doesn't jump.
+            let level = track.fade.next();
+            track.fade = Fade::new(level, 0.0, length);
+            if length > 0 {
+                self.outgoing.push(track);
+            }
+        }
+    }
+
+    /// Jumps the current track to `position`.
+    pub fn seek(&mut self, position: Duration) -> anyhow::Result<()> {
+        let Some(track) = self.current.as_mut() else {
+            return Ok(());
+        };
+        let frame = (position.as_secs_f64() * track.source.sample_rate() as f64) as u64;
+        track.jump(frame)?;
+        track.primed = false;
+        track.frac = 0.0;
+        track.finished = false;
+        Ok(())
+    }
+
+    /// Playback position of the current track.
+    pub fn position(&self) -> Duration {
+        self.current.as_ref().map_or(Duration::ZERO, |t| {
+            // The resampler holds two frames ahead of what is audible.
+            let frame = t.position.saturating_sub(2) as f64 + t.frac;
+            Duration::from_secs_f64(frame / t.source.sample_rate() as f64)
+        })
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.current.as_ref().is_some_and(|t| !t.finished)
+    }
+
+    /// Adds every playing track into interleaved stereo `out`.
+    pub(super) fn mix(&mut self, out: &mut [f32]) {
+        if self.paused {
+            return;
+        }
+        for track in self.current.iter_mut().chain(self.outgoing.iter_mut()) {
+            track.mix(out, self.sample_rate, self.gain);
+        }
+        self.outgoing.retain(|t| !t.finished && !t.fade.is_done());
+        if self.current.as_ref().is_some_and(|t| t.finished) {
+            self.current = None;
+        }
+    }
+}
+
+/// Decodes OGG Vorbis, MP3 and WAV files with symphonia as they play.
+#[cfg(feature = "music")]
+pub struct DecoderSource {
+    format: Box<dyn symphonia::core::formats::FormatReader>,
+    decoder: Box<dyn symphonia::core::codecs::Decoder>,
+    track_id: u32,
+    time_base: Option<symphonia::core::units::TimeBase>,
+    channels: usize,
+    sample_rate: u32,
+    /// Decoded samples of the last packet not yet handed out, interleaved in source channels.
+    pending: Option<symphonia::core::audio::SampleBuffer<f32>>,
+    pending_pos: usize,
+    /// Frames to drop after a seek landed before the requested frame.
+    skip: u64,
+}
+
+#[cfg(feature = "music")]
+impl DecoderSource {
+    /// Opens the file at `path`, the extension is used as a format hint.
+    pub fn open(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)?;
+        let mut hint = symphonia::core::probe::Hint::new();
+        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(ext);
+        }
+        Self::from_media(Box::new(file), hint)
+    }
+
+    /// Decodes from any seekable media source, e.g. a file inside a pack.
+    pub fn from_media(
+        media: Box<dyn symphonia::core::io::MediaSource>,
+        hint: symphonia::core::probe::Hint,
+    ) -> anyhow::Result<Self> {
+        use symphonia::core::{codecs::CODEC_TYPE_NULL, io::MediaSourceStream};
+
+        let stream = MediaSourceStream::new(media, Default::default());
+        let format = symphonia::default::get_probe()
+            .format(&hint, stream, &Default::default(), &Default::default())?
+            .format;
+        let track = format
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| anyhow::anyhow!("DecoderSource::from_media => no audio track"))?;
+        let params = &track.codec_params;
+        let sample_rate = params
+            .sample_rate
+            .ok_or_else(|| anyhow::anyhow!("DecoderSource::from_media => unknown sample rate"))?;
+        let channels = params.channels.map_or(2, |c| c.count());
+        let decoder = symphonia::default::get_codecs().make(params, &Default::default())?;
+        let track_id = track.id;
+        let time_base = params.time_base;
+
+        Ok(Self {
+            format,
+            decoder,
+            track_id,
+            time_base,
+            channels,
+            sample_rate,
+            pending: None,
+            pending_pos: 0,
+            skip: 0,
+        })
+    }
+
+    fn pending_len(&self) -> usize {
+        self.pending.as_ref().map_or(0, |p| p.len())
+    }
+
+    /// Decodes the next packet of the track into `pending`, false at the end of the stream.
+    fn decode_next(&mut self) -> anyhow::Result<bool> {
+        use symphonia::core::errors::Error;
+
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                // Symphonia reports the end of the stream as an unexpected EOF.
+                Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    return Ok(false);
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+            match self.decoder.decode(&packet) {
+                Ok(buffer) => {
+                    let spec = *buffer.spec();
+                    self.channels = spec.channels.count().max(1);
+                    let pending = match &mut self.pending {
+                        Some(p) if p.capacity() >= buffer.capacity() * self.channels => p,
+                        pending => pending.insert(symphonia::core::audio::SampleBuffer::new(
+                            buffer.capacity() as u64,
+                            spec,
+                        )),
+                    };
+                    pending.copy_interleaved_ref(buffer);
+                    self.pending_pos = 0;
+                    return Ok(true);
+                }
+                // Corrupt packets are skipped rather than ending the track.
+                Err(Error::DecodeError(e)) => {
+                    log::warn!("DecoderSource::decode_next => {}", e);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "music")]
+impl StreamSource for DecoderSource {
+    fn channels(&self) -> u16 {
+        self.channels.min(2) as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn read(&mut self, out: &mut [f32]) -> anyhow::Result<usize> {
+        let out_channels = self.channels.min(2);
+        let mut written = 0;
+        while written * out_channels + out_channels <= out.len() {
+            if self.pending_pos >= self.pending_len() && !self.decode_next()? {
+                break;
+            }
+            let Some(pending) = &self.pending else {
+                break;
+            };
+            let frame = &pending.samples()[self.pending_pos..self.pending_pos + self.channels];
+            self.pending_pos += self.channels;
+            if self.skip > 0 {
+                self.skip -= 1;
+                continue;
+            }
+            // Extra channels past stereo are dropped.
+            out[written * out_channels..(written + 1) * out_channels]
+                .copy_from_slice(&frame[..out_channels]);
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    fn seek(&mut self, frame: u64) -> anyhow::Result<()> {
+        use symphonia::core::{
+            formats::{SeekMode, SeekTo},
+            units::Time,
+        };
+
+        let time = Time::from(frame as f64 / self.sample_rate as f64);
+        let seeked = self.format.seek(
+            SeekMode::Accurate,
+            SeekTo::Time {
+                time,
+                track_id: Some(self.track_id),
+            },
+        )?;
+        self.decoder.reset();
+        self.pending = None;
+        self.pending_pos = 0;
+        // Accurate seeks may land on the packet before the requested frame.
+        let behind = seeked.required_ts.saturating_sub(seeked.actual_ts);
+        self.skip = match self.time_base {
+            Some(tb) => {
+                let time = tb.calc_time(behind);
+                ((time.seconds as f64 + time.frac) * self.sample_rate as f64).round() as u64
+            }
+            None => behind,
+        };
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    path::PathBuf,
+    ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use renderdoc::{RenderDoc, V141};
+
+/// Captures the next frame in RenderDoc, registered by the engine on Ctrl+F12. RenderDoc's own
+/// capture keys (F12 / PrtScn) still work when the app is launched from RenderDoc.
+pub const CAPTURE_FRAME: &str = "renderdoc.capture_frame";
+
+/// Programmatic RenderDoc captures, see RenderWindow::capture. Captures only happen when the app
+/// is launched from (or injected by) RenderDoc, otherwise requests are logged and dropped.
+pub struct GpuCapture {
+    renderdoc: Option<RenderDoc<V141>>,
+    pending: bool,
+    capturing: bool,
+    auto_capture: bool,
+    auto_captures: u32,
+    max_auto_captures: u32,
+    /// Set from wgpu's error handler, which may run on any thread.
+    error: Arc<AtomicBool>,
+}
+
+impl std::fmt::Debug for GpuCapture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GpuCapture")
+            .field("available", &self.is_available())
+            .field("pending", &self.pending)
+            .field("auto_capture", &self.auto_capture)
+            .field("auto_captures", &self.auto_captures)
+            .finish()
+    }
+}
+
+impl Default for GpuCapture {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+impl GpuCapture {
+    /// Automatic captures per run, a persistent error would otherwise capture every frame.
+    pub const DEFAULT_MAX_AUTO_CAPTURES: u32 = 3;
+
+    /// Connects to RenderDoc if it is loaded into the process.
+    pub fn new() -> Self {
+        match RenderDoc::<V141>::new() {
+            Ok(renderdoc) => {
+                let (major, minor, patch) = renderdoc.get_api_version();
+                log::info!("GpuCapture::new => RenderDoc {}.{}.{}", major, minor, patch);
+                Self {
+                    renderdoc: Some(renderdoc),
+                    ..Self::disabled()
+                }
+            }
+            Err(e) => {
+                log::info!("GpuCapture::new => RenderDoc not available: {}", e);
+                Self::disabled()
+            }
+        }
+    }
+
+    /// Without RenderDoc, still tracks requests so the app loop behaves the same.
+    pub fn disabled() -> Self {
+        Self {
+            renderdoc: None,
+            pending: false,
+            capturing: false,
+            auto_capture: cfg!(debug_assertions),
+            auto_captures: 0,
+            max_auto_captures: Self::DEFAULT_MAX_AUTO_CAPTURES,
+            error: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    #[inline]
+    pub fn is_available(&self) -> bool {
+        self.renderdoc.is_some()
+    }
+
+    /// Captures the whole of the next frame drawn.
+    pub fn capture_next_frame(&mut self) {
+        if !self.is_available() {
+            log::warn!("GpuCapture::capture_next_frame => RenderDoc not available");
+        }
+        self.pending = true;
+    }
+
+    #[inline]
+    pub fn is_pending(&self) -> bool {
+        self.pending
+    }
+
+    /// Whether validation and surface errors capture the following frame, on in debug builds.
+    #[inline]
+    pub fn auto_capture(&self) -> bool {
+        self.auto_capture
+    }
+
+    pub fn set_auto_capture(&mut self, auto_capture: bool) {
+        self.auto_capture = auto_capture;
+    }
+
+    pub fn set_max_auto_captures(&mut self, max: u32) {
+        self.max_auto_captures = max;
+    }
+
+    /// Automatic captures taken so far.
+    #[inline]
+    pub fn auto_captures(&self) -> u32 {
+        self.auto_captures
+    }
+
+    /// Captures the next frame if auto capture is on and the limit isn't reached yet.
+    pub fn capture_on_error(&mut self, reason: &str) -> bool {
+        if !self.auto_capture || self.pending || self.auto_captures >= self.max_auto_captures {
+            return false;
+        }
+        log::warn!(
+            "GpuCapture::capture_on_error => capturing next frame after {}",
+            reason
+        );
+        self.auto_captures += 1;
+        self.pending = true;
+        true
+    }
+
+    /// Handler for wgpu::Device::on_uncaptured_error. It logs the error instead of panicking
+    /// so the next frame can be captured.
+    pub fn error_handler(&self) -> impl Fn(wgpu::Error) + Send + Sync + 'static {
+        let error = self.error.clone();
+        move |e| {
+            log::error!("GpuCapture => uncaptured wgpu error: {}", e);
+            error.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Starts a pending capture, call before the frame's commands are recorded.
+    pub fn begin_frame(&mut self) -> bool {
+        if self.error.swap(false, Ordering::Relaxed) {
+            self.capture_on_error("a wgpu error");
+        }
+        if !std::mem::take(&mut self.pending) {
+            return false;
+        }
+        if let Some(renderdoc) = self.renderdoc.as_mut() {
+            // Null device and window match whatever wgpu is presenting with.
+            renderdoc.start_frame_capture(ptr::null::<std::ffi::c_void>(), ptr::null());
+            self.capturing = true;
+        }
+        self.capturing
+    }
+
+    /// Ends the capture started by begin_frame, call after the frame is submitted.
+    pub fn end_frame(&mut self) {
+        if !std::mem::take(&mut self.capturing) {
+            return;
+        }
+        if let Some(renderdoc) = self.renderdoc.as_mut() {
+            renderdoc.end_frame_capture(ptr::null::<std::ffi::c_void>(), ptr::null());
+            let count = renderdoc.get_num_captures();
+            if let Some((path, _)) = count.checked_sub(1).and_then(|i| renderdoc.get_capture(i)) {
+                log::info!("GpuCapture::end_frame => saved {}", path.display());
+            }
+        }
+    }
+
+    /// Path prefix captures are saved to, e.g. `captures/radium`.
+    pub fn set_capture_path(&mut self, template: impl Into<PathBuf>) {
+        if let Some(renderdoc) = self.renderdoc.as_mut() {
+            renderdoc.set_capture_file_path_template(template);
+        }
+    }
+
+    /// Number of captures RenderDoc has saved this run.
+    pub fn num_captures(&self) -> u32 {
+        self.renderdoc
+            .as_ref()
+            .map(|renderdoc| renderdoc.get_num_captures())
+            .unwrap_or(0)
+    }
+}
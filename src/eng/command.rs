@@ -1,14 +1,18 @@
-use std::{cell::RefCell, collections::VecDeque, ops::Range, rc::Rc, sync::Arc};
+use std::{collections::HashMap, ops::Range, rc::Rc, sync::Arc};
 
 use wgpu::{BufferAddress, DynamicOffset, IndexFormat};
 
 use crate::gfx::{
     draw::DrawCtx,
     model::{Material, Mesh, Model},
-    wgpu::texture::Texture,
+    wgpu::{surface::SurfaceError, texture::Texture},
 };
 
-use super::render::{DeviceSurface, RenderWindow};
+use super::{
+    occlusion::{OcclusionQuerySet, QuerySetHandle},
+    render::{DeviceSurface, RenderWindow},
+    validate::{validate_commands, CommandError, PassLimits},
+};
 
 #[derive(Debug, Clone)]
 pub enum RenderCommand {
@@ -33,6 +37,9 @@ pub enum RenderCommand {
     SetViewPort(f32, f32, f32, f32, f32, f32),
     /// pub fn set_stencil_reference(&mut self, reference: u32)
     SetStencilReference(u32),
+    /// pub fn set_immediates(&mut self, offset: u32, data: &[u8])
+    /// Needs Features::IMMEDIATES, see gfx::wgpu::immediate::Immediates for the fallback.
+    SetImmediates(u32, Vec<u8>),
     /// pub fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>)
     Draw(Range<u32>, Range<u32>),
     /// pub fn insert_debug_marker(&mut self, label: &str)
@@ -52,6 +59,25 @@ pub enum RenderCommand {
     ///    indirect_offset: BufferAddress,
     /// )
     DrawIndexedIndirect(Arc<wgpu::Buffer>, BufferAddress),
+    /// pub fn multi_draw_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: BufferAddress, count: u32)
+    MultiDrawIndirect(Arc<wgpu::Buffer>, BufferAddress, u32),
+    ///
+    /// pub fn multi_draw_indexed_indirect(
+    ///    &mut self,
+    ///    indirect_buffer: &Buffer,
+    ///    indirect_offset: BufferAddress,
+    ///    count: u32,
+    /// )
+    MultiDrawIndexedIndirect(Arc<wgpu::Buffer>, BufferAddress, u32),
+    /// pub fn begin_occlusion_query(&mut self, query_index: u32)
+    /// Queries index into the pass's occlusion query set, see RenderPass::resolve_query_set.
+    BeginOcclusionQuery(u32),
+    /// pub fn end_occlusion_query(&mut self)
+    EndOcclusionQuery,
+    /// pub fn begin_pipeline_statistics_query(&mut self, query_set: &'a QuerySet, query_index: u32)
+    BeginPipelineStatisticsQuery(QuerySetHandle, u32),
+    /// pub fn end_pipeline_statistics_query(&mut self)
+    EndPipelineStatisticsQuery,
 
     /// TODO ::
     /// pub fn execute_bundles<I: IntoIterator<Item = &'a RenderBundle> + 'a>(
@@ -60,6 +86,273 @@ pub enum RenderCommand {
     /// )
     ExecuteBundles(),
 }
+
+impl RenderCommand {
+    /// Variant name without its arguments, for logs and diagnostics.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SetPipeline(..) => "SetPipeline",
+            Self::SetBindGroup(..) => "SetBindGroup",
+            Self::SetBlendConstant(..) => "SetBlendConstant",
+            Self::SetIndexBuffer(..) => "SetIndexBuffer",
+            Self::SetVertexBuffer(..) => "SetVertexBuffer",
+            Self::SetScissorRect(..) => "SetScissorRect",
+            Self::SetViewPort(..) => "SetViewPort",
+            Self::SetStencilReference(..) => "SetStencilReference",
+            Self::SetImmediates(..) => "SetImmediates",
+            Self::Draw(..) => "Draw",
+            Self::InsertDebugMarker(..) => "InsertDebugMarker",
+            Self::PushDebugGroup(..) => "PushDebugGroup",
+            Self::PopDebugGroup => "PopDebugGroup",
+            Self::DrawIndexed(..) => "DrawIndexed",
+            Self::DrawIndirect(..) => "DrawIndirect",
+            Self::DrawIndexedIndirect(..) => "DrawIndexedIndirect",
+            Self::MultiDrawIndirect(..) => "MultiDrawIndirect",
+            Self::MultiDrawIndexedIndirect(..) => "MultiDrawIndexedIndirect",
+            Self::BeginOcclusionQuery(..) => "BeginOcclusionQuery",
+            Self::EndOcclusionQuery => "EndOcclusionQuery",
+            Self::BeginPipelineStatisticsQuery(..) => "BeginPipelineStatisticsQuery",
+            Self::EndPipelineStatisticsQuery => "EndPipelineStatisticsQuery",
+            Self::ExecuteBundles() => "ExecuteBundles",
+        }
+    }
+}
+/// Commands recorded into a ComputePass, mirroring wgpu::ComputePass.
+#[derive(Debug, Clone)]
+pub enum ComputeCommand {
+    SetPipeline(Arc<wgpu::ComputePipeline>),
+    SetBindGroup(u32, Arc<wgpu::BindGroup>, Option<Vec<DynamicOffset>>),
+    /// pub fn dispatch_workgroups(&mut self, x: u32, y: u32, z: u32)
+    Dispatch(u32, u32, u32),
+    /// pub fn dispatch_workgroups_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: BufferAddress)
+    DispatchIndirect(Arc<wgpu::Buffer>, BufferAddress),
+}
+
+/// Compute work recorded for a frame, see DrawCtx::begin_compute_pass. Compute passes are
+/// encoded before any render pass of the frame, so draws can consume their results.
+#[derive(Debug, Clone, Default)]
+pub struct ComputePass {
+    pub command_queue: Vec<ComputeCommand>,
+    pub label: Option<String>,
+}
+
+impl ComputePass {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = Some(String::from(label));
+        self
+    }
+
+    pub fn set_pipeline(&mut self, pipeline: Arc<wgpu::ComputePipeline>) {
+        self.command_queue
+            .push(ComputeCommand::SetPipeline(pipeline));
+    }
+
+    pub fn set_bind_group(
+        &mut self,
+        index: u32,
+        bind_group: Arc<wgpu::BindGroup>,
+        offsets: Option<Vec<DynamicOffset>>,
+    ) {
+        self.command_queue
+            .push(ComputeCommand::SetBindGroup(index, bind_group, offsets));
+    }
+
+    pub fn dispatch(&mut self, x: u32, y: u32, z: u32) {
+        self.command_queue.push(ComputeCommand::Dispatch(x, y, z));
+    }
+
+    pub fn dispatch_indirect(&mut self, indirect_buffer: Arc<wgpu::Buffer>, offset: BufferAddress) {
+        self.command_queue
+            .push(ComputeCommand::DispatchIndirect(indirect_buffer, offset));
+    }
+
+    /// Records the queued commands into `encoder` and clears the queue.
+    pub fn encode(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        {
+            let mut cp = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some(self.label.as_deref().unwrap_or("Compute Pass")),
+                timestamp_writes: None,
+            });
+            for cmd in self.command_queue.iter() {
+                match cmd {
+                    ComputeCommand::SetPipeline(pipeline) => cp.set_pipeline(pipeline),
+                    ComputeCommand::SetBindGroup(slot, bind_group, offsets) => {
+                        let offsets = match offsets {
+                            Some(os) => os.as_slice(),
+                            None => &[],
+                        };
+                        cp.set_bind_group(*slot, bind_group.as_ref(), offsets);
+                    }
+                    ComputeCommand::Dispatch(x, y, z) => cp.dispatch_workgroups(*x, *y, *z),
+                    ComputeCommand::DispatchIndirect(buffer, offset) => {
+                        cp.dispatch_workgroups_indirect(buffer, *offset)
+                    }
+                }
+            }
+        }
+        self.command_queue.clear();
+    }
+}
+
+/// Records straight into a pass's command queue, handed to the closure of
+/// DrawCtx::with_pass. Debug groups left open when it is dropped are popped.
+#[derive(Debug)]
+pub struct PassRecorder<'a> {
+    commands: &'a mut Vec<RenderCommand>,
+    debug_groups: usize,
+}
+
+impl<'a> PassRecorder<'a> {
+    pub fn new(commands: &'a mut Vec<RenderCommand>) -> Self {
+        Self {
+            commands,
+            debug_groups: 0,
+        }
+    }
+
+    /// Queues any command, e.g. one built by Immediates::command.
+    #[inline]
+    pub fn push(&mut self, cmd: RenderCommand) {
+        self.commands.push(cmd);
+    }
+
+    /// Commands recorded into the pass so far, including those from before the recorder.
+    #[inline]
+    pub fn commands(&self) -> &[RenderCommand] {
+        self.commands
+    }
+
+    pub fn set_pipeline(&mut self, pipeline: Arc<wgpu::RenderPipeline>) {
+        self.push(RenderCommand::SetPipeline(pipeline));
+    }
+
+    pub fn set_bind_group(
+        &mut self,
+        index: u32,
+        bind_group: Arc<wgpu::BindGroup>,
+        offsets: Option<Vec<DynamicOffset>>,
+    ) {
+        self.push(RenderCommand::SetBindGroup(index, bind_group, offsets));
+    }
+
+    pub fn set_blend_constant(&mut self, color: wgpu::Color) {
+        self.push(RenderCommand::SetBlendConstant(color));
+    }
+
+    pub fn set_index_buffer(&mut self, buffer: Arc<wgpu::Buffer>, format: IndexFormat) {
+        self.push(RenderCommand::SetIndexBuffer(buffer, format));
+    }
+
+    pub fn set_vertex_buffer(&mut self, slot: u32, buffer: Arc<wgpu::Buffer>) {
+        self.push(RenderCommand::SetVertexBuffer(slot, buffer));
+    }
+
+    pub fn set_scissor_rect(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        self.push(RenderCommand::SetScissorRect(x, y, width, height));
+    }
+
+    pub fn set_viewport(
+        &mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        min_depth: f32,
+        max_depth: f32,
+    ) {
+        self.push(RenderCommand::SetViewPort(
+            x, y, width, height, min_depth, max_depth,
+        ));
+    }
+
+    pub fn set_stencil_reference(&mut self, reference: u32) {
+        self.push(RenderCommand::SetStencilReference(reference));
+    }
+
+    pub fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+        self.push(RenderCommand::Draw(vertices, instances));
+    }
+
+    pub fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
+        self.push(RenderCommand::DrawIndexed(indices, base_vertex, instances));
+    }
+
+    pub fn draw_indirect(&mut self, indirect_buffer: Arc<wgpu::Buffer>, offset: BufferAddress) {
+        self.push(RenderCommand::DrawIndirect(indirect_buffer, offset));
+    }
+
+    pub fn draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: Arc<wgpu::Buffer>,
+        offset: BufferAddress,
+    ) {
+        self.push(RenderCommand::DrawIndexedIndirect(indirect_buffer, offset));
+    }
+
+    pub fn multi_draw_indirect(
+        &mut self,
+        indirect_buffer: Arc<wgpu::Buffer>,
+        offset: BufferAddress,
+        count: u32,
+    ) {
+        self.push(RenderCommand::MultiDrawIndirect(
+            indirect_buffer,
+            offset,
+            count,
+        ));
+    }
+
+    pub fn multi_draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: Arc<wgpu::Buffer>,
+        offset: BufferAddress,
+        count: u32,
+    ) {
+        self.push(RenderCommand::MultiDrawIndexedIndirect(
+            indirect_buffer,
+            offset,
+            count,
+        ));
+    }
+
+    pub fn insert_debug_marker(&mut self, label: &str) {
+        self.push(RenderCommand::InsertDebugMarker(String::from(label)));
+    }
+
+    pub fn push_debug_group(&mut self, label: &str) {
+        self.debug_groups += 1;
+        self.push(RenderCommand::PushDebugGroup(String::from(label)));
+    }
+
+    /// Pops a group pushed through this recorder, groups opened before it are left alone.
+    pub fn pop_debug_group(&mut self) {
+        if self.debug_groups == 0 {
+            log::warn!("PassRecorder::pop_debug_group => no debug group to pop");
+            return;
+        }
+        self.debug_groups -= 1;
+        self.push(RenderCommand::PopDebugGroup);
+    }
+}
+
+impl Drop for PassRecorder<'_> {
+    fn drop(&mut self) {
+        if self.debug_groups > 0 {
+            log::warn!(
+                "PassRecorder::drop => closing {} debug groups left open",
+                self.debug_groups
+            );
+        }
+        for _ in 0..self.debug_groups {
+            self.commands.push(RenderCommand::PopDebugGroup);
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum RenderPassOp {
     Clear(wgpu::Color),
@@ -71,13 +364,34 @@ impl RenderPassOp {
     pub const CLEAR_WHITE: RenderPassOp = RenderPassOp::Clear(wgpu::Color::WHITE);
 }
 
+/// Color attachment a RenderPass draws into.
+#[derive(Clone, Debug)]
+pub enum RenderTarget {
+    /// The window's current surface texture, presented once the pass is submitted.
+    Surface,
+    /// An offscreen texture that later passes can sample from.
+    Texture(Rc<Texture>),
+    /// No color attachment, only the pass's depth texture is written, e.g. shadow maps.
+    DepthOnly,
+}
+
 #[derive(Clone, Debug)]
 pub struct RenderPass {
     pub command_queue: Vec<RenderCommand>,
     pub surface: Rc<DeviceSurface>,
 
-    pub depth_texture: Rc<Texture>,
+    pub target: RenderTarget,
+    pub depth_texture: Option<Rc<Texture>>,
     pub op: RenderPassOp,
+    /// Debug label of the pass, shows up in graphics debuggers.
+    pub label: Option<String>,
+    /// Query sets to resolve and read back once this pass has been encoded.
+    pub query_sets: Vec<Rc<OcclusionQuerySet>>,
+    /// Run merge_draws over the queue before encoding, on by default.
+    pub merge_draws: bool,
+    /// Run validate_commands before encoding and skip the pass on errors, on by default in
+    /// debug builds.
+    pub validate: bool,
 }
 
 impl RenderPass {
@@ -87,7 +401,71 @@ impl RenderPass {
             command_queue: Vec::with_capacity(32),
             surface: surface.clone(),
             op,
-            depth_texture: depth_texture.clone(),
+            target: RenderTarget::Surface,
+            depth_texture: Some(depth_texture.clone()),
+            label: None,
+            query_sets: Vec::new(),
+            merge_draws: true,
+            validate: cfg!(debug_assertions),
+        }
+    }
+
+    /// Creates a pass that renders to the surface with an optional depth attachment.
+    pub fn to_surface(
+        surface: &Rc<DeviceSurface>,
+        depth_texture: Option<&Rc<Texture>>,
+        op: RenderPassOp,
+    ) -> Self {
+        Self {
+            command_queue: Vec::with_capacity(32),
+            surface: surface.clone(),
+            op,
+            target: RenderTarget::Surface,
+            depth_texture: depth_texture.cloned(),
+            label: None,
+            query_sets: Vec::new(),
+            merge_draws: true,
+            validate: cfg!(debug_assertions),
+        }
+    }
+
+    /// Creates a pass that renders into an offscreen texture instead of the surface.
+    pub fn to_texture(
+        surface: &Rc<DeviceSurface>,
+        target: &Rc<Texture>,
+        depth_texture: Option<&Rc<Texture>>,
+        op: RenderPassOp,
+    ) -> Self {
+        Self {
+            command_queue: Vec::with_capacity(32),
+            surface: surface.clone(),
+            op,
+            target: RenderTarget::Texture(target.clone()),
+            depth_texture: depth_texture.cloned(),
+            label: None,
+            query_sets: Vec::new(),
+            merge_draws: true,
+            validate: cfg!(debug_assertions),
+        }
+    }
+
+    /// Creates a pass that only writes `depth_texture`, pipelines drawn in it need no
+    /// color targets.
+    pub fn depth_only(
+        surface: &Rc<DeviceSurface>,
+        depth_texture: &Rc<Texture>,
+        op: RenderPassOp,
+    ) -> Self {
+        Self {
+            command_queue: Vec::with_capacity(32),
+            surface: surface.clone(),
+            op,
+            target: RenderTarget::DepthOnly,
+            depth_texture: Some(depth_texture.clone()),
+            label: None,
+            query_sets: Vec::new(),
+            merge_draws: true,
+            validate: cfg!(debug_assertions),
         }
     }
 
@@ -99,41 +477,198 @@ impl RenderPass {
         Self::new(&ctx.device_surface, &ctx.depth_texture, op)
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let frame = self.surface.get_current_texture()?;
-        let view = frame
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = Some(String::from(label));
+        self
+    }
+
+    pub fn with_draw_merging(mut self, merge_draws: bool) -> Self {
+        self.merge_draws = merge_draws;
+        self
+    }
+
+    pub fn with_validation(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Drops the depth attachment, e.g. for 2D and UI passes that never depth test.
+    pub fn without_depth(mut self) -> Self {
+        self.depth_texture = None;
+        self
+    }
+
+    /// Format of the depth attachment, None if this pass has none.
+    pub fn depth_format(&self) -> Option<wgpu::TextureFormat> {
+        self.depth_texture.as_ref().map(|t| t.handle.format())
+    }
+
+    /// Checks that a pipeline built for `depth_format` can be drawn in this pass.
+    pub fn validate_depth(&self, depth_format: Option<wgpu::TextureFormat>) -> anyhow::Result<()> {
+        validate_depth(self.depth_format(), depth_format).map_err(|e| {
+            e.context(format!(
+                "RenderPass '{}'",
+                self.label.as_deref().unwrap_or("Render Pass")
+            ))
+        })
+    }
+
+    /// Checks that the color and depth attachments are the same size, wgpu rejects the
+    /// pass otherwise. `surface_size` is the acquired surface texture's size, only used
+    /// when targeting the surface.
+    pub fn validate_attachments(&self, surface_size: Option<wgpu::Extent3d>) -> anyhow::Result<()> {
+        let color = match &self.target {
+            RenderTarget::Texture(texture) => Some(texture.handle.size()),
+            RenderTarget::Surface => surface_size,
+            RenderTarget::DepthOnly => None,
+        };
+        let depth = self.depth_texture.as_ref().map(|t| t.handle.size());
+        if let (Some(color), Some(depth)) = (color, depth) {
+            anyhow::ensure!(
+                color.width == depth.width && color.height == depth.height,
+                "RenderPass '{}' => color attachment is {}x{} but the depth attachment is {}x{}",
+                self.label.as_deref().unwrap_or("Render Pass"),
+                color.width,
+                color.height,
+                depth.width,
+                depth.height
+            );
+        }
+        Ok(())
+    }
+
+    /// Checks the queued commands against the pipelines registered in
+    /// DeviceSurface::pipeline_shapes and the size of the attachments.
+    pub fn validate_commands(&self) -> Vec<CommandError> {
+        let size = |texture: &Texture| [texture.handle.width(), texture.handle.height()];
+        let attachment_size = match &self.target {
+            RenderTarget::Texture(texture) => Some(size(texture)),
+            RenderTarget::Surface => Some([self.surface.width(), self.surface.height()]),
+            RenderTarget::DepthOnly => self.depth_texture.as_deref().map(size),
+        };
+        validate_commands(
+            self.label.as_deref().unwrap_or("Render Pass"),
+            &self.command_queue,
+            PassLimits {
+                attachment_size,
+                max_bind_groups: self.surface.device.limits().max_bind_groups,
+                max_immediate_size: self.surface.device.limits().max_immediate_size,
+                shapes: &self.surface.pipeline_shapes,
+            },
+        )
+    }
+
+    /// Queues a query set to be resolved at the end of this pass, duplicates are ignored.
+    pub fn resolve_query_set(&mut self, query_set: &Rc<OcclusionQuerySet>) {
+        if !self.query_sets.iter().any(|q| Rc::ptr_eq(q, query_set)) {
+            self.query_sets.push(query_set.clone());
+        }
+    }
+
+    /// Acquires the surface if needed, then encodes, submits and presents this pass on its own.
+    /// Use DrawCtx::submit to render several passes into the same frame.
+    pub fn render(&mut self) -> Result<(), SurfaceError> {
+        let frame = match self.target {
+            RenderTarget::Surface => Some(self.surface.get_current_texture()?),
+            RenderTarget::Texture(_) | RenderTarget::DepthOnly => None,
+        };
+        if let Err(e) = self.validate_attachments(frame.as_ref().map(|f| f.texture.size())) {
+            log::warn!("RenderPass::render => {:#}, frame skipped", e);
+            return Err(SurfaceError::AttachmentSize);
+        }
+        let surface_view = frame.as_ref().map(|f| {
+            f.texture
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
 
-        let mut encoder = self.surface.create_command_encoder();
+        let surface = self.surface.clone();
+        let mut encoder = surface.encoders.create(
+            &surface.device,
+            self.label.as_deref().unwrap_or("Render Command Encoder"),
+        );
+        self.encode(&mut encoder, surface_view.as_ref());
+        surface.encoders.finish(encoder);
+        surface.submit_pending();
+        self.finish_queries();
 
+        if let Some(frame) = frame {
+            surface.queue.present(frame);
+        }
+        Ok(())
+    }
+
+    /// Records the queued commands into `encoder` and resolves the pass's query sets.
+    /// `surface_view` is the acquired frame's view, only used when targeting the surface.
+    /// Call finish_queries once the encoder has been submitted.
+    pub fn encode(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        surface_view: Option<&wgpu::TextureView>,
+    ) {
+        let view = match (&self.target, surface_view) {
+            (RenderTarget::Texture(texture), _) => Some(&texture.view),
+            (RenderTarget::DepthOnly, _) => None,
+            (RenderTarget::Surface, Some(view)) => Some(view),
+            (RenderTarget::Surface, None) => {
+                log::warn!("RenderPass::encode => Surface pass encoded without a surface view, pass skipped");
+                self.command_queue.clear();
+                return;
+            }
+        };
+
+        if self.validate {
+            let errors = self.validate_commands();
+            if !errors.is_empty() {
+                for error in errors.iter() {
+                    log::warn!("RenderPass::encode => {}", error);
+                }
+                log::warn!("RenderPass::encode => invalid commands, pass skipped");
+                self.command_queue.clear();
+                return;
+            }
+        }
+        if self.merge_draws {
+            let merged = merge_draws(&mut self.command_queue);
+            self.surface.encoders.record_merged_draws(merged);
+        }
+        self.surface.encoders.record_pass(self.command_queue.len());
         {
-            let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: match self.op {
-                            RenderPassOp::Clear(color) => wgpu::LoadOp::Clear(color),
-                            RenderPassOp::LoadFromMemory => wgpu::LoadOp::Load,
-                        },
-                        store: true,
+            let color = view.map(|view| wgpu::RenderPassColorAttachment {
+                view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: match self.op {
+                        RenderPassOp::Clear(color) => wgpu::LoadOp::Clear(color),
+                        RenderPassOp::LoadFromMemory => wgpu::LoadOp::Load,
                     },
-                })],
-                depth_stencil_attachment: {
-                    Some(wgpu::RenderPassDepthStencilAttachment {
-                        view: &self.depth_texture.view,
+                    store: wgpu::StoreOp::Store,
+                },
+            });
+            let color_attachments = match color {
+                Some(_) => std::slice::from_ref(&color),
+                None => &[],
+            };
+            let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(self.label.as_deref().unwrap_or("Render Pass")),
+                color_attachments,
+                depth_stencil_attachment: self.depth_texture.as_ref().map(|depth_texture| {
+                    wgpu::RenderPassDepthStencilAttachment {
+                        view: &depth_texture.view,
                         depth_ops: Some(wgpu::Operations {
                             load: match self.op {
                                 RenderPassOp::Clear(_) => wgpu::LoadOp::Clear(1.0),
                                 RenderPassOp::LoadFromMemory => wgpu::LoadOp::Load,
                             },
-                            store: true,
+                            store: wgpu::StoreOp::Store,
                         }),
                         stencil_ops: None,
-                    })
-                },
+                    }
+                }),
+                timestamp_writes: None,
+                // A pass can only bind one occlusion query set, the first queued one.
+                occlusion_query_set: self.query_sets.first().map(|q| q.query_set()),
+                multiview_mask: None,
             });
 
             for cmd in self.command_queue.iter() {
@@ -162,6 +697,7 @@ impl RenderPass {
                     RenderCommand::SetStencilReference(reference) => {
                         rp.set_stencil_reference(*reference)
                     }
+                    RenderCommand::SetImmediates(offset, data) => rp.set_immediates(*offset, data),
                     RenderCommand::Draw(vertices, instances) => {
                         rp.draw(vertices.clone(), instances.clone())
                     }
@@ -177,14 +713,157 @@ impl RenderPass {
                     RenderCommand::DrawIndexedIndirect(indirect_buffer, indirect_offset) => {
                         rp.draw_indexed_indirect(indirect_buffer, *indirect_offset)
                     }
+                    RenderCommand::MultiDrawIndirect(indirect_buffer, indirect_offset, count) => {
+                        rp.multi_draw_indirect(indirect_buffer, *indirect_offset, *count)
+                    }
+                    RenderCommand::MultiDrawIndexedIndirect(
+                        indirect_buffer,
+                        indirect_offset,
+                        count,
+                    ) => rp.multi_draw_indexed_indirect(indirect_buffer, *indirect_offset, *count),
+                    RenderCommand::BeginOcclusionQuery(query_index) => {
+                        rp.begin_occlusion_query(*query_index)
+                    }
+                    RenderCommand::EndOcclusionQuery => rp.end_occlusion_query(),
+                    RenderCommand::BeginPipelineStatisticsQuery(query_set, query_index) => {
+                        rp.begin_pipeline_statistics_query(query_set, *query_index)
+                    }
+                    RenderCommand::EndPipelineStatisticsQuery => rp.end_pipeline_statistics_query(),
                     RenderCommand::ExecuteBundles() => todo!(),
                 }
             }
         }
         self.command_queue.clear();
 
-        self.surface.queue.submit(std::iter::once(encoder.finish()));
-        frame.present();
-        Ok(())
+        for query_set in self.query_sets.iter() {
+            query_set.resolve(&self.surface, encoder);
+        }
     }
+
+    /// Forgets the query sets resolved by encode once the encoder was submitted, their
+    /// results were mapped by DeviceSurface::submit_pending.
+    pub fn finish_queries(&mut self) {
+        self.query_sets.clear();
+    }
+}
+
+/// wgpu requires a pipeline's depth-stencil state to match the pass's depth attachment
+/// exactly, this reports a mismatch before wgpu fails validation on encode.
+pub fn validate_depth(
+    pass_format: Option<wgpu::TextureFormat>,
+    pipeline_format: Option<wgpu::TextureFormat>,
+) -> anyhow::Result<()> {
+    match (pass_format, pipeline_format) {
+        (None, None) => Ok(()),
+        (Some(pass), Some(pipeline)) if pass == pipeline => Ok(()),
+        (Some(pass), Some(pipeline)) => anyhow::bail!(
+            "pipeline expects a {:?} depth attachment, the pass has {:?}",
+            pipeline,
+            pass
+        ),
+        (None, Some(pipeline)) => anyhow::bail!(
+            "pipeline expects a {:?} depth attachment, the pass has none",
+            pipeline
+        ),
+        (Some(pass), None) => anyhow::bail!(
+            "pass has a {:?} depth attachment but the pipeline has no depth state",
+            pass
+        ),
+    }
+}
+
+/// State bound so far while walking a pass's commands, used to spot redundant binds.
+#[derive(Default)]
+struct BoundState {
+    pipeline: Option<Arc<wgpu::RenderPipeline>>,
+    bind_groups: HashMap<u32, (Arc<wgpu::BindGroup>, Option<Vec<DynamicOffset>>)>,
+    vertex_buffers: HashMap<u32, Arc<wgpu::Buffer>>,
+    index_buffer: Option<(Arc<wgpu::Buffer>, IndexFormat)>,
+}
+
+impl BoundState {
+    /// Records a state command, returns false if it rebinds what is already bound.
+    fn bind(&mut self, cmd: &RenderCommand) -> bool {
+        match cmd {
+            RenderCommand::SetPipeline(pipeline) => {
+                if self
+                    .pipeline
+                    .as_ref()
+                    .is_some_and(|p| Arc::ptr_eq(p, pipeline))
+                {
+                    return false;
+                }
+                self.pipeline = Some(pipeline.clone());
+            }
+            RenderCommand::SetBindGroup(slot, group, offsets) => {
+                let bound = self.bind_groups.get(slot);
+                if bound.is_some_and(|(g, o)| Arc::ptr_eq(g, group) && o == offsets) {
+                    return false;
+                }
+                self.bind_groups
+                    .insert(*slot, (group.clone(), offsets.clone()));
+            }
+            RenderCommand::SetVertexBuffer(slot, buffer) => {
+                if self
+                    .vertex_buffers
+                    .get(slot)
+                    .is_some_and(|b| Arc::ptr_eq(b, buffer))
+                {
+                    return false;
+                }
+                self.vertex_buffers.insert(*slot, buffer.clone());
+            }
+            RenderCommand::SetIndexBuffer(buffer, format) => {
+                if self
+                    .index_buffer
+                    .as_ref()
+                    .is_some_and(|(b, f)| Arc::ptr_eq(b, buffer) && f == format)
+                {
+                    return false;
+                }
+                self.index_buffer = Some((buffer.clone(), *format));
+            }
+            _ => {}
+        }
+        true
+    }
+}
+
+/// Drops binds of already bound state and folds each draw into the previous one when
+/// both read adjacent ranges with the same instances, e.g. consecutive sprites sharing
+/// an atlas. Returns the number of draws that were merged away.
+pub fn merge_draws(commands: &mut Vec<RenderCommand>) -> u32 {
+    let mut bound = BoundState::default();
+    let mut merged = 0;
+    let mut out: Vec<RenderCommand> = Vec::with_capacity(commands.len());
+
+    for cmd in commands.drain(..) {
+        match (out.last_mut(), cmd) {
+            (
+                Some(RenderCommand::DrawIndexed(indices, base_vertex, instances)),
+                RenderCommand::DrawIndexed(next_indices, next_base_vertex, next_instances),
+            ) if indices.end == next_indices.start
+                && *base_vertex == next_base_vertex
+                && *instances == next_instances =>
+            {
+                indices.end = next_indices.end;
+                merged += 1;
+            }
+            (
+                Some(RenderCommand::Draw(vertices, instances)),
+                RenderCommand::Draw(next_vertices, next_instances),
+            ) if vertices.end == next_vertices.start && *instances == next_instances => {
+                vertices.end = next_vertices.end;
+                merged += 1;
+            }
+            (_, cmd) => {
+                if bound.bind(&cmd) {
+                    out.push(cmd);
+                }
+            }
+        }
+    }
+
+    *commands = out;
+    merged
 }
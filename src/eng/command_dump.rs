@@ -0,0 +1,737 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    path::Path,
+    sync::{Arc, Weak},
+};
+
+use anyhow::{bail, Context};
+
+use crate::sys::fs::write_atomic;
+
+use super::command::{
+    ComputeCommand, ComputePass, RenderCommand, RenderPass, RenderPassOp, RenderTarget,
+};
+
+/// Resource address to a weak handle, used to tell a live resource from a reused address,
+/// and its value.
+type ResourceEntries<V> = HashMap<usize, (Weak<dyn Any>, V)>;
+
+/// Values keyed by Arc'd wgpu resources, an entry goes away with its resource.
+pub(crate) struct ResourceMap<V> {
+    entries: RefCell<ResourceEntries<V>>,
+}
+
+impl<V> Default for ResourceMap<V> {
+    fn default() -> Self {
+        Self {
+            entries: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+impl<V: Clone> ResourceMap<V> {
+    pub(crate) fn insert<T: Any>(&self, resource: &Arc<T>, value: V) {
+        let mut entries = self.entries.borrow_mut();
+        // Addresses are reused once a resource is freed, dead entries would match them.
+        entries.retain(|_, (weak, _)| weak.strong_count() > 0);
+        let weak: Weak<dyn Any> = Arc::downgrade(resource) as Weak<dyn Any>;
+        entries.insert(Self::key(resource), (weak, value));
+    }
+
+    pub(crate) fn get<T: Any>(&self, resource: &Arc<T>) -> Option<V> {
+        self.entries
+            .borrow()
+            .get(&Self::key(resource))
+            .filter(|(weak, _)| weak.strong_count() > 0)
+            .map(|(_, value)| value.clone())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    fn key<T>(resource: &Arc<T>) -> usize {
+        Arc::as_ptr(resource) as *const () as usize
+    }
+}
+
+/// Names for pipelines, bind groups and buffers in command dumps, wgpu doesn't hand labels
+/// back once a resource is created. Lives on DeviceSurface, see DeviceSurface::labels.
+/// Resources without a name are numbered in the order a frame first uses them.
+#[derive(Default)]
+pub struct ResourceLabels {
+    labels: ResourceMap<String>,
+}
+
+impl fmt::Debug for ResourceLabels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResourceLabels")
+            .field("len", &self.labels.len())
+            .finish()
+    }
+}
+
+impl ResourceLabels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Names `resource` until it is dropped.
+    pub fn insert<T: Any>(&self, resource: &Arc<T>, label: &str) {
+        self.labels.insert(resource, String::from(label));
+    }
+
+    pub fn get<T: Any>(&self, resource: &Arc<T>) -> Option<String> {
+        self.labels.get(resource)
+    }
+}
+
+/// Names resources for one dump, the same resource gets the same name in every pass.
+struct Namer<'a> {
+    labels: &'a ResourceLabels,
+    names: HashMap<usize, String>,
+    counts: HashMap<&'static str, usize>,
+}
+
+impl<'a> Namer<'a> {
+    fn new(labels: &'a ResourceLabels) -> Self {
+        Self {
+            labels,
+            names: HashMap::new(),
+            counts: HashMap::new(),
+        }
+    }
+
+    fn name<T: Any>(&mut self, kind: &'static str, resource: &Arc<T>) -> String {
+        let key = Arc::as_ptr(resource) as *const () as usize;
+        if let Some(name) = self.names.get(&key) {
+            return name.clone();
+        }
+        let name = match self.labels.get(resource) {
+            Some(label) => format!("{} '{}'", kind, label),
+            None => {
+                let count = self.counts.entry(kind).or_insert(0);
+                *count += 1;
+                format!("{}#{}", kind, *count - 1)
+            }
+        };
+        self.names.insert(key, name.clone());
+        name
+    }
+
+    fn buffer(&mut self, buffer: &Arc<wgpu::Buffer>) -> String {
+        format!("{} [{} B]", self.name("buffer", buffer), buffer.size())
+    }
+
+    fn offsets(offsets: &Option<Vec<wgpu::DynamicOffset>>) -> String {
+        match offsets {
+            Some(offsets) => format!(", offsets {:?}", offsets),
+            None => String::new(),
+        }
+    }
+
+    fn render(&mut self, cmd: &RenderCommand) -> String {
+        let name = cmd.name();
+        match cmd {
+            RenderCommand::SetPipeline(pipeline) => {
+                format!("{}({})", name, self.name("pipeline", pipeline))
+            }
+            RenderCommand::SetBindGroup(slot, bind_group, offsets) => format!(
+                "{}({}, {}{})",
+                name,
+                slot,
+                self.name("bind_group", bind_group),
+                Self::offsets(offsets)
+            ),
+            RenderCommand::SetBlendConstant(c) => {
+                format!("{}({}, {}, {}, {})", name, c.r, c.g, c.b, c.a)
+            }
+            RenderCommand::SetIndexBuffer(buffer, format) => {
+                format!("{}({}, {:?})", name, self.buffer(buffer), format)
+            }
+            RenderCommand::SetVertexBuffer(slot, buffer) => {
+                format!("{}({}, {})", name, slot, self.buffer(buffer))
+            }
+            RenderCommand::SetScissorRect(x, y, w, h) => {
+                format!("{}({}, {}, {}, {})", name, x, y, w, h)
+            }
+            RenderCommand::SetViewPort(x, y, w, h, min_depth, max_depth) => format!(
+                "{}({}, {}, {}, {}, {}..{})",
+                name, x, y, w, h, min_depth, max_depth
+            ),
+            RenderCommand::SetStencilReference(reference) => format!("{}({})", name, reference),
+            RenderCommand::SetImmediates(offset, data) => {
+                format!("{}({}, {} bytes)", name, offset, data.len())
+            }
+            RenderCommand::Draw(vertices, instances) => {
+                format!("{}({:?}, {:?})", name, vertices, instances)
+            }
+            RenderCommand::InsertDebugMarker(label) | RenderCommand::PushDebugGroup(label) => {
+                format!("{}('{}')", name, label)
+            }
+            RenderCommand::DrawIndexed(indices, base_vertex, instances) => {
+                format!("{}({:?}, {}, {:?})", name, indices, base_vertex, instances)
+            }
+            RenderCommand::DrawIndirect(buffer, offset)
+            | RenderCommand::DrawIndexedIndirect(buffer, offset) => {
+                format!("{}({}, {})", name, self.buffer(buffer), offset)
+            }
+            RenderCommand::MultiDrawIndirect(buffer, offset, count)
+            | RenderCommand::MultiDrawIndexedIndirect(buffer, offset, count) => {
+                format!("{}({}, {}, {})", name, self.buffer(buffer), offset, count)
+            }
+            RenderCommand::BeginOcclusionQuery(index) => format!("{}({})", name, index),
+            RenderCommand::BeginPipelineStatisticsQuery(_, index) => {
+                format!("{}({})", name, index)
+            }
+            RenderCommand::PopDebugGroup
+            | RenderCommand::EndOcclusionQuery
+            | RenderCommand::EndPipelineStatisticsQuery
+            | RenderCommand::ExecuteBundles() => String::from(name),
+        }
+    }
+
+    fn compute(&mut self, cmd: &ComputeCommand) -> String {
+        match cmd {
+            ComputeCommand::SetPipeline(pipeline) => {
+                format!("SetPipeline({})", self.name("compute_pipeline", pipeline))
+            }
+            ComputeCommand::SetBindGroup(slot, bind_group, offsets) => format!(
+                "SetBindGroup({}, {}{})",
+                slot,
+                self.name("bind_group", bind_group),
+                Self::offsets(offsets)
+            ),
+            ComputeCommand::Dispatch(x, y, z) => format!("Dispatch({}, {}, {})", x, y, z),
+            ComputeCommand::DispatchIndirect(buffer, offset) => {
+                format!("DispatchIndirect({}, {})", self.buffer(buffer), offset)
+            }
+        }
+    }
+}
+
+/// A pass's attachments and commands as text, see CommandDump.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PassDump {
+    /// "render" or "compute".
+    pub kind: String,
+    pub label: String,
+    /// Color attachment, e.g. "surface" or "texture 512x512 Rgba16Float".
+    pub target: String,
+    /// Load op and depth attachment, empty for compute passes.
+    pub op: String,
+    pub commands: Vec<String>,
+}
+
+impl PassDump {
+    pub fn render(pass: &RenderPass, labels: &ResourceLabels) -> Self {
+        Self::render_named(pass, &mut Namer::new(labels))
+    }
+
+    fn render_named(pass: &RenderPass, namer: &mut Namer) -> Self {
+        let texture = |handle: &wgpu::Texture| {
+            format!(
+                "{}x{} {:?}",
+                handle.width(),
+                handle.height(),
+                handle.format()
+            )
+        };
+        let target = match &pass.target {
+            RenderTarget::Surface => String::from("surface"),
+            RenderTarget::Texture(t) => format!("texture {}", texture(&t.handle)),
+            RenderTarget::DepthOnly => String::from("depth only"),
+        };
+        let load = match pass.op {
+            RenderPassOp::Clear(c) => format!("clear {}, {}, {}, {}", c.r, c.g, c.b, c.a),
+            RenderPassOp::LoadFromMemory => String::from("load"),
+        };
+        let op = match &pass.depth_texture {
+            Some(depth) => format!("{}, depth {}", load, texture(&depth.handle)),
+            None => format!("{}, no depth", load),
+        };
+        Self {
+            kind: String::from("render"),
+            label: pass.label.clone().unwrap_or_default(),
+            target,
+            op,
+            commands: pass
+                .command_queue
+                .iter()
+                .map(|cmd| namer.render(cmd))
+                .collect(),
+        }
+    }
+
+    fn compute_named(pass: &ComputePass, namer: &mut Namer) -> Self {
+        Self {
+            kind: String::from("compute"),
+            label: pass.label.clone().unwrap_or_default(),
+            target: String::new(),
+            op: String::new(),
+            commands: pass
+                .command_queue
+                .iter()
+                .map(|cmd| namer.compute(cmd))
+                .collect(),
+        }
+    }
+
+    /// What passes are matched by when diffing.
+    fn key(&self) -> String {
+        format!("{} '{}' -> {}", self.kind, self.label, self.target)
+    }
+}
+
+/// Every pass of a frame in submission order with its recorded commands, written before
+/// merge_draws runs. Compare a working and a broken frame with CommandDump::diff, see
+/// RenderWindow::dump_next_frame.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommandDump {
+    pub frame: u64,
+    pub passes: Vec<PassDump>,
+}
+
+impl CommandDump {
+    pub fn record<'a>(
+        frame: u64,
+        labels: &ResourceLabels,
+        compute: impl IntoIterator<Item = &'a ComputePass>,
+        render: impl IntoIterator<Item = &'a RenderPass>,
+    ) -> Self {
+        let mut namer = Namer::new(labels);
+        let compute = compute
+            .into_iter()
+            .map(|pass| PassDump::compute_named(pass, &mut namer))
+            .collect::<Vec<_>>();
+        let render = render
+            .into_iter()
+            .map(|pass| PassDump::render_named(pass, &mut namer))
+            .collect::<Vec<_>>();
+        Self {
+            frame,
+            passes: compute.into_iter().chain(render).collect(),
+        }
+    }
+
+    pub fn command_count(&self) -> usize {
+        self.passes.iter().map(|pass| pass.commands.len()).sum()
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut out = format!("{{\n  \"frame\": {},\n  \"passes\": [", self.frame);
+        for (i, pass) in self.passes.iter().enumerate() {
+            out.push_str(if i == 0 { "\n" } else { ",\n" });
+            out.push_str("    {\n");
+            for (key, value) in [
+                ("kind", &pass.kind),
+                ("label", &pass.label),
+                ("target", &pass.target),
+                ("op", &pass.op),
+            ] {
+                out.push_str(&format!("      \"{}\": {},\n", key, json_string(value)));
+            }
+            out.push_str("      \"commands\": [");
+            for (j, cmd) in pass.commands.iter().enumerate() {
+                out.push_str(if j == 0 { "\n" } else { ",\n" });
+                out.push_str(&format!("        {}", json_string(cmd)));
+            }
+            if !pass.commands.is_empty() {
+                out.push_str("\n      ");
+            }
+            out.push_str("]\n    }");
+        }
+        if !self.passes.is_empty() {
+            out.push_str("\n  ");
+        }
+        out.push_str("]\n}\n");
+        out
+    }
+
+    pub fn from_json(text: &str) -> anyhow::Result<Self> {
+        let mut reader = JsonReader {
+            bytes: text.as_bytes(),
+            pos: 0,
+        };
+        let mut dump = Self::default();
+        reader.object(|reader, key| {
+            match key.as_str() {
+                "frame" => dump.frame = reader.number()? as u64,
+                "passes" => reader.array(|reader| {
+                    let mut pass = PassDump::default();
+                    reader.object(|reader, key| {
+                        match key.as_str() {
+                            "kind" => pass.kind = reader.string()?,
+                            "label" => pass.label = reader.string()?,
+                            "target" => pass.target = reader.string()?,
+                            "op" => pass.op = reader.string()?,
+                            "commands" => reader.array(|reader| {
+                                pass.commands.push(reader.string()?);
+                                Ok(())
+                            })?,
+                            key => bail!("CommandDump::from_json => unknown pass key '{}'", key),
+                        }
+                        Ok(())
+                    })?;
+                    dump.passes.push(pass);
+                    Ok(())
+                })?,
+                key => bail!("CommandDump::from_json => unknown key '{}'", key),
+            }
+            Ok(())
+        })?;
+        Ok(dump)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        write_atomic(path, self.to_json().as_bytes())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("CommandDump::load => {}", path.display()))?;
+        Self::from_json(&text).with_context(|| format!("CommandDump::load => {}", path.display()))
+    }
+
+    /// Changes from `self` to `other`. Passes are matched by kind, label and target,
+    /// commands within matched passes line by line.
+    pub fn diff(&self, other: &CommandDump) -> CommandDiff {
+        let keys = |dump: &CommandDump| dump.passes.iter().map(PassDump::key).collect::<Vec<_>>();
+        let (old_keys, new_keys) = (keys(self), keys(other));
+        let (mut old, mut new) = (self.passes.iter(), other.passes.iter());
+        let passes = diff_lines(&old_keys, &new_keys)
+            .into_iter()
+            .map(|line| match line {
+                DiffLine::Same(key) => {
+                    let (old, new) = (old.next().unwrap(), new.next().unwrap());
+                    PassDiff {
+                        key,
+                        lines: diff_lines(&old.commands, &new.commands),
+                    }
+                }
+                DiffLine::Removed(key) => PassDiff {
+                    key,
+                    lines: old
+                        .next()
+                        .unwrap()
+                        .commands
+                        .iter()
+                        .cloned()
+                        .map(DiffLine::Removed)
+                        .collect(),
+                },
+                DiffLine::Added(key) => PassDiff {
+                    key,
+                    lines: new
+                        .next()
+                        .unwrap()
+                        .commands
+                        .iter()
+                        .cloned()
+                        .map(DiffLine::Added)
+                        .collect(),
+                },
+            })
+            .collect();
+        CommandDiff {
+            frames: (self.frame, other.frame),
+            passes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+impl DiffLine {
+    #[inline]
+    pub fn is_change(&self) -> bool {
+        !matches!(self, Self::Same(_))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassDiff {
+    /// Kind, label and target of the pass.
+    pub key: String,
+    pub lines: Vec<DiffLine>,
+}
+
+impl PassDiff {
+    pub fn has_changes(&self) -> bool {
+        self.lines.iter().any(DiffLine::is_change)
+    }
+}
+
+/// Result of CommandDump::diff, Display prints changed passes with a couple of unchanged
+/// commands around every change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandDiff {
+    pub frames: (u64, u64),
+    pub passes: Vec<PassDiff>,
+}
+
+impl CommandDiff {
+    const CONTEXT: usize = 2;
+
+    pub fn is_empty(&self) -> bool {
+        !self.passes.iter().any(PassDiff::has_changes)
+    }
+}
+
+impl fmt::Display for CommandDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "--- frame {}\n+++ frame {}",
+            self.frames.0, self.frames.1
+        )?;
+        for pass in self.passes.iter().filter(|pass| pass.has_changes()) {
+            writeln!(f, "@@ {}", pass.key)?;
+            let near_change = |i: usize| {
+                let lo = i.saturating_sub(Self::CONTEXT);
+                let hi = (i + Self::CONTEXT + 1).min(pass.lines.len());
+                pass.lines[lo..hi].iter().any(DiffLine::is_change)
+            };
+            let mut skipped = 0;
+            for (i, line) in pass.lines.iter().enumerate() {
+                if !near_change(i) {
+                    skipped += 1;
+                    continue;
+                }
+                if skipped > 0 {
+                    writeln!(f, "  ... {} unchanged", skipped)?;
+                    skipped = 0;
+                }
+                match line {
+                    DiffLine::Same(cmd) => writeln!(f, "  {}", cmd)?,
+                    DiffLine::Removed(cmd) => writeln!(f, "- {}", cmd)?,
+                    DiffLine::Added(cmd) => writeln!(f, "+ {}", cmd)?,
+                }
+            }
+            if skipped > 0 {
+                writeln!(f, "  ... {} unchanged", skipped)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Line diff by longest common subsequence. The common prefix and suffix are trimmed
+/// first, what is left over is only compared if the table stays small, otherwise it is
+/// reported as replaced wholesale.
+pub fn diff_lines(old: &[String], new: &[String]) -> Vec<DiffLine> {
+    const MAX_CELLS: usize = 1 << 22;
+
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut lines: Vec<_> = old[..prefix].iter().cloned().map(DiffLine::Same).collect();
+    if (a.len() + 1) * (b.len() + 1) > MAX_CELLS {
+        lines.extend(a.iter().cloned().map(DiffLine::Removed));
+        lines.extend(b.iter().cloned().map(DiffLine::Added));
+    } else {
+        // lcs[i][j] is the common length of a[i..] and b[j..].
+        let width = b.len() + 1;
+        let mut lcs = vec![0u32; (a.len() + 1) * width];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i * width + j] = if a[i] == b[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                lines.push(DiffLine::Same(a[i].clone()));
+                i += 1;
+                j += 1;
+            } else if i < a.len()
+                && (j == b.len() || lcs[(i + 1) * width + j] >= lcs[i * width + j + 1])
+            {
+                lines.push(DiffLine::Removed(a[i].clone()));
+                i += 1;
+            } else {
+                lines.push(DiffLine::Added(b[j].clone()));
+                j += 1;
+            }
+        }
+    }
+    lines.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .cloned()
+            .map(DiffLine::Same),
+    );
+    lines
+}
+
+pub(crate) fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Just enough JSON to read CommandDump::to_json back: objects, arrays, strings and
+/// numbers.
+struct JsonReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl JsonReader<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> anyhow::Result<()> {
+        match self.peek() {
+            Some(b) if b == byte => {
+                self.pos += 1;
+                Ok(())
+            }
+            found => bail!(
+                "JsonReader => expected '{}' at byte {}, found {:?}",
+                byte as char,
+                self.pos,
+                found.map(|b| b as char)
+            ),
+        }
+    }
+
+    /// Calls `field` with each key, which must consume the value.
+    fn object(
+        &mut self,
+        mut field: impl FnMut(&mut Self, String) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        self.expect(b'{')?;
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(());
+        }
+        loop {
+            let key = self.string()?;
+            self.expect(b':')?;
+            field(self, key)?;
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                _ => return self.expect(b'}'),
+            }
+        }
+    }
+
+    /// Calls `item` for each element, which must consume it.
+    fn array(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        self.expect(b'[')?;
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(());
+        }
+        loop {
+            item(self)?;
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                _ => return self.expect(b']'),
+            }
+        }
+    }
+
+    fn number(&mut self) -> anyhow::Result<f64> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.pos < self.bytes.len()
+            && matches!(
+                self.bytes[self.pos],
+                b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'
+            )
+        {
+            self.pos += 1;
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.pos])?;
+        text.parse()
+            .with_context(|| format!("JsonReader => bad number '{}' at byte {}", text, start))
+    }
+
+    fn string(&mut self) -> anyhow::Result<String> {
+        self.expect(b'"')?;
+        let mut out = Vec::new();
+        loop {
+            let Some(&b) = self.bytes.get(self.pos) else {
+                bail!("JsonReader => unterminated string");
+            };
+            self.pos += 1;
+            match b {
+                b'"' => break,
+                b'\\' => {
+                    let Some(&escape) = self.bytes.get(self.pos) else {
+                        bail!("JsonReader => unterminated string");
+                    };
+                    self.pos += 1;
+                    match escape {
+                        b'"' | b'\\' | b'/' => out.push(escape),
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'b' => out.push(0x08),
+                        b'f' => out.push(0x0c),
+                        b'u' => {
+                            let hex = self
+                                .bytes
+                                .get(self.pos..self.pos + 4)
+                                .context("JsonReader => short \\u escape")?;
+                            self.pos += 4;
+                            let code = u32::from_str_radix(std::str::from_utf8(hex)?, 16)?;
+                            let c = char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER);
+                            out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        }
+                        other => bail!("JsonReader => bad escape '\\{}'", other as char),
+                    }
+                }
+                b => out.push(b),
+            }
+        }
+        Ok(String::from_utf8(out)?)
+    }
+}
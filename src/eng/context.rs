@@ -0,0 +1,215 @@
+use std::{
+    cell::{Cell, RefCell},
+    time::Duration,
+};
+
+#[cfg(feature = "audio")]
+use super::audio::Audio;
+#[cfg(feature = "audio-output")]
+use super::audio::output::AudioOutput;
+#[cfg(feature = "scripting")]
+use super::script::ScriptHost;
+use super::{
+    determinism::{DeterminismAudit, Divergence, TickRecorder},
+    input::shortcut::Shortcuts,
+    tasks::Tasks,
+};
+
+/// Engine wide controls shared between the main loop and the app. Handed out as
+/// Rc<EngineContext>, see RenderWindow::engine.
+#[derive(Debug)]
+pub struct EngineContext {
+    time_scale: Cell<f32>,
+    paused: Cell<bool>,
+    pending_steps: Cell<u32>,
+    fixed_timestep: Cell<Duration>,
+    accumulator: Cell<Duration>,
+    frame_count: Cell<u64>,
+    tasks: Tasks,
+    #[cfg(feature = "audio")]
+    audio: Audio,
+    #[cfg(feature = "audio-output")]
+    audio_output: RefCell<Option<AudioOutput>>,
+    audit: RefCell<Option<DeterminismAudit>>,
+    shortcuts: RefCell<Shortcuts>,
+    #[cfg(feature = "scripting")]
+    scripts: RefCell<ScriptHost>,
+}
+
+impl Default for EngineContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EngineContext {
+    pub const DEFAULT_FIXED_TIMESTEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+    /// Upper bound on fixed updates per frame so a long hitch can't spiral.
+    pub const MAX_FIXED_STEPS: u32 = 8;
+
+    pub fn new() -> Self {
+        Self {
+            time_scale: Cell::new(1.0),
+            paused: Cell::new(false),
+            pending_steps: Cell::new(0),
+            fixed_timestep: Cell::new(Self::DEFAULT_FIXED_TIMESTEP),
+            accumulator: Cell::new(Duration::ZERO),
+            frame_count: Cell::new(0),
+            tasks: Tasks::default(),
+            #[cfg(feature = "audio")]
+            audio: Audio::default(),
+            #[cfg(feature = "audio-output")]
+            audio_output: RefCell::new(None),
+            audit: RefCell::new(None),
+            shortcuts: RefCell::new(Shortcuts::new()),
+            #[cfg(feature = "scripting")]
+            scripts: RefCell::new(ScriptHost::new()),
+        }
+    }
+
+    #[inline]
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale.get()
+    }
+
+    /// Multiplier applied to the dt passed to frame_update and fixed_update, negative values are clamped to 0.
+    pub fn set_time_scale(&self, scale: f32) {
+        self.time_scale.set(scale.max(0.0));
+    }
+
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.paused.get()
+    }
+
+    /// Pausing stops gameplay updates, rendering and window/input handling keep running.
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.set(paused);
+        if !paused {
+            self.pending_steps.set(0);
+        }
+    }
+
+    pub fn toggle_pause(&self) {
+        self.set_paused(!self.is_paused());
+    }
+
+    /// While paused, runs a single update of one fixed timestep on the next frame.
+    pub fn step(&self) {
+        if self.is_paused() {
+            self.pending_steps.set(self.pending_steps.get() + 1);
+        }
+    }
+
+    #[inline]
+    pub fn fixed_timestep(&self) -> Duration {
+        self.fixed_timestep.get()
+    }
+
+    pub fn set_fixed_timestep(&self, timestep: Duration) {
+        if !timestep.is_zero() {
+            self.fixed_timestep.set(timestep);
+        }
+    }
+
+    /// Number of frames the main loop has run.
+    #[inline]
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count.get()
+    }
+
+    /// Async work whose results are delivered on the main thread, see Tasks.
+    #[inline]
+    pub fn tasks(&self) -> &Tasks {
+        &self.tasks
+    }
+
+    /// Key chords matched before key events reach the app, see Shortcuts.
+    #[inline]
+    pub fn shortcuts(&self) -> &RefCell<Shortcuts> {
+        &self.shortcuts
+    }
+
+    /// Loaded scripts and playing cutscenes, updated by the main loop, see ScriptHost.
+    #[cfg(feature = "scripting")]
+    #[inline]
+    pub fn scripts(&self) -> &RefCell<ScriptHost> {
+        &self.scripts
+    }
+
+    /// Shared software mixer, its listener follows the camera of the RenderWindow.
+    #[cfg(feature = "audio")]
+    #[inline]
+    pub fn audio(&self) -> &Audio {
+        &self.audio
+    }
+
+    /// Starts playing the mixer on the default output device, replacing any output opened
+    /// before. Radium::start calls it when the window opens, a failure leaves the game
+    /// running silently.
+    #[cfg(feature = "audio-output")]
+    pub fn open_audio_output(&self) -> anyhow::Result<()> {
+        *self.audio_output.borrow_mut() = None;
+        *self.audio_output.borrow_mut() = Some(AudioOutput::open(&self.audio)?);
+        Ok(())
+    }
+
+    /// The stream opened by EngineContext::open_audio_output, None while nothing plays.
+    #[cfg(feature = "audio-output")]
+    pub fn audio_output(&self) -> std::cell::Ref<'_, Option<AudioOutput>> {
+        self.audio_output.borrow()
+    }
+
+    /// Starts or stops the determinism audit mode, returning the previous audit so its
+    /// hashes can be saved.
+    pub fn set_determinism_audit(
+        &self,
+        audit: Option<DeterminismAudit>,
+    ) -> Option<DeterminismAudit> {
+        self.audit.replace(audit)
+    }
+
+    #[inline]
+    pub fn is_auditing(&self) -> bool {
+        self.audit.borrow().is_some()
+    }
+
+    /// Records this fixed tick's state hashes when auditing, does nothing otherwise so
+    /// calls can stay in fixed_update. See DeterminismAudit::record.
+    pub fn audit_tick(&self, f: impl FnOnce(&mut TickRecorder)) -> Option<Divergence> {
+        self.audit.borrow_mut().as_mut()?.record(f)
+    }
+
+    /// Called by the main loop once per frame with the real frame time. Returns the dt to
+    /// update the app with, or None when paused and no step was requested. Callbacks of
+    /// finished tasks run first, also while paused.
+    pub fn begin_frame(&self, dt: Duration) -> Option<Duration> {
+        self.frame_count.set(self.frame_count.get() + 1);
+        self.tasks.poll();
+        if self.is_paused() {
+            let steps = self.pending_steps.get();
+            if steps == 0 {
+                return None;
+            }
+            self.pending_steps.set(steps - 1);
+            return Some(self.fixed_timestep());
+        }
+        Some(dt.mul_f32(self.time_scale()))
+    }
+
+    /// Accumulates a scaled dt and returns how many fixed updates to run this frame.
+    pub fn fixed_steps(&self, dt: Duration) -> u32 {
+        let timestep = self.fixed_timestep();
+        let mut acc = self.accumulator.get() + dt;
+        let mut steps = 0;
+        while acc >= timestep && steps < Self::MAX_FIXED_STEPS {
+            acc -= timestep;
+            steps += 1;
+        }
+        if steps == Self::MAX_FIXED_STEPS {
+            acc = acc.min(timestep);
+        }
+        self.accumulator.set(acc);
+        steps
+    }
+}
@@ -0,0 +1,409 @@
+use std::fmt;
+
+use anyhow::ensure;
+use cgmath::{Quaternion, Vector2, Vector3};
+
+use crate::{
+    gfx::transform::Transform,
+    sys::{
+        fs::write_atomic,
+        pack::{write_string, ByteReader},
+        rand::{Rng, RngStreams},
+    },
+};
+
+pub const AUDIT_MAGIC: [u8; 4] = *b"RDET";
+pub const AUDIT_VERSION: u32 = 1;
+
+/// FNV-1a over little endian bytes. Unlike DefaultHasher its output is stable across
+/// platforms and Rust versions, so hashes from different builds can be compared.
+#[derive(Debug, Clone)]
+pub struct StateHasher {
+    state: u64,
+}
+
+impl Default for StateHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateHasher {
+    const OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01B3;
+
+    pub fn new() -> Self {
+        Self {
+            state: Self::OFFSET,
+        }
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.state = (self.state ^ *b as u64).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    #[inline]
+    pub fn write_u32(&mut self, v: u32) {
+        self.write_bytes(&v.to_le_bytes());
+    }
+
+    #[inline]
+    pub fn write_u64(&mut self, v: u64) {
+        self.write_bytes(&v.to_le_bytes());
+    }
+
+    /// Hashes the bit pattern, with -0.0 folded into 0.0 and every NaN into one value.
+    pub fn write_f32(&mut self, v: f32) {
+        let v = if v == 0.0 {
+            0.0
+        } else if v.is_nan() {
+            f32::NAN
+        } else {
+            v
+        };
+        self.write_u32(v.to_bits());
+    }
+
+    /// Hashes anything implementing DeterministicHash.
+    pub fn add<T: DeterministicHash + ?Sized>(&mut self, value: &T) -> &mut Self {
+        value.hash_state(self);
+        self
+    }
+
+    #[inline]
+    pub fn finish(&self) -> u64 {
+        self.state
+    }
+}
+
+/// Gameplay state that can be hashed for a DeterminismAudit. Only hash what affects the
+/// simulation, caches and render-only data would report false divergences.
+pub trait DeterministicHash {
+    fn hash_state(&self, hasher: &mut StateHasher);
+}
+
+impl DeterministicHash for u32 {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write_u32(*self);
+    }
+}
+
+impl DeterministicHash for u64 {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write_u64(*self);
+    }
+}
+
+impl DeterministicHash for i32 {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write_u32(*self as u32);
+    }
+}
+
+impl DeterministicHash for bool {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write_bytes(&[*self as u8]);
+    }
+}
+
+impl DeterministicHash for f32 {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write_f32(*self);
+    }
+}
+
+impl DeterministicHash for Vector2<f32> {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write_f32(self.x);
+        hasher.write_f32(self.y);
+    }
+}
+
+impl DeterministicHash for Vector3<f32> {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write_f32(self.x);
+        hasher.write_f32(self.y);
+        hasher.write_f32(self.z);
+    }
+}
+
+impl DeterministicHash for Quaternion<f32> {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write_f32(self.s);
+        self.v.hash_state(hasher);
+    }
+}
+
+/// Position, rotation and size, the cached model matrix is left out.
+impl DeterministicHash for Transform {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        self.position().hash_state(hasher);
+        self.rotation().hash_state(hasher);
+        self.size().hash_state(hasher);
+    }
+}
+
+impl DeterministicHash for Rng {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        for s in self.state() {
+            hasher.write_u64(s);
+        }
+    }
+}
+
+/// Streams are hashed in stream order, not the order they were first used in.
+impl DeterministicHash for RngStreams {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write_u64(self.seed());
+        let mut streams: Vec<_> = self.streams().collect();
+        streams.sort_by_key(|(stream, _)| stream.id());
+        for (stream, rng) in streams {
+            hasher.write_u64(stream.id());
+            rng.hash_state(hasher);
+        }
+    }
+}
+
+impl<T: DeterministicHash> DeterministicHash for [T] {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        hasher.write_u64(self.len() as u64);
+        for v in self {
+            v.hash_state(hasher);
+        }
+    }
+}
+
+impl<T: DeterministicHash> DeterministicHash for Vec<T> {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        self.as_slice().hash_state(hasher);
+    }
+}
+
+impl<T: DeterministicHash + ?Sized> DeterministicHash for &T {
+    fn hash_state(&self, hasher: &mut StateHasher) {
+        (**self).hash_state(hasher);
+    }
+}
+
+/// State hashes of one fixed tick, one per named section (e.g. "transforms", "rng").
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TickHash {
+    pub tick: u64,
+    /// Hash of every section, in order.
+    pub hash: u64,
+    pub sections: Vec<(String, u64)>,
+}
+
+/// Builds a TickHash, see DeterminismAudit::record.
+#[derive(Debug, Default)]
+pub struct TickRecorder {
+    sections: Vec<(String, u64)>,
+}
+
+impl TickRecorder {
+    pub fn section<T: DeterministicHash + ?Sized>(&mut self, name: &str, value: &T) -> &mut Self {
+        self.section_with(name, |h| value.hash_state(h))
+    }
+
+    /// Hashes a section built by hand, e.g. from several fields of a physics world.
+    pub fn section_with(&mut self, name: &str, f: impl FnOnce(&mut StateHasher)) -> &mut Self {
+        let mut hasher = StateHasher::new();
+        f(&mut hasher);
+        self.sections.push((String::from(name), hasher.finish()));
+        self
+    }
+}
+
+/// First tick at which two runs disagree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub tick: u64,
+    /// Sections whose hashes differ or that only one run recorded.
+    pub sections: Vec<String>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "diverged at tick {} in [{}]",
+            self.tick,
+            self.sections.join(", ")
+        )
+    }
+}
+
+impl Divergence {
+    fn between(a: &TickHash, b: &TickHash) -> Option<Self> {
+        if a.hash == b.hash && a.sections == b.sections {
+            return None;
+        }
+        let mut sections: Vec<String> = a
+            .sections
+            .iter()
+            .filter(|(name, hash)| !b.sections.iter().any(|(n, h)| n == name && h == hash))
+            .map(|(name, _)| name.clone())
+            .collect();
+        for (name, _) in b.sections.iter() {
+            if !a.sections.iter().any(|(n, _)| n == name) {
+                sections.push(name.clone());
+            }
+        }
+        Some(Self {
+            tick: a.tick,
+            sections,
+        })
+    }
+}
+
+/// Compares two recorded runs tick by tick, ticks only one of them has are skipped.
+pub fn first_divergence(a: &[TickHash], b: &[TickHash]) -> Option<Divergence> {
+    a.iter().find_map(|ta| {
+        b.binary_search_by_key(&ta.tick, |tb| tb.tick)
+            .ok()
+            .and_then(|i| Divergence::between(ta, &b[i]))
+    })
+}
+
+/// Hashes gameplay state every fixed tick to find where two runs (e.g. live and replay, or
+/// two network peers) stop agreeing. Save a run with write and load it as the reference of
+/// the next with with_reference, the first divergent tick is then logged as it happens.
+#[derive(Debug, Default)]
+pub struct DeterminismAudit {
+    tick: u64,
+    max_ticks: Option<usize>,
+    hashes: Vec<TickHash>,
+    reference: Vec<TickHash>,
+    divergence: Option<Divergence>,
+}
+
+impl DeterminismAudit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps only the last `max_ticks` hashes, for long sessions compared live.
+    pub fn with_max_ticks(mut self, max_ticks: usize) -> Self {
+        self.max_ticks = Some(max_ticks.max(1));
+        self
+    }
+
+    /// Compares every recorded tick against a previous run.
+    pub fn with_reference(mut self, reference: Vec<TickHash>) -> Self {
+        self.reference = reference;
+        self
+    }
+
+    /// Index the next record call gets, ticks count from 0.
+    #[inline]
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    #[inline]
+    pub fn hashes(&self) -> &[TickHash] {
+        &self.hashes
+    }
+
+    /// First divergence from the reference found so far.
+    #[inline]
+    pub fn divergence(&self) -> Option<&Divergence> {
+        self.divergence.as_ref()
+    }
+
+    /// Hashes the sections added by `f` as the next tick. Returns the divergence the first
+    /// time the tick doesn't match the reference.
+    pub fn record(&mut self, f: impl FnOnce(&mut TickRecorder)) -> Option<Divergence> {
+        let mut recorder = TickRecorder::default();
+        f(&mut recorder);
+        let mut hasher = StateHasher::new();
+        for (_, hash) in recorder.sections.iter() {
+            hasher.write_u64(*hash);
+        }
+        let entry = TickHash {
+            tick: self.tick,
+            hash: hasher.finish(),
+            sections: recorder.sections,
+        };
+        self.tick += 1;
+
+        let found = match self.divergence {
+            Some(_) => None,
+            None => self
+                .reference
+                .binary_search_by_key(&entry.tick, |r| r.tick)
+                .ok()
+                .and_then(|i| Divergence::between(&entry, &self.reference[i])),
+        };
+        if let Some(divergence) = found.as_ref() {
+            log::warn!("DeterminismAudit => {}", divergence);
+            self.divergence = Some(divergence.clone());
+        }
+
+        self.hashes.push(entry);
+        if let Some(max) = self.max_ticks {
+            if self.hashes.len() > max {
+                let excess = self.hashes.len() - max;
+                self.hashes.drain(..excess);
+            }
+        }
+        found
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&AUDIT_MAGIC);
+        out.extend_from_slice(&AUDIT_VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.hashes.len() as u32).to_le_bytes());
+        for tick in self.hashes.iter() {
+            out.extend_from_slice(&tick.tick.to_le_bytes());
+            out.extend_from_slice(&tick.hash.to_le_bytes());
+            out.extend_from_slice(&(tick.sections.len() as u32).to_le_bytes());
+            for (name, hash) in tick.sections.iter() {
+                write_string(&mut out, name);
+                out.extend_from_slice(&hash.to_le_bytes());
+            }
+        }
+        out
+    }
+
+    /// Reads hashes written by to_bytes, e.g. to pass to with_reference.
+    pub fn hashes_from_bytes(data: &[u8]) -> anyhow::Result<Vec<TickHash>> {
+        let mut r = ByteReader::new(data);
+        ensure!(
+            r.bytes(4)? == AUDIT_MAGIC,
+            "DeterminismAudit::hashes_from_bytes => Not a determinism audit"
+        );
+        let version = r.u32()?;
+        ensure!(
+            version == AUDIT_VERSION,
+            "DeterminismAudit::hashes_from_bytes => Unsupported version {}",
+            version
+        );
+        let mut hashes = Vec::new();
+        for _ in 0..r.u32()? {
+            let tick = r.u64()?;
+            let hash = r.u64()?;
+            let mut sections = Vec::new();
+            for _ in 0..r.u32()? {
+                sections.push((r.string()?, r.u64()?));
+            }
+            hashes.push(TickHash {
+                tick,
+                hash,
+                sections,
+            });
+        }
+        Ok(hashes)
+    }
+
+    pub fn write(&self, path: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
+        write_atomic(path, &self.to_bytes())
+    }
+
+    pub fn read_hashes(path: impl AsRef<std::path::Path>) -> anyhow::Result<Vec<TickHash>> {
+        Self::hashes_from_bytes(&std::fs::read(path)?)
+    }
+}
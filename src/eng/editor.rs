@@ -0,0 +1,312 @@
+use std::{path::Path, rc::Rc};
+
+use cgmath::{InnerSpace, Quaternion, Vector3};
+use winit::{
+    event::{MouseButton, WindowEvent},
+    keyboard::KeyCode,
+};
+
+use crate::gfx::{
+    draw::DrawCtx,
+    gizmo::{Gizmo, GizmoMode, GizmoRenderer, GridRenderer, Ray},
+    wgpu::texture::Texture,
+};
+
+use super::{
+    app::InputEventStatus,
+    context::EngineContext,
+    input::shortcut::{Chord, ShortcutContext},
+    render::RenderWindow,
+    scene::{PropertyValue, Scene},
+};
+
+/// Editor shell toggled with F1. While enabled the game loop is paused, left click selects
+/// entities, the gizmo moves the selection (W/E/R switch translate/rotate/scale) and
+/// Ctrl+S saves the scene back to its RON file. The keys are registered as shortcuts on
+/// the engine, rebind them through EngineContext::shortcuts. The property panel is exposed as rows
+/// for the app's UI to display and edit, see Editor::properties.
+pub struct Editor {
+    engine: Rc<EngineContext>,
+    enabled: bool,
+    /// Pause state of the engine before the editor was enabled, restored when it is disabled.
+    was_paused: bool,
+    scene: Scene,
+    scene_path: Option<std::path::PathBuf>,
+    selected: Option<usize>,
+    gizmo: Gizmo,
+    grid: GridRenderer,
+    gizmo_renderer: GizmoRenderer,
+    cursor: [f32; 2],
+    pressed: bool,
+    was_pressed: bool,
+}
+
+impl Editor {
+    pub const TOGGLE_KEY: KeyCode = KeyCode::F1;
+    pub const TOGGLE: &'static str = "editor.toggle";
+    pub const SAVE: &'static str = "editor.save";
+    pub const TRANSLATE: &'static str = "editor.translate";
+    pub const ROTATE: &'static str = "editor.rotate";
+    pub const SCALE: &'static str = "editor.scale";
+    /// Above gameplay shortcuts on the same keys.
+    const SHORTCUT_PRIORITY: i32 = 10;
+
+    pub fn new(window: &RenderWindow, scene: Scene) -> Self {
+        let format = window.surface_config().format;
+        let depth = Some(Texture::DEPTH_FORMAT);
+        Self::register_shortcuts(window.engine());
+        Self {
+            engine: window.engine().clone(),
+            enabled: false,
+            was_paused: false,
+            scene,
+            scene_path: None,
+            selected: None,
+            gizmo: Gizmo::default(),
+            grid: GridRenderer::new(window.device(), format, depth),
+            gizmo_renderer: GizmoRenderer::new(window.device(), format, depth),
+            cursor: [0.0; 2],
+            pressed: false,
+            was_pressed: false,
+        }
+    }
+
+    /// Loads the scene at `path`, Editor::save writes back to the same file.
+    pub fn open(window: &RenderWindow, path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut editor = Self::new(window, Scene::load(path.as_ref())?);
+        editor.scene_path = Some(path.as_ref().to_path_buf());
+        Ok(editor)
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled == self.enabled {
+            return;
+        }
+        self.enabled = enabled;
+        let mut shortcuts = self.engine.shortcuts().borrow_mut();
+        shortcuts.set_active(ShortcutContext::Editor, enabled);
+        shortcuts.set_active(ShortcutContext::Gameplay, !enabled);
+        drop(shortcuts);
+        if enabled {
+            self.was_paused = self.engine.is_paused();
+            self.engine.set_paused(true);
+        } else {
+            self.engine.set_paused(self.was_paused);
+            self.pressed = false;
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.set_enabled(!self.enabled);
+    }
+
+    #[inline]
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    #[inline]
+    pub fn scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+
+    #[inline]
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selected = index.filter(|i| *i < self.scene.entities.len());
+    }
+
+    #[inline]
+    pub fn gizmo_mut(&mut self) -> &mut Gizmo {
+        &mut self.gizmo
+    }
+
+    /// Saves to the file the scene was opened from.
+    pub fn save(&self) -> anyhow::Result<()> {
+        match &self.scene_path {
+            Some(path) => self.scene.save(path),
+            None => anyhow::bail!("Editor::save => scene was not opened from a file"),
+        }
+    }
+
+    pub fn save_as(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        self.scene.save(path.as_ref())?;
+        self.scene_path = Some(path.as_ref().to_path_buf());
+        Ok(())
+    }
+
+    /// Call first from RadApp::handle_window_events. Returns Processing for events the
+    /// editor consumed, the app should skip them.
+    pub fn handle_window_events(&mut self, event: &WindowEvent) -> InputEventStatus {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = [position.x as f32, position.y as f32];
+                InputEventStatus::Done
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Left,
+                state,
+                ..
+            } if self.enabled => {
+                self.pressed = state.is_pressed();
+                InputEventStatus::Processing
+            }
+            _ => InputEventStatus::Done,
+        }
+    }
+
+    /// Registers the editor's chords on `engine`, the toggle is global and the rest only
+    /// fire while the editor is enabled.
+    fn register_shortcuts(engine: &EngineContext) {
+        let mut shortcuts = engine.shortcuts().borrow_mut();
+        let priority = Self::SHORTCUT_PRIORITY;
+        let toggle = Chord::new(Self::TOGGLE_KEY);
+        shortcuts.register(Self::TOGGLE, toggle, ShortcutContext::Global, priority);
+        let bindings = [
+            (Self::SAVE, Chord::new(KeyCode::KeyS).ctrl()),
+            (Self::TRANSLATE, Chord::new(KeyCode::KeyW)),
+            (Self::ROTATE, Chord::new(KeyCode::KeyE)),
+            (Self::SCALE, Chord::new(KeyCode::KeyR)),
+        ];
+        for (action, chord) in bindings {
+            shortcuts.register(action, chord, ShortcutContext::Editor, priority);
+        }
+    }
+
+    fn handle_shortcut(&mut self, action: &str) {
+        match action {
+            Self::TOGGLE => self.toggle(),
+            Self::TRANSLATE => self.gizmo.mode = GizmoMode::Translate,
+            Self::ROTATE => self.gizmo.mode = GizmoMode::Rotate,
+            Self::SCALE => self.gizmo.mode = GizmoMode::Scale,
+            Self::SAVE => {
+                if let Err(e) = self.save() {
+                    log::warn!("Editor::handle_shortcut => failed to save scene: {:#}", e);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Applies the editor shortcuts fired this frame, runs picking and gizmo interaction,
+    /// then uploads the grid and gizmo for drawing.
+    pub fn update(&mut self, window: &RenderWindow) {
+        let triggered = self.engine.shortcuts().borrow().triggered().to_vec();
+        for action in triggered.iter() {
+            self.handle_shortcut(action);
+        }
+        if !self.enabled {
+            return;
+        }
+        self.scene.update_transforms();
+        let view_proj = window.camera_uniform().view_proj();
+        let eye = window.camera_uniform().view_position();
+        let size = window.size();
+        let Some(ray) = Ray::from_screen(
+            self.cursor,
+            [size.width as f32, size.height as f32],
+            view_proj,
+        ) else {
+            return;
+        };
+
+        let just_pressed = self.pressed && !self.was_pressed;
+        self.was_pressed = self.pressed;
+
+        let on_gizmo = match self.selected {
+            Some(i) => {
+                let target = &mut self.scene.entities[i].transform;
+                self.gizmo.update(&ray, eye, self.pressed, target)
+            }
+            None => false,
+        };
+        if just_pressed && !on_gizmo {
+            self.selected = self.scene.pick(&ray);
+        }
+
+        let queue = window.device_queue();
+        self.grid.update(queue, view_proj, eye);
+        if let Some(i) = self.selected {
+            let target = &self.scene.entities[i].transform;
+            self.gizmo_renderer
+                .update(queue, &self.gizmo, target, view_proj, eye);
+        }
+    }
+
+    /// Draws the grid and the selection's gizmo into the current pass.
+    pub fn draw(&self, ctx: &mut DrawCtx) {
+        if !self.enabled {
+            return;
+        }
+        self.grid.draw(ctx);
+        if let Some(entity) = self.selected.map(|i| &self.scene.entities[i]) {
+            ctx.push_debug_scope(entity);
+            self.gizmo_renderer.draw(ctx);
+            ctx.pop_debug_scope();
+        }
+    }
+
+    /// Rows of the property panel for the selection: name, position, rotation (the
+    /// quaternion's vector part) and size, followed by the entity's component properties.
+    pub fn properties(&self) -> Vec<(String, PropertyValue)> {
+        let Some(entity) = self.selected.map(|i| &self.scene.entities[i]) else {
+            return Vec::new();
+        };
+        let t = &entity.transform;
+        let rotation = t.rotation();
+        let mut rows = vec![
+            ("name".to_string(), PropertyValue::Text(entity.name.clone())),
+            (
+                "position".to_string(),
+                PropertyValue::Vec3(t.position().into()),
+            ),
+            (
+                "rotation".to_string(),
+                PropertyValue::Vec3(rotation.v.into()),
+            ),
+            ("size".to_string(), PropertyValue::Vec3(t.size().into())),
+        ];
+        rows.extend(entity.properties.iter().cloned());
+        rows
+    }
+
+    /// Applies an edit from the property panel to the selection.
+    pub fn set_property(&mut self, name: &str, value: PropertyValue) -> anyhow::Result<()> {
+        let Some(i) = self.selected else {
+            anyhow::bail!("Editor::set_property => nothing selected");
+        };
+        let entity = &mut self.scene.entities[i];
+        match (name, value) {
+            ("name", PropertyValue::Text(text)) => entity.name = text,
+            ("position", PropertyValue::Vec3(v)) => entity.transform.set_position(v.into()),
+            ("rotation", PropertyValue::Vec3(v)) => {
+                // Rebuild w so the quaternion stays normalized.
+                let v = Vector3::from(v);
+                let w = (1.0 - v.magnitude2()).max(0.0).sqrt();
+                entity
+                    .transform
+                    .set_rotation(Quaternion::from_sv(w, v).normalize());
+            }
+            ("size", PropertyValue::Vec3(v)) => entity.transform.set_size(v.into()),
+            (name, value) => {
+                let Some((_, current)) = entity.properties.iter_mut().find(|(n, _)| n == name)
+                else {
+                    anyhow::bail!("Editor::set_property => unknown property {}", name);
+                };
+                if std::mem::discriminant(current) != std::mem::discriminant(&value) {
+                    anyhow::bail!("Editor::set_property => type mismatch for {}", name);
+                }
+                *current = value;
+            }
+        }
+        Ok(())
+    }
+}
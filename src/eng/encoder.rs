@@ -0,0 +1,98 @@
+use std::cell::{Cell, RefCell};
+
+/// Counters for the command buffers submitted during one frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubmissionStats {
+    pub submissions: u32,
+    pub command_buffers: u32,
+    pub passes: u32,
+    pub commands: u32,
+    /// Draws folded into the preceding draw by merge_draws.
+    pub merged_draws: u32,
+}
+
+/// Creates labeled command encoders and batches their finished command buffers until they
+/// are submitted together. wgpu encoders are single use, so what is reused is the pending
+/// list and the frame's bookkeeping. Lives on DeviceSurface, see DeviceSurface::encoders.
+#[derive(Debug, Default)]
+pub struct EncoderPool {
+    pending: RefCell<Vec<wgpu::CommandBuffer>>,
+    max_passes_per_submission: Cell<Option<usize>>,
+    stats: Cell<SubmissionStats>,
+    last_frame: Cell<SubmissionStats>,
+}
+
+impl EncoderPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn create(&self, device: &wgpu::Device, label: &str) -> wgpu::CommandEncoder {
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some(label) })
+    }
+
+    /// Finishes `encoder` and queues its command buffer for the next flush.
+    pub fn finish(&self, encoder: wgpu::CommandEncoder) {
+        self.pending.borrow_mut().push(encoder.finish());
+    }
+
+    #[inline]
+    pub fn pending(&self) -> usize {
+        self.pending.borrow().len()
+    }
+
+    /// Submits every pending command buffer in one call, None if nothing was pending.
+    pub fn flush(&self, queue: &wgpu::Queue) -> Option<wgpu::SubmissionIndex> {
+        let buffers = std::mem::take(&mut *self.pending.borrow_mut());
+        if buffers.is_empty() {
+            return None;
+        }
+        let mut stats = self.stats.get();
+        stats.submissions += 1;
+        stats.command_buffers += buffers.len() as u32;
+        self.stats.set(stats);
+        Some(queue.submit(buffers))
+    }
+
+    /// Counts a pass with `commands` render commands towards this frame's stats.
+    pub fn record_pass(&self, commands: usize) {
+        let mut stats = self.stats.get();
+        stats.passes += 1;
+        stats.commands += commands as u32;
+        self.stats.set(stats);
+    }
+
+    /// Counts draws removed by merge_draws towards this frame's stats.
+    pub fn record_merged_draws(&self, merged: u32) {
+        let mut stats = self.stats.get();
+        stats.merged_draws += merged;
+        self.stats.set(stats);
+    }
+
+    /// Splits a frame into several submissions of at most `max` passes each, so the GPU can
+    /// start on heavy frames earlier. None submits the whole frame at once.
+    pub fn set_max_passes_per_submission(&self, max: Option<usize>) {
+        self.max_passes_per_submission.set(max.map(|m| m.max(1)));
+    }
+
+    #[inline]
+    pub fn max_passes_per_submission(&self) -> Option<usize> {
+        self.max_passes_per_submission.get()
+    }
+
+    /// Stats recorded since the last end_frame.
+    #[inline]
+    pub fn stats(&self) -> SubmissionStats {
+        self.stats.get()
+    }
+
+    /// Stats of the last completed frame.
+    #[inline]
+    pub fn last_frame_stats(&self) -> SubmissionStats {
+        self.last_frame.get()
+    }
+
+    pub fn end_frame(&self) {
+        self.last_frame.set(self.stats.take());
+    }
+}
@@ -0,0 +1,133 @@
+use std::cell::{Cell, RefCell};
+
+/// Tracks which frame slot the CPU is recording and the last GPU submission of every slot,
+/// so per frame resources aren't overwritten while the GPU may still read them.
+/// Lives on DeviceSurface, see DeviceSurface::frames.
+#[derive(Debug)]
+pub struct FramePacing {
+    slot: Cell<usize>,
+    frame_index: Cell<u64>,
+    submissions: RefCell<Vec<Option<wgpu::SubmissionIndex>>>,
+}
+
+impl Default for FramePacing {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_FRAMES_IN_FLIGHT)
+    }
+}
+
+impl FramePacing {
+    /// Double buffered by default, the CPU records one frame while the GPU renders the last.
+    pub const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
+    pub const MAX_FRAMES_IN_FLIGHT: usize = 3;
+
+    pub fn new(frames_in_flight: usize) -> Self {
+        let count = frames_in_flight.clamp(1, Self::MAX_FRAMES_IN_FLIGHT);
+        Self {
+            slot: Cell::new(0),
+            frame_index: Cell::new(0),
+            submissions: RefCell::new(vec![None; count]),
+        }
+    }
+
+    #[inline]
+    pub fn frames_in_flight(&self) -> usize {
+        self.submissions.borrow().len()
+    }
+
+    /// Changes how many frames the CPU may run ahead of the GPU, clamped to 1..=3.
+    /// 1 waits for the GPU every frame for the lowest latency, 3 is triple buffering.
+    pub fn set_frames_in_flight(&self, frames_in_flight: usize) {
+        let count = frames_in_flight.clamp(1, Self::MAX_FRAMES_IN_FLIGHT);
+        let mut submissions = self.submissions.borrow_mut();
+        submissions.resize(count, None);
+        if self.slot.get() >= count {
+            self.slot.set(0);
+        }
+    }
+
+    /// Slot of the frame being recorded, index into a FrameRing.
+    #[inline]
+    pub fn slot(&self) -> usize {
+        self.slot.get()
+    }
+
+    /// Number of frames begun so far.
+    #[inline]
+    pub fn frame_index(&self) -> u64 {
+        self.frame_index.get()
+    }
+
+    /// Moves to the next slot and returns the submission that last used it, which has to
+    /// finish before the slot's resources can be written again.
+    pub fn advance(&self) -> Option<wgpu::SubmissionIndex> {
+        let count = self.frames_in_flight();
+        if self.frame_index.get() > 0 {
+            self.slot.set((self.slot.get() + 1) % count);
+        }
+        self.frame_index.set(self.frame_index.get() + 1);
+        self.submissions.borrow_mut()[self.slot.get()].take()
+    }
+
+    /// Starts a frame, blocking until the GPU is done with the frame that last used the slot.
+    pub fn begin_frame(&self, device: &wgpu::Device) {
+        if let Some(submission) = self.advance() {
+            let wait = wgpu::PollType::Wait {
+                submission_index: Some(submission),
+                timeout: None,
+            };
+            if let Err(e) = device.poll(wait) {
+                log::warn!("FramePacing::begin_frame => device poll failed: {e}");
+            }
+        }
+    }
+
+    /// Records a queue submission for the current slot, the last one of a frame is waited on.
+    pub fn record_submission(&self, submission: wgpu::SubmissionIndex) {
+        self.submissions.borrow_mut()[self.slot.get()] = Some(submission);
+    }
+}
+
+/// One T per frame slot, e.g. uniform or staging buffers that are rewritten every frame.
+#[derive(Debug, Clone)]
+pub struct FrameRing<T> {
+    items: Vec<T>,
+}
+
+impl<T> FrameRing<T> {
+    /// Creates `count` items, `create` receives the slot index.
+    pub fn new(count: usize, create: impl FnMut(usize) -> T) -> Self {
+        Self {
+            items: (0..count.max(1)).map(create).collect(),
+        }
+    }
+
+    /// Creates one item per frame in flight of `pacing`.
+    pub fn for_pacing(pacing: &FramePacing, create: impl FnMut(usize) -> T) -> Self {
+        Self::new(pacing.frames_in_flight(), create)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Item of the frame being recorded. Wraps if the ring is smaller than the frames in flight.
+    pub fn current(&self, pacing: &FramePacing) -> &T {
+        &self.items[pacing.slot() % self.items.len()]
+    }
+
+    pub fn current_mut(&mut self, pacing: &FramePacing) -> &mut T {
+        let len = self.items.len();
+        &mut self.items[pacing.slot() % len]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.items.iter()
+    }
+}
@@ -0,0 +1,427 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+};
+
+use crate::{gfx::wgpu::texture::Texture, sys::fs::write_atomic};
+
+use super::{
+    command::{ComputeCommand, ComputePass, RenderCommand, RenderPass, RenderPassOp, RenderTarget},
+    command_dump::{json_string, ResourceLabels},
+};
+
+/// A texture, the surface or a buffer or bind group passes of a FrameGraph use.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphResource {
+    /// Unique within the graph, e.g. "surface", "texture#0" or "bind_group 'Shadows'".
+    pub name: String,
+    /// "surface", "texture", "depth", "buffer" or "bind_group".
+    pub kind: String,
+    /// "1280x720" for textures, "256 B" for buffers, empty for bind groups.
+    pub size: String,
+    /// Texture format, empty for buffers and bind groups.
+    pub format: String,
+}
+
+/// One pass of a FrameGraph with the names of the resources it touches.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GraphPass {
+    /// "render" or "compute".
+    pub kind: String,
+    pub label: String,
+    /// "clear" or "load" for render passes, empty for compute passes.
+    pub op: String,
+    /// Attachments loaded from memory, labeled vertex and index buffers and indirect buffers.
+    pub reads: Vec<String>,
+    /// Color and depth attachments.
+    pub writes: Vec<String>,
+    /// Labeled bind groups, wgpu doesn't say which of their bindings are read or written.
+    /// Unlabeled ones are left out so material bind groups don't swamp the graph.
+    pub bindings: Vec<String>,
+}
+
+/// Passes of a frame in submission order and the resources they read and write, exported
+/// to DOT or JSON whenever it changes, see RenderWindow::export_frame_graph. Per frame data
+/// like draw counts is left out so the graph only changes with the pass structure.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameGraph {
+    pub passes: Vec<GraphPass>,
+    pub resources: Vec<GraphResource>,
+}
+
+impl FrameGraph {
+    /// Builds the graph of a frame, `surface` is the size and format of the window surface.
+    pub fn record<'a>(
+        labels: &ResourceLabels,
+        surface: ([u32; 2], wgpu::TextureFormat),
+        compute: impl IntoIterator<Item = &'a ComputePass>,
+        render: impl IntoIterator<Item = &'a RenderPass>,
+    ) -> Self {
+        let mut builder = GraphBuilder {
+            labels,
+            surface,
+            graph: FrameGraph::default(),
+            names: HashMap::new(),
+            counts: HashMap::new(),
+        };
+        for pass in compute {
+            builder.compute(pass);
+        }
+        for pass in render {
+            builder.render(pass);
+        }
+        builder.graph
+    }
+
+    pub fn resource(&self, name: &str) -> Option<&GraphResource> {
+        self.resources.iter().find(|r| r.name == name)
+    }
+
+    /// GraphViz source, passes are boxes in submission order and resources ellipses.
+    /// Reads point into a pass, writes out of it and bind groups are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut out =
+            String::from("digraph frame {\n  rankdir=LR;\n  node [fontname=\"monospace\"];\n");
+        for (i, pass) in self.passes.iter().enumerate() {
+            let title = format!("{}: {}", i, pass.label);
+            let kind = format!("{} {}", pass.kind, pass.op);
+            out.push_str(&format!(
+                "  pass{} [shape=box, style=filled, fillcolor=\"{}\", label={}];\n",
+                i,
+                if pass.kind == "compute" {
+                    "#ffe0b0"
+                } else {
+                    "#c8e0ff"
+                },
+                dot_label(&[&title, kind.trim_end()])
+            ));
+        }
+        for (i, resource) in self.resources.iter().enumerate() {
+            let details = format!("{} {}", resource.size, resource.format);
+            out.push_str(&format!(
+                "  res{} [shape=ellipse, label={}];\n",
+                i,
+                dot_label(&[&resource.name, details.trim()])
+            ));
+        }
+        let index: HashMap<&str, usize> = self
+            .resources
+            .iter()
+            .enumerate()
+            .map(|(i, r)| (r.name.as_str(), i))
+            .collect();
+        for (i, pass) in self.passes.iter().enumerate() {
+            for name in pass.reads.iter() {
+                out.push_str(&format!("  res{} -> pass{};\n", index[name.as_str()], i));
+            }
+            for name in pass.writes.iter() {
+                out.push_str(&format!(
+                    "  pass{} -> res{} [penwidth=2];\n",
+                    i,
+                    index[name.as_str()]
+                ));
+            }
+            for name in pass.bindings.iter() {
+                out.push_str(&format!(
+                    "  res{} -> pass{} [style=dashed, dir=none];\n",
+                    index[name.as_str()],
+                    i
+                ));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let strings = |values: &[String]| {
+            values
+                .iter()
+                .map(|v| json_string(v))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut out = String::from("{\n  \"passes\": [");
+        for (i, pass) in self.passes.iter().enumerate() {
+            out.push_str(if i == 0 { "\n" } else { ",\n" });
+            out.push_str(&format!(
+                "    {{\"kind\": {}, \"label\": {}, \"op\": {}, \"reads\": [{}], \"writes\": [{}], \"bindings\": [{}]}}",
+                json_string(&pass.kind),
+                json_string(&pass.label),
+                json_string(&pass.op),
+                strings(&pass.reads),
+                strings(&pass.writes),
+                strings(&pass.bindings)
+            ));
+        }
+        out.push_str(if self.passes.is_empty() {
+            "],\n"
+        } else {
+            "\n  ],\n"
+        });
+        out.push_str("  \"resources\": [");
+        for (i, resource) in self.resources.iter().enumerate() {
+            out.push_str(if i == 0 { "\n" } else { ",\n" });
+            out.push_str(&format!(
+                "    {{\"name\": {}, \"kind\": {}, \"size\": {}, \"format\": {}}}",
+                json_string(&resource.name),
+                json_string(&resource.kind),
+                json_string(&resource.size),
+                json_string(&resource.format)
+            ));
+        }
+        out.push_str(if self.resources.is_empty() {
+            "]\n}\n"
+        } else {
+            "\n  ]\n}\n"
+        });
+        out
+    }
+
+    /// Writes `path` with the extension replaced by .dot and .json.
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        write_atomic(path.with_extension("dot"), self.to_dot().as_bytes())?;
+        write_atomic(path.with_extension("json"), self.to_json().as_bytes())
+    }
+}
+
+struct GraphBuilder<'a> {
+    labels: &'a ResourceLabels,
+    surface: ([u32; 2], wgpu::TextureFormat),
+    graph: FrameGraph,
+    names: HashMap<usize, String>,
+    counts: HashMap<&'static str, usize>,
+}
+
+impl GraphBuilder<'_> {
+    /// Name of the resource at `key`, added to the graph by `describe` on first use.
+    fn resource(
+        &mut self,
+        key: usize,
+        kind: &'static str,
+        label: Option<String>,
+        describe: impl FnOnce() -> (String, String),
+    ) -> String {
+        if let Some(name) = self.names.get(&key) {
+            return name.clone();
+        }
+        let name = match label {
+            Some(label) => format!("{} '{}'", kind, label),
+            None => {
+                let count = self.counts.entry(kind).or_insert(0);
+                *count += 1;
+                format!("{}#{}", kind, *count - 1)
+            }
+        };
+        let (size, format) = describe();
+        self.graph.resources.push(GraphResource {
+            name: name.clone(),
+            kind: String::from(kind),
+            size,
+            format,
+        });
+        self.names.insert(key, name.clone());
+        name
+    }
+
+    fn texture(&mut self, texture: &Rc<Texture>) -> String {
+        let handle = &texture.handle;
+        let kind = if handle.format().is_depth_stencil_format() {
+            "depth"
+        } else {
+            "texture"
+        };
+        self.resource(Rc::as_ptr(texture) as usize, kind, None, || {
+            (
+                format!("{}x{}", handle.width(), handle.height()),
+                format!("{:?}", handle.format()),
+            )
+        })
+    }
+
+    fn surface(&mut self) -> String {
+        let name = String::from("surface");
+        // No resource lives at address 0, so it can't collide with a real key.
+        if self.names.insert(0, name.clone()).is_none() {
+            let ([width, height], format) = self.surface;
+            self.graph.resources.push(GraphResource {
+                name: name.clone(),
+                kind: name.clone(),
+                size: format!("{}x{}", width, height),
+                format: format!("{:?}", format),
+            });
+        }
+        name
+    }
+
+    fn buffer(&mut self, buffer: &Arc<wgpu::Buffer>, labeled_only: bool) -> Option<String> {
+        let label = self.labels.get(buffer);
+        if labeled_only && label.is_none() {
+            return None;
+        }
+        let size = buffer.size();
+        Some(self.resource(key(buffer), "buffer", label, || {
+            (format!("{} B", size), String::new())
+        }))
+    }
+
+    fn bind_group(&mut self, bind_group: &Arc<wgpu::BindGroup>) -> Option<String> {
+        let label = self.labels.get(bind_group)?;
+        Some(
+            self.resource(key(bind_group), "bind_group", Some(label), || {
+                (String::new(), String::new())
+            }),
+        )
+    }
+
+    fn render(&mut self, pass: &RenderPass) {
+        let mut reads = Vec::new();
+        let mut writes = Vec::new();
+        let mut bindings = Vec::new();
+        let target = match &pass.target {
+            RenderTarget::Surface => Some(self.surface()),
+            RenderTarget::Texture(texture) => Some(self.texture(texture)),
+            RenderTarget::DepthOnly => None,
+        };
+        if let Some(target) = target {
+            if let RenderPassOp::LoadFromMemory = pass.op {
+                push_unique(&mut reads, target.clone());
+            }
+            push_unique(&mut writes, target);
+        }
+        if let Some(depth) = &pass.depth_texture {
+            let depth = self.texture(depth);
+            push_unique(&mut writes, depth);
+        }
+        for cmd in pass.command_queue.iter() {
+            let read = match cmd {
+                RenderCommand::SetBindGroup(_, bind_group, _) => {
+                    if let Some(name) = self.bind_group(bind_group) {
+                        push_unique(&mut bindings, name);
+                    }
+                    None
+                }
+                RenderCommand::SetVertexBuffer(_, buffer)
+                | RenderCommand::SetIndexBuffer(buffer, _) => self.buffer(buffer, true),
+                RenderCommand::DrawIndirect(buffer, _)
+                | RenderCommand::DrawIndexedIndirect(buffer, _)
+                | RenderCommand::MultiDrawIndirect(buffer, _, _)
+                | RenderCommand::MultiDrawIndexedIndirect(buffer, _, _) => {
+                    self.buffer(buffer, false)
+                }
+                _ => None,
+            };
+            if let Some(name) = read {
+                push_unique(&mut reads, name);
+            }
+        }
+        self.graph.passes.push(GraphPass {
+            kind: String::from("render"),
+            label: pass.label.clone().unwrap_or_default(),
+            op: String::from(match pass.op {
+                RenderPassOp::Clear(_) => "clear",
+                RenderPassOp::LoadFromMemory => "load",
+            }),
+            reads,
+            writes,
+            bindings,
+        });
+    }
+
+    fn compute(&mut self, pass: &ComputePass) {
+        let mut reads = Vec::new();
+        let mut bindings = Vec::new();
+        for cmd in pass.command_queue.iter() {
+            match cmd {
+                ComputeCommand::SetBindGroup(_, bind_group, _) => {
+                    if let Some(name) = self.bind_group(bind_group) {
+                        push_unique(&mut bindings, name);
+                    }
+                }
+                ComputeCommand::DispatchIndirect(buffer, _) => {
+                    if let Some(name) = self.buffer(buffer, false) {
+                        push_unique(&mut reads, name);
+                    }
+                }
+                ComputeCommand::SetPipeline(_) | ComputeCommand::Dispatch(..) => {}
+            }
+        }
+        self.graph.passes.push(GraphPass {
+            kind: String::from("compute"),
+            label: pass.label.clone().unwrap_or_default(),
+            op: String::new(),
+            reads,
+            writes: Vec::new(),
+            bindings,
+        });
+    }
+}
+
+fn key<T: Any>(resource: &Arc<T>) -> usize {
+    Arc::as_ptr(resource) as *const () as usize
+}
+
+fn push_unique(names: &mut Vec<String>, name: String) {
+    if !names.contains(&name) {
+        names.push(name);
+    }
+}
+
+/// Quoted DOT label of the non empty `lines`.
+fn dot_label(lines: &[&str]) -> String {
+    let lines = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.replace('\\', "\\\\").replace('"', "\\\""))
+        .collect::<Vec<_>>();
+    format!("\"{}\"", lines.join("\\n"))
+}
+
+/// Where the frame graph is exported and the last graph written, shared by the window and
+/// every DrawCtx, see RenderWindow::export_frame_graph.
+#[derive(Debug, Default)]
+pub struct FrameGraphExport {
+    path: RefCell<Option<PathBuf>>,
+    last: RefCell<Option<FrameGraph>>,
+}
+
+impl FrameGraphExport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts exporting to `path` with .dot and .json extensions, None stops. The next
+    /// frame is always written.
+    pub fn set_path(&self, path: Option<PathBuf>) {
+        self.path.replace(path);
+        self.last.replace(None);
+    }
+
+    pub fn path(&self) -> Option<PathBuf> {
+        self.path.borrow().clone()
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.path.borrow().is_some()
+    }
+
+    /// Writes `graph` if exporting and it differs from the last one written. Returns true
+    /// if it was written.
+    pub fn update(&self, graph: FrameGraph) -> anyhow::Result<bool> {
+        let Some(path) = self.path() else {
+            return Ok(false);
+        };
+        if self.last.borrow().as_ref() == Some(&graph) {
+            return Ok(false);
+        }
+        graph.save(&path)?;
+        self.last.replace(Some(graph));
+        Ok(true)
+    }
+}
@@ -0,0 +1,3 @@
+pub mod mouse;
+pub mod shortcut;
+pub mod text;
@@ -0,0 +1,341 @@
+use std::time::{Duration, Instant};
+
+use winit::event::{ElementState, WindowEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+}
+
+impl MouseButton {
+    pub const ALL: [MouseButton; 5] = [
+        MouseButton::Left,
+        MouseButton::Right,
+        MouseButton::Middle,
+        MouseButton::Back,
+        MouseButton::Forward,
+    ];
+
+    /// None for the extra buttons winit reports as Other.
+    pub fn from_winit(button: winit::event::MouseButton) -> Option<Self> {
+        Some(match button {
+            winit::event::MouseButton::Left => Self::Left,
+            winit::event::MouseButton::Right => Self::Right,
+            winit::event::MouseButton::Middle => Self::Middle,
+            winit::event::MouseButton::Back => Self::Back,
+            winit::event::MouseButton::Forward => Self::Forward,
+            winit::event::MouseButton::Other(_) => return None,
+        })
+    }
+
+    #[inline]
+    const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Thresholds separating clicks from drags and double clicks from two clicks. The defaults
+/// match the usual desktop settings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClickSettings {
+    /// Physical pixels the cursor may move while pressed before the press becomes a drag.
+    pub drag_threshold: f32,
+    /// Longest time between two presses of a double click.
+    pub double_click_time: Duration,
+    /// Farthest apart in physical pixels two presses of a double click may be.
+    pub double_click_distance: f32,
+}
+
+impl Default for ClickSettings {
+    fn default() -> Self {
+        Self {
+            drag_threshold: 4.0,
+            double_click_time: Duration::from_millis(500),
+            double_click_distance: 4.0,
+        }
+    }
+}
+
+/// Positions are physical window pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseEvent {
+    Pressed {
+        button: MouseButton,
+        position: [f32; 2],
+    },
+    Released {
+        button: MouseButton,
+        position: [f32; 2],
+    },
+    /// Press and release without dragging, `count` is 2 for the second click of a double
+    /// click, 3 for a triple click and so on.
+    Click {
+        button: MouseButton,
+        position: [f32; 2],
+        count: u32,
+    },
+    /// Sent on the second press, like the desktop does.
+    DoubleClick {
+        button: MouseButton,
+        position: [f32; 2],
+    },
+    DragStart {
+        button: MouseButton,
+        origin: [f32; 2],
+    },
+    Drag {
+        button: MouseButton,
+        position: [f32; 2],
+        delta: [f32; 2],
+    },
+    DragEnd {
+        button: MouseButton,
+        origin: [f32; 2],
+        position: [f32; 2],
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ButtonState {
+    down: bool,
+    pressed: bool,
+    released: bool,
+    dragging: bool,
+    press_position: [f32; 2],
+    /// Time, position and click count of the last press, for double clicks.
+    last_press: Option<(Instant, [f32; 2], u32)>,
+    click_count: u32,
+}
+
+/// Per button mouse state fed from winit window events. Events are returned as they
+/// happen and kept until end_frame, the pressed/released flags last for one frame too.
+#[derive(Debug, Clone, Default)]
+pub struct MouseInput {
+    settings: ClickSettings,
+    buttons: [ButtonState; 5],
+    position: [f32; 2],
+    delta: [f32; 2],
+    events: Vec<MouseEvent>,
+}
+
+impl MouseInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_settings(mut self, settings: ClickSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    #[inline]
+    pub fn settings(&self) -> &ClickSettings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: ClickSettings) {
+        self.settings = settings;
+    }
+
+    /// Updates the state from `event`, returning the mouse events it caused.
+    pub fn handle_event(&mut self, event: &WindowEvent) -> Vec<MouseEvent> {
+        self.handle_event_at(event, Instant::now())
+    }
+
+    /// Same as handle_event with the time of the event given, for replays and tests.
+    pub fn handle_event_at(&mut self, event: &WindowEvent, now: Instant) -> Vec<MouseEvent> {
+        let start = self.events.len();
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.handle_cursor([position.x as f32, position.y as f32]);
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                if let Some(button) = MouseButton::from_winit(*button) {
+                    match state {
+                        ElementState::Pressed => self.handle_press(button, now),
+                        ElementState::Released => self.handle_release(button),
+                    }
+                }
+            }
+            // Releases after losing focus never report, so let go of everything.
+            WindowEvent::Focused(false) => {
+                for button in MouseButton::ALL {
+                    self.release(button, false);
+                }
+            }
+            _ => {}
+        }
+        self.events[start..].to_vec()
+    }
+
+    /// Presses `button` at the cursor, a second press close enough in time and space
+    /// continues the click run.
+    pub fn handle_press(&mut self, button: MouseButton, now: Instant) {
+        let position = self.position;
+        let settings = self.settings;
+        let state = &mut self.buttons[button.index()];
+        if state.down {
+            return;
+        }
+        let count = match state.last_press {
+            Some((time, last, count))
+                if now.duration_since(time) <= settings.double_click_time
+                    && distance(last, position) <= settings.double_click_distance =>
+            {
+                count + 1
+            }
+            _ => 1,
+        };
+        *state = ButtonState {
+            down: true,
+            pressed: true,
+            press_position: position,
+            last_press: Some((now, position, count)),
+            click_count: count,
+            ..*state
+        };
+        self.events.push(MouseEvent::Pressed { button, position });
+        if count == 2 {
+            self.events
+                .push(MouseEvent::DoubleClick { button, position });
+        }
+    }
+
+    pub fn handle_release(&mut self, button: MouseButton) {
+        self.release(button, true);
+    }
+
+    /// Ends a press, a Click is only sent for `click` releases that didn't drag.
+    fn release(&mut self, button: MouseButton, click: bool) {
+        let position = self.position;
+        let state = &mut self.buttons[button.index()];
+        if !state.down {
+            return;
+        }
+        state.down = false;
+        state.released = true;
+        self.events.push(MouseEvent::Released { button, position });
+        if std::mem::take(&mut state.dragging) {
+            self.events.push(MouseEvent::DragEnd {
+                button,
+                origin: state.press_position,
+                position,
+            });
+        } else if click {
+            self.events.push(MouseEvent::Click {
+                button,
+                position,
+                count: state.click_count,
+            });
+        }
+    }
+
+    /// Moves the cursor to `position` in physical pixels, starting drags past the threshold.
+    pub fn handle_cursor(&mut self, position: [f32; 2]) {
+        let delta = [
+            position[0] - self.position[0],
+            position[1] - self.position[1],
+        ];
+        self.position = position;
+        self.delta = [self.delta[0] + delta[0], self.delta[1] + delta[1]];
+        for button in MouseButton::ALL {
+            let state = &mut self.buttons[button.index()];
+            if !state.down {
+                continue;
+            }
+            if !state.dragging
+                && distance(state.press_position, position) > self.settings.drag_threshold
+            {
+                state.dragging = true;
+                // A drag never counts towards a double click.
+                state.last_press = None;
+                state.click_count = 0;
+                self.events.push(MouseEvent::DragStart {
+                    button,
+                    origin: state.press_position,
+                });
+            }
+            if state.dragging {
+                self.events.push(MouseEvent::Drag {
+                    button,
+                    position,
+                    delta,
+                });
+            }
+        }
+    }
+
+    /// Clears the per frame flags, delta and events, call once the frame has been updated.
+    pub fn end_frame(&mut self) {
+        for state in self.buttons.iter_mut() {
+            state.pressed = false;
+            state.released = false;
+        }
+        self.delta = [0.0; 2];
+        self.events.clear();
+    }
+
+    /// Events since the last end_frame.
+    #[inline]
+    pub fn events(&self) -> &[MouseEvent] {
+        &self.events
+    }
+
+    #[inline]
+    pub fn position(&self) -> [f32; 2] {
+        self.position
+    }
+
+    /// Cursor movement since the last end_frame.
+    #[inline]
+    pub fn delta(&self) -> [f32; 2] {
+        self.delta
+    }
+
+    #[inline]
+    pub fn is_down(&self, button: MouseButton) -> bool {
+        self.buttons[button.index()].down
+    }
+
+    pub fn any_down(&self) -> bool {
+        self.buttons.iter().any(|b| b.down)
+    }
+
+    /// Pressed since the last end_frame.
+    #[inline]
+    pub fn just_pressed(&self, button: MouseButton) -> bool {
+        self.buttons[button.index()].pressed
+    }
+
+    /// Released since the last end_frame.
+    #[inline]
+    pub fn just_released(&self, button: MouseButton) -> bool {
+        self.buttons[button.index()].released
+    }
+
+    #[inline]
+    pub fn is_dragging(&self, button: MouseButton) -> bool {
+        self.buttons[button.index()].dragging
+    }
+
+    /// Where the drag of `button` started, None unless it is dragging.
+    pub fn drag_origin(&self, button: MouseButton) -> Option<[f32; 2]> {
+        let state = &self.buttons[button.index()];
+        state.dragging.then_some(state.press_position)
+    }
+
+    /// Clicks in the current run of `button`, 2 while the second press of a double click
+    /// is held. 0 once the press turned into a drag.
+    #[inline]
+    pub fn click_count(&self, button: MouseButton) -> u32 {
+        self.buttons[button.index()].click_count
+    }
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    (a[0] - b[0]).hypot(a[1] - b[1])
+}
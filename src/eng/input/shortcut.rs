@@ -0,0 +1,361 @@
+use std::fmt;
+
+use anyhow::bail;
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
+};
+
+/// Toggles borderless fullscreen, registered by the engine on F11.
+pub const TOGGLE_FULLSCREEN: &str = "window.toggle_fullscreen";
+
+/// Key names understood by parse_chord and written by Chord's Display.
+const KEY_NAMES: [(&str, KeyCode); 70] = [
+    ("A", KeyCode::KeyA),
+    ("B", KeyCode::KeyB),
+    ("C", KeyCode::KeyC),
+    ("D", KeyCode::KeyD),
+    ("E", KeyCode::KeyE),
+    ("F", KeyCode::KeyF),
+    ("G", KeyCode::KeyG),
+    ("H", KeyCode::KeyH),
+    ("I", KeyCode::KeyI),
+    ("J", KeyCode::KeyJ),
+    ("K", KeyCode::KeyK),
+    ("L", KeyCode::KeyL),
+    ("M", KeyCode::KeyM),
+    ("N", KeyCode::KeyN),
+    ("O", KeyCode::KeyO),
+    ("P", KeyCode::KeyP),
+    ("Q", KeyCode::KeyQ),
+    ("R", KeyCode::KeyR),
+    ("S", KeyCode::KeyS),
+    ("T", KeyCode::KeyT),
+    ("U", KeyCode::KeyU),
+    ("V", KeyCode::KeyV),
+    ("W", KeyCode::KeyW),
+    ("X", KeyCode::KeyX),
+    ("Y", KeyCode::KeyY),
+    ("Z", KeyCode::KeyZ),
+    ("0", KeyCode::Digit0),
+    ("1", KeyCode::Digit1),
+    ("2", KeyCode::Digit2),
+    ("3", KeyCode::Digit3),
+    ("4", KeyCode::Digit4),
+    ("5", KeyCode::Digit5),
+    ("6", KeyCode::Digit6),
+    ("7", KeyCode::Digit7),
+    ("8", KeyCode::Digit8),
+    ("9", KeyCode::Digit9),
+    ("F1", KeyCode::F1),
+    ("F2", KeyCode::F2),
+    ("F3", KeyCode::F3),
+    ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5),
+    ("F6", KeyCode::F6),
+    ("F7", KeyCode::F7),
+    ("F8", KeyCode::F8),
+    ("F9", KeyCode::F9),
+    ("F10", KeyCode::F10),
+    ("F11", KeyCode::F11),
+    ("F12", KeyCode::F12),
+    ("Escape", KeyCode::Escape),
+    ("Enter", KeyCode::Enter),
+    ("Space", KeyCode::Space),
+    ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Backspace),
+    ("Delete", KeyCode::Delete),
+    ("Insert", KeyCode::Insert),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Up", KeyCode::ArrowUp),
+    ("Down", KeyCode::ArrowDown),
+    ("Left", KeyCode::ArrowLeft),
+    ("Right", KeyCode::ArrowRight),
+    ("`", KeyCode::Backquote),
+    ("-", KeyCode::Minus),
+    ("=", KeyCode::Equal),
+    ("[", KeyCode::BracketLeft),
+    ("]", KeyCode::BracketRight),
+    ("/", KeyCode::Slash),
+    ("\\", KeyCode::Backslash),
+];
+
+/// Key plus the exact modifiers held with it, e.g. Ctrl+S or F11.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chord {
+    pub key: KeyCode,
+    pub modifiers: ModifiersState,
+}
+
+impl Chord {
+    pub const fn new(key: KeyCode) -> Self {
+        Self {
+            key,
+            modifiers: ModifiersState::empty(),
+        }
+    }
+
+    pub fn ctrl(mut self) -> Self {
+        self.modifiers |= ModifiersState::CONTROL;
+        self
+    }
+
+    pub fn shift(mut self) -> Self {
+        self.modifiers |= ModifiersState::SHIFT;
+        self
+    }
+
+    pub fn alt(mut self) -> Self {
+        self.modifiers |= ModifiersState::ALT;
+        self
+    }
+
+    /// The Windows / Command key.
+    pub fn logo(mut self) -> Self {
+        self.modifiers |= ModifiersState::SUPER;
+        self
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [
+            (ModifiersState::CONTROL, "Ctrl+"),
+            (ModifiersState::SHIFT, "Shift+"),
+            (ModifiersState::ALT, "Alt+"),
+            (ModifiersState::SUPER, "Super+"),
+        ];
+        for (modifier, name) in modifiers {
+            if self.modifiers.contains(modifier) {
+                f.write_str(name)?;
+            }
+        }
+        match KEY_NAMES.iter().find(|(_, key)| *key == self.key) {
+            Some((name, _)) => f.write_str(name),
+            None => write!(f, "{:?}", self.key),
+        }
+    }
+}
+
+/// Parses chords like `Ctrl+Shift+S` or `F11`, names are case insensitive.
+pub fn parse_chord(text: &str) -> anyhow::Result<Chord> {
+    let mut modifiers = ModifiersState::empty();
+    let mut parts = text.split('+').map(str::trim).peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            let Some((_, key)) = KEY_NAMES
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(part))
+            else {
+                bail!("parse_chord => unknown key {}", part);
+            };
+            return Ok(Chord {
+                key: *key,
+                modifiers,
+            });
+        }
+        modifiers |= match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => ModifiersState::CONTROL,
+            "shift" => ModifiersState::SHIFT,
+            "alt" | "option" => ModifiersState::ALT,
+            "super" | "cmd" | "logo" | "meta" => ModifiersState::SUPER,
+            _ => bail!("parse_chord => unknown modifier {}", part),
+        };
+    }
+    bail!("parse_chord => empty chord")
+}
+
+/// Which part of the app a shortcut belongs to, shortcuts only fire while their context
+/// is active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShortcutContext {
+    /// Always active.
+    Global,
+    Gameplay,
+    Editor,
+    Console,
+}
+
+impl ShortcutContext {
+    #[inline]
+    const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shortcut {
+    pub action: String,
+    pub chord: Chord,
+    pub context: ShortcutContext,
+    /// Highest priority wins when active shortcuts share a chord.
+    pub priority: i32,
+}
+
+/// A key press that matched a shortcut, the key event doesn't reach the app.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShortcutHit {
+    pub action: String,
+    /// Held key auto repeat, only consumed so the key doesn't leak to the app.
+    pub repeat: bool,
+}
+
+/// Chords registered by systems under action names, matched against key presses before
+/// they reach the app. Fired actions are kept until end_frame so systems can poll them.
+#[derive(Debug, Clone)]
+pub struct Shortcuts {
+    shortcuts: Vec<Shortcut>,
+    active: u8,
+    modifiers: ModifiersState,
+    triggered: Vec<String>,
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shortcuts {
+    /// Global and Gameplay shortcuts start out active.
+    pub fn new() -> Self {
+        Self {
+            shortcuts: Vec::new(),
+            active: ShortcutContext::Global.bit() | ShortcutContext::Gameplay.bit(),
+            modifiers: ModifiersState::empty(),
+            triggered: Vec::new(),
+        }
+    }
+
+    /// Binds `action` to `chord`, replacing its previous binding.
+    pub fn register(
+        &mut self,
+        action: &str,
+        chord: Chord,
+        context: ShortcutContext,
+        priority: i32,
+    ) {
+        self.unregister(action);
+        self.shortcuts.push(Shortcut {
+            action: String::from(action),
+            chord,
+            context,
+            priority,
+        });
+    }
+
+    pub fn unregister(&mut self, action: &str) -> bool {
+        let len = self.shortcuts.len();
+        self.shortcuts.retain(|s| s.action != action);
+        self.shortcuts.len() != len
+    }
+
+    /// Moves `action` to another chord keeping its context and priority, false if it
+    /// isn't registered.
+    pub fn rebind(&mut self, action: &str, chord: Chord) -> bool {
+        match self.shortcuts.iter_mut().find(|s| s.action == action) {
+            Some(shortcut) => {
+                shortcut.chord = chord;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn chord(&self, action: &str) -> Option<Chord> {
+        self.shortcuts
+            .iter()
+            .find(|s| s.action == action)
+            .map(|s| s.chord)
+    }
+
+    #[inline]
+    pub fn shortcuts(&self) -> &[Shortcut] {
+        &self.shortcuts
+    }
+
+    pub fn set_active(&mut self, context: ShortcutContext, active: bool) {
+        if active {
+            self.active |= context.bit();
+        } else {
+            self.active &= !context.bit();
+        }
+    }
+
+    #[inline]
+    pub fn is_active(&self, context: ShortcutContext) -> bool {
+        context == ShortcutContext::Global || self.active & context.bit() != 0
+    }
+
+    /// Action `chord` fires right now, the highest priority one in an active context.
+    /// Ties go to the shortcut registered first.
+    pub fn resolve(&self, chord: Chord) -> Option<&str> {
+        self.shortcuts
+            .iter()
+            .filter(|s| s.chord == chord && self.is_active(s.context))
+            .fold(None, |best: Option<&Shortcut>, s| match best {
+                Some(b) if b.priority >= s.priority => Some(b),
+                _ => Some(s),
+            })
+            .map(|s| s.action.as_str())
+    }
+
+    /// Tracks modifiers and matches key presses, returning the shortcut a press fired.
+    pub fn handle_event(&mut self, event: &WindowEvent) -> Option<ShortcutHit> {
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                None
+            }
+            WindowEvent::Focused(false) => {
+                self.modifiers = ModifiersState::empty();
+                None
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key),
+                        state: ElementState::Pressed,
+                        repeat,
+                        ..
+                    },
+                ..
+            } => self.handle_key(*key, *repeat),
+            _ => None,
+        }
+    }
+
+    /// Matches a press of `key` with the current modifiers.
+    pub fn handle_key(&mut self, key: KeyCode, repeat: bool) -> Option<ShortcutHit> {
+        let chord = Chord {
+            key,
+            modifiers: self.modifiers,
+        };
+        let action = String::from(self.resolve(chord)?);
+        if !repeat {
+            self.triggered.push(action.clone());
+        }
+        Some(ShortcutHit { action, repeat })
+    }
+
+    pub fn set_modifiers(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+    }
+
+    /// Actions fired since the last end_frame, in order.
+    #[inline]
+    pub fn triggered(&self) -> &[String] {
+        &self.triggered
+    }
+
+    pub fn was_triggered(&self, action: &str) -> bool {
+        self.triggered.iter().any(|a| a == action)
+    }
+
+    pub fn end_frame(&mut self) {
+        self.triggered.clear();
+    }
+}
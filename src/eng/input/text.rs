@@ -0,0 +1,301 @@
+use std::ops::Range;
+
+use winit::{
+    event::{ElementState, KeyEvent, WindowEvent},
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
+};
+
+/// Source and sink for cut/copy/paste. Implement over the platform clipboard, or use
+/// LocalClipboard to keep it within the app.
+pub trait Clipboard {
+    fn get(&mut self) -> Option<String>;
+    fn set(&mut self, text: &str);
+}
+
+/// Clipboard that only lives inside the app.
+#[derive(Debug, Clone, Default)]
+pub struct LocalClipboard(Option<String>);
+
+impl Clipboard for LocalClipboard {
+    fn get(&mut self) -> Option<String> {
+        self.0.clone()
+    }
+
+    fn set(&mut self, text: &str) {
+        self.0 = Some(String::from(text));
+    }
+}
+
+/// Editing state of a single line text field, fed from winit events. It only tracks the
+/// string, cursor and selection; drawing the field is left to the app.
+/// Cursor and selection positions are byte offsets into text(), always on char boundaries.
+#[derive(Debug, Clone, Default)]
+pub struct TextEditState {
+    text: String,
+    cursor: usize,
+    /// Other end of the selection, the selection spans anchor..cursor.
+    anchor: Option<usize>,
+    modifiers: ModifiersState,
+    max_len: Option<usize>,
+}
+
+impl TextEditState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_text(text: &str) -> Self {
+        let mut s = Self::default();
+        s.set_text(text);
+        s
+    }
+
+    /// Limits the text to `max_len` chars, input past it is dropped.
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the text and moves the cursor to the end.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = String::from(text);
+        self.cursor = self.text.len();
+        self.anchor = None;
+    }
+
+    #[inline]
+    pub const fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Cursor position in chars, for laying out a caret.
+    pub fn cursor_char(&self) -> usize {
+        self.text[..self.cursor].chars().count()
+    }
+
+    pub fn set_cursor(&mut self, cursor: usize, select: bool) {
+        let cursor = self.clamp_to_boundary(cursor);
+        self.move_to(cursor, select);
+    }
+
+    /// Selected byte range, None when nothing is selected.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        if anchor == self.cursor {
+            return None;
+        }
+        Some(anchor.min(self.cursor)..anchor.max(self.cursor))
+    }
+
+    pub fn selected_text(&self) -> Option<&str> {
+        self.selection().map(|r| &self.text[r])
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.cursor = self.text.len();
+    }
+
+    /// Inserts text at the cursor, replacing the selection.
+    pub fn insert(&mut self, text: &str) {
+        self.delete_selection();
+        let mut text = text;
+        if let Some(max_len) = self.max_len {
+            let room = max_len.saturating_sub(self.text.chars().count());
+            let end = text.char_indices().nth(room).map_or(text.len(), |(i, _)| i);
+            text = &text[..end];
+        }
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    pub fn backspace(&mut self) {
+        if self.delete_selection() {
+            return;
+        }
+        if let Some(prev) = self.prev_boundary(self.cursor) {
+            self.text.replace_range(prev..self.cursor, "");
+            self.cursor = prev;
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.delete_selection() {
+            return;
+        }
+        if let Some(next) = self.next_boundary(self.cursor) {
+            self.text.replace_range(self.cursor..next, "");
+        }
+    }
+
+    pub fn copy(&self, clipboard: &mut dyn Clipboard) {
+        if let Some(text) = self.selected_text() {
+            clipboard.set(text);
+        }
+    }
+
+    pub fn cut(&mut self, clipboard: &mut dyn Clipboard) {
+        self.copy(clipboard);
+        self.delete_selection();
+    }
+
+    pub fn paste(&mut self, clipboard: &mut dyn Clipboard) {
+        if let Some(text) = clipboard.get() {
+            // Single line field, newlines become spaces.
+            let text = text.replace(['\r', '\n'], " ");
+            self.insert(&text);
+        }
+    }
+
+    /// Handles KeyboardInput and ModifiersChanged. Editing keys are tried first, otherwise
+    /// the key's text is inserted. Returns true when the event was consumed by the field.
+    pub fn handle_event(&mut self, event: &WindowEvent, clipboard: &mut dyn Clipboard) -> bool {
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                false
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        physical_key,
+                        text,
+                        ..
+                    },
+                ..
+            } => {
+                if let PhysicalKey::Code(key) = physical_key {
+                    if self.handle_key(*key, clipboard) {
+                        return true;
+                    }
+                }
+                let mut inserted = false;
+                for c in text.iter().flat_map(|text| text.chars()) {
+                    inserted |= self.handle_char(c);
+                }
+                inserted
+            }
+            _ => false,
+        }
+    }
+
+    /// Inserts a typed character, control characters are ignored since they are
+    /// handled as keys instead.
+    pub fn handle_char(&mut self, c: char) -> bool {
+        if c.is_control() || self.modifiers.control_key() || self.modifiers.super_key() {
+            return false;
+        }
+        let mut buf = [0u8; 4];
+        self.insert(c.encode_utf8(&mut buf));
+        true
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode, clipboard: &mut dyn Clipboard) -> bool {
+        let select = self.modifiers.shift_key();
+        // Cmd on macOS, Ctrl elsewhere.
+        let command = self.modifiers.control_key() || self.modifiers.super_key();
+        let word = self.modifiers.control_key() || self.modifiers.alt_key();
+
+        match key {
+            KeyCode::ArrowLeft => {
+                let to = if !select && self.selection().is_some() {
+                    self.selection().unwrap().start
+                } else if word {
+                    self.prev_word(self.cursor)
+                } else {
+                    self.prev_boundary(self.cursor).unwrap_or(0)
+                };
+                self.move_to(to, select);
+            }
+            KeyCode::ArrowRight => {
+                let to = if !select && self.selection().is_some() {
+                    self.selection().unwrap().end
+                } else if word {
+                    self.next_word(self.cursor)
+                } else {
+                    self.next_boundary(self.cursor).unwrap_or(self.text.len())
+                };
+                self.move_to(to, select);
+            }
+            KeyCode::Home => self.move_to(0, select),
+            KeyCode::End => self.move_to(self.text.len(), select),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::Delete => self.delete(),
+            KeyCode::KeyA if command => self.select_all(),
+            KeyCode::KeyC if command => self.copy(clipboard),
+            KeyCode::KeyX if command => self.cut(clipboard),
+            KeyCode::KeyV if command => self.paste(clipboard),
+            _ => return false,
+        }
+        true
+    }
+
+    fn move_to(&mut self, to: usize, select: bool) {
+        if select {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = to;
+    }
+
+    fn delete_selection(&mut self) -> bool {
+        let Some(range) = self.selection() else {
+            self.anchor = None;
+            return false;
+        };
+        self.text.replace_range(range.clone(), "");
+        self.cursor = range.start;
+        self.anchor = None;
+        true
+    }
+
+    fn clamp_to_boundary(&self, mut index: usize) -> usize {
+        index = index.min(self.text.len());
+        while !self.text.is_char_boundary(index) {
+            index -= 1;
+        }
+        index
+    }
+
+    fn prev_boundary(&self, index: usize) -> Option<usize> {
+        self.text[..index]
+            .char_indices()
+            .next_back()
+            .map(|(i, _)| i)
+    }
+
+    fn next_boundary(&self, index: usize) -> Option<usize> {
+        self.text[index..]
+            .chars()
+            .next()
+            .map(|c| index + c.len_utf8())
+    }
+
+    /// Start of the word before index, skipping whitespace first.
+    fn prev_word(&self, index: usize) -> usize {
+        let before = &self.text[..index];
+        let trimmed = before.trim_end();
+        trimmed
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8())
+    }
+
+    /// End of the word after index, skipping whitespace first.
+    fn next_word(&self, index: usize) -> usize {
+        let after = &self.text[index..];
+        let skip = after.len() - after.trim_start().len();
+        after[skip..]
+            .char_indices()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(self.text.len(), |(i, _)| index + skip + i)
+    }
+}
@@ -0,0 +1,168 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::gfx::{draw::DrawCtx, wgpu::surface::SurfaceError};
+
+use super::{
+    render::RenderWindow,
+    state::{GameState, Trans},
+    warmup::WarmUp,
+};
+
+/// Thread safe counter of in flight loads. Cloning shares the same counters, so loaders
+/// running on other threads can report completion while the loading screen polls it.
+#[derive(Debug, Clone, Default)]
+pub struct LoadProgress {
+    total: Arc<AtomicUsize>,
+    completed: Arc<AtomicUsize>,
+}
+
+impl LoadProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `count` more loads that have to complete.
+    pub fn begin(&self, count: usize) {
+        self.total.fetch_add(count, Ordering::AcqRel);
+    }
+
+    pub fn complete(&self, count: usize) {
+        self.completed.fetch_add(count, Ordering::AcqRel);
+    }
+
+    /// Runs `load` on a new thread, counting it as one load until it returns.
+    pub fn spawn<T, F>(&self, load: F) -> std::thread::JoinHandle<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        self.begin(1);
+        let progress = self.clone();
+        std::thread::spawn(move || {
+            let result = load();
+            progress.complete(1);
+            result
+        })
+    }
+
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.total.load(Ordering::Acquire)
+    }
+
+    #[inline]
+    pub fn completed(&self) -> usize {
+        self.completed.load(Ordering::Acquire)
+    }
+
+    /// Fraction of loads completed from 0 to 1, 1 when nothing was registered.
+    pub fn fraction(&self) -> f32 {
+        let total = self.total();
+        if total == 0 {
+            return 1.0;
+        }
+        (self.completed() as f32 / total as f32).min(1.0)
+    }
+
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.completed() >= self.total()
+    }
+}
+
+pub type LoadingDrawFn = Box<dyn FnMut(&mut DrawCtx, &LoadProgress) -> Result<(), SurfaceError>>;
+pub type LoadingDoneFn = Box<dyn FnMut(&LoadProgress, Duration) -> bool>;
+pub type NextStateFn = Box<dyn FnOnce() -> Box<dyn GameState>>;
+
+/// GameState shown while loads are in flight. Draws the user callback every frame
+/// independent of how long the loads take, then replaces itself with the next state once
+/// the completion condition is met (all loads done and the minimum time elapsed by default).
+pub struct LoadingScreen {
+    progress: LoadProgress,
+    draw: LoadingDrawFn,
+    done: LoadingDoneFn,
+    next: Option<NextStateFn>,
+    min_duration: Duration,
+    elapsed: Duration,
+    warm_up: Option<(Rc<RefCell<RenderWindow>>, WarmUp)>,
+}
+
+impl LoadingScreen {
+    pub fn new<D, N>(progress: LoadProgress, draw: D, next: N) -> Self
+    where
+        D: FnMut(&mut DrawCtx, &LoadProgress) -> Result<(), SurfaceError> + 'static,
+        N: FnOnce() -> Box<dyn GameState> + 'static,
+    {
+        Self {
+            progress,
+            draw: Box::new(draw),
+            done: Box::new(|progress, _| progress.is_done()),
+            next: Some(Box::new(next)),
+            min_duration: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            warm_up: None,
+        }
+    }
+
+    /// Replaces the completion condition, it receives the progress and time spent loading.
+    pub fn with_condition<F>(mut self, done: F) -> Self
+    where
+        F: FnMut(&LoadProgress, Duration) -> bool + 'static,
+    {
+        self.done = Box::new(done);
+        self
+    }
+
+    /// Keeps the loading screen up for at least `duration` to avoid a single frame flash.
+    pub fn with_min_duration(mut self, duration: Duration) -> Self {
+        self.min_duration = duration;
+        self
+    }
+
+    /// Runs `warm_up` a budget's worth per frame while the screen is up. Its steps count
+    /// towards the progress if it was created with the same LoadProgress, and the report is
+    /// logged once it is done.
+    pub fn with_warm_up(mut self, window: Rc<RefCell<RenderWindow>>, warm_up: WarmUp) -> Self {
+        self.warm_up = Some((window, warm_up));
+        self
+    }
+
+    #[inline]
+    pub fn progress(&self) -> &LoadProgress {
+        &self.progress
+    }
+
+    pub fn warm_up(&self) -> Option<&WarmUp> {
+        self.warm_up.as_ref().map(|(_, warm_up)| warm_up)
+    }
+}
+
+impl GameState for LoadingScreen {
+    fn draw_frame(&mut self, ctx: &mut DrawCtx) -> Result<(), SurfaceError> {
+        (self.draw)(ctx, &self.progress)
+    }
+
+    fn frame_update(&mut self, dt: Duration) -> Trans {
+        self.elapsed += dt;
+        if let Some((window, warm_up)) = self.warm_up.as_mut() {
+            if !warm_up.is_done() && warm_up.run(&window.borrow()) {
+                log::info!("{}", warm_up.report());
+            }
+        }
+        if self.elapsed < self.min_duration || !(self.done)(&self.progress, self.elapsed) {
+            return Trans::None;
+        }
+        match self.next.take() {
+            Some(next) => Trans::Replace(next()),
+            None => Trans::None,
+        }
+    }
+}
@@ -5,20 +5,45 @@ use std::{
     time::Duration,
 };
 
-use winit::{
-    event::{
-        DeviceEvent, ElementState, Event, KeyboardInput, MouseScrollDelta, VirtualKeyCode,
-        WindowEvent,
-    },
-    event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
-};
-
 use crate::gfx::{self, camera::CameraUniform};
 
 use self::render::RenderWindow;
 
+#[cfg(feature = "accesskit")]
+pub mod access;
+#[cfg(feature = "renderdoc")]
+pub mod capture;
 pub mod command;
+pub mod command_dump;
+pub mod context;
+pub mod determinism;
+#[cfg(feature = "editor")]
+pub mod editor;
+pub mod encoder;
 
+pub mod ai;
+pub mod animation;
 pub mod app;
+pub mod asset_graph;
+pub mod assets;
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod frame;
+pub mod frame_graph;
+pub mod input;
+pub mod loading;
+pub mod occlusion;
+#[cfg(feature = "scene")]
+pub mod prefab;
+pub mod readback;
 pub mod render;
+#[cfg(feature = "scene")]
+pub mod scene;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod state;
+pub mod tasks;
+pub mod transition;
+pub mod validate;
+pub mod warmup;
+pub mod watchdog;
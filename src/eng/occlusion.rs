@@ -0,0 +1,331 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    ops::Deref,
+    rc::Rc,
+    sync::Arc,
+};
+
+use crate::gfx::wgpu::{buffer::InstanceRaw, texture::Texture, vertex::Vertex3D};
+
+use super::{readback::ReadbackHandle, render::DeviceSurface};
+
+/// Size in bytes of a single resolved query result.
+const QUERY_RESULT_SIZE: wgpu::BufferAddress = std::mem::size_of::<u64>() as wgpu::BufferAddress;
+
+/// wgpu::QuerySet does not implement Debug, so this wraps it for use in RenderCommand.
+#[derive(Clone)]
+pub struct QuerySetHandle(Arc<wgpu::QuerySet>);
+
+impl std::fmt::Debug for QuerySetHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("QuerySetHandle")
+            .field(&Arc::as_ptr(&self.0))
+            .finish()
+    }
+}
+
+impl Deref for QuerySetHandle {
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+
+    type Target = wgpu::QuerySet;
+}
+
+/// Wraps a wgpu QuerySet used for occlusion testing along with the buffer its results
+/// are resolved into, read back on the CPU through DeviceSurface::readbacks.
+/// Results are the number of samples that passed the depth test.
+#[derive(Debug)]
+pub struct OcclusionQuerySet {
+    set: QuerySetHandle,
+    resolve_buffer: wgpu::Buffer,
+    capacity: u32,
+    used: Cell<u32>,
+    /// Results of the last resolve, until take_results hands them out.
+    readback: RefCell<Option<ReadbackHandle>>,
+}
+
+impl OcclusionQuerySet {
+    pub fn new(device: &wgpu::Device, capacity: u32) -> Self {
+        let set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Occlusion Query Set"),
+            ty: wgpu::QueryType::Occlusion,
+            count: capacity,
+        });
+        let size = capacity as wgpu::BufferAddress * QUERY_RESULT_SIZE;
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Occlusion Resolve Buffer"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            set: QuerySetHandle(Arc::new(set)),
+            resolve_buffer,
+            capacity,
+            used: Cell::new(0),
+            readback: RefCell::new(None),
+        }
+    }
+
+    #[inline]
+    pub fn handle(&self) -> QuerySetHandle {
+        self.set.clone()
+    }
+
+    /// The query set bound as a render pass's occlusion_query_set.
+    #[inline]
+    pub fn query_set(&self) -> &wgpu::QuerySet {
+        &self.set
+    }
+
+    #[inline]
+    pub const fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Queries can only be recorded while no results are being read back.
+    #[inline]
+    pub fn is_idle(&self) -> bool {
+        self.readback.borrow().is_none()
+    }
+
+    /// Reserves the next query index for this frame, returns None if the set is full
+    /// or the previous results are still being read back.
+    pub fn next_index(&self) -> Option<u32> {
+        let used = self.used.get();
+        if !self.is_idle() || used >= self.capacity {
+            return None;
+        }
+        self.used.set(used + 1);
+        Some(used)
+    }
+
+    /// Records the commands that resolve the queries and copy the results into a staging
+    /// buffer. Called by RenderPass::encode once the pass has finished encoding, the copy
+    /// is mapped when the encoder is submitted through DeviceSurface::submit_pending.
+    pub fn resolve(&self, surface: &DeviceSurface, encoder: &mut wgpu::CommandEncoder) {
+        let used = self.used.get();
+        if used == 0 || !self.is_idle() {
+            return;
+        }
+        encoder.resolve_query_set(&self.set, 0..used, &self.resolve_buffer, 0);
+        let handle = surface.readbacks.copy_buffer(
+            &surface.device,
+            encoder,
+            &self.resolve_buffer,
+            0..used as wgpu::BufferAddress * QUERY_RESULT_SIZE,
+        );
+        self.readback.replace(Some(handle));
+    }
+
+    /// Drops any recorded queries, only valid while no readback is in flight.
+    pub fn discard(&self) {
+        if self.is_idle() {
+            self.used.set(0);
+        }
+    }
+
+    /// Returns the sample counts of the last resolve once the readback has completed,
+    /// delivered by Readbacks::poll. A failed readback returns None and the set goes idle.
+    pub fn take_results(&self) -> Option<Vec<u64>> {
+        let result = self.readback.borrow().as_ref()?.try_take()?;
+        self.readback.replace(None);
+        self.used.set(0);
+        match result {
+            Ok(bytes) => Some(
+                bytes
+                    .chunks_exact(QUERY_RESULT_SIZE as usize)
+                    .map(bytemuck::pod_read_unaligned::<u64>)
+                    .collect(),
+            ),
+            Err(e) => {
+                log::warn!("OcclusionQuerySet::take_results => readback failed: {e:#}");
+                None
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Visibility {
+    /// Consecutive query results that came back with zero samples.
+    hidden_frames: u32,
+}
+
+/// Skips drawing expensive objects whose bounding proxy has been fully occluded for
+/// the last `hide_after` query results. Objects are identified by an app supplied id.
+#[derive(Debug)]
+pub struct OcclusionCuller {
+    queries: Rc<OcclusionQuerySet>,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    /// Object id for each query index issued this frame.
+    issued: Vec<u64>,
+    /// Object ids for the queries currently being read back.
+    in_flight: Vec<u64>,
+    visibility: HashMap<u64, Visibility>,
+    hide_after: u32,
+}
+
+impl OcclusionCuller {
+    pub const DEFAULT_CAPACITY: u32 = 256;
+    pub const DEFAULT_HIDE_AFTER: u32 = 3;
+
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        cam_bind_group_layout: &wgpu::BindGroupLayout,
+        capacity: u32,
+    ) -> Self {
+        let queries = Rc::new(OcclusionQuerySet::new(device, capacity));
+
+        let pipeline = Arc::new(create_proxy_pipeline(
+            device,
+            color_format,
+            cam_bind_group_layout,
+        ));
+
+        Self {
+            queries,
+            pipeline,
+            issued: Vec::with_capacity(capacity as usize),
+            in_flight: Vec::new(),
+            visibility: HashMap::new(),
+            hide_after: Self::DEFAULT_HIDE_AFTER,
+        }
+    }
+
+    /// Number of consecutive zero sample results before an object is treated as hidden.
+    pub fn set_hide_after(&mut self, frames: u32) {
+        self.hide_after = frames.max(1);
+    }
+
+    #[inline]
+    pub fn query_set(&self) -> &Rc<OcclusionQuerySet> {
+        &self.queries
+    }
+
+    #[inline]
+    pub fn proxy_pipeline(&self) -> Arc<wgpu::RenderPipeline> {
+        self.pipeline.clone()
+    }
+
+    /// Polls for finished readbacks and folds them into each object's visibility history.
+    /// Should be called once per frame before recording any queries.
+    pub fn update(&mut self, surface: &DeviceSurface) {
+        let queries = &self.queries;
+        surface.readbacks.poll(&surface.device);
+
+        // Queries recorded last frame are in flight once their pass resolved them,
+        // otherwise they were never resolved and are dropped.
+        if !self.issued.is_empty() {
+            if queries.is_idle() {
+                queries.discard();
+                self.issued.clear();
+            } else {
+                self.in_flight = std::mem::take(&mut self.issued);
+            }
+        }
+
+        if let Some(results) = queries.take_results() {
+            for (id, samples) in self.in_flight.drain(..).zip(results) {
+                let vis = self.visibility.entry(id).or_default();
+                if samples == 0 {
+                    vis.hidden_frames = vis.hidden_frames.saturating_add(1);
+                } else {
+                    vis.hidden_frames = 0;
+                }
+            }
+        } else if queries.is_idle() && !self.in_flight.is_empty() {
+            // Mapping failed, results are lost.
+            queries.discard();
+            self.in_flight.clear();
+        }
+    }
+
+    /// Reserves a query index for the given object, None if no query can be recorded this frame.
+    pub fn begin_query(&mut self, id: u64) -> Option<(QuerySetHandle, u32)> {
+        let index = self.queries.next_index()?;
+        self.issued.push(id);
+        Some((self.queries.handle(), index))
+    }
+
+    /// Returns false once the object's proxy has been occluded for `hide_after` results in a row.
+    pub fn is_visible(&self, id: u64) -> bool {
+        self.visibility
+            .get(&id)
+            .is_none_or(|v| v.hidden_frames < self.hide_after)
+    }
+
+    /// Forgets the visibility history of an object, it will be drawn until queried again.
+    pub fn forget(&mut self, id: u64) {
+        self.visibility.remove(&id);
+    }
+}
+
+/// Pipeline used to draw bounding proxies, depth tested but writes neither color nor depth.
+fn create_proxy_pipeline(
+    device: &wgpu::Device,
+    color_format: wgpu::TextureFormat,
+    cam_bind_group_layout: &wgpu::BindGroupLayout,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Occlusion Pipeline Layout"),
+        bind_group_layouts: &[Some(cam_bind_group_layout)],
+        immediate_size: 0,
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Occlusion Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/occlusion.wgsl").into()),
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Occlusion Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            compilation_options: Default::default(),
+            buffers: &[
+                Some(Vertex3D::buffer_layout()),
+                Some(InstanceRaw::buffer_layout()),
+            ],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: color_format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::empty(),
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            // Proxies are tested from both sides so a camera inside the bounds still counts.
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled: Some(false),
+            depth_compare: Some(wgpu::CompareFunction::LessEqual),
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview_mask: None,
+        cache: None,
+    })
+}
@@ -0,0 +1,275 @@
+use std::path::Path;
+
+use cgmath::{ElementWise, Quaternion, Rotation, Vector3};
+use serde::{Deserialize, Serialize};
+
+use crate::gfx::transform::Transform;
+
+use super::scene::{PropertyValue, Scene, SceneEntity};
+
+/// Entity of a prefab. Its transform is relative to `parent`, or to the instance
+/// position for root nodes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrefabNode {
+    pub name: String,
+    /// Index of the parent node, parents always come before their children.
+    #[serde(default)]
+    pub parent: Option<usize>,
+    #[serde(default)]
+    pub position: [f32; 3],
+    #[serde(default = "PrefabNode::identity_rotation")]
+    pub rotation: [f32; 4],
+    #[serde(default = "PrefabNode::unit_size")]
+    pub size: [f32; 3],
+    #[serde(default = "PrefabNode::default_radius")]
+    pub radius: f32,
+    #[serde(default)]
+    pub properties: Vec<(String, PropertyValue)>,
+}
+
+impl PrefabNode {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            parent: None,
+            position: [0.0; 3],
+            rotation: Self::identity_rotation(),
+            size: Self::unit_size(),
+            radius: Self::default_radius(),
+            properties: Vec::new(),
+        }
+    }
+
+    pub fn with_parent(mut self, parent: usize) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    pub fn with_position(mut self, position: [f32; 3]) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_property(mut self, name: impl Into<String>, value: PropertyValue) -> Self {
+        self.properties.push((name.into(), value));
+        self
+    }
+
+    fn identity_rotation() -> [f32; 4] {
+        // cgmath stores quaternions as [x, y, z, w].
+        [0.0, 0.0, 0.0, 1.0]
+    }
+
+    fn unit_size() -> [f32; 3] {
+        [1.0; 3]
+    }
+
+    fn default_radius() -> f32 {
+        0.5
+    }
+}
+
+/// Reusable entity subtree, authored as RON and spawned into a Scene with Scene::instantiate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Prefab {
+    /// Identifies the prefab in PrefabInstance, instances are matched by name on reload.
+    pub name: String,
+    pub nodes: Vec<PrefabNode>,
+}
+
+impl Prefab {
+    pub fn new(name: impl Into<String>, nodes: Vec<PrefabNode>) -> anyhow::Result<Self> {
+        let prefab = Self {
+            name: name.into(),
+            nodes,
+        };
+        prefab.validate()?;
+        Ok(prefab)
+    }
+
+    pub fn from_ron(source: &str) -> anyhow::Result<Self> {
+        let prefab: Self = ron::from_str(source)?;
+        prefab.validate()?;
+        Ok(prefab)
+    }
+
+    pub fn to_ron(&self) -> anyhow::Result<String> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::from_ron(&std::fs::read_to_string(path)?)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        for (i, node) in self.nodes.iter().enumerate() {
+            if let Some(parent) = node.parent.filter(|p| *p >= i) {
+                anyhow::bail!(
+                    "Prefab::validate => node {} ({}) has parent {} that does not come before it",
+                    i,
+                    node.name,
+                    parent
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// World transforms of every node for an instance placed at `origin`.
+    fn world_transforms(&self, origin: Vector3<f32>) -> Vec<Transform> {
+        let mut out: Vec<Transform> = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let position = Vector3::from(node.position);
+            let rotation = Quaternion::from(node.rotation);
+            let size = Vector3::from(node.size);
+            let transform = match node.parent.map(|p| &out[p]) {
+                Some(parent) => Transform::new(
+                    parent.position()
+                        + parent
+                            .rotation()
+                            .rotate_vector(position.mul_element_wise(parent.size())),
+                    parent.rotation() * rotation,
+                    parent.size().mul_element_wise(size),
+                ),
+                None => Transform::new(origin + position, rotation, size),
+            };
+            out.push(transform);
+        }
+        out
+    }
+}
+
+/// Per instance changes applied on top of the prefab every time it is spawned.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrefabOverrides {
+    /// Offset of the root nodes.
+    #[serde(default)]
+    pub position: Option<[f32; 3]>,
+    /// Written to the "tint" property of every node.
+    #[serde(default)]
+    pub tint: Option<[f32; 3]>,
+    /// Replaces properties of the same name on every node that has them.
+    #[serde(default)]
+    pub properties: Vec<(String, PropertyValue)>,
+}
+
+impl PrefabOverrides {
+    pub fn with_position(mut self, position: Vector3<f32>) -> Self {
+        self.position = Some(position.into());
+        self
+    }
+
+    pub fn with_tint(mut self, tint: [f32; 3]) -> Self {
+        self.tint = Some(tint);
+        self
+    }
+
+    pub fn with_property(mut self, name: impl Into<String>, value: PropertyValue) -> Self {
+        self.properties.push((name.into(), value));
+        self
+    }
+
+    fn apply(&self, properties: &mut Vec<(String, PropertyValue)>) {
+        if let Some(tint) = self.tint {
+            set_property(properties, "tint", PropertyValue::Vec3(tint));
+        }
+        for (name, value) in &self.properties {
+            if let Some((_, current)) = properties.iter_mut().find(|(n, _)| n == name) {
+                *current = value.clone();
+            }
+        }
+    }
+}
+
+fn set_property(properties: &mut Vec<(String, PropertyValue)>, name: &str, value: PropertyValue) {
+    match properties.iter_mut().find(|(n, _)| n == name) {
+        Some((_, current)) => *current = value,
+        None => properties.push((name.to_string(), value)),
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PrefabInstanceId(pub u32);
+
+/// Links a scene entity back to the prefab node it was spawned from.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrefabLink {
+    pub instance: PrefabInstanceId,
+    pub node: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrefabInstance {
+    pub id: PrefabInstanceId,
+    /// Prefab::name of the source prefab.
+    pub prefab: String,
+    pub overrides: PrefabOverrides,
+}
+
+impl Scene {
+    /// Spawns every node of `prefab` with `overrides` applied and records the instance,
+    /// so Scene::reload_prefab can rebuild it when the prefab changes.
+    pub fn instantiate(&mut self, prefab: &Prefab, overrides: PrefabOverrides) -> PrefabInstanceId {
+        let id = PrefabInstanceId(self.instances.iter().map(|i| i.id.0 + 1).max().unwrap_or(0));
+        self.spawn(prefab, id, &overrides);
+        self.instances.push(PrefabInstance {
+            id,
+            prefab: prefab.name.clone(),
+            overrides,
+        });
+        id
+    }
+
+    fn spawn(&mut self, prefab: &Prefab, id: PrefabInstanceId, overrides: &PrefabOverrides) {
+        let origin = overrides
+            .position
+            .map_or(Vector3::new(0.0, 0.0, 0.0), Vector3::from);
+        let transforms = prefab.world_transforms(origin);
+        for (node_index, (node, transform)) in prefab.nodes.iter().zip(transforms).enumerate() {
+            let mut properties = node.properties.clone();
+            overrides.apply(&mut properties);
+            let mut entity = SceneEntity::new(node.name.clone(), transform);
+            entity.radius = node.radius;
+            entity.properties = properties;
+            entity.prefab = Some(PrefabLink {
+                instance: id,
+                node: node_index,
+            });
+            self.entities.push(entity);
+        }
+    }
+
+    /// Indices into Scene::entities of the entities spawned for `id`.
+    pub fn instance_entities(&self, id: PrefabInstanceId) -> impl Iterator<Item = usize> + '_ {
+        self.entities
+            .iter()
+            .enumerate()
+            .filter(move |(_, e)| e.prefab.is_some_and(|l| l.instance == id))
+            .map(|(i, _)| i)
+    }
+
+    pub fn remove_instance(&mut self, id: PrefabInstanceId) {
+        self.retain_entities(|e| e.prefab.is_none_or(|l| l.instance != id));
+        self.instances.retain(|i| i.id != id);
+    }
+
+    /// Respawns every instance of `prefab` (matched by name) from its new definition,
+    /// keeping instance ids and overrides. Edits made to the spawned entities are lost.
+    /// Returns the number of instances rebuilt.
+    pub fn reload_prefab(&mut self, prefab: &Prefab) -> usize {
+        let instances: Vec<_> = self
+            .instances
+            .iter()
+            .filter(|i| i.prefab == prefab.name)
+            .map(|i| (i.id, i.overrides.clone()))
+            .collect();
+        for (id, overrides) in &instances {
+            self.retain_entities(|e| e.prefab.is_none_or(|l| l.instance != *id));
+            self.spawn(prefab, *id, overrides);
+        }
+        instances.len()
+    }
+}
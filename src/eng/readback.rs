@@ -0,0 +1,436 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    future::Future,
+    ops::Range,
+    pin::Pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use anyhow::{anyhow, bail};
+
+/// Staging buffers are rounded up to a power of two no smaller than this, so similar
+/// sizes share pooled buffers.
+pub const MIN_STAGING_SIZE: wgpu::BufferAddress = 256;
+
+/// Free staging buffers kept per size, the rest are dropped when released.
+pub const MAX_POOLED_PER_SIZE: usize = 4;
+
+const RECORDED: u8 = 0;
+const MAPPING: u8 = 1;
+const READY: u8 = 2;
+const FAILED: u8 = 3;
+
+/// Size of the pooled staging buffer that holds `size` bytes.
+pub fn staging_size(size: wgpu::BufferAddress) -> wgpu::BufferAddress {
+    size.max(MIN_STAGING_SIZE).next_power_of_two()
+}
+
+/// Rows of a texture copied into a buffer, padded to COPY_BYTES_PER_ROW_ALIGNMENT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TexelLayout {
+    pub row_len: u32,
+    pub padded_row_len: u32,
+    pub rows: u32,
+}
+
+impl TexelLayout {
+    pub fn new(width: u32, height: u32, bytes_per_texel: u32) -> Self {
+        let row_len = width * bytes_per_texel;
+        Self {
+            row_len,
+            padded_row_len: row_len.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT),
+            rows: height,
+        }
+    }
+
+    /// Bytes the copy takes up in the staging buffer.
+    pub fn size(&self) -> wgpu::BufferAddress {
+        self.padded_row_len as wgpu::BufferAddress * self.rows as wgpu::BufferAddress
+    }
+
+    /// Copies the rows out of `padded` without their padding, top row first.
+    pub fn unpad(&self, padded: &[u8]) -> Vec<u8> {
+        padded
+            .chunks(self.padded_row_len as usize)
+            .take(self.rows as usize)
+            .flat_map(|row| &row[..self.row_len as usize])
+            .copied()
+            .collect()
+    }
+}
+
+/// MAP_READ staging buffers reused between readbacks, keyed by staging_size.
+#[derive(Default)]
+pub struct StagingPool {
+    free: RefCell<HashMap<wgpu::BufferAddress, Vec<Arc<wgpu::Buffer>>>>,
+}
+
+impl fmt::Debug for StagingPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StagingPool")
+            .field("free", &self.len())
+            .finish()
+    }
+}
+
+impl StagingPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A free buffer of at least `size` bytes, created if the pool has none.
+    pub fn acquire(&self, device: &wgpu::Device, size: wgpu::BufferAddress) -> Arc<wgpu::Buffer> {
+        let size = staging_size(size);
+        if let Some(buffer) = self.free.borrow_mut().get_mut(&size).and_then(Vec::pop) {
+            return buffer;
+        }
+        Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Staging Buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }))
+    }
+
+    /// Returns an unmapped buffer to the pool.
+    pub fn release(&self, buffer: Arc<wgpu::Buffer>) {
+        let mut free = self.free.borrow_mut();
+        let buffers = free.entry(buffer.size()).or_default();
+        if buffers.len() < MAX_POOLED_PER_SIZE {
+            buffers.push(buffer);
+        }
+    }
+
+    /// Number of free buffers.
+    pub fn len(&self) -> usize {
+        self.free.borrow().values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+type Callback = Box<dyn FnOnce(anyhow::Result<Vec<u8>>)>;
+
+#[derive(Default)]
+struct Slot {
+    result: Option<anyhow::Result<Vec<u8>>>,
+    waker: Option<Waker>,
+    callback: Option<Callback>,
+}
+
+impl Slot {
+    fn deliver(slot: &RefCell<Slot>, result: anyhow::Result<Vec<u8>>) {
+        let mut s = slot.borrow_mut();
+        if let Some(callback) = s.callback.take() {
+            drop(s);
+            callback(result);
+            return;
+        }
+        s.result = Some(result);
+        if let Some(waker) = s.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Bytes of a readback, delivered a frame or more after it was requested. Poll it with
+/// try_take, await it or hand it a callback with then.
+pub struct ReadbackHandle {
+    slot: Rc<RefCell<Slot>>,
+}
+
+impl fmt::Debug for ReadbackHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReadbackHandle")
+            .field("ready", &self.is_ready())
+            .finish()
+    }
+}
+
+impl ReadbackHandle {
+    fn new() -> (Self, Rc<RefCell<Slot>>) {
+        let slot = Rc::new(RefCell::new(Slot::default()));
+        (Self { slot: slot.clone() }, slot)
+    }
+
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.slot.borrow().result.is_some()
+    }
+
+    /// The bytes once the readback has finished, None until then.
+    pub fn try_take(&self) -> Option<anyhow::Result<Vec<u8>>> {
+        self.slot.borrow_mut().result.take()
+    }
+
+    /// Calls `f` with the bytes once the readback has finished, right away if it already has.
+    pub fn then(self, f: impl FnOnce(anyhow::Result<Vec<u8>>) + 'static) {
+        let result = self.slot.borrow_mut().result.take();
+        match result {
+            Some(result) => f(result),
+            None => self.slot.borrow_mut().callback = Some(Box::new(f)),
+        }
+    }
+}
+
+impl Future for ReadbackHandle {
+    type Output = anyhow::Result<Vec<u8>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.borrow_mut();
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+enum Source {
+    Buffer(Arc<wgpu::Buffer>, Range<wgpu::BufferAddress>),
+    Texture(wgpu::Texture, TexelLayout),
+}
+
+struct Request {
+    source: Source,
+    slot: Rc<RefCell<Slot>>,
+}
+
+struct InFlight {
+    buffer: Arc<wgpu::Buffer>,
+    size: wgpu::BufferAddress,
+    layout: Option<TexelLayout>,
+    state: Arc<AtomicU8>,
+    slot: Rc<RefCell<Slot>>,
+}
+
+/// Copies buffers and textures into pooled staging buffers and maps them without blocking.
+/// Lives on DeviceSurface, see DeviceSurface::readbacks. Copies are recorded by
+/// DrawCtx::submit or copy_buffer, mapped once DeviceSurface::submit_pending submitted them
+/// and delivered by poll, which DrawCtx::from_window calls every frame.
+#[derive(Default)]
+pub struct Readbacks {
+    pool: StagingPool,
+    requests: RefCell<Vec<Request>>,
+    in_flight: RefCell<Vec<InFlight>>,
+}
+
+impl fmt::Debug for Readbacks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Readbacks")
+            .field("requests", &self.requests.borrow().len())
+            .field("in_flight", &self.in_flight.borrow().len())
+            .field("pool", &self.pool)
+            .finish()
+    }
+}
+
+impl Readbacks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn pool(&self) -> &StagingPool {
+        &self.pool
+    }
+
+    /// True if read_buffer or read_texture requested copies that aren't recorded yet.
+    pub fn has_requests(&self) -> bool {
+        !self.requests.borrow().is_empty()
+    }
+
+    /// Readbacks recorded but not delivered yet.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.borrow().len()
+    }
+
+    /// Reads `range` of `src` back after the next frame is submitted. `src` needs
+    /// BufferUsages::COPY_SRC, the range has to be 4 byte aligned.
+    pub fn read_buffer(
+        &self,
+        src: Arc<wgpu::Buffer>,
+        range: Range<wgpu::BufferAddress>,
+    ) -> ReadbackHandle {
+        let (handle, slot) = ReadbackHandle::new();
+        self.requests.borrow_mut().push(Request {
+            source: Source::Buffer(src, range),
+            slot,
+        });
+        handle
+    }
+
+    /// Reads mip 0 of `texture` back after the next frame is submitted, rows are tightly
+    /// packed. The texture needs TextureUsages::COPY_SRC and a color format.
+    pub fn read_texture(&self, texture: &wgpu::Texture) -> anyhow::Result<ReadbackHandle> {
+        let format = texture.format();
+        let Some(bytes_per_texel) = format.block_copy_size(None) else {
+            bail!(
+                "Readbacks::read_texture => {:?} can't be copied as a whole",
+                format
+            );
+        };
+        if format.block_dimensions() != (1, 1) {
+            bail!("Readbacks::read_texture => compressed format {:?}", format);
+        }
+        let layout = TexelLayout::new(texture.width(), texture.height(), bytes_per_texel);
+        let (handle, slot) = ReadbackHandle::new();
+        self.requests.borrow_mut().push(Request {
+            source: Source::Texture(texture.clone(), layout),
+            slot,
+        });
+        Ok(handle)
+    }
+
+    /// Records the copies requested since the last call into `encoder`, done by
+    /// DrawCtx::submit after the frame's passes.
+    pub fn encode(&self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder) {
+        let requests = std::mem::take(&mut *self.requests.borrow_mut());
+        for request in requests {
+            match request.source {
+                Source::Buffer(src, range) => {
+                    self.record_buffer(device, encoder, &src, range, request.slot)
+                }
+                Source::Texture(texture, layout) => {
+                    let buffer = self.pool.acquire(device, layout.size());
+                    encoder.copy_texture_to_buffer(
+                        texture.as_image_copy(),
+                        wgpu::TexelCopyBufferInfo {
+                            buffer: &buffer,
+                            layout: wgpu::TexelCopyBufferLayout {
+                                offset: 0,
+                                bytes_per_row: Some(layout.padded_row_len),
+                                rows_per_image: Some(layout.rows),
+                            },
+                        },
+                        texture.size(),
+                    );
+                    self.push(buffer, layout.size(), Some(layout), request.slot);
+                }
+            }
+        }
+    }
+
+    /// Records a copy of `range` of `src` into `encoder` right away, e.g. query resolves.
+    /// The encoder has to be submitted through DeviceSurface::submit_pending.
+    pub fn copy_buffer(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        src: &wgpu::Buffer,
+        range: Range<wgpu::BufferAddress>,
+    ) -> ReadbackHandle {
+        let (handle, slot) = ReadbackHandle::new();
+        self.record_buffer(device, encoder, src, range, slot);
+        handle
+    }
+
+    fn record_buffer(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        src: &wgpu::Buffer,
+        range: Range<wgpu::BufferAddress>,
+        slot: Rc<RefCell<Slot>>,
+    ) {
+        let size = range.end.saturating_sub(range.start);
+        if size == 0 {
+            Slot::deliver(&slot, Ok(Vec::new()));
+            return;
+        }
+        let buffer = self.pool.acquire(device, size);
+        encoder.copy_buffer_to_buffer(src, range.start, &buffer, 0, size);
+        self.push(buffer, size, None, slot);
+    }
+
+    fn push(
+        &self,
+        buffer: Arc<wgpu::Buffer>,
+        size: wgpu::BufferAddress,
+        layout: Option<TexelLayout>,
+        slot: Rc<RefCell<Slot>>,
+    ) {
+        self.in_flight.borrow_mut().push(InFlight {
+            buffer,
+            size,
+            layout,
+            state: Arc::new(AtomicU8::new(RECORDED)),
+            slot,
+        });
+    }
+
+    /// Starts mapping every recorded copy, called by DeviceSurface::submit_pending once
+    /// they have been submitted.
+    pub fn map_submitted(&self) {
+        for readback in self.in_flight.borrow().iter() {
+            if readback.state.load(Ordering::Acquire) != RECORDED {
+                continue;
+            }
+            readback.state.store(MAPPING, Ordering::Release);
+            let state = readback.state.clone();
+            readback
+                .buffer
+                .slice(..readback.size)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    let next = if result.is_ok() { READY } else { FAILED };
+                    state.store(next, Ordering::Release);
+                });
+        }
+    }
+
+    /// Polls the device without blocking and delivers every finished readback. Returns how
+    /// many were delivered.
+    pub fn poll(&self, device: &wgpu::Device) -> usize {
+        let mapping = self
+            .in_flight
+            .borrow()
+            .iter()
+            .any(|r| r.state.load(Ordering::Acquire) == MAPPING);
+        if mapping {
+            if let Err(e) = device.poll(wgpu::PollType::Poll) {
+                log::warn!("Readbacks::poll => device poll failed: {e}");
+            }
+        }
+
+        let in_flight = std::mem::take(&mut *self.in_flight.borrow_mut());
+        let (done, pending): (Vec<_>, Vec<_>) = in_flight
+            .into_iter()
+            .partition(|r| matches!(r.state.load(Ordering::Acquire), READY | FAILED));
+        self.in_flight.borrow_mut().extend(pending);
+        let delivered = done.len();
+        // Callbacks may request new readbacks, so nothing is borrowed while they run.
+        for readback in done {
+            let result = if readback.state.load(Ordering::Acquire) == READY {
+                let result = readback
+                    .buffer
+                    .slice(..readback.size)
+                    .get_mapped_range()
+                    .map(|view| match readback.layout {
+                        Some(layout) => layout.unpad(&view),
+                        None => view.to_vec(),
+                    })
+                    .map_err(|e| anyhow!("Readbacks::poll => {e}"));
+                readback.buffer.unmap();
+                self.pool.release(readback.buffer);
+                result
+            } else {
+                Err(anyhow!(
+                    "Readbacks::poll => mapping the staging buffer failed"
+                ))
+            };
+            Slot::deliver(&readback.slot, result);
+        }
+        delivered
+    }
+}
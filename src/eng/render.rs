@@ -1,8 +1,7 @@
 use std::{
     borrow::BorrowMut,
-    cell::{Cell, Ref, RefCell, RefMut},
-    collections::VecDeque,
-    ops::Range,
+    cell::{OnceCell, Ref, RefCell, RefMut},
+    path::PathBuf,
     rc::Rc,
     sync::Arc,
     time::Duration,
@@ -11,20 +10,34 @@ use std::{
 use cgmath::Rad;
 use wgpu::{util::DeviceExt, Device, DynamicOffset, RenderPass, TextureView};
 use winit::{
-    dpi::PhysicalSize,
-    event::{DeviceEvent, ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
-    window::{Window, WindowBuilder, WindowId},
+    dpi::{LogicalSize, PhysicalSize},
+    event::ElementState,
+    event_loop::ActiveEventLoop,
+    keyboard::KeyCode,
+    window::{Fullscreen, Window, WindowId},
 };
 
 use crate::gfx::{
+    bounds::BoundsRenderer,
     camera::{Camera, CameraControl, CameraUniform, PanCamera, Projection},
+    globals::FrameGlobals,
     draw::DrawCtx,
+    environment::Environment,
     light::LightUniform,
     model::{Material, Mesh, Model},
+    outline::OutlineRenderer,
+    post::{
+        accessibility::{Accessibility, AccessibilitySettings},
+        PostProcessor,
+    },
+    scale::{DisplayScale, VirtualResolution},
     wgpu::{
-        buffer::{create_render_pipeline, InstanceRaw},
-        texture::Texture,
+        buffer::InstanceRaw,
+        immediate::MAX_IMMEDIATE_SIZE,
+        pipeline_cache::PersistentPipelineCache,
+        shader::{PipelineOptions, Shader, ShaderError, UNLIT_WGSL},
+        surface::{self, SurfaceError},
+        texture::{Texture, TextureType, FLAT_NORMAL},
         vertex::Vertex3D,
     },
 };
@@ -35,17 +48,40 @@ use self::{
 };
 
 use super::{
-    app::{InputEventStatus, MouseState},
+    app::{EngineConfig, InputEventStatus, MouseState},
     command::RenderCommand,
+    command_dump::ResourceLabels,
+    context::EngineContext,
+    encoder::{EncoderPool, SubmissionStats},
+    frame::FramePacing,
+    frame_graph::FrameGraphExport,
+    input::{
+        mouse::MouseInput,
+        shortcut::{Chord, ShortcutContext, TOGGLE_FULLSCREEN},
+    },
+    readback::Readbacks,
+    transition::{Transition, TransitionKind},
+    validate::{PipelineShape, PipelineShapes},
+    watchdog::FrameWatchdog,
 };
 use anyhow::*;
 
 #[derive(Debug)]
 pub struct DeviceSurface {
-    pub surface: wgpu::Surface,
+    pub surface: wgpu::Surface<'static>,
     pub device: wgpu::Device,
     pub queue: Arc<wgpu::Queue>,
     pub config: RefCell<wgpu::SurfaceConfiguration>,
+    /// Frame slot and submission tracking, see FramePacing.
+    pub frames: FramePacing,
+    /// Labeled encoders and batched submissions, see EncoderPool.
+    pub encoders: EncoderPool,
+    /// Resource names for command dumps, see RenderWindow::dump_next_frame.
+    pub labels: ResourceLabels,
+    /// Pipeline layouts RenderPass::validate_commands checks draws against.
+    pub pipeline_shapes: PipelineShapes,
+    /// Pooled non blocking buffer and texture downloads, see Readbacks.
+    pub readbacks: Readbacks,
 }
 
 impl DeviceSurface {
@@ -59,33 +95,65 @@ impl DeviceSurface {
         self.config.borrow().width
     }
 
-    pub fn get_current_texture(&self) -> Result<wgpu::SurfaceTexture, wgpu::SurfaceError> {
-        self.surface.get_current_texture()
+    pub fn get_current_texture(&self) -> Result<wgpu::SurfaceTexture, SurfaceError> {
+        surface::acquire(&self.surface)
     }
 
     pub fn create_command_encoder(&self) -> wgpu::CommandEncoder {
-        self.device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Command Encoder"),
-            })
+        self.encoders.create(&self.device, "Render Command Encoder")
+    }
+
+    /// Submits the encoders queued on the pool and tracks the submission for frame pacing.
+    pub fn submit_pending(&self) {
+        if let Some(submission) = self.encoders.flush(&self.queue) {
+            self.frames.record_submission(submission);
+            self.readbacks.map_submitted();
+        }
     }
 }
 
 pub struct RenderWindow {
     device_surface: Rc<DeviceSurface>,
     size: winit::dpi::PhysicalSize<u32>,
-    window: Window,
+    /// Size from the latest resize event, applied by apply_pending_resize.
+    pending_size: Option<winit::dpi::PhysicalSize<u32>>,
+    scale: DisplayScale,
+    virtual_resolution: Option<VirtualResolution>,
+    /// Wrap draws in debug groups named after what they draw, see DrawCtx::debug_labels.
+    debug_labels: bool,
+    #[cfg(feature = "renderdoc")]
+    capture: super::capture::GpuCapture,
+    /// Where the next DrawCtx writes its CommandDump, see dump_next_frame.
+    command_dump: RefCell<Option<PathBuf>>,
+    /// Shared with every DrawCtx, see export_frame_graph.
+    frame_graph: Rc<FrameGraphExport>,
+    #[cfg(feature = "accesskit")]
+    screen_reader: super::access::ScreenReader,
+    window: Arc<Window>,
     clear_color: wgpu::Color,
-    pipeline: Arc<wgpu::RenderPipeline>,
+    shader: Rc<Shader>,
 
     camera: RenderCamera,
+    globals: FrameGlobals,
 
     light_render: light::LightRenderer,
+    environment: Environment,
+    outline: Rc<OutlineRenderer>,
+    bounds: Rc<BoundsRenderer>,
+    post: Rc<RefCell<PostProcessor>>,
+    transition: Transition,
+    accessibility: Accessibility,
+    engine: Rc<EngineContext>,
+    watchdog: Rc<FrameWatchdog>,
+    pipeline_cache: Option<PersistentPipelineCache>,
+    adapter_info: wgpu::AdapterInfo,
+    surface_caps: wgpu::SurfaceCapabilities,
+    default_material: OnceCell<Rc<Material>>,
+    unlit_shader: OnceCell<Rc<Shader>>,
 
     depth_texture: Rc<Texture>,
 
-    event_loop: Option<Rc<EventLoop<()>>>,
-    mouse_state: MouseState,
+    mouse: MouseInput,
     texture_bind_group_layout: wgpu::BindGroupLayout,
 }
 
@@ -103,14 +171,14 @@ impl RenderWindow {
     pub fn camera_uniform(&self) -> &CameraUniform {
         &self.camera.cam.uniform
     }
-    pub const fn handle(&self) -> &Window {
+    pub fn handle(&self) -> &Window {
         &self.window
     }
     pub fn window_id(&self) -> WindowId {
         self.window.id()
     }
 
-    pub fn surface_texture(&self) -> Result<wgpu::SurfaceTexture, wgpu::SurfaceError> {
+    pub fn surface_texture(&self) -> Result<wgpu::SurfaceTexture, SurfaceError> {
         self.device_surface().get_current_texture()
     }
 
@@ -120,13 +188,189 @@ impl RenderWindow {
 
     #[inline]
     pub fn pipeline(&self) -> Arc<wgpu::RenderPipeline> {
-        self.pipeline.clone()
+        self.shader.pipeline()
+    }
+
+    #[inline]
+    pub fn shader(&self) -> &Rc<Shader> {
+        &self.shader
+    }
+    /// Swaps the default mesh shader for `source`, built with the same layout. If it fails
+    /// to compile the error is logged with its line numbers and the previous pipeline stays.
+    pub fn reload_shader(&mut self, source: &str) -> Result<(), ShaderError> {
+        let ds = self.device_surface.clone();
+        match self.shader.with_source(&ds.device, "Normal Shader", source) {
+            Result::Ok(shader) => {
+                self.shader = Rc::new(shader);
+                self.register_mesh_pipeline(&self.shader, "Default Mesh");
+                Result::Ok(())
+            }
+            Err(e) => {
+                log::error!(
+                    "RenderWindow::reload_shader => {}, keeping the previous pipeline\n{}",
+                    e,
+                    e.report
+                );
+                Err(e)
+            }
+        }
+    }
+
+    /// Builds a user shader for the default mesh layout (texture, camera and light groups,
+    /// Vertex3D and InstanceRaw buffers). If it fails to compile the error is logged with
+    /// its line numbers and the built-in unlit shader is returned instead.
+    pub fn mesh_shader(&self, label: &str, source: &str) -> Rc<Shader> {
+        let ds = &self.device_surface;
+        match self.shader.with_source(&ds.device, label, source) {
+            Result::Ok(shader) => {
+                let shader = Rc::new(shader);
+                self.register_mesh_pipeline(&shader, label);
+                shader
+            }
+            Err(e) => {
+                log::error!(
+                    "RenderWindow::mesh_shader => {}, using the unlit shader\n{}",
+                    e,
+                    e.report
+                );
+                self.unlit_shader()
+            }
+        }
+    }
+
+    /// Diffuse only shader for the default mesh layout, the fallback of mesh_shader.
+    pub fn unlit_shader(&self) -> Rc<Shader> {
+        self.unlit_shader
+            .get_or_init(|| {
+                let shader = self
+                    .shader
+                    .with_source(&self.device_surface.device, "Unlit Shader", UNLIT_WGSL)
+                    .unwrap_or_else(|e| panic!("RenderWindow::unlit_shader => {}", e));
+                let shader = Rc::new(shader);
+                self.register_mesh_pipeline(&shader, "Unlit Mesh");
+                shader
+            })
+            .clone()
+    }
+
+    fn register_mesh_pipeline(&self, shader: &Shader, label: &str) {
+        let ds = &self.device_surface;
+        ds.labels.insert(&shader.pipeline(), label);
+        // Texture, camera and light groups, mesh vertices and instances.
+        ds.pipeline_shapes.insert(
+            &shader.pipeline(),
+            PipelineShape {
+                bind_groups: 3,
+                vertex_buffers: 2,
+            },
+        );
     }
+
     #[inline]
     pub fn light_render_pipeline(&self) -> Arc<wgpu::RenderPipeline> {
         self.light_render.pipeline()
     }
 
+    #[inline]
+    pub fn outline_renderer(&self) -> &Rc<OutlineRenderer> {
+        &self.outline
+    }
+
+    /// Debug draw of the bounds of everything drawn, off by default.
+    #[inline]
+    pub fn bounds_renderer(&self) -> &Rc<BoundsRenderer> {
+        &self.bounds
+    }
+
+    /// Post process stack applied to every frame, see PostProcessor::push_effect.
+    #[inline]
+    pub fn post_processor(&self) -> &Rc<RefCell<PostProcessor>> {
+        &self.post
+    }
+
+    /// Time scale, pause and stepping controls for the main loop.
+    #[inline]
+    pub fn engine(&self) -> &Rc<EngineContext> {
+        &self.engine
+    }
+
+    /// Frame slot and submission tracking shared by every pass, see FramePacing.
+    #[inline]
+    pub fn frame_pacing(&self) -> &FramePacing {
+        &self.device_surface.frames
+    }
+
+    /// How many frames the CPU may record ahead of the GPU, clamped to 1..=3.
+    pub fn set_frames_in_flight(&self, frames_in_flight: usize) {
+        self.device_surface
+            .frames
+            .set_frames_in_flight(frames_in_flight);
+    }
+
+    /// Submission counts of the last drawn frame.
+    #[inline]
+    pub fn submission_stats(&self) -> SubmissionStats {
+        self.device_surface.encoders.last_frame_stats()
+    }
+
+    #[inline]
+    pub fn transition(&self) -> &Transition {
+        &self.transition
+    }
+
+    /// Starts a full screen transition, see TransitionKind for what is available.
+    pub fn start_transition(&mut self, kind: TransitionKind, duration: Duration) {
+        let ds = self.device_surface.clone();
+        self.transition.start(
+            &ds.device,
+            &ds.queue,
+            &mut RefCell::borrow_mut(&self.post),
+            kind,
+            duration,
+        );
+    }
+
+    pub fn clear_transition(&mut self) {
+        self.transition.clear(&mut RefCell::borrow_mut(&self.post));
+    }
+
+    pub fn update_transition(&mut self, dt: Duration) {
+        let queue = self.device_surface.queue.clone();
+        self.transition
+            .update(dt, &queue, &mut RefCell::borrow_mut(&self.post));
+    }
+
+    #[inline]
+    pub fn accessibility(&self) -> &AccessibilitySettings {
+        self.accessibility.settings()
+    }
+
+    /// Color vision filter and reduced flash mode, see EngineConfig::accessibility.
+    pub fn set_accessibility(&mut self, settings: AccessibilitySettings) {
+        self.accessibility.set_settings(settings);
+    }
+
+    pub fn update_accessibility(&mut self, dt: Duration) {
+        let ds = self.device_surface.clone();
+        self.accessibility
+            .update(&ds.device, &ds.queue, &mut RefCell::borrow_mut(&self.post), dt);
+    }
+
+    /// Applies a config key that can change while running, `ui_scale` or `accessibility.*`,
+    /// e.g. from a console or ScriptCommand::SetConfig. Returns false for other keys.
+    pub fn apply_setting(&mut self, key: &str, value: &str) -> anyhow::Result<bool> {
+        if key == "ui_scale" {
+            self.set_ui_scale(value.parse()?);
+            return Ok(true);
+        }
+        let mut settings = *self.accessibility.settings();
+        if !settings.set(key, value)? {
+            return Ok(false);
+        }
+        self.set_accessibility(settings);
+        Ok(true)
+    }
+
     #[inline]
     pub fn depth_texture(&self) -> &Rc<Texture> {
         &self.depth_texture
@@ -137,11 +381,46 @@ impl RenderWindow {
         self.light_render.bind_group()
     }
 
+    #[inline]
+    pub(crate) fn light_renderer(&self) -> &light::LightRenderer {
+        &self.light_render
+    }
+
+    #[inline]
+    pub const fn light(&self) -> LightUniform {
+        self.light_render.uniform()
+    }
+
+    /// Position and color of the scene light used by the 3D shaders from the next frame on.
+    pub fn set_light(&mut self, light: LightUniform) {
+        self.light_render
+            .set_uniform(&self.device_surface.queue, light);
+    }
+
+    #[inline]
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    /// Fog and sky used by the 3D shaders from the next frame on.
+    pub fn set_environment(&mut self, environment: Environment) {
+        self.light_render
+            .set_environment(&self.device_surface.queue, &environment);
+        self.environment = environment;
+    }
+
     #[inline]
     pub fn texture_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
         &self.texture_bind_group_layout
     }
 
+    /// Layout of the light bind group at group 2 of the 3D pipeline, for pipelines drawn
+    /// with light_bind_group.
+    #[inline]
+    pub fn light_bind_group_layout(&self) -> Arc<wgpu::BindGroupLayout> {
+        self.light_render.layout()
+    }
+
     #[inline]
     pub fn device_queue(&self) -> &wgpu::Queue {
         &self.device_surface().queue
@@ -152,11 +431,6 @@ impl RenderWindow {
         &self.device_surface().device
     }
 
-    #[inline]
-    pub fn event_loop(&self) -> Option<Rc<EventLoop<()>>> {
-        self.event_loop.clone()
-    }
-
     #[inline]
     pub fn surface_config(&self) -> Ref<wgpu::SurfaceConfiguration> {
         self.device_surface().config.borrow()
@@ -177,8 +451,152 @@ impl RenderWindow {
         self.size
     }
 
-    pub const fn mouse_state(&self) -> MouseState {
-        self.mouse_state
+    /// Window size in logical points, the size UI is laid out in before ui_scale.
+    pub fn logical_size(&self) -> LogicalSize<f64> {
+        self.size.to_logical(self.scale.scale_factor)
+    }
+
+    #[inline]
+    pub fn scale_factor(&self) -> f64 {
+        self.scale.scale_factor
+    }
+
+    /// Scale factor and UI scale, with logical / physical conversions for input and UI.
+    #[inline]
+    pub const fn display_scale(&self) -> DisplayScale {
+        self.scale
+    }
+
+    /// Applies a ScaleFactorChanged event, the surface is resized separately.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        if scale_factor != self.scale.scale_factor {
+            log::info!(
+                "RenderWindow::set_scale_factor => {} -> {}",
+                self.scale.scale_factor,
+                scale_factor
+            );
+            self.scale.scale_factor = scale_factor;
+        }
+    }
+
+    /// User UI and text scale on top of the platform scale factor, see EngineConfig::ui_scale.
+    pub fn set_ui_scale(&mut self, ui_scale: f32) {
+        self.scale.ui_scale = ui_scale;
+    }
+
+    #[inline]
+    pub const fn virtual_resolution(&self) -> Option<VirtualResolution> {
+        self.virtual_resolution
+    }
+
+    /// Fixed resolution the game is laid out in, None renders at the window's size.
+    pub fn set_virtual_resolution(&mut self, virtual_resolution: Option<VirtualResolution>) {
+        self.virtual_resolution = virtual_resolution;
+    }
+
+    #[inline]
+    pub const fn debug_labels(&self) -> bool {
+        self.debug_labels
+    }
+
+    /// Labels draws with the names of their meshes, materials and batches so GPU captures
+    /// show them, on by default in debug builds.
+    pub fn set_debug_labels(&mut self, enabled: bool) {
+        self.debug_labels = enabled;
+    }
+
+    /// Writes every pass and command of the next frame to `path` as JSON when it is
+    /// submitted, compare two dumps with CommandDump::diff. Name pipelines and buffers in
+    /// the dump with DeviceSurface::labels.
+    pub fn dump_next_frame(&self, path: impl Into<PathBuf>) {
+        self.command_dump.replace(Some(path.into()));
+    }
+
+    /// Path requested by dump_next_frame, taken by the DrawCtx of the next frame.
+    pub(crate) fn take_command_dump(&self) -> Option<PathBuf> {
+        self.command_dump.take()
+    }
+
+    /// Writes the passes of the frame and the attachments, buffers and labeled bind groups
+    /// they use to `path` as .dot and .json every time they change, None stops. Render the
+    /// .dot with GraphViz, e.g. `dot -Tsvg frame.dot`.
+    pub fn export_frame_graph(&self, path: Option<PathBuf>) {
+        self.frame_graph.set_path(path);
+    }
+
+    #[inline]
+    pub fn frame_graph_export(&self) -> &Rc<FrameGraphExport> {
+        &self.frame_graph
+    }
+
+    /// RenderDoc captures of the next frame, or automatically on errors in debug builds.
+    #[cfg(feature = "renderdoc")]
+    #[inline]
+    pub fn capture(&self) -> &super::capture::GpuCapture {
+        &self.capture
+    }
+
+    #[cfg(feature = "renderdoc")]
+    #[inline]
+    pub fn capture_mut(&mut self) -> &mut super::capture::GpuCapture {
+        &mut self.capture
+    }
+
+    /// Accessibility tree of the engine's widgets, exposed to screen readers through
+    /// AccessKit when the window came from RenderWindow::with_config.
+    #[cfg(feature = "accesskit")]
+    #[inline]
+    pub fn screen_reader(&self) -> &super::access::ScreenReader {
+        &self.screen_reader
+    }
+
+    #[cfg(feature = "accesskit")]
+    #[inline]
+    pub fn screen_reader_mut(&mut self) -> &mut super::access::ScreenReader {
+        &mut self.screen_reader
+    }
+
+    /// Lets the AccessKit adapter see `event` before the app does.
+    #[cfg(feature = "accesskit")]
+    pub fn process_access_event(&mut self, event: &winit::event::WindowEvent) {
+        self.screen_reader.process_event(&self.window, event);
+    }
+
+    /// Physical x, y, width, height the virtual resolution covers in the window.
+    pub fn virtual_viewport(&self) -> Option<[f32; 4]> {
+        self.virtual_resolution
+            .map(|v| v.viewport([self.size.width, self.size.height]))
+    }
+
+    /// Maps a physical window position, e.g. the cursor, into the virtual resolution. The
+    /// position is returned unchanged without one, None over letterbox bars.
+    pub fn to_virtual(&self, position: [f32; 2]) -> Option<[f32; 2]> {
+        match self.virtual_resolution {
+            Some(v) => v.to_virtual(position, [self.size.width, self.size.height]),
+            None => Some(position),
+        }
+    }
+
+    /// Pressed while any button is held, Moving if the cursor moved this frame.
+    pub fn mouse_state(&self) -> MouseState {
+        if self.mouse.any_down() {
+            MouseState::Pressed
+        } else if self.mouse.delta() != [0.0; 2] {
+            MouseState::Moving
+        } else {
+            MouseState::Idle
+        }
+    }
+
+    /// Per button state, clicks and drags of this frame.
+    #[inline]
+    pub fn mouse(&self) -> &MouseInput {
+        &self.mouse
+    }
+
+    #[inline]
+    pub fn mouse_mut(&mut self) -> &mut MouseInput {
+        &mut self.mouse
     }
 
     #[inline]
@@ -186,73 +604,191 @@ impl RenderWindow {
         self.camera.bind_group()
     }
 
-    pub async fn new() -> anyhow::Result<Self> {
-        let event_loop = EventLoop::new();
-        let window = WindowBuilder::new().build(&event_loop)?;
-        Self::from_winit(window, Some(Rc::new(event_loop))).await
+    /// White diffuse and flat normal material for meshes without textures, uploaded on
+    /// first use or by WarmUp::with_engine_defaults.
+    pub fn default_material(&self) -> anyhow::Result<Rc<Material>> {
+        if let Some(material) = self.default_material.get() {
+            return Ok(material.clone());
+        }
+        let ds = &self.device_surface;
+        let diffuse = Texture::from_color(
+            &ds.device,
+            &ds.queue,
+            [255, 255, 255, 255],
+            TextureType::Diffuse,
+            Some("Default Diffuse"),
+        )?;
+        let normal = Texture::from_color(
+            &ds.device,
+            &ds.queue,
+            FLAT_NORMAL,
+            TextureType::Normal,
+            Some("Default Normal"),
+        )?;
+        let material = Rc::new(Material::new(
+            &ds.device,
+            diffuse,
+            normal,
+            &self.texture_bind_group_layout,
+            Some("Default Material"),
+        ));
+        Ok(self.default_material.get_or_init(|| material).clone())
+    }
+
+    /// Driver pipeline cache to pass to custom pipelines, None where unsupported.
+    #[inline]
+    pub fn pipeline_cache(&self) -> Option<&wgpu::PipelineCache> {
+        self.pipeline_cache.as_ref().map(|c| c.cache().as_ref())
+    }
+
+    /// Writes the pipeline cache to disk, failures are only logged since it is just a cache.
+    pub fn save_pipeline_cache(&self) {
+        if let Some(Err(e)) = self.pipeline_cache.as_ref().map(|c| c.save()) {
+            log::warn!("RenderWindow::save_pipeline_cache => {:#}", e);
+        }
+    }
+
+    /// Opens a window with default options, only valid once the event loop has resumed.
+    pub async fn new(event_loop: &ActiveEventLoop) -> anyhow::Result<Self> {
+        Self::with_config(event_loop, &EngineConfig::default()).await
+    }
+
+    /// Same as RenderWindow::new, with `config` deciding how the device is created.
+    pub async fn with_config(
+        event_loop: &ActiveEventLoop,
+        config: &EngineConfig,
+    ) -> anyhow::Result<Self> {
+        let attributes = config.window.attributes();
+        // AccessKit's adapter has to exist before the window is first shown.
+        #[cfg(feature = "accesskit")]
+        let attributes = attributes.with_visible(false);
+        let window = event_loop.create_window(attributes)?;
+        #[cfg(feature = "accesskit")]
+        let screen_reader =
+            super::access::ScreenReader::new(event_loop, &window, &config.window.title);
+        #[allow(unused_mut)]
+        let mut s = Self::from_winit_with_config(window, config).await?;
+        #[cfg(feature = "accesskit")]
+        {
+            s.screen_reader = screen_reader;
+            s.window.set_visible(true);
+        }
+        Ok(s)
+    }
+
+    pub async fn from_winit(window: winit::window::Window) -> anyhow::Result<Self> {
+        Self::from_winit_with_config(window, &EngineConfig::default()).await
     }
 
-    pub async fn from_winit<EvntLoop>(
+    pub async fn from_winit_with_config(
         window: winit::window::Window,
-        event_loop: EvntLoop,
-    ) -> anyhow::Result<Self>
-    where
-        EvntLoop: Into<Option<Rc<EventLoop<()>>>>,
-    {
+        engine_config: &EngineConfig,
+    ) -> anyhow::Result<Self> {
         let size = window.inner_size();
+        // Surface<'static> needs the window to outlive it, so both share ownership.
+        let window = Arc::new(window);
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
-            dx12_shader_compiler: Default::default(),
+            ..wgpu::InstanceDescriptor::new_without_display_handle()
         });
 
-        let surface = unsafe { instance.create_surface(&window)? };
+        let surface = instance.create_surface(window.clone())?;
 
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::default(),
                 compatible_surface: Some(&surface),
                 force_fallback_adapter: false,
+                apply_limit_buckets: false,
             })
             .await
             .expect("Failed to request compatible adapter");
 
+        let use_pipeline_cache = engine_config.pipeline_cache
+            && adapter.features().contains(wgpu::Features::PIPELINE_CACHE);
+        let use_immediates = engine_config.immediates
+            && adapter.features().contains(wgpu::Features::IMMEDIATES);
+        let mut required_features = wgpu::Features::MAPPABLE_PRIMARY_BUFFERS;
+        if use_pipeline_cache {
+            required_features |= wgpu::Features::PIPELINE_CACHE;
+        }
+        if use_immediates {
+            required_features |= wgpu::Features::IMMEDIATES;
+        }
+        // Baked textures are BC compressed, without it they are decompressed on load.
+        required_features |= adapter.features() & wgpu::Features::TEXTURE_COMPRESSION_BC;
+        let mut required_limits = if cfg!(target_arch = "wasm32") {
+            wgpu::Limits::downlevel_webgl2_defaults()
+        } else {
+            wgpu::Limits::default()
+        };
+        if use_immediates {
+            required_limits.max_immediate_size =
+                adapter.limits().max_immediate_size.min(MAX_IMMEDIATE_SIZE);
+        }
+
         let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::MAPPABLE_PRIMARY_BUFFERS,
-                    limits: if cfg!(target_arch = "wasm32") {
-                        wgpu::Limits::downlevel_webgl2_defaults()
-                    } else {
-                        wgpu::Limits::default()
-                    },
-                    label: None,
-                },
-                None,
-            )
+            .request_device(&wgpu::DeviceDescriptor {
+                label: None,
+                required_features,
+                required_limits,
+                ..Default::default()
+            })
             .await
             .expect("Failed to request compatible device");
 
-        let surface_caps = surface.get_capabilities(&adapter);
+        let pipeline_cache = if use_pipeline_cache {
+            match engine_config.cache_dir() {
+                Result::Ok(dir) => {
+                    PersistentPipelineCache::load(&device, &adapter.get_info(), dir)
+                }
+                Err(e) => {
+                    log::warn!("RenderWindow::from_winit => no pipeline cache: {:#}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
 
-        // Assumes sRGB shader format.
-        let surface_format = surface_caps
-            .formats
-            .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
+        let adapter_info = adapter.get_info();
+        let surface_caps = surface.get_capabilities(&adapter);
+        let mut surface_options = engine_config.surface.clone();
+        let transparent = engine_config.window.transparent;
+        if transparent && surface_options.alpha_mode == wgpu::CompositeAlphaMode::Auto {
+            surface_options.alpha_mode = surface::transparent_alpha_mode(&surface_caps);
+        }
+        let chosen = surface_options.choose(&surface_caps);
+        log::info!(
+            "RenderWindow::from_winit => {} ({:?}, {:?}), surface {:?}, alpha {:?}",
+            adapter_info.name,
+            adapter_info.device_type,
+            adapter_info.backend,
+            chosen.format,
+            chosen.alpha_mode
+        );
 
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
+            format: chosen.format,
+            color_space: wgpu::SurfaceColorSpace::Auto,
             width: size.width,
             height: size.height,
             present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
+            alpha_mode: chosen.alpha_mode,
+            view_formats: chosen.view_formats,
+            desired_maximum_frame_latency: 2,
         };
         surface.configure(&device, &config);
         let queue = Arc::new(queue);
+        #[cfg(feature = "renderdoc")]
+        let capture = {
+            let capture = super::capture::GpuCapture::new();
+            if capture.is_available() {
+                device.on_uncaptured_error(Arc::new(capture.error_handler()));
+            }
+            capture
+        };
 
         let config = RefCell::new(config);
         let surface = DeviceSurface {
@@ -260,6 +796,11 @@ impl RenderWindow {
             device,
             queue,
             config,
+            frames: FramePacing::default(),
+            encoders: EncoderPool::new(),
+            labels: ResourceLabels::new(),
+            pipeline_shapes: PipelineShapes::new(),
+            readbacks: Readbacks::new(),
         };
 
         let device = &surface.device;
@@ -309,33 +850,50 @@ impl RenderWindow {
 
         let light_render = light::LightRenderer::new(
             &surface.device,
+            &surface.queue,
             surface.config.borrow().format,
             camera.layout().as_ref(),
         );
 
+        let outline = OutlineRenderer::new(
+            device,
+            config.borrow().format,
+            size.width,
+            size.height,
+            camera.layout().as_ref(),
+        );
+        let bounds =
+            BoundsRenderer::new(device, config.borrow().format, camera.layout().as_ref());
+
+        let mut post = PostProcessor::new(device, config.borrow().format, size.width, size.height);
+        let transition = Transition::new(device, &surface.queue, &mut post);
+        let accessibility = Accessibility::new(device, &mut post, engine_config.accessibility);
+
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
                 bind_group_layouts: &[
-                    &texture_bind_group_layout,
-                    camera.layout().as_ref(),
-                    light_render.layout().as_ref(),
+                    Some(&texture_bind_group_layout),
+                    Some(camera.layout().as_ref()),
+                    Some(light_render.layout().as_ref()),
                 ],
-                push_constant_ranges: &[],
+                immediate_size: 0,
             });
 
-        let render_pipeline = {
+        let shader = {
             let shader = wgpu::ShaderModuleDescriptor {
                 label: Some("Normal Shader"),
                 source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/basic.wgsl").into()),
             };
-            create_render_pipeline(
-                &device,
-                &render_pipeline_layout,
+            Shader::new(
+                device,
+                shader,
+                render_pipeline_layout,
                 config.borrow().format,
                 Some(Texture::DEPTH_FORMAT),
                 &[Vertex3D::buffer_layout(), InstanceRaw::buffer_layout()],
-                shader,
+                PipelineOptions::default(),
+                pipeline_cache.as_ref().map(|c| c.cache().clone()),
             )
         };
 
@@ -349,19 +907,123 @@ impl RenderWindow {
         let s = Self {
             device_surface: surface,
             size,
+            pending_size: None,
+            scale: DisplayScale::new(window.scale_factor()).with_ui_scale(engine_config.ui_scale),
+            virtual_resolution: None,
+            debug_labels: cfg!(debug_assertions),
+            #[cfg(feature = "renderdoc")]
+            capture,
+            command_dump: RefCell::new(None),
+            frame_graph: Rc::new(FrameGraphExport::new()),
+            #[cfg(feature = "accesskit")]
+            screen_reader: super::access::ScreenReader::disconnected(&window.title()),
             window,
-            clear_color: wgpu::Color::BLACK,
-            pipeline: Arc::new(render_pipeline),
+            clear_color: if transparent {
+                wgpu::Color::TRANSPARENT
+            } else {
+                wgpu::Color::BLACK
+            },
+            shader: Rc::new(shader),
             camera,
+            globals: FrameGlobals::new(size.width, size.height),
             depth_texture,
             light_render,
-            event_loop: event_loop.into(),
-            mouse_state: MouseState::Idle,
+            environment: Environment::default(),
+            outline: Rc::new(outline),
+            bounds: Rc::new(bounds),
+            post: Rc::new(RefCell::new(post)),
+            transition,
+            accessibility,
+            engine: Rc::new(EngineContext::new()),
+            watchdog: Rc::new(FrameWatchdog::new(Duration::from_millis(
+                engine_config.watchdog_ms,
+            ))),
+            pipeline_cache,
+            adapter_info,
+            surface_caps,
+            default_material: OnceCell::new(),
+            unlit_shader: OnceCell::new(),
+            mouse: MouseInput::new(),
             texture_bind_group_layout,
         };
+        if engine_config.window.click_through {
+            s.set_click_through(true);
+        }
+        s.register_mesh_pipeline(&s.shader, "Default Mesh");
+        let ds = &s.device_surface;
+        ds.labels.insert(&s.light_render_pipeline(), "Light Mesh");
+        ds.pipeline_shapes.insert(
+            &s.light_render_pipeline(),
+            PipelineShape {
+                bind_groups: 2,
+                vertex_buffers: 1,
+            },
+        );
+        s.engine.shortcuts().borrow_mut().register(
+            TOGGLE_FULLSCREEN,
+            Chord::new(KeyCode::F11),
+            ShortcutContext::Global,
+            0,
+        );
+        #[cfg(feature = "renderdoc")]
+        s.engine.shortcuts().borrow_mut().register(
+            super::capture::CAPTURE_FRAME,
+            Chord::new(KeyCode::F12).ctrl(),
+            ShortcutContext::Global,
+            0,
+        );
+        #[cfg(feature = "accesskit")]
+        {
+            use super::access::{ACTIVATE, DECREMENT, FOCUS_NEXT, FOCUS_PREV, INCREMENT};
+            let mut shortcuts = s.engine.shortcuts().borrow_mut();
+            for (action, chord) in [
+                (FOCUS_NEXT, Chord::new(KeyCode::Tab)),
+                (FOCUS_PREV, Chord::new(KeyCode::Tab).shift()),
+                (ACTIVATE, Chord::new(KeyCode::Enter)),
+                (INCREMENT, Chord::new(KeyCode::ArrowRight).ctrl()),
+                (DECREMENT, Chord::new(KeyCode::ArrowLeft).ctrl()),
+            ] {
+                shortcuts.register(action, chord, ShortcutContext::Global, 0);
+            }
+        }
         Ok(s)
     }
 
+    /// Color the main pass is cleared with each frame.
+    #[inline]
+    pub fn clear_color(&self) -> wgpu::Color {
+        self.clear_color
+    }
+
+    pub fn set_clear_color(&mut self, color: wgpu::Color) {
+        self.clear_color = color;
+    }
+
+    #[inline]
+    pub fn is_fullscreen(&self) -> bool {
+        self.window.fullscreen().is_some()
+    }
+
+    /// Switches between windowed and borderless fullscreen on the current monitor.
+    pub fn toggle_fullscreen(&self) {
+        let fullscreen = match self.window.fullscreen() {
+            Some(_) => None,
+            None => Some(Fullscreen::Borderless(None)),
+        };
+        self.window.set_fullscreen(fullscreen);
+    }
+
+    /// Lets mouse input pass through the window, returns false where the platform can't.
+    pub fn set_click_through(&self, click_through: bool) -> bool {
+        match self.window.set_cursor_hittest(!click_through) {
+            Result::Ok(()) => true,
+            Err(e) => {
+                log::warn!("RenderWindow::set_click_through => {}", e);
+                false
+            }
+        }
+    }
+
     // pub fn begin_draw(&self) -> Result<DrawCtx, wgpu::SurfaceError> {
     // let mut encoder =
     // self.surface
@@ -382,12 +1044,54 @@ impl RenderWindow {
     //
     pub fn update_camera(&mut self, dt: std::time::Duration) {
         self.camera.frame_update(dt);
+        #[cfg(feature = "audio")]
+        let cam = &self.camera.cam.cam;
+        #[cfg(feature = "audio")]
+        self.engine
+            .audio()
+            .follow_camera(cam.position(), cam.forward(), dt);
         self.set_camera_uniform(CameraUniform::from_camera(
             &self.camera.cam.cam,
             &self.camera.projection,
         ));
         self.write_camera_buffer();
     }
+    #[inline]
+    /// The adapter the window renders with.
+    pub fn adapter_info(&self) -> &wgpu::AdapterInfo {
+        &self.adapter_info
+    }
+
+    /// What the surface supports, the options in EngineConfig::surface are chosen from these.
+    pub fn surface_capabilities(&self) -> &wgpu::SurfaceCapabilities {
+        &self.surface_caps
+    }
+
+    /// Logs diagnostics for frames over EngineConfig::watchdog_ms, see FrameWatchdog.
+    pub fn watchdog(&self) -> &Rc<FrameWatchdog> {
+        &self.watchdog
+    }
+
+    pub fn globals(&self) -> &FrameGlobals {
+        &self.globals
+    }
+
+    /// Tracks the cursor for the globals uniform, in physical pixels.
+    pub fn set_cursor_position(&mut self, x: f32, y: f32) {
+        self.globals.cursor = [x, y];
+    }
+
+    /// Advances the per frame globals and writes them to the camera bind group.
+    pub fn update_globals(&mut self, dt: std::time::Duration) {
+        self.globals.resolution = [self.size.width, self.size.height];
+        self.globals.advance(dt);
+        self.write_buffer(
+            &self.camera.globals_buffer,
+            0,
+            bytemuck::cast_slice(&[self.globals.uniform()]),
+        );
+    }
+
     pub fn set_camera_uniform(&mut self, uniform: CameraUniform) {
         self.camera.set_uniform(uniform);
     }
@@ -412,7 +1116,7 @@ impl RenderWindow {
     // pub fn submit_draw_ctx(&mut self, ctx: &DrawCtx) -> Result<(), wgpu::SurfaceError> {
     // self.submit_frame()
     // }
-    pub fn submit_frame(&self, ctx: DrawCtx) -> Result<(), wgpu::SurfaceError> {
+    pub fn submit_frame(&self, ctx: DrawCtx) -> Result<(), SurfaceError> {
         ctx.submit()
     }
 
@@ -427,6 +1131,31 @@ impl RenderWindow {
     // draw_ctx.submit(encoder.into_inner());
     // std::result::Result::Ok(())
 
+    /// Queues a resize for the next frame boundary, see apply_pending_resize. Resizing
+    /// while a DrawCtx is alive would leave it with a stale depth texture.
+    pub fn request_resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.pending_size = Some(new_size);
+    }
+
+    #[inline]
+    pub fn has_pending_resize(&self) -> bool {
+        self.pending_size.is_some()
+    }
+
+    /// Applies the latest requested size, returns true if there was one. Only the last of
+    /// several resize events in a frame is applied.
+    pub fn apply_pending_resize(&mut self) -> bool {
+        match self.pending_size.take() {
+            Some(size) => {
+                self.resize(size);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reconfigures the surface and recreates size dependent targets right away, prefer
+    /// request_resize from event handlers.
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
@@ -440,7 +1169,17 @@ impl RenderWindow {
                 let c = self.surface_config();
                 let t = Texture::depth_texture(self.surface_device(), &*c, Some("Depth Texture"));
                 Rc::new(t)
-            }
+            };
+            self.outline = Rc::new(self.outline.resized(
+                self.surface_device(),
+                new_size.width,
+                new_size.height,
+            ));
+            RefCell::borrow_mut(&self.post).resize(
+                self.surface_device(),
+                new_size.width,
+                new_size.height,
+            );
         }
 
         self.camera
@@ -452,17 +1191,15 @@ impl RenderWindow {
 pub struct RenderCamera {
     cam: PanCamera,
     buffer: Arc<wgpu::Buffer>,
+    /// GlobalsUniform at binding 1, see RenderWindow::update_globals.
+    globals_buffer: Arc<wgpu::Buffer>,
     bind_group: Arc<wgpu::BindGroup>,
     layout: Arc<wgpu::BindGroupLayout>,
     projection: Projection,
 }
 
 impl RenderCamera {
-    pub fn process_keyboard(
-        &mut self,
-        key: VirtualKeyCode,
-        state: ElementState,
-    ) -> InputEventStatus {
+    pub fn process_keyboard(&mut self, key: KeyCode, state: ElementState) -> InputEventStatus {
         self.cam.process_keyboard(key, state)
     }
 
@@ -498,26 +1235,39 @@ impl RenderCamera {
             contents: bytemuck::cast_slice(&[cam_uniform]),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
+        let globals = FrameGlobals::new(rs.width(), rs.height()).uniform();
+        let globals_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Globals Buffer"),
+            contents: bytemuck::cast_slice(&[globals]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
         let cam_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
+                entries: &[uniform_entry(0), uniform_entry(1)],
                 label: Some("camera_bind_group_layout"),
             });
         let cam_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             layout: &cam_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: cam_buffer.as_entire_binding(),
-            }],
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: cam_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: globals_buffer.as_entire_binding(),
+                },
+            ],
             label: Some("camera_bind_group"),
         });
 
@@ -534,6 +1284,7 @@ impl RenderCamera {
         Self {
             cam: pan_cam,
             buffer,
+            globals_buffer: Arc::new(globals_buffer),
             bind_group,
             layout,
             projection,
@@ -547,6 +1298,10 @@ impl RenderCamera {
     pub fn buffer(&self) -> Arc<wgpu::Buffer> {
         self.buffer.clone()
     }
+    /// GlobalsUniform buffer, for camera bind groups of offscreen views.
+    pub fn globals_buffer(&self) -> Arc<wgpu::Buffer> {
+        self.globals_buffer.clone()
+    }
     pub fn bind_group(&self) -> Arc<wgpu::BindGroup> {
         self.bind_group.clone()
     }
@@ -566,18 +1321,24 @@ pub mod light {
     use crate::{
         eng::command::RenderCommand,
         gfx::{
+            environment::Environment,
             light::LightUniform,
             model::{Mesh, Model},
+            probe::{create_cube_texture, create_probe_sampler, ProbeUniform},
             wgpu::{buffer::create_render_pipeline, texture::Texture, vertex::Vertex3D},
         },
+        sys::cubemap::BakedCubemap,
     };
 
     pub struct LightRenderer {
         render_pipeline: Arc<wgpu::RenderPipeline>,
         uniform: LightUniform,
         buffer: Arc<wgpu::Buffer>,
+        environment: Arc<wgpu::Buffer>,
         bind_group: Arc<wgpu::BindGroup>,
         layout: Arc<wgpu::BindGroupLayout>,
+        /// Black cube and disabled probe uniform bound when no reflection probe is.
+        _default_probe: (wgpu::Texture, wgpu::Buffer),
     }
 
     impl LightRenderer {
@@ -586,6 +1347,7 @@ pub mod light {
         }
         pub fn new(
             device: &Device,
+            queue: &wgpu::Queue,
             format: wgpu::TextureFormat,
             cam_bind_group_layout: &wgpu::BindGroupLayout,
         ) -> Self {
@@ -600,34 +1362,91 @@ pub mod light {
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             });
 
+            // Fog and sky ride along with the light, so every lit shader can use them.
+            let environment = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Environment Uniform Buffer"),
+                contents: bytemuck::cast_slice(&[Environment::default().uniform()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
             let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
                     },
-                    count: None,
-                }],
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
                 label: None,
             });
 
-            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-                layout: &layout,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: buffer.as_entire_binding(),
-                }],
-                label: None,
+            let (probe_texture, probe_view) = create_cube_texture(
+                device,
+                queue,
+                &BakedCubemap::solid(1, [0, 0, 0, 255]),
+                Some("Default Reflection Probe"),
+            );
+            let probe_uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Default Reflection Probe Uniform"),
+                contents: bytemuck::cast_slice(&[ProbeUniform::default()]),
+                usage: wgpu::BufferUsages::UNIFORM,
             });
+            let bind_group = create_light_bind_group(
+                device,
+                &layout,
+                &buffer,
+                &environment,
+                &probe_view,
+                &create_probe_sampler(device),
+                &probe_uniform,
+            );
 
             let render_pipeline = {
                 let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                     label: Some("Light Pipeline Layout"),
-                    bind_group_layouts: &[cam_bind_group_layout, &layout],
-                    push_constant_ranges: &[],
+                    bind_group_layouts: &[Some(cam_bind_group_layout), Some(&layout)],
+                    immediate_size: 0,
                 });
                 let shader = wgpu::ShaderModuleDescriptor {
                     label: Some("Light Shader"),
@@ -652,10 +1471,32 @@ pub mod light {
                 render_pipeline,
                 uniform,
                 buffer,
+                environment: Arc::new(environment),
                 bind_group,
                 layout,
+                _default_probe: (probe_texture, probe_uniform),
             }
         }
+
+        /// Light bind group sharing the light and environment buffers, with a reflection
+        /// probe's cube texture and uniform, see gfx::probe::ReflectionProbes.
+        pub fn probe_bind_group(
+            &self,
+            device: &Device,
+            probe_view: &wgpu::TextureView,
+            probe_sampler: &wgpu::Sampler,
+            probe_uniform: &wgpu::Buffer,
+        ) -> wgpu::BindGroup {
+            create_light_bind_group(
+                device,
+                &self.layout,
+                &self.buffer,
+                &self.environment,
+                probe_view,
+                probe_sampler,
+                probe_uniform,
+            )
+        }
         pub fn pipeline(&self) -> Arc<wgpu::RenderPipeline> {
             self.render_pipeline.clone()
         }
@@ -663,6 +1504,60 @@ pub mod light {
         pub fn layout(&self) -> Arc<wgpu::BindGroupLayout> {
             self.layout.clone()
         }
+
+        pub const fn uniform(&self) -> LightUniform {
+            self.uniform
+        }
+
+        pub fn set_uniform(&mut self, queue: &wgpu::Queue, uniform: LightUniform) {
+            self.uniform = uniform;
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+        }
+
+        pub fn set_environment(&self, queue: &wgpu::Queue, environment: &Environment) {
+            queue.write_buffer(
+                &self.environment,
+                0,
+                bytemuck::cast_slice(&[environment.uniform()]),
+            );
+        }
+    }
+
+    fn create_light_bind_group(
+        device: &Device,
+        layout: &wgpu::BindGroupLayout,
+        light: &wgpu::Buffer,
+        environment: &wgpu::Buffer,
+        probe_view: &wgpu::TextureView,
+        probe_sampler: &wgpu::Sampler,
+        probe_uniform: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: light.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: environment.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(probe_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(probe_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: probe_uniform.as_entire_binding(),
+                },
+            ],
+            label: None,
+        })
     }
 
     pub fn draw_light_mesh(
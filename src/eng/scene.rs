@@ -0,0 +1,584 @@
+use std::{
+    any::{Any, TypeId},
+    fmt,
+    path::Path,
+};
+
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, One, Point3, Quaternion, Vector3};
+use serde::{Deserialize, Serialize};
+
+use crate::gfx::{
+    debug_name::DebugName, environment::Environment, gizmo::Ray, transform::Transform,
+    weather::WeatherSettings,
+};
+
+use super::prefab::{PrefabInstance, PrefabLink};
+
+/// Value of an editable component property.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PropertyValue {
+    Bool(bool),
+    Float(f32),
+    Vec3([f32; 3]),
+    Text(String),
+}
+
+/// Runtime data attached to a SceneEntity by type, e.g. a Sprite or a game's Health.
+/// Implemented for every clonable type, components are not saved with the scene.
+pub trait Component: Any + fmt::Debug {
+    fn clone_component(&self) -> Box<dyn Component>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any + fmt::Debug + Clone> Component for T {
+    fn clone_component(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// At most one component of each type, sorted by type for lookups by binary search.
+#[derive(Debug, Default)]
+pub struct Components {
+    entries: Vec<(TypeId, Box<dyn Component>)>,
+}
+
+impl Clone for Components {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self
+                .entries
+                .iter()
+                .map(|(ty, c)| (*ty, (**c).clone_component()))
+                .collect(),
+        }
+    }
+}
+
+impl Components {
+    fn find(&self, ty: TypeId) -> Result<usize, usize> {
+        self.entries.binary_search_by(|(t, _)| t.cmp(&ty))
+    }
+
+    /// Adds `component`, returning the one of the same type it replaced.
+    pub fn insert<T: Component>(&mut self, component: T) -> Option<T> {
+        let ty = TypeId::of::<T>();
+        match self.find(ty) {
+            Ok(i) => {
+                let old = std::mem::replace(&mut self.entries[i].1, Box::new(component));
+                downcast(old)
+            }
+            Err(i) => {
+                self.entries.insert(i, (ty, Box::new(component)));
+                None
+            }
+        }
+    }
+
+    pub fn remove<T: Component>(&mut self) -> Option<T> {
+        let i = self.find(TypeId::of::<T>()).ok()?;
+        downcast(self.entries.remove(i).1)
+    }
+
+    pub fn get<T: Component>(&self) -> Option<&T> {
+        let i = self.find(TypeId::of::<T>()).ok()?;
+        self.entries[i].1.as_any().downcast_ref()
+    }
+
+    pub fn get_mut<T: Component>(&mut self) -> Option<&mut T> {
+        let i = self.find(TypeId::of::<T>()).ok()?;
+        self.entries[i].1.as_any_mut().downcast_mut()
+    }
+
+    #[inline]
+    pub fn contains<T: Component>(&self) -> bool {
+        self.find(TypeId::of::<T>()).is_ok()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn downcast<T: Component>(component: Box<dyn Component>) -> Option<T> {
+    let any: Box<dyn Any> = component;
+    any.downcast().ok().map(|c| *c)
+}
+
+/// Components fetched together by Scene::query, `&T` or a tuple of up to four of them.
+pub trait ComponentQuery<'a> {
+    type Item;
+    fn fetch(components: &'a Components) -> Option<Self::Item>;
+}
+
+impl<'a, T: Component> ComponentQuery<'a> for &'a T {
+    type Item = &'a T;
+
+    fn fetch(components: &'a Components) -> Option<Self::Item> {
+        components.get::<T>()
+    }
+}
+
+macro_rules! impl_component_query {
+    ($($q:ident),+) => {
+        impl<'a, $($q: ComponentQuery<'a>),+> ComponentQuery<'a> for ($($q,)+) {
+            type Item = ($($q::Item,)+);
+
+            fn fetch(components: &'a Components) -> Option<Self::Item> {
+                Some(($($q::fetch(components)?,)+))
+            }
+        }
+    };
+}
+
+impl_component_query!(A);
+impl_component_query!(A, B);
+impl_component_query!(A, B, C);
+impl_component_query!(A, B, C, D);
+
+/// An object the editor can select, move and save.
+#[derive(Debug, Clone)]
+pub struct SceneEntity {
+    pub name: String,
+    /// Relative to the parent, or world space for root entities.
+    pub transform: Transform,
+    /// Radius of the bounding sphere used for picking, before scaling.
+    pub radius: f32,
+    /// Component properties shown in the property panel after the transform rows.
+    pub properties: Vec<(String, PropertyValue)>,
+    /// Set for entities spawned from a prefab, see Scene::instantiate.
+    pub prefab: Option<PrefabLink>,
+    /// Sorted labels for Scene::tagged, saved with the scene.
+    tags: Vec<String>,
+    pub components: Components,
+    /// Index into Scene::entities, set with Scene::set_parent.
+    parent: Option<usize>,
+    world: Matrix4<f32>,
+    world_changed: bool,
+    /// Parent changed, so the world transform needs rebuilding even if the local did not.
+    reparented: bool,
+}
+
+impl DebugName for SceneEntity {
+    fn debug_kind(&self) -> &'static str {
+        "Entity"
+    }
+
+    fn debug_name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl SceneEntity {
+    pub fn new(name: impl Into<String>, transform: Transform) -> Self {
+        let world = transform.clone().model();
+        Self {
+            name: name.into(),
+            transform,
+            radius: 0.5,
+            properties: Vec::new(),
+            prefab: None,
+            tags: Vec::new(),
+            components: Components::default(),
+            parent: None,
+            world,
+            world_changed: false,
+            reparented: false,
+        }
+    }
+
+    #[inline]
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    /// World matrix as of the last Scene::update_transforms.
+    #[inline]
+    pub fn world(&self) -> Matrix4<f32> {
+        self.world
+    }
+
+    #[inline]
+    pub fn world_position(&self) -> Vector3<f32> {
+        self.world.w.truncate()
+    }
+
+    /// True if the last Scene::update_transforms moved this entity, either through its own
+    /// transform or one of its parents. Renderers and physics can skip the others.
+    #[inline]
+    pub fn world_changed(&self) -> bool {
+        self.world_changed
+    }
+
+    pub fn with_property(mut self, name: impl Into<String>, value: PropertyValue) -> Self {
+        self.properties.push((name.into(), value));
+        self
+    }
+
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.add_tag(tag);
+        self
+    }
+
+    pub fn with_component<T: Component>(mut self, component: T) -> Self {
+        self.components.insert(component);
+        self
+    }
+
+    #[inline]
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Returns false if the entity already had `tag`.
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        match self.tags.binary_search_by(|t| t.as_str().cmp(tag)) {
+            Ok(_) => false,
+            Err(i) => {
+                self.tags.insert(i, String::from(tag));
+                true
+            }
+        }
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        match self.tags.binary_search_by(|t| t.as_str().cmp(tag)) {
+            Ok(i) => {
+                self.tags.remove(i);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags
+            .binary_search_by(|t| t.as_str().cmp(tag))
+            .is_ok()
+    }
+
+    /// Distance along `ray` to this entity's bounding sphere, in world space.
+    pub fn intersect(&self, ray: &Ray) -> Option<f32> {
+        let scale = self
+            .world
+            .x
+            .truncate()
+            .magnitude()
+            .max(self.world.y.truncate().magnitude())
+            .max(self.world.z.truncate().magnitude());
+        let radius = self.radius * scale;
+        let to_center = Point3::from_vec(self.world_position()) - ray.origin;
+        let t = to_center.dot(ray.dir);
+        let dist2 = to_center.magnitude2() - t * t;
+        if t < 0.0 || dist2 > radius * radius {
+            return None;
+        }
+        Some(t - (radius * radius - dist2).sqrt())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EntityFile {
+    name: String,
+    position: [f32; 3],
+    rotation: [f32; 4],
+    size: [f32; 3],
+    radius: f32,
+    #[serde(default)]
+    properties: Vec<(String, PropertyValue)>,
+    #[serde(default)]
+    prefab: Option<PrefabLink>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    parent: Option<usize>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct SceneFile {
+    entities: Vec<EntityFile>,
+    #[serde(default)]
+    instances: Vec<PrefabInstance>,
+    #[serde(default)]
+    environment: Environment,
+    #[serde(default)]
+    weather: WeatherSettings,
+}
+
+/// Flat list of entities, saved to and loaded from RON.
+#[derive(Debug, Default, Clone)]
+pub struct Scene {
+    pub entities: Vec<SceneEntity>,
+    /// Prefab instances, with the overrides they were spawned with.
+    pub instances: Vec<PrefabInstance>,
+    /// Fog and sky, apply with RenderWindow::set_environment.
+    pub environment: Environment,
+    /// Rain or snow, apply with WeatherState::set_settings.
+    pub weather: WeatherSettings,
+}
+
+impl Scene {
+    pub fn to_ron(&self) -> anyhow::Result<String> {
+        let file = SceneFile {
+            entities: self
+                .entities
+                .iter()
+                .map(|e| EntityFile {
+                    name: e.name.clone(),
+                    position: e.transform.position().into(),
+                    rotation: e.transform.rotation().into(),
+                    size: e.transform.size().into(),
+                    radius: e.radius,
+                    properties: e.properties.clone(),
+                    prefab: e.prefab,
+                    tags: e.tags.clone(),
+                    parent: e.parent,
+                })
+                .collect(),
+            instances: self.instances.clone(),
+            environment: self.environment,
+            weather: self.weather,
+        };
+        Ok(ron::ser::to_string_pretty(
+            &file,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn from_ron(source: &str) -> anyhow::Result<Self> {
+        let file: SceneFile = ron::from_str(source)?;
+        let parents: Vec<_> = file.entities.iter().map(|e| e.parent).collect();
+        let entities = file
+            .entities
+            .into_iter()
+            .map(|e| {
+                let mut entity = SceneEntity::new(
+                    e.name,
+                    Transform::new(
+                        e.position.into(),
+                        Quaternion::from(e.rotation),
+                        e.size.into(),
+                    ),
+                );
+                entity.radius = e.radius;
+                entity.properties = e.properties;
+                entity.prefab = e.prefab;
+                for tag in e.tags.iter() {
+                    entity.add_tag(tag);
+                }
+                entity
+            })
+            .collect();
+        let mut scene = Self {
+            entities,
+            instances: file.instances,
+            environment: file.environment,
+            weather: file.weather,
+        };
+        for (child, parent) in parents.into_iter().enumerate() {
+            if parent.is_some() {
+                scene.set_parent(child, parent)?;
+            }
+        }
+        scene.update_transforms();
+        Ok(scene)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::from_ron(&std::fs::read_to_string(path)?)
+    }
+
+    /// Makes `child` relative to `parent`, or a root entity for None. Its local transform
+    /// is kept, so it moves to the same offset from the new parent.
+    pub fn set_parent(&mut self, child: usize, parent: Option<usize>) -> anyhow::Result<()> {
+        let len = self.entities.len();
+        if child >= len || parent.is_some_and(|p| p >= len) {
+            anyhow::bail!(
+                "Scene::set_parent => entity {} or parent {:?} out of range ({} entities)",
+                child,
+                parent,
+                len
+            );
+        }
+        let mut ancestor = parent;
+        while let Some(a) = ancestor {
+            if a == child {
+                anyhow::bail!(
+                    "Scene::set_parent => parenting {} to {:?} would form a cycle",
+                    child,
+                    parent
+                );
+            }
+            ancestor = self.entities[a].parent;
+        }
+        let entity = &mut self.entities[child];
+        entity.parent = parent;
+        entity.reparented = true;
+        Ok(())
+    }
+
+    /// Direct children of `parent`.
+    pub fn children(&self, parent: usize) -> impl Iterator<Item = usize> + '_ {
+        self.entities
+            .iter()
+            .enumerate()
+            .filter(move |(_, e)| e.parent == Some(parent))
+            .map(|(i, _)| i)
+    }
+
+    /// Removes the entities `keep` returns false for, remapping parent indices. Children
+    /// of a removed entity become roots and keep their local transform.
+    pub fn retain_entities(&mut self, mut keep: impl FnMut(&SceneEntity) -> bool) {
+        let mut next = 0;
+        let remap: Vec<Option<usize>> = self
+            .entities
+            .iter()
+            .map(|e| {
+                keep(e).then(|| {
+                    next += 1;
+                    next - 1
+                })
+            })
+            .collect();
+        let mut index = 0;
+        self.entities.retain(|_| {
+            index += 1;
+            remap[index - 1].is_some()
+        });
+        for entity in self.entities.iter_mut() {
+            if let Some(parent) = entity.parent {
+                entity.parent = remap[parent];
+                entity.reparented |= entity.parent.is_none();
+            }
+        }
+    }
+
+    /// Rebuilds the world transform of every entity whose transform or parent chain
+    /// changed since the last call, flagging them for SceneEntity::world_changed.
+    /// Runs as a single pass with parents before children, no recursion. Returns the
+    /// number of entities that moved.
+    pub fn update_transforms(&mut self) -> usize {
+        let mut moved = 0;
+        let in_order = self
+            .entities
+            .iter()
+            .enumerate()
+            .all(|(i, e)| e.parent.is_none_or(|p| p < i));
+        if in_order {
+            for i in 0..self.entities.len() {
+                moved += self.update_world(i) as usize;
+            }
+        } else {
+            for i in self.hierarchy_order() {
+                moved += self.update_world(i) as usize;
+            }
+        }
+        moved
+    }
+
+    /// Entity indices sorted by depth, so parents come before their children.
+    fn hierarchy_order(&self) -> Vec<usize> {
+        let mut depths: Vec<Option<u32>> = vec![None; self.entities.len()];
+        let mut chain = Vec::new();
+        for i in 0..self.entities.len() {
+            let mut node = i;
+            while depths[node].is_none() {
+                chain.push(node);
+                match self.entities[node].parent {
+                    Some(p) => node = p,
+                    None => break,
+                }
+            }
+            let mut depth = depths[node].map_or(0, |d| d + 1);
+            while let Some(n) = chain.pop() {
+                depths[n] = Some(depth);
+                depth += 1;
+            }
+        }
+        let mut order: Vec<usize> = (0..self.entities.len()).collect();
+        order.sort_by_key(|i| depths[*i]);
+        order
+    }
+
+    fn update_world(&mut self, i: usize) -> bool {
+        let (parent_world, parent_changed) = match self.entities[i].parent {
+            Some(p) => (self.entities[p].world, self.entities[p].world_changed),
+            None => (Matrix4::one(), false),
+        };
+        let entity = &mut self.entities[i];
+        let changed = entity.transform.take_changed() | entity.reparented | parent_changed;
+        if changed {
+            entity.world = parent_world * entity.transform.model();
+        }
+        entity.reparented = false;
+        entity.world_changed = changed;
+        changed
+    }
+
+    /// Entities labelled `tag`, with their index.
+    pub fn tagged<'a>(
+        &'a self,
+        tag: &'a str,
+    ) -> impl Iterator<Item = (usize, &'a SceneEntity)> + 'a {
+        self.entities
+            .iter()
+            .enumerate()
+            .filter(move |(_, e)| e.has_tag(tag))
+    }
+
+    /// Entities having every component in `Q`, e.g. `scene.query::<(&Sprite, &Health)>()`,
+    /// with their index. The entity gives access to its transform and tags.
+    pub fn query<'a, Q: ComponentQuery<'a>>(
+        &'a self,
+    ) -> impl Iterator<Item = (usize, &'a SceneEntity, Q::Item)> + 'a {
+        self.entities
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| Q::fetch(&e.components).map(|item| (i, e, item)))
+    }
+
+    /// Entities with a `T`, with their transform and the component both mutable.
+    pub fn query_mut<T: Component>(
+        &mut self,
+    ) -> impl Iterator<Item = (usize, &mut Transform, &mut T)> + '_ {
+        self.entities.iter_mut().enumerate().filter_map(|(i, e)| {
+            let SceneEntity {
+                transform,
+                components,
+                ..
+            } = e;
+            components
+                .get_mut::<T>()
+                .map(|component| (i, transform, component))
+        })
+    }
+
+    /// Closest entity hit by `ray`.
+    pub fn pick(&self, ray: &Ray) -> Option<usize> {
+        self.entities
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| e.intersect(ray).map(|t| (i, t)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+}
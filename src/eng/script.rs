@@ -0,0 +1,442 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    fmt,
+    rc::Rc,
+};
+
+use anyhow::{anyhow, bail};
+use rhai::{Array, CallFnOptions, Dynamic, EvalAltResult, FuncArgs, Scope, AST};
+
+use crate::sys::pack::AssetPack;
+
+/// Something a script asked the engine to do, handed to RadApp::process_script_command
+/// as the cutscene reaches it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptCommand {
+    /// `spawn_entity(name)` or `spawn_entity(name, [x, y, z])`, usually a prefab name.
+    Spawn { name: String, position: [f32; 3] },
+    /// `move_camera([x, y, z], [tx, ty, tz], seconds)`, 0 seconds cuts.
+    MoveCamera {
+        position: [f32; 3],
+        target: [f32; 3],
+        duration: f32,
+    },
+    /// `play_sound(name)` or `play_sound(name, gain)`.
+    PlaySound { name: String, gain: f32 },
+    /// `say(speaker, text)` or `show_text(text)`, the cutscene waits for Cutscene::advance.
+    ShowText {
+        speaker: Option<String>,
+        text: String,
+    },
+    /// `wait(seconds)`, consumed by the cutscene and never handed out.
+    Wait(f32),
+    /// `emit(name)`, a game specific event.
+    Event(String),
+    /// `set_time_of_day(hours)` or `set_time_of_day(hours, seconds)`, see
+    /// TimeOfDay::transition_to.
+    SetTimeOfDay { hours: f32, duration: f32 },
+    /// `set_config(key, value)` for settings that change while running, e.g.
+    /// `set_config("accessibility.reduced_flash", true)`, see RenderWindow::apply_setting.
+    SetConfig { key: String, value: String },
+}
+
+/// Commands recorded by one script run, released over time by update.
+#[derive(Debug, Clone, Default)]
+pub struct Cutscene {
+    name: String,
+    commands: VecDeque<ScriptCommand>,
+    wait: f32,
+    waiting_for_text: bool,
+}
+
+impl Cutscene {
+    pub fn new(name: &str, commands: Vec<ScriptCommand>) -> Self {
+        Self {
+            name: String::from(name),
+            commands: commands.into(),
+            wait: 0.0,
+            waiting_for_text: false,
+        }
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Advances `dt` seconds, returning the commands reached in order. Stops at a
+    /// ShowText until advance is called.
+    pub fn update(&mut self, dt: f32) -> Vec<ScriptCommand> {
+        let mut reached = Vec::new();
+        let mut dt = dt;
+        while !self.waiting_for_text {
+            if self.wait > 0.0 {
+                if dt < self.wait {
+                    self.wait -= dt;
+                    break;
+                }
+                dt -= self.wait;
+                self.wait = 0.0;
+            }
+            match self.commands.pop_front() {
+                Some(ScriptCommand::Wait(seconds)) => self.wait = seconds.max(0.0),
+                Some(command @ ScriptCommand::ShowText { .. }) => {
+                    reached.push(command);
+                    self.waiting_for_text = true;
+                }
+                Some(command) => reached.push(command),
+                None => break,
+            }
+        }
+        reached
+    }
+
+    /// Continues past the text being shown, e.g. when the player presses confirm.
+    pub fn advance(&mut self) {
+        self.waiting_for_text = false;
+    }
+
+    #[inline]
+    pub fn is_waiting_for_text(&self) -> bool {
+        self.waiting_for_text
+    }
+
+    /// Ends the cutscene, returning what's left without waits or text and with camera
+    /// moves turned into cuts, so the game ends up in the state the cutscene would leave.
+    pub fn skip(&mut self) -> Vec<ScriptCommand> {
+        self.wait = 0.0;
+        self.waiting_for_text = false;
+        self.commands
+            .drain(..)
+            .filter_map(|command| match command {
+                ScriptCommand::Wait(_) | ScriptCommand::ShowText { .. } => None,
+                ScriptCommand::MoveCamera {
+                    position, target, ..
+                } => Some(ScriptCommand::MoveCamera {
+                    position,
+                    target,
+                    duration: 0.0,
+                }),
+                command => Some(command),
+            })
+            .collect()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.commands.is_empty() && self.wait <= 0.0 && !self.waiting_for_text
+    }
+}
+
+/// Compiled rhai scripts with engine bindings for cutscenes and dialogue. Running a script
+/// records its commands into a Cutscene, the main loop updates the playing cutscenes and
+/// passes the commands they reach to the app. Flags set by scripts persist between runs.
+pub struct ScriptHost {
+    engine: rhai::Engine,
+    scripts: HashMap<String, AST>,
+    recorded: Rc<RefCell<Vec<ScriptCommand>>>,
+    flags: Rc<RefCell<HashMap<String, Dynamic>>>,
+    cutscenes: Vec<Cutscene>,
+}
+
+impl fmt::Debug for ScriptHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScriptHost")
+            .field("scripts", &self.scripts.keys().collect::<Vec<_>>())
+            .field("cutscenes", &self.cutscenes)
+            .finish()
+    }
+}
+
+impl Default for ScriptHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptHost {
+    /// Runaway scripts are stopped after this many operations instead of hanging the frame.
+    pub const MAX_OPERATIONS: u64 = 1_000_000;
+
+    pub fn new() -> Self {
+        let mut engine = rhai::Engine::new();
+        engine.set_max_operations(Self::MAX_OPERATIONS);
+        let recorded = Rc::new(RefCell::new(Vec::new()));
+        let flags = Rc::new(RefCell::new(HashMap::new()));
+
+        let r = recorded.clone();
+        engine.register_fn("spawn_entity", move |name: &str| {
+            r.borrow_mut().push(ScriptCommand::Spawn {
+                name: String::from(name),
+                position: [0.0; 3],
+            });
+        });
+        let r = recorded.clone();
+        engine.register_fn(
+            "spawn_entity",
+            move |name: &str, position: Array| -> Result<(), Box<EvalAltResult>> {
+                r.borrow_mut().push(ScriptCommand::Spawn {
+                    name: String::from(name),
+                    position: vec3(&position)?,
+                });
+                Ok(())
+            },
+        );
+        let r = recorded.clone();
+        engine.register_fn(
+            "move_camera",
+            move |position: Array, target: Array| -> Result<(), Box<EvalAltResult>> {
+                r.borrow_mut().push(move_camera(&position, &target, 0.0)?);
+                Ok(())
+            },
+        );
+        let r = recorded.clone();
+        engine.register_fn(
+            "move_camera",
+            move |position: Array,
+                  target: Array,
+                  seconds: Dynamic|
+                  -> Result<(), Box<EvalAltResult>> {
+                r.borrow_mut()
+                    .push(move_camera(&position, &target, number(&seconds)?)?);
+                Ok(())
+            },
+        );
+        let r = recorded.clone();
+        engine.register_fn("play_sound", move |name: &str| {
+            r.borrow_mut().push(ScriptCommand::PlaySound {
+                name: String::from(name),
+                gain: 1.0,
+            });
+        });
+        let r = recorded.clone();
+        engine.register_fn(
+            "play_sound",
+            move |name: &str, gain: Dynamic| -> Result<(), Box<EvalAltResult>> {
+                r.borrow_mut().push(ScriptCommand::PlaySound {
+                    name: String::from(name),
+                    gain: number(&gain)?,
+                });
+                Ok(())
+            },
+        );
+        let r = recorded.clone();
+        engine.register_fn("say", move |speaker: &str, text: &str| {
+            r.borrow_mut().push(ScriptCommand::ShowText {
+                speaker: Some(String::from(speaker)),
+                text: String::from(text),
+            });
+        });
+        let r = recorded.clone();
+        engine.register_fn("show_text", move |text: &str| {
+            r.borrow_mut().push(ScriptCommand::ShowText {
+                speaker: None,
+                text: String::from(text),
+            });
+        });
+        let r = recorded.clone();
+        engine.register_fn(
+            "wait",
+            move |seconds: Dynamic| -> Result<(), Box<EvalAltResult>> {
+                r.borrow_mut().push(ScriptCommand::Wait(number(&seconds)?));
+                Ok(())
+            },
+        );
+        let r = recorded.clone();
+        engine.register_fn("emit", move |event: &str| {
+            r.borrow_mut()
+                .push(ScriptCommand::Event(String::from(event)));
+        });
+        let r = recorded.clone();
+        engine.register_fn(
+            "set_time_of_day",
+            move |hours: Dynamic| -> Result<(), Box<EvalAltResult>> {
+                r.borrow_mut().push(ScriptCommand::SetTimeOfDay {
+                    hours: number(&hours)?,
+                    duration: 0.0,
+                });
+                Ok(())
+            },
+        );
+        let r = recorded.clone();
+        engine.register_fn(
+            "set_time_of_day",
+            move |hours: Dynamic, seconds: Dynamic| -> Result<(), Box<EvalAltResult>> {
+                r.borrow_mut().push(ScriptCommand::SetTimeOfDay {
+                    hours: number(&hours)?,
+                    duration: number(&seconds)?,
+                });
+                Ok(())
+            },
+        );
+        let r = recorded.clone();
+        engine.register_fn("set_config", move |key: &str, value: Dynamic| {
+            r.borrow_mut().push(ScriptCommand::SetConfig {
+                key: String::from(key),
+                value: value.to_string(),
+            });
+        });
+        let f = flags.clone();
+        engine.register_fn("set_flag", move |name: &str, value: Dynamic| {
+            f.borrow_mut().insert(String::from(name), value);
+        });
+        let f = flags.clone();
+        engine.register_fn("flag", move |name: &str| {
+            f.borrow().get(name).cloned().unwrap_or(Dynamic::UNIT)
+        });
+        let f = flags.clone();
+        engine.register_fn("has_flag", move |name: &str| f.borrow().contains_key(name));
+
+        Self {
+            engine,
+            scripts: HashMap::new(),
+            recorded,
+            flags,
+            cutscenes: Vec::new(),
+        }
+    }
+
+    /// Compiles `source` under `name`, replacing a script already loaded under it. Load
+    /// files with sys::fs::load_to_str, or from a pack with load_packed.
+    pub fn load(&mut self, name: &str, source: &str) -> anyhow::Result<()> {
+        let ast = self
+            .engine
+            .compile(source)
+            .map_err(|e| anyhow!("ScriptHost::load => {}: {}", name, e))?;
+        self.scripts.insert(String::from(name), ast);
+        Ok(())
+    }
+
+    /// Loads a script the asset importer stored as a raw pack entry.
+    pub fn load_packed(&mut self, pack: &AssetPack, name: &str) -> anyhow::Result<()> {
+        let Some(data) = pack.get(name) else {
+            bail!("ScriptHost::load_packed => {} not found in pack", name);
+        };
+        self.load(name, std::str::from_utf8(data)?)
+    }
+
+    #[inline]
+    pub fn is_loaded(&self, name: &str) -> bool {
+        self.scripts.contains_key(name)
+    }
+
+    pub fn unload(&mut self, name: &str) -> bool {
+        self.scripts.remove(name).is_some()
+    }
+
+    fn script(&self, name: &str) -> anyhow::Result<&AST> {
+        match self.scripts.get(name) {
+            Some(ast) => Ok(ast),
+            None => bail!("ScriptHost => no script named {}", name),
+        }
+    }
+
+    /// Runs the top level of script `name` and plays what it recorded as a cutscene.
+    pub fn play(&mut self, name: &str) -> anyhow::Result<()> {
+        self.recorded.borrow_mut().clear();
+        let result = self
+            .engine
+            .run_ast_with_scope(&mut Scope::new(), self.script(name)?);
+        let commands = std::mem::take(&mut *self.recorded.borrow_mut());
+        result.map_err(|e| anyhow!("ScriptHost::play => {}: {}", name, e))?;
+        self.cutscenes.push(Cutscene::new(name, commands));
+        Ok(())
+    }
+
+    /// Calls `function` defined in script `name`, e.g. a dialogue choice handler. Commands
+    /// it records are played as a cutscene of their own.
+    pub fn call(
+        &mut self,
+        name: &str,
+        function: &str,
+        args: impl FuncArgs,
+    ) -> anyhow::Result<Dynamic> {
+        self.recorded.borrow_mut().clear();
+        let result = self.engine.call_fn_with_options::<Dynamic>(
+            CallFnOptions::new().eval_ast(false),
+            &mut Scope::new(),
+            self.script(name)?,
+            function,
+            args,
+        );
+        let commands = std::mem::take(&mut *self.recorded.borrow_mut());
+        let value =
+            result.map_err(|e| anyhow!("ScriptHost::call => {}::{}: {}", name, function, e))?;
+        if !commands.is_empty() {
+            self.cutscenes.push(Cutscene::new(name, commands));
+        }
+        Ok(value)
+    }
+
+    /// Updates every playing cutscene by `dt` seconds, returning the commands they
+    /// reached. Called by the main loop before frame_update.
+    pub fn update(&mut self, dt: f32) -> Vec<ScriptCommand> {
+        let commands = self
+            .cutscenes
+            .iter_mut()
+            .flat_map(|cutscene| cutscene.update(dt))
+            .collect();
+        self.cutscenes.retain(|c| !c.is_finished());
+        commands
+    }
+
+    /// Continues every cutscene waiting on text.
+    pub fn advance(&mut self) {
+        for cutscene in self.cutscenes.iter_mut() {
+            cutscene.advance();
+        }
+    }
+
+    /// Ends every cutscene, see Cutscene::skip.
+    pub fn skip(&mut self) -> Vec<ScriptCommand> {
+        let commands = self.cutscenes.iter_mut().flat_map(Cutscene::skip).collect();
+        self.cutscenes.clear();
+        commands
+    }
+
+    #[inline]
+    pub fn cutscenes(&self) -> &[Cutscene] {
+        &self.cutscenes
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.cutscenes.is_empty()
+    }
+
+    pub fn flag(&self, name: &str) -> Option<Dynamic> {
+        self.flags.borrow().get(name).cloned()
+    }
+
+    /// Sets a flag scripts read with `flag(name)`, e.g. quest progress.
+    pub fn set_flag(&self, name: &str, value: Dynamic) {
+        self.flags.borrow_mut().insert(String::from(name), value);
+    }
+}
+
+/// Scripts write `1` as often as `1.0`, accept both.
+fn number(value: &Dynamic) -> Result<f32, Box<EvalAltResult>> {
+    value
+        .as_float()
+        .map(|f| f as f32)
+        .or_else(|_| value.as_int().map(|i| i as f32))
+        .map_err(|_| format!("expected a number, got {}", value.type_name()).into())
+}
+
+fn vec3(array: &Array) -> Result<[f32; 3], Box<EvalAltResult>> {
+    match array.as_slice() {
+        [x, y, z] => Ok([number(x)?, number(y)?, number(z)?]),
+        _ => Err(format!("expected [x, y, z], got {} values", array.len()).into()),
+    }
+}
+
+fn move_camera(
+    position: &Array,
+    target: &Array,
+    duration: f32,
+) -> Result<ScriptCommand, Box<EvalAltResult>> {
+    Ok(ScriptCommand::MoveCamera {
+        position: vec3(position)?,
+        target: vec3(target)?,
+        duration,
+    })
+}
@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+use winit::{
+    event::{ElementState, MouseScrollDelta, WindowEvent},
+    keyboard::KeyCode,
+};
+
+use crate::gfx::{self, wgpu::surface::SurfaceError};
+
+use super::app::{InputEventStatus, RadApp};
+
+/// Change to the state stack requested by the top state.
+pub enum Trans {
+    None,
+    /// Pauses the current state and pushes a new one on top of it.
+    Push(Box<dyn GameState>),
+    /// Removes the current state, resuming the one below.
+    Pop,
+    /// Removes the current state and pushes a new one in its place.
+    Replace(Box<dyn GameState>),
+    /// Removes every state and pushes a new one.
+    Switch(Box<dyn GameState>),
+}
+
+impl std::fmt::Debug for Trans {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Trans::None => "None",
+            Trans::Push(_) => "Push",
+            Trans::Pop => "Pop",
+            Trans::Replace(_) => "Replace",
+            Trans::Switch(_) => "Switch",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A single state of the game flow (menu, gameplay, pause...). Mirrors the RadApp hooks,
+/// but only the state on top of the stack receives them. Input that should change state
+/// is recorded and acted on by returning a Trans from frame_update.
+pub trait GameState {
+    fn on_enter(&mut self) {}
+    fn on_exit(&mut self) {}
+    /// Another state was pushed on top of this one.
+    fn on_pause(&mut self) {}
+    /// The state above this one was popped.
+    fn on_resume(&mut self) {}
+
+    fn process_keyboard(&mut self, _key: KeyCode, _state: ElementState) -> InputEventStatus {
+        InputEventStatus::Done
+    }
+    fn process_mouse(&mut self, _mouse_dx: f64, _mouse_dy: f64) {}
+    fn process_scroll(&mut self, _delta: &MouseScrollDelta) {}
+    fn handle_window_events(&mut self, _event: &WindowEvent) -> InputEventStatus {
+        InputEventStatus::Done
+    }
+
+    fn draw_frame(&mut self, ctx: &mut gfx::draw::DrawCtx) -> Result<(), SurfaceError>;
+    fn frame_update(&mut self, dt: Duration) -> Trans;
+    fn fixed_update(&mut self, _dt: Duration) {}
+
+    /// When true the state below is drawn first, e.g. for a pause menu over gameplay.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// Pushdown automaton of GameStates. Implements RadApp so it can be handed straight to
+/// Radium::start, routing every hook to the state on top.
+#[derive(Default)]
+pub struct StateStack {
+    states: Vec<Box<dyn GameState>>,
+}
+
+impl StateStack {
+    pub fn new(initial: Box<dyn GameState>) -> Self {
+        let mut s = Self::default();
+        s.push(initial);
+        s
+    }
+
+    pub fn push(&mut self, mut state: Box<dyn GameState>) {
+        if let Some(top) = self.states.last_mut() {
+            top.on_pause();
+        }
+        state.on_enter();
+        self.states.push(state);
+    }
+
+    pub fn pop(&mut self) -> Option<Box<dyn GameState>> {
+        let mut state = self.states.pop()?;
+        state.on_exit();
+        if let Some(top) = self.states.last_mut() {
+            top.on_resume();
+        }
+        Some(state)
+    }
+
+    pub fn replace(&mut self, mut state: Box<dyn GameState>) {
+        if let Some(mut top) = self.states.pop() {
+            top.on_exit();
+        }
+        state.on_enter();
+        self.states.push(state);
+    }
+
+    pub fn switch(&mut self, state: Box<dyn GameState>) {
+        while let Some(mut top) = self.states.pop() {
+            top.on_exit();
+        }
+        self.push(state);
+    }
+
+    pub fn apply(&mut self, trans: Trans) {
+        match trans {
+            Trans::None => {}
+            Trans::Push(state) => self.push(state),
+            Trans::Pop => {
+                self.pop();
+            }
+            Trans::Replace(state) => self.replace(state),
+            Trans::Switch(state) => self.switch(state),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn top_mut(&mut self) -> Option<&mut Box<dyn GameState>> {
+        self.states.last_mut()
+    }
+
+    /// Updates the top state and applies the transition it returns.
+    pub fn update(&mut self, dt: Duration) {
+        if let Some(top) = self.states.last_mut() {
+            let trans = top.frame_update(dt);
+            self.apply(trans);
+        }
+    }
+}
+
+impl RadApp for StateStack {
+    fn process_keyboard(&mut self, key: KeyCode, state: ElementState) -> InputEventStatus {
+        self.states
+            .last_mut()
+            .map_or(InputEventStatus::Done, |s| s.process_keyboard(key, state))
+    }
+
+    fn process_mouse(&mut self, mouse_dx: f64, mouse_dy: f64) {
+        if let Some(top) = self.states.last_mut() {
+            top.process_mouse(mouse_dx, mouse_dy);
+        }
+    }
+
+    fn process_scroll(&mut self, delta: &MouseScrollDelta) {
+        if let Some(top) = self.states.last_mut() {
+            top.process_scroll(delta);
+        }
+    }
+
+    fn handle_window_events(&mut self, event: &WindowEvent) -> InputEventStatus {
+        self.states
+            .last_mut()
+            .map_or(InputEventStatus::Done, |s| s.handle_window_events(event))
+    }
+
+    fn draw_frame(&mut self, ctx: &mut gfx::draw::DrawCtx) -> Result<(), SurfaceError> {
+        // Draw from the lowest state visible under the overlays on top.
+        let first = self
+            .states
+            .iter()
+            .rposition(|s| !s.is_overlay())
+            .unwrap_or(0);
+        for state in self.states[first..].iter_mut() {
+            state.draw_frame(ctx)?;
+        }
+        Ok(())
+    }
+
+    fn frame_update(&mut self, dt: Duration) {
+        self.update(dt);
+    }
+
+    fn fixed_update(&mut self, dt: Duration) {
+        if let Some(top) = self.states.last_mut() {
+            top.fixed_update(dt);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    cell::{OnceCell, RefCell},
+    fmt,
+    future::Future,
+    sync::mpsc::{self, Receiver, TryRecvError},
+};
+
+/// Async work for the engine, run on a tokio multi thread runtime (the same executor
+/// sys::fs uses) so it keeps making progress while winit owns the main thread.
+/// Results come back on the main thread: either through a TaskHandle that is checked
+/// every frame, or a callback that runs during poll. Lives on EngineContext, see
+/// EngineContext::tasks, and the main loop polls it before frame_update.
+/// Nothing here waits for a task: the engine itself runs inside main's tokio runtime, so
+/// blocking on a result would stall (or, for a runtime's block_on, panic) the loop. Check
+/// results with TaskHandle::try_take or on_complete instead.
+pub struct Tasks {
+    worker_threads: usize,
+    runtime: OnceCell<tokio::runtime::Runtime>,
+    callbacks: RefCell<Vec<Box<dyn FnMut() -> bool>>>,
+}
+
+impl fmt::Debug for Tasks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tasks")
+            .field("worker_threads", &self.worker_threads)
+            .field("started", &self.runtime.get().is_some())
+            .field("callbacks", &self.callbacks.borrow().len())
+            .finish()
+    }
+}
+
+impl Default for Tasks {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_WORKER_THREADS)
+    }
+}
+
+impl Drop for Tasks {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks, which tokio refuses inside another runtime like main's.
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl Tasks {
+    pub const DEFAULT_WORKER_THREADS: usize = 2;
+
+    /// The runtime starts on the first spawn, so apps that never spawn don't pay for it.
+    pub fn new(worker_threads: usize) -> Self {
+        Self {
+            worker_threads: worker_threads.max(1),
+            runtime: OnceCell::new(),
+            callbacks: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn runtime(&self) -> &tokio::runtime::Runtime {
+        self.runtime.get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(self.worker_threads)
+                .thread_name("radium-task")
+                .enable_all()
+                .build()
+                .expect("Tasks::runtime => Failed to start the task runtime")
+        })
+    }
+
+    /// Runs `future` on a worker, the result is picked up with TaskHandle::try_take.
+    pub fn spawn<T, F>(&self, future: F) -> TaskHandle<T>
+    where
+        T: Send + 'static,
+        F: Future<Output = T> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        self.runtime().spawn(async move {
+            // The handle may have been dropped, then nobody wants the result.
+            let _ = tx.send(future.await);
+        });
+        TaskHandle { rx, done: false }
+    }
+
+    /// Runs blocking work like decoding on tokio's blocking pool.
+    pub fn spawn_blocking<T, F>(&self, work: F) -> TaskHandle<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        self.runtime().spawn_blocking(move || {
+            let _ = tx.send(work());
+        });
+        TaskHandle { rx, done: false }
+    }
+
+    /// Runs `future` on a worker and `then` with its result on the main thread during the
+    /// poll after it finishes. `then` doesn't need to be Send, so it can touch Rc state.
+    pub fn spawn_then<T, F>(&self, future: F, then: impl FnOnce(T) + 'static)
+    where
+        T: Send + 'static,
+        F: Future<Output = T> + Send + 'static,
+    {
+        let handle = self.spawn(future);
+        self.on_complete(handle, then);
+    }
+
+    /// Calls `then` on the main thread once `handle` finishes, dropped if the task panics.
+    pub fn on_complete<T: Send + 'static>(
+        &self,
+        mut handle: TaskHandle<T>,
+        then: impl FnOnce(T) + 'static,
+    ) {
+        let mut then = Some(then);
+        self.callbacks
+            .borrow_mut()
+            .push(Box::new(move || match handle.try_take() {
+                Some(value) => {
+                    if let Some(then) = then.take() {
+                        then(value);
+                    }
+                    true
+                }
+                None => handle.is_finished(),
+            }));
+    }
+
+    /// Runs the callbacks of finished tasks, returns how many ran. Callbacks may spawn
+    /// more tasks, those are checked on the next poll.
+    pub fn poll(&self) -> usize {
+        let mut callbacks = std::mem::take(&mut *self.callbacks.borrow_mut());
+        let before = callbacks.len();
+        callbacks.retain_mut(|done| !done());
+        let ran = before - callbacks.len();
+        self.callbacks.borrow_mut().append(&mut callbacks);
+        ran
+    }
+
+    /// Callbacks waiting for their task.
+    #[inline]
+    pub fn pending(&self) -> usize {
+        self.callbacks.borrow().len()
+    }
+}
+
+/// Result of a spawned task, checked from the main thread without blocking.
+#[derive(Debug)]
+pub struct TaskHandle<T> {
+    rx: Receiver<T>,
+    done: bool,
+}
+
+impl<T> TaskHandle<T> {
+    /// The result once the task has finished, None while it runs and after it was taken.
+    pub fn try_take(&mut self) -> Option<T> {
+        if self.done {
+            return None;
+        }
+        match self.rx.try_recv() {
+            Ok(value) => {
+                self.done = true;
+                Some(value)
+            }
+            Err(TryRecvError::Disconnected) => {
+                self.done = true;
+                None
+            }
+            Err(TryRecvError::Empty) => None,
+        }
+    }
+
+    /// True once the result was taken or the task ended without one (it panicked).
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.done
+    }
+}
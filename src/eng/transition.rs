@@ -0,0 +1,331 @@
+use std::{cell::RefCell, rc::Rc, sync::Arc, time::Duration};
+
+use wgpu::util::DeviceExt;
+
+use crate::gfx::{
+    post::{EffectBindGroup, PostEffect, PostProcessor},
+    wgpu::texture::Texture,
+};
+
+/// Name the transition effect is registered under in the post stack.
+pub const TRANSITION_EFFECT: &str = "transition";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WipeDirection {
+    LeftToRight,
+    RightToLeft,
+    TopToBottom,
+    BottomToTop,
+}
+
+#[derive(Debug, Clone)]
+pub enum TransitionKind {
+    /// Fades the scene in from a solid color.
+    FadeIn(wgpu::Color),
+    /// Fades the scene out to a solid color. The color stays up once finished until
+    /// Transition::clear is called or another transition starts.
+    FadeOut(wgpu::Color),
+    /// Blends from another render target to the live scene.
+    Crossfade(Rc<Texture>),
+    /// Reveals the live scene over another render target with a moving edge.
+    Wipe {
+        from: Rc<Texture>,
+        direction: WipeDirection,
+        softness: f32,
+    },
+    /// Reveals the live scene over another render target, pixels with a lower red value in
+    /// the mask are revealed first.
+    Dissolve {
+        from: Rc<Texture>,
+        mask: Rc<Texture>,
+        softness: f32,
+    },
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct TransitionParams {
+    color: [f32; 4],
+    mode: u32,
+    direction: u32,
+    from_color: u32,
+    progress: f32,
+    softness: f32,
+    _pad: [f32; 3],
+}
+
+impl TransitionParams {
+    const NONE: u32 = 0;
+    const FADE: u32 = 1;
+    const WIPE: u32 = 2;
+    const DISSOLVE: u32 = 3;
+
+    fn new(kind: &TransitionKind, progress: f32) -> Self {
+        let mut params = Self {
+            color: [0.0; 4],
+            mode: Self::NONE,
+            direction: 0,
+            from_color: 0,
+            progress,
+            softness: 0.0,
+            _pad: [0.0; 3],
+        };
+        match kind {
+            TransitionKind::FadeIn(color) => {
+                params.mode = Self::FADE;
+                params.from_color = 1;
+                params.color = color_to_array(color);
+            }
+            TransitionKind::FadeOut(color) => {
+                params.mode = Self::FADE;
+                params.from_color = 1;
+                params.color = color_to_array(color);
+                params.progress = 1.0 - progress;
+            }
+            TransitionKind::Crossfade(_) => {
+                params.mode = Self::FADE;
+            }
+            TransitionKind::Wipe {
+                direction,
+                softness,
+                ..
+            } => {
+                params.mode = Self::WIPE;
+                params.direction = *direction as u32;
+                params.softness = *softness;
+            }
+            TransitionKind::Dissolve { softness, .. } => {
+                params.mode = Self::DISSOLVE;
+                params.softness = *softness;
+            }
+        }
+        params
+    }
+}
+
+fn color_to_array(color: &wgpu::Color) -> [f32; 4] {
+    [
+        color.r as f32,
+        color.g as f32,
+        color.b as f32,
+        color.a as f32,
+    ]
+}
+
+/// Drives full screen transitions through an effect at the end of the post stack.
+/// The effect is only enabled while a transition is running (or a fade out is holding),
+/// so it costs nothing the rest of the time.
+pub struct Transition {
+    kind: Option<TransitionKind>,
+    duration: Duration,
+    elapsed: Duration,
+    layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    placeholder: Rc<Texture>,
+    bind_group: EffectBindGroup,
+}
+
+impl Transition {
+    /// Creates the transition resources and pushes its (disabled) effect onto the post stack.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, post: &mut PostProcessor) -> Self {
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Transition Params Buffer"),
+            contents: bytemuck::cast_slice(&[TransitionParams::new(
+                &TransitionKind::FadeIn(wgpu::Color::BLACK),
+                1.0,
+            )]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+        let sampler_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_entry(1),
+                sampler_entry(2),
+                texture_entry(3),
+                sampler_entry(4),
+            ],
+            label: Some("transition_bind_group_layout"),
+        });
+
+        let placeholder = Rc::new(Texture::render_target(
+            device,
+            1,
+            1,
+            wgpu::TextureFormat::Rgba8Unorm,
+            Some("Transition Placeholder"),
+        ));
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                aspect: wgpu::TextureAspect::All,
+                texture: &placeholder.handle,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &[255, 255, 255, 255],
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4),
+                rows_per_image: Some(1),
+            },
+            placeholder.handle.size(),
+        );
+
+        let bind_group =
+            create_bind_group(device, &layout, &params_buffer, &placeholder, &placeholder);
+
+        let s = Self {
+            kind: None,
+            duration: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            layout,
+            params_buffer,
+            placeholder,
+            bind_group: Rc::new(RefCell::new(Arc::new(bind_group))),
+        };
+
+        let mut effect = PostEffect::new(
+            device,
+            post,
+            TRANSITION_EFFECT,
+            include_str!("../shaders/post_transition.wgsl"),
+            Some(&s.layout),
+            Some(s.bind_group.clone()),
+        );
+        effect.enabled = false;
+        post.push_effect(effect);
+        s
+    }
+
+    /// Starts a transition, replacing any that is already running.
+    pub fn start(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        post: &mut PostProcessor,
+        kind: TransitionKind,
+        duration: Duration,
+    ) {
+        let (from, mask) = match &kind {
+            TransitionKind::FadeIn(_) | TransitionKind::FadeOut(_) => {
+                (&self.placeholder, &self.placeholder)
+            }
+            TransitionKind::Crossfade(from) | TransitionKind::Wipe { from, .. } => {
+                (from, &self.placeholder)
+            }
+            TransitionKind::Dissolve { from, mask, .. } => (from, mask),
+        };
+        let bind_group = create_bind_group(device, &self.layout, &self.params_buffer, from, mask);
+        *self.bind_group.borrow_mut() = Arc::new(bind_group);
+
+        self.kind = Some(kind);
+        self.duration = duration;
+        self.elapsed = Duration::ZERO;
+        self.write_params(queue);
+        post.set_enabled(TRANSITION_EFFECT, true);
+    }
+
+    /// Advances the running transition, call once per frame.
+    pub fn update(&mut self, dt: Duration, queue: &wgpu::Queue, post: &mut PostProcessor) {
+        let Some(kind) = self.kind.as_ref() else {
+            return;
+        };
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+        self.write_params(queue);
+
+        if self.is_finished() && !matches!(kind, TransitionKind::FadeOut(_)) {
+            self.clear(post);
+        }
+    }
+
+    /// Stops the current transition and shows the scene as is.
+    pub fn clear(&mut self, post: &mut PostProcessor) {
+        self.kind = None;
+        post.set_enabled(TRANSITION_EFFECT, false);
+    }
+
+    /// Progress of the current transition from 0 to 1, 1 when no transition is running.
+    pub fn progress(&self) -> f32 {
+        if self.kind.is_none() || self.duration.is_zero() {
+            return 1.0;
+        }
+        self.elapsed.as_secs_f32() / self.duration.as_secs_f32()
+    }
+
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.kind.is_some()
+    }
+
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    fn write_params(&self, queue: &wgpu::Queue) {
+        if let Some(kind) = self.kind.as_ref() {
+            let params = TransitionParams::new(kind, self.progress());
+            queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+        }
+    }
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    params: &wgpu::Buffer,
+    from: &Texture,
+    mask: &Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&from.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(&from.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&mask.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::Sampler(&mask.sampler),
+            },
+        ],
+        label: Some("transition_bind_group"),
+    })
+}
@@ -0,0 +1,241 @@
+use std::{fmt, sync::Arc};
+
+use super::{command::RenderCommand, command_dump::ResourceMap};
+
+/// Bind groups and vertex buffers a pipeline's layout expects, wgpu doesn't hand these
+/// back once the pipeline is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineShape {
+    pub bind_groups: u32,
+    pub vertex_buffers: u32,
+}
+
+/// Shapes of the pipelines validate_commands knows about, lives on DeviceSurface, see
+/// DeviceSurface::pipeline_shapes. Draws with pipelines that were never registered skip
+/// the layout checks.
+#[derive(Default)]
+pub struct PipelineShapes {
+    shapes: ResourceMap<PipelineShape>,
+}
+
+impl fmt::Debug for PipelineShapes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PipelineShapes")
+            .field("len", &self.shapes.len())
+            .finish()
+    }
+}
+
+impl PipelineShapes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, pipeline: &Arc<wgpu::RenderPipeline>, shape: PipelineShape) {
+        self.shapes.insert(pipeline, shape);
+    }
+
+    pub fn get(&self, pipeline: &Arc<wgpu::RenderPipeline>) -> Option<PipelineShape> {
+        self.shapes.get(pipeline)
+    }
+}
+
+/// A recorded command wgpu would reject, found before the pass is encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandError {
+    /// Label of the pass the command was recorded into.
+    pub pass: String,
+    /// Index of the command in the pass's queue.
+    pub index: usize,
+    pub command: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RenderPass '{}' command {} ({}): {}",
+            self.pass, self.index, self.command, self.message
+        )
+    }
+}
+
+/// What validate_commands needs to know about the pass besides its commands.
+#[derive(Debug, Clone, Copy)]
+pub struct PassLimits<'a> {
+    /// Width and height of the pass's attachments.
+    pub attachment_size: Option<[u32; 2]>,
+    /// Device limit on bind group indices, see wgpu::Limits::max_bind_groups.
+    pub max_bind_groups: u32,
+    /// Device limit on immediate data, 0 without Features::IMMEDIATES.
+    pub max_immediate_size: u32,
+    pub shapes: &'a PipelineShapes,
+}
+
+/// Checks `commands` for mistakes that would otherwise surface as a wgpu validation panic
+/// or an empty frame: draws without a pipeline, vertex or index buffer, bind group indices
+/// the pipeline layout doesn't have, scissor rects outside the attachments and immediates
+/// past the device limit.
+pub fn validate_commands(
+    pass: &str,
+    commands: &[RenderCommand],
+    limits: PassLimits,
+) -> Vec<CommandError> {
+    let mut errors = Vec::new();
+    let mut error = |index: usize, cmd: &RenderCommand, message: String| {
+        errors.push(CommandError {
+            pass: String::from(pass),
+            index,
+            command: cmd.name(),
+            message,
+        })
+    };
+
+    let mut has_pipeline = false;
+    let mut shape = None;
+    let mut vertex_buffers = 0u64;
+    let mut index_buffer = false;
+    let mut debug_groups = 0usize;
+
+    for (i, cmd) in commands.iter().enumerate() {
+        let indexed = match cmd {
+            RenderCommand::SetPipeline(pipeline) => {
+                has_pipeline = true;
+                shape = limits.shapes.get(pipeline);
+                None
+            }
+            RenderCommand::SetBindGroup(index, ..) => {
+                if *index >= limits.max_bind_groups {
+                    error(
+                        i,
+                        cmd,
+                        format!(
+                            "bind group {} is past the device limit of {}",
+                            index, limits.max_bind_groups
+                        ),
+                    );
+                } else if let Some(shape) = shape.filter(|s| *index >= s.bind_groups) {
+                    error(
+                        i,
+                        cmd,
+                        format!(
+                            "bind group {} is out of range, the pipeline layout has {}",
+                            index, shape.bind_groups
+                        ),
+                    );
+                }
+                None
+            }
+            RenderCommand::SetVertexBuffer(slot, _) => {
+                if *slot < u64::BITS {
+                    vertex_buffers |= 1 << slot;
+                }
+                None
+            }
+            RenderCommand::SetIndexBuffer(..) => {
+                index_buffer = true;
+                None
+            }
+            RenderCommand::SetScissorRect(x, y, w, h) => {
+                if let Some([width, height]) = limits.attachment_size {
+                    let right = x.saturating_add(*w);
+                    let bottom = y.saturating_add(*h);
+                    if right > width || bottom > height {
+                        error(
+                            i,
+                            cmd,
+                            format!(
+                                "scissor {},{} {}x{} reaches outside the {}x{} attachment",
+                                x, y, w, h, width, height
+                            ),
+                        );
+                    }
+                }
+                None
+            }
+            RenderCommand::SetImmediates(offset, data) => {
+                let end = *offset as usize + data.len();
+                if offset % 4 != 0 || data.len() % 4 != 0 {
+                    error(
+                        i,
+                        cmd,
+                        format!(
+                            "immediates at {} with {} bytes are not 4 byte aligned",
+                            offset,
+                            data.len()
+                        ),
+                    );
+                } else if end > limits.max_immediate_size as usize {
+                    error(
+                        i,
+                        cmd,
+                        format!(
+                            "immediates up to byte {} are past the device limit of {}",
+                            end, limits.max_immediate_size
+                        ),
+                    );
+                }
+                None
+            }
+            RenderCommand::PushDebugGroup(_) => {
+                debug_groups += 1;
+                None
+            }
+            RenderCommand::PopDebugGroup => {
+                match debug_groups.checked_sub(1) {
+                    Some(groups) => debug_groups = groups,
+                    None => error(i, cmd, String::from("no debug group to pop")),
+                }
+                None
+            }
+            RenderCommand::Draw(..)
+            | RenderCommand::DrawIndirect(..)
+            | RenderCommand::MultiDrawIndirect(..) => Some(false),
+            RenderCommand::DrawIndexed(..)
+            | RenderCommand::DrawIndexedIndirect(..)
+            | RenderCommand::MultiDrawIndexedIndirect(..) => Some(true),
+            _ => None,
+        };
+
+        let Some(indexed) = indexed else {
+            continue;
+        };
+        if !has_pipeline {
+            error(i, cmd, String::from("draw without a pipeline set"));
+            continue;
+        }
+        if indexed && !index_buffer {
+            error(
+                i,
+                cmd,
+                String::from("indexed draw without an index buffer set"),
+            );
+        }
+        if let Some(shape) = shape {
+            let missing: Vec<_> = (0..shape.vertex_buffers.min(u64::BITS))
+                .filter(|slot| vertex_buffers & (1 << slot) == 0)
+                .collect();
+            if !missing.is_empty() {
+                error(
+                    i,
+                    cmd,
+                    format!("draw without vertex buffer slots {:?} set", missing),
+                );
+            }
+        }
+    }
+    if debug_groups > 0 {
+        if let Some(last) = commands.last() {
+            error(
+                commands.len() - 1,
+                last,
+                format!(
+                    "{} debug groups left open at the end of the pass",
+                    debug_groups
+                ),
+            );
+        }
+    }
+    errors
+}
@@ -0,0 +1,173 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use crate::gfx::wgpu::shader::{DepthMode, PipelineOptions};
+
+use super::{loading::LoadProgress, render::RenderWindow};
+
+pub type WarmUpFn<C> = Box<dyn FnOnce(&C) -> anyhow::Result<usize>>;
+
+/// Result of one warm-up step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WarmUpEntry {
+    pub name: String,
+    /// Number of things the step created or primed, e.g. pipelines.
+    pub items: usize,
+    pub duration: Duration,
+    pub error: Option<String>,
+}
+
+/// What a WarmUp did and how long each step took.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WarmUpReport {
+    pub entries: Vec<WarmUpEntry>,
+}
+
+impl WarmUpReport {
+    pub fn total(&self) -> Duration {
+        self.entries.iter().map(|e| e.duration).sum()
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &WarmUpEntry> {
+        self.entries.iter().filter(|e| e.error.is_some())
+    }
+}
+
+impl fmt::Display for WarmUpReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "warm-up: {} steps in {:.1?}",
+            self.entries.len(),
+            self.total()
+        )?;
+        for entry in &self.entries {
+            write!(
+                f,
+                "  {}: {} items in {:.1?}",
+                entry.name, entry.items, entry.duration
+            )?;
+            match &entry.error {
+                Some(error) => writeln!(f, " (failed: {})", error)?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Queue of start up work, e.g. building pipelines and uploading textures, spread over
+/// frames so a loading screen keeps drawing. Each step counts as one load in the shared
+/// LoadProgress, see LoadingScreen::with_warm_up.
+pub struct WarmUp<C = RenderWindow> {
+    steps: VecDeque<(String, WarmUpFn<C>)>,
+    progress: LoadProgress,
+    budget: Duration,
+    report: WarmUpReport,
+}
+
+impl<C> WarmUp<C> {
+    /// Work done per call to WarmUp::run unless changed with with_budget.
+    pub const DEFAULT_BUDGET: Duration = Duration::from_millis(8);
+
+    pub fn new(progress: &LoadProgress) -> Self {
+        Self {
+            steps: VecDeque::new(),
+            progress: progress.clone(),
+            budget: Self::DEFAULT_BUDGET,
+            report: WarmUpReport::default(),
+        }
+    }
+
+    /// Time WarmUp::run may spend per call, at least one step always runs.
+    pub fn with_budget(mut self, budget: Duration) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Queues a step, it returns how many things it warmed for the report.
+    pub fn add<F>(&mut self, name: impl Into<String>, step: F)
+    where
+        F: FnOnce(&C) -> anyhow::Result<usize> + 'static,
+    {
+        self.progress.begin(1);
+        self.steps.push_back((name.into(), Box::new(step)));
+    }
+
+    pub fn with_step<F>(mut self, name: impl Into<String>, step: F) -> Self
+    where
+        F: FnOnce(&C) -> anyhow::Result<usize> + 'static,
+    {
+        self.add(name, step);
+        self
+    }
+
+    /// Runs queued steps until the budget is spent, returns true once all of them ran.
+    /// Failed steps are logged and recorded in the report, the rest still run.
+    pub fn run(&mut self, ctx: &C) -> bool {
+        let start = Instant::now();
+        while let Some((name, step)) = self.steps.pop_front() {
+            let step_start = Instant::now();
+            let result = step(ctx);
+            let duration = step_start.elapsed();
+            if let Err(e) = result.as_ref() {
+                log::warn!("WarmUp::run => {} failed: {:#}", name, e);
+            }
+            self.report.entries.push(WarmUpEntry {
+                name,
+                items: *result.as_ref().unwrap_or(&0),
+                duration,
+                error: result.err().map(|e| format!("{:#}", e)),
+            });
+            self.progress.complete(1);
+            if start.elapsed() >= self.budget {
+                break;
+            }
+        }
+        self.is_done()
+    }
+
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.steps.len()
+    }
+
+    #[inline]
+    pub fn report(&self) -> &WarmUpReport {
+        &self.report
+    }
+}
+
+impl WarmUp<RenderWindow> {
+    /// Queues the engine's own warm-up: pipeline variants of the main shader that are
+    /// otherwise built on first use, and the default material's textures.
+    pub fn with_engine_defaults(self) -> Self {
+        self.with_step("pipeline variants", |window: &RenderWindow| {
+            let device = &window.device_surface().device;
+            let variants = [
+                PipelineOptions::default().with_depth(DepthMode::ReadOnly),
+                PipelineOptions::default().with_depth(DepthMode::Disabled),
+            ];
+            for options in variants {
+                window.shader().variant(device, options);
+            }
+            Ok(variants.len())
+        })
+        .with_step("default material", |window: &RenderWindow| {
+            window.default_material()?;
+            Ok(2)
+        })
+        .with_step("pipeline cache", |window: &RenderWindow| {
+            window.save_pipeline_cache();
+            Ok(window.pipeline_cache().map_or(0, |_| 1))
+        })
+    }
+}
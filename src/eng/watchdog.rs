@@ -0,0 +1,227 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Time spent in a named scope of the frame, see FrameWatchdog::scope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeTiming {
+    pub name: &'static str,
+    /// Nesting depth, 0 for scopes opened directly in the frame.
+    pub depth: usize,
+    pub duration: Duration,
+}
+
+/// Snapshot of a frame that went over the watchdog threshold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameDiagnostics {
+    pub frame: u64,
+    pub duration: Duration,
+    pub threshold: Duration,
+    /// In the order the scopes closed, so inner scopes come before their parent.
+    pub scopes: Vec<ScopeTiming>,
+    pub counters: Vec<(&'static str, usize)>,
+    /// Oldest first.
+    pub commands: Vec<String>,
+}
+
+impl fmt::Display for FrameDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "frame {} took {:.1?} (threshold {:.1?})",
+            self.frame, self.duration, self.threshold
+        )?;
+        for scope in self.scopes.iter() {
+            writeln!(
+                f,
+                "  {:indent$}{}: {:.1?}",
+                "",
+                scope.name,
+                scope.duration,
+                indent = scope.depth * 2
+            )?;
+        }
+        for (name, value) in self.counters.iter() {
+            writeln!(f, "  {} = {}", name, value)?;
+        }
+        if !self.commands.is_empty() {
+            writeln!(f, "  last commands:")?;
+            for command in self.commands.iter() {
+                writeln!(f, "    {}", command)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Watches frame times and logs a FrameDiagnostics when a frame runs longer than the
+/// threshold, so hitches reported from the field come with some context. The app loop
+/// brackets every frame with begin_frame and end_frame, anything else can add scopes,
+/// counters (queue sizes, pending loads) and command summaries in between.
+#[derive(Debug)]
+pub struct FrameWatchdog {
+    threshold: Cell<Duration>,
+    max_commands: usize,
+    frame: Cell<u64>,
+    frame_start: Cell<Option<Instant>>,
+    depth: Cell<usize>,
+    scopes: RefCell<Vec<ScopeTiming>>,
+    counters: RefCell<Vec<(&'static str, usize)>>,
+    commands: RefCell<VecDeque<String>>,
+    dumps: Cell<u64>,
+}
+
+impl Default for FrameWatchdog {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_THRESHOLD)
+    }
+}
+
+impl FrameWatchdog {
+    pub const DEFAULT_THRESHOLD: Duration = Duration::from_millis(250);
+    pub const DEFAULT_MAX_COMMANDS: usize = 32;
+
+    /// A zero threshold disables the watchdog.
+    pub fn new(threshold: Duration) -> Self {
+        Self {
+            threshold: Cell::new(threshold),
+            max_commands: Self::DEFAULT_MAX_COMMANDS,
+            frame: Cell::new(0),
+            frame_start: Cell::new(None),
+            depth: Cell::new(0),
+            scopes: RefCell::new(Vec::new()),
+            counters: RefCell::new(Vec::new()),
+            commands: RefCell::new(VecDeque::new()),
+            dumps: Cell::new(0),
+        }
+    }
+
+    /// Number of command lines of a frame kept for the dump, older ones are dropped first.
+    pub fn with_max_commands(mut self, max_commands: usize) -> Self {
+        self.max_commands = max_commands;
+        self
+    }
+
+    #[inline]
+    pub fn threshold(&self) -> Duration {
+        self.threshold.get()
+    }
+
+    #[inline]
+    pub fn set_threshold(&self, threshold: Duration) {
+        self.threshold.set(threshold);
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        !self.threshold.get().is_zero()
+    }
+
+    /// Frames that went over the threshold so far.
+    #[inline]
+    pub fn dumps(&self) -> u64 {
+        self.dumps.get()
+    }
+
+    pub fn begin_frame(&self) {
+        self.frame_start.set(Some(Instant::now()));
+        self.depth.set(0);
+        self.scopes.borrow_mut().clear();
+        self.commands.borrow_mut().clear();
+    }
+
+    /// True once the current frame has run past the threshold, so end_frame will dump.
+    /// Check it before building anything only the dump needs, like command summaries.
+    pub fn is_armed(&self) -> bool {
+        let threshold = self.threshold.get();
+        !threshold.is_zero()
+            && self
+                .frame_start
+                .get()
+                .is_some_and(|start| start.elapsed() > threshold)
+    }
+
+    /// Times the returned guard's lifetime as part of the current frame.
+    pub fn scope(&self, name: &'static str) -> WatchdogScope<'_> {
+        let depth = self.depth.get();
+        self.depth.set(depth + 1);
+        WatchdogScope {
+            watchdog: self,
+            name,
+            depth,
+            start: Instant::now(),
+        }
+    }
+
+    /// Sets a value shown in the dump. Counters keep their last value across frames.
+    pub fn set_counter(&self, name: &'static str, value: usize) {
+        let mut counters = self.counters.borrow_mut();
+        match counters.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => counters.push((name, value)),
+        }
+    }
+
+    pub fn record_command(&self, command: impl Into<String>) {
+        if self.max_commands == 0 {
+            return;
+        }
+        let mut commands = self.commands.borrow_mut();
+        if commands.len() == self.max_commands {
+            commands.pop_front();
+        }
+        commands.push_back(command.into());
+    }
+
+    pub fn record_commands(&self, commands: impl IntoIterator<Item = String>) {
+        for command in commands {
+            self.record_command(command);
+        }
+    }
+
+    /// Ends the frame, logging and returning its diagnostics if it took longer than the
+    /// threshold. Does nothing when disabled or begin_frame wasn't called.
+    pub fn end_frame(&self) -> Option<FrameDiagnostics> {
+        let start = self.frame_start.take()?;
+        let frame = self.frame.get();
+        self.frame.set(frame + 1);
+        let duration = start.elapsed();
+        let threshold = self.threshold.get();
+        if threshold.is_zero() || duration <= threshold {
+            return None;
+        }
+        self.dumps.set(self.dumps.get() + 1);
+        let diagnostics = FrameDiagnostics {
+            frame,
+            duration,
+            threshold,
+            scopes: self.scopes.borrow().clone(),
+            counters: self.counters.borrow().clone(),
+            commands: self.commands.borrow().iter().cloned().collect(),
+        };
+        log::warn!("FrameWatchdog => long frame, {}", diagnostics);
+        Some(diagnostics)
+    }
+}
+
+/// Guard returned by FrameWatchdog::scope, records its duration when dropped.
+pub struct WatchdogScope<'a> {
+    watchdog: &'a FrameWatchdog,
+    name: &'static str,
+    depth: usize,
+    start: Instant,
+}
+
+impl Drop for WatchdogScope<'_> {
+    fn drop(&mut self) {
+        self.watchdog.depth.set(self.depth);
+        self.watchdog.scopes.borrow_mut().push(ScopeTiming {
+            name: self.name,
+            depth: self.depth,
+            duration: self.start.elapsed(),
+        });
+    }
+}
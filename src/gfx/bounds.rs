@@ -0,0 +1,184 @@
+use std::{
+    cell::{Cell, RefCell},
+    sync::Arc,
+};
+
+use cgmath::Matrix4;
+
+use crate::{eng::command::RenderCommand, sys::geom::bounds::Aabb3};
+
+use super::{gizmo::GizmoVertex, wgpu::texture::Texture};
+
+/// Corner pairs of the 12 edges of a box, see Aabb3::corners.
+const EDGES: [(usize, usize); 12] = [
+    (0, 1),
+    (2, 3),
+    (4, 5),
+    (6, 7),
+    (0, 2),
+    (1, 3),
+    (4, 6),
+    (5, 7),
+    (0, 4),
+    (1, 5),
+    (2, 6),
+    (3, 7),
+];
+
+/// Line list for the edges of `aabb`.
+pub fn box_lines(aabb: &Aabb3, color: [f32; 4]) -> Vec<GizmoVertex> {
+    if aabb.is_empty() {
+        return Vec::new();
+    }
+    let corners = aabb.corners();
+    EDGES
+        .iter()
+        .flat_map(|&(a, b)| [corners[a], corners[b]])
+        .map(|p| GizmoVertex {
+            position: p.into(),
+            color,
+        })
+        .collect()
+}
+
+/// Debug draw of bounding boxes. While enabled, DrawCtx pushes the bounds of every mesh
+/// and model it draws, and the boxes are drawn as lines over the last pass on submit.
+pub struct BoundsRenderer {
+    pipeline: Arc<wgpu::RenderPipeline>,
+    vertices: RefCell<Arc<wgpu::Buffer>>,
+    capacity: Cell<u64>,
+    lines: RefCell<Vec<GizmoVertex>>,
+    enabled: Cell<bool>,
+}
+
+impl BoundsRenderer {
+    pub const DEFAULT_COLOR: [f32; 4] = [0.2, 1.0, 0.4, 1.0];
+    const INITIAL_CAPACITY: u64 = 24 * 64;
+
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        cam_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Bounds Pipeline Layout"),
+            bind_group_layouts: &[Some(cam_bind_group_layout)],
+            immediate_size: 0,
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Bounds Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/bounds.wgsl").into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Bounds Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[Some(GizmoVertex::buffer_layout())],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            // Tested against the scene so boxes are hidden behind closer geometry, never written.
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: Some(false),
+                depth_compare: Some(wgpu::CompareFunction::LessEqual),
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline: Arc::new(pipeline),
+            vertices: RefCell::new(Arc::new(create_vertex_buffer(
+                device,
+                Self::INITIAL_CAPACITY,
+            ))),
+            capacity: Cell::new(Self::INITIAL_CAPACITY),
+            lines: RefCell::new(Vec::new()),
+            enabled: Cell::new(false),
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.get()
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.set(enabled);
+    }
+
+    /// Flips the debug draw on or off, e.g. from a key binding.
+    pub fn toggle(&self) {
+        self.enabled.set(!self.enabled.get());
+    }
+
+    /// Clears the boxes pushed last frame.
+    pub fn begin_frame(&self) {
+        self.lines.borrow_mut().clear();
+    }
+
+    /// Queues `aabb` moved by `transform`. Ignored while disabled.
+    pub fn push(&self, aabb: &Aabb3, transform: &Matrix4<f32>, color: [f32; 4]) {
+        if !self.is_enabled() {
+            return;
+        }
+        let lines = box_lines(&aabb.transformed(transform), color);
+        self.lines.borrow_mut().extend(lines);
+    }
+
+    /// Uploads this frame's boxes and returns the commands that draw them, empty if there
+    /// is nothing to draw. The camera bind group goes in group 0.
+    pub fn commands(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera_bind_group: Arc<wgpu::BindGroup>,
+    ) -> Vec<RenderCommand> {
+        let lines = self.lines.borrow();
+        if !self.is_enabled() || lines.is_empty() {
+            return Vec::new();
+        }
+        let count = lines.len() as u64;
+        if count > self.capacity.get() {
+            let capacity = count.next_power_of_two();
+            *self.vertices.borrow_mut() = Arc::new(create_vertex_buffer(device, capacity));
+            self.capacity.set(capacity);
+        }
+        let vertices = self.vertices.borrow().clone();
+        queue.write_buffer(&vertices, 0, bytemuck::cast_slice(&lines));
+        vec![
+            RenderCommand::SetPipeline(self.pipeline.clone()),
+            RenderCommand::SetBindGroup(0, camera_bind_group, None),
+            RenderCommand::SetVertexBuffer(0, vertices),
+            RenderCommand::Draw(0..count as u32, 0..1),
+        ]
+    }
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, capacity: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Bounds Vertex Buffer"),
+        size: capacity * std::mem::size_of::<GizmoVertex>() as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
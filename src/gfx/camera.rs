@@ -3,7 +3,8 @@ use std::time::Duration;
 use cgmath::{perspective, InnerSpace, Matrix4, Point3, Rad, Vector3};
 use winit::{
     dpi::PhysicalPosition,
-    event::{ElementState, KeyboardInput, MouseScrollDelta, VirtualKeyCode, WindowEvent},
+    event::{ElementState, MouseScrollDelta},
+    keyboard::KeyCode,
 };
 
 use crate::{
@@ -32,15 +33,20 @@ impl Camera {
         }
     }
 
-    pub fn calc_view_matrix(&self) -> Matrix4<f32> {
+    #[inline]
+    pub fn position(&self) -> Point3<f32> {
+        self.position
+    }
+
+    /// Normalized view direction.
+    pub fn forward(&self) -> Vector3<f32> {
         let (pitch_sin, pitch_cos) = self.pitch.0.sin_cos();
         let (yaw_sin, yaw_cos) = self.yaw.0.sin_cos();
+        Vector3::new(pitch_cos * yaw_cos, pitch_sin, pitch_cos * yaw_sin).normalize()
+    }
 
-        Matrix4::look_to_rh(
-            self.position,
-            Vector3::new(pitch_cos * yaw_cos, pitch_sin, pitch_cos * yaw_sin).normalize(),
-            Vector3::unit_y(),
-        )
+    pub fn calc_view_matrix(&self) -> Matrix4<f32> {
+        Matrix4::look_to_rh(self.position, self.forward(), Vector3::unit_y())
     }
 }
 
@@ -69,6 +75,17 @@ impl CameraUniform {
         }
     }
 
+    #[inline]
+    pub fn view_proj(&self) -> Matrix4<f32> {
+        self.view_proj.into()
+    }
+
+    #[inline]
+    pub fn view_position(&self) -> Point3<f32> {
+        let [x, y, z, _] = self.view_position;
+        Point3::new(x, y, z)
+    }
+
     pub fn from_camera(camera: &Camera, projection: &Projection) -> Self {
         CameraUniform {
             view_position: camera.position.to_homogeneous().into(),
@@ -148,38 +165,34 @@ impl PanCamera {
         }
     }
 
-    pub fn process_keyboard(
-        &mut self,
-        key: VirtualKeyCode,
-        state: ElementState,
-    ) -> InputEventStatus {
+    pub fn process_keyboard(&mut self, key: KeyCode, state: ElementState) -> InputEventStatus {
         let amount = if state == ElementState::Pressed {
             1.0
         } else {
             0.0
         };
         match key {
-            VirtualKeyCode::W | VirtualKeyCode::Up => {
+            KeyCode::KeyW | KeyCode::ArrowUp => {
                 self.ctrl.units_forward = amount;
                 InputEventStatus::Processing
             }
-            VirtualKeyCode::S | VirtualKeyCode::Down => {
+            KeyCode::KeyS | KeyCode::ArrowDown => {
                 self.ctrl.units_back = amount;
                 InputEventStatus::Processing
             }
-            VirtualKeyCode::A | VirtualKeyCode::Left => {
+            KeyCode::KeyA | KeyCode::ArrowLeft => {
                 self.ctrl.units_left = amount;
                 InputEventStatus::Processing
             }
-            VirtualKeyCode::D | VirtualKeyCode::Right => {
+            KeyCode::KeyD | KeyCode::ArrowRight => {
                 self.ctrl.units_right = amount;
                 InputEventStatus::Processing
             }
-            VirtualKeyCode::Space => {
+            KeyCode::Space => {
                 self.ctrl.units_up = amount;
                 InputEventStatus::Processing
             }
-            VirtualKeyCode::LControl => {
+            KeyCode::ControlLeft => {
                 self.ctrl.units_down = amount;
                 InputEventStatus::Processing
             }
@@ -226,6 +239,16 @@ impl Projection {
         self.aspect = width as f32 / height as f32;
     }
 
+    #[inline]
+    pub fn znear(&self) -> f32 {
+        self.znear
+    }
+
+    #[inline]
+    pub fn zfar(&self) -> f32 {
+        self.zfar
+    }
+
     pub fn calc_matrix(&self) -> Matrix4<f32> {
         OPENGL_TO_WGPU_MATRIX * perspective(self.fovy, self.aspect, self.znear, self.zfar)
     }
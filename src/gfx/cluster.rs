@@ -0,0 +1,693 @@
+use std::sync::Arc;
+
+use cgmath::{EuclideanSpace, Matrix4, Point3, SquareMatrix, Transform, Vector3, Vector4};
+use wgpu::util::DeviceExt;
+
+use super::{
+    camera::{Camera, Projection},
+    point_shadow::{PointShadowSettings, PointShadows},
+    shadow::ShadowCaster,
+    wgpu::{buffer::InstanceRaw, texture::Texture, vertex::Vertex3D},
+};
+
+/// Light slots per cluster, lights past it are dropped from that cluster. Mirrored in
+/// cluster_common.wgsl.
+pub const MAX_LIGHTS_PER_CLUSTER: usize = 64;
+
+/// Declarations shared by the cluster assignment and the clustered forward shader.
+pub const CLUSTER_WGSL: &str = include_str!("../shaders/cluster_common.wgsl");
+
+/// Point light with a hard cutoff at `radius`, lit by ClusteredLights.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PointLight {
+    pub position: Point3<f32>,
+    pub radius: f32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Whether the light may get a shadow cube, see ClusteredLights::with_shadows.
+    pub casts_shadows: bool,
+}
+
+impl PointLight {
+    pub fn new(position: Point3<f32>, radius: f32) -> Self {
+        Self {
+            position,
+            radius,
+            color: [1.0; 3],
+            intensity: 1.0,
+            casts_shadows: true,
+        }
+    }
+
+    pub fn with_color(mut self, color: [f32; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn with_casts_shadows(mut self, casts_shadows: bool) -> Self {
+        self.casts_shadows = casts_shadows;
+        self
+    }
+
+    /// Raw light without a shadow slot.
+    pub fn to_raw(&self) -> PointLightRaw {
+        PointLightRaw {
+            position_radius: [
+                self.position.x,
+                self.position.y,
+                self.position.z,
+                self.radius,
+            ],
+            color_intensity: [self.color[0], self.color[1], self.color[2], self.intensity],
+            shadow: [-1.0, 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+/// GPU layout of a PointLight, see cluster_common.wgsl.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PointLightRaw {
+    pub position_radius: [f32; 4],
+    pub color_intensity: [f32; 4],
+    /// Shadow cube slot (negative without one), depth bias and normal bias.
+    pub shadow: [f32; 4],
+}
+
+/// Camera the clusters are built for, the near and far planes bound the depth slices.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ClusterView {
+    pub view: Matrix4<f32>,
+    pub proj: Matrix4<f32>,
+    pub near: f32,
+    pub far: f32,
+    /// Render target size in pixels.
+    pub screen: [u32; 2],
+}
+
+impl ClusterView {
+    pub fn new(camera: &Camera, projection: &Projection, screen: [u32; 2]) -> Self {
+        Self {
+            view: camera.calc_view_matrix(),
+            proj: projection.calc_matrix(),
+            near: projection.znear(),
+            far: projection.zfar(),
+            screen,
+        }
+    }
+}
+
+/// How the view frustum is divided, screen tiles along x and y times exponential depth
+/// slices along z.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ClusterGrid {
+    pub tiles: [u32; 3],
+}
+
+impl Default for ClusterGrid {
+    fn default() -> Self {
+        Self { tiles: [16, 9, 24] }
+    }
+}
+
+impl ClusterGrid {
+    pub fn new(x: u32, y: u32, slices: u32) -> Self {
+        Self {
+            tiles: [x.max(1), y.max(1), slices.max(1)],
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tiles.iter().map(|&t| t as usize).product()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub fn index(&self, [x, y, z]: [u32; 3]) -> usize {
+        ((z * self.tiles[1] + y) * self.tiles[0] + x) as usize
+    }
+
+    /// Depth slice of a view space distance, mirrors cluster_slice in cluster_common.wgsl.
+    pub fn slice(&self, view: &ClusterView, depth: f32) -> u32 {
+        let slices = self.tiles[2] as f32;
+        let slice = ((depth.max(view.near) / view.near).ln() * slices
+            / (view.far / view.near).ln())
+        .floor();
+        slice.clamp(0.0, slices - 1.0) as u32
+    }
+
+    fn slice_depth(&self, view: &ClusterView, slice: u32) -> f32 {
+        view.near * (view.far / view.near).powf(slice as f32 / self.tiles[2] as f32)
+    }
+
+    /// Cluster `world` falls in, None outside the frustum.
+    pub fn cluster_at(&self, view: &ClusterView, world: Point3<f32>) -> Option<usize> {
+        let view_position = view.view.transform_point(world);
+        let clip = view.proj * view_position.to_homogeneous();
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        if ndc.x.abs() > 1.0 || ndc.y.abs() > 1.0 || !(0.0..=1.0).contains(&ndc.z) {
+            return None;
+        }
+        // Tile rows run top to bottom like fragment coordinates.
+        let x = ((ndc.x * 0.5 + 0.5) * self.tiles[0] as f32) as u32;
+        let y = ((0.5 - ndc.y * 0.5) * self.tiles[1] as f32) as u32;
+        let z = self.slice(view, -view_position.z);
+        Some(self.index([x.min(self.tiles[0] - 1), y.min(self.tiles[1] - 1), z]))
+    }
+
+    /// View space bounds of every cluster, mirrors cs_main in cluster.wgsl.
+    pub fn bounds(&self, view: &ClusterView) -> Vec<(Vector3<f32>, Vector3<f32>)> {
+        let inv_proj = view.proj.invert().unwrap_or(Matrix4::identity());
+        let unproject = |x: f32, y: f32| {
+            let p = inv_proj * Vector4::new(x, y, 0.0, 1.0);
+            p.truncate() / p.w
+        };
+        let [tx, ty, tz] = self.tiles;
+        let mut bounds = Vec::with_capacity(self.len());
+        for z in 0..tz {
+            let depths = [self.slice_depth(view, z), self.slice_depth(view, z + 1)];
+            for y in 0..ty {
+                for x in 0..tx {
+                    let x0 = -1.0 + 2.0 * x as f32 / tx as f32;
+                    let x1 = -1.0 + 2.0 * (x + 1) as f32 / tx as f32;
+                    let y0 = 1.0 - 2.0 * (y + 1) as f32 / ty as f32;
+                    let y1 = 1.0 - 2.0 * y as f32 / ty as f32;
+                    let corners = [
+                        unproject(x0, y0),
+                        unproject(x1, y0),
+                        unproject(x0, y1),
+                        unproject(x1, y1),
+                    ];
+                    let mut lo = Vector3::new(f32::MAX, f32::MAX, f32::MAX);
+                    let mut hi = Vector3::new(f32::MIN, f32::MIN, f32::MIN);
+                    for corner in corners {
+                        for depth in depths {
+                            let p = corner * (depth / -corner.z);
+                            lo = Vector3::new(lo.x.min(p.x), lo.y.min(p.y), lo.z.min(p.z));
+                            hi = Vector3::new(hi.x.max(p.x), hi.y.max(p.y), hi.z.max(p.z));
+                        }
+                    }
+                    bounds.push((lo, hi));
+                }
+            }
+        }
+        bounds
+    }
+
+    /// CPU fallback of the compute assignment, same layout as the GPU buffers.
+    pub fn assign(&self, view: &ClusterView, lights: &[PointLight]) -> ClusterLists {
+        let centers = lights
+            .iter()
+            .map(|light| view.view.transform_point(light.position))
+            .collect::<Vec<_>>();
+        let mut lists = ClusterLists {
+            counts: vec![0; self.len()],
+            indices: vec![0; self.len() * MAX_LIGHTS_PER_CLUSTER],
+        };
+        for (cluster, (lo, hi)) in self.bounds(view).into_iter().enumerate() {
+            let mut count = 0;
+            for (i, (light, center)) in lights.iter().zip(&centers).enumerate() {
+                if count == MAX_LIGHTS_PER_CLUSTER {
+                    break;
+                }
+                let d = Vector3::new(
+                    center.x - center.x.clamp(lo.x, hi.x),
+                    center.y - center.y.clamp(lo.y, hi.y),
+                    center.z - center.z.clamp(lo.z, hi.z),
+                );
+                if d.x * d.x + d.y * d.y + d.z * d.z <= light.radius * light.radius {
+                    lists.indices[cluster * MAX_LIGHTS_PER_CLUSTER + count] = i as u32;
+                    count += 1;
+                }
+            }
+            lists.counts[cluster] = count as u32;
+        }
+        lists
+    }
+}
+
+/// Per cluster light lists, `counts[c]` lights in `indices` starting at
+/// `c * MAX_LIGHTS_PER_CLUSTER`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterLists {
+    pub counts: Vec<u32>,
+    pub indices: Vec<u32>,
+}
+
+impl ClusterLists {
+    /// Indices into the light list of every light touching `cluster`.
+    pub fn lights(&self, cluster: usize) -> &[u32] {
+        let start = cluster * MAX_LIGHTS_PER_CLUSTER;
+        &self.indices[start..start + self.counts[cluster] as usize]
+    }
+}
+
+/// Where lights get sorted into clusters each frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum LightAssignment {
+    /// A compute pass, nothing is read back.
+    #[default]
+    Compute,
+    /// ClusterGrid::assign, for adapters without compute shaders.
+    Cpu,
+}
+
+/// GPU layout of the cluster parameters, see cluster_common.wgsl.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ClusterParams {
+    view: [[f32; 4]; 4],
+    inv_proj: [[f32; 4]; 4],
+    tiles: [u32; 4],
+    screen: [f32; 4],
+}
+
+/// Forward shading with hundreds of dynamic point lights. Each frame update sorts the lights
+/// into the clusters of the view, and draws through DrawCtx::draw_model_clustered light
+/// every fragment with only the lights of its cluster, on top of the window light.
+#[derive(Debug)]
+pub struct ClusteredLights {
+    grid: ClusterGrid,
+    assignment: LightAssignment,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    compute: Option<(Arc<wgpu::ComputePipeline>, wgpu::BindGroupLayout)>,
+    layout: wgpu::BindGroupLayout,
+    params: Arc<wgpu::Buffer>,
+    lights: Arc<wgpu::Buffer>,
+    counts: Arc<wgpu::Buffer>,
+    indices: Arc<wgpu::Buffer>,
+    bind_group: Arc<wgpu::BindGroup>,
+    compute_bind_group: Option<Arc<wgpu::BindGroup>>,
+    shadows: PointShadows,
+    capacity: usize,
+    count: usize,
+}
+
+impl ClusteredLights {
+    const WORKGROUP_SIZE: u32 = 64;
+
+    /// `layouts` are the texture, camera and light bind group layouts of the window's 3D
+    /// pipeline, the clusters are bound after them at group 3.
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        layouts: [&wgpu::BindGroupLayout; 3],
+        grid: ClusterGrid,
+        assignment: LightAssignment,
+    ) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("cluster_bind_group_layout"),
+            entries: &[
+                layout_entry(0, wgpu::ShaderStages::FRAGMENT, None),
+                layout_entry(1, wgpu::ShaderStages::FRAGMENT, Some(true)),
+                layout_entry(2, wgpu::ShaderStages::FRAGMENT, Some(true)),
+                layout_entry(3, wgpu::ShaderStages::FRAGMENT, Some(true)),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::CubeArray,
+                        sample_type: wgpu::TextureSampleType::Depth,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Clustered Pipeline Layout"),
+            bind_group_layouts: &[
+                Some(layouts[0]),
+                Some(layouts[1]),
+                Some(layouts[2]),
+                Some(&layout),
+            ],
+            immediate_size: 0,
+        });
+        let source = format!(
+            "{}\n{}\n{}",
+            include_str!("../shaders/basic.wgsl"),
+            CLUSTER_WGSL,
+            include_str!("../shaders/clustered.wgsl")
+        );
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Clustered Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Clustered Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[
+                    Some(Vertex3D::buffer_layout()),
+                    Some(InstanceRaw::buffer_layout()),
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_clustered"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: Some(true),
+                depth_compare: Some(wgpu::CompareFunction::Less),
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        let compute = match assignment {
+            LightAssignment::Compute => Some(create_compute_pipeline(device)),
+            LightAssignment::Cpu => None,
+        };
+
+        let params = Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cluster Params"),
+            size: std::mem::size_of::<ClusterParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }));
+        let list_usage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST;
+        let counts = Arc::new(
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Cluster Light Counts"),
+                contents: bytemuck::cast_slice(&vec![0u32; grid.len()]),
+                usage: list_usage,
+            }),
+        );
+        let indices = Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cluster Light Indices"),
+            size: (grid.len() * MAX_LIGHTS_PER_CLUSTER * std::mem::size_of::<u32>()) as u64,
+            usage: list_usage,
+            mapped_at_creation: false,
+        }));
+        let capacity = 64;
+        let lights = create_light_buffer(device, capacity);
+        let buffers = [&params, &lights, &counts, &indices];
+        // No shadows until with_shadows, the cube array still needs binding.
+        let shadows = PointShadows::new(
+            device,
+            PointShadowSettings::default()
+                .with_max_lights(0)
+                .with_resolution(1),
+        );
+        let (bind_group, compute_bind_group) =
+            create_bind_groups(device, &layout, compute.as_ref(), buffers, &shadows);
+
+        Self {
+            grid,
+            assignment,
+            pipeline: Arc::new(pipeline),
+            compute,
+            layout,
+            params,
+            lights,
+            counts,
+            indices,
+            bind_group,
+            compute_bind_group,
+            shadows,
+            capacity,
+            count: 0,
+        }
+    }
+
+    /// Gives the nearest `settings.max_lights` shadow casting lights a shadow cube, rendered
+    /// by render_shadows.
+    pub fn with_shadows(mut self, device: &wgpu::Device, settings: PointShadowSettings) -> Self {
+        self.shadows = PointShadows::new(device, settings);
+        self.rebuild_bind_groups(device);
+        self
+    }
+
+    #[inline]
+    pub fn shadows(&self) -> &PointShadows {
+        &self.shadows
+    }
+
+    #[inline]
+    pub fn grid(&self) -> ClusterGrid {
+        self.grid
+    }
+
+    #[inline]
+    pub fn assignment(&self) -> LightAssignment {
+        self.assignment
+    }
+
+    /// Lights uploaded by the last update.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    #[inline]
+    pub(crate) fn pipeline(&self) -> Arc<wgpu::RenderPipeline> {
+        self.pipeline.clone()
+    }
+
+    #[inline]
+    pub(crate) fn bind_group(&self) -> Arc<wgpu::BindGroup> {
+        self.bind_group.clone()
+    }
+
+    /// Uploads `lights` and sorts them into the clusters of `view`, growing the light buffer
+    /// if needed. With LightAssignment::Compute this records a compute pass that runs before
+    /// the frame's render passes.
+    pub fn update(
+        &mut self,
+        ctx: &mut super::draw::DrawCtx,
+        view: &ClusterView,
+        lights: &[PointLight],
+    ) {
+        if lights.len() > self.capacity {
+            self.capacity = lights.len().next_power_of_two();
+            self.lights = create_light_buffer(&ctx.device_surface.device, self.capacity);
+            self.rebuild_bind_groups(&ctx.device_surface.device);
+        }
+        self.count = lights.len();
+        let viewer = view
+            .view
+            .invert()
+            .map(|inv| inv.transform_point(Point3::origin()))
+            .unwrap_or(Point3::origin());
+        self.shadows.update(ctx, viewer, lights);
+        let raw = lights
+            .iter()
+            .enumerate()
+            .map(|(i, light)| PointLightRaw {
+                shadow: self.shadows.raw_params(i),
+                ..light.to_raw()
+            })
+            .collect::<Vec<_>>();
+        if !raw.is_empty() {
+            ctx.write_buffer(self.lights.clone(), 0, bytemuck::cast_slice(&raw));
+        }
+        let params = ClusterParams {
+            view: view.view.into(),
+            inv_proj: view.proj.invert().unwrap_or(Matrix4::identity()).into(),
+            tiles: [
+                self.grid.tiles[0],
+                self.grid.tiles[1],
+                self.grid.tiles[2],
+                lights.len() as u32,
+            ],
+            screen: [
+                view.screen[0] as f32,
+                view.screen[1] as f32,
+                view.near,
+                view.far,
+            ],
+        };
+        ctx.write_buffer(self.params.clone(), 0, bytemuck::bytes_of(&params));
+
+        match (&self.compute, &self.compute_bind_group) {
+            (Some((pipeline, _)), Some(bind_group)) => {
+                let pass = ctx.begin_compute_pass("Cluster Light Pass");
+                pass.set_pipeline(pipeline.clone());
+                pass.set_bind_group(0, bind_group.clone(), None);
+                pass.dispatch(
+                    (self.grid.len() as u32).div_ceil(Self::WORKGROUP_SIZE),
+                    1,
+                    1,
+                );
+            }
+            _ => {
+                let lists = self.grid.assign(view, lights);
+                ctx.write_buffer(self.counts.clone(), 0, bytemuck::cast_slice(&lists.counts));
+                ctx.write_buffer(
+                    self.indices.clone(),
+                    0,
+                    bytemuck::cast_slice(&lists.indices),
+                );
+            }
+        }
+    }
+
+    /// Renders the shadow cubes of the lights picked by the last update, see
+    /// PointShadows::render.
+    pub fn render_shadows(&self, ctx: &mut super::draw::DrawCtx, casters: &[ShadowCaster]) {
+        self.shadows.render(ctx, casters);
+    }
+
+    fn rebuild_bind_groups(&mut self, device: &wgpu::Device) {
+        let buffers = [&self.params, &self.lights, &self.counts, &self.indices];
+        (self.bind_group, self.compute_bind_group) = create_bind_groups(
+            device,
+            &self.layout,
+            self.compute.as_ref(),
+            buffers,
+            &self.shadows,
+        );
+    }
+}
+
+/// Fragment bind group with the shadow cubes and, with a compute pipeline, the compute one over
+/// the same buffers.
+fn create_bind_groups(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    compute: Option<&(Arc<wgpu::ComputePipeline>, wgpu::BindGroupLayout)>,
+    buffers: [&Arc<wgpu::Buffer>; 4],
+    shadows: &PointShadows,
+) -> (Arc<wgpu::BindGroup>, Option<Arc<wgpu::BindGroup>>) {
+    let mut entries = buffers
+        .iter()
+        .enumerate()
+        .map(|(binding, buffer)| wgpu::BindGroupEntry {
+            binding: binding as u32,
+            resource: buffer.as_entire_binding(),
+        })
+        .collect::<Vec<_>>();
+    let compute_bind_group = compute.map(|(_, layout)| {
+        Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("cluster_compute_bind_group"),
+            layout,
+            entries: &entries,
+        }))
+    });
+    entries.extend([
+        wgpu::BindGroupEntry {
+            binding: 4,
+            resource: wgpu::BindingResource::TextureView(shadows.cube_view()),
+        },
+        wgpu::BindGroupEntry {
+            binding: 5,
+            resource: wgpu::BindingResource::Sampler(shadows.sampler()),
+        },
+    ]);
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("cluster_bind_group"),
+        layout,
+        entries: &entries,
+    });
+    (Arc::new(bind_group), compute_bind_group)
+}
+
+fn create_compute_pipeline(
+    device: &wgpu::Device,
+) -> (Arc<wgpu::ComputePipeline>, wgpu::BindGroupLayout) {
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("cluster_compute_bind_group_layout"),
+        entries: &[
+            layout_entry(0, wgpu::ShaderStages::COMPUTE, None),
+            layout_entry(1, wgpu::ShaderStages::COMPUTE, Some(true)),
+            layout_entry(2, wgpu::ShaderStages::COMPUTE, Some(false)),
+            layout_entry(3, wgpu::ShaderStages::COMPUTE, Some(false)),
+        ],
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Cluster Light Pipeline Layout"),
+        bind_group_layouts: &[Some(&layout)],
+        immediate_size: 0,
+    });
+    let source = format!(
+        "{}\n{}",
+        CLUSTER_WGSL,
+        include_str!("../shaders/cluster.wgsl")
+    );
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Cluster Light Shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("Cluster Light Pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("cs_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+    (Arc::new(pipeline), layout)
+}
+
+/// Uniform when `read_only` is None, storage otherwise.
+fn layout_entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+    read_only: Option<bool>,
+) -> wgpu::BindGroupLayoutEntry {
+    let ty = match read_only {
+        Some(read_only) => wgpu::BufferBindingType::Storage { read_only },
+        None => wgpu::BufferBindingType::Uniform,
+    };
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn create_light_buffer(device: &wgpu::Device, capacity: usize) -> Arc<wgpu::Buffer> {
+    Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Cluster Lights"),
+        size: (capacity * std::mem::size_of::<PointLightRaw>()) as u64,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    }))
+}
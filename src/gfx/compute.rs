@@ -0,0 +1,699 @@
+use std::sync::Arc;
+
+use super::{draw::DrawCtx, wgpu::texture::Texture};
+use crate::eng::readback::ReadbackHandle;
+
+/// Format of every texture the image kernels write, bind it as rgba16float in WGSL.
+pub const STORAGE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Largest radius GaussianBlur takes, the weights live in four vec4s of its uniform.
+pub const MAX_BLUR_RADIUS: u32 = 15;
+
+/// Bins of LuminanceHistogram. Bin 0 counts black texels, the rest split the log range.
+pub const HISTOGRAM_BINS: usize = 256;
+
+/// Elements one PrefixSum workgroup scans.
+pub const SCAN_BLOCK: u32 = 256;
+
+const WORKGROUP_2D: u32 = 8;
+const HISTOGRAM_WORKGROUP: u32 = 16;
+
+/// Normalized half kernel of a gaussian, center weight first. The full kernel mirrors it,
+/// so `w[0] + 2 * (w[1] + .. + w[radius])` is one.
+pub fn gaussian_weights(sigma: f32, radius: u32) -> Vec<f32> {
+    let radius = radius.min(MAX_BLUR_RADIUS);
+    let sigma = sigma.max(f32::EPSILON);
+    let mut weights: Vec<f32> = (0..=radius)
+        .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+    weights.iter_mut().for_each(|w| *w /= total);
+    weights
+}
+
+/// Radius that covers three sigma of the kernel, clamped to MAX_BLUR_RADIUS.
+pub fn blur_radius(sigma: f32) -> u32 {
+    ((sigma * 3.0).ceil() as u32).min(MAX_BLUR_RADIUS)
+}
+
+/// Sizes of each level below `width` x `height`, halving until `levels` or 1x1.
+pub fn downsample_sizes(width: u32, height: u32, levels: u32) -> Vec<(u32, u32)> {
+    let mut size = (width.max(1), height.max(1));
+    let mut sizes = vec![];
+    while (sizes.len() as u32) < levels && size != (1, 1) {
+        size = ((size.0 / 2).max(1), (size.1 / 2).max(1));
+        sizes.push(size);
+    }
+    sizes
+}
+
+/// CPU mirror of the histogram shader's binning, for tests and readback decoding.
+pub fn luminance_bin(rgb: [f32; 3], min_log_lum: f32, max_log_lum: f32) -> usize {
+    let lum = rgb[0] * 0.2126 + rgb[1] * 0.7152 + rgb[2] * 0.0722;
+    if lum < 0.0001 {
+        return 0;
+    }
+    let inv_range = 1.0 / (max_log_lum - min_log_lum).max(f32::EPSILON);
+    let t = ((lum.log2() - min_log_lum) * inv_range).clamp(0.0, 1.0);
+    (t * 254.0 + 1.0) as usize
+}
+
+/// Average log2 luminance of a read back histogram, ignoring black texels. Returns
+/// `min_log_lum` when every texel was black.
+pub fn average_log_luminance(bins: &[u32], min_log_lum: f32, max_log_lum: f32) -> f32 {
+    let (weighted, count) = bins
+        .iter()
+        .enumerate()
+        .skip(1)
+        .fold((0.0, 0u64), |(weighted, count), (i, &n)| {
+            (weighted + i as f64 * n as f64, count + n as u64)
+        });
+    if count == 0 {
+        return min_log_lum;
+    }
+    let t = ((weighted / count as f64) as f32 - 1.0) / 254.0;
+    min_log_lum + t * (max_log_lum - min_log_lum)
+}
+
+/// Decodes the bytes of a LuminanceHistogram readback.
+pub fn histogram_bins(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(bytemuck::pod_read_unaligned)
+        .collect()
+}
+
+/// Element counts of every level a PrefixSum scans, the input first. Each level holds
+/// the block totals of the one before it, down to a single block.
+pub fn scan_levels(count: u32) -> Vec<u32> {
+    let mut levels = vec![count.max(1)];
+    while *levels.last().unwrap() > SCAN_BLOCK {
+        levels.push(levels.last().unwrap().div_ceil(SCAN_BLOCK));
+    }
+    levels
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct BlurParams {
+    direction: [i32; 2],
+    radius: u32,
+    _pad: u32,
+    weights: [[f32; 4]; 4],
+}
+
+impl BlurParams {
+    fn new(direction: [i32; 2], sigma: f32) -> Self {
+        let radius = blur_radius(sigma);
+        let mut weights = [[0.0; 4]; 4];
+        for (i, w) in gaussian_weights(sigma, radius).into_iter().enumerate() {
+            weights[i / 4][i % 4] = w;
+        }
+        Self {
+            direction,
+            radius,
+            _pad: 0,
+            weights,
+        }
+    }
+}
+
+/// Separable gaussian blur. A horizontal pass writes an intermediate texture and a
+/// vertical pass reads it back into `output`, both the size given at creation.
+#[derive(Debug)]
+pub struct GaussianBlur {
+    pipeline: Arc<wgpu::ComputePipeline>,
+    layout: wgpu::BindGroupLayout,
+    params: [Arc<wgpu::Buffer>; 2],
+    temp: Texture,
+    output: Texture,
+    bind_groups: Option<[Arc<wgpu::BindGroup>; 2]>,
+    width: u32,
+    height: u32,
+    sigma: f32,
+}
+
+impl GaussianBlur {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32, sigma: f32) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("blur_bind_group_layout"),
+            entries: &[
+                buffer_entry(0, wgpu::BufferBindingType::Uniform),
+                texture_entry(1),
+                storage_texture_entry(2),
+            ],
+        });
+        let pipeline = compute_pipeline(
+            device,
+            "Blur",
+            &layout,
+            include_str!("../shaders/compute_blur.wgsl"),
+            "cs_main",
+        );
+        let params = [[1, 0], [0, 1]].map(|direction| {
+            Arc::new(uniform_buffer(
+                device,
+                "Blur Params",
+                bytemuck::bytes_of(&BlurParams::new(direction, sigma)),
+            ))
+        });
+        Self {
+            pipeline,
+            layout,
+            params,
+            temp: storage_target(device, width, height, "Blur Intermediate"),
+            output: storage_target(device, width, height, "Blur Output"),
+            bind_groups: None,
+            width: width.max(1),
+            height: height.max(1),
+            sigma,
+        }
+    }
+
+    #[inline]
+    pub fn sigma(&self) -> f32 {
+        self.sigma
+    }
+
+    /// Changes the kernel, the radius follows sigma up to MAX_BLUR_RADIUS.
+    pub fn set_sigma(&mut self, queue: &wgpu::Queue, sigma: f32) {
+        self.sigma = sigma;
+        for (buffer, direction) in self.params.iter().zip([[1, 0], [0, 1]]) {
+            let params = BlurParams::new(direction, sigma);
+            queue.write_buffer(buffer, 0, bytemuck::bytes_of(&params));
+        }
+    }
+
+    /// Binds the texture to blur. Reads past its edges clamp, so a smaller input stretches
+    /// its border over the rest of the output.
+    pub fn set_input(&mut self, device: &wgpu::Device, input: &wgpu::TextureView) {
+        let horizontal = texture_bind_group(
+            device,
+            &self.layout,
+            Some(&self.params[0]),
+            input,
+            &self.temp.view,
+        );
+        let vertical = texture_bind_group(
+            device,
+            &self.layout,
+            Some(&self.params[1]),
+            &self.temp.view,
+            &self.output.view,
+        );
+        self.bind_groups = Some([horizontal, vertical]);
+    }
+
+    /// Blurred result of the last `blur`.
+    #[inline]
+    pub fn output(&self) -> &Texture {
+        &self.output
+    }
+
+    /// Records both passes. Does nothing until an input is set.
+    pub fn blur(&self, ctx: &mut DrawCtx) {
+        let Some(bind_groups) = &self.bind_groups else {
+            log::warn!("GaussianBlur::blur => no input set, skipping");
+            return;
+        };
+        let groups = workgroups_2d(self.width, self.height);
+        let pass = ctx.begin_compute_pass("Blur Pass");
+        pass.set_pipeline(self.pipeline.clone());
+        for bind_group in bind_groups {
+            pass.set_bind_group(0, bind_group.clone(), None);
+            pass.dispatch(groups.0, groups.1, 1);
+        }
+    }
+}
+
+/// Chain of half size levels, each a 2x2 box filter of the one above, for bloom and
+/// other effects that work down a mip pyramid.
+#[derive(Debug)]
+pub struct DownsampleChain {
+    pipeline: Arc<wgpu::ComputePipeline>,
+    layout: wgpu::BindGroupLayout,
+    levels: Vec<Texture>,
+    bind_groups: Vec<Arc<wgpu::BindGroup>>,
+}
+
+impl DownsampleChain {
+    /// Levels below a `width` x `height` source, at most `levels` of them.
+    pub fn new(device: &wgpu::Device, width: u32, height: u32, levels: u32) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("downsample_bind_group_layout"),
+            entries: &[texture_entry(0), storage_texture_entry(1)],
+        });
+        let pipeline = compute_pipeline(
+            device,
+            "Downsample",
+            &layout,
+            include_str!("../shaders/compute_downsample.wgsl"),
+            "cs_main",
+        );
+        let levels = downsample_sizes(width, height, levels)
+            .into_iter()
+            .map(|(w, h)| storage_target(device, w, h, "Downsample Level"))
+            .collect();
+        Self {
+            pipeline,
+            layout,
+            levels,
+            bind_groups: vec![],
+        }
+    }
+
+    /// Binds the full size source the first level reads.
+    pub fn set_input(&mut self, device: &wgpu::Device, input: &wgpu::TextureView) {
+        let sources = std::iter::once(input).chain(self.levels.iter().map(|level| &level.view));
+        self.bind_groups = sources
+            .zip(&self.levels)
+            .map(|(src, dst)| texture_bind_group(device, &self.layout, None, src, &dst.view))
+            .collect();
+    }
+
+    /// Levels from largest to smallest.
+    #[inline]
+    pub fn levels(&self) -> &[Texture] {
+        &self.levels
+    }
+
+    /// Records every level in order. Does nothing until an input is set.
+    pub fn downsample(&self, ctx: &mut DrawCtx) {
+        if self.bind_groups.is_empty() {
+            if !self.levels.is_empty() {
+                log::warn!("DownsampleChain::downsample => no input set, skipping");
+            }
+            return;
+        }
+        let pass = ctx.begin_compute_pass("Downsample Pass");
+        pass.set_pipeline(self.pipeline.clone());
+        for (bind_group, level) in self.bind_groups.iter().zip(&self.levels) {
+            let size = level.handle.size();
+            let groups = workgroups_2d(size.width, size.height);
+            pass.set_bind_group(0, bind_group.clone(), None);
+            pass.dispatch(groups.0, groups.1, 1);
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct HistogramParams {
+    min_log_lum: f32,
+    inv_log_range: f32,
+    _pad: [f32; 2],
+}
+
+/// Log luminance histogram of a texture in a storage buffer of HISTOGRAM_BINS u32s,
+/// the input to auto exposure. Read it back with `read` or bind `bins` in a later pass.
+#[derive(Debug)]
+pub struct LuminanceHistogram {
+    pipeline: Arc<wgpu::ComputePipeline>,
+    layout: wgpu::BindGroupLayout,
+    params: Arc<wgpu::Buffer>,
+    bins: Arc<wgpu::Buffer>,
+    bind_group: Option<Arc<wgpu::BindGroup>>,
+    size: (u32, u32),
+    min_log_lum: f32,
+    max_log_lum: f32,
+}
+
+impl LuminanceHistogram {
+    pub fn new(device: &wgpu::Device, min_log_lum: f32, max_log_lum: f32) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("histogram_bind_group_layout"),
+            entries: &[
+                buffer_entry(0, wgpu::BufferBindingType::Uniform),
+                texture_entry(1),
+                buffer_entry(2, wgpu::BufferBindingType::Storage { read_only: false }),
+            ],
+        });
+        let pipeline = compute_pipeline(
+            device,
+            "Histogram",
+            &layout,
+            include_str!("../shaders/compute_histogram.wgsl"),
+            "cs_main",
+        );
+        let params = Arc::new(uniform_buffer(
+            device,
+            "Histogram Params",
+            bytemuck::bytes_of(&histogram_params(min_log_lum, max_log_lum)),
+        ));
+        let bins = Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Histogram Bins"),
+            size: (HISTOGRAM_BINS * std::mem::size_of::<u32>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        }));
+        Self {
+            pipeline,
+            layout,
+            params,
+            bins,
+            bind_group: None,
+            size: (0, 0),
+            min_log_lum,
+            max_log_lum,
+        }
+    }
+
+    #[inline]
+    pub fn range(&self) -> (f32, f32) {
+        (self.min_log_lum, self.max_log_lum)
+    }
+
+    pub fn set_range(&mut self, queue: &wgpu::Queue, min_log_lum: f32, max_log_lum: f32) {
+        self.min_log_lum = min_log_lum;
+        self.max_log_lum = max_log_lum;
+        let params = histogram_params(min_log_lum, max_log_lum);
+        queue.write_buffer(&self.params, 0, bytemuck::bytes_of(&params));
+    }
+
+    /// Binds the texture to measure, `width` x `height` texels of it are counted.
+    pub fn set_input(
+        &mut self,
+        device: &wgpu::Device,
+        input: &wgpu::TextureView,
+        width: u32,
+        height: u32,
+    ) {
+        self.bind_group = Some(Arc::new(device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("histogram_bind_group"),
+                layout: &self.layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: self.params.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(input),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: self.bins.as_entire_binding(),
+                    },
+                ],
+            },
+        )));
+        self.size = (width, height);
+    }
+
+    #[inline]
+    pub fn bins(&self) -> &Arc<wgpu::Buffer> {
+        &self.bins
+    }
+
+    /// Clears the bins and records the count. Does nothing until an input is set.
+    pub fn measure(&self, ctx: &mut DrawCtx) {
+        let Some(bind_group) = &self.bind_group else {
+            log::warn!("LuminanceHistogram::measure => no input set, skipping");
+            return;
+        };
+        ctx.write_buffer(self.bins.clone(), 0, &[0; HISTOGRAM_BINS * 4]);
+        let groups = (
+            self.size.0.div_ceil(HISTOGRAM_WORKGROUP),
+            self.size.1.div_ceil(HISTOGRAM_WORKGROUP),
+        );
+        let pass = ctx.begin_compute_pass("Histogram Pass");
+        pass.set_pipeline(self.pipeline.clone());
+        pass.set_bind_group(0, bind_group.clone(), None);
+        pass.dispatch(groups.0, groups.1, 1);
+    }
+
+    /// Reads the bins back once this frame is submitted, decode them with `histogram_bins`.
+    pub fn read(&self, ctx: &DrawCtx) -> ReadbackHandle {
+        ctx.read_buffer(self.bins.clone(), 0..self.bins.size())
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ScanParams {
+    count: u32,
+    _pad: [u32; 3],
+}
+
+#[derive(Debug)]
+struct ScanLevel {
+    count: u32,
+    bind_group: Arc<wgpu::BindGroup>,
+}
+
+/// Exclusive prefix sum over a storage buffer of u32s, in place. Every level scans
+/// blocks of SCAN_BLOCK and hands the block totals to the next, then the scanned totals
+/// are added back on the way down. Handles up to SCAN_BLOCK * 65535 elements.
+#[derive(Debug)]
+pub struct PrefixSum {
+    scan: Arc<wgpu::ComputePipeline>,
+    add: Arc<wgpu::ComputePipeline>,
+    levels: Vec<ScanLevel>,
+    total: Arc<wgpu::Buffer>,
+}
+
+impl PrefixSum {
+    /// Scans the first `count` u32s of `data`, which needs STORAGE usage.
+    pub fn new(device: &wgpu::Device, data: &Arc<wgpu::Buffer>, count: u32) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("scan_bind_group_layout"),
+            entries: &[
+                buffer_entry(0, wgpu::BufferBindingType::Uniform),
+                buffer_entry(1, wgpu::BufferBindingType::Storage { read_only: false }),
+                buffer_entry(2, wgpu::BufferBindingType::Storage { read_only: false }),
+            ],
+        });
+        let source = include_str!("../shaders/compute_scan.wgsl");
+        let scan = compute_pipeline(device, "Scan", &layout, source, "scan_blocks");
+        let add = compute_pipeline(device, "Scan Add", &layout, source, "add_offsets");
+
+        let mut input = data.clone();
+        let mut levels = vec![];
+        for count in scan_levels(count) {
+            let sums = Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Scan Block Sums"),
+                size: (count.div_ceil(SCAN_BLOCK) * 4) as u64,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }));
+            let params = uniform_buffer(
+                device,
+                "Scan Params",
+                bytemuck::bytes_of(&ScanParams {
+                    count,
+                    _pad: [0; 3],
+                }),
+            );
+            let bind_group = Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("scan_bind_group"),
+                layout: &layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: input.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: sums.as_entire_binding(),
+                    },
+                ],
+            }));
+            levels.push(ScanLevel { count, bind_group });
+            input = sums;
+        }
+        Self {
+            scan,
+            add,
+            levels,
+            total: input,
+        }
+    }
+
+    /// Holds the sum of every element after `scan`, e.g. the count of a stream compaction.
+    #[inline]
+    pub fn total_buffer(&self) -> &Arc<wgpu::Buffer> {
+        &self.total
+    }
+
+    /// Records the scan of the whole buffer.
+    pub fn scan(&self, ctx: &mut DrawCtx) {
+        let pass = ctx.begin_compute_pass("Scan Pass");
+        pass.set_pipeline(self.scan.clone());
+        for level in &self.levels {
+            pass.set_bind_group(0, level.bind_group.clone(), None);
+            pass.dispatch(level.count.div_ceil(SCAN_BLOCK), 1, 1);
+        }
+        if self.levels.len() < 2 {
+            return;
+        }
+        // The last level is a single block, its totals need adding nowhere.
+        pass.set_pipeline(self.add.clone());
+        for level in self.levels[..self.levels.len() - 1].iter().rev() {
+            pass.set_bind_group(0, level.bind_group.clone(), None);
+            pass.dispatch(level.count.div_ceil(SCAN_BLOCK), 1, 1);
+        }
+    }
+}
+
+fn histogram_params(min_log_lum: f32, max_log_lum: f32) -> HistogramParams {
+    HistogramParams {
+        min_log_lum,
+        inv_log_range: 1.0 / (max_log_lum - min_log_lum).max(f32::EPSILON),
+        _pad: [0.0; 2],
+    }
+}
+
+fn workgroups_2d(width: u32, height: u32) -> (u32, u32) {
+    (width.div_ceil(WORKGROUP_2D), height.div_ceil(WORKGROUP_2D))
+}
+
+fn compute_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: &wgpu::BindGroupLayout,
+    source: &str,
+    entry_point: &str,
+) -> Arc<wgpu::ComputePipeline> {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(&format!("{label} Pipeline Layout")),
+        bind_group_layouts: &[Some(layout)],
+        immediate_size: 0,
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(&format!("{label} Shader")),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    Arc::new(
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(&format!("{label} Pipeline")),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some(entry_point),
+            compilation_options: Default::default(),
+            cache: None,
+        }),
+    )
+}
+
+fn uniform_buffer(device: &wgpu::Device, label: &str, contents: &[u8]) -> wgpu::Buffer {
+    use wgpu::util::DeviceExt;
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+fn storage_target(device: &wgpu::Device, width: u32, height: u32, label: &str) -> Texture {
+    let handle = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: STORAGE_FORMAT,
+        usage: wgpu::TextureUsages::STORAGE_BINDING
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = handle.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
+    Texture {
+        handle,
+        view,
+        sampler,
+    }
+}
+
+/// Bind group of an optional uniform at 0, then the source and destination textures.
+fn texture_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    params: Option<&wgpu::Buffer>,
+    src: &wgpu::TextureView,
+    dst: &wgpu::TextureView,
+) -> Arc<wgpu::BindGroup> {
+    let first = params.is_some() as u32;
+    let mut entries = vec![
+        wgpu::BindGroupEntry {
+            binding: first,
+            resource: wgpu::BindingResource::TextureView(src),
+        },
+        wgpu::BindGroupEntry {
+            binding: first + 1,
+            resource: wgpu::BindingResource::TextureView(dst),
+        },
+    ];
+    if let Some(params) = params {
+        entries.insert(
+            0,
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params.as_entire_binding(),
+            },
+        );
+    }
+    Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("compute_texture_bind_group"),
+        layout,
+        entries: &entries,
+    }))
+}
+
+fn buffer_entry(binding: u32, ty: wgpu::BufferBindingType) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn texture_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+        },
+        count: None,
+    }
+}
+
+fn storage_texture_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::StorageTexture {
+            access: wgpu::StorageTextureAccess::WriteOnly,
+            format: STORAGE_FORMAT,
+            view_dimension: wgpu::TextureViewDimension::D2,
+        },
+        count: None,
+    }
+}
@@ -0,0 +1,570 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{bail, ensure};
+use cgmath::{EuclideanSpace, InnerSpace, Point3, Quaternion, Vector3};
+use wgpu::util::DeviceExt;
+
+use super::{
+    draw::DrawCtx,
+    model::{Material, Mesh},
+    wgpu::{
+        buffer::{Instance, InstanceRaw},
+        texture::Texture,
+        vertex::Vertex3D,
+    },
+};
+
+/// One clip's frames in a vertex animation texture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexAnimationClip {
+    pub name: String,
+    /// Texture row of the clip's first frame.
+    pub first_frame: u32,
+    pub frames: u32,
+    pub fps: f32,
+    pub looping: bool,
+}
+
+impl VertexAnimationClip {
+    #[inline]
+    pub fn duration(&self) -> f32 {
+        self.frames as f32 / self.fps
+    }
+
+    /// Frames to blend between `time` seconds into the clip. Looping clips blend their last
+    /// frame back into the first, others hold the last frame.
+    pub fn sample(&self, time: f32) -> AnimationFrame {
+        let last = self.frames.saturating_sub(1);
+        let position = time.max(0.0) * self.fps;
+        let (frame, next, blend) = if self.looping {
+            let position = position % self.frames as f32;
+            let frame = (position as u32).min(last);
+            (frame, (frame + 1) % self.frames.max(1), position.fract())
+        } else if position >= last as f32 {
+            (last, last, 0.0)
+        } else {
+            (position as u32, position as u32 + 1, position.fract())
+        };
+        AnimationFrame {
+            row: self.first_frame + frame,
+            next_row: self.first_frame + next,
+            blend,
+        }
+    }
+}
+
+/// Rows of the vertex animation texture an instance is drawn with.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct AnimationFrame {
+    pub row: u32,
+    pub next_row: u32,
+    /// How far towards `next_row`, 0..1.
+    pub blend: f32,
+}
+
+/// Object space vertex positions of every frame of every clip, for a single mesh. Texture
+/// columns are the mesh's vertices in vertex buffer order, rows are frames. Normals are taken
+/// from the mesh as is.
+#[derive(Debug, Clone, Default)]
+pub struct VertexAnimationData {
+    vertex_count: usize,
+    clips: Vec<VertexAnimationClip>,
+    texels: Vec<[f32; 4]>,
+}
+
+impl VertexAnimationData {
+    pub fn new(vertex_count: usize) -> Self {
+        Self {
+            vertex_count,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    #[inline]
+    pub fn clips(&self) -> &[VertexAnimationClip] {
+        &self.clips
+    }
+
+    /// Index of the clip called `name`.
+    pub fn clip(&self, name: &str) -> Option<u32> {
+        self.clips
+            .iter()
+            .position(|clip| clip.name == name)
+            .map(|i| i as u32)
+    }
+
+    /// Rows in the texture, every frame of every clip.
+    pub fn frame_count(&self) -> u32 {
+        (self.texels.len() / self.vertex_count.max(1)) as u32
+    }
+
+    /// Appends a clip of pre baked frames, each holding a position for every vertex. Returns
+    /// the clip index instances play it by.
+    pub fn add_clip(
+        &mut self,
+        name: impl Into<String>,
+        fps: f32,
+        looping: bool,
+        frames: &[Vec<[f32; 3]>],
+    ) -> anyhow::Result<u32> {
+        let name = name.into();
+        ensure!(
+            !frames.is_empty() && fps > 0.0,
+            "VertexAnimationData::add_clip => clip {} needs frames and a positive fps",
+            name
+        );
+        if let Some(frame) = frames.iter().find(|f| f.len() != self.vertex_count) {
+            bail!(
+                "VertexAnimationData::add_clip => clip {} has a frame of {} vertices, expected {}",
+                name,
+                frame.len(),
+                self.vertex_count
+            );
+        }
+        let first_frame = self.frame_count();
+        self.texels
+            .extend(frames.iter().flatten().map(|&[x, y, z]| [x, y, z, 1.0]));
+        self.clips.push(VertexAnimationClip {
+            name,
+            first_frame,
+            frames: frames.len() as u32,
+            fps,
+            looping,
+        });
+        Ok(self.clips.len() as u32 - 1)
+    }
+
+    /// Bakes `frames` frames by moving every rest pose vertex with `animate(time, position)`.
+    pub fn bake_clip(
+        &mut self,
+        name: impl Into<String>,
+        fps: f32,
+        looping: bool,
+        frames: u32,
+        rest: &[Vertex3D],
+        mut animate: impl FnMut(f32, [f32; 3]) -> [f32; 3],
+    ) -> anyhow::Result<u32> {
+        let baked = (0..frames)
+            .map(|frame| {
+                let time = frame as f32 / fps;
+                rest.iter()
+                    .map(|v| animate(time, v.position))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        self.add_clip(name, fps, looping, &baked)
+    }
+}
+
+/// A character in a Crowd, playing `clip` from `time_offset` seconds in.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CrowdInstance {
+    pub position: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub clip: u32,
+    pub time_offset: f32,
+    /// Playback rate of the clip.
+    pub speed: f32,
+}
+
+impl CrowdInstance {
+    pub fn new(position: Vector3<f32>, rotation: Quaternion<f32>, clip: u32) -> Self {
+        Self {
+            position,
+            rotation,
+            clip,
+            time_offset: 0.0,
+            speed: 1.0,
+        }
+    }
+
+    pub fn with_time_offset(mut self, time_offset: f32) -> Self {
+        self.time_offset = time_offset;
+        self
+    }
+
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn to_raw(&self, frame: AnimationFrame) -> CrowdInstanceRaw {
+        let instance = Instance {
+            position: self.position,
+            rotation: self.rotation,
+        };
+        CrowdInstanceRaw {
+            instance: instance.to_raw(),
+            animation: [frame.row as f32, frame.next_row as f32, frame.blend, 0.0],
+        }
+    }
+}
+
+/// InstanceRaw followed by the instance's AnimationFrame, see crowd.wgsl.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CrowdInstanceRaw {
+    instance: InstanceRaw,
+    animation: [f32; 4],
+}
+
+impl std::fmt::Debug for CrowdInstanceRaw {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CrowdInstanceRaw")
+            .field("animation", &self.animation)
+            .finish()
+    }
+}
+
+impl CrowdInstanceRaw {
+    const ATTRIBS: [wgpu::VertexAttribute; 8] = wgpu::vertex_attr_array![5 => Float32x4, 6 => Float32x4, 7 => Float32x4, 8 => Float32x4, 9 => Float32x3, 10 => Float32x3, 11 => Float32x3, 12 => Float32x4];
+
+    pub fn buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<CrowdInstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// Up to `max_distance` from the viewer, instances resample their animation every
+/// `interval` frames.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CrowdLodLevel {
+    pub max_distance: f32,
+    pub interval: u32,
+}
+
+/// Animation update rates by distance, far instances keep their last frame in between
+/// updates. Levels are sorted by distance, instances past the last level use its interval.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrowdLod {
+    pub levels: Vec<CrowdLodLevel>,
+}
+
+impl Default for CrowdLod {
+    fn default() -> Self {
+        Self::new(&[(20.0, 1), (50.0, 2), (100.0, 4), (f32::MAX, 8)])
+    }
+}
+
+impl CrowdLod {
+    /// `(max_distance, interval)` pairs.
+    pub fn new(levels: &[(f32, u32)]) -> Self {
+        let mut levels = levels
+            .iter()
+            .map(|&(max_distance, interval)| CrowdLodLevel {
+                max_distance,
+                interval: interval.max(1),
+            })
+            .collect::<Vec<_>>();
+        levels.sort_by(|a, b| a.max_distance.total_cmp(&b.max_distance));
+        Self { levels }
+    }
+
+    /// Every instance animated every frame.
+    pub fn full() -> Self {
+        Self::new(&[])
+    }
+
+    /// Frames between animation updates `distance` from the viewer.
+    pub fn interval(&self, distance: f32) -> u32 {
+        self.levels
+            .iter()
+            .find(|level| distance <= level.max_distance)
+            .or(self.levels.last())
+            .map(|level| level.interval)
+            .unwrap_or(1)
+    }
+
+    /// Whether the instance at `index`, `distance` from the viewer, resamples its animation
+    /// on frame `tick`. Index staggers the instances of a level across its interval.
+    pub fn is_due(&self, distance: f32, tick: u64, index: usize) -> bool {
+        (tick + index as u64).is_multiple_of(self.interval(distance) as u64)
+    }
+}
+
+/// Many instances of one vertex animated mesh drawn in a single instanced draw, each with its
+/// own clip and time offset. animate steps the clips on the CPU with LOD based update rates,
+/// draw uploads the instances and draws them lit like draw_mesh_instanced.
+#[derive(Debug)]
+pub struct Crowd {
+    clips: Vec<VertexAnimationClip>,
+    lod: CrowdLod,
+    instances: Vec<CrowdInstance>,
+    frames: Vec<AnimationFrame>,
+    raw: Vec<CrowdInstanceRaw>,
+    time: f32,
+    tick: u64,
+    updated: usize,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    bind_group: Arc<wgpu::BindGroup>,
+    buffer: Arc<wgpu::Buffer>,
+    capacity: usize,
+}
+
+impl Crowd {
+    /// `layouts` are the texture, camera and light bind group layouts of the window's 3D
+    /// pipeline, the animation texture is bound after them at group 3.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        color_format: wgpu::TextureFormat,
+        layouts: [&wgpu::BindGroupLayout; 3],
+        animation: &VertexAnimationData,
+        lod: CrowdLod,
+    ) -> anyhow::Result<Self> {
+        let (width, height) = (animation.vertex_count as u32, animation.frame_count());
+        let max = device.limits().max_texture_dimension_2d;
+        ensure!(
+            width > 0 && height > 0,
+            "Crowd::new => vertex animation has no vertices or frames"
+        );
+        ensure!(
+            width <= max && height <= max,
+            "Crowd::new => vertex animation of {}x{} exceeds the {} texture limit",
+            width,
+            height,
+            max
+        );
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("Vertex Animation Texture"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba32Float,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::LayerMajor,
+            bytemuck::cast_slice(&animation.texels),
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("vertex_animation_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("vertex_animation_bind_group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            }],
+        });
+        let capacity = 64;
+
+        Ok(Self {
+            clips: animation.clips.clone(),
+            lod,
+            instances: Vec::new(),
+            frames: Vec::new(),
+            raw: Vec::new(),
+            time: 0.0,
+            tick: 0,
+            updated: 0,
+            pipeline: Arc::new(create_pipeline(device, color_format, layouts, &layout)),
+            bind_group: Arc::new(bind_group),
+            buffer: create_instance_buffer(device, capacity),
+            capacity,
+        })
+    }
+
+    #[inline]
+    pub fn clips(&self) -> &[VertexAnimationClip] {
+        &self.clips
+    }
+
+    #[inline]
+    pub fn lod(&self) -> &CrowdLod {
+        &self.lod
+    }
+
+    pub fn set_lod(&mut self, lod: CrowdLod) {
+        self.lod = lod;
+    }
+
+    #[inline]
+    pub fn instances(&self) -> &[CrowdInstance] {
+        &self.instances
+    }
+
+    /// Moving instances is picked up by the next animate.
+    #[inline]
+    pub fn instances_mut(&mut self) -> &mut Vec<CrowdInstance> {
+        &mut self.instances
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Frames the instances were drawn with by the last animate.
+    #[inline]
+    pub fn frames(&self) -> &[AnimationFrame] {
+        &self.frames
+    }
+
+    /// Instances whose animation was resampled by the last animate.
+    #[inline]
+    pub fn updated(&self) -> usize {
+        self.updated
+    }
+
+    /// Advances the crowd's clock and resamples the animation of every instance due for an
+    /// update at its distance from `viewer`. Updates of one LOD level are staggered across
+    /// frames by instance index.
+    pub fn animate(&mut self, dt: Duration, viewer: Point3<f32>) {
+        self.time += dt.as_secs_f32();
+        self.tick += 1;
+        self.updated = 0;
+        self.frames.truncate(self.instances.len());
+        self.raw.clear();
+        for (i, instance) in self.instances.iter().enumerate() {
+            let distance = (Point3::from_vec(instance.position) - viewer).magnitude();
+            if i >= self.frames.len() || self.lod.is_due(distance, self.tick, i) {
+                let frame = self
+                    .clips
+                    .get(instance.clip as usize)
+                    .map(|clip| clip.sample(self.time * instance.speed + instance.time_offset))
+                    .unwrap_or_default();
+                if i < self.frames.len() {
+                    self.frames[i] = frame;
+                } else {
+                    self.frames.push(frame);
+                }
+                self.updated += 1;
+            }
+            self.raw.push(instance.to_raw(self.frames[i]));
+        }
+    }
+
+    /// Uploads the instances as of the last animate and draws `mesh` once for all of them.
+    pub fn draw(&mut self, ctx: &mut DrawCtx, mesh: &Mesh, mat: &Material) {
+        if self.raw.is_empty() {
+            return;
+        }
+        if self.raw.len() > self.capacity {
+            self.capacity = self.raw.len().next_power_of_two();
+            self.buffer = create_instance_buffer(&ctx.device_surface.device, self.capacity);
+        }
+        ctx.write_buffer(self.buffer.clone(), 0, bytemuck::cast_slice(&self.raw));
+        ctx.draw_crowd(mesh, mat, self);
+    }
+
+    #[inline]
+    pub(crate) fn pipeline(&self) -> Arc<wgpu::RenderPipeline> {
+        self.pipeline.clone()
+    }
+
+    #[inline]
+    pub(crate) fn bind_group(&self) -> Arc<wgpu::BindGroup> {
+        self.bind_group.clone()
+    }
+
+    #[inline]
+    pub(crate) fn instance_buffer(&self) -> Arc<wgpu::Buffer> {
+        self.buffer.clone()
+    }
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> Arc<wgpu::Buffer> {
+    Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Crowd Instances"),
+        size: (capacity * std::mem::size_of::<CrowdInstanceRaw>()) as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    }))
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    color_format: wgpu::TextureFormat,
+    layouts: [&wgpu::BindGroupLayout; 3],
+    animation_layout: &wgpu::BindGroupLayout,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Crowd Pipeline Layout"),
+        bind_group_layouts: &[
+            Some(layouts[0]),
+            Some(layouts[1]),
+            Some(layouts[2]),
+            Some(animation_layout),
+        ],
+        immediate_size: 0,
+    });
+    let source = format!(
+        "{}\n{}",
+        include_str!("../shaders/basic.wgsl"),
+        include_str!("../shaders/crowd.wgsl")
+    );
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Crowd Shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Crowd Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_crowd"),
+            compilation_options: Default::default(),
+            buffers: &[
+                Some(Vertex3D::buffer_layout()),
+                Some(CrowdInstanceRaw::buffer_layout()),
+            ],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: color_format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            cull_mode: Some(wgpu::Face::Back),
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled: Some(true),
+            depth_compare: Some(wgpu::CompareFunction::Less),
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview_mask: None,
+        cache: None,
+    })
+}
@@ -0,0 +1,299 @@
+use std::sync::Arc;
+
+use cgmath::{EuclideanSpace, InnerSpace, Matrix, Matrix4, Point3, Vector4};
+use wgpu::util::DeviceExt;
+
+use super::{
+    draw::DrawCtx,
+    model::{Material, Mesh},
+    wgpu::buffer::InstanceRaw,
+};
+
+/// The six planes of a view frustum, normals pointing inwards.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near, far as (normal, distance).
+    pub planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of a wgpu style view projection, depth from 0 to 1.
+    pub fn from_view_proj(view_proj: Matrix4<f32>) -> Self {
+        let row = |i| view_proj.row(i);
+        let planes = [
+            row(3) + row(0),
+            row(3) - row(0),
+            row(3) + row(1),
+            row(3) - row(1),
+            row(2),
+            row(3) - row(2),
+        ];
+        Self {
+            planes: planes.map(|p| p / p.truncate().magnitude()),
+        }
+    }
+
+    /// False only if the sphere is fully outside one of the planes.
+    pub fn intersects_sphere(&self, center: Point3<f32>, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|p| p.truncate().dot(center.to_vec()) + p.w >= -radius)
+    }
+}
+
+/// Bounding sphere of a mesh in model space, scaled by each instance's largest axis.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Point3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Point3<f32>, radius: f32) -> Self {
+        Self { center, radius }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct CullParams {
+    planes: [[f32; 4]; 6],
+    sphere: [f32; 4],
+    count: u32,
+    _pad: [u32; 3],
+}
+
+/// Frustum culls large instance counts on the GPU. A compute pass tests every instance's
+/// bounding sphere, compacts the visible ones into instance_buffer and counts them into
+/// indirect_buffer, so the draw never round trips through the CPU.
+#[derive(Debug)]
+pub struct GpuCuller {
+    pipeline: Arc<wgpu::ComputePipeline>,
+    layout: wgpu::BindGroupLayout,
+    params: Arc<wgpu::Buffer>,
+    instances: Arc<wgpu::Buffer>,
+    visible: Arc<wgpu::Buffer>,
+    args: Arc<wgpu::Buffer>,
+    bind_group: Arc<wgpu::BindGroup>,
+    capacity: usize,
+    count: u32,
+    pub bounds: BoundingSphere,
+}
+
+impl GpuCuller {
+    const WORKGROUP_SIZE: u32 = 64;
+
+    pub fn new(device: &wgpu::Device, capacity: usize, bounds: BoundingSphere) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("cull_bind_group_layout"),
+            entries: &[
+                layout_entry(0, wgpu::BufferBindingType::Uniform),
+                layout_entry(1, wgpu::BufferBindingType::Storage { read_only: true }),
+                layout_entry(2, wgpu::BufferBindingType::Storage { read_only: false }),
+                layout_entry(3, wgpu::BufferBindingType::Storage { read_only: false }),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Cull Pipeline Layout"),
+            bind_group_layouts: &[Some(&layout)],
+            immediate_size: 0,
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Cull Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/cull.wgsl").into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Cull Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: Some("cs_main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let params = Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cull Params"),
+            size: std::mem::size_of::<CullParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }));
+        let args = Arc::new(
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Cull Indirect Args"),
+                contents: wgpu::util::DrawIndexedIndirectArgs {
+                    index_count: 0,
+                    instance_count: 0,
+                    first_index: 0,
+                    base_vertex: 0,
+                    first_instance: 0,
+                }
+                .as_bytes(),
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::INDIRECT
+                    | wgpu::BufferUsages::COPY_DST,
+            }),
+        );
+        let capacity = capacity.max(1);
+        let (instances, visible) = create_instance_buffers(device, capacity);
+        let bind_group = create_bind_group(device, &layout, &params, &instances, &visible, &args);
+
+        Self {
+            pipeline: Arc::new(pipeline),
+            layout,
+            params,
+            instances,
+            visible,
+            args,
+            bind_group,
+            capacity,
+            count: 0,
+            bounds,
+        }
+    }
+
+    /// Uploads the instances to cull, growing the buffers if needed.
+    pub fn set_instances(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instances: &[InstanceRaw],
+    ) {
+        if instances.len() > self.capacity {
+            self.capacity = instances.len().next_power_of_two();
+            let (input, visible) = create_instance_buffers(device, self.capacity);
+            self.instances = input;
+            self.visible = visible;
+            self.bind_group = create_bind_group(
+                device,
+                &self.layout,
+                &self.params,
+                &self.instances,
+                &self.visible,
+                &self.args,
+            );
+        }
+        queue.write_buffer(&self.instances, 0, bytemuck::cast_slice(instances));
+        self.count = instances.len() as u32;
+    }
+
+    #[inline]
+    pub fn len(&self) -> u32 {
+        self.count
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Visible instances of the last cull, bind it at vertex slot 1 like any InstanceRaw buffer.
+    #[inline]
+    pub fn instance_buffer(&self) -> &Arc<wgpu::Buffer> {
+        &self.visible
+    }
+
+    /// DrawIndexedIndirect arguments at offset 0.
+    #[inline]
+    pub fn indirect_buffer(&self) -> &Arc<wgpu::Buffer> {
+        &self.args
+    }
+
+    /// Records the cull for a mesh with `index_count` indices. The arguments are reset with
+    /// a queue write, so a culler can only be culled once per frame.
+    pub fn cull(&self, ctx: &mut DrawCtx, view_proj: Matrix4<f32>, index_count: u32) {
+        let frustum = Frustum::from_view_proj(view_proj);
+        let params = CullParams {
+            planes: frustum.planes.map(Into::into),
+            sphere: [
+                self.bounds.center.x,
+                self.bounds.center.y,
+                self.bounds.center.z,
+                self.bounds.radius,
+            ],
+            count: self.count,
+            _pad: [0; 3],
+        };
+        let args = wgpu::util::DrawIndexedIndirectArgs {
+            index_count,
+            instance_count: 0,
+            first_index: 0,
+            base_vertex: 0,
+            first_instance: 0,
+        };
+        ctx.write_buffer(self.params.clone(), 0, bytemuck::bytes_of(&params));
+        ctx.write_buffer(self.args.clone(), 0, args.as_bytes());
+        if self.count == 0 {
+            return;
+        }
+
+        let pass = ctx.begin_compute_pass("Cull Pass");
+        pass.set_pipeline(self.pipeline.clone());
+        pass.set_bind_group(0, self.bind_group.clone(), None);
+        pass.dispatch(self.count.div_ceil(Self::WORKGROUP_SIZE), 1, 1);
+    }
+
+    /// Draws the instances that survived the last cull.
+    pub fn draw(&self, ctx: &mut DrawCtx, mesh: &Mesh, mat: &Material) {
+        ctx.set_vertex_buffer(1, self.visible.clone());
+        ctx.draw_mesh_indirect(mesh, mat, self.args.clone(), 0);
+    }
+}
+
+fn layout_entry(binding: u32, ty: wgpu::BufferBindingType) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn create_instance_buffers(
+    device: &wgpu::Device,
+    capacity: usize,
+) -> (Arc<wgpu::Buffer>, Arc<wgpu::Buffer>) {
+    let size = (capacity * std::mem::size_of::<InstanceRaw>()) as u64;
+    let instances = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Cull Instances"),
+        size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let visible = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Cull Visible Instances"),
+        size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+        mapped_at_creation: false,
+    });
+    (Arc::new(instances), Arc::new(visible))
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    params: &wgpu::Buffer,
+    instances: &wgpu::Buffer,
+    visible: &wgpu::Buffer,
+    args: &wgpu::Buffer,
+) -> Arc<wgpu::BindGroup> {
+    fn entry(binding: u32, buffer: &wgpu::Buffer) -> wgpu::BindGroupEntry<'_> {
+        wgpu::BindGroupEntry {
+            binding,
+            resource: buffer.as_entire_binding(),
+        }
+    }
+    Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("cull_bind_group"),
+        layout,
+        entries: &[
+            entry(0, params),
+            entry(1, instances),
+            entry(2, visible),
+            entry(3, args),
+        ],
+    }))
+}
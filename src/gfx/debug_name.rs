@@ -0,0 +1,56 @@
+use std::fmt;
+
+/// Id hashed from a name, identical across runs and machines so GPU captures and profiles
+/// of different sessions can be compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DebugId(pub u64);
+
+impl DebugId {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    pub const fn from_name(name: &str) -> Self {
+        Self(Self::FNV_OFFSET).with(name)
+    }
+
+    /// Id of `name` nested under this one, e.g. a mesh within a model.
+    pub const fn with(self, name: &str) -> Self {
+        let bytes = name.as_bytes();
+        // Separator so ("ab", "c") and ("a", "bc") differ.
+        let mut hash = (self.0 ^ 0xff).wrapping_mul(Self::FNV_PRIME);
+        let mut i = 0;
+        while i < bytes.len() {
+            hash = (hash ^ bytes[i] as u64).wrapping_mul(Self::FNV_PRIME);
+            i += 1;
+        }
+        Self(hash)
+    }
+}
+
+impl fmt::Display for DebugId {
+    /// 8 hex digits, short enough to read in a capture's event list.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:08x}", (self.0 ^ (self.0 >> 32)) as u32)
+    }
+}
+
+/// Things that show up by name in GPU captures, see DrawCtx::push_debug_scope. Draws of
+/// meshes, materials and quad batches are labelled automatically.
+pub trait DebugName {
+    /// What kind of thing this is, e.g. "Mesh".
+    fn debug_kind(&self) -> &'static str;
+    fn debug_name(&self) -> &str;
+
+    fn debug_id(&self) -> DebugId {
+        DebugId::from_name(self.debug_kind()).with(self.debug_name())
+    }
+
+    /// `Kind name #id`, unnamed things still get a stable id from their kind.
+    fn debug_label(&self) -> String {
+        let name = match self.debug_name() {
+            "" => "<unnamed>",
+            name => name,
+        };
+        format!("{} {} #{}", self.debug_kind(), name, self.debug_id())
+    }
+}
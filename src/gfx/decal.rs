@@ -0,0 +1,440 @@
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use cgmath::{
+    EuclideanSpace, InnerSpace, Matrix4, Point3, Rad, SquareMatrix, Transform, Vector3, Vector4,
+};
+
+use super::{
+    quad::UvRect,
+    wgpu::{texture::Texture, vertex::Vertex3D},
+};
+
+/// A texture projected onto geometry, e.g. a bullet hole or a sticker. The projector is a
+/// box centered on `position`, facing along `-normal` into the surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decal {
+    pub position: Point3<f32>,
+    /// Surface normal the decal is projected against, the box's depth axis.
+    pub normal: Vector3<f32>,
+    /// Width, height and projection depth of the box.
+    pub size: Vector3<f32>,
+    /// Spin around the normal.
+    pub rotation: Rad<f32>,
+    pub uv: UvRect,
+    pub color: [f32; 4],
+    /// Time until the decal is removed, None keeps it until the budget pushes it out.
+    pub lifetime: Option<Duration>,
+    /// Fades the alpha to zero over the end of the lifetime.
+    pub fade_out: Duration,
+}
+
+impl Decal {
+    pub fn new(position: Point3<f32>, normal: Vector3<f32>, size: Vector3<f32>) -> Self {
+        Self {
+            position,
+            normal: normal.normalize(),
+            size,
+            rotation: Rad(0.0),
+            uv: UvRect::FULL,
+            color: [1.0; 4],
+            lifetime: None,
+            fade_out: Duration::ZERO,
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: Rad<f32>) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn with_uv(mut self, uv: UvRect) -> Self {
+        self.uv = uv;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_lifetime(mut self, lifetime: Duration) -> Self {
+        self.lifetime = Some(lifetime);
+        self
+    }
+
+    pub fn with_fade_out(mut self, fade_out: Duration) -> Self {
+        self.fade_out = fade_out;
+        self
+    }
+
+    /// Maps the unit cube centered on the origin onto the projector box in world space.
+    pub fn matrix(&self) -> Matrix4<f32> {
+        let z = self.normal;
+        let up = if z.y.abs() < 0.99 {
+            Vector3::unit_y()
+        } else {
+            Vector3::unit_x()
+        };
+        let x = up.cross(z).normalize();
+        let y = z.cross(x);
+        let (sin, cos) = self.rotation.0.sin_cos();
+        let (x, y) = (x * cos + y * sin, y * cos - x * sin);
+        Matrix4::from_cols(
+            (x * self.size.x).extend(0.0),
+            (y * self.size.y).extend(0.0),
+            (z * self.size.z).extend(0.0),
+            Vector4::new(self.position.x, self.position.y, self.position.z, 1.0),
+        )
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct DecalVertex {
+    pub position: [f32; 3],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl DecalVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x4];
+
+    pub fn buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// CPU side geometry a decal can land on, e.g. a BakedMesh or a level's collision mesh.
+#[derive(Debug, Clone, Copy)]
+pub struct DecalTarget<'a> {
+    pub vertices: &'a [Vertex3D],
+    pub indices: &'a [u32],
+    /// Model to world transform of the geometry.
+    pub transform: Matrix4<f32>,
+}
+
+/// Clips the triangles of `target` facing the projector against the decal's box. Returns a
+/// world space triangle list, empty if the decal misses the target.
+pub fn clip_decal(decal: &Decal, target: &DecalTarget) -> Vec<DecalVertex> {
+    let matrix = decal.matrix();
+    let Some(inv) = matrix.invert() else {
+        return Vec::new();
+    };
+    let to_local = inv * target.transform;
+    let uv = |p: Point3<f32>| {
+        let [u, v] = [p.x + 0.5, 0.5 - p.y];
+        [
+            decal.uv.min[0] + u * (decal.uv.max[0] - decal.uv.min[0]),
+            decal.uv.min[1] + v * (decal.uv.max[1] - decal.uv.min[1]),
+        ]
+    };
+
+    let mut out = Vec::new();
+    for triangle in target.indices.chunks_exact(3) {
+        let triangle = [triangle[0], triangle[1], triangle[2]];
+        let world = triangle.map(|i| {
+            target
+                .transform
+                .transform_point(Point3::from(target.vertices[i as usize].position))
+        });
+        if (world[1] - world[0])
+            .cross(world[2] - world[0])
+            .dot(decal.normal)
+            <= 0.0
+        {
+            continue;
+        }
+        let local = triangle
+            .map(|i| to_local.transform_point(Point3::from(target.vertices[i as usize].position)));
+        let polygon = clip_to_unit_box(local.to_vec());
+        for i in 1..polygon.len().saturating_sub(1) {
+            for p in [polygon[0], polygon[i], polygon[i + 1]] {
+                out.push(DecalVertex {
+                    position: matrix.transform_point(p).into(),
+                    uv: uv(p),
+                    color: decal.color,
+                });
+            }
+        }
+    }
+    out
+}
+
+/// Sutherland-Hodgman against the six faces of the cube from -0.5 to 0.5.
+fn clip_to_unit_box(mut polygon: Vec<Point3<f32>>) -> Vec<Point3<f32>> {
+    for axis in 0..3 {
+        for side in [-1.0f32, 1.0] {
+            // Positive inside the plane.
+            let dist = |p: &Point3<f32>| 0.5 - side * p.to_vec()[axis];
+            let mut clipped = Vec::with_capacity(polygon.len() + 1);
+            for (i, a) in polygon.iter().enumerate() {
+                let b = &polygon[(i + 1) % polygon.len()];
+                let (da, db) = (dist(a), dist(b));
+                if da >= 0.0 {
+                    clipped.push(*a);
+                }
+                if (da >= 0.0) != (db >= 0.0) {
+                    clipped.push(a + (b - a) * (da / (da - db)));
+                }
+            }
+            polygon = clipped;
+            if polygon.is_empty() {
+                return polygon;
+            }
+        }
+    }
+    polygon
+}
+
+#[derive(Debug, Clone)]
+struct ActiveDecal {
+    vertices: Vec<DecalVertex>,
+    age: Duration,
+    lifetime: Option<Duration>,
+    fade_out: Duration,
+}
+
+impl ActiveDecal {
+    fn alpha(&self) -> f32 {
+        match self.lifetime {
+            Some(lifetime) if !self.fade_out.is_zero() => {
+                let remaining = lifetime.saturating_sub(self.age);
+                (remaining.as_secs_f32() / self.fade_out.as_secs_f32()).min(1.0)
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+/// Clipped decals in spawn order. Once more than `max_decals` are alive the oldest are
+/// removed, so constant spawning, e.g. from gunfire, never grows the vertex buffer unbounded.
+#[derive(Debug, Clone)]
+pub struct Decals {
+    decals: VecDeque<ActiveDecal>,
+    pub max_decals: usize,
+}
+
+impl Decals {
+    pub fn new(max_decals: usize) -> Self {
+        Self {
+            decals: VecDeque::new(),
+            max_decals,
+        }
+    }
+
+    /// Clips `decal` against every target. Returns false if it hit nothing.
+    pub fn spawn(&mut self, decal: &Decal, targets: &[DecalTarget]) -> bool {
+        let vertices: Vec<DecalVertex> = targets
+            .iter()
+            .flat_map(|target| clip_decal(decal, target))
+            .collect();
+        if vertices.is_empty() {
+            return false;
+        }
+        self.decals.push_back(ActiveDecal {
+            vertices,
+            age: Duration::ZERO,
+            lifetime: decal.lifetime,
+            fade_out: decal.fade_out,
+        });
+        while self.decals.len() > self.max_decals {
+            self.decals.pop_front();
+        }
+        true
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.decals.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.decals.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.decals.clear();
+    }
+
+    /// Ages every decal and removes the expired ones.
+    pub fn frame_update(&mut self, dt: Duration) {
+        for decal in self.decals.iter_mut() {
+            decal.age += dt;
+        }
+        self.decals
+            .retain(|d| d.lifetime.is_none_or(|lifetime| d.age < lifetime));
+    }
+
+    /// Triangle list of every decal with its fade applied.
+    pub fn vertices(&self) -> Vec<DecalVertex> {
+        let mut out = Vec::new();
+        for decal in self.decals.iter() {
+            let alpha = decal.alpha();
+            out.extend(decal.vertices.iter().map(|v| {
+                let mut v = *v;
+                v.color[3] *= alpha;
+                v
+            }));
+        }
+        out
+    }
+}
+
+/// Draws Decals with one atlas texture, pick each decal's image with Decal::uv. Decals are
+/// depth tested against the scene with a bias so they don't z-fight with the surface under them.
+pub struct DecalRenderer {
+    pipeline: Arc<wgpu::RenderPipeline>,
+    bind_group: Arc<wgpu::BindGroup>,
+    vertices: Arc<wgpu::Buffer>,
+    capacity: u64,
+    vertex_count: u32,
+    pub decals: Decals,
+}
+
+impl DecalRenderer {
+    pub const DEFAULT_MAX_DECALS: usize = 256;
+
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        cam_bind_group_layout: &wgpu::BindGroupLayout,
+        atlas: &Texture,
+    ) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("decal_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&atlas.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&atlas.sampler),
+                },
+            ],
+            label: Some("decal_bind_group"),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Decal Pipeline Layout"),
+            bind_group_layouts: &[Some(cam_bind_group_layout), Some(&layout)],
+            immediate_size: 0,
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Decal Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/decal.wgsl").into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Decal Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[Some(DecalVertex::buffer_layout())],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: Some(false),
+                depth_compare: Some(wgpu::CompareFunction::LessEqual),
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState {
+                    constant: -2,
+                    slope_scale: -1.0,
+                    clamp: 0.0,
+                },
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        let capacity = 1024;
+        Self {
+            pipeline: Arc::new(pipeline),
+            bind_group: Arc::new(bind_group),
+            vertices: Arc::new(create_vertex_buffer(device, capacity)),
+            capacity,
+            vertex_count: 0,
+            decals: Decals::new(Self::DEFAULT_MAX_DECALS),
+        }
+    }
+
+    /// Uploads the decals with their current fade, call after Decals::frame_update.
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let vertices = self.decals.vertices();
+        let count = vertices.len() as u64;
+        if count > self.capacity {
+            self.capacity = count.next_power_of_two();
+            self.vertices = Arc::new(create_vertex_buffer(device, self.capacity));
+        }
+        queue.write_buffer(&self.vertices, 0, bytemuck::cast_slice(&vertices));
+        self.vertex_count = count as u32;
+    }
+
+    #[inline]
+    pub(crate) fn pipeline(&self) -> Arc<wgpu::RenderPipeline> {
+        self.pipeline.clone()
+    }
+
+    #[inline]
+    pub(crate) fn bind_group(&self) -> Arc<wgpu::BindGroup> {
+        self.bind_group.clone()
+    }
+
+    #[inline]
+    pub(crate) fn vertex_buffer(&self) -> Arc<wgpu::Buffer> {
+        self.vertices.clone()
+    }
+
+    #[inline]
+    pub fn vertex_count(&self) -> u32 {
+        self.vertex_count
+    }
+}
+
+fn create_vertex_buffer(device: &wgpu::Device, capacity: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Decal Vertex Buffer"),
+        size: capacity * std::mem::size_of::<DecalVertex>() as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
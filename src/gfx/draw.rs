@@ -1,41 +1,279 @@
-use std::{ops::Range, rc::Rc, sync::Arc};
+use std::{cell::RefCell, ops::Range, path::PathBuf, rc::Rc, sync::Arc};
 
+use cgmath::{Matrix4, Point3, SquareMatrix};
 use wgpu::{BufferAddress, DynamicOffset, IndexFormat};
 
 use crate::eng::{
-    command::{RenderCommand, RenderPass, RenderPassOp},
+    command::{
+        ComputePass, PassRecorder, RenderCommand, RenderPass, RenderPassOp, RenderTarget,
+    },
+    command_dump::CommandDump,
+    frame_graph::{FrameGraph, FrameGraphExport},
+    occlusion::{OcclusionCuller, QuerySetHandle},
+    readback::ReadbackHandle,
     render::{
-        light::{draw_light_mesh_instanced, draw_light_model_instanced},
-        mesh::{draw_mesh_instanced, draw_model_instanced},
+        light::draw_light_mesh_instanced,
+        mesh::draw_mesh_instanced,
         DeviceSurface, RenderCamera, RenderWindow,
     },
 };
 
+use crate::sys::geom::bounds::Aabb3;
+
 use super::{
+    bounds::BoundsRenderer,
+    cluster::ClusteredLights,
+    crowd::Crowd,
+    debug_name::DebugName,
+    decal::DecalRenderer,
+    lightmap::LightmapRenderer,
+    material_params::MaterialParamBuffer,
     model::{Material, Mesh, Model},
-    wgpu::texture::Texture,
+    outline::OutlineRenderer,
+    post::PostProcessor,
+    probe::ReflectionProbes,
+    shadow::CascadedShadows,
+    wgpu::{
+        immediate::Immediates,
+        shader::{PipelineOptions, Shader},
+        surface::SurfaceError,
+        texture::Texture,
+    },
 };
 
 pub struct DrawCtx {
     // command_queue: Vec<RenderCommand>,
     camera_bind_group: Arc<wgpu::BindGroup>,
     light_bind_group: Arc<wgpu::BindGroup>,
+    /// Window light bind group without a reflection probe, restored by
+    /// clear_reflection_probe.
+    default_light_bind_group: Arc<wgpu::BindGroup>,
     light_render_pipeline: Arc<wgpu::RenderPipeline>,
-    render_pipeline: Arc<wgpu::RenderPipeline>,
+    shader: Rc<Shader>,
     pub device_surface: Rc<DeviceSurface>,
     pub depth_texture: Rc<Texture>,
+    outline: Rc<OutlineRenderer>,
+    /// Offscreen pass that renders outlined models into the outline mask.
+    outline_pass: Option<RenderPass>,
+    bounds: Rc<BoundsRenderer>,
+    post: Rc<RefCell<PostProcessor>>,
+    virtual_viewport: Option<[f32; 4]>,
+    debug_labels: bool,
 
     passes: Vec<RenderPass>,
+    compute_passes: Vec<ComputePass>,
+    /// Depth only passes encoded before every render pass, see begin_shadow_pass.
+    shadow_passes: Vec<RenderPass>,
+    /// Set by RenderWindow::dump_next_frame, written by submit before encoding.
+    command_dump: Option<PathBuf>,
+    frame_graph: Rc<FrameGraphExport>,
 }
 
 impl DrawCtx {
-    pub fn submit(mut self) -> Result<(), wgpu::SurfaceError> {
-        for pass in self.passes.iter_mut() {
-            pass.render()?
+    /// Encodes every pass of the frame into one command encoder, submits it once and
+    /// presents the surface once. The surface is only acquired if a pass targets it.
+    pub fn submit(mut self) -> Result<(), SurfaceError> {
+        let mut outline_pass = self.outline_pass.take();
+        if outline_pass.is_some() {
+            let cmds = self.outline.composite_commands();
+            if let Some(pass) = self.passes.last_mut() {
+                pass.command_queue.extend(cmds);
+            }
+        }
+        let cmds = self.bounds.commands(
+            &self.device_surface.device,
+            &self.device_surface.queue,
+            self.camera_bind_group.clone(),
+        );
+        if let (false, Some(pass)) = (cmds.is_empty(), self.passes.last_mut()) {
+            match pass.validate_depth(Some(Texture::DEPTH_FORMAT)) {
+                Ok(()) => pass.command_queue.extend(cmds),
+                Err(e) => log::warn!("DrawCtx::submit => {:#}, bounds skipped", e),
+            }
+        }
+
+        // Nothing to post process when every pass renders offscreen, e.g. probe captures.
+        let post = self.post.borrow();
+        let to_surface = self
+            .passes
+            .iter()
+            .any(|pass| matches!(pass.target, RenderTarget::Surface));
+        if post.is_active() && to_surface {
+            let scene = post.scene_target();
+            for pass in self.passes.iter_mut() {
+                if let RenderTarget::Surface = pass.target {
+                    pass.target = RenderTarget::Texture(scene.clone());
+                }
+            }
+            self.passes.extend(post.passes(&self.device_surface));
+        }
+        drop(post);
+
+        let frame = if self
+            .passes
+            .iter()
+            .any(|pass| matches!(pass.target, RenderTarget::Surface))
+        {
+            Some(self.device_surface.get_current_texture()?)
+        } else {
+            None
+        };
+        // Checked up front so a mismatch drops the whole frame instead of half of it.
+        let surface_size = frame.as_ref().map(|f| f.texture.size());
+        let mut passes = self
+            .shadow_passes
+            .iter()
+            .chain(outline_pass.iter())
+            .chain(self.passes.iter());
+        if let Err(e) = passes.try_for_each(|pass| pass.validate_attachments(surface_size)) {
+            log::warn!("DrawCtx::submit => {:#}, frame skipped", e);
+            self.device_surface.encoders.end_frame();
+            return Err(SurfaceError::AttachmentSize);
+        }
+        let surface_view = frame.as_ref().map(|f| {
+            f.texture
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+
+        if let Some(path) = self.command_dump.take() {
+            let dump = CommandDump::record(
+                self.device_surface.frames.frame_index(),
+                &self.device_surface.labels,
+                self.compute_passes.iter(),
+                self.shadow_passes
+                    .iter()
+                    .chain(outline_pass.iter())
+                    .chain(self.passes.iter()),
+            );
+            match dump.save(&path) {
+                Ok(()) => log::info!(
+                    "DrawCtx::submit => dumped {} commands to {}",
+                    dump.command_count(),
+                    path.display()
+                ),
+                Err(e) => log::warn!("DrawCtx::submit => command dump failed: {:#}", e),
+            }
+        }
+        if self.frame_graph.is_enabled() {
+            let config = self.device_surface.config.borrow();
+            let graph = FrameGraph::record(
+                &self.device_surface.labels,
+                ([config.width, config.height], config.format),
+                self.compute_passes.iter(),
+                self.shadow_passes
+                    .iter()
+                    .chain(outline_pass.iter())
+                    .chain(self.passes.iter()),
+            );
+            drop(config);
+            match self.frame_graph.update(graph) {
+                Ok(true) => log::info!(
+                    "DrawCtx::submit => frame graph changed, exported to {}",
+                    self.frame_graph.path().unwrap_or_default().display()
+                ),
+                Ok(false) => {}
+                Err(e) => log::warn!("DrawCtx::submit => frame graph export failed: {:#}", e),
+            }
         }
+
+        let ds = &self.device_surface;
+        let max_passes = ds.encoders.max_passes_per_submission();
+        let mut encoder = None;
+        let mut encoded = 0;
+        if !self.compute_passes.is_empty() {
+            let enc =
+                encoder.get_or_insert_with(|| ds.encoders.create(&ds.device, "Frame Encoder"));
+            for pass in self.compute_passes.iter_mut() {
+                ds.encoders.record_pass(pass.command_queue.len());
+                pass.encode(enc);
+            }
+        }
+        // Shadow maps and the outline mask have to be drawn before the passes reading them.
+        let passes = self
+            .shadow_passes
+            .iter_mut()
+            .chain(outline_pass.iter_mut())
+            .chain(self.passes.iter_mut());
+        for pass in passes {
+            let enc =
+                encoder.get_or_insert_with(|| ds.encoders.create(&ds.device, "Frame Encoder"));
+            pass.encode(enc, surface_view.as_ref());
+            encoded += 1;
+            if max_passes.is_some_and(|max| encoded >= max) {
+                ds.encoders.finish(encoder.take().unwrap());
+                ds.submit_pending();
+                encoded = 0;
+            }
+        }
+        if ds.readbacks.has_requests() {
+            let enc =
+                encoder.get_or_insert_with(|| ds.encoders.create(&ds.device, "Frame Encoder"));
+            ds.readbacks.encode(&ds.device, enc);
+        }
+        if let Some(encoder) = encoder {
+            ds.encoders.finish(encoder);
+        }
+        ds.submit_pending();
+
+        let passes = outline_pass.iter_mut().chain(self.passes.iter_mut());
+        for pass in passes {
+            pass.finish_queries();
+        }
+        if let Some(frame) = frame {
+            ds.queue.present(frame);
+        }
+        self.device_surface.encoders.end_frame();
         Ok(())
     }
 
+    /// One line per pass with its command count and the names of its last `last` commands.
+    pub fn command_summary(&self, last: usize) -> Vec<String> {
+        let compute = self.compute_passes.iter().map(|pass| {
+            format!(
+                "{}: {} compute commands",
+                pass.label.as_deref().unwrap_or("compute pass"),
+                pass.command_queue.len()
+            )
+        });
+        let render = self.passes.iter().enumerate().map(|(i, pass)| {
+            let queue = &pass.command_queue;
+            let tail: Vec<_> = queue[queue.len().saturating_sub(last)..]
+                .iter()
+                .map(|cmd| cmd.name())
+                .collect();
+            match pass.label.as_deref() {
+                Some(label) => format!(
+                    "{}: {} commands, last [{}]",
+                    label,
+                    queue.len(),
+                    tail.join(", ")
+                ),
+                None => format!(
+                    "pass {}: {} commands, last [{}]",
+                    i,
+                    queue.len(),
+                    tail.join(", ")
+                ),
+            }
+        });
+        compute.chain(render).collect()
+    }
+
+    /// Reads `range` of `src` back once this frame is submitted, see Readbacks::read_buffer.
+    pub fn read_buffer(
+        &self,
+        src: Arc<wgpu::Buffer>,
+        range: Range<wgpu::BufferAddress>,
+    ) -> ReadbackHandle {
+        self.device_surface.readbacks.read_buffer(src, range)
+    }
+
+    /// Reads `texture` back once this frame is submitted, e.g. a screenshot of a render
+    /// target drawn this frame, see Readbacks::read_texture.
+    pub fn read_texture(&self, texture: &Texture) -> anyhow::Result<ReadbackHandle> {
+        self.device_surface.readbacks.read_texture(&texture.handle)
+    }
+
     pub fn write_buffer(&self, dst: Arc<wgpu::Buffer>, offset: u64, data: &[u8]) {
         self.device_surface
             .queue
@@ -46,29 +284,180 @@ impl DrawCtx {
     // }
 
     pub fn from_window(window: &RenderWindow) -> Self {
+        let device_surface = window.device_surface();
+        device_surface.frames.begin_frame(&device_surface.device);
+        device_surface.readbacks.poll(&device_surface.device);
+        let outline = window.outline_renderer().clone();
+        outline.begin_frame();
+        let bounds = window.bounds_renderer().clone();
+        bounds.begin_frame();
         Self {
             // command_queue: Vec::new(),
             camera_bind_group: window.camera_bind_group(),
             light_bind_group: window.light_bind_group(),
+            default_light_bind_group: window.light_bind_group(),
             light_render_pipeline: window.light_render_pipeline(),
-            render_pipeline: window.pipeline(),
+            shader: window.shader().clone(),
 
             device_surface: window.device_surface().clone(),
             depth_texture: window.depth_texture().clone(),
+            outline,
+            outline_pass: None,
+            bounds,
+            post: window.post_processor().clone(),
+            virtual_viewport: window.virtual_viewport(),
+            debug_labels: window.debug_labels(),
             passes: Vec::new(),
+            compute_passes: Vec::new(),
+            shadow_passes: Vec::new(),
+            command_dump: window.take_command_dump(),
+            frame_graph: window.frame_graph_export().clone(),
+        }
+    }
+
+    /// Queues a box for the bounds debug draw, ignored unless it is enabled.
+    pub fn debug_bounds(&self, aabb: &Aabb3, transform: &Matrix4<f32>, color: [f32; 4]) {
+        self.bounds.push(aabb, transform, color);
+    }
+
+    /// Bounds of drawn meshes are pushed in model space, instance transforms live in GPU
+    /// buffers and aren't known here. Use debug_bounds to place them in the world instead.
+    fn push_mesh_bounds(&self, mesh: &Mesh) {
+        self.bounds.push(
+            &mesh.bounds,
+            &Matrix4::identity(),
+            BoundsRenderer::DEFAULT_COLOR,
+        );
+    }
+
+    #[inline]
+    pub fn post(&self) -> &Rc<RefCell<PostProcessor>> {
+        &self.post
+    }
+
+    /// Redirects every pass targeting the surface into `target`, swapping their depth
+    /// attachment for `depth` if they have one.
+    pub(crate) fn retarget(&mut self, target: &Rc<Texture>, depth: &Rc<Texture>) {
+        for pass in self.passes.iter_mut() {
+            if let RenderTarget::Surface = pass.target {
+                pass.target = RenderTarget::Texture(target.clone());
+                if pass.depth_texture.is_some() {
+                    pass.depth_texture = Some(depth.clone());
+                }
+            }
         }
     }
 
+    /// Meshes drawn from now on reflect the probe of `probes` closest to `position`, or
+    /// nothing if no probe reaches it.
+    pub fn set_reflection_probe(&mut self, probes: &ReflectionProbes, position: Point3<f32>) {
+        self.light_bind_group = probes
+            .select(position)
+            .unwrap_or_else(|| self.default_light_bind_group.clone());
+    }
+
+    pub fn clear_reflection_probe(&mut self) {
+        self.light_bind_group = self.default_light_bind_group.clone();
+    }
+
     pub fn begin_render_pass(&mut self, op: RenderPassOp) {
         self.passes.push(RenderPass::from_draw_ctx(self, op));
     }
 
+    /// Starts a pass like begin_render_pass and hands `record` a recorder writing into it,
+    /// for imperative recording without going through DrawCtx. Drawing continues in the
+    /// same pass afterwards.
+    pub fn with_pass<R>(
+        &mut self,
+        op: RenderPassOp,
+        record: impl FnOnce(&mut PassRecorder) -> R,
+    ) -> R {
+        self.begin_render_pass(op);
+        let mut recorder = PassRecorder::new(&mut self.current_pass_mut().command_queue);
+        record(&mut recorder)
+    }
+
+    /// Starts a pass without a depth attachment, only pipelines built with
+    /// DepthMode::Disabled can be drawn in it.
+    pub fn begin_render_pass_without_depth(&mut self, op: RenderPassOp) {
+        self.passes
+            .push(RenderPass::from_draw_ctx(self, op).without_depth());
+    }
+
+    /// Starts a compute pass, it runs before every render pass of this frame.
+    pub fn begin_compute_pass(&mut self, label: &str) -> &mut ComputePass {
+        self.compute_passes
+            .push(ComputePass::new().with_label(label));
+        self.compute_passes.last_mut().unwrap()
+    }
+
+    /// Starts a pass that only writes `depth`, cleared to the far plane. It runs after the
+    /// compute passes and before every other render pass of this frame, so the scene can
+    /// sample what it draws.
+    pub fn begin_shadow_pass(&mut self, depth: &Rc<Texture>, label: &str) -> &mut RenderPass {
+        let pass = RenderPass::depth_only(&self.device_surface, depth, RenderPassOp::CLEAR_WHITE)
+            .with_label(label);
+        self.shadow_passes.push(pass);
+        self.shadow_passes.last_mut().unwrap()
+    }
+
+    /// Records `draw` into a pass rendering into `target`, with `camera` bound in place of
+    /// the window's camera for its 3D draws. The pass runs before every pass of this frame
+    /// targeting the surface, so they can sample `target`. Drawing continues in the
+    /// current pass afterwards.
+    pub fn render_to_texture(
+        &mut self,
+        target: &Rc<Texture>,
+        depth: Option<&Rc<Texture>>,
+        op: RenderPassOp,
+        camera: Arc<wgpu::BindGroup>,
+        label: &str,
+        draw: impl FnOnce(&mut DrawCtx),
+    ) {
+        let start = self.passes.len();
+        self.passes.push(
+            RenderPass::to_texture(&self.device_surface, target, depth, op).with_label(label),
+        );
+        let window_camera = std::mem::replace(&mut self.camera_bind_group, camera);
+        draw(self);
+        self.camera_bind_group = window_camera;
+
+        let recorded = self.passes.split_off(start);
+        let at = self
+            .passes
+            .iter()
+            .position(|pass| matches!(pass.target, RenderTarget::Surface))
+            .unwrap_or(start);
+        self.passes.splice(at..at, recorded);
+    }
+
     pub fn current_pass_mut(&mut self) -> &mut RenderPass {
         self.passes
             .last_mut()
             .expect("DrawCtx::current_pass => RenderPass queue on DrawCtx empty")
     }
 
+    /// Binds the pipeline `shader` builds for `options`, failing if its depth state does
+    /// not match the current pass.
+    pub fn set_shader(&mut self, shader: &Shader, options: PipelineOptions) -> anyhow::Result<()> {
+        self.current_pass_mut()
+            .validate_depth(shader.depth_format(options))?;
+        let pipeline = shader.variant(&self.device_surface.device, options);
+        self.set_pipeline(pipeline);
+        Ok(())
+    }
+
+    /// Logs and returns false if pipelines built for `depth_format` can't be used in the current pass.
+    fn depth_matches(&mut self, depth_format: Option<wgpu::TextureFormat>) -> bool {
+        match self.current_pass_mut().validate_depth(depth_format) {
+            Ok(()) => true,
+            Err(e) => {
+                log::warn!("DrawCtx::depth_matches => {:#}, draw skipped", e);
+                false
+            }
+        }
+    }
+
     pub fn set_pipeline(&mut self, pipeline: Arc<wgpu::RenderPipeline>) {
         self.current_pass_mut()
             .command_queue
@@ -90,6 +479,18 @@ impl DrawCtx {
             ));
     }
 
+    /// Binds `mat`'s animatable parameters at `index`, does nothing if it has none.
+    pub fn set_material_params(
+        &mut self,
+        index: u32,
+        params: &MaterialParamBuffer,
+        mat: &Material,
+    ) {
+        if let Some(slot) = mat.params {
+            self.set_bind_group(index, params.bind_group(), Some(vec![params.offset(slot)]));
+        }
+    }
+
     pub fn set_blend_constant(&mut self, color: wgpu::Color) {
         self.current_pass_mut()
             .command_queue
@@ -130,12 +531,34 @@ impl DrawCtx {
             ));
     }
 
+    /// Restricts drawing to the window's virtual resolution, does nothing without one.
+    pub fn set_virtual_viewport(&mut self) {
+        if let Some([x, y, width, height]) = self.virtual_viewport {
+            self.set_viewport(x, y, width, height, 0.0, 1.0);
+        }
+    }
+
     pub fn set_stencil_reference(&mut self, reference: u32) {
         self.current_pass_mut()
             .command_queue
             .push(RenderCommand::SetStencilReference(reference));
     }
 
+    /// Sets the per draw data of pipelines built with `immediates`, as wgpu immediates or
+    /// through its uniform fallback. `data` is padded to a multiple of 4 bytes.
+    pub fn set_immediates(&mut self, immediates: &Immediates, data: &[u8]) {
+        let surface = &self.device_surface;
+        let Some(cmd) = immediates.command(
+            &surface.device,
+            &surface.queue,
+            surface.frames.frame_index(),
+            data,
+        ) else {
+            return;
+        };
+        self.current_pass_mut().command_queue.push(cmd);
+    }
+
     pub fn draw(&mut self, verticies: Range<u32>, instances: Range<u32>) {
         self.current_pass_mut()
             .command_queue
@@ -160,6 +583,52 @@ impl DrawCtx {
             .push(RenderCommand::PopDebugGroup);
     }
 
+    /// Whether draws are wrapped in debug groups, see RenderWindow::set_debug_labels.
+    #[inline]
+    pub fn debug_labels(&self) -> bool {
+        self.debug_labels
+    }
+
+    pub fn set_debug_labels(&mut self, enabled: bool) {
+        self.debug_labels = enabled;
+    }
+
+    /// Opens a debug group labelled after `named`, e.g. an entity, when debug labels are on.
+    /// Close it with pop_debug_scope.
+    pub fn push_debug_scope(&mut self, named: &impl DebugName) {
+        if self.debug_labels {
+            self.push_debug_group(&named.debug_label());
+        }
+    }
+
+    pub fn pop_debug_scope(&mut self) {
+        if self.debug_labels {
+            self.pop_debug_group();
+        }
+    }
+
+    /// Marks where `named` is used, e.g. a material being bound, when debug labels are on.
+    pub fn insert_debug_label(&mut self, named: &impl DebugName) {
+        if self.debug_labels {
+            self.insert_debug_marker(&named.debug_label());
+        }
+    }
+
+    /// Queues `cmds` for a mesh draw inside a debug group named after the mesh.
+    fn push_mesh_commands(
+        &mut self,
+        mesh: &Mesh,
+        mat: Option<&Material>,
+        cmds: Vec<RenderCommand>,
+    ) {
+        self.push_debug_scope(mesh);
+        if let Some(mat) = mat {
+            self.insert_debug_label(mat);
+        }
+        self.current_pass_mut().command_queue.extend(cmds);
+        self.pop_debug_scope();
+    }
+
     pub fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
         self.current_pass_mut()
             .command_queue
@@ -192,27 +661,147 @@ impl DrawCtx {
             ));
     }
 
+    /// Issues `count` indirect draws packed back to back from `indirect_offset`.
+    pub fn multi_draw_indirect(
+        &mut self,
+        indirect_buffer: Arc<wgpu::Buffer>,
+        indirect_offset: BufferAddress,
+        count: u32,
+    ) {
+        self.current_pass_mut()
+            .command_queue
+            .push(RenderCommand::MultiDrawIndirect(
+                indirect_buffer,
+                indirect_offset,
+                count,
+            ));
+    }
+
+    /// Issues `count` indexed indirect draws packed back to back from `indirect_offset`.
+    pub fn multi_draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: Arc<wgpu::Buffer>,
+        indirect_offset: BufferAddress,
+        count: u32,
+    ) {
+        self.current_pass_mut()
+            .command_queue
+            .push(RenderCommand::MultiDrawIndexedIndirect(
+                indirect_buffer,
+                indirect_offset,
+                count,
+            ));
+    }
+
+    pub fn begin_pipeline_statistics_query(&mut self, query_set: QuerySetHandle, query_index: u32) {
+        self.current_pass_mut()
+            .command_queue
+            .push(RenderCommand::BeginPipelineStatisticsQuery(
+                query_set,
+                query_index,
+            ));
+    }
+
+    pub fn end_pipeline_statistics_query(&mut self) {
+        self.current_pass_mut()
+            .command_queue
+            .push(RenderCommand::EndPipelineStatisticsQuery);
+    }
+
+    /// Draws the bounding proxy of object `id` inside an occlusion query. Expects the
+    /// instance buffer for `instances` to already be bound to slot 1.
+    /// Check OcclusionCuller::is_visible before drawing the object itself.
+    pub fn draw_occlusion_proxy(
+        &mut self,
+        culler: &mut OcclusionCuller,
+        id: u64,
+        proxy: &Mesh,
+        instances: Range<u32>,
+    ) {
+        let Some((_, query_index)) = culler.begin_query(id) else {
+            return;
+        };
+        let pipeline = culler.proxy_pipeline();
+        let camera_bind_group = self.camera_bind_group.clone();
+
+        let pass = self.current_pass_mut();
+        pass.resolve_query_set(culler.query_set());
+        pass.command_queue.extend([
+            RenderCommand::SetPipeline(pipeline),
+            RenderCommand::SetVertexBuffer(0, proxy.vert_buff.clone()),
+            RenderCommand::SetIndexBuffer(proxy.index_buff.clone(), IndexFormat::Uint32),
+            RenderCommand::SetBindGroup(0, camera_bind_group, None),
+            RenderCommand::BeginOcclusionQuery(query_index),
+            RenderCommand::DrawIndexed(0..proxy.num_elements, 0, instances),
+            RenderCommand::EndOcclusionQuery,
+        ]);
+    }
+
+    /// Draws the decals last uploaded to `decals` over the scene.
+    pub fn draw_decals(&mut self, decals: &DecalRenderer) {
+        if decals.vertex_count() == 0 || !self.depth_matches(Some(Texture::DEPTH_FORMAT)) {
+            return;
+        }
+        let camera_bind_group = self.camera_bind_group.clone();
+        self.current_pass_mut().command_queue.extend([
+            RenderCommand::SetPipeline(decals.pipeline()),
+            RenderCommand::SetBindGroup(0, camera_bind_group, None),
+            RenderCommand::SetBindGroup(1, decals.bind_group(), None),
+            RenderCommand::SetVertexBuffer(0, decals.vertex_buffer()),
+            RenderCommand::Draw(0..decals.vertex_count(), 0..1),
+        ]);
+    }
+
+    /// Draws every surface added to `lightmaps`, lit only by the baked lightmap.
+    pub fn draw_lightmapped(&mut self, lightmaps: &LightmapRenderer) {
+        if lightmaps.surface_count() == 0 || !self.depth_matches(Some(Texture::DEPTH_FORMAT)) {
+            return;
+        }
+        let camera_bind_group = self.camera_bind_group.clone();
+        let pass = self.current_pass_mut();
+        pass.command_queue.extend([
+            RenderCommand::SetPipeline(lightmaps.pipeline()),
+            RenderCommand::SetBindGroup(1, camera_bind_group, None),
+            RenderCommand::SetBindGroup(2, lightmaps.bind_group(), None),
+        ]);
+        for (vertices, count, material) in lightmaps.surfaces() {
+            pass.command_queue.extend([
+                RenderCommand::SetBindGroup(0, material, None),
+                RenderCommand::SetVertexBuffer(0, vertices),
+                RenderCommand::Draw(0..count, 0..1),
+            ]);
+        }
+    }
+
     pub fn draw_light_model(&mut self, model: &Model) {
         self.draw_light_model_instanced(model, 0..1);
     }
     pub fn draw_light_model_instanced(&mut self, model: &Model, instances: Range<u32>) {
+        if !self.depth_matches(Some(Texture::DEPTH_FORMAT)) {
+            return;
+        }
         let lrp = self.light_render_pipeline.clone();
         self.current_pass_mut()
             .command_queue
             .push(RenderCommand::SetPipeline(lrp));
 
-        let cmds = draw_light_model_instanced(
-            model,
-            instances,
-            self.camera_bind_group.clone(),
-            self.light_bind_group.clone(),
-        );
-        self.current_pass_mut().command_queue.extend(cmds);
+        for mesh in &model.meshes {
+            let cmds = draw_light_mesh_instanced(
+                mesh,
+                instances.clone(),
+                self.camera_bind_group.clone(),
+                self.light_bind_group.clone(),
+            );
+            self.push_mesh_commands(mesh, None, cmds);
+        }
     }
     pub fn draw_light_mesh(&mut self, mesh: &Mesh) {
         self.draw_light_mesh_instanced(mesh, 0..1);
     }
     pub fn draw_light_mesh_instanced(&mut self, mesh: &Mesh, instances: Range<u32>) {
+        if !self.depth_matches(Some(Texture::DEPTH_FORMAT)) {
+            return;
+        }
         let lrp = self.light_render_pipeline.clone();
         self.current_pass_mut()
             .command_queue
@@ -223,13 +812,32 @@ impl DrawCtx {
             self.camera_bind_group.clone(),
             self.light_bind_group.clone(),
         );
-        self.current_pass_mut().command_queue.extend(cmds);
+        self.push_mesh_commands(mesh, None, cmds);
     }
+    /// Pipeline used to draw meshes with the given material, honoring its depth bias.
+    fn material_pipeline(&self, mat: &Material) -> Arc<wgpu::RenderPipeline> {
+        match mat.depth_bias {
+            Some(bias) => self
+                .shader
+                .with_depth_bias(&self.device_surface.device, bias),
+            None => self.shader.pipeline(),
+        }
+    }
+
+    /// Draws the text of the last TextBuffer::upload.
+    #[cfg(feature = "ttf")]
+    pub fn draw_text(&mut self, text: &super::text::TextBuffer) {
+        text.draw(self);
+    }
+
     pub fn draw_mesh(&mut self, mesh: &Mesh, mat: &Material) {
         self.draw_mesh_instanced(mesh, mat, 0..1);
     }
     pub fn draw_mesh_instanced(&mut self, mesh: &Mesh, mat: &Material, instances: Range<u32>) {
-        let rp = self.render_pipeline.clone();
+        if !self.depth_matches(self.shader.depth_format(self.shader.options())) {
+            return;
+        }
+        let rp = self.material_pipeline(mat);
         self.current_pass_mut()
             .command_queue
             .push(RenderCommand::SetPipeline(rp));
@@ -241,24 +849,241 @@ impl DrawCtx {
             self.camera_bind_group.clone(),
             self.light_bind_group.clone(),
         );
-        self.current_pass_mut().command_queue.extend(cmds);
+        self.push_mesh_commands(mesh, Some(mat), cmds);
+        self.push_mesh_bounds(mesh);
     }
 
-    pub fn draw_model(&mut self, model: &Model) {
-        self.draw_model_instanced(model, 0..1);
+    /// Draws a mesh with the instance count and range read from `indirect_buffer`, e.g.
+    /// written by GpuCuller. The instance buffer has to be bound at slot 1 already.
+    pub fn draw_mesh_indirect(
+        &mut self,
+        mesh: &Mesh,
+        mat: &Material,
+        indirect_buffer: Arc<wgpu::Buffer>,
+        indirect_offset: BufferAddress,
+    ) {
+        if !self.depth_matches(self.shader.depth_format(self.shader.options())) {
+            return;
+        }
+        let rp = self.material_pipeline(mat);
+        let mut cmds = draw_mesh_instanced(
+            mesh,
+            mat,
+            0..0,
+            self.camera_bind_group.clone(),
+            self.light_bind_group.clone(),
+        );
+        cmds.pop();
+        cmds.insert(0, RenderCommand::SetPipeline(rp));
+        cmds.push(RenderCommand::DrawIndexedIndirect(
+            indirect_buffer,
+            indirect_offset,
+        ));
+        self.push_mesh_commands(mesh, Some(mat), cmds);
+        self.push_mesh_bounds(mesh);
     }
-    pub fn draw_model_instanced(&mut self, model: &Model, instances: Range<u32>) {
-        let rp = self.render_pipeline.clone();
+
+    /// Draws a mesh with a temporary depth bias, overriding any bias set on its material.
+    /// The biased pipeline only applies to this draw.
+    pub fn draw_mesh_with_depth_bias(
+        &mut self,
+        mesh: &Mesh,
+        mat: &Material,
+        instances: Range<u32>,
+        depth_bias: wgpu::DepthBiasState,
+    ) {
+        if !self.depth_matches(self.shader.depth_format(self.shader.options())) {
+            return;
+        }
+        let rp = self
+            .shader
+            .with_depth_bias(&self.device_surface.device, depth_bias);
         self.current_pass_mut()
             .command_queue
             .push(RenderCommand::SetPipeline(rp));
 
-        let cmds = draw_model_instanced(
-            model,
+        let cmds = draw_mesh_instanced(
+            mesh,
+            mat,
             instances,
             self.camera_bind_group.clone(),
             self.light_bind_group.clone(),
         );
-        self.current_pass_mut().command_queue.extend(cmds);
+        self.push_mesh_commands(mesh, Some(mat), cmds);
+        self.push_mesh_bounds(mesh);
+    }
+
+    pub fn draw_model(&mut self, model: &Model) {
+        self.draw_model_instanced(model, 0..1);
+    }
+    pub fn draw_model_instanced(&mut self, model: &Model, instances: Range<u32>) {
+        if !self.depth_matches(self.shader.depth_format(self.shader.options())) {
+            return;
+        }
+        let mut bound: Option<Arc<wgpu::RenderPipeline>> = None;
+        for mesh in &model.meshes {
+            let mat = &model.materials[mesh.material];
+            let rp = self.material_pipeline(mat);
+            if !bound.as_ref().is_some_and(|b| Arc::ptr_eq(b, &rp)) {
+                self.current_pass_mut()
+                    .command_queue
+                    .push(RenderCommand::SetPipeline(rp.clone()));
+                bound = Some(rp);
+            }
+
+            let cmds = draw_mesh_instanced(
+                mesh,
+                mat,
+                instances.clone(),
+                self.camera_bind_group.clone(),
+                self.light_bind_group.clone(),
+            );
+            self.push_mesh_commands(mesh, Some(mat), cmds);
+            self.push_mesh_bounds(mesh);
+        }
+    }
+
+    /// Draws a model lit by the window light plus the point lights last sorted into
+    /// `lights` by ClusteredLights::update. Material depth biases are ignored.
+    pub fn draw_model_clustered(
+        &mut self,
+        model: &Model,
+        instances: Range<u32>,
+        lights: &ClusteredLights,
+    ) {
+        if !self.depth_matches(Some(Texture::DEPTH_FORMAT)) {
+            return;
+        }
+        self.current_pass_mut().command_queue.extend([
+            RenderCommand::SetPipeline(lights.pipeline()),
+            RenderCommand::SetBindGroup(3, lights.bind_group(), None),
+        ]);
+        for mesh in &model.meshes {
+            let mat = &model.materials[mesh.material];
+            let cmds = draw_mesh_instanced(
+                mesh,
+                mat,
+                instances.clone(),
+                self.camera_bind_group.clone(),
+                self.light_bind_group.clone(),
+            );
+            self.push_mesh_commands(mesh, Some(mat), cmds);
+            self.push_mesh_bounds(mesh);
+        }
+    }
+
+    /// Draws every instance of `crowd` as uploaded by Crowd::draw, vertex animated and lit
+    /// like draw_mesh_instanced. Material depth biases are ignored.
+    pub fn draw_crowd(&mut self, mesh: &Mesh, mat: &Material, crowd: &Crowd) {
+        if !self.depth_matches(Some(Texture::DEPTH_FORMAT)) {
+            return;
+        }
+        self.current_pass_mut().command_queue.extend([
+            RenderCommand::SetPipeline(crowd.pipeline()),
+            RenderCommand::SetBindGroup(3, crowd.bind_group(), None),
+            RenderCommand::SetVertexBuffer(1, crowd.instance_buffer()),
+        ]);
+        let cmds = draw_mesh_instanced(
+            mesh,
+            mat,
+            0..crowd.len() as u32,
+            self.camera_bind_group.clone(),
+            self.light_bind_group.clone(),
+        );
+        self.push_mesh_commands(mesh, Some(mat), cmds);
+        self.push_mesh_bounds(mesh);
+    }
+
+    /// Draws a model lit by the window light plus the directional light of `shadows`,
+    /// shadowed by its cascades. Material depth biases are ignored.
+    pub fn draw_model_shadowed(
+        &mut self,
+        model: &Model,
+        instances: Range<u32>,
+        shadows: &CascadedShadows,
+    ) {
+        if !self.depth_matches(Some(Texture::DEPTH_FORMAT)) {
+            return;
+        }
+        self.current_pass_mut().command_queue.extend([
+            RenderCommand::SetPipeline(shadows.pipeline()),
+            RenderCommand::SetBindGroup(3, shadows.bind_group(), None),
+        ]);
+        for mesh in &model.meshes {
+            let mat = &model.materials[mesh.material];
+            let cmds = draw_mesh_instanced(
+                mesh,
+                mat,
+                instances.clone(),
+                self.camera_bind_group.clone(),
+                self.light_bind_group.clone(),
+            );
+            self.push_mesh_commands(mesh, Some(mat), cmds);
+            self.push_mesh_bounds(mesh);
+        }
+    }
+
+    /// Draws a model and outlines its silhouette in the given color. The outline is drawn over
+    /// the rest of the scene once the frame is submitted.
+    pub fn draw_outlined(&mut self, model: &Model, color: wgpu::Color) {
+        let instances = self.outline.identity_instance();
+        self.draw_outlined_instanced(model, instances, 0..1, color);
+    }
+
+    /// Same as draw_outlined but for instanced models. Binds `instance_buffer` to slot 1 for
+    /// the draw and restores whatever was bound there before.
+    pub fn draw_outlined_instanced(
+        &mut self,
+        model: &Model,
+        instance_buffer: Arc<wgpu::Buffer>,
+        instances: Range<u32>,
+        color: wgpu::Color,
+    ) {
+        let prev_instances = self
+            .current_pass_mut()
+            .command_queue
+            .iter()
+            .rev()
+            .find_map(|cmd| match cmd {
+                RenderCommand::SetVertexBuffer(1, buffer) => Some(buffer.clone()),
+                _ => None,
+            });
+        self.set_vertex_buffer(1, instance_buffer.clone());
+        self.draw_model_instanced(model, instances.clone());
+        if let Some(prev) = prev_instances {
+            self.set_vertex_buffer(1, prev);
+        }
+
+        let Some(color_offset) = self.outline.push_color(&self.device_surface.queue, color) else {
+            log::warn!(
+                "DrawCtx::draw_outlined_instanced => Too many outlines this frame, outline skipped"
+            );
+            return;
+        };
+
+        let outline = &self.outline;
+        let device_surface = &self.device_surface;
+        let pass = self.outline_pass.get_or_insert_with(|| {
+            RenderPass::to_texture(
+                device_surface,
+                outline.mask(),
+                None,
+                RenderPassOp::Clear(wgpu::Color::TRANSPARENT),
+            )
+            .with_label("Outline Mask Pass")
+        });
+        pass.command_queue.extend([
+            RenderCommand::SetPipeline(outline.mask_pipeline()),
+            RenderCommand::SetBindGroup(0, self.camera_bind_group.clone(), None),
+            RenderCommand::SetBindGroup(1, outline.color_bind_group(), Some(vec![color_offset])),
+            RenderCommand::SetVertexBuffer(1, instance_buffer),
+        ]);
+        for mesh in &model.meshes {
+            pass.command_queue.extend([
+                RenderCommand::SetVertexBuffer(0, mesh.vert_buff.clone()),
+                RenderCommand::SetIndexBuffer(mesh.index_buff.clone(), IndexFormat::Uint32),
+                RenderCommand::DrawIndexed(0..mesh.num_elements, 0, instances.clone()),
+            ]);
+        }
     }
 }
@@ -0,0 +1,260 @@
+use cgmath::{InnerSpace, Vector3};
+
+/// How fog thickens with the distance from the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "scene", derive(serde::Serialize, serde::Deserialize))]
+pub enum Fog {
+    #[default]
+    None,
+    /// No fog before `start`, fully fogged after `end`.
+    Linear {
+        start: f32,
+        end: f32,
+    },
+    Exponential {
+        density: f32,
+    },
+    ExponentialSquared {
+        density: f32,
+    },
+}
+
+impl Fog {
+    /// Fog amount at `distance`, 0 is clear and 1 is fully fogged.
+    pub fn factor(&self, distance: f32) -> f32 {
+        let f = match *self {
+            Fog::None => 0.0,
+            Fog::Linear { start, end } => (distance - start) / (end - start).max(1e-4),
+            Fog::Exponential { density } => 1.0 - (-density * distance).exp(),
+            Fog::ExponentialSquared { density } => 1.0 - (-(density * distance).powi(2)).exp(),
+        };
+        f.clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "scene", derive(serde::Serialize, serde::Deserialize))]
+pub enum Sky {
+    /// Blends from `horizon` to `zenith` above the horizon and to `ground` below it.
+    Gradient {
+        zenith: [f32; 3],
+        horizon: [f32; 3],
+        ground: [f32; 3],
+    },
+    /// Preetham's analytic daylight model. Turbidity runs from about 2 for a clear sky to
+    /// 10 for haze, `sun_direction` points towards the sun.
+    Preetham {
+        sun_direction: [f32; 3],
+        turbidity: f32,
+        exposure: f32,
+    },
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Sky::Gradient {
+            zenith: [0.18, 0.36, 0.7],
+            horizon: [0.7, 0.8, 0.9],
+            ground: [0.25, 0.23, 0.2],
+        }
+    }
+}
+
+impl Sky {
+    /// Linear color of the sky looking along `dir`.
+    pub fn color(&self, dir: Vector3<f32>) -> [f32; 3] {
+        let dir = dir.normalize();
+        match *self {
+            Sky::Gradient {
+                zenith,
+                horizon,
+                ground,
+            } => {
+                let (to, t) = if dir.y >= 0.0 {
+                    (zenith, dir.y)
+                } else {
+                    (ground, -dir.y)
+                };
+                std::array::from_fn(|i| horizon[i] + (to[i] - horizon[i]) * t.sqrt())
+            }
+            Sky::Preetham {
+                sun_direction,
+                turbidity,
+                exposure,
+            } => preetham(
+                dir,
+                Vector3::from(sun_direction).normalize(),
+                turbidity,
+                exposure,
+            ),
+        }
+    }
+}
+
+/// Perez distribution coefficients A to E for luminance Y and chromaticity x and y.
+fn perez_coefficients(t: f32) -> [[f32; 5]; 3] {
+    [
+        [
+            0.1787 * t - 1.4630,
+            -0.3554 * t + 0.4275,
+            -0.0227 * t + 5.3251,
+            0.1206 * t - 2.5771,
+            -0.0670 * t + 0.3703,
+        ],
+        [
+            -0.0193 * t - 0.2592,
+            -0.0665 * t + 0.0008,
+            -0.0004 * t + 0.2125,
+            -0.0641 * t - 0.8989,
+            -0.0033 * t + 0.0452,
+        ],
+        [
+            -0.0167 * t - 0.2608,
+            -0.0950 * t + 0.0092,
+            -0.0079 * t + 0.2102,
+            -0.0441 * t - 1.6537,
+            -0.0109 * t + 0.0529,
+        ],
+    ]
+}
+
+fn perez(c: [f32; 5], cos_theta: f32, gamma: f32) -> f32 {
+    (1.0 + c[0] * (c[1] / cos_theta).exp())
+        * (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * gamma.cos().powi(2))
+}
+
+/// Mirrors sky_color in basic.wgsl, keep the two in sync.
+fn preetham(dir: Vector3<f32>, sun: Vector3<f32>, turbidity: f32, exposure: f32) -> [f32; 3] {
+    let t = turbidity;
+    // The model is only defined above the horizon.
+    let cos_theta = dir.y.max(0.001);
+    let theta_s = sun.y.clamp(0.0, 1.0).acos();
+    let gamma = dir.dot(sun).clamp(-1.0, 1.0).acos();
+
+    let chi = (4.0 / 9.0 - t / 120.0) * (std::f32::consts::PI - 2.0 * theta_s);
+    let zenith_y = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+    let (s, s2, s3) = (theta_s, theta_s * theta_s, theta_s.powi(3));
+    let zenith_x = t * t * (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s)
+        + t * (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s + 0.00394)
+        + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s + 0.25886);
+    let zenith_yc = t * t * (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s)
+        + t * (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s + 0.00516)
+        + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s + 0.26688);
+
+    let c = perez_coefficients(t);
+    let sky =
+        |c: [f32; 5], zenith: f32| zenith * perez(c, cos_theta, gamma) / perez(c, 1.0, theta_s);
+    let luminance = sky(c[0], zenith_y.max(0.0));
+    let x = sky(c[1], zenith_x);
+    let y = sky(c[2], zenith_yc).max(1e-4);
+
+    // Yxy to XYZ to linear sRGB, with an exponential tone curve on the luminance.
+    let big_y = 1.0 - (-exposure * luminance).exp();
+    let big_x = x / y * big_y;
+    let big_z = (1.0 - x - y) / y * big_y;
+    [
+        3.2406 * big_x - 1.5372 * big_y - 0.4986 * big_z,
+        -0.9689 * big_x + 1.8758 * big_y + 0.0415 * big_z,
+        0.0557 * big_x - 0.2040 * big_y + 1.0570 * big_z,
+    ]
+    .map(|c| c.max(0.0))
+}
+
+/// Per scene fog and sky, bound next to the light for the 3D shaders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "scene", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "scene", serde(default))]
+pub struct Environment {
+    pub fog: Fog,
+    pub fog_color: [f32; 3],
+    /// How much fog takes on the sky color along the view ray, 1 fades distant geometry
+    /// into the sky for a cheap aerial perspective.
+    pub fog_sky_blend: f32,
+    pub sky: Sky,
+    /// Light added to every lit surface, e.g. skylight or moonlight.
+    pub ambient: [f32; 3],
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self {
+            fog: Fog::None,
+            fog_color: [0.7, 0.75, 0.8],
+            fog_sky_blend: 0.0,
+            sky: Sky::default(),
+            ambient: [0.0; 3],
+        }
+    }
+}
+
+impl Environment {
+    pub fn with_fog(mut self, fog: Fog, color: [f32; 3]) -> Self {
+        self.fog = fog;
+        self.fog_color = color;
+        self
+    }
+
+    pub fn with_fog_sky_blend(mut self, blend: f32) -> Self {
+        self.fog_sky_blend = blend;
+        self
+    }
+
+    pub fn with_sky(mut self, sky: Sky) -> Self {
+        self.sky = sky;
+        self
+    }
+
+    pub fn with_ambient(mut self, ambient: [f32; 3]) -> Self {
+        self.ambient = ambient;
+        self
+    }
+
+    pub fn uniform(&self) -> EnvironmentUniform {
+        let (fog_mode, fog_params) = match self.fog {
+            Fog::None => (0.0, [0.0; 2]),
+            Fog::Linear { start, end } => (1.0, [start, end]),
+            Fog::Exponential { density } => (2.0, [density, 0.0]),
+            Fog::ExponentialSquared { density } => (3.0, [density, 0.0]),
+        };
+        let (sky_mode, sky) = match self.sky {
+            Sky::Gradient {
+                zenith,
+                horizon,
+                ground,
+            } => (0.0, [zenith, horizon, ground]),
+            Sky::Preetham {
+                sun_direction,
+                turbidity,
+                exposure,
+            } => {
+                let sun = Vector3::from(sun_direction).normalize();
+                (1.0, [sun.into(), [turbidity, exposure, 0.0], [0.0; 3]])
+            }
+        };
+        EnvironmentUniform {
+            fog_color: [
+                self.fog_color[0],
+                self.fog_color[1],
+                self.fog_color[2],
+                fog_mode,
+            ],
+            fog_params: [fog_params[0], fog_params[1], self.fog_sky_blend, sky_mode],
+            sky: sky.map(|v| [v[0], v[1], v[2], 0.0]),
+            ambient: [self.ambient[0], self.ambient[1], self.ambient[2], 0.0],
+        }
+    }
+}
+
+/// GPU layout of an Environment, see basic.wgsl.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct EnvironmentUniform {
+    /// rgb: fog color, w: fog mode.
+    pub fog_color: [f32; 4],
+    /// x, y: linear start and end or density, z: sky blend, w: sky mode.
+    pub fog_params: [f32; 4],
+    /// Gradient: zenith, horizon, ground. Preetham: sun direction, (turbidity, exposure).
+    pub sky: [[f32; 4]; 3],
+    /// rgb: ambient light.
+    pub ambient: [f32; 4],
+}
@@ -0,0 +1,475 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, ensure};
+
+use super::quad::{MaterialSlot, QuadBuffer, Sprite, UvRect};
+
+/// A glyph in a BMFont page, in page pixels with y growing downwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BmChar {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Offset from the pen position to the glyph's top left corner.
+    pub offset: [i32; 2],
+    pub advance: i32,
+    pub page: u32,
+}
+
+/// AngelCode BMFont description, parsed from the text or binary (version 3) .fnt format.
+/// Page textures are loaded separately, see sys::fs::load_bmfont.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BmFont {
+    pub face: String,
+    /// Size the font was rasterized at, in pixels.
+    pub size: i32,
+    /// Distance between baselines.
+    pub line_height: u32,
+    /// Distance from the top of a line to its baseline.
+    pub base: u32,
+    /// Size of each page texture.
+    pub page_size: [u32; 2],
+    /// Page texture file names relative to the .fnt file.
+    pub pages: Vec<String>,
+    pub chars: HashMap<char, BmChar>,
+    pub kerning: HashMap<(char, char), i32>,
+}
+
+/// One glyph quad from BmFont::layout, world units with y growing upwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphQuad {
+    pub page: u32,
+    /// Bottom left corner.
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub uv: UvRect,
+}
+
+impl BmFont {
+    const BINARY_MAGIC: &'static [u8] = b"BMF";
+
+    /// Parses either .fnt format, telling them apart by the binary magic.
+    pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        if bytes.starts_with(Self::BINARY_MAGIC) {
+            Self::parse_binary(bytes)
+        } else {
+            Self::parse(std::str::from_utf8(bytes)?)
+        }
+    }
+
+    /// Parses the text .fnt format, lines like `char id=65 x=0 y=0 width=8 ...`.
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut font = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let mut tokens = split_tokens(line).into_iter();
+            let Some(tag) = tokens.next() else {
+                continue;
+            };
+            let mut fields = HashMap::new();
+            for token in tokens {
+                if let Some((key, value)) = token.split_once('=') {
+                    fields.insert(key.to_string(), value.trim_matches('"').to_string());
+                }
+            }
+            let int = |key: &str| -> anyhow::Result<i32> {
+                match fields.get(key) {
+                    Some(value) => Ok(value.parse()?),
+                    None => bail!("BmFont::parse => line {} missing {}", number + 1, key),
+                }
+            };
+            match tag.as_str() {
+                "info" => {
+                    font.face = fields.get("face").cloned().unwrap_or_default();
+                    font.size = int("size")?.abs();
+                }
+                "common" => {
+                    font.line_height = int("lineHeight")? as u32;
+                    font.base = int("base")? as u32;
+                    font.page_size = [int("scaleW")? as u32, int("scaleH")? as u32];
+                }
+                "page" => {
+                    let id = int("id")? as usize;
+                    let Some(file) = fields.get("file") else {
+                        bail!("BmFont::parse => line {} missing file", number + 1);
+                    };
+                    if font.pages.len() <= id {
+                        font.pages.resize(id + 1, String::new());
+                    }
+                    font.pages[id] = file.clone();
+                }
+                "char" => {
+                    // id=-1 is the optional glyph for missing characters, which we don't use.
+                    let Some(c) = char::from_u32(int("id")? as u32) else {
+                        continue;
+                    };
+                    font.chars.insert(
+                        c,
+                        BmChar {
+                            x: int("x")? as u32,
+                            y: int("y")? as u32,
+                            width: int("width")? as u32,
+                            height: int("height")? as u32,
+                            offset: [int("xoffset")?, int("yoffset")?],
+                            advance: int("xadvance")?,
+                            page: int("page").unwrap_or(0) as u32,
+                        },
+                    );
+                }
+                "kerning" => {
+                    let first = char::from_u32(int("first")? as u32);
+                    let second = char::from_u32(int("second")? as u32);
+                    if let (Some(first), Some(second)) = (first, second) {
+                        font.kerning.insert((first, second), int("amount")?);
+                    }
+                }
+                _ => {}
+            }
+        }
+        font.validate()?;
+        Ok(font)
+    }
+
+    /// Parses the binary .fnt format written by BMFont's "binary" output option.
+    pub fn parse_binary(bytes: &[u8]) -> anyhow::Result<Self> {
+        ensure!(
+            bytes.len() >= 4 && bytes.starts_with(Self::BINARY_MAGIC),
+            "BmFont::parse_binary => not a binary BMFont file"
+        );
+        ensure!(
+            bytes[3] == 3,
+            "BmFont::parse_binary => unsupported version {}",
+            bytes[3]
+        );
+        let mut font = Self::default();
+        let mut rest = &bytes[4..];
+        while !rest.is_empty() {
+            ensure!(rest.len() >= 5, "BmFont::parse_binary => truncated block");
+            let ty = rest[0];
+            let len = u32::from_le_bytes([rest[1], rest[2], rest[3], rest[4]]) as usize;
+            ensure!(
+                rest.len() >= 5 + len,
+                "BmFont::parse_binary => truncated block"
+            );
+            let block = &rest[5..5 + len];
+            rest = &rest[5 + len..];
+            let u16_at = |at: usize| u16::from_le_bytes([block[at], block[at + 1]]);
+            let i16_at = |at: usize| i16::from_le_bytes([block[at], block[at + 1]]) as i32;
+            let u32_at = |at: usize| {
+                u32::from_le_bytes([block[at], block[at + 1], block[at + 2], block[at + 3]])
+            };
+            match ty {
+                1 => {
+                    ensure!(len >= 14, "BmFont::parse_binary => truncated info block");
+                    font.size = i16_at(0).abs();
+                    let name = &block[14..];
+                    let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+                    font.face = String::from_utf8_lossy(&name[..end]).into_owned();
+                }
+                2 => {
+                    ensure!(len >= 10, "BmFont::parse_binary => truncated common block");
+                    font.line_height = u16_at(0) as u32;
+                    font.base = u16_at(2) as u32;
+                    font.page_size = [u16_at(4) as u32, u16_at(6) as u32];
+                }
+                3 => {
+                    font.pages = block
+                        .split(|&b| b == 0)
+                        .filter(|name| !name.is_empty())
+                        .map(|name| String::from_utf8_lossy(name).into_owned())
+                        .collect();
+                }
+                4 => {
+                    for at in (0..len / 20).map(|i| i * 20) {
+                        let Some(c) = char::from_u32(u32_at(at)) else {
+                            continue;
+                        };
+                        font.chars.insert(
+                            c,
+                            BmChar {
+                                x: u16_at(at + 4) as u32,
+                                y: u16_at(at + 6) as u32,
+                                width: u16_at(at + 8) as u32,
+                                height: u16_at(at + 10) as u32,
+                                offset: [i16_at(at + 12), i16_at(at + 14)],
+                                advance: i16_at(at + 16),
+                                page: block[at + 18] as u32,
+                            },
+                        );
+                    }
+                }
+                5 => {
+                    for at in (0..len / 10).map(|i| i * 10) {
+                        let first = char::from_u32(u32_at(at));
+                        let second = char::from_u32(u32_at(at + 4));
+                        if let (Some(first), Some(second)) = (first, second) {
+                            font.kerning.insert((first, second), i16_at(at + 8));
+                        }
+                    }
+                }
+                _ => log::warn!("BmFont::parse_binary => unknown block type {}", ty),
+            }
+        }
+        font.validate()?;
+        Ok(font)
+    }
+
+    /// Writes the text .fnt format parse reads, chars and kerning pairs sorted by id.
+    pub fn to_text(&self) -> String {
+        let [width, height] = self.page_size;
+        let mut lines = vec![
+            format!("info face=\"{}\" size={}", self.face, self.size),
+            format!(
+                "common lineHeight={} base={} scaleW={} scaleH={} pages={}",
+                self.line_height,
+                self.base,
+                width,
+                height,
+                self.pages.len()
+            ),
+        ];
+        for (id, file) in self.pages.iter().enumerate() {
+            lines.push(format!("page id={} file=\"{}\"", id, file));
+        }
+        let mut chars: Vec<_> = self.chars.iter().collect();
+        chars.sort_by_key(|(c, _)| **c);
+        lines.push(format!("chars count={}", chars.len()));
+        for (c, g) in chars {
+            lines.push(format!(
+                "char id={} x={} y={} width={} height={} xoffset={} yoffset={} xadvance={} page={}",
+                *c as u32, g.x, g.y, g.width, g.height, g.offset[0], g.offset[1], g.advance, g.page
+            ));
+        }
+        let mut kerning: Vec<_> = self.kerning.iter().collect();
+        kerning.sort_by_key(|(pair, _)| **pair);
+        lines.push(format!("kernings count={}", kerning.len()));
+        for ((first, second), amount) in kerning {
+            lines.push(format!(
+                "kerning first={} second={} amount={}",
+                *first as u32, *second as u32, amount
+            ));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.page_size[0] > 0 && self.page_size[1] > 0,
+            "BmFont => missing common block"
+        );
+        ensure!(!self.pages.is_empty(), "BmFont => font has no pages");
+        if let Some(c) = self
+            .chars
+            .iter()
+            .find(|(_, g)| g.page as usize >= self.pages.len())
+        {
+            bail!("BmFont => glyph {:?} is on missing page {}", c.0, c.1.page);
+        }
+        Ok(())
+    }
+
+    /// Glyph for `c`, unknown characters fall back to '?' if the font has one.
+    pub fn glyph(&self, c: char) -> Option<&BmChar> {
+        self.chars.get(&c).or_else(|| self.chars.get(&'?'))
+    }
+
+    /// Extra advance between `first` and `second`, usually negative.
+    #[inline]
+    pub fn kerning(&self, first: char, second: char) -> i32 {
+        self.kerning.get(&(first, second)).copied().unwrap_or(0)
+    }
+
+    /// Normalized rect of `glyph` within its page.
+    pub fn glyph_uv(&self, glyph: &BmChar) -> UvRect {
+        let [page_w, page_h] = self.page_size.map(|s| s as f32);
+        UvRect::new(
+            [glyph.x as f32 / page_w, glyph.y as f32 / page_h],
+            [
+                (glyph.x + glyph.width) as f32 / page_w,
+                (glyph.y + glyph.height) as f32 / page_h,
+            ],
+        )
+    }
+
+    /// Width of the widest line and the height of all lines, in font pixels.
+    pub fn measure(&self, text: &str) -> [u32; 2] {
+        let mut width = 0;
+        let mut lines = 0;
+        for line in text.split('\n') {
+            let mut pen = 0;
+            let mut prev = None;
+            for c in line.chars() {
+                let Some(glyph) = self.glyph(c) else {
+                    continue;
+                };
+                if let Some(prev) = prev {
+                    pen += self.kerning(prev, c);
+                }
+                pen += glyph.advance;
+                prev = Some(c);
+            }
+            width = width.max(pen.max(0) as u32);
+            lines += 1;
+        }
+        [width, lines * self.line_height]
+    }
+
+    /// Quads for `text` with the top left of its first line at `origin` and every font
+    /// pixel `scale` world units big. Line breaks on '\n', whitespace glyphs are skipped.
+    /// Positions are snapped to whole pixels so integer scales stay pixel perfect.
+    pub fn layout(&self, text: &str, origin: [f32; 2], scale: f32) -> Vec<GlyphQuad> {
+        let origin = origin.map(f32::round);
+        let mut quads = Vec::with_capacity(text.len());
+        for (line_index, line) in text.split('\n').enumerate() {
+            let top = origin[1] - (line_index as u32 * self.line_height) as f32 * scale;
+            let mut pen = 0;
+            let mut prev = None;
+            for c in line.chars() {
+                let Some(glyph) = self.glyph(c) else {
+                    continue;
+                };
+                if let Some(prev) = prev {
+                    pen += self.kerning(prev, c);
+                }
+                prev = Some(c);
+                if glyph.width > 0 && glyph.height > 0 && !c.is_whitespace() {
+                    let size = [glyph.width as f32 * scale, glyph.height as f32 * scale];
+                    let x = origin[0] + (pen + glyph.offset[0]) as f32 * scale;
+                    let y = top - glyph.offset[1] as f32 * scale - size[1];
+                    quads.push(GlyphQuad {
+                        page: glyph.page,
+                        position: [x, y],
+                        size,
+                        uv: self.glyph_uv(glyph),
+                    });
+                }
+                pen += glyph.advance;
+            }
+        }
+        quads
+    }
+}
+
+/// Splits a .fnt line on spaces outside of quotes.
+fn split_tokens(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                token.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// How BitmapFont::push_text draws, mirrors the matching Sprite fields.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    /// World units per font pixel, whole numbers keep bitmap fonts crisp.
+    pub scale: f32,
+    pub color: [f32; 4],
+    pub layer: i32,
+    pub z: f32,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            color: [1.0; 4],
+            layer: 0,
+            z: 0.0,
+        }
+    }
+}
+
+impl TextStyle {
+    pub const fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub const fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub const fn with_layer(mut self, layer: i32, z: f32) -> Self {
+        self.layer = layer;
+        self.z = z;
+        self
+    }
+}
+
+/// A BmFont with the QuadBuffer material drawing each of its pages, so text is pushed
+/// like any other sprites and batched by page.
+#[derive(Debug, Clone)]
+pub struct BitmapFont {
+    pub font: BmFont,
+    /// Indexed by page.
+    pub pages: Vec<MaterialSlot>,
+}
+
+impl BitmapFont {
+    /// `pages` are materials sampling each page texture, e.g. registered with
+    /// QuadBuffer::add_material using BitmapFont::page_sampler.
+    pub fn new(font: BmFont, pages: Vec<MaterialSlot>) -> anyhow::Result<Self> {
+        ensure!(
+            pages.len() == font.pages.len(),
+            "BitmapFont::new => font has {} pages but {} materials were given",
+            font.pages.len(),
+            pages.len()
+        );
+        Ok(Self { font, pages })
+    }
+
+    /// Nearest filtering sampler for page textures, linear filtering blurs pixel fonts.
+    pub fn page_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Bitmap Font Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        })
+    }
+
+    /// Pushes `text` with the top left of its first line at `origin`, see BmFont::layout.
+    pub fn push_text(
+        &self,
+        quads: &mut QuadBuffer,
+        text: &str,
+        origin: [f32; 2],
+        style: TextStyle,
+    ) {
+        for glyph in self.font.layout(text, origin, style.scale) {
+            quads.push_sprite(
+                &Sprite::new(glyph.position, glyph.size)
+                    .with_pivot([0.0, 0.0])
+                    .with_uv(glyph.uv)
+                    .with_color(style.color)
+                    .with_layer(style.layer, style.z)
+                    .with_material(self.pages[glyph.page as usize]),
+            );
+        }
+    }
+
+    /// Size of `text` in world units at `scale`.
+    pub fn measure(&self, text: &str, scale: f32) -> [f32; 2] {
+        self.font.measure(text).map(|s| s as f32 * scale)
+    }
+}
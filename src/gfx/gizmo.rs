@@ -0,0 +1,718 @@
+use std::sync::Arc;
+
+use cgmath::{
+    EuclideanSpace, InnerSpace, Matrix4, Point3, Quaternion, Rad, Rotation3, SquareMatrix, Vector3,
+    Vector4,
+};
+use wgpu::util::DeviceExt;
+
+use super::{draw::DrawCtx, transform::Transform};
+
+/// A world space ray, usually built from the cursor position.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    /// Normalized direction.
+    pub dir: Vector3<f32>,
+}
+
+impl Ray {
+    pub fn new(origin: Point3<f32>, dir: Vector3<f32>) -> Self {
+        Self {
+            origin,
+            dir: dir.normalize(),
+        }
+    }
+
+    /// Unprojects `cursor` (in pixels, origin top left) through the inverse of `view_proj`.
+    /// Returns None if `view_proj` can't be inverted.
+    pub fn from_screen(
+        cursor: [f32; 2],
+        viewport: [f32; 2],
+        view_proj: Matrix4<f32>,
+    ) -> Option<Self> {
+        let inv = view_proj.invert()?;
+        let x = 2.0 * cursor[0] / viewport[0] - 1.0;
+        let y = 1.0 - 2.0 * cursor[1] / viewport[1];
+        let unproject = |z: f32| {
+            let p = inv * Vector4::new(x, y, z, 1.0);
+            Point3::from_homogeneous(p)
+        };
+        // wgpu clip space depth runs from 0 (near) to 1 (far).
+        let near = unproject(0.0);
+        let far = unproject(1.0);
+        Some(Self::new(near, far - near))
+    }
+
+    #[inline]
+    pub fn at(&self, t: f32) -> Point3<f32> {
+        self.origin + self.dir * t
+    }
+
+    /// Distance along the ray to the plane through `point` with `normal`.
+    pub fn intersect_plane(&self, point: Point3<f32>, normal: Vector3<f32>) -> Option<f32> {
+        let denom = self.dir.dot(normal);
+        if denom.abs() < 1e-6 {
+            return None;
+        }
+        let t = (point - self.origin).dot(normal) / denom;
+        (t >= 0.0).then_some(t)
+    }
+
+    /// Closest approach to the line through `point` along the unit vector `axis`.
+    /// Returns (parameter along the axis, distance along the ray, distance between the two).
+    pub fn closest_to_line(
+        &self,
+        point: Point3<f32>,
+        axis: Vector3<f32>,
+    ) -> Option<(f32, f32, f32)> {
+        let w0 = point - self.origin;
+        let b = axis.dot(self.dir);
+        let denom = 1.0 - b * b;
+        if denom.abs() < 1e-6 {
+            return None;
+        }
+        let d = axis.dot(w0);
+        let e = self.dir.dot(w0);
+        let s = (b * e - d) / denom;
+        let t = (e - b * d) / denom;
+        let dist = ((point + axis * s) - self.at(t)).magnitude();
+        Some((s, t, dist))
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GizmoMode {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GizmoAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl GizmoAxis {
+    pub const ALL: [GizmoAxis; 3] = [GizmoAxis::X, GizmoAxis::Y, GizmoAxis::Z];
+
+    pub fn dir(self) -> Vector3<f32> {
+        match self {
+            GizmoAxis::X => Vector3::unit_x(),
+            GizmoAxis::Y => Vector3::unit_y(),
+            GizmoAxis::Z => Vector3::unit_z(),
+        }
+    }
+
+    pub fn color(self) -> [f32; 4] {
+        match self {
+            GizmoAxis::X => [0.9, 0.2, 0.2, 1.0],
+            GizmoAxis::Y => [0.2, 0.85, 0.2, 1.0],
+            GizmoAxis::Z => [0.25, 0.4, 0.95, 1.0],
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Drag {
+    axis: GizmoAxis,
+    /// Axis parameter for translate/scale, plane angle for rotate, at the start of the drag.
+    start: f32,
+    start_position: Vector3<f32>,
+    start_rotation: Quaternion<f32>,
+    start_size: Vector3<f32>,
+}
+
+/// Translate/rotate/scale handles for a single target Transform. Handles are world axis
+/// aligned and sized relative to the camera distance so they stay constant on screen.
+#[derive(Debug, Clone)]
+pub struct Gizmo {
+    pub mode: GizmoMode,
+    /// Handle length as a fraction of the distance between the camera and the target.
+    pub screen_scale: f32,
+    /// Pick radius around a handle, relative to the handle length.
+    pub pick_radius: f32,
+    hovered: Option<GizmoAxis>,
+    drag: Option<Drag>,
+    was_pressed: bool,
+}
+
+impl Default for Gizmo {
+    fn default() -> Self {
+        Self {
+            mode: GizmoMode::default(),
+            screen_scale: 0.15,
+            pick_radius: 0.08,
+            hovered: None,
+            drag: None,
+            was_pressed: false,
+        }
+    }
+}
+
+impl Gizmo {
+    pub fn new(mode: GizmoMode) -> Self {
+        Self {
+            mode,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub fn hovered(&self) -> Option<GizmoAxis> {
+        self.hovered
+    }
+
+    /// Axis currently being dragged.
+    #[inline]
+    pub fn active(&self) -> Option<GizmoAxis> {
+        self.drag.map(|d| d.axis)
+    }
+
+    #[inline]
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// World space length of the handles for a target at `position`.
+    pub fn handle_length(&self, eye: Point3<f32>, position: Vector3<f32>) -> f32 {
+        (Point3::from_vec(position) - eye).magnitude() * self.screen_scale
+    }
+
+    /// Hit tests the handles against `ray`, returning the closest hovered axis.
+    pub fn pick(&self, ray: &Ray, eye: Point3<f32>, position: Vector3<f32>) -> Option<GizmoAxis> {
+        let length = self.handle_length(eye, position);
+        let radius = length * self.pick_radius;
+        let center = Point3::from_vec(position);
+
+        let mut best: Option<(GizmoAxis, f32)> = None;
+        for axis in GizmoAxis::ALL {
+            let hit = match self.mode {
+                GizmoMode::Translate | GizmoMode::Scale => ray
+                    .closest_to_line(center, axis.dir())
+                    .filter(|(s, t, dist)| {
+                        *t >= 0.0 && *s >= 0.0 && *s <= length && *dist <= radius
+                    })
+                    .map(|(_, t, _)| t),
+                GizmoMode::Rotate => ray
+                    .intersect_plane(center, axis.dir())
+                    .filter(|t| ((ray.at(*t) - center).magnitude() - length).abs() <= radius),
+            };
+            if let Some(t) = hit {
+                if best.is_none_or(|(_, best_t)| t < best_t) {
+                    best = Some((axis, t));
+                }
+            }
+        }
+        best.map(|(axis, _)| axis)
+    }
+
+    /// Feeds the cursor ray and button state, applying any drag to `target`.
+    /// Returns true while the gizmo is hovered or dragged, so the caller can skip its own picking.
+    pub fn update(
+        &mut self,
+        ray: &Ray,
+        eye: Point3<f32>,
+        pressed: bool,
+        target: &mut Transform,
+    ) -> bool {
+        let just_pressed = pressed && !self.was_pressed;
+        self.was_pressed = pressed;
+
+        if let Some(drag) = self.drag {
+            if !pressed {
+                self.drag = None;
+            } else if let Some(value) = self.drag_value(ray, drag.axis, drag.start_position) {
+                self.apply(&drag, value, target);
+            }
+            return true;
+        }
+
+        self.hovered = self.pick(ray, eye, target.position());
+        if let (true, Some(axis)) = (just_pressed, self.hovered) {
+            if let Some(start) = self.drag_value(ray, axis, target.position()) {
+                self.drag = Some(Drag {
+                    axis,
+                    start,
+                    start_position: target.position(),
+                    start_rotation: target.rotation(),
+                    start_size: target.size(),
+                });
+            }
+        }
+        self.hovered.is_some()
+    }
+
+    fn drag_value(&self, ray: &Ray, axis: GizmoAxis, position: Vector3<f32>) -> Option<f32> {
+        let center = Point3::from_vec(position);
+        match self.mode {
+            GizmoMode::Translate | GizmoMode::Scale => {
+                ray.closest_to_line(center, axis.dir()).map(|(s, _, _)| s)
+            }
+            GizmoMode::Rotate => {
+                let n = axis.dir();
+                let t = ray.intersect_plane(center, n)?;
+                let v = ray.at(t) - center;
+                // Angle around `n` measured from the next axis, so u x w == n.
+                let (u, w) = match axis {
+                    GizmoAxis::X => (Vector3::unit_y(), Vector3::unit_z()),
+                    GizmoAxis::Y => (Vector3::unit_z(), Vector3::unit_x()),
+                    GizmoAxis::Z => (Vector3::unit_x(), Vector3::unit_y()),
+                };
+                Some(v.dot(w).atan2(v.dot(u)))
+            }
+        }
+    }
+
+    fn apply(&self, drag: &Drag, value: f32, target: &mut Transform) {
+        let dir = drag.axis.dir();
+        match self.mode {
+            GizmoMode::Translate => {
+                target.set_position(drag.start_position + dir * (value - drag.start));
+            }
+            GizmoMode::Rotate => {
+                let delta = Quaternion::from_axis_angle(dir, Rad(value - drag.start));
+                target.set_rotation(delta * drag.start_rotation);
+            }
+            GizmoMode::Scale => {
+                if drag.start.abs() < 1e-6 {
+                    return;
+                }
+                let factor = value / drag.start;
+                let mut size = drag.start_size;
+                match drag.axis {
+                    GizmoAxis::X => size.x *= factor,
+                    GizmoAxis::Y => size.y *= factor,
+                    GizmoAxis::Z => size.z *= factor,
+                }
+                target.set_size(size);
+            }
+        }
+    }
+
+    /// Triangle list for the current mode, in world space around `position`.
+    pub fn mesh(&self, eye: Point3<f32>, position: Vector3<f32>) -> Vec<GizmoVertex> {
+        let length = self.handle_length(eye, position);
+        let mut out = Vec::new();
+        for axis in GizmoAxis::ALL {
+            let color = if self.active() == Some(axis) || self.hovered == Some(axis) {
+                HIGHLIGHT_COLOR
+            } else {
+                axis.color()
+            };
+            let start = out.len();
+            match self.mode {
+                GizmoMode::Translate => {
+                    push_box(&mut out, [0.0, -0.01, -0.01], [0.8, 0.01, 0.01], color);
+                    push_cone(&mut out, 0.8, 1.0, 0.05, color);
+                }
+                GizmoMode::Rotate => push_ring(&mut out, 1.0, 0.012, color),
+                GizmoMode::Scale => {
+                    push_box(&mut out, [0.0, -0.01, -0.01], [0.92, 0.01, 0.01], color);
+                    push_box(&mut out, [0.92, -0.04, -0.04], [1.0, 0.04, 0.04], color);
+                }
+            }
+            // Handles are built along +X (rings around X), swizzle them onto their axis.
+            for v in &mut out[start..] {
+                let [x, y, z] = v.position;
+                let p = match axis {
+                    GizmoAxis::X => [x, y, z],
+                    GizmoAxis::Y => [y, x, z],
+                    GizmoAxis::Z => [z, y, x],
+                };
+                v.position = [
+                    position.x + p[0] * length,
+                    position.y + p[1] * length,
+                    position.z + p[2] * length,
+                ];
+            }
+        }
+        out
+    }
+}
+
+const HIGHLIGHT_COLOR: [f32; 4] = [1.0, 0.85, 0.1, 1.0];
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GizmoVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl GizmoVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+
+    pub fn buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+fn push_tri(out: &mut Vec<GizmoVertex>, tri: [[f32; 3]; 3], color: [f32; 4]) {
+    out.extend(tri.map(|position| GizmoVertex { position, color }));
+}
+
+fn push_quad(out: &mut Vec<GizmoVertex>, q: [[f32; 3]; 4], color: [f32; 4]) {
+    push_tri(out, [q[0], q[1], q[2]], color);
+    push_tri(out, [q[0], q[2], q[3]], color);
+}
+
+fn push_box(out: &mut Vec<GizmoVertex>, min: [f32; 3], max: [f32; 3], color: [f32; 4]) {
+    let c = |i: usize| {
+        [
+            if i & 1 == 0 { min[0] } else { max[0] },
+            if i & 2 == 0 { min[1] } else { max[1] },
+            if i & 4 == 0 { min[2] } else { max[2] },
+        ]
+    };
+    for face in [
+        [0, 2, 6, 4],
+        [1, 5, 7, 3],
+        [0, 4, 5, 1],
+        [2, 3, 7, 6],
+        [0, 1, 3, 2],
+        [4, 6, 7, 5],
+    ] {
+        push_quad(out, face.map(c), color);
+    }
+}
+
+const SEGMENTS: usize = 16;
+
+fn circle(i: usize, radius: f32) -> (f32, f32) {
+    let a = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+    (a.cos() * radius, a.sin() * radius)
+}
+
+fn push_cone(out: &mut Vec<GizmoVertex>, base: f32, tip: f32, radius: f32, color: [f32; 4]) {
+    for i in 0..SEGMENTS {
+        let (y0, z0) = circle(i, radius);
+        let (y1, z1) = circle(i + 1, radius);
+        push_tri(
+            out,
+            [[base, y0, z0], [base, y1, z1], [tip, 0.0, 0.0]],
+            color,
+        );
+        push_tri(
+            out,
+            [[base, y1, z1], [base, y0, z0], [base, 0.0, 0.0]],
+            color,
+        );
+    }
+}
+
+/// Thin tube around the X axis.
+fn push_ring(out: &mut Vec<GizmoVertex>, radius: f32, thickness: f32, color: [f32; 4]) {
+    const SEGMENTS: usize = 48;
+    for i in 0..SEGMENTS {
+        let a0 = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+        let a1 = (i + 1) as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+        let at = |a: f32, r: f32, x: f32| [x, a.cos() * r, a.sin() * r];
+        let (inner, outer) = (radius - thickness, radius + thickness);
+        // Outer, inner, and the two flat sides of a square cross section.
+        push_quad(
+            out,
+            [
+                at(a0, outer, -thickness),
+                at(a1, outer, -thickness),
+                at(a1, outer, thickness),
+                at(a0, outer, thickness),
+            ],
+            color,
+        );
+        push_quad(
+            out,
+            [
+                at(a0, inner, thickness),
+                at(a1, inner, thickness),
+                at(a1, inner, -thickness),
+                at(a0, inner, -thickness),
+            ],
+            color,
+        );
+        push_quad(
+            out,
+            [
+                at(a0, inner, thickness),
+                at(a0, outer, thickness),
+                at(a1, outer, thickness),
+                at(a1, inner, thickness),
+            ],
+            color,
+        );
+        push_quad(
+            out,
+            [
+                at(a1, inner, -thickness),
+                at(a1, outer, -thickness),
+                at(a0, outer, -thickness),
+                at(a0, inner, -thickness),
+            ],
+            color,
+        );
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct GridUniform {
+    view_proj: [[f32; 4]; 4],
+    inv_view_proj: [[f32; 4]; 4],
+    eye: [f32; 4],
+    /// x: cell size, y: fade distance.
+    params: [f32; 4],
+}
+
+fn view_uniform_layout(device: &wgpu::Device, label: &str) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+        label: Some(label),
+    })
+}
+
+/// Overlays never write depth. The grid depth tests against the scene, handles always pass.
+fn overlay_depth(
+    depth_format: Option<wgpu::TextureFormat>,
+    compare: wgpu::CompareFunction,
+) -> Option<wgpu::DepthStencilState> {
+    depth_format.map(|format| wgpu::DepthStencilState {
+        format,
+        depth_write_enabled: Some(false),
+        depth_compare: Some(compare),
+        stencil: wgpu::StencilState::default(),
+        bias: wgpu::DepthBiasState::default(),
+    })
+}
+
+/// Infinite XZ ground grid drawn from a full screen triangle pair, with the X and Z axes
+/// highlighted and lines fading out with distance from the camera.
+pub struct GridRenderer {
+    pipeline: Arc<wgpu::RenderPipeline>,
+    uniform: Arc<wgpu::Buffer>,
+    bind_group: Arc<wgpu::BindGroup>,
+    depth_format: Option<wgpu::TextureFormat>,
+    pub cell_size: f32,
+    pub fade_distance: f32,
+}
+
+impl GridRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+    ) -> Self {
+        let layout = view_uniform_layout(device, "grid_bind_group_layout");
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Grid Uniform Buffer"),
+            size: std::mem::size_of::<GridUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.as_entire_binding(),
+            }],
+            label: Some("grid_bind_group"),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Grid Pipeline Layout"),
+            bind_group_layouts: &[Some(&layout)],
+            immediate_size: 0,
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Grid Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/grid.wgsl").into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Grid Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: overlay_depth(depth_format, wgpu::CompareFunction::Less),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline: Arc::new(pipeline),
+            uniform: Arc::new(uniform),
+            bind_group: Arc::new(bind_group),
+            depth_format,
+            cell_size: 1.0,
+            fade_distance: 100.0,
+        }
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, view_proj: Matrix4<f32>, eye: Point3<f32>) {
+        let Some(inv) = view_proj.invert() else {
+            log::warn!("GridRenderer::update => view projection is not invertible");
+            return;
+        };
+        let uniform = GridUniform {
+            view_proj: view_proj.into(),
+            inv_view_proj: inv.into(),
+            eye: eye.to_homogeneous().into(),
+            params: [self.cell_size, self.fade_distance, 0.0, 0.0],
+        };
+        queue.write_buffer(&self.uniform, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    pub fn draw(&self, ctx: &mut DrawCtx) {
+        if let Err(e) = ctx.current_pass_mut().validate_depth(self.depth_format) {
+            log::warn!("GridRenderer::draw => {:#}, draw skipped", e);
+            return;
+        }
+        ctx.set_pipeline(self.pipeline.clone());
+        ctx.set_bind_group(0, self.bind_group.clone(), None);
+        ctx.draw(0..6, 0..1);
+    }
+}
+
+/// Draws a Gizmo's handles on top of the scene.
+pub struct GizmoRenderer {
+    pipeline: Arc<wgpu::RenderPipeline>,
+    uniform: Arc<wgpu::Buffer>,
+    bind_group: Arc<wgpu::BindGroup>,
+    vertices: Arc<wgpu::Buffer>,
+    vertex_count: u32,
+    depth_format: Option<wgpu::TextureFormat>,
+}
+
+impl GizmoRenderer {
+    /// Largest mesh any mode generates, the rotate rings.
+    const MAX_VERTICES: u64 = 3 * 48 * 4 * 6;
+
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+    ) -> Self {
+        let layout = view_uniform_layout(device, "gizmo_bind_group_layout");
+        let identity: [[f32; 4]; 4] = Matrix4::<f32>::identity().into();
+        let uniform = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Gizmo Uniform Buffer"),
+            contents: bytemuck::cast_slice(&[identity]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.as_entire_binding(),
+            }],
+            label: Some("gizmo_bind_group"),
+        });
+        let vertices = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Gizmo Vertex Buffer"),
+            size: Self::MAX_VERTICES * std::mem::size_of::<GizmoVertex>() as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Gizmo Pipeline Layout"),
+            bind_group_layouts: &[Some(&layout)],
+            immediate_size: 0,
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Gizmo Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/gizmo.wgsl").into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Gizmo Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[Some(GizmoVertex::buffer_layout())],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: overlay_depth(depth_format, wgpu::CompareFunction::Always),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline: Arc::new(pipeline),
+            uniform: Arc::new(uniform),
+            bind_group: Arc::new(bind_group),
+            vertices: Arc::new(vertices),
+            vertex_count: 0,
+            depth_format,
+        }
+    }
+
+    /// Rebuilds the handle mesh for `gizmo` around `target`.
+    pub fn update(
+        &mut self,
+        queue: &wgpu::Queue,
+        gizmo: &Gizmo,
+        target: &Transform,
+        view_proj: Matrix4<f32>,
+        eye: Point3<f32>,
+    ) {
+        let mesh = gizmo.mesh(eye, target.position());
+        debug_assert!(mesh.len() as u64 <= Self::MAX_VERTICES);
+        let view_proj: [[f32; 4]; 4] = view_proj.into();
+        queue.write_buffer(&self.uniform, 0, bytemuck::cast_slice(&[view_proj]));
+        queue.write_buffer(&self.vertices, 0, bytemuck::cast_slice(&mesh));
+        self.vertex_count = mesh.len() as u32;
+    }
+
+    pub fn draw(&self, ctx: &mut DrawCtx) {
+        if let Err(e) = ctx.current_pass_mut().validate_depth(self.depth_format) {
+            log::warn!("GizmoRenderer::draw => {:#}, draw skipped", e);
+            return;
+        }
+        ctx.set_pipeline(self.pipeline.clone());
+        ctx.set_bind_group(0, self.bind_group.clone(), None);
+        ctx.set_vertex_buffer(0, self.vertices.clone());
+        ctx.draw(0..self.vertex_count, 0..1);
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+/// WGSL declaration of GlobalsUniform. The buffer lives at binding 1 of the camera bind
+/// group, so any shader that binds the camera can read it by declaring
+/// `@group(<camera group>) @binding(1) var<uniform> globals: Globals;`
+pub const GLOBALS_WGSL: &str = include_str!("../shaders/globals.wgsl");
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GlobalsUniform {
+    /// Render resolution in pixels.
+    pub resolution: [f32; 2],
+    /// Cursor position in pixels from the top left of the window.
+    pub cursor: [f32; 2],
+    /// Seconds since the window was created, unaffected by time scale and pause.
+    pub time: f32,
+    pub delta_time: f32,
+    pub frame: u32,
+    _pad: u32,
+}
+
+/// Per frame values shared by every shader, see GLOBALS_WGSL.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct FrameGlobals {
+    pub elapsed: Duration,
+    pub delta: Duration,
+    pub frame: u64,
+    pub resolution: [u32; 2],
+    pub cursor: [f32; 2],
+}
+
+impl FrameGlobals {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            resolution: [width, height],
+            ..Default::default()
+        }
+    }
+
+    /// Starts a new frame `dt` after the previous one.
+    pub fn advance(&mut self, dt: Duration) {
+        self.elapsed += dt;
+        self.delta = dt;
+        self.frame += 1;
+    }
+
+    pub fn uniform(&self) -> GlobalsUniform {
+        GlobalsUniform {
+            resolution: self.resolution.map(|r| r as f32),
+            cursor: self.cursor,
+            time: self.elapsed.as_secs_f32(),
+            delta_time: self.delta.as_secs_f32(),
+            // Wraps after about two years at 60 fps, shaders only need it for variation.
+            frame: self.frame as u32,
+            _pad: 0,
+        }
+    }
+}
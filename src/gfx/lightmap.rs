@@ -0,0 +1,219 @@
+use std::sync::Arc;
+
+use cgmath::{Point3, Transform};
+use wgpu::util::DeviceExt;
+
+use crate::sys::lightmap::BakeSurface;
+
+use super::{model::Material, wgpu::texture::Texture};
+
+/// Declarations for sampling lightmaps in WGSL, prepend to shaders that read a tile
+/// lightmap from sys::lightmap::bake_tiles.
+pub const LIGHTMAP_WGSL: &str = include_str!("../shaders/lightmap_common.wgsl");
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct LightmapVertex {
+    pub position: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub lightmap_uv: [f32; 2],
+}
+
+impl LightmapVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x2];
+
+    pub fn buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// World space triangle list of `surface` with the lightmap uvs baked for it, one vertex
+/// per index since triangles sharing a vertex can land in different charts.
+pub fn lightmap_vertices(surface: &BakeSurface, uvs: &[[f32; 2]]) -> Vec<LightmapVertex> {
+    surface
+        .indices
+        .iter()
+        .zip(uvs)
+        .map(|(&index, &lightmap_uv)| {
+            let v = surface.vertices[index as usize];
+            LightmapVertex {
+                position: surface
+                    .transform
+                    .transform_point(Point3::from(v.position))
+                    .into(),
+                tex_coords: v.tex_coords,
+                lightmap_uv,
+            }
+        })
+        .collect()
+}
+
+struct LightmappedSurface {
+    vertices: Arc<wgpu::Buffer>,
+    vertex_count: u32,
+    material: Arc<wgpu::BindGroup>,
+}
+
+/// Draws static geometry lit by a baked lightmap instead of the realtime light. Surfaces
+/// are drawn opaque with depth writes, in the same pass as the rest of the scene.
+pub struct LightmapRenderer {
+    pipeline: Arc<wgpu::RenderPipeline>,
+    bind_group: Arc<wgpu::BindGroup>,
+    surfaces: Vec<LightmappedSurface>,
+}
+
+impl LightmapRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+        cam_bind_group_layout: &wgpu::BindGroupLayout,
+        lightmap: &Texture,
+    ) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("lightmap_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&lightmap.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&lightmap.sampler),
+                },
+            ],
+            label: Some("lightmap_bind_group"),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Lightmap Pipeline Layout"),
+            bind_group_layouts: &[
+                Some(texture_bind_group_layout),
+                Some(cam_bind_group_layout),
+                Some(&layout),
+            ],
+            immediate_size: 0,
+        });
+        let source = format!(
+            "{}\n{}",
+            LIGHTMAP_WGSL,
+            include_str!("../shaders/lightmap.wgsl")
+        );
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Lightmap Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Lightmap Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[Some(LightmapVertex::buffer_layout())],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: Some(true),
+                depth_compare: Some(wgpu::CompareFunction::Less),
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline: Arc::new(pipeline),
+            bind_group: Arc::new(bind_group),
+            surfaces: Vec::new(),
+        }
+    }
+
+    /// Adds a surface built with lightmap_vertices, textured with `material`'s diffuse map.
+    pub fn add_surface(
+        &mut self,
+        device: &wgpu::Device,
+        vertices: &[LightmapVertex],
+        material: &Material,
+    ) {
+        if vertices.is_empty() {
+            return;
+        }
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Lightmap Vertex Buffer"),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        self.surfaces.push(LightmappedSurface {
+            vertices: Arc::new(buffer),
+            vertex_count: vertices.len() as u32,
+            material: material.bind_group.clone(),
+        });
+    }
+
+    pub fn clear_surfaces(&mut self) {
+        self.surfaces.clear();
+    }
+
+    #[inline]
+    pub fn surface_count(&self) -> usize {
+        self.surfaces.len()
+    }
+
+    #[inline]
+    pub(crate) fn pipeline(&self) -> Arc<wgpu::RenderPipeline> {
+        self.pipeline.clone()
+    }
+
+    #[inline]
+    pub(crate) fn bind_group(&self) -> Arc<wgpu::BindGroup> {
+        self.bind_group.clone()
+    }
+
+    /// (vertex buffer, vertex count, material bind group) of every surface.
+    pub(crate) fn surfaces(
+        &self,
+    ) -> impl Iterator<Item = (Arc<wgpu::Buffer>, u32, Arc<wgpu::BindGroup>)> + '_ {
+        self.surfaces
+            .iter()
+            .map(|s| (s.vertices.clone(), s.vertex_count, s.material.clone()))
+    }
+}
@@ -0,0 +1,367 @@
+use std::sync::Arc;
+
+use anyhow::*;
+use wgpu::DynamicOffset;
+
+use crate::eng::animation::Interpolation;
+
+/// WGSL declaration of MaterialParamsUniform, bind MaterialParamBuffer::bind_group with
+/// `@group(<n>) @binding(0) var<uniform> params: MaterialParams;`
+pub const MATERIAL_PARAMS_WGSL: &str = include_str!("../shaders/material_params.wgsl");
+
+/// A parameter of MaterialParamsUniform. Scalars are stored in and read from x.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MaterialParam {
+    Tint,
+    /// Emissive color in rgb, its intensity is EmissiveIntensity.
+    Emissive,
+    EmissiveIntensity,
+    /// 0 is fully visible, 1 fully dissolved.
+    Dissolve,
+    /// One of four free scalars for custom shaders.
+    Custom(u8),
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MaterialParamsUniform {
+    pub tint: [f32; 4],
+    /// rgb color and w intensity.
+    pub emissive: [f32; 4],
+    pub dissolve: f32,
+    _pad: [f32; 3],
+    pub custom: [f32; 4],
+}
+
+impl Default for MaterialParamsUniform {
+    fn default() -> Self {
+        Self {
+            tint: [1.0; 4],
+            emissive: [0.0, 0.0, 0.0, 1.0],
+            dissolve: 0.0,
+            _pad: [0.0; 3],
+            custom: [0.0; 4],
+        }
+    }
+}
+
+impl MaterialParamsUniform {
+    pub fn get(&self, param: MaterialParam) -> [f32; 4] {
+        let scalar = |x| [x, 0.0, 0.0, 0.0];
+        match param {
+            MaterialParam::Tint => self.tint,
+            MaterialParam::Emissive => self.emissive,
+            MaterialParam::EmissiveIntensity => scalar(self.emissive[3]),
+            MaterialParam::Dissolve => scalar(self.dissolve),
+            MaterialParam::Custom(i) => scalar(self.custom[i as usize % 4]),
+        }
+    }
+
+    pub fn set(&mut self, param: MaterialParam, value: [f32; 4]) {
+        match param {
+            MaterialParam::Tint => self.tint = value,
+            MaterialParam::Emissive => {
+                self.emissive = [value[0], value[1], value[2], self.emissive[3]]
+            }
+            MaterialParam::EmissiveIntensity => self.emissive[3] = value[0],
+            MaterialParam::Dissolve => self.dissolve = value[0],
+            MaterialParam::Custom(i) => self.custom[i as usize % 4] = value[0],
+        }
+    }
+}
+
+/// Index of a material's parameters in a MaterialParamStore.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MaterialParamSlot(pub u32);
+
+/// CPU side parameters of every animated material, remembering which changed since the
+/// last upload.
+#[derive(Debug, Clone, Default)]
+pub struct MaterialParamStore {
+    values: Vec<MaterialParamsUniform>,
+    dirty: Vec<bool>,
+}
+
+impl MaterialParamStore {
+    pub fn add(&mut self, values: MaterialParamsUniform) -> MaterialParamSlot {
+        self.values.push(values);
+        self.dirty.push(true);
+        MaterialParamSlot(self.values.len() as u32 - 1)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn values(&self, slot: MaterialParamSlot) -> Option<&MaterialParamsUniform> {
+        self.values.get(slot.0 as usize)
+    }
+
+    /// Sets one parameter, the slot is only marked for upload if the value changed.
+    pub fn set(&mut self, slot: MaterialParamSlot, param: MaterialParam, value: [f32; 4]) {
+        let Some(values) = self.values.get_mut(slot.0 as usize) else {
+            return;
+        };
+        let before = *values;
+        values.set(param, value);
+        if *values != before {
+            self.dirty[slot.0 as usize] = true;
+        }
+    }
+
+    pub fn is_dirty(&self, slot: MaterialParamSlot) -> bool {
+        self.dirty.get(slot.0 as usize).copied().unwrap_or(false)
+    }
+
+    /// Slots changed since the last call, clearing their dirty flag.
+    pub fn take_dirty(&mut self) -> Vec<MaterialParamSlot> {
+        let mut slots = Vec::new();
+        for (i, dirty) in self.dirty.iter_mut().enumerate() {
+            if std::mem::take(dirty) {
+                slots.push(MaterialParamSlot(i as u32));
+            }
+        }
+        slots
+    }
+}
+
+/// Material parameters on the GPU, one dynamic uniform buffer with a slot per material.
+pub struct MaterialParamBuffer {
+    pub store: MaterialParamStore,
+    capacity: u32,
+    stride: u32,
+    buffer: Arc<wgpu::Buffer>,
+    layout: Arc<wgpu::BindGroupLayout>,
+    bind_group: Arc<wgpu::BindGroup>,
+}
+
+impl MaterialParamBuffer {
+    pub fn new(device: &wgpu::Device, capacity: u32) -> Self {
+        let size = std::mem::size_of::<MaterialParamsUniform>() as u32;
+        let stride = size.max(device.limits().min_uniform_buffer_offset_alignment);
+        let capacity = capacity.max(1);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Material Params Buffer"),
+            size: (stride * capacity) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(size as wgpu::BufferAddress),
+                },
+                count: None,
+            }],
+            label: Some("material_params_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(size as wgpu::BufferAddress),
+                }),
+            }],
+            label: Some("material_params_bind_group"),
+        });
+
+        Self {
+            store: MaterialParamStore::default(),
+            capacity,
+            stride,
+            buffer: Arc::new(buffer),
+            layout: Arc::new(layout),
+            bind_group: Arc::new(bind_group),
+        }
+    }
+
+    /// Reserves a slot for a material, fails once `capacity` slots are in use.
+    pub fn add(&mut self, values: MaterialParamsUniform) -> Result<MaterialParamSlot> {
+        if self.store.len() as u32 >= self.capacity {
+            bail!(
+                "MaterialParamBuffer::add => all {} slots are in use",
+                self.capacity
+            );
+        }
+        Ok(self.store.add(values))
+    }
+
+    #[inline]
+    pub fn layout(&self) -> &Arc<wgpu::BindGroupLayout> {
+        &self.layout
+    }
+
+    #[inline]
+    pub fn bind_group(&self) -> Arc<wgpu::BindGroup> {
+        self.bind_group.clone()
+    }
+
+    /// Dynamic offset to bind `slot` with.
+    #[inline]
+    pub fn offset(&self, slot: MaterialParamSlot) -> DynamicOffset {
+        slot.0 * self.stride
+    }
+
+    /// Writes the slots that changed since the last upload, returns how many were written.
+    pub fn upload(&mut self, queue: &wgpu::Queue) -> usize {
+        let dirty = self.store.take_dirty();
+        for &slot in &dirty {
+            if let Some(values) = self.store.values(slot) {
+                queue.write_buffer(
+                    &self.buffer,
+                    self.offset(slot) as wgpu::BufferAddress,
+                    bytemuck::bytes_of(values),
+                );
+            }
+        }
+        dirty.len()
+    }
+}
+
+/// Keyframed values for one material parameter, scalars use x.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamCurve {
+    /// (seconds, value), sorted by time.
+    pub keys: Vec<(f32, [f32; 4])>,
+    pub interpolation: Interpolation,
+    pub looping: bool,
+}
+
+impl ParamCurve {
+    pub fn new(mut keys: Vec<(f32, [f32; 4])>) -> Self {
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            keys,
+            interpolation: Interpolation::default(),
+            looping: false,
+        }
+    }
+
+    /// Curve for a scalar parameter.
+    pub fn scalar(keys: &[(f32, f32)]) -> Self {
+        Self::new(keys.iter().map(|&(t, v)| (t, [v, 0.0, 0.0, 0.0])).collect())
+    }
+
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+
+    #[inline]
+    pub fn duration(&self) -> f32 {
+        self.keys.last().map_or(0.0, |k| k.0)
+    }
+
+    /// Value at `time`, holding the first and last keys outside of their range unless looping.
+    pub fn sample(&self, time: f32) -> Option<[f32; 4]> {
+        let first = self.keys.first()?;
+        let duration = self.duration();
+        let time = if self.looping && duration > 0.0 {
+            time.rem_euclid(duration)
+        } else {
+            time
+        };
+        let next = self.keys.partition_point(|k| k.0 <= time);
+        if next == 0 {
+            return Some(first.1);
+        }
+        let (t0, a) = self.keys[next - 1];
+        let Some(&(t1, b)) = self.keys.get(next) else {
+            return Some(a);
+        };
+        match self.interpolation {
+            Interpolation::Step => Some(a),
+            Interpolation::Linear => {
+                let t = if t1 > t0 {
+                    (time - t0) / (t1 - t0)
+                } else {
+                    1.0
+                };
+                Some(std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ParamAnimation {
+    slot: MaterialParamSlot,
+    param: MaterialParam,
+    curve: ParamCurve,
+    time: f32,
+}
+
+/// Plays curves on material parameters, only materials whose values actually change are
+/// marked for upload.
+#[derive(Debug, Clone, Default)]
+pub struct MaterialAnimator {
+    animations: Vec<ParamAnimation>,
+}
+
+impl MaterialAnimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts playing `curve` on a parameter, replacing any curve already playing on it.
+    pub fn play(&mut self, slot: MaterialParamSlot, param: MaterialParam, curve: ParamCurve) {
+        self.stop(slot, param);
+        self.animations.push(ParamAnimation {
+            slot,
+            param,
+            curve,
+            time: 0.0,
+        });
+    }
+
+    pub fn stop(&mut self, slot: MaterialParamSlot, param: MaterialParam) {
+        self.animations
+            .retain(|a| !(a.slot == slot && a.param == param));
+    }
+
+    /// Stops every curve on a material.
+    pub fn stop_all(&mut self, slot: MaterialParamSlot) {
+        self.animations.retain(|a| a.slot != slot);
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.animations.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.animations.is_empty()
+    }
+
+    /// Advances every curve and writes its value, finished non looping curves are removed
+    /// after writing their last key.
+    pub fn frame_update(&mut self, dt: std::time::Duration, store: &mut MaterialParamStore) {
+        let dt = dt.as_secs_f32();
+        self.animations.retain_mut(|a| {
+            a.time += dt;
+            if let Some(value) = a.curve.sample(a.time) {
+                store.set(a.slot, a.param, value);
+            }
+            a.curve.looping || a.time < a.curve.duration()
+        });
+    }
+}
@@ -0,0 +1,457 @@
+use std::{rc::Rc, sync::Arc};
+
+use cgmath::{ortho, Matrix4, Point3, Rad, Vector3, Vector4};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    eng::{command::RenderPassOp, render::RenderWindow},
+    sys::math::OPENGL_TO_WGPU_MATRIX,
+};
+
+use super::{
+    camera::CameraUniform,
+    cull::Frustum,
+    draw::DrawCtx,
+    overlay::OverlayPipeline,
+    quad::{MaterialSlot, QuadBuffer, Sprite, UvRect},
+    wgpu::texture::Texture,
+};
+
+/// Bit flags choosing what shows up on a minimap, objects opt in with the layers they
+/// belong to and the minimap draws those sharing one with MinimapSettings::layers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct MinimapLayers(pub u32);
+
+impl MinimapLayers {
+    pub const NONE: MinimapLayers = MinimapLayers(0);
+    pub const TERRAIN: MinimapLayers = MinimapLayers(1);
+    pub const STRUCTURES: MinimapLayers = MinimapLayers(1 << 1);
+    pub const ACTORS: MinimapLayers = MinimapLayers(1 << 2);
+    pub const ALL: MinimapLayers = MinimapLayers(u32::MAX);
+
+    #[inline]
+    pub const fn union(self, other: MinimapLayers) -> MinimapLayers {
+        MinimapLayers(self.0 | other.0)
+    }
+
+    #[inline]
+    pub const fn intersects(self, other: MinimapLayers) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinimapSettings {
+    /// Width and height of the map texture in pixels.
+    pub resolution: u32,
+    /// Half the width of the area shown, in world units.
+    pub extent: f32,
+    /// Height of the camera above the focus.
+    pub height: f32,
+    /// Distance below the camera that is still drawn.
+    pub depth: f32,
+    /// The map is rendered every `interval` frames and reused in between.
+    pub interval: u32,
+    /// Keep the focus' heading pointing up instead of -Z.
+    pub rotate: bool,
+    pub layers: MinimapLayers,
+    pub clear_color: wgpu::Color,
+}
+
+impl Default for MinimapSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 256,
+            extent: 50.0,
+            height: 100.0,
+            depth: 200.0,
+            interval: 4,
+            rotate: false,
+            layers: MinimapLayers::ALL,
+            clear_color: wgpu::Color {
+                r: 0.05,
+                g: 0.06,
+                b: 0.08,
+                a: 1.0,
+            },
+        }
+    }
+}
+
+impl MinimapSettings {
+    pub fn with_resolution(mut self, resolution: u32) -> Self {
+        self.resolution = resolution.max(1);
+        self
+    }
+
+    pub fn with_extent(mut self, extent: f32) -> Self {
+        self.extent = extent;
+        self
+    }
+
+    pub fn with_interval(mut self, interval: u32) -> Self {
+        self.interval = interval.max(1);
+        self
+    }
+
+    pub fn with_rotation(mut self, rotate: bool) -> Self {
+        self.rotate = rotate;
+        self
+    }
+
+    pub fn with_layers(mut self, layers: MinimapLayers) -> Self {
+        self.layers = layers;
+        self
+    }
+}
+
+/// Top down orthographic view of the map around `focus`. The top of the map points along
+/// `heading`, measured counter clockwise from -Z when seen from above.
+pub fn minimap_view_proj(
+    focus: Point3<f32>,
+    heading: Rad<f32>,
+    extent: f32,
+    height: f32,
+    depth: f32,
+) -> Matrix4<f32> {
+    let (sin, cos) = heading.0.sin_cos();
+    let up = Vector3::new(-sin, 0.0, -cos);
+    let eye = focus + Vector3::unit_y() * height;
+    let view = Matrix4::look_to_rh(eye, -Vector3::unit_y(), up);
+    let proj = ortho(-extent, extent, -extent, extent, 0.0, height + depth);
+    OPENGL_TO_WGPU_MATRIX * proj * view
+}
+
+/// What a Minimap is currently showing, handed to the draw callback of Minimap::render.
+#[derive(Debug, Clone, Copy)]
+pub struct MinimapView {
+    pub view_proj: Matrix4<f32>,
+    pub layers: MinimapLayers,
+}
+
+impl MinimapView {
+    /// Whether objects on `layers` belong on the map.
+    #[inline]
+    pub fn includes(&self, layers: MinimapLayers) -> bool {
+        self.layers.intersects(layers)
+    }
+
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_view_proj(self.view_proj)
+    }
+
+    /// Where `position` lands on the map, (0, 0) at the bottom left and (1, 1) at the top
+    /// right. Values outside 0 to 1 are off the map.
+    pub fn to_map(&self, position: Point3<f32>) -> [f32; 2] {
+        let clip = self.view_proj * position.to_homogeneous();
+        [(clip.x + 1.0) * 0.5, (clip.y + 1.0) * 0.5]
+    }
+
+    /// Counter clockwise angle on the map of a world space `direction`, 0 points up.
+    pub fn map_angle(&self, direction: Vector3<f32>) -> f32 {
+        let d = self.view_proj * Vector4::new(direction.x, direction.y, direction.z, 0.0);
+        if d.x == 0.0 && d.y == 0.0 {
+            return 0.0;
+        }
+        (-d.x).atan2(d.y)
+    }
+}
+
+/// An overview camera rendering the scene from above into a texture every few frames, see
+/// MinimapWidget for putting it on screen.
+pub struct Minimap {
+    settings: MinimapSettings,
+    target: Rc<Texture>,
+    depth: Rc<Texture>,
+    camera_buffer: Arc<wgpu::Buffer>,
+    camera_bind_group: Arc<wgpu::BindGroup>,
+    view: MinimapView,
+    frame: u32,
+}
+
+impl Minimap {
+    pub fn new(window: &RenderWindow, settings: MinimapSettings) -> Self {
+        let device = window.device();
+        let format = window.surface_config().format;
+        let size = settings.resolution.max(1);
+        let target = Rc::new(Texture::render_target(
+            device,
+            size,
+            size,
+            format,
+            Some("Minimap"),
+        ));
+        let mut depth_config = window.surface_config().clone();
+        depth_config.width = size;
+        depth_config.height = size;
+        let depth = Rc::new(Texture::depth_texture(
+            device,
+            &depth_config,
+            Some("Minimap Depth"),
+        ));
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Minimap Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::default()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let camera = window.camera();
+        let globals = camera.globals_buffer();
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &camera.layout(),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: globals.as_entire_binding(),
+                },
+            ],
+            label: Some("minimap_camera_bind_group"),
+        });
+
+        Self {
+            settings,
+            target,
+            depth,
+            camera_buffer: Arc::new(camera_buffer),
+            camera_bind_group: Arc::new(camera_bind_group),
+            view: MinimapView {
+                view_proj: Matrix4::from_scale(1.0),
+                layers: settings.layers,
+            },
+            frame: 0,
+        }
+    }
+
+    #[inline]
+    pub fn settings(&self) -> &MinimapSettings {
+        &self.settings
+    }
+
+    /// Takes effect on the next render, except resolution which is fixed at creation.
+    pub fn set_settings(&mut self, settings: MinimapSettings) {
+        self.settings = settings;
+    }
+
+    #[inline]
+    pub fn texture(&self) -> &Rc<Texture> {
+        &self.target
+    }
+
+    /// The view of the last render.
+    #[inline]
+    pub fn view(&self) -> &MinimapView {
+        &self.view
+    }
+
+    /// Whether the next render call draws the map, the first one always does.
+    #[inline]
+    pub fn is_due(&self) -> bool {
+        self.frame.is_multiple_of(self.settings.interval.max(1))
+    }
+
+    /// Re-renders the map around `focus` when due, see MinimapSettings::interval. `draw`
+    /// records the simplified scene with the minimap camera bound, skipping objects the
+    /// view doesn't include. Call at the start of draw_frame, returns whether it rendered.
+    pub fn render(
+        &mut self,
+        ctx: &mut DrawCtx,
+        focus: Point3<f32>,
+        heading: Rad<f32>,
+        draw: impl FnOnce(&mut DrawCtx, &MinimapView),
+    ) -> bool {
+        let due = self.is_due();
+        self.frame = self.frame.wrapping_add(1);
+        if !due {
+            return false;
+        }
+        let s = self.settings;
+        let heading = if s.rotate { heading } else { Rad(0.0) };
+        self.view = MinimapView {
+            view_proj: minimap_view_proj(focus, heading, s.extent, s.height, s.depth),
+            layers: s.layers,
+        };
+        let eye = focus + Vector3::unit_y() * s.height;
+        let uniform = CameraUniform::new(&self.view.view_proj.into(), &eye.to_homogeneous().into());
+        ctx.write_buffer(self.camera_buffer.clone(), 0, bytemuck::bytes_of(&uniform));
+
+        let view = self.view;
+        ctx.render_to_texture(
+            &self.target,
+            Some(&self.depth),
+            RenderPassOp::Clear(s.clear_color),
+            self.camera_bind_group.clone(),
+            "Minimap",
+            |ctx| draw(ctx, &view),
+        );
+        true
+    }
+}
+
+/// An icon drawn over the map, e.g. the player or a quest marker.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinimapMarker {
+    pub position: Point3<f32>,
+    /// Icons point up, rotated to match this world space direction if set.
+    pub direction: Option<Vector3<f32>>,
+    /// Region of the widget's icon atlas, the full texture by default.
+    pub icon: UvRect,
+    /// In pixels.
+    pub size: f32,
+    pub color: [f32; 4],
+    /// Pin markers off the map to its edge instead of hiding them.
+    pub clamp_to_edge: bool,
+}
+
+impl MinimapMarker {
+    pub fn new(position: Point3<f32>) -> Self {
+        Self {
+            position,
+            direction: None,
+            icon: UvRect::FULL,
+            size: 12.0,
+            color: [1.0; 4],
+            clamp_to_edge: false,
+        }
+    }
+
+    pub fn with_direction(mut self, direction: Vector3<f32>) -> Self {
+        self.direction = Some(direction);
+        self
+    }
+
+    pub fn with_icon(mut self, icon: UvRect) -> Self {
+        self.icon = icon;
+        self
+    }
+
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn clamped(mut self) -> Self {
+        self.clamp_to_edge = true;
+        self
+    }
+
+    /// Pixel position and rotation inside a map drawn at `min` with `size`, None when the
+    /// marker is off the map and not clamped.
+    pub fn place(
+        &self,
+        view: &MinimapView,
+        min: [f32; 2],
+        size: [f32; 2],
+    ) -> Option<([f32; 2], f32)> {
+        let [mut u, mut v] = view.to_map(self.position);
+        let outside = !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v);
+        if outside && !self.clamp_to_edge {
+            return None;
+        }
+        if outside {
+            // Pull towards the center until it touches the edge.
+            let (dx, dy) = (u - 0.5, v - 0.5);
+            let scale = 0.5 / dx.abs().max(dy.abs());
+            u = 0.5 + dx * scale;
+            v = 0.5 + dy * scale;
+        }
+        let rotation = self.direction.map_or(0.0, |d| view.map_angle(d));
+        Some(([min[0] + u * size[0], min[1] + v * size[1]], rotation))
+    }
+}
+
+/// Draws a Minimap in a screen rectangle with markers composited on top. Positions are in
+/// pixels from the bottom left of the window, draw it in a pass targeting the surface.
+pub struct MinimapWidget {
+    quads: QuadBuffer,
+    icons: MaterialSlot,
+    /// Bottom left corner in pixels.
+    pub position: [f32; 2],
+    /// Size in pixels.
+    pub size: [f32; 2],
+    /// Tints the map, alpha below 1 lets the scene show through.
+    pub tint: [f32; 4],
+    markers: Vec<MinimapMarker>,
+}
+
+impl MinimapWidget {
+    /// `icons` is an atlas markers pick their icon from with MinimapMarker::with_icon,
+    /// without one markers are plain colored squares.
+    pub fn new(
+        window: &RenderWindow,
+        minimap: &Minimap,
+        icons: Option<&Texture>,
+    ) -> anyhow::Result<Self> {
+        let device = window.device();
+        let overlay = OverlayPipeline::new(window)?;
+        let mut quads = QuadBuffer::new(device, 64);
+        quads.set_default_material(overlay.material(device, minimap.texture(), "Minimap"));
+        let icons = quads.add_material(match icons {
+            Some(icons) => overlay.material(device, icons, "Minimap Icons"),
+            None => overlay.solid_material(device, "Minimap Icons"),
+        });
+
+        Ok(Self {
+            quads,
+            icons,
+            position: [16.0, 16.0],
+            size: [200.0, 200.0],
+            tint: [1.0; 4],
+            markers: Vec::new(),
+        })
+    }
+
+    pub fn with_rect(mut self, position: [f32; 2], size: [f32; 2]) -> Self {
+        self.position = position;
+        self.size = size;
+        self
+    }
+
+    #[inline]
+    pub fn markers(&self) -> &[MinimapMarker] {
+        &self.markers
+    }
+
+    /// Markers drawn over the map until cleared.
+    pub fn push_marker(&mut self, marker: MinimapMarker) {
+        self.markers.push(marker);
+    }
+
+    pub fn clear_markers(&mut self) {
+        self.markers.clear();
+    }
+
+    /// Uploads and records the map and its markers as seen in `minimap`'s last render.
+    pub fn draw(&mut self, ctx: &mut DrawCtx, minimap: &Minimap) {
+        let view = minimap.view();
+        self.quads.clear();
+        self.quads.push_sprite(
+            &Sprite::new(self.position, self.size)
+                .with_pivot([0.0, 0.0])
+                .with_color(self.tint),
+        );
+        for marker in self.markers.iter() {
+            if let Some((position, rotation)) = marker.place(view, self.position, self.size) {
+                self.quads.push_sprite(
+                    &Sprite::new(position, [marker.size; 2])
+                        .with_rotation(rotation)
+                        .with_uv(marker.icon)
+                        .with_color(marker.color)
+                        .with_layer(1, 0.0)
+                        .with_material(self.icons),
+                );
+            }
+        }
+        let ds = ctx.device_surface.clone();
+        self.quads.upload(&ds.device, &ds.queue);
+        self.quads.draw(ctx);
+    }
+}
@@ -1,6 +1,39 @@
+pub mod bounds;
 pub mod camera;
+pub mod cluster;
+pub mod compute;
+pub mod crowd;
+pub mod cull;
+pub mod debug_name;
+pub mod decal;
 pub mod draw;
+pub mod environment;
+pub mod font;
+pub mod gizmo;
+pub mod globals;
 pub mod light;
+pub mod lightmap;
+pub mod material_params;
+pub mod minimap;
 pub mod model;
+pub mod outline;
+pub mod overlay;
+pub mod parallax;
+pub mod point_shadow;
+pub mod post;
+pub mod probe;
+pub mod quad;
+pub mod rich_text;
+pub mod scale;
+pub mod scatter;
+pub mod shadow;
+pub mod streaming;
+#[cfg(feature = "ttf")]
+pub mod text;
+pub mod time_of_day;
 pub mod transform;
+pub mod tweak;
+#[cfg(feature = "video")]
+pub mod video;
+pub mod weather;
 pub mod wgpu;
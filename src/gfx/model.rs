@@ -1,18 +1,78 @@
 use std::sync::Arc;
 
-use super::wgpu::texture::Texture;
+use cgmath::Point3;
+use wgpu::util::DeviceExt;
+
+use crate::sys::geom::bounds::Aabb3;
+
+use super::{
+    debug_name::DebugName,
+    material_params::MaterialParamSlot,
+    wgpu::{texture::Texture, vertex::Vertex3D},
+};
 
 pub struct Model {
     pub meshes: Vec<Mesh>,
     pub materials: Vec<Material>,
 }
 
+impl Model {
+    /// Union of every mesh's bounds, in model space.
+    pub fn bounds(&self) -> Aabb3 {
+        self.meshes
+            .iter()
+            .fold(Aabb3::EMPTY, |b, m| b.union(&m.bounds))
+    }
+}
+
 pub struct Mesh {
     pub name: String,
     pub vert_buff: Arc<wgpu::Buffer>,
     pub index_buff: Arc<wgpu::Buffer>,
     pub num_elements: u32,
     pub material: usize, // ???
+    /// Model space bounds of the vertices, computed at load time.
+    pub bounds: Aabb3,
+}
+
+impl Mesh {
+    /// Uploads vertices and indices built at runtime, e.g. procedural geometry.
+    pub fn new(
+        device: &wgpu::Device,
+        name: &str,
+        vertices: &[Vertex3D],
+        indices: &[u32],
+        material: usize,
+    ) -> Self {
+        let vert_buff = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{:?} Vertex Buffer", name)),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buff = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{:?} Index Buffer", name)),
+            contents: bytemuck::cast_slice(indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Self {
+            name: name.to_string(),
+            vert_buff: Arc::new(vert_buff),
+            index_buff: Arc::new(index_buff),
+            num_elements: indices.len() as u32,
+            material,
+            bounds: Aabb3::from_points(vertices.iter().map(|v| Point3::from(v.position))),
+        }
+    }
+}
+
+impl DebugName for Mesh {
+    fn debug_kind(&self) -> &'static str {
+        "Mesh"
+    }
+
+    fn debug_name(&self) -> &str {
+        &self.name
+    }
 }
 
 pub struct Material {
@@ -20,6 +80,10 @@ pub struct Material {
     pub diffuse_texture: Texture,
     pub normal_texture: Texture,
     pub bind_group: Arc<wgpu::BindGroup>,
+    /// Depth bias applied when drawing meshes using this material, e.g. for decals.
+    pub depth_bias: Option<wgpu::DepthBiasState>,
+    /// Animatable parameters in a MaterialParamBuffer, for shaders that read them.
+    pub params: Option<MaterialParamSlot>,
 }
 
 impl Material {
@@ -30,35 +94,87 @@ impl Material {
         layout: &wgpu::BindGroupLayout,
         name: Option<&str>,
     ) -> Self {
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::TextureView(&normal_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 3,
-                    resource: wgpu::BindingResource::Sampler(&normal_texture.sampler),
-                },
-            ],
-            label: name,
-        });
-
-        let bind_group = Arc::new(bind_group);
+        let bind_group =
+            create_material_bind_group(device, &diffuse_texture, &normal_texture, layout, name);
         Self {
             name: String::from(name.unwrap_or_default()),
             diffuse_texture,
             normal_texture,
             bind_group,
+            depth_bias: None,
+            params: None,
         }
     }
+
+    /// Swaps in new textures and rebuilds the bind group, e.g. when TextureStreamer changed
+    /// which mips are resident.
+    pub fn set_textures(
+        &mut self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        diffuse_texture: Texture,
+        normal_texture: Texture,
+    ) {
+        self.bind_group = create_material_bind_group(
+            device,
+            &diffuse_texture,
+            &normal_texture,
+            layout,
+            Some(&self.name),
+        );
+        self.diffuse_texture = diffuse_texture;
+        self.normal_texture = normal_texture;
+    }
+
+    pub fn with_depth_bias(mut self, depth_bias: wgpu::DepthBiasState) -> Self {
+        self.depth_bias = Some(depth_bias);
+        self
+    }
+
+    pub fn with_params(mut self, params: MaterialParamSlot) -> Self {
+        self.params = Some(params);
+        self
+    }
+}
+
+fn create_material_bind_group(
+    device: &wgpu::Device,
+    diffuse_texture: &Texture,
+    normal_texture: &Texture,
+    layout: &wgpu::BindGroupLayout,
+    name: Option<&str>,
+) -> Arc<wgpu::BindGroup> {
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&normal_texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(&normal_texture.sampler),
+            },
+        ],
+        label: name,
+    });
+    Arc::new(bind_group)
+}
+
+impl DebugName for Material {
+    fn debug_kind(&self) -> &'static str {
+        "Material"
+    }
+
+    fn debug_name(&self) -> &str {
+        &self.name
+    }
 }
@@ -0,0 +1,353 @@
+use std::{cell::Cell, rc::Rc, sync::Arc};
+
+use cgmath::{One, Zero};
+use wgpu::{util::DeviceExt, DynamicOffset};
+
+use crate::eng::command::RenderCommand;
+
+use super::wgpu::{
+    buffer::{Instance, InstanceRaw},
+    texture::Texture,
+    vertex::Vertex3D,
+};
+
+/// Draws selection outlines. Outlined meshes are rendered with their outline color into
+/// a mask texture, then a full screen pass dilates the mask and blends the result over the scene.
+pub struct OutlineRenderer {
+    mask: Rc<Texture>,
+    mask_pipeline: Arc<wgpu::RenderPipeline>,
+    composite_pipeline: Arc<wgpu::RenderPipeline>,
+    composite_layout: Arc<wgpu::BindGroupLayout>,
+    composite_bind_group: Arc<wgpu::BindGroup>,
+    params_buffer: Arc<wgpu::Buffer>,
+    color_buffer: Arc<wgpu::Buffer>,
+    color_bind_group: Arc<wgpu::BindGroup>,
+    color_stride: u32,
+    next_color: Cell<u32>,
+    identity_instance: Arc<wgpu::Buffer>,
+}
+
+impl OutlineRenderer {
+    pub const MASK_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+    /// Number of outline colors that can be pushed in a single frame.
+    pub const MAX_OUTLINES: u32 = 64;
+    pub const DEFAULT_WIDTH: f32 = 3.0;
+
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        cam_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        // Each outline color lives at its own dynamic offset in a single uniform buffer.
+        let color_stride = (std::mem::size_of::<[f32; 4]>() as u32)
+            .max(device.limits().min_uniform_buffer_offset_alignment);
+        let color_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Outline Color Buffer"),
+            size: (color_stride * Self::MAX_OUTLINES) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let color_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress
+                    ),
+                },
+                count: None,
+            }],
+            label: Some("outline_color_bind_group_layout"),
+        });
+        let color_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &color_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &color_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(
+                        std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress
+                    ),
+                }),
+            }],
+            label: Some("outline_color_bind_group"),
+        });
+
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Outline Params Buffer"),
+            contents: bytemuck::cast_slice(&[Self::DEFAULT_WIDTH, 0.0, 0.0, 0.0]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let composite_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("outline_composite_bind_group_layout"),
+        });
+
+        let mask_pipeline = {
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Outline Mask Pipeline Layout"),
+                bind_group_layouts: &[Some(cam_bind_group_layout), Some(&color_layout)],
+                immediate_size: 0,
+            });
+            let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Outline Mask Shader"),
+                source: wgpu::ShaderSource::Wgsl(
+                    include_str!("../shaders/outline_mask.wgsl").into(),
+                ),
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Outline Mask Pipeline"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    compilation_options: Default::default(),
+                    buffers: &[
+                        Some(Vertex3D::buffer_layout()),
+                        Some(InstanceRaw::buffer_layout()),
+                    ],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: Self::MASK_FORMAT,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                // The mask ignores scene depth so occluded selections still show their outline.
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview_mask: None,
+                cache: None,
+            })
+        };
+
+        let composite_pipeline = {
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Outline Composite Pipeline Layout"),
+                bind_group_layouts: &[Some(&composite_layout)],
+                immediate_size: 0,
+            });
+            let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("Outline Shader"),
+                source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/outline.wgsl").into()),
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Outline Composite Pipeline"),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_main"),
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: color_format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                // Surface passes always carry a depth attachment, outlines draw on top of it.
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: Texture::DEPTH_FORMAT,
+                    depth_write_enabled: Some(false),
+                    depth_compare: Some(wgpu::CompareFunction::Always),
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview_mask: None,
+                cache: None,
+            })
+        };
+
+        let identity_instance = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Outline Identity Instance"),
+            contents: bytemuck::cast_slice(&[Instance {
+                position: cgmath::Vector3::zero(),
+                rotation: cgmath::Quaternion::one(),
+            }
+            .to_raw()]),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let mask = Rc::new(Texture::render_target(
+            device,
+            width,
+            height,
+            Self::MASK_FORMAT,
+            Some("Outline Mask"),
+        ));
+        let composite_bind_group =
+            create_composite_bind_group(device, &composite_layout, &mask, &params_buffer);
+
+        Self {
+            mask,
+            mask_pipeline: Arc::new(mask_pipeline),
+            composite_pipeline: Arc::new(composite_pipeline),
+            composite_layout: Arc::new(composite_layout),
+            composite_bind_group: Arc::new(composite_bind_group),
+            params_buffer: Arc::new(params_buffer),
+            color_buffer: Arc::new(color_buffer),
+            color_bind_group: Arc::new(color_bind_group),
+            color_stride,
+            next_color: Cell::new(0),
+            identity_instance: Arc::new(identity_instance),
+        }
+    }
+
+    /// Returns a copy of this renderer with a mask matching the new surface size.
+    pub fn resized(&self, device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let mask = Rc::new(Texture::render_target(
+            device,
+            width,
+            height,
+            Self::MASK_FORMAT,
+            Some("Outline Mask"),
+        ));
+        let composite_bind_group =
+            create_composite_bind_group(device, &self.composite_layout, &mask, &self.params_buffer);
+
+        Self {
+            mask,
+            mask_pipeline: self.mask_pipeline.clone(),
+            composite_pipeline: self.composite_pipeline.clone(),
+            composite_layout: self.composite_layout.clone(),
+            composite_bind_group: Arc::new(composite_bind_group),
+            params_buffer: self.params_buffer.clone(),
+            color_buffer: self.color_buffer.clone(),
+            color_bind_group: self.color_bind_group.clone(),
+            color_stride: self.color_stride,
+            next_color: Cell::new(0),
+            identity_instance: self.identity_instance.clone(),
+        }
+    }
+
+    /// Resets the per frame outline colors, called when a DrawCtx is created.
+    pub fn begin_frame(&self) {
+        self.next_color.set(0);
+    }
+
+    /// Sets the outline width in pixels for every outline.
+    pub fn set_width(&self, queue: &wgpu::Queue, width: f32) {
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::cast_slice(&[width.max(0.0), 0.0, 0.0, 0.0]),
+        );
+    }
+
+    /// Uploads an outline color for this frame, returning the dynamic offset to bind it with.
+    /// Returns None once MAX_OUTLINES colors have been pushed.
+    pub fn push_color(&self, queue: &wgpu::Queue, color: wgpu::Color) -> Option<DynamicOffset> {
+        let index = self.next_color.get();
+        if index >= Self::MAX_OUTLINES {
+            return None;
+        }
+        self.next_color.set(index + 1);
+
+        let offset = index * self.color_stride;
+        let color = [
+            color.r as f32,
+            color.g as f32,
+            color.b as f32,
+            color.a as f32,
+        ];
+        queue.write_buffer(
+            &self.color_buffer,
+            offset as wgpu::BufferAddress,
+            bytemuck::cast_slice(&color),
+        );
+        Some(offset)
+    }
+
+    #[inline]
+    pub fn mask(&self) -> &Rc<Texture> {
+        &self.mask
+    }
+
+    #[inline]
+    pub fn mask_pipeline(&self) -> Arc<wgpu::RenderPipeline> {
+        self.mask_pipeline.clone()
+    }
+
+    #[inline]
+    pub fn color_bind_group(&self) -> Arc<wgpu::BindGroup> {
+        self.color_bind_group.clone()
+    }
+
+    #[inline]
+    pub fn identity_instance(&self) -> Arc<wgpu::Buffer> {
+        self.identity_instance.clone()
+    }
+
+    /// Commands that dilate the mask and blend the outlines over the current color target.
+    pub fn composite_commands(&self) -> Vec<RenderCommand> {
+        vec![
+            RenderCommand::SetPipeline(self.composite_pipeline.clone()),
+            RenderCommand::SetBindGroup(0, self.composite_bind_group.clone(), None),
+            RenderCommand::Draw(0..3, 0..1),
+        ]
+    }
+}
+
+fn create_composite_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    mask: &Texture,
+    params_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&mask.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: params_buffer.as_entire_binding(),
+            },
+        ],
+        label: Some("outline_composite_bind_group"),
+    })
+}
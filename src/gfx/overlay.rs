@@ -0,0 +1,229 @@
+use std::sync::Arc;
+
+use wgpu::util::DeviceExt;
+
+use crate::eng::render::RenderWindow;
+
+use super::{
+    quad::QuadMaterial,
+    wgpu::{
+        texture::{Texture, TextureType},
+        vertex::SpriteVertex,
+    },
+};
+
+/// Screen space quads of overlay widgets, see shaders/overlay.wgsl.
+pub const OVERLAY_WGSL: &str = include_str!("../shaders/overlay.wgsl");
+
+/// Pipeline shared by widgets drawn over the scene with a QuadBuffer, like MinimapWidget
+/// and TweakRenderer. Quads are positioned in window pixels from the bottom left corner
+/// and each material samples one texture.
+pub struct OverlayPipeline {
+    pipeline: Arc<wgpu::RenderPipeline>,
+    texture_layout: wgpu::BindGroupLayout,
+    msdf_pipeline: Arc<wgpu::RenderPipeline>,
+    msdf_layout: wgpu::BindGroupLayout,
+    camera: Arc<wgpu::BindGroup>,
+    white: Texture,
+}
+
+impl OverlayPipeline {
+    pub fn new(window: &RenderWindow) -> anyhow::Result<Self> {
+        let device = window.device();
+        let texture_layout = create_texture_layout(device, false);
+        let msdf_layout = create_texture_layout(device, true);
+        let image = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            1,
+            1,
+            image::Rgba([255; 4]),
+        ));
+        let white = Texture::from_image(
+            device,
+            window.device_queue(),
+            &image,
+            TextureType::Diffuse,
+            Some("Overlay White"),
+        )?;
+        let camera_layout = window.camera().layout();
+        let format = window.surface_config().format;
+        let pipeline = Arc::new(create_pipeline(
+            device,
+            format,
+            &camera_layout,
+            &texture_layout,
+            "fs_main",
+        ));
+        let msdf_pipeline = Arc::new(create_pipeline(
+            device,
+            format,
+            &camera_layout,
+            &msdf_layout,
+            "fs_msdf",
+        ));
+        Ok(Self {
+            pipeline,
+            texture_layout,
+            msdf_pipeline,
+            msdf_layout,
+            camera: window.camera_bind_group(),
+            white,
+        })
+    }
+
+    /// Material sampling `texture`, e.g. a map, an icon atlas or a font page.
+    pub fn material(&self, device: &wgpu::Device, texture: &Texture, name: &str) -> QuadMaterial {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.texture_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+            ],
+            label: Some(name),
+        });
+        QuadMaterial::new(self.pipeline.clone())
+            .with_name(name)
+            .with_bind_group(0, self.camera.clone())
+            .with_bind_group(1, Arc::new(bind_group))
+    }
+
+    /// Material drawing text from an MSDF font page, sharp at any scale. `distance_range`
+    /// is the font's BakedFont::distance_range, the quad color tints the glyphs.
+    pub fn msdf_material(
+        &self,
+        device: &wgpu::Device,
+        texture: &Texture,
+        distance_range: f32,
+        name: &str,
+    ) -> QuadMaterial {
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(name),
+            contents: bytemuck::cast_slice(&[distance_range, 0.0, 0.0, 0.0]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.msdf_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params.as_entire_binding(),
+                },
+            ],
+            label: Some(name),
+        });
+        QuadMaterial::new(self.msdf_pipeline.clone())
+            .with_name(name)
+            .with_bind_group(0, self.camera.clone())
+            .with_bind_group(1, Arc::new(bind_group))
+    }
+
+    /// Material for plain colored quads.
+    pub fn solid_material(&self, device: &wgpu::Device, name: &str) -> QuadMaterial {
+        self.material(device, &self.white, name)
+    }
+}
+
+/// Texture and sampler, plus the distance range uniform for MSDF materials.
+fn create_texture_layout(device: &wgpu::Device, msdf: bool) -> wgpu::BindGroupLayout {
+    let mut entries = vec![
+        wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        },
+    ];
+    if msdf {
+        entries.push(wgpu::BindGroupLayoutEntry {
+            binding: 2,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        });
+    }
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &entries,
+        label: Some(if msdf {
+            "overlay_msdf_bind_group_layout"
+        } else {
+            "overlay_texture_bind_group_layout"
+        }),
+    })
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    color_format: wgpu::TextureFormat,
+    camera_layout: &wgpu::BindGroupLayout,
+    texture_layout: &wgpu::BindGroupLayout,
+    fragment: &str,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Overlay Pipeline Layout"),
+        bind_group_layouts: &[Some(camera_layout), Some(texture_layout)],
+        immediate_size: 0,
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Overlay Shader"),
+        source: wgpu::ShaderSource::Wgsl(OVERLAY_WGSL.into()),
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Overlay Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            compilation_options: Default::default(),
+            buffers: &[Some(SpriteVertex::buffer_layout())],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some(fragment),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: color_format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        // Drawn over everything in the surface pass, which has a depth attachment.
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled: Some(false),
+            depth_compare: Some(wgpu::CompareFunction::Always),
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview_mask: None,
+        cache: None,
+    })
+}
@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use super::quad::{MaterialSlot, QuadBuffer, Sprite, UvRect};
+
+/// A textured background layer, see ParallaxBackground.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParallaxLayer {
+    pub material: MaterialSlot,
+    pub uv: UvRect,
+    /// Size of one tile in world units.
+    pub size: [f32; 2],
+    /// Bottom left corner of the first tile while the camera is at the origin.
+    pub offset: [f32; 2],
+    /// How far the layer moves with the camera, 0 stays fixed on screen like a distant
+    /// sky and 1 moves with the world.
+    pub scroll_factor: [f32; 2],
+    /// Scroll speed in world units per second, e.g. for drifting clouds.
+    pub auto_scroll: [f32; 2],
+    /// Tile infinitely along x and y.
+    pub repeat: [bool; 2],
+    pub color: [f32; 4],
+}
+
+impl ParallaxLayer {
+    pub fn new(material: MaterialSlot, size: [f32; 2]) -> Self {
+        Self {
+            material,
+            uv: UvRect::FULL,
+            size,
+            offset: [0.0, 0.0],
+            scroll_factor: [1.0, 1.0],
+            auto_scroll: [0.0, 0.0],
+            repeat: [true, false],
+            color: [1.0; 4],
+        }
+    }
+
+    pub fn with_uv(mut self, uv: UvRect) -> Self {
+        self.uv = uv;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: [f32; 2]) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_scroll_factor(mut self, scroll_factor: [f32; 2]) -> Self {
+        self.scroll_factor = scroll_factor;
+        self
+    }
+
+    pub fn with_auto_scroll(mut self, auto_scroll: [f32; 2]) -> Self {
+        self.auto_scroll = auto_scroll;
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat_x: bool, repeat_y: bool) -> Self {
+        self.repeat = [repeat_x, repeat_y];
+        self
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+/// Stack of parallax layers drawn behind everything else. Layers are pushed into a
+/// QuadBuffer on sprite layers starting at ParallaxBackground::BASE_LAYER, so they sort
+/// before the main sprite pass, back to front in the order they were added.
+#[derive(Debug, Clone, Default)]
+pub struct ParallaxBackground {
+    layers: Vec<ParallaxLayer>,
+    /// Distance travelled by each layer's auto scroll, wrapped to its tile size.
+    scrolled: Vec<[f32; 2]>,
+}
+
+impl ParallaxBackground {
+    pub const BASE_LAYER: i32 = i32::MIN;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a layer in front of the existing ones and returns its index.
+    pub fn add_layer(&mut self, layer: ParallaxLayer) -> usize {
+        self.layers.push(layer);
+        self.scrolled.push([0.0, 0.0]);
+        self.layers.len() - 1
+    }
+
+    pub fn layer_mut(&mut self, index: usize) -> Option<&mut ParallaxLayer> {
+        self.layers.get_mut(index)
+    }
+
+    #[inline]
+    pub fn layers(&self) -> &[ParallaxLayer] {
+        &self.layers
+    }
+
+    pub fn clear(&mut self) {
+        self.layers.clear();
+        self.scrolled.clear();
+    }
+
+    /// Advances the auto scroll of every layer.
+    pub fn frame_update(&mut self, dt: Duration) {
+        let dt = dt.as_secs_f32();
+        for (layer, scrolled) in self.layers.iter().zip(self.scrolled.iter_mut()) {
+            *scrolled = std::array::from_fn(|axis| {
+                let s = scrolled[axis] + layer.auto_scroll[axis] * dt;
+                // Wrapping keeps the offset small on repeating axes, so precision never degrades.
+                if layer.repeat[axis] && layer.size[axis] > 0.0 {
+                    s % layer.size[axis]
+                } else {
+                    s
+                }
+            });
+        }
+    }
+
+    /// Sprites covering the view centered on `camera` with `view_size` world units.
+    pub fn sprites(&self, camera: [f32; 2], view_size: [f32; 2]) -> Vec<Sprite> {
+        let mut sprites = Vec::new();
+        for (i, (layer, scrolled)) in self.layers.iter().zip(&self.scrolled).enumerate() {
+            if layer.size[0] <= 0.0 || layer.size[1] <= 0.0 {
+                continue;
+            }
+            let origin: [f32; 2] = std::array::from_fn(|axis| {
+                layer.offset[axis]
+                    + camera[axis] * (1.0 - layer.scroll_factor[axis])
+                    + scrolled[axis]
+            });
+            // Range of tile indices overlapping the view along each axis.
+            let tiles = |axis: usize| {
+                if !layer.repeat[axis] {
+                    return 0..1;
+                }
+                let min = camera[axis] - view_size[axis] * 0.5 - origin[axis];
+                let max = camera[axis] + view_size[axis] * 0.5 - origin[axis];
+                let first = (min / layer.size[axis]).floor() as i64;
+                let last = (max / layer.size[axis]).ceil() as i64;
+                first..last.max(first + 1)
+            };
+            for y in tiles(1) {
+                for x in tiles(0) {
+                    let position = [
+                        origin[0] + x as f32 * layer.size[0],
+                        origin[1] + y as f32 * layer.size[1],
+                    ];
+                    sprites.push(
+                        Sprite::new(position, layer.size)
+                            .with_pivot([0.0, 0.0])
+                            .with_uv(layer.uv)
+                            .with_color(layer.color)
+                            .with_layer(Self::BASE_LAYER + i as i32, 0.0)
+                            .with_material(layer.material),
+                    );
+                }
+            }
+        }
+        sprites
+    }
+
+    /// Pushes the visible tiles of every layer, they sort behind the scene's sprites on upload.
+    pub fn push(&self, quads: &mut QuadBuffer, camera: [f32; 2], view_size: [f32; 2]) {
+        for sprite in self.sprites(camera, view_size) {
+            quads.push_sprite(&sprite);
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use std::{rc::Rc, sync::Arc};
+
+use cgmath::{Deg, EuclideanSpace, InnerSpace, Matrix4, Point3, Vector3};
+
+use crate::{eng::command::RenderCommand, sys::math::OPENGL_TO_WGPU_MATRIX};
+
+use super::{
+    cluster::PointLight,
+    draw::DrawCtx,
+    shadow::{push_casters, ShadowCaster},
+    wgpu::{buffer::InstanceRaw, texture::Texture, vertex::Vertex3D},
+};
+
+/// Look direction and up vector of each cube face in wgpu's layer order (+X, -X, +Y, -Y, +Z,
+/// -Z). Unlike the probe faces these are sampled on the GPU, so they follow the cube sampling
+/// convention: the view is mirrored along x by cube_face_view_proj.
+const FACES: [([f32; 3], [f32; 3]); 6] = [
+    ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ([-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ([0.0, 1.0, 0.0], [0.0, 0.0, -1.0]),
+    ([0.0, -1.0, 0.0], [0.0, 0.0, 1.0]),
+    ([0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+    ([0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+];
+
+/// View projection rendering `face` of a shadow cube centered on `position`.
+pub fn cube_face_view_proj(
+    position: Point3<f32>,
+    face: usize,
+    near: f32,
+    far: f32,
+) -> Matrix4<f32> {
+    let (dir, up) = FACES[face];
+    let view = Matrix4::look_to_rh(position, Vector3::from(dir), Vector3::from(up));
+    let proj = cgmath::perspective(Deg(90.0), 1.0, near, far);
+    let mirror = Matrix4::from_nonuniform_scale(-1.0, 1.0, 1.0);
+    OPENGL_TO_WGPU_MATRIX * mirror * proj * view
+}
+
+/// Indices of the shadow casting `lights` nearest to `viewer`, at most `budget` of them,
+/// nearest first.
+pub fn select_shadow_lights(
+    lights: &[PointLight],
+    viewer: Point3<f32>,
+    budget: usize,
+) -> Vec<usize> {
+    let mut candidates = lights
+        .iter()
+        .enumerate()
+        .filter(|(_, light)| light.casts_shadows && light.radius > 0.0)
+        .map(|(i, light)| (i, (light.position - viewer).magnitude2()))
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+    candidates
+        .into_iter()
+        .take(budget)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PointShadowSettings {
+    /// Lights casting shadows each frame, the nearest to the camera win. Each costs six
+    /// depth passes.
+    pub max_lights: usize,
+    /// Width and height of each cube face.
+    pub resolution: u32,
+    /// Near plane of the face projections.
+    pub near: f32,
+    /// Subtracted from the receiver's distance over the light radius, against shadow acne.
+    pub depth_bias: f32,
+    /// World units receivers are pushed along their normal before the lookup.
+    pub normal_bias: f32,
+}
+
+impl Default for PointShadowSettings {
+    fn default() -> Self {
+        Self {
+            max_lights: 4,
+            resolution: 512,
+            near: 0.05,
+            depth_bias: 0.01,
+            normal_bias: 0.02,
+        }
+    }
+}
+
+impl PointShadowSettings {
+    pub fn with_max_lights(mut self, max_lights: usize) -> Self {
+        self.max_lights = max_lights;
+        self
+    }
+
+    pub fn with_resolution(mut self, resolution: u32) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    pub fn with_depth_bias(mut self, depth_bias: f32) -> Self {
+        self.depth_bias = depth_bias;
+        self
+    }
+
+    /// The cube array keeps one slot even with shadows off so it can always be bound.
+    fn slots(&self) -> usize {
+        self.max_lights.max(1)
+    }
+}
+
+/// See PointShadowFace in point_shadow_depth.wgsl.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct FaceUniform {
+    view_proj: [[f32; 4]; 4],
+    light: [f32; 4],
+}
+
+/// Cube shadow maps for the point lights nearest the camera, owned by ClusteredLights, see
+/// ClusteredLights::with_shadows. Faces are rendered one depth pass each: wgpu's multiview
+/// is too narrowly supported to rely on for layered rendering.
+#[derive(Debug)]
+pub struct PointShadows {
+    settings: PointShadowSettings,
+    selected: Vec<usize>,
+    faces: Vec<Rc<Texture>>,
+    cube_view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    _map: wgpu::Texture,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    face_buffers: Vec<Arc<wgpu::Buffer>>,
+    face_bind_groups: Vec<Arc<wgpu::BindGroup>>,
+}
+
+impl PointShadows {
+    pub fn new(device: &wgpu::Device, settings: PointShadowSettings) -> Self {
+        let layers = settings.slots() * FACES.len();
+        let map = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Point Shadow Map"),
+            size: wgpu::Extent3d {
+                width: settings.resolution,
+                height: settings.resolution,
+                depth_or_array_layers: layers as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Texture::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Point Shadow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+        let faces = (0..layers as u32)
+            .map(|layer| {
+                let view = map.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Point Shadow Face"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                });
+                Rc::new(Texture {
+                    handle: map.clone(),
+                    view,
+                    sampler: sampler.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let cube_view = map.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Point Shadow Cubes"),
+            dimension: Some(wgpu::TextureViewDimension::CubeArray),
+            array_layer_count: Some(layers as u32),
+            ..Default::default()
+        });
+
+        let face_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("point_shadow_face_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let face_buffers = (0..layers)
+            .map(|_| {
+                Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Point Shadow Face Uniform"),
+                    size: std::mem::size_of::<FaceUniform>() as u64,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }))
+            })
+            .collect::<Vec<_>>();
+        let face_bind_groups = face_buffers
+            .iter()
+            .map(|buffer| {
+                Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("point_shadow_face_bind_group"),
+                    layout: &face_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                }))
+            })
+            .collect();
+
+        Self {
+            settings,
+            selected: Vec::new(),
+            faces,
+            cube_view,
+            sampler,
+            _map: map,
+            pipeline: Arc::new(create_depth_pipeline(device, &face_layout)),
+            face_buffers,
+            face_bind_groups,
+        }
+    }
+
+    #[inline]
+    pub fn settings(&self) -> &PointShadowSettings {
+        &self.settings
+    }
+
+    /// Indices into the lights of the last update that cast shadows, the shadow slot of each
+    /// is its position here.
+    #[inline]
+    pub fn selected(&self) -> &[usize] {
+        &self.selected
+    }
+
+    #[inline]
+    pub(crate) fn cube_view(&self) -> &wgpu::TextureView {
+        &self.cube_view
+    }
+
+    #[inline]
+    pub(crate) fn sampler(&self) -> &wgpu::Sampler {
+        &self.sampler
+    }
+
+    /// Picks the lights that cast shadows this frame and uploads their face projections.
+    pub fn update(&mut self, ctx: &mut DrawCtx, viewer: Point3<f32>, lights: &[PointLight]) {
+        self.selected = select_shadow_lights(lights, viewer, self.settings.max_lights);
+        for (slot, &i) in self.selected.iter().enumerate() {
+            let light = &lights[i];
+            let far = light.radius.max(self.settings.near * 2.0);
+            for face in 0..FACES.len() {
+                let uniform = FaceUniform {
+                    view_proj: cube_face_view_proj(light.position, face, self.settings.near, far)
+                        .into(),
+                    light: light.position.to_vec().extend(light.radius).into(),
+                };
+                ctx.write_buffer(
+                    self.face_buffers[slot * FACES.len() + face].clone(),
+                    0,
+                    bytemuck::bytes_of(&uniform),
+                );
+            }
+        }
+    }
+
+    /// Shadow slot, depth bias and normal bias for the light at `index` in the last update,
+    /// see PointLightRaw::shadow.
+    pub(crate) fn raw_params(&self, index: usize) -> [f32; 4] {
+        let slot = self
+            .selected
+            .iter()
+            .position(|&i| i == index)
+            .map(|slot| slot as f32)
+            .unwrap_or(-1.0);
+        [
+            slot,
+            self.settings.depth_bias,
+            self.settings.normal_bias,
+            0.0,
+        ]
+    }
+
+    /// Records six depth passes per selected light drawing every caster. Passes run before the
+    /// frame's other render passes.
+    pub fn render(&self, ctx: &mut DrawCtx, casters: &[ShadowCaster]) {
+        for slot in 0..self.selected.len() {
+            for face in 0..FACES.len() {
+                let layer = slot * FACES.len() + face;
+                let label = format!("Point Shadow {} Face {}", slot, face);
+                let pass = ctx.begin_shadow_pass(&self.faces[layer], &label);
+                pass.command_queue.extend([
+                    RenderCommand::SetPipeline(self.pipeline.clone()),
+                    RenderCommand::SetBindGroup(0, self.face_bind_groups[layer].clone(), None),
+                ]);
+                push_casters(&mut pass.command_queue, casters);
+            }
+        }
+    }
+}
+
+fn create_depth_pipeline(
+    device: &wgpu::Device,
+    face_layout: &wgpu::BindGroupLayout,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Point Shadow Pipeline Layout"),
+        bind_group_layouts: &[Some(face_layout)],
+        immediate_size: 0,
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Point Shadow Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/point_shadow_depth.wgsl").into()),
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Point Shadow Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            compilation_options: Default::default(),
+            buffers: &[
+                Some(Vertex3D::buffer_layout()),
+                Some(InstanceRaw::buffer_layout()),
+            ],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            compilation_options: Default::default(),
+            targets: &[],
+        }),
+        // The mirrored face projections flip the winding, so no culling.
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled: Some(true),
+            depth_compare: Some(wgpu::CompareFunction::LessEqual),
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview_mask: None,
+        cache: None,
+    })
+}
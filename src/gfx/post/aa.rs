@@ -0,0 +1,150 @@
+use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+use wgpu::util::DeviceExt;
+
+use super::{EffectBindGroup, PostEffect, PostProcessor};
+
+/// Name the FXAA effect is registered under in the post stack.
+pub const FXAA_EFFECT: &str = "fxaa";
+
+/// Anti-aliasing applied by the post stack. Unlike MSAA it works on the final image, so it
+/// also smooths edges produced by other post effects.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AntiAliasMode {
+    #[default]
+    Off,
+    Fxaa(FxaaSettings),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FxaaSettings {
+    /// How much subpixel aliasing is blended away, 0 keeps the image sharpest.
+    pub subpixel: f32,
+    /// Smallest local contrast, relative to the brightest neighbour, treated as an edge.
+    pub edge_threshold: f32,
+    /// Contrast below this is never an edge, keeps dark areas from being blurred.
+    pub edge_threshold_min: f32,
+}
+
+impl FxaaSettings {
+    pub const LOW: FxaaSettings = FxaaSettings {
+        subpixel: 0.5,
+        edge_threshold: 0.25,
+        edge_threshold_min: 0.0833,
+    };
+    pub const MEDIUM: FxaaSettings = FxaaSettings {
+        subpixel: 0.75,
+        edge_threshold: 0.166,
+        edge_threshold_min: 0.0625,
+    };
+    pub const HIGH: FxaaSettings = FxaaSettings {
+        subpixel: 1.0,
+        edge_threshold: 0.125,
+        edge_threshold_min: 0.0312,
+    };
+}
+
+impl Default for FxaaSettings {
+    fn default() -> Self {
+        Self::MEDIUM
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct FxaaParams {
+    subpixel: f32,
+    edge_threshold: f32,
+    edge_threshold_min: f32,
+    _pad: f32,
+}
+
+impl From<FxaaSettings> for FxaaParams {
+    fn from(s: FxaaSettings) -> Self {
+        Self {
+            subpixel: s.subpixel.clamp(0.0, 1.0),
+            edge_threshold: s.edge_threshold.max(0.0),
+            edge_threshold_min: s.edge_threshold_min.max(0.0),
+            _pad: 0.0,
+        }
+    }
+}
+
+/// Owns the anti-aliasing resources. Push AntiAliasing::effect into the PostProcessor
+/// last, so it runs on the final image, then pick a mode with set_mode.
+pub struct AntiAliasing {
+    mode: AntiAliasMode,
+    layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    bind_group: EffectBindGroup,
+}
+
+impl AntiAliasing {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("FXAA Params Buffer"),
+            contents: bytemuck::cast_slice(&[FxaaParams::from(FxaaSettings::default())]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("fxaa_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params_buffer.as_entire_binding(),
+            }],
+            label: Some("fxaa_bind_group"),
+        });
+
+        Self {
+            mode: AntiAliasMode::Off,
+            layout,
+            params_buffer,
+            bind_group: Rc::new(RefCell::new(Arc::new(bind_group))),
+        }
+    }
+
+    /// Builds the effect to push into the post stack, disabled until a mode is set.
+    pub fn effect(&self, device: &wgpu::Device, post: &PostProcessor) -> PostEffect {
+        let mut effect = PostEffect::new(
+            device,
+            post,
+            FXAA_EFFECT,
+            include_str!("../../shaders/post_fxaa.wgsl"),
+            Some(&self.layout),
+            Some(self.bind_group.clone()),
+        );
+        effect.enabled = matches!(self.mode, AntiAliasMode::Fxaa(_));
+        effect
+    }
+
+    #[inline]
+    pub const fn mode(&self) -> AntiAliasMode {
+        self.mode
+    }
+
+    /// Switches modes, enabling or disabling the effect in `post`.
+    pub fn set_mode(&mut self, queue: &wgpu::Queue, post: &mut PostProcessor, mode: AntiAliasMode) {
+        self.mode = mode;
+        if let AntiAliasMode::Fxaa(settings) = mode {
+            queue.write_buffer(
+                &self.params_buffer,
+                0,
+                bytemuck::cast_slice(&[FxaaParams::from(settings)]),
+            );
+        }
+        post.set_enabled(FXAA_EFFECT, matches!(mode, AntiAliasMode::Fxaa(_)));
+    }
+}
@@ -0,0 +1,459 @@
+use std::{cell::RefCell, rc::Rc, sync::Arc, time::Duration};
+
+use wgpu::util::DeviceExt;
+
+use super::{EffectBindGroup, PostEffect, PostProcessor};
+
+/// Name the accessibility effect is registered under in the post stack.
+pub const ACCESSIBILITY_EFFECT: &str = "accessibility";
+
+const ACCESSIBILITY_WGSL: &str = include_str!("../../shaders/post_accessibility.wgsl");
+const HISTORY_WGSL: &str = include_str!("../../shaders/post_accessibility_history.wgsl");
+const NO_HISTORY_WGSL: &str = "fn limit_flash(rgb: vec3<f32>, pixel: vec2<u32>) -> vec3<f32> {
+    return rgb;
+}";
+
+/// Color vision deficiency the color filter targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorVision {
+    #[default]
+    Normal,
+    /// Missing or weak red cones.
+    Protan,
+    /// Missing or weak green cones.
+    Deutan,
+    /// Missing or weak blue cones.
+    Tritan,
+}
+
+impl ColorVision {
+    pub const ALL: [ColorVision; 4] = [Self::Normal, Self::Protan, Self::Deutan, Self::Tritan];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Protan => "protan",
+            Self::Deutan => "deutan",
+            Self::Tritan => "tritan",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|v| v.name() == name)
+    }
+
+    /// Full severity simulation in linear RGB (Machado et al. 2009), rows of a 3x3 matrix.
+    pub const fn simulation(self) -> [[f32; 3]; 3] {
+        match self {
+            Self::Normal => IDENTITY,
+            Self::Protan => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Self::Deutan => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Self::Tritan => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+}
+
+/// What the color filter does for the chosen ColorVision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorFilterMode {
+    /// Shows the frame as seen with the deficiency, to check a game's palette.
+    Simulate,
+    /// Shifts the colors that are lost into ones that are still told apart (daltonization).
+    #[default]
+    Correct,
+}
+
+impl ColorFilterMode {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Simulate => "simulate",
+            Self::Correct => "correct",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Simulate, Self::Correct]
+            .into_iter()
+            .find(|m| m.name() == name)
+    }
+}
+
+const IDENTITY: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+/// Accessibility options applied to every frame by RenderWindow, stored in
+/// EngineConfig::accessibility. The UI scale is EngineConfig::ui_scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccessibilitySettings {
+    pub color_vision: ColorVision,
+    pub color_filter: ColorFilterMode,
+    /// 0 leaves colors unchanged, 1 applies the full filter.
+    pub filter_strength: f32,
+    /// Limits how fast any pixel can brighten, to tone down flashes and strobing.
+    pub reduced_flash: bool,
+    /// Largest brightening in reduced flash mode, in stops (doublings of luminance) per second.
+    pub max_flash_rate: f32,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            color_vision: ColorVision::Normal,
+            color_filter: ColorFilterMode::default(),
+            filter_strength: 1.0,
+            reduced_flash: false,
+            max_flash_rate: 4.0,
+        }
+    }
+}
+
+impl AccessibilitySettings {
+    /// False if the effect would leave every frame unchanged.
+    pub fn is_active(&self) -> bool {
+        self.reduced_flash
+            || (self.color_vision != ColorVision::Normal && self.filter_strength > 0.0)
+    }
+
+    /// Rows of the matrix the color filter applies to linear RGB.
+    pub fn color_matrix(&self) -> [[f32; 3]; 3] {
+        let s = self.color_vision.simulation();
+        let filter = match self.color_filter {
+            ColorFilterMode::Simulate => s,
+            ColorFilterMode::Correct => {
+                // Moves the error between the original and simulated color into the
+                // channels the deficiency leaves intact: I + shift * (I - S).
+                let shift = match self.color_vision {
+                    ColorVision::Tritan => [[1.0, 0.0, 0.7], [0.0, 1.0, 0.7], [0.0, 0.0, 0.0]],
+                    _ => [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]],
+                };
+                let mut m = IDENTITY;
+                for (r, row) in m.iter_mut().enumerate() {
+                    for (c, value) in row.iter_mut().enumerate() {
+                        *value += (0..3)
+                            .map(|k| shift[r][k] * (IDENTITY[k][c] - s[k][c]))
+                            .sum::<f32>();
+                    }
+                }
+                m
+            }
+        };
+        let t = self.filter_strength.clamp(0.0, 1.0);
+        let mut m = IDENTITY;
+        for (r, row) in m.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value += (filter[r][c] - IDENTITY[r][c]) * t;
+            }
+        }
+        m
+    }
+
+    /// Applies an `accessibility.<field>` config entry, returns false if the key isn't an
+    /// accessibility setting.
+    pub fn set(&mut self, key: &str, value: &str) -> anyhow::Result<bool> {
+        let Some(field) = key.strip_prefix("accessibility.") else {
+            return Ok(false);
+        };
+        match field {
+            "color_vision" => {
+                self.color_vision = ColorVision::from_name(value).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "AccessibilitySettings::set => unknown color vision {}",
+                        value
+                    )
+                })?
+            }
+            "color_filter" => {
+                self.color_filter = ColorFilterMode::from_name(value).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "AccessibilitySettings::set => unknown color filter {}",
+                        value
+                    )
+                })?
+            }
+            "filter_strength" => self.filter_strength = value.parse()?,
+            "reduced_flash" => self.reduced_flash = value.parse()?,
+            "max_flash_rate" => self.max_flash_rate = value.parse()?,
+            _ => anyhow::bail!("AccessibilitySettings::set => unknown setting {}", field),
+        }
+        Ok(true)
+    }
+
+    /// `key = value` lines read back by AccessibilitySettings::set.
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let key = |field: &str| format!("accessibility.{}", field);
+        vec![
+            (key("color_vision"), self.color_vision.name().to_string()),
+            (key("color_filter"), self.color_filter.name().to_string()),
+            (key("filter_strength"), self.filter_strength.to_string()),
+            (key("reduced_flash"), self.reduced_flash.to_string()),
+            (key("max_flash_rate"), self.max_flash_rate.to_string()),
+        ]
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct AccessibilityParams {
+    color: [[f32; 4]; 3],
+    max_change: f32,
+    reduced_flash: u32,
+    reset: u32,
+    _pad: f32,
+}
+
+/// Owns the accessibility post effect, pushed onto the post stack by RenderWindow and
+/// only enabled while AccessibilitySettings::is_active. It keeps itself last in the
+/// stack so effects added later are filtered too.
+pub struct Accessibility {
+    settings: AccessibilitySettings,
+    /// False where fragment shaders can't write storage textures, e.g. WebGL.
+    history_supported: bool,
+    layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    history_size: (u32, u32),
+    /// Luminance after limiting is kept in two textures, every frame reads one and
+    /// writes the other through these.
+    history_bind_groups: Vec<Arc<wgpu::BindGroup>>,
+    frame: usize,
+    reset: bool,
+    bind_group: EffectBindGroup,
+}
+
+impl Accessibility {
+    /// Creates the effect resources and pushes the effect onto `post`.
+    pub fn new(
+        device: &wgpu::Device,
+        post: &mut PostProcessor,
+        settings: AccessibilitySettings,
+    ) -> Self {
+        let history_supported = device.limits().max_storage_textures_per_shader_stage > 0;
+        if settings.reduced_flash && !history_supported {
+            log::warn!("Accessibility::new => reduced flash is not supported on this device");
+        }
+
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Accessibility Params Buffer"),
+            contents: bytemuck::bytes_of(&params(&settings, Duration::ZERO, true)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let mut entries = vec![wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }];
+        if history_supported {
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                },
+                count: None,
+            });
+            entries.push(wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::StorageTexture {
+                    access: wgpu::StorageTextureAccess::WriteOnly,
+                    format: HISTORY_FORMAT,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                },
+                count: None,
+            });
+        }
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: Some("accessibility_bind_group_layout"),
+        });
+
+        let size = post.size();
+        let history_bind_groups =
+            create_history(device, &layout, &params_buffer, history_supported, size);
+        let bind_group = Rc::new(RefCell::new(history_bind_groups[0].clone()));
+        let accessibility = Self {
+            settings,
+            history_supported,
+            layout,
+            params_buffer,
+            history_size: size,
+            history_bind_groups,
+            frame: 0,
+            reset: true,
+            bind_group,
+        };
+
+        let history = if history_supported {
+            HISTORY_WGSL
+        } else {
+            NO_HISTORY_WGSL
+        };
+        let mut effect = PostEffect::new(
+            device,
+            post,
+            ACCESSIBILITY_EFFECT,
+            &format!("{}\n{}", history, ACCESSIBILITY_WGSL),
+            Some(&accessibility.layout),
+            Some(accessibility.bind_group.clone()),
+        );
+        effect.enabled = settings.is_active();
+        post.push_effect(effect);
+        accessibility
+    }
+
+    #[inline]
+    pub fn settings(&self) -> &AccessibilitySettings {
+        &self.settings
+    }
+
+    /// Takes effect on the next Accessibility::update.
+    pub fn set_settings(&mut self, settings: AccessibilitySettings) {
+        if settings.reduced_flash && !self.settings.reduced_flash {
+            self.reset = true;
+        }
+        self.settings = settings;
+    }
+
+    /// Writes the settings and swaps the history textures, call once per frame.
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        post: &mut PostProcessor,
+        dt: Duration,
+    ) {
+        let active = self.settings.is_active();
+        post.set_enabled(ACCESSIBILITY_EFFECT, active);
+        // Effects pushed after this one would escape the filter.
+        if post.effect_index(ACCESSIBILITY_EFFECT) != Some(post.effects().len() - 1) {
+            if let Some(effect) = post.remove_effect(ACCESSIBILITY_EFFECT) {
+                post.push_effect(effect);
+            }
+        }
+        if !active {
+            self.reset = true;
+            return;
+        }
+        if post.size() != self.history_size {
+            self.resize_history(device, post.size());
+        }
+        let reset = std::mem::take(&mut self.reset);
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::bytes_of(&params(&self.settings, dt, reset)),
+        );
+        self.frame = self.frame.wrapping_add(1);
+        *self.bind_group.borrow_mut() =
+            self.history_bind_groups[self.frame % self.history_bind_groups.len()].clone();
+    }
+
+    fn resize_history(&mut self, device: &wgpu::Device, size: (u32, u32)) {
+        self.history_bind_groups = create_history(
+            device,
+            &self.layout,
+            &self.params_buffer,
+            self.history_supported,
+            size,
+        );
+        self.history_size = size;
+        self.reset = true;
+    }
+}
+
+/// A bind group for each way of reading one history texture and writing the other, or
+/// one with just the params without history support.
+fn create_history(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    params_buffer: &wgpu::Buffer,
+    supported: bool,
+    (width, height): (u32, u32),
+) -> Vec<Arc<wgpu::BindGroup>> {
+    let params = wgpu::BindGroupEntry {
+        binding: 0,
+        resource: params_buffer.as_entire_binding(),
+    };
+    if !supported {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[params],
+            label: Some("accessibility_bind_group"),
+        });
+        return vec![Arc::new(bind_group)];
+    }
+
+    let history: Vec<_> = ["Accessibility History A", "Accessibility History B"]
+        .into_iter()
+        .map(|label| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: width.max(1),
+                        height: height.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: HISTORY_FORMAT,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::STORAGE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        })
+        .collect();
+    // Each frame reads the history the previous one wrote.
+    [0, 1]
+        .map(|read| {
+            Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout,
+                entries: &[
+                    params.clone(),
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&history[read]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&history[1 - read]),
+                    },
+                ],
+                label: Some("accessibility_bind_group"),
+            }))
+        })
+        .to_vec()
+}
+
+const HISTORY_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Float;
+
+fn params(settings: &AccessibilitySettings, dt: Duration, reset: bool) -> AccessibilityParams {
+    let m = settings.color_matrix();
+    AccessibilityParams {
+        color: m.map(|[r, g, b]| [r, g, b, 0.0]),
+        max_change: settings.max_flash_rate.max(0.0) * dt.as_secs_f32(),
+        reduced_flash: settings.reduced_flash as u32,
+        reset: reset as u32,
+        _pad: 0.0,
+    }
+}
@@ -0,0 +1,209 @@
+use std::{cell::RefCell, rc::Rc, sync::Arc, time::Duration};
+
+use anyhow::*;
+use wgpu::naga;
+use wgpu::util::DeviceExt;
+
+use super::{EffectBindGroup, PostEffect, PostProcessor, POST_COMMON_WGSL};
+
+/// Declarations every custom effect can use on top of post_common.wgsl, see
+/// shaders/post_custom.wgsl.
+pub const POST_CUSTOM_WGSL: &str = include_str!("../../shaders/post_custom.wgsl");
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct PostFrame {
+    resolution: [f32; 2],
+    time: f32,
+    delta_time: f32,
+}
+
+/// What reflection found in a custom effect's WGSL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CustomEffectInfo {
+    /// Size in bytes of the uniform block at @group(1) @binding(1), 0 if there is none.
+    pub params_size: u32,
+}
+
+/// Parses and validates a custom effect against the post interface. The source must
+/// provide a fragment `fs_main` and may only bind the input texture, the frame uniform
+/// and one uniform block of its own.
+pub fn validate_custom_effect(source: &str) -> Result<CustomEffectInfo> {
+    let full = custom_source(source);
+    let module = naga::front::wgsl::parse_str(&full)
+        .map_err(|e| anyhow!("validate_custom_effect => {}", e.emit_to_string(&full)))?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::default(),
+    )
+    .validate(&module)
+    .map_err(|e| anyhow!("validate_custom_effect => {}", e.emit_to_string(&full)))?;
+
+    if !module
+        .entry_points
+        .iter()
+        .any(|ep| ep.name == "fs_main" && ep.stage == naga::ShaderStage::Fragment)
+    {
+        bail!("validate_custom_effect => missing @fragment fn fs_main");
+    }
+
+    let mut params_size = 0;
+    for (_, var) in module.global_variables.iter() {
+        let Some(binding) = var.binding.as_ref() else {
+            continue;
+        };
+        let name = var.name.as_deref().unwrap_or("_");
+        match (binding.group, binding.binding) {
+            (0, 0) | (0, 1) | (1, 0) => {}
+            (1, 1) if var.space == naga::AddressSpace::Uniform => {
+                params_size = module.types[var.ty].inner.size(module.to_ctx());
+            }
+            (1, 1) => bail!("validate_custom_effect => {} at @group(1) @binding(1) must be var<uniform>", name),
+            (group, binding) => bail!(
+                "validate_custom_effect => {} at @group({}) @binding({}) is not part of the post interface",
+                name,
+                group,
+                binding
+            ),
+        }
+    }
+    Ok(CustomEffectInfo { params_size })
+}
+
+fn custom_source(source: &str) -> String {
+    format!("{}\n{}\n{}", POST_COMMON_WGSL, POST_CUSTOM_WGSL, source)
+}
+
+/// A post effect written by the user. Owns the frame uniform and the effect's own
+/// uniform block, insert CustomEffect::effect anywhere in the PostProcessor and call
+/// frame_update once per frame.
+pub struct CustomEffect {
+    name: String,
+    source: String,
+    info: CustomEffectInfo,
+    time: Duration,
+    layout: wgpu::BindGroupLayout,
+    frame_buffer: wgpu::Buffer,
+    params_buffer: wgpu::Buffer,
+    bind_group: EffectBindGroup,
+}
+
+impl CustomEffect {
+    /// Validates `source` and creates its buffers, `params` is the initial value of its
+    /// uniform block and must match the block's size.
+    pub fn new(device: &wgpu::Device, name: &str, source: &str, params: &[u8]) -> Result<Self> {
+        let info = validate_custom_effect(source)
+            .with_context(|| format!("CustomEffect::new => invalid effect {}", name))?;
+        if params.len() != info.params_size as usize {
+            bail!(
+                "CustomEffect::new => {} expects {} bytes of params, got {}",
+                name,
+                info.params_size,
+                params.len()
+            );
+        }
+
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[uniform_entry(0), uniform_entry(1)],
+            label: Some("custom_effect_bind_group_layout"),
+        });
+        let frame_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Custom Effect Frame Buffer"),
+            contents: bytemuck::bytes_of(&<PostFrame as bytemuck::Zeroable>::zeroed()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        // Effects without a uniform block still get a small buffer to fill the slot.
+        let mut contents = params.to_vec();
+        contents.resize(params.len().max(16).next_multiple_of(16), 0);
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Custom Effect Params Buffer"),
+            contents: &contents,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: frame_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("custom_effect_bind_group"),
+        });
+
+        Ok(Self {
+            name: String::from(name),
+            source: format!("{}\n{}", POST_CUSTOM_WGSL, source),
+            info,
+            time: Duration::ZERO,
+            layout,
+            frame_buffer,
+            params_buffer,
+            bind_group: Rc::new(RefCell::new(Arc::new(bind_group))),
+        })
+    }
+
+    /// Builds the effect to insert into the post stack, see PostProcessor::insert_before
+    /// and PostProcessor::insert_after.
+    pub fn effect(&self, device: &wgpu::Device, post: &PostProcessor) -> PostEffect {
+        PostEffect::new(
+            device,
+            post,
+            &self.name,
+            &self.source,
+            Some(&self.layout),
+            Some(self.bind_group.clone()),
+        )
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    pub const fn info(&self) -> CustomEffectInfo {
+        self.info
+    }
+
+    /// Advances time and writes the frame uniform, call once per frame.
+    pub fn frame_update(&mut self, queue: &wgpu::Queue, post: &PostProcessor, dt: Duration) {
+        self.time += dt;
+        let size = post.size();
+        let frame = PostFrame {
+            resolution: [size.0 as f32, size.1 as f32],
+            time: self.time.as_secs_f32(),
+            delta_time: dt.as_secs_f32(),
+        };
+        queue.write_buffer(&self.frame_buffer, 0, bytemuck::bytes_of(&frame));
+    }
+
+    /// Writes the effect's uniform block, `params` must match its size.
+    pub fn set_params<T: bytemuck::Pod>(&self, queue: &wgpu::Queue, params: &T) -> Result<()> {
+        let bytes = bytemuck::bytes_of(params);
+        if bytes.len() != self.info.params_size as usize {
+            bail!(
+                "CustomEffect::set_params => {} expects {} bytes of params, got {}",
+                self.name,
+                self.info.params_size,
+                bytes.len()
+            );
+        }
+        queue.write_buffer(&self.params_buffer, 0, bytes);
+        Ok(())
+    }
+}
@@ -0,0 +1,444 @@
+use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+use anyhow::*;
+use image::GenericImageView;
+use wgpu::util::DeviceExt;
+
+use super::{EffectBindGroup, PostEffect, PostProcessor};
+
+/// Name the color grade effect is registered under in the post stack.
+pub const COLOR_GRADE_EFFECT: &str = "color_grade";
+
+/// Maximum number of colors a palette swap can remap.
+pub const MAX_PALETTE_COLORS: u32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ColorGradeMode {
+    #[default]
+    Passthrough,
+    /// Remaps colors through the loaded 3D LUT, strength blends between the source and graded color.
+    Lut {
+        strength: f32,
+    },
+    /// Replaces colors within tolerance (sRGB distance) of a palette entry with its replacement.
+    PaletteSwap {
+        tolerance: f32,
+    },
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GradeParams {
+    mode: u32,
+    strength: f32,
+    lut_size: f32,
+    palette_len: u32,
+    tolerance: f32,
+    _pad: [f32; 3],
+}
+
+impl GradeParams {
+    fn new(mode: ColorGradeMode, lut_size: u32, palette_len: u32) -> Self {
+        let (mode, strength, tolerance) = match mode {
+            ColorGradeMode::Passthrough => (0, 0.0, 0.0),
+            ColorGradeMode::Lut { strength } => (1, strength.clamp(0.0, 1.0), 0.0),
+            ColorGradeMode::PaletteSwap { tolerance } => (2, 0.0, tolerance.max(0.0)),
+        };
+        Self {
+            mode,
+            strength,
+            lut_size: lut_size as f32,
+            palette_len,
+            tolerance,
+            _pad: [0.0; 3],
+        }
+    }
+}
+
+/// A size x size x size color lookup table, stored red fastest then green then blue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut {
+    pub size: u32,
+    pub data: Vec<[u8; 4]>,
+}
+
+impl Lut {
+    /// LUT that maps every color to itself.
+    pub fn identity(size: u32) -> Self {
+        let size = size.max(2);
+        let max = (size - 1) as f32;
+        let mut data = Vec::with_capacity((size * size * size) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.push([
+                        (r as f32 / max * 255.0).round() as u8,
+                        (g as f32 / max * 255.0).round() as u8,
+                        (b as f32 / max * 255.0).round() as u8,
+                        255,
+                    ]);
+                }
+            }
+        }
+        Self { size, data }
+    }
+
+    /// Loads a LUT from the common horizontal strip layout, size slices of size x size laid
+    /// out left to right with blue increasing per slice, red along x and green along y.
+    pub fn from_strip(img: &image::DynamicImage) -> Result<Self> {
+        let (width, height) = img.dimensions();
+        if height < 2 || width != height * height {
+            bail!(
+                "LUT strip must be (size * size) x size pixels, got {}x{}",
+                width,
+                height
+            );
+        }
+
+        let size = height;
+        let rgba = img.to_rgba8();
+        let mut data = Vec::with_capacity((size * size * size) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    let p = rgba.get_pixel(b * size + r, g);
+                    data.push(p.0);
+                }
+            }
+        }
+        Ok(Self { size, data })
+    }
+
+    pub fn from_strip_bytes(bytes: &[u8]) -> Result<Self> {
+        let img = image::load_from_memory(bytes)?;
+        Self::from_strip(&img)
+    }
+
+    /// Parses an Adobe/Resolve .cube file. Only 3D LUTs with the default 0..1 domain are supported.
+    pub fn from_cube_str(src: &str) -> Result<Self> {
+        let mut size = None;
+        let mut data = Vec::new();
+
+        for line in src.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let first = parts.next().unwrap_or_default();
+            match first {
+                "LUT_3D_SIZE" => {
+                    let s: u32 = parts
+                        .next()
+                        .context("LUT_3D_SIZE is missing a value")?
+                        .parse()?;
+                    if s < 2 {
+                        bail!("LUT_3D_SIZE must be at least 2, got {}", s);
+                    }
+                    size = Some(s);
+                }
+                "LUT_1D_SIZE" => bail!("1D .cube LUTs are not supported"),
+                "TITLE" | "DOMAIN_MIN" | "DOMAIN_MAX" | "LUT_3D_INPUT_RANGE" => {}
+                _ => {
+                    let mut rgb = [0u8; 4];
+                    rgb[3] = 255;
+                    for (i, v) in std::iter::once(first).chain(parts).enumerate() {
+                        if i >= 3 {
+                            bail!("Expected 3 values per .cube entry: {}", line);
+                        }
+                        let v: f32 = v
+                            .parse()
+                            .with_context(|| format!("Invalid .cube entry: {}", line))?;
+                        rgb[i] = (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+                    }
+                    data.push(rgb);
+                }
+            }
+        }
+
+        let size = size.context(".cube file is missing LUT_3D_SIZE")?;
+        let expected = (size * size * size) as usize;
+        if data.len() != expected {
+            bail!(
+                ".cube file has {} entries, expected {} for size {}",
+                data.len(),
+                expected,
+                size
+            );
+        }
+        Ok(Self { size, data })
+    }
+}
+
+/// Owns the resources for the color grade post effect. Push ColorGrade::effect into the
+/// PostProcessor once, then drive it through the setters.
+pub struct ColorGrade {
+    mode: ColorGradeMode,
+    lut_size: u32,
+    palette_len: u32,
+    layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    lut: wgpu::Texture,
+    lut_sampler: wgpu::Sampler,
+    palette: wgpu::Texture,
+    bind_group: EffectBindGroup,
+}
+
+impl ColorGrade {
+    pub const DEFAULT_LUT_SIZE: u32 = 16;
+
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let mode = ColorGradeMode::default();
+        let lut = Lut::identity(Self::DEFAULT_LUT_SIZE);
+
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Color Grade Params Buffer"),
+            contents: bytemuck::cast_slice(&[GradeParams::new(mode, lut.size, 0)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D3,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("color_grade_bind_group_layout"),
+        });
+
+        let lut_texture = create_lut_texture(device, lut.size);
+        write_lut(queue, &lut_texture, &lut);
+
+        let lut_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::MipmapFilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let palette = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Color Grade Palette"),
+            size: wgpu::Extent3d {
+                width: MAX_PALETTE_COLORS,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let bind_group = create_bind_group(
+            device,
+            &layout,
+            &params_buffer,
+            &lut_texture,
+            &lut_sampler,
+            &palette,
+        );
+
+        Self {
+            mode,
+            lut_size: lut.size,
+            palette_len: 0,
+            layout,
+            params_buffer,
+            lut: lut_texture,
+            lut_sampler,
+            palette,
+            bind_group: Rc::new(RefCell::new(Arc::new(bind_group))),
+        }
+    }
+
+    /// Builds the effect to push into the post stack, it shares this ColorGrade's resources.
+    pub fn effect(&self, device: &wgpu::Device, post: &PostProcessor) -> PostEffect {
+        PostEffect::new(
+            device,
+            post,
+            COLOR_GRADE_EFFECT,
+            include_str!("../../shaders/post_grade.wgsl"),
+            Some(&self.layout),
+            Some(self.bind_group.clone()),
+        )
+    }
+
+    #[inline]
+    pub const fn mode(&self) -> ColorGradeMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, queue: &wgpu::Queue, mode: ColorGradeMode) {
+        self.mode = mode;
+        self.write_params(queue);
+    }
+
+    /// Uploads a new LUT, recreating the 3D texture if its size changed.
+    pub fn set_lut(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, lut: &Lut) {
+        if lut.size != self.lut_size {
+            self.lut = create_lut_texture(device, lut.size);
+            self.lut_size = lut.size;
+            let bind_group = create_bind_group(
+                device,
+                &self.layout,
+                &self.params_buffer,
+                &self.lut,
+                &self.lut_sampler,
+                &self.palette,
+            );
+            *self.bind_group.borrow_mut() = Arc::new(bind_group);
+        }
+        write_lut(queue, &self.lut, lut);
+        self.write_params(queue);
+    }
+
+    /// Sets the colors to swap, source[i] is replaced with replacement[i]. Colors are sRGB
+    /// and lists longer than MAX_PALETTE_COLORS are truncated.
+    pub fn set_palette(
+        &mut self,
+        queue: &wgpu::Queue,
+        source: &[[u8; 4]],
+        replacement: &[[u8; 4]],
+    ) {
+        let len = source
+            .len()
+            .min(replacement.len())
+            .min(MAX_PALETTE_COLORS as usize);
+
+        let mut texels = vec![[0u8; 4]; 2 * MAX_PALETTE_COLORS as usize];
+        texels[..len].copy_from_slice(&source[..len]);
+        texels[MAX_PALETTE_COLORS as usize..MAX_PALETTE_COLORS as usize + len]
+            .copy_from_slice(&replacement[..len]);
+
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                aspect: wgpu::TextureAspect::All,
+                texture: &self.palette,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            bytemuck::cast_slice(&texels),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * MAX_PALETTE_COLORS),
+                rows_per_image: Some(2),
+            },
+            self.palette.size(),
+        );
+        self.palette_len = len as u32;
+        self.write_params(queue);
+    }
+
+    fn write_params(&self, queue: &wgpu::Queue) {
+        let params = GradeParams::new(self.mode, self.lut_size, self.palette_len);
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[params]));
+    }
+}
+
+fn create_lut_texture(device: &wgpu::Device, size: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Color Grade LUT"),
+        size: wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: size,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D3,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+fn write_lut(queue: &wgpu::Queue, texture: &wgpu::Texture, lut: &Lut) {
+    queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            aspect: wgpu::TextureAspect::All,
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        bytemuck::cast_slice(&lut.data),
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * lut.size),
+            rows_per_image: Some(lut.size),
+        },
+        texture.size(),
+    );
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    params: &wgpu::Buffer,
+    lut: &wgpu::Texture,
+    lut_sampler: &wgpu::Sampler,
+    palette: &wgpu::Texture,
+) -> wgpu::BindGroup {
+    let lut_view = lut.create_view(&wgpu::TextureViewDescriptor::default());
+    let palette_view = palette.create_view(&wgpu::TextureViewDescriptor::default());
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&lut_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(lut_sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&palette_view),
+            },
+        ],
+        label: Some("color_grade_bind_group"),
+    })
+}
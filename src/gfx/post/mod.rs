@@ -0,0 +1,319 @@
+use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+use crate::{
+    eng::{
+        command::{RenderCommand, RenderPass, RenderPassOp},
+        render::DeviceSurface,
+    },
+    gfx::wgpu::texture::Texture,
+};
+
+pub mod aa;
+pub mod accessibility;
+pub mod custom;
+pub mod grade;
+
+/// Source every effect shader is appended to, provides vs_main and the group 0 input bindings.
+pub const POST_COMMON_WGSL: &str = include_str!("../../shaders/post_common.wgsl");
+
+/// Effect resources bound at group 1. Shared between the effect in the stack and the
+/// controller that owns the resources, so they can be rebuilt without touching the stack.
+pub type EffectBindGroup = Rc<RefCell<Arc<wgpu::BindGroup>>>;
+
+/// A single full screen pass in the post process stack.
+pub struct PostEffect {
+    name: String,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    bind_group: Option<EffectBindGroup>,
+    pub enabled: bool,
+}
+
+impl PostEffect {
+    /// Builds an effect from WGSL that provides `fs_main`, see shaders/post_common.wgsl for
+    /// the inputs available to it. `layout` describes the effect's own group 1 resources.
+    pub fn new(
+        device: &wgpu::Device,
+        post: &PostProcessor,
+        name: &str,
+        fragment_source: &str,
+        layout: Option<&wgpu::BindGroupLayout>,
+        bind_group: Option<EffectBindGroup>,
+    ) -> Self {
+        let source = format!("{}\n{}", POST_COMMON_WGSL, fragment_source);
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(name),
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        let mut bind_group_layouts = vec![Some(post.input_layout.as_ref())];
+        bind_group_layouts.extend(layout.map(Some));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(name),
+            bind_group_layouts: &bind_group_layouts,
+            immediate_size: 0,
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(name),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: post.format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        Self {
+            name: String::from(name),
+            pipeline: Arc::new(pipeline),
+            bind_group,
+            enabled: true,
+        }
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn commands(&self, input: Arc<wgpu::BindGroup>) -> Vec<RenderCommand> {
+        let mut cmds = vec![
+            RenderCommand::SetPipeline(self.pipeline.clone()),
+            RenderCommand::SetBindGroup(0, input, None),
+        ];
+        if let Some(bind_group) = self.bind_group.as_ref() {
+            cmds.push(RenderCommand::SetBindGroup(
+                1,
+                bind_group.borrow().clone(),
+                None,
+            ));
+        }
+        cmds.push(RenderCommand::Draw(0..3, 0..1));
+        cmds
+    }
+}
+
+/// Ordered stack of full screen effects applied to the scene before it is presented.
+/// While any effect is enabled, surface passes are redirected into an offscreen scene
+/// target which the effects then ping-pong between, the last one writing to the surface.
+pub struct PostProcessor {
+    format: wgpu::TextureFormat,
+    targets: [Rc<Texture>; 2],
+    input_layout: Arc<wgpu::BindGroupLayout>,
+    input_bind_groups: [Arc<wgpu::BindGroup>; 2],
+    effects: Vec<PostEffect>,
+}
+
+impl PostProcessor {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let input_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("post_input_bind_group_layout"),
+        });
+
+        let (targets, input_bind_groups) =
+            create_targets(device, &input_layout, format, width, height);
+
+        Self {
+            format,
+            targets,
+            input_layout: Arc::new(input_layout),
+            input_bind_groups,
+            effects: Vec::new(),
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        let (targets, input_bind_groups) =
+            create_targets(device, &self.input_layout, self.format, width, height);
+        self.targets = targets;
+        self.input_bind_groups = input_bind_groups;
+    }
+
+    #[inline]
+    pub const fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    /// Size of the post targets in pixels.
+    pub fn size(&self) -> (u32, u32) {
+        let size = self.targets[0].handle.size();
+        (size.width, size.height)
+    }
+
+    #[inline]
+    pub fn input_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.input_layout
+    }
+
+    /// Texture the scene is rendered into while the stack is active.
+    #[inline]
+    pub fn scene_target(&self) -> &Rc<Texture> {
+        &self.targets[0]
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.effects.iter().any(|e| e.enabled)
+    }
+
+    pub fn push_effect(&mut self, effect: PostEffect) {
+        self.effects.push(effect);
+    }
+
+    pub fn insert_effect(&mut self, index: usize, effect: PostEffect) {
+        let index = index.min(self.effects.len());
+        self.effects.insert(index, effect);
+    }
+
+    /// Inserts `effect` just before the effect called `name`.
+    pub fn insert_before(&mut self, name: &str, effect: PostEffect) -> anyhow::Result<()> {
+        let index = self
+            .effect_index(name)
+            .ok_or_else(|| anyhow::anyhow!("PostProcessor::insert_before => no effect {}", name))?;
+        self.effects.insert(index, effect);
+        Ok(())
+    }
+
+    /// Inserts `effect` just after the effect called `name`.
+    pub fn insert_after(&mut self, name: &str, effect: PostEffect) -> anyhow::Result<()> {
+        let index = self
+            .effect_index(name)
+            .ok_or_else(|| anyhow::anyhow!("PostProcessor::insert_after => no effect {}", name))?;
+        self.effects.insert(index + 1, effect);
+        Ok(())
+    }
+
+    pub fn effect_index(&self, name: &str) -> Option<usize> {
+        self.effects.iter().position(|e| e.name == name)
+    }
+
+    pub fn remove_effect(&mut self, name: &str) -> Option<PostEffect> {
+        let index = self.effect_index(name)?;
+        Some(self.effects.remove(index))
+    }
+
+    pub fn effect_mut(&mut self, name: &str) -> Option<&mut PostEffect> {
+        self.effects.iter_mut().find(|e| e.name == name)
+    }
+
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) {
+        if let Some(effect) = self.effect_mut(name) {
+            effect.enabled = enabled;
+        }
+    }
+
+    pub fn effects(&self) -> &[PostEffect] {
+        &self.effects
+    }
+
+    /// Builds the passes for every enabled effect, reading the scene target and ending on the surface.
+    pub fn passes(&self, surface: &Rc<DeviceSurface>) -> Vec<RenderPass> {
+        let enabled = self
+            .effects
+            .iter()
+            .filter(|e| e.enabled)
+            .collect::<Vec<_>>();
+        let last = enabled.len().saturating_sub(1);
+
+        enabled
+            .into_iter()
+            .enumerate()
+            .map(|(i, effect)| {
+                let mut pass = if i == last {
+                    RenderPass::to_surface(surface, None, RenderPassOp::CLEAR_BLACK)
+                } else {
+                    RenderPass::to_texture(
+                        surface,
+                        &self.targets[(i + 1) % 2],
+                        None,
+                        RenderPassOp::CLEAR_BLACK,
+                    )
+                };
+                pass.label = Some(format!("Post {}", effect.name()));
+                pass.command_queue
+                    .extend(effect.commands(self.input_bind_groups[i % 2].clone()));
+                pass
+            })
+            .collect()
+    }
+}
+
+fn create_targets(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+) -> ([Rc<Texture>; 2], [Arc<wgpu::BindGroup>; 2]) {
+    let targets = [
+        Rc::new(Texture::render_target(
+            device,
+            width,
+            height,
+            format,
+            Some("Post Target A"),
+        )),
+        Rc::new(Texture::render_target(
+            device,
+            width,
+            height,
+            format,
+            Some("Post Target B"),
+        )),
+    ];
+    let bind_groups = [0, 1].map(|i| {
+        Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&targets[i].view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&targets[i].sampler),
+                },
+            ],
+            label: Some("post_input_bind_group"),
+        }))
+    });
+    (targets, bind_groups)
+}
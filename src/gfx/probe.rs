@@ -0,0 +1,393 @@
+use std::{
+    rc::Rc,
+    sync::{mpsc, Arc},
+};
+
+use anyhow::ensure;
+use cgmath::{Deg, EuclideanSpace, InnerSpace, Matrix4, Point3, Vector3};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    eng::render::RenderWindow,
+    sys::{cubemap::BakedCubemap, math::OPENGL_TO_WGPU_MATRIX},
+};
+
+use super::{camera::CameraUniform, draw::DrawCtx, wgpu::texture::Texture};
+
+/// View direction and up vector of every cube face in layer order. The ups are the GL ones,
+/// which render each face upside down in wgpu, capture_probe flips the rows back on readback.
+const FACES: [([f32; 3], [f32; 3]); 6] = [
+    ([1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+    ([-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+    ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+    ([0.0, -1.0, 0.0], [0.0, 0.0, -1.0]),
+    ([0.0, 0.0, 1.0], [0.0, -1.0, 0.0]),
+    ([0.0, 0.0, -1.0], [0.0, -1.0, 0.0]),
+];
+
+/// See the Probe struct in basic.wgsl.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ProbeUniform {
+    pub position_radius: [f32; 4],
+    /// x: intensity, y: 1 when a probe is bound.
+    pub params: [f32; 4],
+}
+
+/// Point a cubemap of the scene is captured from, lighting the specular reflections of
+/// objects within `radius` of it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReflectionProbe {
+    pub position: Point3<f32>,
+    /// Range of influence, reflections are also projected onto a sphere this size so
+    /// nearby surroundings don't look infinitely far away.
+    pub radius: f32,
+    /// Width and height of every face in pixels.
+    pub resolution: u32,
+    pub intensity: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl ReflectionProbe {
+    pub fn new(position: Point3<f32>, radius: f32) -> Self {
+        Self {
+            position,
+            radius,
+            resolution: 128,
+            intensity: 1.0,
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+
+    pub fn with_resolution(mut self, resolution: u32) -> Self {
+        self.resolution = resolution.max(1);
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// Near and far planes used while capturing.
+    pub fn with_clip(mut self, near: f32, far: f32) -> Self {
+        self.near = near;
+        self.far = far;
+        self
+    }
+
+    #[inline]
+    pub fn contains(&self, position: Point3<f32>) -> bool {
+        (position - self.position).magnitude2() <= self.radius * self.radius
+    }
+
+    /// View projection that renders cube face `face` (0..6, in layer order) upside down.
+    pub fn face_view_proj(&self, face: usize) -> Matrix4<f32> {
+        let (dir, up) = FACES[face];
+        let view = Matrix4::look_to_rh(self.position, Vector3::from(dir), Vector3::from(up));
+        let proj = cgmath::perspective(Deg(90.0), 1.0, self.near, self.far);
+        OPENGL_TO_WGPU_MATRIX * proj * view
+    }
+
+    pub fn uniform(&self) -> ProbeUniform {
+        ProbeUniform {
+            position_radius: self.position.to_vec().extend(self.radius).into(),
+            params: [self.intensity, 1.0, 0.0, 0.0],
+        }
+    }
+}
+
+/// Index of the probe `position` should reflect. Among the probes containing it, the one
+/// it is relatively closest to wins, so a small probe inside a big one takes over near its
+/// center.
+pub fn select_probe(probes: &[ReflectionProbe], position: Point3<f32>) -> Option<usize> {
+    probes
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.radius > 0.0 && p.contains(position))
+        .map(|(i, p)| (i, (position - p.position).magnitude() / p.radius))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
+
+/// Uploads `cubemap` as a sampleable cube texture. Captured faces hold display encoded
+/// colors, so they are sampled as sRGB whatever the surface format was.
+pub(crate) fn create_cube_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    cubemap: &BakedCubemap,
+    label: Option<&str>,
+) -> (wgpu::Texture, wgpu::TextureView) {
+    let size = wgpu::Extent3d {
+        width: cubemap.size,
+        height: cubemap.size,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label,
+        size: wgpu::Extent3d {
+            depth_or_array_layers: 6,
+            ..size
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    for (layer, face) in cubemap.faces.iter().enumerate() {
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                aspect: wgpu::TextureAspect::All,
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer as u32,
+                },
+            },
+            face,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(cubemap.size * 4),
+                rows_per_image: Some(cubemap.size),
+            },
+            size,
+        );
+    }
+    let view = texture.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::Cube),
+        ..Default::default()
+    });
+    (texture, view)
+}
+
+pub(crate) fn create_probe_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Reflection Probe Sampler"),
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    })
+}
+
+struct ProbeResources {
+    _texture: wgpu::Texture,
+    _uniform: wgpu::Buffer,
+    bind_group: Arc<wgpu::BindGroup>,
+}
+
+/// Baked probes of a scene with the light bind group each one is drawn with. Pick one per
+/// object with DrawCtx::set_reflection_probe.
+pub struct ReflectionProbes {
+    probes: Vec<ReflectionProbe>,
+    resources: Vec<ProbeResources>,
+    sampler: wgpu::Sampler,
+}
+
+impl ReflectionProbes {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            probes: Vec::new(),
+            resources: Vec::new(),
+            sampler: create_probe_sampler(device),
+        }
+    }
+
+    /// Uploads `cubemap`, captured by capture_probe or loaded from a pack, for `probe`.
+    pub fn add(
+        &mut self,
+        window: &RenderWindow,
+        probe: ReflectionProbe,
+        cubemap: &BakedCubemap,
+    ) -> usize {
+        let ds = window.device_surface();
+        let (texture, view) =
+            create_cube_texture(&ds.device, &ds.queue, cubemap, Some("Reflection Probe"));
+        let uniform = ds
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Reflection Probe Uniform"),
+                contents: bytemuck::cast_slice(&[probe.uniform()]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let bind_group =
+            window
+                .light_renderer()
+                .probe_bind_group(&ds.device, &view, &self.sampler, &uniform);
+        self.probes.push(probe);
+        self.resources.push(ProbeResources {
+            _texture: texture,
+            _uniform: uniform,
+            bind_group: Arc::new(bind_group),
+        });
+        self.probes.len() - 1
+    }
+
+    #[inline]
+    pub fn probes(&self) -> &[ReflectionProbe] {
+        &self.probes
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.probes.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.probes.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.probes.clear();
+        self.resources.clear();
+    }
+
+    /// Light bind group of the probe select_probe picks for `position`.
+    pub fn select(&self, position: Point3<f32>) -> Option<Arc<wgpu::BindGroup>> {
+        select_probe(&self.probes, position).map(|i| self.resources[i].bind_group.clone())
+    }
+}
+
+/// Renders the six faces of `probe` with `draw` and reads them back, e.g. while loading a
+/// level. `draw` is called once per face with the camera set to that face, every pass it
+/// queues for the surface is redirected into the face. Post processing is skipped and the
+/// window's camera uniform is restored afterwards.
+pub fn capture_probe(
+    window: &mut RenderWindow,
+    probe: &ReflectionProbe,
+    mut draw: impl FnMut(&mut DrawCtx),
+) -> anyhow::Result<BakedCubemap> {
+    let format = window.surface_config().format;
+    let bgra = matches!(
+        format,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+    );
+    ensure!(
+        bgra || matches!(
+            format,
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb
+        ),
+        "capture_probe => Unsupported surface format {:?}",
+        format
+    );
+
+    let ds = window.device_surface().clone();
+    let size = probe.resolution;
+    let target = Rc::new(Texture::render_target(
+        &ds.device,
+        size,
+        size,
+        format,
+        Some("Probe Face"),
+    ));
+    let mut depth_config = window.surface_config().clone();
+    depth_config.width = size;
+    depth_config.height = size;
+    let depth = Rc::new(Texture::depth_texture(
+        &ds.device,
+        &depth_config,
+        Some("Probe Face Depth"),
+    ));
+    let row_len = size * 4;
+    let bytes_per_row = row_len.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let readback = ds.device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Probe Readback Buffer"),
+        size: (bytes_per_row * size) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let saved = *window.camera_uniform();
+    let mut faces: [Vec<u8>; 6] = Default::default();
+    let mut result = Ok(());
+    for (i, face) in faces.iter_mut().enumerate() {
+        let view_proj = probe.face_view_proj(i);
+        window.set_camera_uniform(CameraUniform::new(
+            &view_proj.into(),
+            &probe.position.to_homogeneous().into(),
+        ));
+        window.write_camera_buffer();
+
+        let mut ctx = window.create_draw_context();
+        draw(&mut ctx);
+        ctx.retarget(&target, &depth);
+        if let Err(e) = ctx.submit() {
+            result = Err(e.into());
+            break;
+        }
+
+        let mut encoder = ds
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Probe Readback Encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            target.handle.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: Some(size),
+                },
+            },
+            target.handle.size(),
+        );
+        let submission = ds.queue.submit([encoder.finish()]);
+        match read_face(&ds.device, &readback, submission, bytes_per_row, row_len) {
+            Ok(texels) => *face = texels,
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        }
+        if bgra {
+            face.chunks_exact_mut(4).for_each(|t| t.swap(0, 2));
+        }
+    }
+
+    window.set_camera_uniform(saved);
+    window.write_camera_buffer();
+    result.map(|()| BakedCubemap { size, faces })
+}
+
+/// Waits for `submission` and copies the face out of `buffer`, bottom row first to undo
+/// the flip described on FACES.
+fn read_face(
+    device: &wgpu::Device,
+    buffer: &wgpu::Buffer,
+    submission: wgpu::SubmissionIndex,
+    bytes_per_row: u32,
+    row_len: u32,
+) -> anyhow::Result<Vec<u8>> {
+    let slice = buffer.slice(..);
+    let (tx, rx) = mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = tx.send(result);
+    });
+    device.poll(wgpu::PollType::Wait {
+        submission_index: Some(submission),
+        timeout: None,
+    })?;
+    rx.recv()??;
+
+    let data = slice.get_mapped_range()?;
+    let face = data
+        .chunks_exact(bytes_per_row as usize)
+        .rev()
+        .flat_map(|row| &row[..row_len as usize])
+        .copied()
+        .collect();
+    drop(data);
+    buffer.unmap();
+    Ok(face)
+}
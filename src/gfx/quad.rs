@@ -0,0 +1,491 @@
+use std::{ops::Range, sync::Arc};
+
+use wgpu::util::DeviceExt;
+
+use super::{
+    debug_name::DebugName,
+    draw::DrawCtx,
+    wgpu::{shader::Shader, vertex::SpriteVertex},
+};
+
+/// Sub rectangle of a texture in normalized coordinates, e.g. a frame in a sprite sheet.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UvRect {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+impl UvRect {
+    pub const FULL: UvRect = UvRect {
+        min: [0.0, 0.0],
+        max: [1.0, 1.0],
+    };
+
+    pub const fn new(min: [f32; 2], max: [f32; 2]) -> Self {
+        Self { min, max }
+    }
+
+    /// Rect of the cell at (column, row) in a sheet split into columns x rows equal cells.
+    pub fn from_grid(column: u32, row: u32, columns: u32, rows: u32) -> Self {
+        let w = 1.0 / columns.max(1) as f32;
+        let h = 1.0 / rows.max(1) as f32;
+        let min = [column as f32 * w, row as f32 * h];
+        Self {
+            min,
+            max: [min[0] + w, min[1] + h],
+        }
+    }
+}
+
+impl Default for UvRect {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+/// Index of a QuadMaterial registered on a QuadBuffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MaterialSlot(pub u16);
+
+impl MaterialSlot {
+    /// The buffer's default sprite material, see QuadBuffer::set_default_material.
+    pub const DEFAULT: MaterialSlot = MaterialSlot(0);
+}
+
+/// Pipeline and bind groups a run of quads is drawn with, e.g. an outline, dissolve or
+/// wave distortion effect. Pipelines must accept SpriteVertex at slot 0.
+#[derive(Debug, Clone)]
+pub struct QuadMaterial {
+    /// Labels its batches in GPU captures.
+    pub name: String,
+    pub pipeline: Arc<wgpu::RenderPipeline>,
+    /// Bind groups set before drawing, as (group index, bind group).
+    pub bind_groups: Vec<(u32, Arc<wgpu::BindGroup>)>,
+}
+
+impl QuadMaterial {
+    pub fn new(pipeline: Arc<wgpu::RenderPipeline>) -> Self {
+        Self {
+            name: String::new(),
+            pipeline,
+            bind_groups: Vec::new(),
+        }
+    }
+
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = String::from(name);
+        self
+    }
+
+    /// Material using the default pipeline of `shader`.
+    pub fn from_shader(shader: &Shader) -> Self {
+        Self::new(shader.pipeline())
+    }
+
+    pub fn with_bind_group(mut self, index: u32, bind_group: Arc<wgpu::BindGroup>) -> Self {
+        self.bind_groups.push((index, bind_group));
+        self
+    }
+
+    fn bind(&self, ctx: &mut DrawCtx) {
+        ctx.insert_debug_label(self);
+        ctx.set_pipeline(self.pipeline.clone());
+        for (index, bind_group) in self.bind_groups.iter() {
+            ctx.set_bind_group(*index, bind_group.clone(), None);
+        }
+    }
+}
+
+impl DebugName for QuadMaterial {
+    fn debug_kind(&self) -> &'static str {
+        "Quad Material"
+    }
+
+    fn debug_name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Consecutive quads in draw order that share a material.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuadBatch {
+    pub material: MaterialSlot,
+    /// Range of quads, not indices, within the uploaded buffer.
+    pub quads: Range<u32>,
+}
+
+/// Splits quads already in draw order into batches wherever the material changes.
+/// Quads are never reordered to join batches, that would break layer ordering.
+pub fn material_batches(materials: impl IntoIterator<Item = MaterialSlot>) -> Vec<QuadBatch> {
+    let mut batches: Vec<QuadBatch> = Vec::new();
+    for (i, material) in materials.into_iter().enumerate() {
+        let i = i as u32;
+        match batches.last_mut() {
+            Some(batch) if batch.material == material => batch.quads.end = i + 1,
+            _ => batches.push(QuadBatch {
+                material,
+                quads: i..i + 1,
+            }),
+        }
+    }
+    batches
+}
+
+/// Parameters for QuadBuffer::push_sprite.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprite {
+    /// Where the pivot ends up in world space.
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    /// Counter clockwise rotation about the pivot, in radians.
+    pub rotation: f32,
+    /// Point the sprite is positioned and rotated around, normalized to its size.
+    /// (0, 0) is the bottom left corner, (0.5, 0.5) the center.
+    pub pivot: [f32; 2],
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub uv: UvRect,
+    pub color: [f32; 4],
+    /// Depth written to the vertices, also breaks ties within a layer when sorting.
+    pub z: f32,
+    /// Sprites are drawn in ascending layer order regardless of push order.
+    pub layer: i32,
+    pub material: MaterialSlot,
+}
+
+impl Sprite {
+    pub const fn new(position: [f32; 2], size: [f32; 2]) -> Self {
+        Self {
+            position,
+            size,
+            rotation: 0.0,
+            pivot: [0.5, 0.5],
+            flip_x: false,
+            flip_y: false,
+            uv: UvRect::FULL,
+            color: [1.0; 4],
+            z: 0.0,
+            layer: 0,
+            material: MaterialSlot::DEFAULT,
+        }
+    }
+
+    pub const fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub const fn with_pivot(mut self, pivot: [f32; 2]) -> Self {
+        self.pivot = pivot;
+        self
+    }
+
+    pub const fn flipped(mut self, flip_x: bool, flip_y: bool) -> Self {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+        self
+    }
+
+    pub const fn with_uv(mut self, uv: UvRect) -> Self {
+        self.uv = uv;
+        self
+    }
+
+    pub const fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub const fn with_layer(mut self, layer: i32, z: f32) -> Self {
+        self.layer = layer;
+        self.z = z;
+        self
+    }
+
+    /// Draws the sprite with a material registered through QuadBuffer::add_material.
+    pub const fn with_material(mut self, material: MaterialSlot) -> Self {
+        self.material = material;
+        self
+    }
+
+    /// Corners in counter clockwise order starting at the bottom left.
+    pub fn vertices(&self) -> [SpriteVertex; 4] {
+        let [w, h] = self.size;
+        let [px, py] = [self.pivot[0] * w, self.pivot[1] * h];
+        let (sin, cos) = self.rotation.sin_cos();
+
+        let (mut u0, mut u1) = (self.uv.min[0], self.uv.max[0]);
+        // Texture v grows downwards, so the bottom of the quad samples uv.max.
+        let (mut v0, mut v1) = (self.uv.max[1], self.uv.min[1]);
+        if self.flip_x {
+            std::mem::swap(&mut u0, &mut u1);
+        }
+        if self.flip_y {
+            std::mem::swap(&mut v0, &mut v1);
+        }
+
+        let corners = [
+            ([0.0, 0.0], [u0, v0]),
+            ([w, 0.0], [u1, v0]),
+            ([w, h], [u1, v1]),
+            ([0.0, h], [u0, v1]),
+        ];
+        corners.map(|([x, y], tex_coords)| {
+            let (x, y) = (x - px, y - py);
+            SpriteVertex {
+                position: [
+                    self.position[0] + x * cos - y * sin,
+                    self.position[1] + x * sin + y * cos,
+                    self.z,
+                ],
+                tex_coords,
+                color: self.color,
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Quad {
+    layer: i32,
+    z: f32,
+    material: MaterialSlot,
+    vertices: [SpriteVertex; 4],
+}
+
+/// CPU side batch of textured quads that is uploaded and drawn with one indexed draw per
+/// run of quads sharing a material. Quads are sorted by layer then z on upload, ties keep
+/// their push order. The GPU buffers persist across frames and only the quads that changed
+/// since the last upload are written, so a static UI costs no upload at all.
+pub struct QuadBuffer {
+    quads: Vec<Quad>,
+    capacity: usize,
+    uploaded: usize,
+    /// Copy of the vertices in vertex_buffer, diffed against on upload.
+    resident: Vec<SpriteVertex>,
+    uploaded_bytes: u64,
+    vertex_buffer: Arc<wgpu::Buffer>,
+    index_buffer: Arc<wgpu::Buffer>,
+    /// Indexed by MaterialSlot, slot 0 is the default material.
+    materials: Vec<Option<QuadMaterial>>,
+    batches: Vec<QuadBatch>,
+}
+
+impl QuadBuffer {
+    pub const INDICES_PER_QUAD: u32 = 6;
+    /// Unchanged quads between two dirty ranges closer than this are rewritten anyway,
+    /// one larger write is cheaper than many small ones.
+    pub const MERGE_GAP: usize = 8;
+
+    pub fn new(device: &wgpu::Device, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        let (vertex_buffer, index_buffer) = create_buffers(device, capacity);
+        Self {
+            quads: Vec::with_capacity(capacity),
+            capacity,
+            uploaded: 0,
+            resident: Vec::new(),
+            uploaded_bytes: 0,
+            vertex_buffer,
+            index_buffer,
+            materials: vec![None],
+            batches: Vec::new(),
+        }
+    }
+
+    /// Material for quads without one. Without it the pipeline bound by the caller is used,
+    /// which only works while no other material has been drawn before them.
+    pub fn set_default_material(&mut self, material: QuadMaterial) {
+        self.materials[0] = Some(material);
+    }
+
+    /// Registers a material for sprites to select with Sprite::with_material.
+    pub fn add_material(&mut self, material: QuadMaterial) -> MaterialSlot {
+        self.materials.push(Some(material));
+        MaterialSlot(self.materials.len() as u16 - 1)
+    }
+
+    /// Replaces the material in an existing slot, e.g. to update its parameters.
+    pub fn set_material(&mut self, slot: MaterialSlot, material: QuadMaterial) {
+        if let Some(m) = self.materials.get_mut(slot.0 as usize) {
+            *m = Some(material);
+        } else {
+            log::warn!(
+                "QuadBuffer::set_material => Unknown material slot {}",
+                slot.0
+            );
+        }
+    }
+
+    /// Batches of the last upload.
+    #[inline]
+    pub fn batches(&self) -> &[QuadBatch] {
+        &self.batches
+    }
+
+    /// Axis aligned quad with the full texture, `position` is its bottom left corner.
+    pub fn push_quad(&mut self, position: [f32; 2], size: [f32; 2], color: [f32; 4]) {
+        self.push_sprite(
+            &Sprite::new(position, size)
+                .with_pivot([0.0, 0.0])
+                .with_color(color),
+        );
+    }
+
+    pub fn push_sprite(&mut self, sprite: &Sprite) {
+        self.quads.push(Quad {
+            layer: sprite.layer,
+            z: sprite.z,
+            material: sprite.material,
+            vertices: sprite.vertices(),
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.quads.clear();
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.quads.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.quads.is_empty()
+    }
+
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Sorts the pushed quads into draw order and returns their vertices, 4 per quad.
+    pub fn sorted_vertices(&mut self) -> Vec<SpriteVertex> {
+        self.quads
+            .sort_by(|a, b| a.layer.cmp(&b.layer).then(a.z.total_cmp(&b.z)));
+        self.quads.iter().flat_map(|q| q.vertices).collect()
+    }
+
+    /// Bytes written by the last upload.
+    #[inline]
+    pub fn uploaded_bytes(&self) -> u64 {
+        self.uploaded_bytes
+    }
+
+    /// Writes the quads that changed since the last upload to the GPU, growing the buffers
+    /// if needed. Growing rewrites everything.
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.quads.len() > self.capacity {
+            self.capacity = self.quads.len().next_power_of_two();
+            let (vertex_buffer, index_buffer) = create_buffers(device, self.capacity);
+            self.vertex_buffer = vertex_buffer;
+            self.index_buffer = index_buffer;
+            self.resident.clear();
+        }
+
+        let vertices = self.sorted_vertices();
+        self.uploaded_bytes = 0;
+        for quads in dirty_quads(&self.resident, &vertices, Self::MERGE_GAP) {
+            let range = quads.start * 4..quads.end * 4;
+            let offset = (range.start * std::mem::size_of::<SpriteVertex>()) as u64;
+            let bytes: &[u8] = bytemuck::cast_slice(&vertices[range]);
+            queue.write_buffer(&self.vertex_buffer, offset, bytes);
+            self.uploaded_bytes += bytes.len() as u64;
+        }
+        self.resident = vertices;
+        self.uploaded = self.quads.len();
+        self.batches = material_batches(self.quads.iter().map(|q| q.material));
+    }
+
+    /// Records the draws for the last upload, one per batch. Batches using the default
+    /// material without one set are drawn with the pipeline and bind groups the caller bound.
+    pub fn draw(&self, ctx: &mut DrawCtx) {
+        if self.uploaded == 0 {
+            return;
+        }
+        ctx.set_vertex_buffer(0, self.vertex_buffer.clone());
+        ctx.set_index_buffer(self.index_buffer.clone(), wgpu::IndexFormat::Uint32);
+
+        let mut caller_bound = true;
+        for (i, batch) in self.batches.iter().enumerate() {
+            let material = match self.materials.get(batch.material.0 as usize) {
+                Some(Some(material)) => Some(material),
+                Some(None) if caller_bound => None,
+                _ => {
+                    log::warn!(
+                        "QuadBuffer::draw => No material in slot {}, batch skipped",
+                        batch.material.0
+                    );
+                    continue;
+                }
+            };
+            if ctx.debug_labels() {
+                ctx.push_debug_group(&format!("Quad Batch {} ({} quads)", i, batch.quads.len()));
+            }
+            if let Some(material) = material {
+                material.bind(ctx);
+                caller_bound = false;
+            }
+            let indices = batch.quads.start * Self::INDICES_PER_QUAD
+                ..batch.quads.end * Self::INDICES_PER_QUAD;
+            ctx.draw_indexed(indices, 0, 0..1);
+            if ctx.debug_labels() {
+                ctx.pop_debug_group();
+            }
+        }
+    }
+}
+
+/// Ranges of quads, 4 vertices each, in `new` that differ from `old` or lie past its end.
+/// Ranges separated by fewer than `merge_gap` unchanged quads are joined.
+pub fn dirty_quads(
+    old: &[SpriteVertex],
+    new: &[SpriteVertex],
+    merge_gap: usize,
+) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (i, quad) in new.chunks_exact(4).enumerate() {
+        let unchanged = old
+            .get(i * 4..i * 4 + 4)
+            .is_some_and(|old| {
+                bytemuck::cast_slice::<_, u8>(old) == bytemuck::cast_slice::<_, u8>(quad)
+            });
+        if unchanged {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(last) if i - last.end < merge_gap => last.end = i + 1,
+            _ => ranges.push(i..i + 1),
+        }
+    }
+    ranges
+}
+
+/// Index pattern for `count` quads, two counter clockwise triangles each.
+pub fn quad_indices(count: usize) -> Vec<u32> {
+    (0..count as u32)
+        .flat_map(|i| {
+            let v = i * 4;
+            [v, v + 1, v + 2, v, v + 2, v + 3]
+        })
+        .collect()
+}
+
+fn create_buffers(
+    device: &wgpu::Device,
+    capacity: usize,
+) -> (Arc<wgpu::Buffer>, Arc<wgpu::Buffer>) {
+    let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Quad Vertex Buffer"),
+        size: (capacity * 4 * std::mem::size_of::<SpriteVertex>()) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Quad Index Buffer"),
+        contents: bytemuck::cast_slice(&quad_indices(capacity)),
+        usage: wgpu::BufferUsages::INDEX,
+    });
+    (Arc::new(vertex_buffer), Arc::new(index_buffer))
+}
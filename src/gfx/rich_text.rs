@@ -0,0 +1,563 @@
+use std::ops::Range;
+
+use anyhow::{bail, ensure};
+
+use crate::sys::rand::Rng;
+
+use super::{
+    font::{BitmapFont, GlyphQuad},
+    quad::{MaterialSlot, QuadBuffer, Sprite},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FontStyle {
+    #[default]
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+}
+
+impl FontStyle {
+    fn with_bold(self) -> Self {
+        match self {
+            Self::Regular | Self::Bold => Self::Bold,
+            Self::Italic | Self::BoldItalic => Self::BoldItalic,
+        }
+    }
+
+    fn with_italic(self) -> Self {
+        match self {
+            Self::Regular | Self::Italic => Self::Italic,
+            Self::Bold | Self::BoldItalic => Self::BoldItalic,
+        }
+    }
+}
+
+/// Per glyph animation applied when the text is pushed, for dialogue.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TextEffect {
+    #[default]
+    None,
+    /// Glyphs bob up and down in a wave travelling along the text.
+    Wave {
+        /// World units.
+        amplitude: f32,
+        /// Radians between neighbouring glyphs.
+        frequency: f32,
+        /// Radians per second.
+        speed: f32,
+    },
+    /// Glyphs jitter randomly, jumping `rate` times per second.
+    Shake { amplitude: f32, rate: f32 },
+}
+
+impl TextEffect {
+    pub const WAVE: TextEffect = TextEffect::Wave {
+        amplitude: 2.0,
+        frequency: 0.6,
+        speed: 8.0,
+    };
+    pub const SHAKE: TextEffect = TextEffect::Shake {
+        amplitude: 1.0,
+        rate: 20.0,
+    };
+
+    /// Offset of glyph `index` at `time` seconds.
+    pub fn offset(&self, index: usize, time: f32) -> [f32; 2] {
+        match *self {
+            TextEffect::None => [0.0; 2],
+            TextEffect::Wave {
+                amplitude,
+                frequency,
+                speed,
+            } => [
+                0.0,
+                amplitude * (time * speed + index as f32 * frequency).sin(),
+            ],
+            TextEffect::Shake { amplitude, rate } => {
+                let step = (time * rate).floor() as u64;
+                let mut rng = Rng::new(step.wrapping_mul(0x9E37_79B9) ^ index as u64);
+                let v = rng.in_unit_circle();
+                [v.x * amplitude, v.y * amplitude]
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SpanStyle {
+    /// None keeps the color passed to RichText::push.
+    pub color: Option<[f32; 4]>,
+    pub font: FontStyle,
+    pub effect: TextEffect,
+}
+
+/// A run of text sharing one style.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TextSpan {
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+impl TextSpan {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: String::from(text),
+            style: SpanStyle::default(),
+        }
+    }
+
+    pub fn with_color(mut self, color: [f32; 4]) -> Self {
+        self.style.color = Some(color);
+        self
+    }
+
+    pub fn with_font(mut self, font: FontStyle) -> Self {
+        self.style.font = font;
+        self
+    }
+
+    pub fn with_effect(mut self, effect: TextEffect) -> Self {
+        self.style.effect = effect;
+        self
+    }
+}
+
+/// Parses BBCode style markup into spans: `[b]`, `[i]`, `[color=#rrggbb]` (or
+/// `#rrggbbaa`), `[wave]`, `[wave=amplitude]`, `[shake]` and `[shake=amplitude]`, each
+/// closed by its `[/tag]`. `[[` writes a literal `[`.
+pub fn parse_markup(text: &str) -> anyhow::Result<Vec<TextSpan>> {
+    let mut spans: Vec<TextSpan> = Vec::new();
+    let mut stack: Vec<(String, SpanStyle)> = Vec::new();
+    let mut style = SpanStyle::default();
+    let mut rest = text;
+    while let Some(open) = rest.find('[') {
+        push_span(&mut spans, &rest[..open], style);
+        rest = &rest[open + 1..];
+        if let Some(after) = rest.strip_prefix('[') {
+            push_span(&mut spans, "[", style);
+            rest = after;
+            continue;
+        }
+        let Some(close) = rest.find(']') else {
+            bail!("parse_markup => unclosed tag in {:?}", text);
+        };
+        let tag = &rest[..close];
+        rest = &rest[close + 1..];
+
+        if let Some(name) = tag.strip_prefix('/') {
+            match stack.pop() {
+                Some((open, previous)) if open == name => style = previous,
+                Some((open, _)) => bail!("parse_markup => [/{}] closes [{}]", name, open),
+                None => bail!("parse_markup => [/{}] was never opened", name),
+            }
+            continue;
+        }
+        let (name, value) = tag.split_once('=').unwrap_or((tag, ""));
+        stack.push((String::from(name), style));
+        match (name, value) {
+            ("b", "") => style.font = style.font.with_bold(),
+            ("i", "") => style.font = style.font.with_italic(),
+            ("color", value) => style.color = Some(parse_hex_color(value)?),
+            ("wave", "") => style.effect = TextEffect::WAVE,
+            ("wave", value) => {
+                style.effect = TextEffect::Wave {
+                    amplitude: value.parse()?,
+                    frequency: 0.6,
+                    speed: 8.0,
+                }
+            }
+            ("shake", "") => style.effect = TextEffect::SHAKE,
+            ("shake", value) => {
+                style.effect = TextEffect::Shake {
+                    amplitude: value.parse()?,
+                    rate: 20.0,
+                }
+            }
+            _ => bail!("parse_markup => unknown tag [{}]", tag),
+        }
+    }
+    push_span(&mut spans, rest, style);
+    if let Some((open, _)) = stack.last() {
+        bail!("parse_markup => [{}] is never closed", open);
+    }
+    Ok(spans)
+}
+
+/// Appends `text` to the last span if it has the same style.
+fn push_span(spans: &mut Vec<TextSpan>, text: &str, style: SpanStyle) {
+    if text.is_empty() {
+        return;
+    }
+    match spans.last_mut() {
+        Some(last) if last.style == style => last.text.push_str(text),
+        _ => spans.push(TextSpan {
+            text: String::from(text),
+            style,
+        }),
+    }
+}
+
+/// `#rrggbb` or `#rrggbbaa` into a color with 0..1 channels.
+pub fn parse_hex_color(text: &str) -> anyhow::Result<[f32; 4]> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    ensure!(
+        (hex.len() == 6 || hex.len() == 8) && hex.is_ascii(),
+        "parse_hex_color => expected #rrggbb or #rrggbbaa, got {}",
+        text
+    );
+    let mut color = [1.0; 4];
+    for (i, channel) in color.iter_mut().enumerate().take(hex.len() / 2) {
+        *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)? as f32 / 255.0;
+    }
+    Ok(color)
+}
+
+/// Bitmap fonts for each FontStyle, missing variants fall back to the closest one.
+#[derive(Debug, Clone)]
+pub struct FontFamily {
+    pub regular: BitmapFont,
+    pub bold: Option<BitmapFont>,
+    pub italic: Option<BitmapFont>,
+    pub bold_italic: Option<BitmapFont>,
+}
+
+impl FontFamily {
+    pub fn new(regular: BitmapFont) -> Self {
+        Self {
+            regular,
+            bold: None,
+            italic: None,
+            bold_italic: None,
+        }
+    }
+
+    pub fn with_bold(mut self, font: BitmapFont) -> Self {
+        self.bold = Some(font);
+        self
+    }
+
+    pub fn with_italic(mut self, font: BitmapFont) -> Self {
+        self.italic = Some(font);
+        self
+    }
+
+    pub fn with_bold_italic(mut self, font: BitmapFont) -> Self {
+        self.bold_italic = Some(font);
+        self
+    }
+
+    pub fn get(&self, style: FontStyle) -> &BitmapFont {
+        let font = match style {
+            FontStyle::Regular => None,
+            FontStyle::Bold => self.bold.as_ref(),
+            FontStyle::Italic => self.italic.as_ref(),
+            FontStyle::BoldItalic => self
+                .bold_italic
+                .as_ref()
+                .or(self.bold.as_ref())
+                .or(self.italic.as_ref()),
+        };
+        font.unwrap_or(&self.regular)
+    }
+}
+
+/// A positioned glyph ready for the batcher.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RichGlyph {
+    pub quad: GlyphQuad,
+    pub material: MaterialSlot,
+    pub color: Option<[f32; 4]>,
+    pub effect: TextEffect,
+    /// Index of the character in the laid out text, drives effects and typewriter reveals.
+    pub index: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RichText {
+    pub glyphs: Vec<RichGlyph>,
+    /// Character range of each line, without the whitespace it was broken at.
+    pub lines: Vec<Range<usize>>,
+    /// Size of the text itself, not the layout box.
+    pub size: [f32; 2],
+}
+
+impl RichText {
+    /// Pushes the glyphs with effects evaluated at `time` seconds. Glyphs without a span
+    /// color use `color`, only the first `visible` characters are drawn if given.
+    pub fn push(
+        &self,
+        quads: &mut QuadBuffer,
+        time: f32,
+        color: [f32; 4],
+        layer: i32,
+        z: f32,
+        visible: Option<usize>,
+    ) {
+        for glyph in self.glyphs.iter() {
+            if visible.is_some_and(|v| glyph.index >= v) {
+                break;
+            }
+            let [dx, dy] = glyph.effect.offset(glyph.index, time);
+            let position = [glyph.quad.position[0] + dx, glyph.quad.position[1] + dy];
+            quads.push_sprite(
+                &Sprite::new(position, glyph.quad.size)
+                    .with_pivot([0.0, 0.0])
+                    .with_uv(glyph.quad.uv)
+                    .with_color(glyph.color.unwrap_or(color))
+                    .with_layer(layer, z)
+                    .with_material(glyph.material),
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct StyledChar {
+    c: char,
+    style: SpanStyle,
+}
+
+/// Lays styled spans out in a box: word wrap to a width, alignment and line spacing.
+/// Coordinates are world units with y growing upwards like QuadBuffer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextLayout {
+    /// World units per font pixel.
+    pub scale: f32,
+    /// Wrap width, None only breaks lines at '\n'.
+    pub max_width: Option<f32>,
+    /// Box height for vertical alignment, None fits the text.
+    pub height: Option<f32>,
+    pub h_align: HAlign,
+    pub v_align: VAlign,
+    /// Multiplier on the regular font's line height.
+    pub line_spacing: f32,
+}
+
+impl Default for TextLayout {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            max_width: None,
+            height: None,
+            h_align: HAlign::default(),
+            v_align: VAlign::default(),
+            line_spacing: 1.0,
+        }
+    }
+}
+
+impl TextLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_max_width(mut self, width: f32) -> Self {
+        self.max_width = Some(width);
+        self
+    }
+
+    pub fn with_height(mut self, height: f32) -> Self {
+        self.height = Some(height);
+        self
+    }
+
+    pub fn with_align(mut self, h_align: HAlign, v_align: VAlign) -> Self {
+        self.h_align = h_align;
+        self.v_align = v_align;
+        self
+    }
+
+    pub fn with_line_spacing(mut self, line_spacing: f32) -> Self {
+        self.line_spacing = line_spacing;
+        self
+    }
+
+    /// Lays `spans` out with the top left of the box at `origin`.
+    pub fn layout(&self, family: &FontFamily, spans: &[TextSpan], origin: [f32; 2]) -> RichText {
+        let chars = spans
+            .iter()
+            .flat_map(|span| {
+                span.text.chars().map(|c| StyledChar {
+                    c,
+                    style: span.style,
+                })
+            })
+            .collect::<Vec<_>>();
+        let lines = self.break_lines(family, &chars);
+
+        let regular = &family.regular.font;
+        let line_height = regular.line_height as f32 * self.scale;
+        let advance = line_height * self.line_spacing;
+        let widths = lines
+            .iter()
+            .map(|line| self.measure(family, &chars[line.clone()]))
+            .collect::<Vec<_>>();
+        let width = widths.iter().copied().fold(0.0, f32::max);
+        let height = match lines.len() {
+            0 => 0.0,
+            n => (n - 1) as f32 * advance + line_height,
+        };
+        let box_width = self.max_width.unwrap_or(width);
+        let top = origin[1]
+            - match (self.v_align, self.height) {
+                (VAlign::Top, _) | (_, None) => 0.0,
+                (VAlign::Middle, Some(h)) => ((h - height) * 0.5).round(),
+                (VAlign::Bottom, Some(h)) => h - height,
+            };
+
+        let mut glyphs = Vec::with_capacity(chars.len());
+        for (line_index, (line, line_width)) in lines.iter().zip(widths).enumerate() {
+            let left = origin[0]
+                + match self.h_align {
+                    HAlign::Left => 0.0,
+                    HAlign::Center => ((box_width - line_width) * 0.5).round(),
+                    HAlign::Right => box_width - line_width,
+                };
+            let line_top = top - line_index as f32 * advance;
+            let mut pen = 0.0;
+            let mut prev: Option<StyledChar> = None;
+            for index in line.clone() {
+                let sc = chars[index];
+                let bitmap = family.get(sc.style.font);
+                let font = &bitmap.font;
+                let Some(glyph) = font.glyph(sc.c) else {
+                    continue;
+                };
+                pen += self.kerning(family, prev, sc);
+                prev = Some(sc);
+                if glyph.width > 0 && glyph.height > 0 && !sc.c.is_whitespace() {
+                    // Line up baselines when variants have different ascents.
+                    let baseline = (regular.base as f32 - font.base as f32) * self.scale;
+                    let size = [
+                        glyph.width as f32 * self.scale,
+                        glyph.height as f32 * self.scale,
+                    ];
+                    let x = left + pen + glyph.offset[0] as f32 * self.scale;
+                    let y = line_top - baseline - glyph.offset[1] as f32 * self.scale - size[1];
+                    glyphs.push(RichGlyph {
+                        quad: GlyphQuad {
+                            page: glyph.page,
+                            position: [x, y],
+                            size,
+                            uv: font.glyph_uv(glyph),
+                        },
+                        material: bitmap.pages[glyph.page as usize],
+                        color: sc.style.color,
+                        effect: sc.style.effect,
+                        index,
+                    });
+                }
+                pen += glyph.advance as f32 * self.scale;
+            }
+        }
+
+        RichText {
+            glyphs,
+            lines,
+            size: [width, height],
+        }
+    }
+
+    /// Kerning only applies between glyphs of the same font.
+    fn kerning(&self, family: &FontFamily, prev: Option<StyledChar>, sc: StyledChar) -> f32 {
+        match prev {
+            Some(prev) if prev.style.font == sc.style.font => {
+                family.get(sc.style.font).font.kerning(prev.c, sc.c) as f32 * self.scale
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn advance(&self, family: &FontFamily, prev: Option<StyledChar>, sc: StyledChar) -> f32 {
+        let advance = family
+            .get(sc.style.font)
+            .font
+            .glyph(sc.c)
+            .map_or(0, |g| g.advance);
+        self.kerning(family, prev, sc) + advance as f32 * self.scale
+    }
+
+    /// Width of `chars` ignoring trailing whitespace.
+    fn measure(&self, family: &FontFamily, chars: &[StyledChar]) -> f32 {
+        let end = chars
+            .iter()
+            .rposition(|sc| !sc.c.is_whitespace())
+            .map_or(0, |i| i + 1);
+        self.measure_all(family, &chars[..end])
+    }
+
+    /// Greedy word wrap, breaking at the last space that fits or mid word when a single
+    /// word is wider than the line.
+    fn break_lines(&self, family: &FontFamily, chars: &[StyledChar]) -> Vec<Range<usize>> {
+        let mut lines = Vec::new();
+        let mut start = 0;
+        let mut pen = 0.0;
+        let mut space = None;
+        let mut i = 0;
+        while i < chars.len() {
+            let sc = chars[i];
+            if sc.c == '\n' {
+                lines.push(start..i);
+                start = i + 1;
+                pen = 0.0;
+                space = None;
+                i += 1;
+                continue;
+            }
+            let prev = (i > start).then(|| chars[i - 1]);
+            let advance = self.advance(family, prev, sc);
+            if sc.c == ' ' {
+                space = Some(i);
+            } else if self.max_width.is_some_and(|w| pen + advance > w) && i > start {
+                match space.take() {
+                    Some(space) => {
+                        lines.push(start..space);
+                        start = space + 1;
+                    }
+                    None => {
+                        lines.push(start..i);
+                        start = i;
+                    }
+                }
+                pen = self.measure_all(family, &chars[start..i]);
+                continue;
+            }
+            pen += advance;
+            i += 1;
+        }
+        lines.push(start..chars.len());
+        lines
+    }
+
+    fn measure_all(&self, family: &FontFamily, chars: &[StyledChar]) -> f32 {
+        let mut prev = None;
+        chars.iter().fold(0.0, |width, &sc| {
+            let advance = self.advance(family, prev, sc);
+            prev = Some(sc);
+            width + advance
+        })
+    }
+}
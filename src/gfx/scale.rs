@@ -0,0 +1,134 @@
+/// Converts between logical points, which UI and text are authored in, and the physical
+/// pixels the surface and input events use.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayScale {
+    /// Physical pixels per logical point reported by the platform, 2 on most HiDPI screens.
+    pub scale_factor: f64,
+    /// User preference applied on top of the scale factor for UI and text only.
+    pub ui_scale: f32,
+}
+
+impl Default for DisplayScale {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl DisplayScale {
+    pub fn new(scale_factor: f64) -> Self {
+        Self {
+            scale_factor,
+            ui_scale: 1.0,
+        }
+    }
+
+    pub fn with_ui_scale(mut self, ui_scale: f32) -> Self {
+        self.ui_scale = ui_scale;
+        self
+    }
+
+    /// Physical pixels per logical UI point.
+    #[inline]
+    pub fn ui_factor(&self) -> f32 {
+        self.scale_factor as f32 * self.ui_scale
+    }
+
+    #[inline]
+    pub fn to_physical(&self, logical: [f32; 2]) -> [f32; 2] {
+        logical.map(|c| c * self.scale_factor as f32)
+    }
+
+    #[inline]
+    pub fn to_logical(&self, physical: [f32; 2]) -> [f32; 2] {
+        physical.map(|c| c / self.scale_factor as f32)
+    }
+
+    /// Physical size of a UI element `size` logical points big.
+    #[inline]
+    pub fn ui_size(&self, size: f32) -> f32 {
+        size * self.ui_factor()
+    }
+
+    /// Pixel height to rasterize text of `size` logical points at, whole pixels keep
+    /// glyphs crisp.
+    pub fn text_px(&self, size: f32) -> f32 {
+        self.ui_size(size).round().max(1.0)
+    }
+
+    /// Turns a physical position, e.g. from CursorMoved, into logical UI points.
+    #[inline]
+    pub fn to_ui(&self, physical: [f32; 2]) -> [f32; 2] {
+        physical.map(|c| c / self.ui_factor())
+    }
+}
+
+/// How a VirtualResolution is fitted into the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScaleMode {
+    /// Fill the window, distorting the aspect ratio.
+    Stretch,
+    /// Largest size keeping the aspect ratio, letterboxed.
+    #[default]
+    Fit,
+    /// Largest whole multiple that fits, for pixel art.
+    Integer,
+}
+
+/// Fixed size the game renders at regardless of the window's physical size or DPI, placed
+/// in the window by `mode`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VirtualResolution {
+    pub size: [u32; 2],
+    pub mode: ScaleMode,
+}
+
+impl VirtualResolution {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            size: [width.max(1), height.max(1)],
+            mode: ScaleMode::default(),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: ScaleMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Physical pixels per virtual pixel along x and y in a `physical` sized window.
+    pub fn scale(&self, physical: [u32; 2]) -> [f32; 2] {
+        let [sx, sy] = [0, 1].map(|i| physical[i] as f32 / self.size[i] as f32);
+        match self.mode {
+            ScaleMode::Stretch => [sx, sy],
+            ScaleMode::Fit => [sx.min(sy); 2],
+            ScaleMode::Integer => [sx.min(sy).floor().max(1.0); 2],
+        }
+    }
+
+    /// Centered x, y, width, height in physical pixels, for DrawCtx::set_viewport.
+    pub fn viewport(&self, physical: [u32; 2]) -> [f32; 4] {
+        let scale = self.scale(physical);
+        let [w, h] = [0, 1].map(|i| self.size[i] as f32 * scale[i]);
+        let x = ((physical[0] as f32 - w) * 0.5).floor();
+        let y = ((physical[1] as f32 - h) * 0.5).floor();
+        [x, y, w, h]
+    }
+
+    /// Virtual position under physical window position `position`, None over the bars.
+    pub fn to_virtual(&self, position: [f32; 2], physical: [u32; 2]) -> Option<[f32; 2]> {
+        let [x, y, w, h] = self.viewport(physical);
+        let local = [position[0] - x, position[1] - y];
+        if local[0] < 0.0 || local[1] < 0.0 || local[0] >= w || local[1] >= h {
+            return None;
+        }
+        let scale = self.scale(physical);
+        Some([local[0] / scale[0], local[1] / scale[1]])
+    }
+
+    /// Physical window position of virtual position `position`.
+    pub fn to_physical(&self, position: [f32; 2], physical: [u32; 2]) -> [f32; 2] {
+        let [x, y, _, _] = self.viewport(physical);
+        let scale = self.scale(physical);
+        [x + position[0] * scale[0], y + position[1] * scale[1]]
+    }
+}
@@ -0,0 +1,580 @@
+use std::{collections::HashMap, ops::Range, sync::Arc};
+
+use cgmath::{
+    Deg, EuclideanSpace, InnerSpace, Matrix3, Matrix4, One, Point3, Quaternion, Rotation3, Vector3,
+};
+
+use crate::sys::{geom::bounds::Aabb3, rand::Rng};
+
+use super::{
+    cull::Frustum,
+    draw::DrawCtx,
+    model::{Material, Mesh},
+    wgpu::{buffer::InstanceRaw, vertex::Vertex3D},
+};
+
+/// A point instances can be placed at.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SurfacePoint {
+    pub position: Point3<f32>,
+    pub normal: Vector3<f32>,
+}
+
+/// Something instances are scattered over.
+pub trait ScatterSurface {
+    /// World area in square units, scaled by ScatterLayer::density into an instance count.
+    fn area(&self) -> f32;
+    /// A uniformly distributed point on the surface, None if the pick missed it.
+    fn sample(&self, rng: &mut Rng) -> Option<SurfacePoint>;
+}
+
+/// Terrain heights on a regular grid in the XZ plane, `columns` by `rows` samples
+/// `cell_size` apart starting at `origin`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeightField {
+    pub columns: usize,
+    pub rows: usize,
+    pub heights: Vec<f32>,
+    /// World x and z of the first sample.
+    pub origin: [f32; 2],
+    pub cell_size: f32,
+}
+
+impl HeightField {
+    pub fn new(
+        columns: usize,
+        rows: usize,
+        heights: Vec<f32>,
+        origin: [f32; 2],
+        cell_size: f32,
+    ) -> Self {
+        assert_eq!(
+            heights.len(),
+            columns * rows,
+            "HeightField::new => expected {} heights",
+            columns * rows
+        );
+        Self {
+            columns,
+            rows,
+            heights,
+            origin,
+            cell_size,
+        }
+    }
+
+    /// Heights from the luminance of an image, 0 to `height_scale`.
+    pub fn from_image(
+        image: &image::DynamicImage,
+        origin: [f32; 2],
+        cell_size: f32,
+        height_scale: f32,
+    ) -> Self {
+        let luma = image.to_luma8();
+        let heights = luma
+            .pixels()
+            .map(|p| p.0[0] as f32 / 255.0 * height_scale)
+            .collect();
+        Self::new(
+            luma.width() as usize,
+            luma.height() as usize,
+            heights,
+            origin,
+            cell_size,
+        )
+    }
+
+    /// World x and z extents.
+    pub fn size(&self) -> [f32; 2] {
+        [
+            self.columns.saturating_sub(1) as f32 * self.cell_size,
+            self.rows.saturating_sub(1) as f32 * self.cell_size,
+        ]
+    }
+
+    /// Bilinear height at world `x`, `z`, None off the field.
+    pub fn height(&self, x: f32, z: f32) -> Option<f32> {
+        let gx = (x - self.origin[0]) / self.cell_size;
+        let gz = (z - self.origin[1]) / self.cell_size;
+        let (max_x, max_z) = (
+            self.columns.saturating_sub(1) as f32,
+            self.rows.saturating_sub(1) as f32,
+        );
+        if self.heights.is_empty() || !(0.0..=max_x).contains(&gx) || !(0.0..=max_z).contains(&gz) {
+            return None;
+        }
+        let (x0, z0) = (gx.floor() as usize, gz.floor() as usize);
+        let (x1, z1) = ((x0 + 1).min(self.columns - 1), (z0 + 1).min(self.rows - 1));
+        let (tx, tz) = (gx.fract(), gz.fract());
+        let h = |x: usize, z: usize| self.heights[z * self.columns + x];
+        let near = h(x0, z0) + (h(x1, z0) - h(x0, z0)) * tx;
+        let far = h(x0, z1) + (h(x1, z1) - h(x0, z1)) * tx;
+        Some(near + (far - near) * tz)
+    }
+
+    /// Surface normal from central differences of the heights, clamped to the field's edges.
+    pub fn normal(&self, x: f32, z: f32) -> Vector3<f32> {
+        let e = self.cell_size * 0.5;
+        let [w, d] = self.size();
+        let h = |x: f32, z: f32| {
+            let x = x.clamp(self.origin[0], self.origin[0] + w);
+            let z = z.clamp(self.origin[1], self.origin[1] + d);
+            self.height(x, z).unwrap_or(0.0)
+        };
+        let (x0, x1) = (h(x - e, z), h(x + e, z));
+        let (z0, z1) = (h(x, z - e), h(x, z + e));
+        let span_x = (x + e).min(self.origin[0] + w) - (x - e).max(self.origin[0]);
+        let span_z = (z + e).min(self.origin[1] + d) - (z - e).max(self.origin[1]);
+        let dx = (x1 - x0) / span_x.max(f32::EPSILON);
+        let dz = (z1 - z0) / span_z.max(f32::EPSILON);
+        Vector3::new(-dx, 1.0, -dz).normalize()
+    }
+}
+
+impl ScatterSurface for HeightField {
+    fn area(&self) -> f32 {
+        let [w, d] = self.size();
+        w * d
+    }
+
+    fn sample(&self, rng: &mut Rng) -> Option<SurfacePoint> {
+        let [w, d] = self.size();
+        let x = self.origin[0] + rng.next_f32() * w;
+        let z = self.origin[1] + rng.next_f32() * d;
+        let y = self.height(x, z)?;
+        Some(SurfacePoint {
+            position: Point3::new(x, y, z),
+            normal: self.normal(x, z),
+        })
+    }
+}
+
+/// Triangles of a mesh in world space, picked by area.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshSurface {
+    triangles: Vec<[Point3<f32>; 3]>,
+    /// Running total of the triangle areas.
+    areas: Vec<f32>,
+}
+
+impl MeshSurface {
+    pub fn new(positions: &[Point3<f32>], indices: &[u32]) -> Self {
+        let mut total = 0.0;
+        let mut areas = Vec::with_capacity(indices.len() / 3);
+        let triangles = indices
+            .chunks_exact(3)
+            .map(|t| {
+                let tri = [
+                    positions[t[0] as usize],
+                    positions[t[1] as usize],
+                    positions[t[2] as usize],
+                ];
+                total += (tri[1] - tri[0]).cross(tri[2] - tri[0]).magnitude() * 0.5;
+                areas.push(total);
+                tri
+            })
+            .collect();
+        Self { triangles, areas }
+    }
+
+    /// Vertices of a mesh placed with `transform`, e.g. a BakedMesh.
+    pub fn from_vertices(vertices: &[Vertex3D], indices: &[u32], transform: Matrix4<f32>) -> Self {
+        let positions = vertices
+            .iter()
+            .map(|v| {
+                Point3::from_homogeneous(transform * Point3::from(v.position).to_homogeneous())
+            })
+            .collect::<Vec<_>>();
+        Self::new(&positions, indices)
+    }
+}
+
+impl ScatterSurface for MeshSurface {
+    fn area(&self) -> f32 {
+        self.areas.last().copied().unwrap_or(0.0)
+    }
+
+    fn sample(&self, rng: &mut Rng) -> Option<SurfacePoint> {
+        let pick = rng.next_f32() * self.area();
+        let i = self
+            .areas
+            .partition_point(|&a| a <= pick)
+            .min(self.triangles.len().checked_sub(1)?);
+        let [a, b, c] = self.triangles[i];
+        let normal = (b - a).cross(c - a);
+        if normal.magnitude2() == 0.0 {
+            return None;
+        }
+        // Folding the unit square keeps the barycentric pick uniform.
+        let (mut u, mut v) = (rng.next_f32(), rng.next_f32());
+        if u + v > 1.0 {
+            (u, v) = (1.0 - u, 1.0 - v);
+        }
+        Some(SurfacePoint {
+            position: a + (b - a) * u + (c - a) * v,
+            normal: normal.normalize(),
+        })
+    }
+}
+
+/// Values 0..1 over a world rectangle of the XZ plane, scaling how many instances survive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityMap {
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<f32>,
+    /// World x and z of the map's corner and its extent along both.
+    pub origin: [f32; 2],
+    pub size: [f32; 2],
+}
+
+impl DensityMap {
+    /// Values from the luminance of an image, the first row at `origin`'s z.
+    pub fn from_image(image: &image::DynamicImage, origin: [f32; 2], size: [f32; 2]) -> Self {
+        let luma = image.to_luma8();
+        Self {
+            width: luma.width() as usize,
+            height: luma.height() as usize,
+            values: luma.pixels().map(|p| p.0[0] as f32 / 255.0).collect(),
+            origin,
+            size,
+        }
+    }
+
+    /// Bilinear density at world `x`, `z`, 0 outside the map.
+    pub fn sample(&self, x: f32, z: f32) -> f32 {
+        let u = (x - self.origin[0]) / self.size[0];
+        let v = (z - self.origin[1]) / self.size[1];
+        if self.values.is_empty() || !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return 0.0;
+        }
+        let gx = u * self.width.saturating_sub(1) as f32;
+        let gz = v * self.height.saturating_sub(1) as f32;
+        let (x0, z0) = (gx.floor() as usize, gz.floor() as usize);
+        let (x1, z1) = ((x0 + 1).min(self.width - 1), (z0 + 1).min(self.height - 1));
+        let d = |x: usize, z: usize| self.values[z * self.width + x];
+        let near = d(x0, z0) + (d(x1, z0) - d(x0, z0)) * gx.fract();
+        let far = d(x0, z1) + (d(x1, z1) - d(x0, z1)) * gx.fract();
+        (near + (far - near) * gz.fract()).clamp(0.0, 1.0)
+    }
+}
+
+/// Shrinks instances to nothing between `start` and `end` distance from the viewer, chunks
+/// entirely past `end` aren't drawn.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ScatterFade {
+    pub start: f32,
+    pub end: f32,
+}
+
+impl ScatterFade {
+    pub fn new(start: f32, end: f32) -> Self {
+        Self {
+            start,
+            end: end.max(start),
+        }
+    }
+
+    /// Scale of an instance `distance` away, 1 up to start and 0 from end.
+    pub fn factor(&self, distance: f32) -> f32 {
+        if self.end <= self.start {
+            return if distance <= self.end { 1.0 } else { 0.0 };
+        }
+        let t = ((distance - self.start) / (self.end - self.start)).clamp(0.0, 1.0);
+        1.0 - t * t * (3.0 - 2.0 * t)
+    }
+}
+
+/// How one kind of instance (grass, rocks, trees) is spread over a surface.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScatterLayer {
+    /// Instances per square unit where the density map is 1.
+    pub density: f32,
+    pub density_map: Option<DensityMap>,
+    /// Uniform scale range of the instances.
+    pub scale: Range<f32>,
+    /// How far instances tilt from up towards the surface normal, 0..1.
+    pub align_to_normal: f32,
+    /// Steeper surfaces are left bare.
+    pub max_slope: Deg<f32>,
+    /// Cap on the instances generated.
+    pub max_instances: usize,
+}
+
+impl Default for ScatterLayer {
+    fn default() -> Self {
+        Self {
+            density: 1.0,
+            density_map: None,
+            scale: 1.0..1.0,
+            align_to_normal: 0.0,
+            max_slope: Deg(90.0),
+            max_instances: 1_000_000,
+        }
+    }
+}
+
+impl ScatterLayer {
+    pub fn new(density: f32) -> Self {
+        Self {
+            density,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_density_map(mut self, density_map: DensityMap) -> Self {
+        self.density_map = Some(density_map);
+        self
+    }
+
+    pub fn with_scale(mut self, scale: Range<f32>) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_align_to_normal(mut self, align_to_normal: f32) -> Self {
+        self.align_to_normal = align_to_normal;
+        self
+    }
+
+    pub fn with_max_slope(mut self, max_slope: Deg<f32>) -> Self {
+        self.max_slope = max_slope;
+        self
+    }
+
+    pub fn with_max_instances(mut self, max_instances: usize) -> Self {
+        self.max_instances = max_instances;
+        self
+    }
+
+    /// Places the layer's instances over `surface`. The same seed always gives the same
+    /// instances.
+    pub fn scatter(&self, surface: &impl ScatterSurface, seed: u64) -> Vec<ScatterInstance> {
+        let mut rng = Rng::new(seed);
+        let attempts = ((surface.area() * self.density).round() as usize).min(self.max_instances);
+        let min_up = self.max_slope.0.to_radians().cos();
+        let mut instances = Vec::with_capacity(attempts);
+        for _ in 0..attempts {
+            // Every attempt draws the same numbers so rejections don't shift later instances.
+            let point = surface.sample(&mut rng);
+            let (keep, yaw, scale) = (rng.next_f32(), rng.next_f32(), rng.next_f32());
+            let Some(point) = point else {
+                continue;
+            };
+            let density = self
+                .density_map
+                .as_ref()
+                .map_or(1.0, |map| map.sample(point.position.x, point.position.z));
+            if keep >= density || point.normal.y < min_up - 1e-6 {
+                continue;
+            }
+            let tilt = Quaternion::one().nlerp(
+                Quaternion::from_arc(Vector3::unit_y(), point.normal, None),
+                self.align_to_normal.clamp(0.0, 1.0),
+            );
+            let scale = self.scale.start + (self.scale.end - self.scale.start) * scale;
+            instances.push(ScatterInstance {
+                position: point.position,
+                rotation: tilt * Quaternion::from_angle_y(Deg(yaw * 360.0)),
+                scale,
+            });
+        }
+        instances
+    }
+}
+
+/// One scattered instance.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ScatterInstance {
+    pub position: Point3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: f32,
+}
+
+impl ScatterInstance {
+    /// Instance data with the scale multiplied by `fade`.
+    pub fn to_raw(&self, fade: f32) -> InstanceRaw {
+        let model = Matrix4::from_translation(self.position.to_vec())
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_scale(self.scale * fade);
+        InstanceRaw::new(model, Matrix3::from(self.rotation))
+    }
+}
+
+/// Instances grouped into a grid of square chunks on the XZ plane, stored chunk after chunk
+/// so every chunk is one contiguous instance range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScatterChunks {
+    pub chunk_size: f32,
+    pub instances: Vec<ScatterInstance>,
+    pub chunks: Vec<ScatterChunk>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScatterChunk {
+    /// Bounds of the instance positions grown by the mesh radius times the largest scale.
+    pub bounds: Aabb3,
+    pub instances: Range<u32>,
+}
+
+impl ScatterChunks {
+    /// `radius` is the bounding radius of the mesh drawn for each instance, at scale 1.
+    pub fn new(instances: Vec<ScatterInstance>, chunk_size: f32, radius: f32) -> Self {
+        let key = |p: Point3<f32>| {
+            (
+                (p.x / chunk_size).floor() as i32,
+                (p.z / chunk_size).floor() as i32,
+            )
+        };
+        let mut cells: HashMap<(i32, i32), Vec<ScatterInstance>> = HashMap::new();
+        for instance in instances {
+            cells
+                .entry(key(instance.position))
+                .or_default()
+                .push(instance);
+        }
+        let mut cells = cells.into_iter().collect::<Vec<_>>();
+        cells.sort_by_key(|(key, _)| (key.1, key.0));
+
+        let mut sorted = Vec::new();
+        let chunks = cells
+            .into_iter()
+            .map(|(_, cell)| {
+                let start = sorted.len() as u32;
+                let mut bounds = Aabb3::EMPTY;
+                for instance in &cell {
+                    let r = radius * instance.scale;
+                    let p = instance.position;
+                    bounds = bounds
+                        .expanded(p - Vector3::new(r, r, r))
+                        .expanded(p + Vector3::new(r, r, r));
+                }
+                sorted.extend(cell);
+                ScatterChunk {
+                    bounds,
+                    instances: start..sorted.len() as u32,
+                }
+            })
+            .collect();
+        Self {
+            chunk_size,
+            instances: sorted,
+            chunks,
+        }
+    }
+
+    /// Indices of the chunks inside `frustum` that start before `fade.end` from `viewer`.
+    pub fn visible(&self, frustum: &Frustum, viewer: Point3<f32>, fade: ScatterFade) -> Vec<usize> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| {
+                let center = chunk.bounds.center();
+                let radius = chunk.bounds.size().magnitude() * 0.5;
+                (center - viewer).magnitude() - radius <= fade.end
+                    && frustum.intersects_sphere(center, radius)
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+/// Scattered instances drawn through the instanced mesh path. update culls chunks by
+/// frustum and distance and refreshes the faded instances, draw issues one instanced draw
+/// per run of visible chunks.
+#[derive(Debug)]
+pub struct Scatter {
+    chunks: ScatterChunks,
+    fade: ScatterFade,
+    /// Whether each chunk's uploaded instances are at full scale.
+    unfaded: Vec<bool>,
+    visible: Vec<Range<u32>>,
+    buffer: Arc<wgpu::Buffer>,
+}
+
+impl Scatter {
+    pub fn new(device: &wgpu::Device, chunks: ScatterChunks, fade: ScatterFade) -> Self {
+        let raw = chunks
+            .instances
+            .iter()
+            .map(|instance| instance.to_raw(1.0))
+            .collect::<Vec<_>>();
+        let buffer = wgpu::util::DeviceExt::create_buffer_init(
+            device,
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Scatter Instances"),
+                contents: bytemuck::cast_slice(&raw),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            },
+        );
+        Self {
+            unfaded: vec![true; chunks.chunks.len()],
+            chunks,
+            fade,
+            visible: Vec::new(),
+            buffer: Arc::new(buffer),
+        }
+    }
+
+    #[inline]
+    pub fn chunks(&self) -> &ScatterChunks {
+        &self.chunks
+    }
+
+    #[inline]
+    pub fn fade(&self) -> ScatterFade {
+        self.fade
+    }
+
+    /// Instance ranges drawn by draw, as of the last update.
+    #[inline]
+    pub fn visible(&self) -> &[Range<u32>] {
+        &self.visible
+    }
+
+    /// Culls the chunks against `view_proj` and rewrites the instances of chunks within the
+    /// fade band around `viewer`. Chunks nearer than fade.start are only written once.
+    pub fn update(&mut self, ctx: &mut DrawCtx, view_proj: Matrix4<f32>, viewer: Point3<f32>) {
+        let frustum = Frustum::from_view_proj(view_proj);
+        self.visible.clear();
+        for i in self.chunks.visible(&frustum, viewer, self.fade) {
+            let chunk = &self.chunks.chunks[i];
+            let radius = chunk.bounds.size().magnitude() * 0.5;
+            let far = (chunk.bounds.center() - viewer).magnitude() + radius;
+            let fading = far > self.fade.start;
+            if fading || !self.unfaded[i] {
+                let range = chunk.instances.start as usize..chunk.instances.end as usize;
+                let raw = self.chunks.instances[range]
+                    .iter()
+                    .map(|instance| {
+                        let distance = (instance.position - viewer).magnitude();
+                        instance.to_raw(if fading {
+                            self.fade.factor(distance)
+                        } else {
+                            1.0
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let offset =
+                    chunk.instances.start as u64 * std::mem::size_of::<InstanceRaw>() as u64;
+                ctx.write_buffer(self.buffer.clone(), offset, bytemuck::cast_slice(&raw));
+                self.unfaded[i] = !fading;
+            }
+            match self.visible.last_mut() {
+                Some(last) if last.end == chunk.instances.start => last.end = chunk.instances.end,
+                _ => self.visible.push(chunk.instances.clone()),
+            }
+        }
+    }
+
+    /// Draws `mesh` for every visible instance.
+    pub fn draw(&self, ctx: &mut DrawCtx, mesh: &Mesh, mat: &Material) {
+        if self.visible.is_empty() {
+            return;
+        }
+        ctx.set_vertex_buffer(1, self.buffer.clone());
+        for range in &self.visible {
+            ctx.draw_mesh_instanced(mesh, mat, range.clone());
+        }
+    }
+}
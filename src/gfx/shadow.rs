@@ -0,0 +1,655 @@
+use std::{ops::Range, rc::Rc, sync::Arc};
+
+use cgmath::{
+    ortho, EuclideanSpace, InnerSpace, Matrix, Matrix4, Point3, SquareMatrix, Transform, Vector3,
+    Vector4,
+};
+use wgpu::util::DeviceExt;
+
+use crate::{eng::command::RenderCommand, sys::math::OPENGL_TO_WGPU_MATRIX};
+
+use super::{
+    camera::{Camera, Projection},
+    draw::DrawCtx,
+    model::Model,
+    wgpu::{buffer::InstanceRaw, texture::Texture, vertex::Vertex3D},
+};
+
+/// Most cascades a CascadedShadows can split the view into.
+pub const MAX_CASCADES: usize = 4;
+
+/// Sun like light shining along `direction` everywhere, the light cascaded shadows are cast
+/// from.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DirectionalLight {
+    pub direction: Vector3<f32>,
+    pub color: [f32; 3],
+    pub intensity: f32,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vector3<f32>) -> Self {
+        Self {
+            direction: direction.normalize(),
+            color: [1.0; 3],
+            intensity: 1.0,
+        }
+    }
+
+    pub fn with_color(mut self, color: [f32; 3]) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ShadowSettings {
+    /// Clamped to 1..=MAX_CASCADES.
+    pub cascades: usize,
+    /// Width and height of each cascade's depth map.
+    pub resolution: u32,
+    /// Blend between uniform (0) and logarithmic (1) cascade splits.
+    pub split_lambda: f32,
+    /// Shadows end here or at the far plane, whichever is closer.
+    pub max_distance: f32,
+    /// How far behind a cascade casters are still rendered.
+    pub caster_distance: f32,
+    /// Rasterizer bias of the depth passes, against shadow acne.
+    pub depth_bias: wgpu::DepthBiasState,
+    /// World units receivers are pushed along their normal before the lookup.
+    pub normal_bias: f32,
+    /// PCF kernel radius in texels, 0 takes a single comparison.
+    pub pcf_radius: u32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            cascades: MAX_CASCADES,
+            resolution: 2048,
+            split_lambda: 0.75,
+            max_distance: 100.0,
+            caster_distance: 50.0,
+            depth_bias: wgpu::DepthBiasState {
+                constant: 2,
+                slope_scale: 2.0,
+                clamp: 0.0,
+            },
+            normal_bias: 0.02,
+            pcf_radius: 1,
+        }
+    }
+}
+
+impl ShadowSettings {
+    pub fn with_cascades(mut self, cascades: usize) -> Self {
+        self.cascades = cascades;
+        self
+    }
+
+    pub fn with_resolution(mut self, resolution: u32) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    pub fn with_max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = max_distance;
+        self
+    }
+
+    pub fn with_pcf_radius(mut self, pcf_radius: u32) -> Self {
+        self.pcf_radius = pcf_radius;
+        self
+    }
+
+    fn cascade_count(&self) -> usize {
+        self.cascades.clamp(1, MAX_CASCADES)
+    }
+}
+
+/// One slice of the view frustum and the light space projection covering it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Cascade {
+    pub view_proj: Matrix4<f32>,
+    /// View distances the cascade covers.
+    pub near: f32,
+    pub far: f32,
+    /// World size of one shadow map texel.
+    pub texel_size: f32,
+}
+
+/// View distances splitting `near..far` into `count` cascades, `count + 1` values from near
+/// to far. `lambda` blends uniform and logarithmic splits.
+pub fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    (0..=count)
+        .map(|i| {
+            let t = i as f32 / count as f32;
+            let log = near * (far / near).powf(t);
+            let uniform = near + (far - near) * t;
+            lambda * log + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+/// Fits an orthographic light projection around the view distances `near..far` of the
+/// camera. The cascade is bounded by a sphere so its size doesn't change as the camera
+/// turns, and its origin is snapped to whole texels so shadow edges don't shimmer as the
+/// camera moves.
+pub fn fit_cascade(
+    view: Matrix4<f32>,
+    proj: Matrix4<f32>,
+    (near, far): (f32, f32),
+    light_dir: Vector3<f32>,
+    resolution: u32,
+    caster_distance: f32,
+) -> Cascade {
+    let inv_proj = proj.invert().unwrap_or(Matrix4::identity());
+    let inv_view = view.invert().unwrap_or(Matrix4::identity());
+    let mut corners = Vec::with_capacity(8);
+    for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+        let p = inv_proj * Vector4::new(x, y, 0.0, 1.0);
+        let p = p.truncate() / p.w;
+        for depth in [near, far] {
+            let corner = Point3::from_vec(p * (depth / -p.z));
+            corners.push(inv_view.transform_point(corner));
+        }
+    }
+    let center = Point3::centroid(&corners);
+    let radius = corners
+        .iter()
+        .map(|c| (c - center).magnitude())
+        .fold(0.0, f32::max);
+    // Rounded up so float noise doesn't change the texel size from frame to frame.
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let light_dir = light_dir.normalize();
+    let up = if light_dir.y.abs() > 0.99 {
+        Vector3::unit_z()
+    } else {
+        Vector3::unit_y()
+    };
+    let eye = center - light_dir * (radius + caster_distance);
+    let light_view = Matrix4::look_at_rh(eye, center, up);
+    let mut light_proj = OPENGL_TO_WGPU_MATRIX
+        * ortho(
+            -radius,
+            radius,
+            -radius,
+            radius,
+            0.0,
+            2.0 * radius + caster_distance,
+        );
+
+    let half = resolution as f32 * 0.5;
+    let origin = (light_proj * light_view) * Vector4::new(0.0, 0.0, 0.0, 1.0);
+    let texel = origin.truncate().truncate() * half;
+    let snapped = texel.map(f32::round);
+    light_proj[3][0] += (snapped.x - texel.x) / half;
+    light_proj[3][1] += (snapped.y - texel.y) / half;
+
+    Cascade {
+        view_proj: light_proj * light_view,
+        near,
+        far,
+        texel_size: 2.0 * radius / resolution as f32,
+    }
+}
+
+/// GPU layout of the shadow parameters, see shadowed.wgsl.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ShadowUniform {
+    cascades: [[[f32; 4]; 4]; MAX_CASCADES],
+    splits: [f32; 4],
+    view_row: [f32; 4],
+    light_dir: [f32; 4],
+    color: [f32; 4],
+    params: [f32; 4],
+}
+
+/// A model to render into the shadow map, with the instance buffer it is drawn with.
+#[derive(Clone)]
+pub struct ShadowCaster<'a> {
+    pub model: &'a Model,
+    pub instances: Arc<wgpu::Buffer>,
+    pub range: Range<u32>,
+}
+
+/// Cascaded shadow maps for a DirectionalLight. update fits the cascades to the camera,
+/// render draws the casters into one depth pass per cascade, and models drawn with
+/// DrawCtx::draw_model_shadowed are lit by the light through the shadow map, on top of the
+/// window light.
+#[derive(Debug)]
+pub struct CascadedShadows {
+    settings: ShadowSettings,
+    light: DirectionalLight,
+    cascades: Vec<Cascade>,
+    debug_cascades: bool,
+    layers: Vec<Rc<Texture>>,
+    _map: wgpu::Texture,
+    depth_pipeline: Arc<wgpu::RenderPipeline>,
+    caster_buffers: Vec<Arc<wgpu::Buffer>>,
+    caster_bind_groups: Vec<Arc<wgpu::BindGroup>>,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    uniform: Arc<wgpu::Buffer>,
+    bind_group: Arc<wgpu::BindGroup>,
+}
+
+impl CascadedShadows {
+    /// `layouts` are the texture, camera and light bind group layouts of the window's 3D
+    /// pipeline, the shadows are bound after them at group 3.
+    pub fn new(
+        device: &wgpu::Device,
+        color_format: wgpu::TextureFormat,
+        layouts: [&wgpu::BindGroupLayout; 3],
+        settings: ShadowSettings,
+    ) -> Self {
+        let count = settings.cascade_count();
+        let map = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Cascaded Shadow Map"),
+            size: wgpu::Extent3d {
+                width: settings.resolution,
+                height: settings.resolution,
+                depth_or_array_layers: count as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Texture::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+        let layers = (0..count as u32)
+            .map(|layer| {
+                let view = map.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Shadow Cascade"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                });
+                Rc::new(Texture {
+                    handle: map.clone(),
+                    view,
+                    sampler: sampler.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let array_view = map.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Shadow Cascades"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+
+        let caster_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("shadow_caster_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let caster_buffers = (0..count)
+            .map(|_| {
+                Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Shadow Caster Uniform"),
+                    size: std::mem::size_of::<[[f32; 4]; 4]>() as u64,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }))
+            })
+            .collect::<Vec<_>>();
+        let caster_bind_groups = caster_buffers
+            .iter()
+            .map(|buffer| {
+                Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("shadow_caster_bind_group"),
+                    layout: &caster_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                }))
+            })
+            .collect();
+        let depth_pipeline = create_depth_pipeline(device, &caster_layout, settings.depth_bias);
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("shadow_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        sample_type: wgpu::TextureSampleType::Depth,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+            ],
+        });
+        let uniform = Arc::new(
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Shadow Uniform"),
+                contents: bytemuck::bytes_of(&ShadowUniform::zeroed_for(&settings)),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }),
+        );
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shadow_bind_group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&array_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        let pipeline = create_shadowed_pipeline(device, color_format, layouts, &layout);
+
+        Self {
+            settings,
+            light: DirectionalLight::new(-Vector3::unit_y()),
+            cascades: Vec::new(),
+            debug_cascades: false,
+            layers,
+            _map: map,
+            depth_pipeline: Arc::new(depth_pipeline),
+            caster_buffers,
+            caster_bind_groups,
+            pipeline: Arc::new(pipeline),
+            uniform,
+            bind_group: Arc::new(bind_group),
+        }
+    }
+
+    #[inline]
+    pub fn settings(&self) -> &ShadowSettings {
+        &self.settings
+    }
+
+    #[inline]
+    pub fn light(&self) -> &DirectionalLight {
+        &self.light
+    }
+
+    /// Cascades fitted by the last update, nearest first.
+    #[inline]
+    pub fn cascades(&self) -> &[Cascade] {
+        &self.cascades
+    }
+
+    #[inline]
+    pub fn debug_cascades(&self) -> bool {
+        self.debug_cascades
+    }
+
+    /// Tints shadowed models by the cascade they sample, red, green, blue then yellow.
+    pub fn set_debug_cascades(&mut self, debug_cascades: bool) {
+        self.debug_cascades = debug_cascades;
+    }
+
+    #[inline]
+    pub(crate) fn pipeline(&self) -> Arc<wgpu::RenderPipeline> {
+        self.pipeline.clone()
+    }
+
+    #[inline]
+    pub(crate) fn bind_group(&self) -> Arc<wgpu::BindGroup> {
+        self.bind_group.clone()
+    }
+
+    /// Fits the cascades to the camera and uploads them with `light`.
+    pub fn update(
+        &mut self,
+        ctx: &DrawCtx,
+        camera: &Camera,
+        projection: &Projection,
+        light: DirectionalLight,
+    ) {
+        let view = camera.calc_view_matrix();
+        let proj = projection.calc_matrix();
+        let far = projection.zfar().min(self.settings.max_distance);
+        let splits = cascade_splits(
+            projection.znear(),
+            far,
+            self.settings.cascade_count(),
+            self.settings.split_lambda,
+        );
+        self.light = light;
+        self.cascades = splits
+            .windows(2)
+            .map(|split| {
+                fit_cascade(
+                    view,
+                    proj,
+                    (split[0], split[1]),
+                    light.direction,
+                    self.settings.resolution,
+                    self.settings.caster_distance,
+                )
+            })
+            .collect();
+
+        let mut uniform = ShadowUniform::zeroed_for(&self.settings);
+        for (i, cascade) in self.cascades.iter().enumerate() {
+            uniform.cascades[i] = cascade.view_proj.into();
+            uniform.splits[i] = cascade.far;
+            let matrix: [[f32; 4]; 4] = cascade.view_proj.into();
+            ctx.write_buffer(
+                self.caster_buffers[i].clone(),
+                0,
+                bytemuck::cast_slice(&matrix),
+            );
+        }
+        uniform.view_row = view.row(2).into();
+        uniform.light_dir = light.direction.extend(light.intensity).into();
+        uniform.color = [
+            light.color[0],
+            light.color[1],
+            light.color[2],
+            self.cascades.len() as f32,
+        ];
+        uniform.params[3] = if self.debug_cascades { 1.0 } else { 0.0 };
+        ctx.write_buffer(self.uniform.clone(), 0, bytemuck::bytes_of(&uniform));
+    }
+
+    /// Records one depth pass per cascade drawing every caster. Passes run before the
+    /// frame's other render passes.
+    pub fn render(&self, ctx: &mut DrawCtx, casters: &[ShadowCaster]) {
+        for (i, layer) in self.layers.iter().enumerate().take(self.cascades.len()) {
+            let label = format!("Shadow Cascade {}", i);
+            let pass = ctx.begin_shadow_pass(layer, &label);
+            pass.command_queue.extend([
+                RenderCommand::SetPipeline(self.depth_pipeline.clone()),
+                RenderCommand::SetBindGroup(0, self.caster_bind_groups[i].clone(), None),
+            ]);
+            push_casters(&mut pass.command_queue, casters);
+        }
+    }
+}
+
+/// Draws of every caster's meshes, after the depth pipeline and caster bind group are set.
+pub(crate) fn push_casters(queue: &mut Vec<RenderCommand>, casters: &[ShadowCaster]) {
+    for caster in casters {
+        queue.push(RenderCommand::SetVertexBuffer(1, caster.instances.clone()));
+        for mesh in &caster.model.meshes {
+            queue.extend([
+                RenderCommand::SetVertexBuffer(0, mesh.vert_buff.clone()),
+                RenderCommand::SetIndexBuffer(mesh.index_buff.clone(), wgpu::IndexFormat::Uint32),
+                RenderCommand::DrawIndexed(0..mesh.num_elements, 0, caster.range.clone()),
+            ]);
+        }
+    }
+}
+
+impl ShadowUniform {
+    fn zeroed_for(settings: &ShadowSettings) -> Self {
+        Self {
+            cascades: [Matrix4::identity().into(); MAX_CASCADES],
+            splits: [0.0; 4],
+            view_row: [0.0; 4],
+            light_dir: [0.0, -1.0, 0.0, 0.0],
+            color: [0.0; 4],
+            params: [
+                1.0 / settings.resolution as f32,
+                settings.normal_bias,
+                settings.pcf_radius as f32,
+                0.0,
+            ],
+        }
+    }
+}
+
+fn create_depth_pipeline(
+    device: &wgpu::Device,
+    caster_layout: &wgpu::BindGroupLayout,
+    depth_bias: wgpu::DepthBiasState,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Shadow Depth Pipeline Layout"),
+        bind_group_layouts: &[Some(caster_layout)],
+        immediate_size: 0,
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Shadow Depth Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/shadow_depth.wgsl").into()),
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Shadow Depth Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            compilation_options: Default::default(),
+            buffers: &[
+                Some(Vertex3D::buffer_layout()),
+                Some(InstanceRaw::buffer_layout()),
+            ],
+        },
+        fragment: None,
+        primitive: wgpu::PrimitiveState {
+            cull_mode: Some(wgpu::Face::Back),
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled: Some(true),
+            depth_compare: Some(wgpu::CompareFunction::LessEqual),
+            stencil: wgpu::StencilState::default(),
+            bias: depth_bias,
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview_mask: None,
+        cache: None,
+    })
+}
+
+fn create_shadowed_pipeline(
+    device: &wgpu::Device,
+    color_format: wgpu::TextureFormat,
+    layouts: [&wgpu::BindGroupLayout; 3],
+    shadow_layout: &wgpu::BindGroupLayout,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Shadowed Pipeline Layout"),
+        bind_group_layouts: &[
+            Some(layouts[0]),
+            Some(layouts[1]),
+            Some(layouts[2]),
+            Some(shadow_layout),
+        ],
+        immediate_size: 0,
+    });
+    let source = format!(
+        "{}\n{}",
+        include_str!("../shaders/basic.wgsl"),
+        include_str!("../shaders/shadowed.wgsl")
+    );
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Shadowed Shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Shadowed Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            compilation_options: Default::default(),
+            buffers: &[
+                Some(Vertex3D::buffer_layout()),
+                Some(InstanceRaw::buffer_layout()),
+            ],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_shadowed"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: color_format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            cull_mode: Some(wgpu::Face::Back),
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled: Some(true),
+            depth_compare: Some(wgpu::CompareFunction::Less),
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview_mask: None,
+        cache: None,
+    })
+}
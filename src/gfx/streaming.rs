@@ -0,0 +1,302 @@
+use std::{cmp::Reverse, sync::Arc};
+
+use cgmath::Rad;
+
+use crate::sys::baked::{BakedTexture, TextureEncoding};
+
+use super::{
+    model::Material,
+    wgpu::texture::{Texture, TextureType},
+};
+
+/// Mip level giving about one texel per pixel when a texture `size` texels across covers
+/// `screen_pixels` on screen.
+pub fn mip_for_screen_size(size: u32, screen_pixels: f32) -> u32 {
+    if screen_pixels <= 0.0 {
+        return u32::MAX;
+    }
+    (size as f32 / screen_pixels).log2().floor().max(0.0) as u32
+}
+
+/// Height in pixels of a sphere of `radius` at `distance` from a perspective camera.
+pub fn screen_size(radius: f32, distance: f32, fov_y: Rad<f32>, viewport_height: f32) -> f32 {
+    if distance <= radius {
+        return f32::INFINITY;
+    }
+    radius / (distance * (fov_y.0 * 0.5).tan()) * viewport_height
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreamingSettings {
+    /// VRAM budget in bytes shared by every streamed texture.
+    pub budget: u64,
+    /// Mips this size and smaller are always resident.
+    pub low_res_size: u32,
+    /// Frames without a request before a texture falls back to its low res mips.
+    pub evict_after: u64,
+    /// Textures that get higher mips uploaded per update, spreads uploads over frames.
+    pub max_uploads_per_update: usize,
+}
+
+impl Default for StreamingSettings {
+    fn default() -> Self {
+        Self {
+            budget: 256 * 1024 * 1024,
+            low_res_size: 64,
+            evict_after: 120,
+            max_uploads_per_update: 4,
+        }
+    }
+}
+
+/// Which mips of a streamed texture are on the GPU, independent of the GPU texture itself.
+/// Mip 0 is the full resolution, a resident mip of N means mips N and smaller are loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MipResidency {
+    pub width: u32,
+    pub height: u32,
+    pub mip_count: u32,
+    /// How the mips are stored on the GPU, see Texture::upload_encoding.
+    pub encoding: TextureEncoding,
+    /// Largest mip that always stays resident.
+    pub floor: u32,
+    pub resident: u32,
+    /// Largest mip asked for by the last request.
+    pub wanted: u32,
+    /// Frame of the last request.
+    pub last_used: u64,
+}
+
+impl MipResidency {
+    pub fn new(width: u32, height: u32, mip_count: u32, low_res_size: u32) -> Self {
+        let last = mip_count.saturating_sub(1);
+        let floor = (0..last)
+            .find(|&level| (width.max(height) >> level) <= low_res_size)
+            .unwrap_or(last);
+        Self {
+            width,
+            height,
+            mip_count,
+            encoding: TextureEncoding::Rgba8,
+            floor,
+            resident: floor,
+            wanted: floor,
+            last_used: 0,
+        }
+    }
+
+    /// Bytes of the mips from `first_mip` down.
+    pub fn bytes(&self, first_mip: u32) -> u64 {
+        (first_mip..self.mip_count)
+            .map(|level| {
+                let w = (self.width >> level).max(1);
+                let h = (self.height >> level).max(1);
+                self.encoding.mip_bytes(w, h) as u64
+            })
+            .sum()
+    }
+}
+
+/// Resident mip every texture should have at `frame`. Textures not requested for a while
+/// fall back to their floor, at most `max_uploads_per_update` of the most recently used
+/// textures stream in, and while over budget the least recently used give up their largest mip.
+pub fn plan_residency(
+    textures: &[MipResidency],
+    settings: &StreamingSettings,
+    frame: u64,
+) -> Vec<u32> {
+    let mut targets: Vec<u32> = textures
+        .iter()
+        .map(|t| {
+            if frame.saturating_sub(t.last_used) > settings.evict_after {
+                t.floor
+            } else {
+                t.wanted.min(t.floor)
+            }
+        })
+        .collect();
+
+    let mut incoming: Vec<usize> = (0..textures.len())
+        .filter(|&i| targets[i] < textures[i].resident)
+        .collect();
+    incoming.sort_by_key(|&i| Reverse(textures[i].last_used));
+    for &i in incoming.iter().skip(settings.max_uploads_per_update) {
+        targets[i] = textures[i].resident;
+    }
+
+    let mut total: u64 = textures
+        .iter()
+        .zip(&targets)
+        .map(|(t, &mip)| t.bytes(mip))
+        .sum();
+    while total > settings.budget {
+        // Oldest first, the largest of those first.
+        let Some(i) = (0..textures.len())
+            .filter(|&i| targets[i] < textures[i].floor)
+            .min_by_key(|&i| (textures[i].last_used, targets[i]))
+        else {
+            break;
+        };
+        let t = &textures[i];
+        total -= t.bytes(targets[i]) - t.bytes(targets[i] + 1);
+        targets[i] += 1;
+    }
+    targets
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StreamedTexture(usize);
+
+struct StreamEntry {
+    source: Arc<BakedTexture>,
+    ty: TextureType,
+    label: String,
+    texture: Texture,
+    residency: MipResidency,
+    /// Largest mip requested since the last update.
+    requested: Option<u32>,
+}
+
+/// Streams mips of baked textures in and out under a VRAM budget. Only the low res mips
+/// are uploaded up front, larger ones follow the screen size passed to request. Dropping
+/// or adding mips recreates the GPU texture at the new size, so materials using it have
+/// to be rebound with bind_material after update reports a change.
+pub struct TextureStreamer {
+    entries: Vec<StreamEntry>,
+    frame: u64,
+    pub settings: StreamingSettings,
+}
+
+impl TextureStreamer {
+    pub fn new(settings: StreamingSettings) -> Self {
+        Self {
+            entries: Vec::new(),
+            frame: 0,
+            settings,
+        }
+    }
+
+    /// Uploads the low res mips of `source` and starts tracking it.
+    pub fn add(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: Arc<BakedTexture>,
+        ty: TextureType,
+        label: &str,
+    ) -> StreamedTexture {
+        let mut residency = MipResidency::new(
+            source.width,
+            source.height,
+            source.mips.len() as u32,
+            self.settings.low_res_size,
+        );
+        residency.last_used = self.frame;
+        residency.encoding = Texture::upload_encoding(device, &source, 0);
+        let texture =
+            Texture::from_baked_mips(device, queue, &source, residency.resident, ty, Some(label));
+        self.entries.push(StreamEntry {
+            source,
+            ty,
+            label: label.to_string(),
+            texture,
+            residency,
+            requested: None,
+        });
+        StreamedTexture(self.entries.len() - 1)
+    }
+
+    /// Currently resident texture, its top level is mip `residency(id).resident`.
+    #[inline]
+    pub fn texture(&self, id: StreamedTexture) -> &Texture {
+        &self.entries[id.0].texture
+    }
+
+    #[inline]
+    pub fn residency(&self, id: StreamedTexture) -> &MipResidency {
+        &self.entries[id.0].residency
+    }
+
+    pub fn resident_bytes(&self) -> u64 {
+        self.entries
+            .iter()
+            .map(|e| e.residency.bytes(e.residency.resident))
+            .sum()
+    }
+
+    /// Asks for enough detail to cover `screen_pixels`, call it every frame the texture is
+    /// visible. The largest request of the frame wins.
+    pub fn request(&mut self, id: StreamedTexture, screen_pixels: f32) {
+        let entry = &mut self.entries[id.0];
+        let size = entry.residency.width.max(entry.residency.height);
+        let mip = mip_for_screen_size(size, screen_pixels);
+        entry.requested = Some(entry.requested.map_or(mip, |r| r.min(mip)));
+    }
+
+    /// Same as request for an object of `radius` at `distance` from the camera.
+    pub fn request_at_distance(
+        &mut self,
+        id: StreamedTexture,
+        radius: f32,
+        distance: f32,
+        fov_y: Rad<f32>,
+        viewport_height: f32,
+    ) {
+        self.request(id, screen_size(radius, distance, fov_y, viewport_height));
+    }
+
+    /// Applies this frame's requests, uploads or drops mips and returns the textures that
+    /// changed. Call once per frame after the requests.
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<StreamedTexture> {
+        self.frame += 1;
+        for entry in self.entries.iter_mut() {
+            if let Some(mip) = entry.requested.take() {
+                entry.residency.wanted = mip;
+                entry.residency.last_used = self.frame;
+            }
+        }
+        let residency: Vec<MipResidency> = self.entries.iter().map(|e| e.residency).collect();
+        let targets = plan_residency(&residency, &self.settings, self.frame);
+
+        let mut changed = Vec::new();
+        for (i, (entry, target)) in self.entries.iter_mut().zip(targets).enumerate() {
+            if entry.residency.resident == target {
+                continue;
+            }
+            entry.texture = Texture::from_baked_mips(
+                device,
+                queue,
+                &entry.source,
+                target,
+                entry.ty,
+                Some(&entry.label),
+            );
+            entry.residency.resident = target;
+            changed.push(StreamedTexture(i));
+        }
+        changed
+    }
+
+    /// Rebinds `material` to whatever is resident for `diffuse` and `normal`.
+    pub fn bind_material(
+        &self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        material: &mut Material,
+        diffuse: StreamedTexture,
+        normal: StreamedTexture,
+    ) {
+        material.set_textures(
+            device,
+            layout,
+            self.texture(diffuse).clone(),
+            self.texture(normal).clone(),
+        );
+    }
+}
+
+impl Default for TextureStreamer {
+    fn default() -> Self {
+        Self::new(StreamingSettings::default())
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::{HashMap, HashSet};
+
+use ab_glyph::{Font, FontArc, PxScale, ScaleFont};
+
+use crate::sys::rect_pack::SkylinePacker;
+
+use super::{
+    draw::DrawCtx,
+    font::{BmChar, BmFont, TextStyle},
+    quad::{QuadBuffer, QuadMaterial, Sprite},
+    wgpu::texture::Texture,
+};
+
+/// Format of the atlas texture, white with the glyph coverage in alpha so sprite colors
+/// tint the text.
+pub const ATLAS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Union of two rects given as [x, y, width, height].
+pub fn union_rect(a: Option<[u32; 4]>, b: [u32; 4]) -> [u32; 4] {
+    let Some(a) = a else {
+        return b;
+    };
+    let min = [a[0].min(b[0]), a[1].min(b[1])];
+    let max = [
+        (a[0] + a[2]).max(b[0] + b[2]),
+        (a[1] + a[3]).max(b[1] + b[3]),
+    ];
+    [min[0], min[1], max[0] - min[0], max[1] - min[1]]
+}
+
+/// A TrueType or OpenType font rasterized at one pixel size into a single page atlas.
+/// Glyphs are rasterized the first time text using them is prepared, and their placements
+/// and kerning are kept as a BmFont so TTF text lays out exactly like bitmap fonts.
+pub struct GlyphAtlas {
+    font: FontArc,
+    px: f32,
+    packer: SkylinePacker,
+    pixels: image::RgbaImage,
+    layout: BmFont,
+    kerned: HashSet<(char, char)>,
+    /// Glyphs that didn't fit, not retried until clear.
+    rejected: HashSet<char>,
+    /// Region rasterized since the last take_dirty, as [x, y, width, height].
+    dirty: Option<[u32; 4]>,
+}
+
+impl std::fmt::Debug for GlyphAtlas {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GlyphAtlas")
+            .field("px", &self.px)
+            .field("size", &self.size())
+            .field("glyphs", &self.layout.chars.len())
+            .finish()
+    }
+}
+
+impl GlyphAtlas {
+    pub const DEFAULT_SIZE: u32 = 512;
+
+    /// Parses `data` and prepares an empty `size` x `size` atlas for glyphs `px` pixels high.
+    pub fn new(data: Vec<u8>, px: f32, size: u32) -> anyhow::Result<Self> {
+        let font = FontArc::try_from_vec(data)
+            .map_err(|e| anyhow::anyhow!("GlyphAtlas::new => invalid font: {}", e))?;
+        let scaled = font.as_scaled(PxScale::from(px));
+        let layout = BmFont {
+            face: String::new(),
+            size: px.round() as i32,
+            line_height: (scaled.ascent() - scaled.descent() + scaled.line_gap()).ceil() as u32,
+            base: scaled.ascent().round() as u32,
+            page_size: [size, size],
+            pages: Vec::new(),
+            chars: HashMap::new(),
+            kerning: HashMap::new(),
+        };
+        Ok(Self {
+            font,
+            px,
+            packer: SkylinePacker::new(size, size).with_padding(1),
+            pixels: image::RgbaImage::from_pixel(size, size, image::Rgba([255, 255, 255, 0])),
+            layout,
+            kerned: HashSet::new(),
+            rejected: HashSet::new(),
+            dirty: None,
+        })
+    }
+
+    /// Glyph placements and metrics in atlas pixels, lay text out with BmFont::layout.
+    #[inline]
+    pub fn font(&self) -> &BmFont {
+        &self.layout
+    }
+
+    #[inline]
+    pub fn px(&self) -> f32 {
+        self.px
+    }
+
+    #[inline]
+    pub fn size(&self) -> u32 {
+        self.pixels.width()
+    }
+
+    #[inline]
+    pub fn pixels(&self) -> &image::RgbaImage {
+        &self.pixels
+    }
+
+    /// Rasterizes the glyphs and kerning pairs of `text` that aren't in the atlas yet.
+    /// Returns false if some glyphs didn't fit, those are drawn as '?' if it did.
+    pub fn prepare(&mut self, text: &str) -> bool {
+        let mut fits = true;
+        for c in text.chars().filter(|&c| c != '\n') {
+            if self.rejected.contains(&c) {
+                fits = false;
+            } else if !self.layout.chars.contains_key(&c) && !self.rasterize(c) {
+                self.rejected.insert(c);
+                fits = false;
+            }
+        }
+        let chars: Vec<char> = text.chars().collect();
+        for pair in chars.windows(2) {
+            let pair = (pair[0], pair[1]);
+            if pair.0 == '\n' || pair.1 == '\n' || !self.kerned.insert(pair) {
+                continue;
+            }
+            let scaled = self.font.as_scaled(PxScale::from(self.px));
+            let kern = scaled
+                .kern(self.font.glyph_id(pair.0), self.font.glyph_id(pair.1))
+                .round() as i32;
+            if kern != 0 {
+                self.layout.kerning.insert(pair, kern);
+            }
+        }
+        fits
+    }
+
+    /// Region rasterized since the last call, as [x, y, width, height].
+    pub fn take_dirty(&mut self) -> Option<[u32; 4]> {
+        self.dirty.take()
+    }
+
+    /// Forgets every glyph, e.g. once the atlas filled up with glyphs no longer shown.
+    pub fn clear(&mut self) {
+        self.packer.clear();
+        self.pixels.pixels_mut().for_each(|p| p[3] = 0);
+        self.layout.chars.clear();
+        self.layout.kerning.clear();
+        self.kerned.clear();
+        self.rejected.clear();
+        self.dirty = Some([0, 0, self.size(), self.size()]);
+    }
+
+    fn rasterize(&mut self, c: char) -> bool {
+        let scaled = self.font.as_scaled(PxScale::from(self.px));
+        let id = self.font.glyph_id(c);
+        let advance = scaled.h_advance(id).round() as i32;
+        let glyph = id.with_scale_and_position(self.px, ab_glyph::point(0.0, scaled.ascent()));
+        let mut placed = BmChar {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            offset: [0, 0],
+            advance,
+            page: 0,
+        };
+        if let Some(outline) = self.font.outline_glyph(glyph) {
+            let bounds = outline.px_bounds();
+            let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+            let Some([x, y]) = self.packer.insert(width, height) else {
+                log::warn!(
+                    "GlyphAtlas::rasterize => no room for '{}' in the {}px atlas",
+                    c,
+                    self.size()
+                );
+                return false;
+            };
+            outline.draw(|gx, gy, coverage| {
+                let alpha = (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+                self.pixels
+                    .put_pixel(x + gx, y + gy, image::Rgba([255, 255, 255, alpha]));
+            });
+            self.dirty = Some(union_rect(self.dirty, [x, y, width, height]));
+            placed = BmChar {
+                x,
+                y,
+                width,
+                height,
+                offset: [bounds.min.x as i32, bounds.min.y as i32],
+                ..placed
+            };
+        }
+        self.layout.chars.insert(c, placed);
+        true
+    }
+}
+
+/// Text drawn from a GlyphAtlas through a QuadBuffer, with the atlas texture kept in sync
+/// on upload. Give it a material sampling `texture`, e.g. OverlayPipeline::material for
+/// screen space text.
+pub struct TextBuffer {
+    atlas: GlyphAtlas,
+    texture: Texture,
+    quads: QuadBuffer,
+}
+
+impl TextBuffer {
+    pub fn new(device: &wgpu::Device, atlas: GlyphAtlas, capacity: usize) -> Self {
+        let size = wgpu::Extent3d {
+            width: atlas.size(),
+            height: atlas.size(),
+            depth_or_array_layers: 1,
+        };
+        let handle = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Glyph Atlas"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ATLAS_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = handle.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Glyph Atlas Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let mut atlas = atlas;
+        // The texture starts out undefined, the first upload writes all of it.
+        atlas.dirty = Some([0, 0, atlas.size(), atlas.size()]);
+        Self {
+            atlas,
+            texture: Texture {
+                handle,
+                view,
+                sampler,
+            },
+            quads: QuadBuffer::new(device, capacity),
+        }
+    }
+
+    /// Atlas texture for the material, see set_material.
+    #[inline]
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    #[inline]
+    pub fn atlas(&self) -> &GlyphAtlas {
+        &self.atlas
+    }
+
+    #[inline]
+    pub fn atlas_mut(&mut self) -> &mut GlyphAtlas {
+        &mut self.atlas
+    }
+
+    /// Material the text is drawn with, it must sample `texture` at the quads' uvs.
+    pub fn set_material(&mut self, material: QuadMaterial) {
+        self.quads.set_default_material(material);
+    }
+
+    /// Pushes `text` with the top left of its first line at `origin`, `style.scale` world
+    /// units per atlas pixel.
+    pub fn push_text(&mut self, text: &str, origin: [f32; 2], style: TextStyle) {
+        self.atlas.prepare(text);
+        for glyph in self.atlas.font().layout(text, origin, style.scale) {
+            self.quads.push_sprite(
+                &Sprite::new(glyph.position, glyph.size)
+                    .with_pivot([0.0, 0.0])
+                    .with_uv(glyph.uv)
+                    .with_color(style.color)
+                    .with_layer(style.layer, style.z),
+            );
+        }
+    }
+
+    /// Size of `text` in world units at `scale`.
+    pub fn measure(&mut self, text: &str, scale: f32) -> [f32; 2] {
+        self.atlas.prepare(text);
+        self.atlas.font().measure(text).map(|s| s as f32 * scale)
+    }
+
+    /// Removes the pushed text, glyphs stay in the atlas.
+    pub fn clear(&mut self) {
+        self.quads.clear();
+    }
+
+    /// Writes newly rasterized glyphs to the atlas texture and the pushed text to the GPU.
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some([x, y, width, height]) = self.atlas.take_dirty() {
+            let pixels = self.atlas.pixels();
+            let row = pixels.width() as usize * 4;
+            let bytes: Vec<u8> = (y..y + height)
+                .flat_map(|r| {
+                    let start = r as usize * row + x as usize * 4;
+                    &pixels.as_raw()[start..start + width as usize * 4]
+                })
+                .copied()
+                .collect();
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &self.texture.handle,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x, y, z: 0 },
+                },
+                &bytes,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * width),
+                    rows_per_image: Some(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        self.quads.upload(device, queue);
+    }
+
+    /// Records the draws of the last upload, see QuadBuffer::draw.
+    pub fn draw(&self, ctx: &mut DrawCtx) {
+        self.quads.draw(ctx);
+    }
+}
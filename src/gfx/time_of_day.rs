@@ -0,0 +1,391 @@
+use std::time::Duration;
+
+use cgmath::{Angle, Deg, EuclideanSpace, InnerSpace, Point3, Rad, Vector3};
+
+use super::{
+    environment::{Environment, Fog, Sky},
+    light::LightUniform,
+    shadow::DirectionalLight,
+};
+
+/// Lighting at one hour of the day, TimeOfDay blends between the keys either side of the
+/// current hour.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "scene", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimeOfDayKey {
+    /// From 0 to 24.
+    pub hour: f32,
+    /// Sun light before dawn and after dusk is moonlight.
+    pub sun_color: [f32; 3],
+    pub sun_intensity: f32,
+    pub ambient: [f32; 3],
+    pub zenith: [f32; 3],
+    pub horizon: [f32; 3],
+    pub ground: [f32; 3],
+    pub fog_color: [f32; 3],
+    /// Exponential fog density, only applied when TimeOfDay animates fog.
+    pub fog_density: f32,
+}
+
+impl TimeOfDayKey {
+    pub fn night() -> Self {
+        Self {
+            hour: 0.0,
+            sun_color: [0.5, 0.6, 0.9],
+            sun_intensity: 0.1,
+            ambient: [0.01, 0.012, 0.02],
+            zenith: [0.005, 0.008, 0.02],
+            horizon: [0.02, 0.025, 0.05],
+            ground: [0.01, 0.01, 0.01],
+            fog_color: [0.02, 0.025, 0.04],
+            fog_density: 0.02,
+        }
+    }
+
+    pub fn dawn() -> Self {
+        Self {
+            hour: 6.0,
+            sun_color: [1.0, 0.6, 0.4],
+            sun_intensity: 0.6,
+            ambient: [0.05, 0.04, 0.05],
+            zenith: [0.25, 0.3, 0.5],
+            horizon: [0.9, 0.55, 0.4],
+            ground: [0.15, 0.12, 0.1],
+            fog_color: [0.7, 0.55, 0.5],
+            fog_density: 0.03,
+        }
+    }
+
+    pub fn noon() -> Self {
+        Self {
+            hour: 12.0,
+            sun_color: [1.0, 0.97, 0.9],
+            sun_intensity: 1.0,
+            ambient: [0.08, 0.09, 0.1],
+            zenith: [0.18, 0.36, 0.7],
+            horizon: [0.7, 0.8, 0.9],
+            ground: [0.25, 0.23, 0.2],
+            fog_color: [0.7, 0.75, 0.8],
+            fog_density: 0.005,
+        }
+    }
+
+    pub fn dusk() -> Self {
+        Self {
+            hour: 18.0,
+            sun_color: [1.0, 0.45, 0.25],
+            sun_intensity: 0.5,
+            ambient: [0.05, 0.035, 0.04],
+            zenith: [0.15, 0.15, 0.35],
+            horizon: [0.95, 0.45, 0.25],
+            ground: [0.12, 0.1, 0.08],
+            fog_color: [0.6, 0.4, 0.35],
+            fog_density: 0.015,
+        }
+    }
+
+    /// Night, dawn, noon and dusk, the default keys of a TimeOfDay.
+    pub fn presets() -> Vec<Self> {
+        vec![Self::night(), Self::dawn(), Self::noon(), Self::dusk()]
+    }
+
+    pub fn with_hour(mut self, hour: f32) -> Self {
+        self.hour = hour.rem_euclid(24.0);
+        self
+    }
+
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let f = |a: f32, b: f32| a + (b - a) * t;
+        let v = |a: [f32; 3], b: [f32; 3]| std::array::from_fn(|i| f(a[i], b[i]));
+        Self {
+            hour: f(self.hour, other.hour),
+            sun_color: v(self.sun_color, other.sun_color),
+            sun_intensity: f(self.sun_intensity, other.sun_intensity),
+            ambient: v(self.ambient, other.ambient),
+            zenith: v(self.zenith, other.zenith),
+            horizon: v(self.horizon, other.horizon),
+            ground: v(self.ground, other.ground),
+            fog_color: v(self.fog_color, other.fog_color),
+            fog_density: f(self.fog_density, other.fog_density),
+        }
+    }
+}
+
+/// Blended lighting at one hour, see TimeOfDay::sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeOfDaySample {
+    /// The sun by day and the moon, opposite it, by night.
+    pub light: DirectionalLight,
+    /// Points towards the sun, below the horizon at night.
+    pub sun_direction: Vector3<f32>,
+    pub key: TimeOfDayKey,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Transition {
+    from: f32,
+    to: f32,
+    elapsed: f32,
+    duration: f32,
+}
+
+/// Moves the sun across a day/night cycle and blends keyframed sky, ambient and fog to
+/// match. Call update once per frame and apply the result to the window's environment and
+/// light, or feed light() to CascadedShadows::update.
+#[derive(Debug, Clone)]
+pub struct TimeOfDay {
+    hour: f32,
+    /// Real seconds for a full 24 hours.
+    day_length: f32,
+    paused: bool,
+    keys: Vec<TimeOfDayKey>,
+    animate_fog: bool,
+    /// Compass direction of sunrise, 0 rises in +X and sets in -X.
+    azimuth: Deg<f32>,
+    /// Sun elevation at noon.
+    max_elevation: Deg<f32>,
+    hooks: Vec<(f32, String)>,
+    transition: Option<Transition>,
+}
+
+impl Default for TimeOfDay {
+    fn default() -> Self {
+        Self::new(12.0, 20.0 * 60.0)
+    }
+}
+
+impl TimeOfDay {
+    /// Starts at `hour` with a cycle lasting `day_length` seconds and the preset keys.
+    pub fn new(hour: f32, day_length: f32) -> Self {
+        Self {
+            hour: hour.rem_euclid(24.0),
+            day_length: day_length.max(1e-3),
+            paused: false,
+            keys: TimeOfDayKey::presets(),
+            animate_fog: false,
+            azimuth: Deg(0.0),
+            max_elevation: Deg(60.0),
+            hooks: Vec::new(),
+            transition: None,
+        }
+    }
+
+    /// Replaces the keys, at least one is needed.
+    pub fn with_keys(mut self, keys: Vec<TimeOfDayKey>) -> Self {
+        self.set_keys(keys);
+        self
+    }
+
+    /// Also drive fog density and color from the keys.
+    pub fn with_fog(mut self, animate_fog: bool) -> Self {
+        self.animate_fog = animate_fog;
+        self
+    }
+
+    pub fn with_sun_path(mut self, azimuth: Deg<f32>, max_elevation: Deg<f32>) -> Self {
+        self.azimuth = azimuth;
+        self.max_elevation = max_elevation;
+        self
+    }
+
+    /// Names returned by update when the clock passes `hour`, e.g. script functions to
+    /// call with ScriptHost::call.
+    pub fn with_hook(mut self, hour: f32, name: &str) -> Self {
+        self.hooks.push((hour.rem_euclid(24.0), String::from(name)));
+        self
+    }
+
+    #[inline]
+    pub fn hour(&self) -> f32 {
+        self.hour
+    }
+
+    /// Jumps to `hour` without firing hooks in between.
+    pub fn set_hour(&mut self, hour: f32) {
+        self.hour = hour.rem_euclid(24.0);
+        self.transition = None;
+    }
+
+    /// Runs the clock forward to `hour` over `seconds`, 0 cuts. Hooks passed on the way
+    /// still fire. Handles ScriptCommand::SetTimeOfDay.
+    pub fn transition_to(&mut self, hour: f32, seconds: f32) {
+        if seconds <= 0.0 {
+            self.set_hour(hour);
+            return;
+        }
+        let ahead = (hour - self.hour).rem_euclid(24.0);
+        self.transition = Some(Transition {
+            from: self.hour,
+            to: self.hour + ahead,
+            elapsed: 0.0,
+            duration: seconds,
+        });
+    }
+
+    #[inline]
+    pub fn day_length(&self) -> f32 {
+        self.day_length
+    }
+
+    pub fn set_day_length(&mut self, day_length: f32) {
+        self.day_length = day_length.max(1e-3);
+    }
+
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Stops the clock, transitions still play.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    #[inline]
+    pub fn keys(&self) -> &[TimeOfDayKey] {
+        &self.keys
+    }
+
+    pub fn set_keys(&mut self, mut keys: Vec<TimeOfDayKey>) {
+        if keys.is_empty() {
+            keys = TimeOfDayKey::presets();
+        }
+        keys.sort_by(|a, b| a.hour.total_cmp(&b.hour));
+        self.keys = keys;
+    }
+
+    /// Advances the clock, returning the hooks it passed in order.
+    pub fn update(&mut self, dt: Duration) -> Vec<String> {
+        let dt = dt.as_secs_f32();
+        let from = self.hour;
+        let advanced = match self.transition.as_mut() {
+            Some(t) => {
+                t.elapsed += dt;
+                let done = t.elapsed >= t.duration;
+                let hour = if done {
+                    t.to
+                } else {
+                    t.from + (t.to - t.from) * t.elapsed / t.duration
+                };
+                if done {
+                    self.transition = None;
+                }
+                (hour - from).rem_euclid(24.0)
+            }
+            None if self.paused => 0.0,
+            None => dt * 24.0 / self.day_length,
+        };
+        self.hour = (from + advanced).rem_euclid(24.0);
+
+        let mut passed = self
+            .hooks
+            .iter()
+            .filter_map(|(hour, name)| {
+                let ahead = (hour - from).rem_euclid(24.0);
+                (advanced > 0.0 && ahead > 0.0 && ahead <= advanced).then_some((ahead, name))
+            })
+            .collect::<Vec<_>>();
+        passed.sort_by(|a, b| a.0.total_cmp(&b.0));
+        passed.into_iter().map(|(_, name)| name.clone()).collect()
+    }
+
+    /// Direction towards the sun at `hour`. Rises at 6, peaks at noon and sets at 18.
+    pub fn sun_direction(&self, hour: f32) -> Vector3<f32> {
+        let (sin, cos) = Rad((hour - 6.0) / 12.0 * std::f32::consts::PI).sin_cos();
+        let (x, y, z) = (
+            cos,
+            sin * self.max_elevation.sin(),
+            sin * self.max_elevation.cos(),
+        );
+        let (sa, ca) = self.azimuth.sin_cos();
+        Vector3::new(x * ca - z * sa, y, x * sa + z * ca).normalize()
+    }
+
+    /// Keys blended at `hour` and the light they give.
+    pub fn sample(&self, hour: f32) -> TimeOfDaySample {
+        let hour = hour.rem_euclid(24.0);
+        let next = self.keys.iter().position(|k| k.hour > hour);
+        let (a, b) = match next {
+            Some(0) | None => (self.keys[self.keys.len() - 1], self.keys[0]),
+            Some(i) => (self.keys[i - 1], self.keys[i]),
+        };
+        let span = (b.hour - a.hour).rem_euclid(24.0);
+        let t = if span > 0.0 {
+            (hour - a.hour).rem_euclid(24.0) / span
+        } else {
+            0.0
+        };
+        let key = a.lerp(&b, t.clamp(0.0, 1.0)).with_hour(hour);
+
+        let sun_direction = self.sun_direction(hour);
+        // The moon rises as the sun sets.
+        let towards_light = if sun_direction.y >= 0.0 {
+            sun_direction
+        } else {
+            -sun_direction
+        };
+        TimeOfDaySample {
+            light: DirectionalLight::new(-towards_light)
+                .with_color(key.sun_color)
+                .with_intensity(key.sun_intensity),
+            sun_direction,
+            key,
+        }
+    }
+
+    /// Lighting at the current hour.
+    #[inline]
+    pub fn current(&self) -> TimeOfDaySample {
+        self.sample(self.hour)
+    }
+
+    /// The sun or moon for CascadedShadows::update.
+    #[inline]
+    pub fn light(&self) -> DirectionalLight {
+        self.current().light
+    }
+
+    /// Sky, ambient and, when animated, fog at the current hour on top of `environment`.
+    /// Preetham skies keep their turbidity and exposure and follow the sun.
+    pub fn apply(&self, environment: &Environment) -> Environment {
+        let sample = self.current();
+        let key = sample.key;
+        let mut environment = *environment;
+        environment.sky = match environment.sky {
+            Sky::Preetham {
+                turbidity,
+                exposure,
+                ..
+            } => Sky::Preetham {
+                sun_direction: sample.sun_direction.into(),
+                turbidity,
+                exposure,
+            },
+            Sky::Gradient { .. } => Sky::Gradient {
+                zenith: key.zenith,
+                horizon: key.horizon,
+                ground: key.ground,
+            },
+        };
+        environment.ambient = key.ambient;
+        if self.animate_fog {
+            environment.fog = Fog::Exponential {
+                density: key.fog_density,
+            };
+            environment.fog_color = key.fog_color;
+        }
+        environment
+    }
+
+    /// The window's point light placed `distance` towards the sun or moon from `center`,
+    /// far enough away it lights the scene like a directional light.
+    pub fn light_uniform(&self, center: Point3<f32>, distance: f32) -> LightUniform {
+        let light = self.light();
+        let position = center.to_vec() - light.direction * distance;
+        let c = light.color.map(|c| c * light.intensity);
+        LightUniform {
+            position: position.extend(0.0).into(),
+            color: [c[0], c[1], c[2], 0.0],
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use cgmath::{Vector3, Zero};
+use cgmath::{Matrix4, One, Quaternion, Vector3, Zero};
 
 #[derive(Debug, Clone)]
 pub struct Transform {
@@ -7,6 +7,7 @@ pub struct Transform {
     rotation: cgmath::Quaternion<f32>,
     model: cgmath::Matrix4<f32>,
     needs_update: bool,
+    changed: bool,
 }
 
 impl Default for Transform {
@@ -14,9 +15,73 @@ impl Default for Transform {
         Self {
             position: Vector3::zero(),
             size: Vector3::new(1.0, 1.0, 1.0),
-            rotation: cgmath::Quaternion::zero(),
-            model: cgmath::Matrix4::zero(),
+            rotation: cgmath::Quaternion::one(),
+            model: cgmath::Matrix4::one(),
             needs_update: false,
+            changed: false,
         }
     }
 }
+
+impl Transform {
+    pub fn new(position: Vector3<f32>, rotation: Quaternion<f32>, size: Vector3<f32>) -> Self {
+        Self {
+            position,
+            size,
+            rotation,
+            model: Matrix4::one(),
+            needs_update: true,
+            changed: true,
+        }
+    }
+
+    #[inline]
+    pub const fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    #[inline]
+    pub const fn rotation(&self) -> Quaternion<f32> {
+        self.rotation
+    }
+
+    #[inline]
+    pub const fn size(&self) -> Vector3<f32> {
+        self.size
+    }
+
+    pub fn set_position(&mut self, position: Vector3<f32>) {
+        self.position = position;
+        self.needs_update = true;
+        self.changed = true;
+    }
+
+    pub fn set_rotation(&mut self, rotation: Quaternion<f32>) {
+        self.rotation = rotation;
+        self.needs_update = true;
+        self.changed = true;
+    }
+
+    pub fn set_size(&mut self, size: Vector3<f32>) {
+        self.size = size;
+        self.needs_update = true;
+        self.changed = true;
+    }
+
+    /// True if a setter was called since the last call, unlike the cache behind
+    /// Transform::model this is not reset by reading the matrix.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Model matrix (translation * rotation * scale), rebuilt only after a setter was called.
+    pub fn model(&mut self) -> Matrix4<f32> {
+        if self.needs_update {
+            self.model = Matrix4::from_translation(self.position)
+                * Matrix4::from(self.rotation)
+                * Matrix4::from_nonuniform_scale(self.size.x, self.size.y, self.size.z);
+            self.needs_update = false;
+        }
+        self.model
+    }
+}
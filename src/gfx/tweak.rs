@@ -0,0 +1,613 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    ops::Range,
+};
+
+#[cfg(feature = "accesskit")]
+use crate::eng::access::{
+    AccessAction, AccessActionKind, AccessId, AccessNode, AccessRange, AccessRole, AccessTree,
+    Accessible,
+};
+use crate::eng::{app::EngineConfig, input::mouse::MouseButton, render::RenderWindow};
+
+use super::{
+    draw::DrawCtx,
+    font::{BitmapFont, BmFont, TextStyle},
+    overlay::OverlayPipeline,
+    quad::{QuadBuffer, Sprite},
+    wgpu::texture::Texture,
+};
+
+/// Value of a tweak, as stored in the config file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TweakValue {
+    Float(f32),
+    Bool(bool),
+}
+
+impl TweakValue {
+    /// Parses `true` and `false` as bools, anything else as a float.
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        Ok(match value {
+            "true" => Self::Bool(true),
+            "false" => Self::Bool(false),
+            value => Self::Float(value.parse()?),
+        })
+    }
+}
+
+impl std::fmt::Display for TweakValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Float(v) => write!(f, "{}", v),
+            Self::Bool(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// Tweaks by name, stored in EngineConfig::tweaks so they persist with the config file.
+/// Names become part of a `tweak.<name>` key and shouldn't contain spaces or '='.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TweakValues {
+    values: BTreeMap<String, TweakValue>,
+}
+
+impl TweakValues {
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<TweakValue> {
+        self.values.get(name).copied()
+    }
+
+    pub fn insert(&mut self, name: &str, value: TweakValue) {
+        self.values.insert(String::from(name), value);
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Applies a `tweak.<name>` config entry, returns false if the key isn't a tweak.
+    pub fn set(&mut self, key: &str, value: &str) -> anyhow::Result<bool> {
+        let Some(name) = key.strip_prefix("tweak.") else {
+            return Ok(false);
+        };
+        if name.is_empty() {
+            anyhow::bail!("TweakValues::set => tweak key without a name");
+        }
+        self.insert(name, TweakValue::parse(value)?);
+        Ok(true)
+    }
+
+    /// `key = value` lines read back by TweakValues::set.
+    pub fn entries(&self) -> Vec<(String, String)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (format!("tweak.{}", name), value.to_string()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TweakWidget {
+    /// Filled up to `fraction` of the track.
+    Slider {
+        fraction: f32,
+    },
+    Toggle {
+        on: bool,
+    },
+}
+
+/// A widget laid out this frame, for TweakRenderer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TweakRow {
+    pub label: String,
+    /// Current value as text, shown after the widget.
+    pub value: String,
+    pub widget: TweakWidget,
+    /// Slider bounds, None for toggles.
+    pub range: Option<Range<f32>>,
+    /// Left, top, width and height of the widget in window pixels, y growing downwards.
+    pub rect: [f32; 4],
+    /// Has keyboard focus, only ever set with the accesskit feature.
+    pub focused: bool,
+}
+
+/// Immediate mode panel of sliders and toggles for tuning values while the game runs,
+/// drawn with TweakRenderer. Call TweakPanel::begin once a frame, then a tweak function
+/// for every value, e.g. `panel.tweak_f32("bloom", &mut bloom, 0.0..2.0)`. Values the
+/// panel saw last run are restored from EngineConfig::tweaks the first time they are
+/// tweaked, TweakPanel::save writes them back. With the accesskit feature rows are
+/// focusable with Tab and readable by screen readers, see Accessible.
+#[derive(Debug, Clone)]
+pub struct TweakPanel {
+    values: TweakValues,
+    restored: HashSet<String>,
+    /// Top left corner in window pixels, y growing downwards like the cursor.
+    pub position: [f32; 2],
+    pub width: f32,
+    pub row_height: f32,
+    /// Width of the label column, widgets fill the rest of the row.
+    pub label_width: f32,
+    visible: bool,
+    cursor: [f32; 2],
+    down: bool,
+    pressed: bool,
+    /// Slider held since the press that grabbed it, it keeps following the cursor
+    /// outside of its row.
+    active: Option<String>,
+    rows: Vec<TweakRow>,
+    last_height: f32,
+    #[cfg(feature = "accesskit")]
+    access_actions: Vec<AccessAction>,
+    #[cfg(feature = "accesskit")]
+    access_focus: Option<AccessId>,
+}
+
+impl TweakPanel {
+    pub fn new(values: TweakValues) -> Self {
+        Self {
+            values,
+            restored: HashSet::new(),
+            position: [16.0, 16.0],
+            width: 320.0,
+            row_height: 22.0,
+            label_width: 120.0,
+            visible: true,
+            cursor: [0.0; 2],
+            down: false,
+            pressed: false,
+            active: None,
+            rows: Vec::new(),
+            last_height: 0.0,
+            #[cfg(feature = "accesskit")]
+            access_actions: Vec::new(),
+            #[cfg(feature = "accesskit")]
+            access_focus: None,
+        }
+    }
+
+    pub fn with_position(mut self, position: [f32; 2]) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_width(mut self, width: f32, label_width: f32) -> Self {
+        self.width = width;
+        self.label_width = label_width;
+        self
+    }
+
+    #[inline]
+    pub fn values(&self) -> &TweakValues {
+        &self.values
+    }
+
+    #[inline]
+    pub fn rows(&self) -> &[TweakRow] {
+        &self.rows
+    }
+
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Hidden panels still restore and record values, they just take no input.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        if !visible {
+            self.active = None;
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.set_visible(!self.visible);
+    }
+
+    /// Starts a frame with the cursor in window pixels and the left button state.
+    pub fn begin(&mut self, cursor: [f32; 2], down: bool) {
+        self.pressed = down && !self.down;
+        self.down = down;
+        self.cursor = cursor;
+        if !down {
+            self.active = None;
+        }
+        self.last_height = self.rows.len() as f32 * self.row_height;
+        self.rows.clear();
+    }
+
+    /// TweakPanel::begin with the window's cursor and left mouse button.
+    pub fn begin_frame(&mut self, window: &RenderWindow) {
+        let mouse = window.mouse();
+        self.begin(mouse.position(), mouse.is_down(MouseButton::Left));
+        #[cfg(feature = "accesskit")]
+        self.begin_access(window.screen_reader().tree());
+    }
+
+    /// Takes this frame's focus and the actions aimed at the panel's rows from `tree`,
+    /// call after TweakPanel::begin.
+    #[cfg(feature = "accesskit")]
+    pub fn begin_access(&mut self, tree: &AccessTree) {
+        self.access_focus = tree.focus();
+        self.access_actions = tree.actions().to_vec();
+    }
+
+    /// Id of the row tweaking `name` in the accessibility tree.
+    #[cfg(feature = "accesskit")]
+    pub fn access_id(name: &str) -> AccessId {
+        AccessId::from_name(&format!("tweak.{}", name))
+    }
+
+    /// True while the cursor is over the panel or dragging one of its sliders, so the
+    /// game can ignore the click.
+    pub fn wants_mouse(&self) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let height = self
+            .last_height
+            .max(self.rows.len() as f32 * self.row_height);
+        self.active.is_some()
+            || contains(
+                [self.position[0], self.position[1], self.width, height],
+                self.cursor,
+            )
+    }
+
+    /// Slider from `range.start` to `range.end`. Returns true if `value` changed, either
+    /// by dragging or by being restored from the config.
+    pub fn tweak_f32(&mut self, name: &str, value: &mut f32, range: Range<f32>) -> bool {
+        let clamp = |v: f32| v.clamp(range.start.min(range.end), range.start.max(range.end));
+        let mut changed = false;
+        if let Some(TweakValue::Float(saved)) = self.restore(name) {
+            changed = *value != clamp(saved);
+            *value = clamp(saved);
+        }
+
+        if self.visible {
+            #[cfg(feature = "accesskit")]
+            {
+                // Screen readers and the access shortcuts step by a twentieth of the range.
+                let step = (range.end - range.start) / 20.0;
+                for kind in self.take_access_actions(name) {
+                    let stepped = match kind {
+                        AccessActionKind::Increment => clamp(*value + step.abs()),
+                        AccessActionKind::Decrement => clamp(*value - step.abs()),
+                        AccessActionKind::SetValue(v) => clamp(v as f32),
+                        AccessActionKind::Click => continue,
+                    };
+                    changed |= *value != stepped;
+                    *value = stepped;
+                }
+            }
+            let rect = self.control_rect();
+            if self.pressed && contains(rect, self.cursor) {
+                self.active = Some(String::from(name));
+            }
+            if self.active.as_deref() == Some(name) {
+                let t = ((self.cursor[0] - rect[0]) / rect[2].max(1.0)).clamp(0.0, 1.0);
+                let dragged = range.start + (range.end - range.start) * t;
+                changed |= *value != dragged;
+                *value = dragged;
+            }
+            let span = range.end - range.start;
+            let fraction = if span == 0.0 {
+                0.0
+            } else {
+                ((*value - range.start) / span).clamp(0.0, 1.0)
+            };
+            self.push_row(
+                name,
+                format!("{:.2}", value),
+                TweakWidget::Slider { fraction },
+                Some(range),
+                rect,
+            );
+        }
+        self.values.insert(name, TweakValue::Float(*value));
+        changed
+    }
+
+    /// Checkbox flipped by clicking anywhere on its row. Returns true if `value` changed.
+    pub fn tweak_bool(&mut self, name: &str, value: &mut bool) -> bool {
+        let mut changed = false;
+        if let Some(TweakValue::Bool(saved)) = self.restore(name) {
+            changed = *value != saved;
+            *value = saved;
+        }
+
+        if self.visible {
+            let row = [
+                self.position[0],
+                self.row_top(),
+                self.width,
+                self.row_height,
+            ];
+            if self.pressed && self.active.is_none() && contains(row, self.cursor) {
+                *value = !*value;
+                changed = true;
+            }
+            #[cfg(feature = "accesskit")]
+            for kind in self.take_access_actions(name) {
+                if kind == AccessActionKind::Click {
+                    *value = !*value;
+                    changed = true;
+                }
+            }
+            let rect = self.control_rect();
+            let size = rect[3];
+            self.push_row(
+                name,
+                value.to_string(),
+                TweakWidget::Toggle { on: *value },
+                None,
+                [rect[0], rect[1], size, size],
+            );
+        }
+        self.values.insert(name, TweakValue::Bool(*value));
+        changed
+    }
+
+    /// Copies the panel's values into `config` and saves it to `path`.
+    pub fn save(
+        &self,
+        config: &mut EngineConfig,
+        path: impl AsRef<std::path::Path>,
+    ) -> anyhow::Result<()> {
+        config.tweaks = self.values.clone();
+        config.save(path)
+    }
+
+    /// The stored value for `name` the first time it is tweaked, None after that.
+    fn restore(&mut self, name: &str) -> Option<TweakValue> {
+        if self.restored.contains(name) {
+            return None;
+        }
+        self.restored.insert(String::from(name));
+        self.values.get(name)
+    }
+
+    fn row_top(&self) -> f32 {
+        self.position[1] + self.rows.len() as f32 * self.row_height
+    }
+
+    /// Widget area of the next row, inset from the row so rows don't touch.
+    fn control_rect(&self) -> [f32; 4] {
+        let inset = (self.row_height * 0.2).floor();
+        [
+            self.position[0] + self.label_width,
+            self.row_top() + inset,
+            (self.width - self.label_width - 56.0).max(1.0),
+            self.row_height - inset * 2.0,
+        ]
+    }
+
+    fn push_row(
+        &mut self,
+        name: &str,
+        value: String,
+        widget: TweakWidget,
+        range: Option<Range<f32>>,
+        rect: [f32; 4],
+    ) {
+        #[cfg(feature = "accesskit")]
+        let focused = self.access_focus == Some(Self::access_id(name));
+        #[cfg(not(feature = "accesskit"))]
+        let focused = false;
+        self.rows.push(TweakRow {
+            label: String::from(name),
+            value,
+            widget,
+            range,
+            rect,
+            focused,
+        });
+    }
+
+    /// Removes and returns the queued actions for the row tweaking `name`.
+    #[cfg(feature = "accesskit")]
+    fn take_access_actions(&mut self, name: &str) -> Vec<AccessActionKind> {
+        let id = Self::access_id(name);
+        let mut kinds = Vec::new();
+        self.access_actions.retain(|action| {
+            if action.target == id {
+                kinds.push(action.kind);
+                false
+            } else {
+                true
+            }
+        });
+        kinds
+    }
+}
+
+/// The panel is a group of its rows' sliders and checkboxes, push it after the tweak
+/// calls so the rows are this frame's.
+#[cfg(feature = "accesskit")]
+impl Accessible for TweakPanel {
+    fn push_access_nodes(&self, tree: &mut AccessTree, parent: AccessId) {
+        if !self.visible || self.rows.is_empty() {
+            return;
+        }
+        let group = AccessId::from_name("tweak");
+        tree.push(
+            AccessNode::new(group, AccessRole::Group, "Tweaks")
+                .with_parent(parent)
+                .with_rect([
+                    self.position[0],
+                    self.position[1],
+                    self.width,
+                    self.rows.len() as f32 * self.row_height,
+                ]),
+        );
+        for row in self.rows.iter() {
+            let role = match row.widget {
+                TweakWidget::Slider { .. } => AccessRole::Slider,
+                TweakWidget::Toggle { .. } => AccessRole::CheckBox,
+            };
+            let mut node = AccessNode::new(Self::access_id(&row.label), role, &row.label)
+                .with_parent(group)
+                .with_rect(row.rect)
+                .with_value(row.value.clone());
+            match (row.widget, &row.range) {
+                (TweakWidget::Slider { fraction }, Some(range)) => {
+                    node = node.with_range(AccessRange {
+                        value: (range.start + (range.end - range.start) * fraction) as f64,
+                        min: range.start as f64,
+                        max: range.end as f64,
+                        step: ((range.end - range.start) / 20.0).abs() as f64,
+                    });
+                }
+                (TweakWidget::Toggle { on }, _) => node = node.with_toggled(on),
+                (TweakWidget::Slider { .. }, None) => {}
+            }
+            tree.push(node);
+        }
+    }
+}
+
+fn contains(rect: [f32; 4], point: [f32; 2]) -> bool {
+    point[0] >= rect[0]
+        && point[0] < rect[0] + rect[2]
+        && point[1] >= rect[1]
+        && point[1] < rect[1] + rect[3]
+}
+
+/// Draws a TweakPanel with the overlay pipeline, labels and values are skipped without
+/// a font.
+pub struct TweakRenderer {
+    quads: QuadBuffer,
+    font: Option<BitmapFont>,
+    pub text_scale: f32,
+    pub background: [f32; 4],
+    pub track: [f32; 4],
+    pub accent: [f32; 4],
+    pub text: [f32; 4],
+}
+
+impl TweakRenderer {
+    /// `font` is a BmFont and its page textures, e.g. from sys::fs::load_bmfont.
+    pub fn new(
+        window: &RenderWindow,
+        font: Option<(BmFont, Vec<Texture>)>,
+    ) -> anyhow::Result<Self> {
+        let device = window.device();
+        let overlay = OverlayPipeline::new(window)?;
+        let mut quads = QuadBuffer::new(device, 128);
+        quads.set_default_material(overlay.solid_material(device, "Tweak Panel"));
+        let font = match font {
+            Some((font, pages)) => {
+                let pages = pages
+                    .iter()
+                    .map(|page| {
+                        quads.add_material(overlay.material(device, page, "Tweak Panel Font"))
+                    })
+                    .collect();
+                Some(BitmapFont::new(font, pages)?)
+            }
+            None => None,
+        };
+        Ok(Self {
+            quads,
+            font,
+            text_scale: 1.0,
+            background: [0.05, 0.05, 0.07, 0.8],
+            track: [0.2, 0.2, 0.25, 1.0],
+            accent: [0.35, 0.6, 1.0, 1.0],
+            text: [0.9, 0.9, 0.9, 1.0],
+        })
+    }
+
+    /// Uploads and records the rows `panel` laid out this frame.
+    pub fn draw(&mut self, ctx: &mut DrawCtx, panel: &TweakPanel) {
+        self.quads.clear();
+        if !panel.is_visible() || panel.rows().is_empty() {
+            return;
+        }
+        let ds = ctx.device_surface.clone();
+        let height = ds.height() as f32;
+        // Panel rects have y growing downwards, the overlay has it growing upwards.
+        let flip = |rect: [f32; 4]| [rect[0], height - rect[1] - rect[3]];
+
+        let panel_rect = [
+            panel.position[0],
+            panel.position[1],
+            panel.width,
+            panel.rows().len() as f32 * panel.row_height,
+        ];
+        self.quads.push_quad(
+            flip(panel_rect),
+            [panel_rect[2], panel_rect[3]],
+            self.background,
+        );
+        for (i, row) in panel.rows().iter().enumerate() {
+            let [x, _, w, h] = row.rect;
+            if row.focused {
+                let row_rect = [
+                    panel.position[0],
+                    panel.position[1] + i as f32 * panel.row_height,
+                    3.0,
+                    panel.row_height,
+                ];
+                self.push(flip(row_rect), [row_rect[2], row_rect[3]], self.accent, 1);
+            }
+            match row.widget {
+                TweakWidget::Slider { fraction } => {
+                    self.push(flip(row.rect), [w, h], self.track, 1);
+                    self.push(flip(row.rect), [w * fraction, h], self.accent, 2);
+                }
+                TweakWidget::Toggle { on } => {
+                    self.push(flip(row.rect), [w, h], self.track, 1);
+                    if on {
+                        let inset = (h * 0.25).floor();
+                        let [ix, iy] = flip(row.rect);
+                        self.push(
+                            [ix + inset, iy + inset],
+                            [w - inset * 2.0, h - inset * 2.0],
+                            self.accent,
+                            2,
+                        );
+                    }
+                }
+            }
+            if let Some(font) = &self.font {
+                let row_top = panel.position[1] + i as f32 * panel.row_height;
+                let line = font.font.line_height as f32 * self.text_scale;
+                let top = height - row_top - ((panel.row_height - line) * 0.5).max(0.0);
+                let style = TextStyle::default()
+                    .with_scale(self.text_scale)
+                    .with_color(self.text)
+                    .with_layer(3, 0.0);
+                font.push_text(
+                    &mut self.quads,
+                    &row.label,
+                    [panel.position[0] + 6.0, top],
+                    style,
+                );
+                let value_x = match row.widget {
+                    TweakWidget::Slider { .. } => x + w + 6.0,
+                    TweakWidget::Toggle { .. } => x + h + 6.0,
+                };
+                font.push_text(&mut self.quads, &row.value, [value_x, top], style);
+            }
+        }
+        self.quads.upload(&ds.device, &ds.queue);
+        self.quads.draw(ctx);
+    }
+
+    fn push(&mut self, position: [f32; 2], size: [f32; 2], color: [f32; 4], layer: i32) {
+        self.quads.push_sprite(
+            &Sprite::new(position, size)
+                .with_pivot([0.0, 0.0])
+                .with_color(color)
+                .with_layer(layer, 0.0),
+        );
+    }
+}
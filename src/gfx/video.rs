@@ -0,0 +1,451 @@
+use std::{io::Cursor, sync::Arc, time::Duration};
+
+use image::{AnimationDecoder, ImageDecoder, ImageFormat, RgbaImage};
+
+use super::wgpu::texture::{Texture, TextureType};
+
+/// A decoded frame and how long it stays on screen.
+#[derive(Debug, Clone)]
+pub struct VideoFrame {
+    pub image: RgbaImage,
+    pub duration: Duration,
+}
+
+/// Decodes video frames one at a time, see AnimatedImage and, with the ffmpeg feature,
+/// FfmpegVideo. Implement this to plug in other decoders.
+pub trait VideoSource {
+    /// Width and height of every frame.
+    fn size(&self) -> (u32, u32);
+    /// The next frame, None once the end of the video is reached.
+    fn next_frame(&mut self) -> anyhow::Result<Option<VideoFrame>>;
+    /// Starts decoding from the first frame again.
+    fn rewind(&mut self) -> anyhow::Result<()>;
+}
+
+/// Animated GIF, APNG or WebP decoded frame by frame with the image crate.
+pub struct AnimatedImage {
+    bytes: Arc<[u8]>,
+    format: ImageFormat,
+    size: (u32, u32),
+    frames: image::Frames<'static>,
+}
+
+impl std::fmt::Debug for AnimatedImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnimatedImage")
+            .field("format", &self.format)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+impl AnimatedImage {
+    pub fn from_bytes(bytes: impl Into<Arc<[u8]>>) -> anyhow::Result<Self> {
+        let bytes = bytes.into();
+        let format = image::guess_format(&bytes)?;
+        let (size, frames) = Self::decode(&bytes, format)?;
+        Ok(Self {
+            bytes,
+            format,
+            size,
+            frames,
+        })
+    }
+
+    pub fn open(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    fn decode(
+        bytes: &Arc<[u8]>,
+        format: ImageFormat,
+    ) -> anyhow::Result<((u32, u32), image::Frames<'static>)> {
+        let reader = Cursor::new(bytes.clone());
+        Ok(match format {
+            ImageFormat::Gif => {
+                let decoder = image::codecs::gif::GifDecoder::new(reader)?;
+                (decoder.dimensions(), decoder.into_frames())
+            }
+            ImageFormat::Png => {
+                let decoder = image::codecs::png::PngDecoder::new(reader)?;
+                (decoder.dimensions(), decoder.apng().into_frames())
+            }
+            ImageFormat::WebP => {
+                let decoder = image::codecs::webp::WebPDecoder::new(reader)?;
+                (decoder.dimensions(), decoder.into_frames())
+            }
+            _ => anyhow::bail!(
+                "AnimatedImage::decode => {:?} is not an animated format",
+                format
+            ),
+        })
+    }
+}
+
+impl VideoSource for AnimatedImage {
+    fn size(&self) -> (u32, u32) {
+        self.size
+    }
+
+    fn next_frame(&mut self) -> anyhow::Result<Option<VideoFrame>> {
+        let Some(frame) = self.frames.next().transpose()? else {
+            return Ok(None);
+        };
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        Ok(Some(VideoFrame {
+            duration: Duration::from_secs_f64(numer as f64 / denom.max(1) as f64 / 1000.0),
+            image: frame.into_buffer(),
+        }))
+    }
+
+    fn rewind(&mut self) -> anyhow::Result<()> {
+        self.frames = Self::decode(&self.bytes, self.format)?.1;
+        Ok(())
+    }
+}
+
+/// MP4, WebM, MKV or any other container and codec the system FFmpeg libraries can
+/// demux and decode (H.264, VP9, AV1, ...), converted to RGBA one frame at a time.
+#[cfg(feature = "ffmpeg")]
+pub struct FfmpegVideo {
+    input: ffmpeg_next::format::context::Input,
+    decoder: ffmpeg_next::decoder::Video,
+    scaler: ffmpeg_next::software::scaling::Context,
+    stream: usize,
+    /// Seconds per tick of the stream's timestamps.
+    time_base: f64,
+    /// Frame length from the stream's frame rate, for frames without a duration.
+    frame_time: Duration,
+    decoded: ffmpeg_next::frame::Video,
+    rgba: ffmpeg_next::frame::Video,
+}
+
+#[cfg(feature = "ffmpeg")]
+impl std::fmt::Debug for FfmpegVideo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FfmpegVideo")
+            .field("codec", &self.decoder.id())
+            .field("size", &self.size())
+            .field("frame_time", &self.frame_time)
+            .finish()
+    }
+}
+
+#[cfg(feature = "ffmpeg")]
+impl FfmpegVideo {
+    /// Frame length used when the stream reports no frame rate either.
+    const FALLBACK_FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 30);
+
+    /// Opens the best video stream of the file at `path`, other streams are ignored.
+    pub fn open(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        use ffmpeg_next::{
+            format::Pixel,
+            media::Type,
+            software::scaling::{Context, Flags},
+        };
+
+        let path = path.as_ref();
+        ffmpeg_next::init()?;
+        let input = ffmpeg_next::format::input(path)?;
+        let stream = input.streams().best(Type::Video).ok_or_else(|| {
+            anyhow::anyhow!("FfmpegVideo::open => {} has no video stream", path.display())
+        })?;
+        let index = stream.index();
+        let time_base = f64::from(stream.time_base());
+        let rate = stream.avg_frame_rate();
+        let frame_time = if rate.numerator() > 0 && rate.denominator() > 0 {
+            Duration::from_secs_f64(f64::from(rate.invert()))
+        } else {
+            Self::FALLBACK_FRAME_TIME
+        };
+        let decoder = ffmpeg_next::codec::context::Context::from_parameters(stream.parameters())?
+            .decoder()
+            .video()?;
+        let scaler = Context::get(
+            decoder.format(),
+            decoder.width(),
+            decoder.height(),
+            Pixel::RGBA,
+            decoder.width(),
+            decoder.height(),
+            Flags::BILINEAR,
+        )?;
+
+        Ok(Self {
+            input,
+            decoder,
+            scaler,
+            stream: index,
+            time_base,
+            frame_time,
+            decoded: ffmpeg_next::frame::Video::empty(),
+            rgba: ffmpeg_next::frame::Video::empty(),
+        })
+    }
+
+    /// Decodes the next frame into `decoded`, feeding packets of the video stream to the
+    /// decoder until it has one. False once the decoder is drained.
+    fn decode_next(&mut self) -> anyhow::Result<bool> {
+        use ffmpeg_next::{error::EAGAIN, Error};
+
+        loop {
+            match self.decoder.receive_frame(&mut self.decoded) {
+                Ok(()) => return Ok(true),
+                Err(Error::Eof) => return Ok(false),
+                Err(Error::Other { errno: EAGAIN }) => {}
+                Err(e) => return Err(e.into()),
+            }
+            let mut packet = ffmpeg_next::Packet::empty();
+            match packet.read(&mut self.input) {
+                Ok(()) if packet.stream() == self.stream => self.decoder.send_packet(&packet)?,
+                Ok(()) => {}
+                // Flushes the frames the decoder still holds, then receive_frame ends with Eof.
+                Err(Error::Eof) => self.decoder.send_eof()?,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "ffmpeg")]
+impl VideoSource for FfmpegVideo {
+    fn size(&self) -> (u32, u32) {
+        (self.decoder.width(), self.decoder.height())
+    }
+
+    fn next_frame(&mut self) -> anyhow::Result<Option<VideoFrame>> {
+        if !self.decode_next()? {
+            return Ok(None);
+        }
+        self.scaler.run(&self.decoded, &mut self.rgba)?;
+
+        let (width, height) = (self.rgba.width(), self.rgba.height());
+        let row = width as usize * 4;
+        let stride = self.rgba.stride(0);
+        let data = self.rgba.data(0);
+        // Rows are padded to the stride, copy out just the pixels.
+        let mut pixels = Vec::with_capacity(row * height as usize);
+        for y in 0..height as usize {
+            pixels.extend_from_slice(&data[y * stride..y * stride + row]);
+        }
+        let image = RgbaImage::from_raw(width, height, pixels)
+            .ok_or_else(|| anyhow::anyhow!("FfmpegVideo::next_frame => short frame"))?;
+
+        let ticks = self.decoded.packet().duration;
+        let duration = if ticks > 0 {
+            Duration::from_secs_f64(ticks as f64 * self.time_base)
+        } else {
+            self.frame_time
+        };
+        Ok(Some(VideoFrame { image, duration }))
+    }
+
+    fn rewind(&mut self) -> anyhow::Result<()> {
+        self.input.seek(0, ..1)?;
+        self.decoder.flush();
+        Ok(())
+    }
+}
+
+/// Frame timing of a VideoSource, independent of the GPU texture it ends up in.
+pub struct VideoPlayback {
+    source: Box<dyn VideoSource>,
+    current: Option<VideoFrame>,
+    /// Time spent on the current frame.
+    elapsed: Duration,
+    finished: bool,
+    pub looping: bool,
+    pub paused: bool,
+    /// Playback rate, 2.0 plays twice as fast.
+    pub speed: f32,
+}
+
+impl VideoPlayback {
+    /// Shortest time a frame is shown, zero delays would otherwise spin forever.
+    pub const MIN_FRAME_TIME: Duration = Duration::from_millis(10);
+    /// Frames decoded at most per advance. Further behind than that, e.g. after a hitch,
+    /// playback drops the rest of the backlog instead of decoding it on the render thread.
+    pub const MAX_CATCH_UP: u32 = 2;
+
+    pub fn new(source: Box<dyn VideoSource>) -> Self {
+        Self {
+            source,
+            current: None,
+            elapsed: Duration::ZERO,
+            finished: false,
+            looping: false,
+            paused: false,
+            speed: 1.0,
+        }
+    }
+
+    #[inline]
+    pub fn size(&self) -> (u32, u32) {
+        self.source.size()
+    }
+
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn current(&self) -> Option<&VideoFrame> {
+        self.current.as_ref()
+    }
+
+    /// Plays from the first frame again.
+    pub fn restart(&mut self) -> anyhow::Result<()> {
+        self.source.rewind()?;
+        self.current = None;
+        self.elapsed = Duration::ZERO;
+        self.finished = false;
+        Ok(())
+    }
+
+    /// Moves playback forward by `dt`, returns the frame to display if it changed.
+    /// Catches up by at most MAX_CATCH_UP frames, time past that is dropped so playback
+    /// continues from the newest decoded frame.
+    pub fn advance(&mut self, dt: Duration) -> anyhow::Result<Option<&VideoFrame>> {
+        if self.finished {
+            return Ok(None);
+        }
+        let Some(current) = &self.current else {
+            self.current = self.next_frame()?;
+            return Ok(self.current.as_ref());
+        };
+        if self.paused {
+            return Ok(None);
+        }
+        let mut frame_time = current.duration.max(Self::MIN_FRAME_TIME);
+        self.elapsed += dt.mul_f32(self.speed.max(0.0));
+        let mut decoded = 0;
+        let mut changed = false;
+        while self.elapsed >= frame_time {
+            if decoded == Self::MAX_CATCH_UP {
+                self.elapsed = Duration::ZERO;
+                break;
+            }
+            decoded += 1;
+            self.elapsed -= frame_time;
+            let Some(next) = self.next_frame()? else {
+                break;
+            };
+            frame_time = next.duration.max(Self::MIN_FRAME_TIME);
+            self.current = Some(next);
+            changed = true;
+        }
+        Ok(if changed { self.current.as_ref() } else { None })
+    }
+
+    fn next_frame(&mut self) -> anyhow::Result<Option<VideoFrame>> {
+        let mut frame = self.source.next_frame()?;
+        if frame.is_none() && self.looping {
+            self.source.rewind()?;
+            frame = self.source.next_frame()?;
+        }
+        if frame.is_none() {
+            self.finished = true;
+        }
+        Ok(frame)
+    }
+}
+
+/// Streams a VideoSource into a texture, so cutscenes and animated backgrounds can be
+/// drawn like any other textured quad. Only the newest frame is uploaded each update.
+pub struct VideoTexture {
+    playback: VideoPlayback,
+    texture: Texture,
+}
+
+impl VideoTexture {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: Box<dyn VideoSource>,
+        label: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let mut playback = VideoPlayback::new(source);
+        let (width, height) = playback.size();
+        let first = match playback.advance(Duration::ZERO)? {
+            Some(frame) => frame.image.clone(),
+            None => RgbaImage::new(width.max(1), height.max(1)),
+        };
+        let texture = Texture::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(first),
+            TextureType::Diffuse,
+            label,
+        )?;
+        Ok(Self { playback, texture })
+    }
+
+    #[inline]
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    #[inline]
+    pub fn playback(&self) -> &VideoPlayback {
+        &self.playback
+    }
+
+    #[inline]
+    pub fn playback_mut(&mut self) -> &mut VideoPlayback {
+        &mut self.playback
+    }
+
+    /// Bind group with the texture view at binding 0 and the sampler at binding 1.
+    pub fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("video_bind_group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&self.texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.texture.sampler),
+                },
+            ],
+        })
+    }
+
+    /// Advances playback and uploads the new frame if there is one.
+    pub fn frame_update(&mut self, queue: &wgpu::Queue, dt: Duration) -> anyhow::Result<()> {
+        let Some(frame) = self.playback.advance(dt)? else {
+            return Ok(());
+        };
+        let size = self.texture.handle.size();
+        if frame.image.dimensions() != (size.width, size.height) {
+            anyhow::bail!(
+                "VideoTexture::frame_update => frame is {:?}, texture is {}x{}",
+                frame.image.dimensions(),
+                size.width,
+                size.height
+            );
+        }
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                aspect: wgpu::TextureAspect::All,
+                texture: &self.texture.handle,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &frame.image,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size.width),
+                rows_per_image: Some(size.height),
+            },
+            size,
+        );
+        Ok(())
+    }
+}
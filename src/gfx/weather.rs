@@ -0,0 +1,557 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use cgmath::{EuclideanSpace, InnerSpace, Matrix3, Matrix4, Point3, Quaternion, Vector3};
+use wgpu::util::DeviceExt;
+
+use crate::sys::rand::Rng;
+
+use super::{
+    draw::DrawCtx,
+    model::{Material, Mesh},
+    post::{custom::CustomEffect, PostProcessor},
+    wgpu::{buffer::InstanceRaw, vertex::Vertex3D},
+};
+
+/// Screen droplets custom effect, see shaders/weather_droplets.wgsl.
+pub const WEATHER_DROPLETS_WGSL: &str = include_str!("../shaders/weather_droplets.wgsl");
+/// Wind struct and wind_sway for vegetation shaders, see shaders/wind.wgsl.
+pub const WIND_WGSL: &str = include_str!("../shaders/wind.wgsl");
+
+/// Wind shared by weather particles and vegetation sway.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "scene", derive(serde::Serialize, serde::Deserialize))]
+pub struct Wind {
+    /// Direction on the XZ plane, normalized when used.
+    pub direction: [f32; 2],
+    /// Meters per second.
+    pub speed: f32,
+    /// Extra speed at the peak of a gust.
+    pub gust: f32,
+    /// Gusts per second.
+    pub gust_frequency: f32,
+}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Self {
+            direction: [1.0, 0.0],
+            speed: 0.0,
+            gust: 0.0,
+            gust_frequency: 0.2,
+        }
+    }
+}
+
+impl Wind {
+    pub fn new(direction: [f32; 2], speed: f32) -> Self {
+        Self {
+            direction,
+            speed,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_gust(mut self, gust: f32, frequency: f32) -> Self {
+        self.gust = gust;
+        self.gust_frequency = frequency;
+        self
+    }
+
+    fn direction(&self) -> Vector3<f32> {
+        let dir = Vector3::new(self.direction[0], 0.0, self.direction[1]);
+        if dir.magnitude2() > 0.0 {
+            dir.normalize()
+        } else {
+            Vector3::unit_x()
+        }
+    }
+
+    /// Air velocity `time` seconds in, gusts never blow against the wind.
+    pub fn velocity(&self, time: f32) -> Vector3<f32> {
+        let gust = self.gust * (time * self.gust_frequency * std::f32::consts::TAU).sin();
+        self.direction() * (self.speed + gust).max(0.0)
+    }
+
+    pub fn uniform(&self, time: f32) -> WindUniform {
+        let dir = self.direction();
+        WindUniform {
+            direction: [dir.x, dir.z],
+            speed: self.speed,
+            time,
+            gust: self.gust,
+            gust_frequency: self.gust_frequency,
+            _pad: [0.0; 2],
+        }
+    }
+}
+
+/// Wind as seen by shaders, matches Wind in shaders/wind.wgsl.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct WindUniform {
+    pub direction: [f32; 2],
+    pub speed: f32,
+    pub time: f32,
+    pub gust: f32,
+    pub gust_frequency: f32,
+    _pad: [f32; 2],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "scene", derive(serde::Serialize, serde::Deserialize))]
+pub enum WeatherKind {
+    #[default]
+    Clear,
+    /// Falling streaks and drops on the screen.
+    Rain,
+    /// Drifting flakes that slowly settle, see Weather::accumulation.
+    Snow,
+}
+
+/// What the weather is doing, saved with the scene.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "scene", derive(serde::Serialize, serde::Deserialize))]
+pub struct WeatherSettings {
+    pub kind: WeatherKind,
+    /// Disabled weather stops spawning, the particles already falling still land.
+    pub enabled: bool,
+    /// 0 to 1, the share of the particle budget in use.
+    pub intensity: f32,
+    pub wind: Wind,
+    /// Seconds of snowfall at full intensity until fully covered, also how long it takes to
+    /// melt.
+    pub accumulation_time: f32,
+}
+
+impl Default for WeatherSettings {
+    fn default() -> Self {
+        Self::clear()
+    }
+}
+
+impl WeatherSettings {
+    pub fn clear() -> Self {
+        Self {
+            kind: WeatherKind::Clear,
+            enabled: true,
+            intensity: 0.0,
+            wind: Wind::default(),
+            accumulation_time: 120.0,
+        }
+    }
+
+    pub fn rain() -> Self {
+        Self {
+            kind: WeatherKind::Rain,
+            intensity: 0.8,
+            wind: Wind::new([1.0, 0.3], 3.0).with_gust(2.0, 0.15),
+            ..Self::clear()
+        }
+    }
+
+    pub fn snow() -> Self {
+        Self {
+            kind: WeatherKind::Snow,
+            intensity: 0.6,
+            wind: Wind::new([0.4, 1.0], 1.0).with_gust(1.5, 0.1),
+            ..Self::clear()
+        }
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_wind(mut self, wind: Wind) -> Self {
+        self.wind = wind;
+        self
+    }
+
+    /// Whether new particles spawn.
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.kind != WeatherKind::Clear && self.intensity > 0.0
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct WeatherParticle {
+    pub position: Point3<f32>,
+    pub velocity: Vector3<f32>,
+    pub kind: WeatherKind,
+    /// Fall speed and wind response vary a little per particle.
+    pub weight: f32,
+}
+
+impl WeatherParticle {
+    /// Rain is stretched along its velocity into a streak, snow is a small flake.
+    pub fn to_raw(&self) -> InstanceRaw {
+        let (rotation, scale) = match self.kind {
+            WeatherKind::Rain => {
+                let speed = self.velocity.magnitude();
+                let rotation = if speed > 0.0 {
+                    Quaternion::from_arc(Vector3::unit_y(), -self.velocity / speed, None)
+                } else {
+                    Quaternion::new(1.0, 0.0, 0.0, 0.0)
+                };
+                let length = (speed * RAIN_STREAK).clamp(0.1, 0.8);
+                (rotation, Vector3::new(0.01, length, 0.01))
+            }
+            _ => (
+                Quaternion::new(1.0, 0.0, 0.0, 0.0),
+                Vector3::new(0.03, 0.03, 0.03),
+            ),
+        };
+        let model = Matrix4::from_translation(self.position.to_vec())
+            * Matrix4::from(rotation)
+            * Matrix4::from_nonuniform_scale(scale.x, scale.y, scale.z);
+        InstanceRaw::new(model, Matrix3::from(rotation))
+    }
+}
+
+const RAIN_FALL: f32 = 9.0;
+const SNOW_FALL: f32 = 1.2;
+/// Seconds of motion a rain streak covers.
+const RAIN_STREAK: f32 = 0.04;
+
+/// CPU side of the weather: particles falling in a box around the viewer, snow cover and
+/// screen droplet strength. Weather owns one and uploads it.
+#[derive(Debug, Clone)]
+pub struct WeatherState {
+    settings: WeatherSettings,
+    capacity: usize,
+    /// Half width of the box particles fall in, centred on the viewer.
+    radius: f32,
+    /// Height of the box, from radius below the viewer.
+    height: f32,
+    particles: Vec<WeatherParticle>,
+    rng: Rng,
+    time: f32,
+    accumulation: f32,
+    droplets: f32,
+}
+
+impl WeatherState {
+    /// `capacity` is the particle count at full intensity.
+    pub fn new(settings: WeatherSettings, capacity: usize, seed: u64) -> Self {
+        Self {
+            settings,
+            capacity,
+            radius: 20.0,
+            height: 30.0,
+            particles: Vec::with_capacity(capacity),
+            rng: Rng::new(seed),
+            time: 0.0,
+            accumulation: 0.0,
+            droplets: 0.0,
+        }
+    }
+
+    pub fn with_bounds(mut self, radius: f32, height: f32) -> Self {
+        self.radius = radius;
+        self.height = height;
+        self
+    }
+
+    #[inline]
+    pub fn settings(&self) -> &WeatherSettings {
+        &self.settings
+    }
+
+    /// Switches presets at runtime, particles of the old kind keep falling until they land.
+    #[inline]
+    pub fn set_settings(&mut self, settings: WeatherSettings) {
+        self.settings = settings;
+    }
+
+    #[inline]
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.settings.enabled = enabled;
+    }
+
+    /// Returns the new enabled state.
+    pub fn toggle(&mut self) -> bool {
+        self.settings.enabled = !self.settings.enabled;
+        self.settings.enabled
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    #[inline]
+    pub fn particles(&self) -> &[WeatherParticle] {
+        &self.particles
+    }
+
+    /// Seconds since creation, drives the wind.
+    #[inline]
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// Snow cover from 0 to 1, for materials to blend towards white.
+    #[inline]
+    pub fn accumulation(&self) -> f32 {
+        self.accumulation
+    }
+
+    /// Strength of the screen droplets from 0 to 1.
+    #[inline]
+    pub fn droplets(&self) -> f32 {
+        self.droplets
+    }
+
+    /// Particles wanted at the current settings.
+    pub fn target_count(&self) -> usize {
+        if self.settings.is_active() {
+            (self.capacity as f32 * self.settings.intensity.clamp(0.0, 1.0)).round() as usize
+        } else {
+            0
+        }
+    }
+
+    pub fn wind_uniform(&self) -> WindUniform {
+        self.settings.wind.uniform(self.time)
+    }
+
+    /// Moves the particles and respawns the ones that landed above `viewer`. Particles
+    /// leaving the box sideways wrap around so the density stays even while moving.
+    pub fn update(&mut self, dt: Duration, viewer: Point3<f32>) {
+        let dt = dt.as_secs_f32();
+        self.time += dt;
+        let settings = self.settings;
+        let active = settings.is_active();
+        let fade = |value: f32, target: bool, seconds: f32| {
+            let step = dt / seconds.max(1e-3);
+            if target {
+                (value + step * settings.intensity).min(1.0)
+            } else {
+                (value - step).max(0.0)
+            }
+        };
+        self.accumulation = fade(
+            self.accumulation,
+            active && settings.kind == WeatherKind::Snow,
+            settings.accumulation_time,
+        );
+        self.droplets = fade(
+            self.droplets,
+            active && settings.kind == WeatherKind::Rain,
+            2.0,
+        );
+
+        let wind = settings.wind.velocity(self.time);
+        let target = self.target_count();
+        let (radius, height) = (self.radius, self.height);
+        let floor = viewer.y - radius;
+        let mut i = 0;
+        while i < self.particles.len() {
+            let count = self.particles.len();
+            let particle = &mut self.particles[i];
+            let (fall, response) = match particle.kind {
+                WeatherKind::Rain => (RAIN_FALL, 0.3),
+                _ => (SNOW_FALL, 1.0),
+            };
+            let mut velocity = wind * response * particle.weight;
+            velocity.y = -fall * particle.weight;
+            if particle.kind == WeatherKind::Snow {
+                let phase = self.time * 1.7 + particle.weight * 40.0;
+                velocity.x += phase.sin() * 0.3;
+                velocity.z += (phase * 0.8).cos() * 0.3;
+            }
+            particle.velocity = velocity;
+            particle.position += velocity * dt;
+            for axis in [0, 2] {
+                let offset = particle.position[axis] - viewer[axis];
+                if offset.abs() > radius {
+                    particle.position[axis] -= offset.signum() * radius * 2.0;
+                }
+            }
+            if particle.position.y < floor || particle.position.y > floor + height {
+                if count > target || particle.kind != settings.kind || !active {
+                    self.particles.swap_remove(i);
+                    continue;
+                }
+                let (x, z) = (
+                    self.rng.range_f32(-radius..radius),
+                    self.rng.range_f32(-radius..radius),
+                );
+                self.particles[i].position =
+                    Point3::new(viewer.x + x, floor + height, viewer.z + z);
+            }
+            i += 1;
+        }
+
+        // Spread new particles through the whole box so turning weather on doesn't start
+        // with a single sheet falling from the top.
+        let spawn = target.saturating_sub(self.particles.len());
+        for _ in 0..spawn {
+            let position = Point3::new(
+                viewer.x + self.rng.range_f32(-radius..radius),
+                floor + self.rng.range_f32(0.0..height),
+                viewer.z + self.rng.range_f32(-radius..radius),
+            );
+            self.particles.push(WeatherParticle {
+                position,
+                velocity: Vector3::new(0.0, 0.0, 0.0),
+                kind: settings.kind,
+                weight: self.rng.range_f32(0.8..1.2),
+            });
+        }
+    }
+}
+
+/// Params block of shaders/weather_droplets.wgsl.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct DropletParams {
+    strength: f32,
+    speed: f32,
+    scale: f32,
+    _pad: f32,
+}
+
+/// Rain and snow drawn as instanced particles, with a screen droplets post effect and a wind
+/// uniform for vegetation. Call frame_update once per frame and draw in the frame's pass.
+pub struct Weather {
+    state: WeatherState,
+    raw: Vec<InstanceRaw>,
+    capacity: usize,
+    buffer: Arc<wgpu::Buffer>,
+    wind_buffer: wgpu::Buffer,
+    droplets: CustomEffect,
+}
+
+impl Weather {
+    pub fn new(device: &wgpu::Device, state: WeatherState) -> Result<Self> {
+        let params = DropletParams {
+            strength: 0.0,
+            speed: 0.3,
+            scale: 12.0,
+            _pad: 0.0,
+        };
+        let droplets = CustomEffect::new(
+            device,
+            "Weather Droplets",
+            WEATHER_DROPLETS_WGSL,
+            bytemuck::bytes_of(&params),
+        )?;
+        let wind_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Weather Wind Buffer"),
+            contents: bytemuck::bytes_of(&state.wind_uniform()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let capacity = state.capacity().max(1);
+        Ok(Self {
+            state,
+            raw: Vec::new(),
+            capacity,
+            buffer: create_instance_buffer(device, capacity),
+            wind_buffer,
+            droplets,
+        })
+    }
+
+    #[inline]
+    pub fn state(&self) -> &WeatherState {
+        &self.state
+    }
+
+    #[inline]
+    pub fn state_mut(&mut self) -> &mut WeatherState {
+        &mut self.state
+    }
+
+    /// The screen droplets effect, insert CustomEffect::effect into the post stack.
+    #[inline]
+    pub fn droplets(&self) -> &CustomEffect {
+        &self.droplets
+    }
+
+    /// WindUniform for vegetation shaders, see WIND_WGSL.
+    #[inline]
+    pub fn wind_buffer(&self) -> &wgpu::Buffer {
+        &self.wind_buffer
+    }
+
+    /// Advances the particles and writes the wind and droplet uniforms.
+    pub fn frame_update(
+        &mut self,
+        queue: &wgpu::Queue,
+        post: &PostProcessor,
+        dt: Duration,
+        viewer: Point3<f32>,
+    ) -> Result<()> {
+        self.state.update(dt, viewer);
+        self.raw.clear();
+        self.raw
+            .extend(self.state.particles().iter().map(WeatherParticle::to_raw));
+        queue.write_buffer(
+            &self.wind_buffer,
+            0,
+            bytemuck::bytes_of(&self.state.wind_uniform()),
+        );
+        self.droplets.frame_update(queue, post, dt);
+        self.droplets.set_params(
+            queue,
+            &DropletParams {
+                strength: self.state.droplets(),
+                speed: 0.3,
+                scale: 12.0,
+                _pad: 0.0,
+            },
+        )
+    }
+
+    /// Draws every particle as an instance of `mesh`, a unit sized shape centred on the
+    /// origin such as particle_mesh.
+    pub fn draw(&mut self, ctx: &mut DrawCtx, mesh: &Mesh, mat: &Material) {
+        if self.raw.is_empty() {
+            return;
+        }
+        if self.raw.len() > self.capacity {
+            self.capacity = self.raw.len().next_power_of_two();
+            self.buffer = create_instance_buffer(&ctx.device_surface.device, self.capacity);
+        }
+        ctx.write_buffer(self.buffer.clone(), 0, bytemuck::cast_slice(&self.raw));
+        ctx.set_vertex_buffer(1, self.buffer.clone());
+        ctx.draw_mesh_instanced(mesh, mat, 0..self.raw.len() as u32);
+    }
+}
+
+/// Two crossed unit quads centred on the origin, visible from any side. Stretched into
+/// streaks for rain and left square for snow.
+pub fn particle_mesh(device: &wgpu::Device, material: usize) -> Mesh {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for (axis, normal) in [
+        ([1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
+    ] {
+        let base = vertices.len() as u32;
+        for (u, v) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+            vertices.push(Vertex3D {
+                position: [axis[0] * (u - 0.5), v - 0.5, axis[2] * (u - 0.5)],
+                tex_coords: [u, 1.0 - v],
+                normal,
+                ..Default::default()
+            });
+        }
+        indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+        indices.extend([base, base + 2, base + 1, base, base + 3, base + 2]);
+    }
+    Mesh::new(device, "Weather Particle", &vertices, &indices, material)
+}
+
+fn create_instance_buffer(device: &wgpu::Device, capacity: usize) -> Arc<wgpu::Buffer> {
+    Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Weather Instances"),
+        size: (capacity * std::mem::size_of::<InstanceRaw>()) as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    }))
+}
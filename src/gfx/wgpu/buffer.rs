@@ -1,6 +1,8 @@
 use std::sync::Arc;
 
 use wgpu::{util::DeviceExt, VertexAttribute};
+
+use super::shader::{DepthMode, PipelineOptions};
 const TEMP: u32 = 0;
 
 pub struct Instance {
@@ -26,6 +28,14 @@ impl Instance {
     }
 }
 impl InstanceRaw {
+    /// From a full model matrix, e.g. with scale, and the matrix normals are rotated by.
+    pub fn new(model: cgmath::Matrix4<f32>, normal: cgmath::Matrix3<f32>) -> Self {
+        Self {
+            model: model.into(),
+            normal: normal.into(),
+        }
+    }
+
     const ATTRIBS: [VertexAttribute; 7] = wgpu::vertex_attr_array![5 => Float32x4, 6 => Float32x4, 7 => Float32x4, 8 => Float32x4, 9 => Float32x3, 10 => Float32x3, 11 => Float32x3];
     pub fn buffer_layout() -> wgpu::VertexBufferLayout<'static> {
         use std::mem;
@@ -47,18 +57,43 @@ pub fn create_render_pipeline(
     shader: wgpu::ShaderModuleDescriptor,
 ) -> wgpu::RenderPipeline {
     let shader = device.create_shader_module(shader);
+    create_render_pipeline_with_options(
+        device,
+        layout,
+        color_format,
+        depth_format,
+        vertex_layouts,
+        &shader,
+        &PipelineOptions::default(),
+        None,
+    )
+}
 
+#[allow(clippy::too_many_arguments)]
+pub fn create_render_pipeline_with_options(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    color_format: wgpu::TextureFormat,
+    depth_format: Option<wgpu::TextureFormat>,
+    vertex_layouts: &[wgpu::VertexBufferLayout],
+    shader: &wgpu::ShaderModule,
+    options: &PipelineOptions,
+    cache: Option<&wgpu::PipelineCache>,
+) -> wgpu::RenderPipeline {
+    let vertex_layouts: Vec<_> = vertex_layouts.iter().cloned().map(Some).collect();
     device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some("Render Pipeline"),
         layout: Some(layout),
         vertex: wgpu::VertexState {
-            module: &shader,
-            entry_point: "vs_main",
-            buffers: vertex_layouts,
+            module: shader,
+            entry_point: Some("vs_main"),
+            compilation_options: Default::default(),
+            buffers: &vertex_layouts,
         },
         fragment: Some(wgpu::FragmentState {
-            module: &shader,
-            entry_point: "fs_main",
+            module: shader,
+            entry_point: Some("fs_main"),
+            compilation_options: Default::default(),
             targets: &[Some(wgpu::ColorTargetState {
                 format: color_format,
                 blend: Some(wgpu::BlendState {
@@ -80,18 +115,21 @@ pub fn create_render_pipeline(
             // Requires Features::CONSERVATIVE_RASTERIZATION
             conservative: false,
         },
-        depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
-            format,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::Less,
-            stencil: wgpu::StencilState::default(),
-            bias: wgpu::DepthBiasState::default(),
-        }),
+        depth_stencil: options
+            .depth_format(depth_format)
+            .map(|format| wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: Some(options.depth == DepthMode::ReadWrite),
+                depth_compare: Some(wgpu::CompareFunction::Less),
+                stencil: wgpu::StencilState::default(),
+                bias: options.depth_bias,
+            }),
         multisample: wgpu::MultisampleState {
             count: 1,
             mask: !0,
             alpha_to_coverage_enabled: false,
         },
-        multiview: None,
+        multiview_mask: None,
+        cache,
     })
 }
@@ -0,0 +1,279 @@
+use std::{borrow::Cow, cell::RefCell, sync::Arc};
+
+use crate::eng::command::RenderCommand;
+
+/// Largest block of immediates requested from the device, what Vulkan guarantees.
+pub const MAX_IMMEDIATE_SIZE: u32 = 128;
+
+/// How a shader receives its immediates, small per draw data like object indices and tints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImmediateMode {
+    /// wgpu immediates (push constants), set with RenderCommand::SetImmediates.
+    Native,
+    /// A dynamic offset into a uniform buffer bound at binding 0 of this group, for devices
+    /// without Features::IMMEDIATES.
+    Uniform(u32),
+}
+
+impl ImmediateMode {
+    /// Native if the device has the feature and room for `size` bytes, otherwise a uniform
+    /// at `fallback_group`.
+    pub fn choose(
+        features: wgpu::Features,
+        limits: &wgpu::Limits,
+        size: u32,
+        fallback_group: u32,
+    ) -> Self {
+        if features.contains(wgpu::Features::IMMEDIATES) && size <= limits.max_immediate_size {
+            Self::Native
+        } else {
+            Self::Uniform(fallback_group)
+        }
+    }
+
+    pub fn for_device(device: &wgpu::Device, size: u32, fallback_group: u32) -> Self {
+        Self::choose(device.features(), &device.limits(), size, fallback_group)
+    }
+}
+
+/// Rewrites the `var<immediate>` declaration of `source` for `mode`. Shaders are written
+/// against native immediates, the fallback turns the declaration into a uniform binding.
+pub fn immediate_wgsl(source: &str, mode: ImmediateMode) -> Cow<'_, str> {
+    match mode {
+        ImmediateMode::Native => Cow::Borrowed(source),
+        ImmediateMode::Uniform(group) => Cow::Owned(source.replace(
+            "var<immediate>",
+            &format!("@group({}) @binding(0) var<uniform>", group),
+        )),
+    }
+}
+
+/// Distance between two fallback slots in the uniform buffer, `size` rounded up to the
+/// device's dynamic offset alignment.
+pub fn uniform_stride(size: u32, offset_alignment: u32) -> u32 {
+    size.max(4).next_multiple_of(offset_alignment.max(4))
+}
+
+#[derive(Debug)]
+struct UniformRing {
+    buffer: Arc<wgpu::Buffer>,
+    bind_group: Arc<wgpu::BindGroup>,
+    capacity: u32,
+    next: u32,
+    frame: u64,
+}
+
+#[derive(Debug)]
+struct UniformFallback {
+    layout: wgpu::BindGroupLayout,
+    stride: u32,
+    ring: RefCell<UniformRing>,
+}
+
+impl UniformFallback {
+    const INITIAL_SLOTS: u32 = 64;
+
+    fn new(device: &wgpu::Device, size: u32) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("immediates_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(size as u64),
+                },
+                count: None,
+            }],
+        });
+        let stride = uniform_stride(size, device.limits().min_uniform_buffer_offset_alignment);
+        let ring = create_ring(device, &layout, size, stride, Self::INITIAL_SLOTS);
+        Self {
+            layout,
+            stride,
+            ring: RefCell::new(ring),
+        }
+    }
+}
+
+/// Per draw data for one pipeline layout, as wgpu immediates where the device supports
+/// them and a dynamic uniform buffer otherwise. Build the pipeline layout with
+/// `pipeline_layout` and the shader source with `wgsl`, then set the data per draw with
+/// DrawCtx::set_immediates.
+#[derive(Debug)]
+pub struct Immediates {
+    mode: ImmediateMode,
+    size: u32,
+    fallback: Option<UniformFallback>,
+}
+
+impl Immediates {
+    /// `size` bytes of immediates, falling back to a uniform at `fallback_group`. Pick the
+    /// first group the pipeline layout doesn't use.
+    pub fn new(device: &wgpu::Device, size: u32, fallback_group: u32) -> Self {
+        let size = size.max(4).next_multiple_of(wgpu::IMMEDIATE_DATA_ALIGNMENT);
+        let mode = ImmediateMode::for_device(device, size, fallback_group);
+        let fallback = match mode {
+            ImmediateMode::Native => None,
+            ImmediateMode::Uniform(_) => Some(UniformFallback::new(device, size)),
+        };
+        Self {
+            mode,
+            size,
+            fallback,
+        }
+    }
+
+    #[inline]
+    pub fn mode(&self) -> ImmediateMode {
+        self.mode
+    }
+
+    #[inline]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Value for PipelineLayoutDescriptor::immediate_size, 0 with the fallback.
+    #[inline]
+    pub fn immediate_size(&self) -> u32 {
+        match self.mode {
+            ImmediateMode::Native => self.size,
+            ImmediateMode::Uniform(_) => 0,
+        }
+    }
+
+    /// Layout the fallback binds at its group, None with native immediates.
+    pub fn bind_group_layout(&self) -> Option<&wgpu::BindGroupLayout> {
+        self.fallback.as_ref().map(|f| &f.layout)
+    }
+
+    /// Pipeline layout of `bind_group_layouts` plus the immediates. The fallback layout is
+    /// placed at its group, groups in between are left empty.
+    pub fn pipeline_layout(
+        &self,
+        device: &wgpu::Device,
+        label: &str,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+    ) -> wgpu::PipelineLayout {
+        let mut layouts: Vec<Option<&wgpu::BindGroupLayout>> =
+            bind_group_layouts.iter().copied().map(Some).collect();
+        if let (ImmediateMode::Uniform(group), Some(layout)) = (self.mode, self.bind_group_layout())
+        {
+            let group = group as usize;
+            if layouts.len() <= group {
+                layouts.resize(group + 1, None);
+            }
+            if layouts[group].is_some() {
+                log::warn!(
+                    "Immediates::pipeline_layout => '{}' already uses group {}, replaced",
+                    label,
+                    group
+                );
+            }
+            layouts[group] = Some(layout);
+        }
+        device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(label),
+            bind_group_layouts: &layouts,
+            immediate_size: self.immediate_size(),
+        })
+    }
+
+    /// `source` with its `var<immediate>` declaration rewritten for this device.
+    pub fn wgsl<'a>(&self, source: &'a str) -> Cow<'a, str> {
+        immediate_wgsl(source, self.mode)
+    }
+
+    /// The command that makes `data` visible to the following draws, None if it doesn't
+    /// fit. The fallback writes it to the next free slot of its uniform buffer, slots are
+    /// reused once `frame` changes.
+    pub fn command(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        frame: u64,
+        data: &[u8],
+    ) -> Option<RenderCommand> {
+        if data.len() > self.size as usize {
+            log::warn!(
+                "Immediates::command => {} bytes don't fit in {}, skipped",
+                data.len(),
+                self.size
+            );
+            return None;
+        }
+        let mut bytes = data.to_vec();
+        bytes.resize(
+            data.len()
+                .next_multiple_of(wgpu::IMMEDIATE_DATA_ALIGNMENT as usize),
+            0,
+        );
+
+        let (ImmediateMode::Uniform(group), Some(fallback)) = (self.mode, &self.fallback) else {
+            return Some(RenderCommand::SetImmediates(0, bytes));
+        };
+        let mut ring = fallback.ring.borrow_mut();
+        if ring.frame != frame {
+            ring.frame = frame;
+            ring.next = 0;
+        }
+        if ring.next == ring.capacity {
+            // Earlier draws of this frame keep the old buffer alive through their bind group.
+            let capacity = ring.capacity * 2;
+            *ring = UniformRing {
+                frame,
+                ..create_ring(
+                    device,
+                    &fallback.layout,
+                    self.size,
+                    fallback.stride,
+                    capacity,
+                )
+            };
+        }
+        let offset = ring.next * fallback.stride;
+        queue.write_buffer(&ring.buffer, offset as u64, &bytes);
+        ring.next += 1;
+        Some(RenderCommand::SetBindGroup(
+            group,
+            ring.bind_group.clone(),
+            Some(vec![offset]),
+        ))
+    }
+}
+
+fn create_ring(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    size: u32,
+    stride: u32,
+    capacity: u32,
+) -> UniformRing {
+    let buffer = Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Immediates Fallback"),
+        size: (stride * capacity) as u64,
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    }));
+    let bind_group = Arc::new(device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("immediates_bind_group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer: &buffer,
+                offset: 0,
+                size: wgpu::BufferSize::new(size as u64),
+            }),
+        }],
+    }));
+    UniformRing {
+        buffer,
+        bind_group,
+        capacity,
+        next: 0,
+        frame: 0,
+    }
+}
@@ -1,4 +1,8 @@
 pub mod buffer;
+pub mod immediate;
+pub mod pipeline_cache;
+pub mod shader;
+pub mod surface;
 pub mod texture;
 pub mod uniform;
 pub mod vertex;
@@ -0,0 +1,79 @@
+use std::{path::PathBuf, sync::Arc};
+
+use crate::sys::fs;
+
+/// Driver pipeline cache persisted in the app's cache directory, so pipelines compiled on
+/// a previous run don't have to be compiled again. Only Vulkan supports it at the moment,
+/// the driver validates the data and silently starts over if it doesn't match.
+#[derive(Debug)]
+pub struct PersistentPipelineCache {
+    cache: Arc<wgpu::PipelineCache>,
+    path: PathBuf,
+}
+
+impl PersistentPipelineCache {
+    /// File the cache for `adapter` is stored in, None if the backend has no pipeline cache.
+    /// Keyed by vendor and device, and by engine version so stale shaders are dropped.
+    pub fn file_name(adapter: &wgpu::AdapterInfo) -> Option<String> {
+        wgpu::util::pipeline_cache_key(adapter)
+            .map(|key| format!("{}_{}.bin", key, env!("CARGO_PKG_VERSION")))
+    }
+
+    /// Creates the cache from the file in `dir` if there is one. Returns None if the device
+    /// was not created with Features::PIPELINE_CACHE or the backend doesn't support it.
+    pub fn load(
+        device: &wgpu::Device,
+        adapter: &wgpu::AdapterInfo,
+        dir: impl Into<PathBuf>,
+    ) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::PIPELINE_CACHE) {
+            return None;
+        }
+        let path = dir.into().join(Self::file_name(adapter)?);
+        let data = match std::fs::read(&path) {
+            Ok(data) => Some(data),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                log::warn!("PersistentPipelineCache::load => {}: {}", path.display(), e);
+                None
+            }
+        };
+        // SAFETY: data is either None or was written by PersistentPipelineCache::save
+        // through get_data, and fallback makes the driver discard anything it rejects.
+        let cache = unsafe {
+            device.create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+                label: Some("Pipeline Cache"),
+                data: data.as_deref(),
+                fallback: true,
+            })
+        };
+        log::info!(
+            "PersistentPipelineCache::load => {} ({})",
+            path.display(),
+            if data.is_some() { "warm" } else { "cold" }
+        );
+        Some(Self {
+            cache: Arc::new(cache),
+            path,
+        })
+    }
+
+    /// Pass to the `cache` field of pipeline descriptors.
+    #[inline]
+    pub fn cache(&self) -> &Arc<wgpu::PipelineCache> {
+        &self.cache
+    }
+
+    #[inline]
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Writes everything compiled so far, call after warm-up and on exit.
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(data) = self.cache.get_data() {
+            fs::write_atomic(&self.path, &data)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+use std::{cell::RefCell, collections::HashMap, fmt, sync::Arc};
+
+use wgpu::naga;
+
+use super::buffer::create_render_pipeline_with_options;
+
+/// Diffuse only mesh shader, the fallback of RenderWindow::mesh_shader.
+pub const UNLIT_WGSL: &str = include_str!("../../shaders/unlit.wgsl");
+
+/// A WGSL shader that failed to parse, validate or build its pipeline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderError {
+    pub label: String,
+    /// 1-based line and column of the error, None if wgpu didn't point at the source.
+    pub location: Option<(u32, u32)>,
+    pub message: String,
+    /// Full report with the offending source lines, meant for the log.
+    pub report: String,
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location {
+            Some((line, column)) => {
+                write!(f, "{}:{}:{}: {}", self.label, line, column, self.message)
+            }
+            None => write!(f, "{}: {}", self.label, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// Parses and validates `source` the way wgpu would, without creating anything on the
+/// device, whose default error handler panics on invalid shaders.
+pub fn check_wgsl(label: &str, source: &str) -> Result<(), ShaderError> {
+    let module = naga::front::wgsl::parse_str(source).map_err(|e| ShaderError {
+        label: String::from(label),
+        location: e.location(source).map(|l| (l.line_number, l.line_position)),
+        message: String::from(e.message()),
+        report: e.emit_to_string_with_path(source, label),
+    })?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)
+    .map_err(|e| ShaderError {
+        label: String::from(label),
+        location: e.location(source).map(|l| (l.line_number, l.line_position)),
+        message: e.to_string(),
+        report: e.emit_to_string_with_path(source, label),
+    })?;
+    Ok(())
+}
+
+/// How a pipeline uses the depth attachment of the pass it is drawn in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DepthMode {
+    /// Depth tested and written, the usual mode for opaque 3D geometry.
+    #[default]
+    ReadWrite,
+    /// Depth tested but never written, e.g. for transparent geometry.
+    ReadOnly,
+    /// No depth-stencil state at all, the pipeline can only be used in passes
+    /// without a depth attachment such as 2D and UI passes.
+    Disabled,
+}
+
+/// Fixed function state that can vary between pipelines built from the same Shader.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PipelineOptions {
+    /// Constant/slope scaled depth offset, the wgpu equivalent of glPolygonOffset.
+    /// Used to keep decals and outlines from z-fighting with their base geometry.
+    pub depth_bias: wgpu::DepthBiasState,
+    pub depth: DepthMode,
+}
+
+impl PipelineOptions {
+    pub const fn with_depth_bias(mut self, depth_bias: wgpu::DepthBiasState) -> Self {
+        self.depth_bias = depth_bias;
+        self
+    }
+
+    pub const fn with_depth(mut self, depth: DepthMode) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Depth format of pipelines built with these options from a shader targeting `depth_format`.
+    pub fn depth_format(
+        &self,
+        depth_format: Option<wgpu::TextureFormat>,
+    ) -> Option<wgpu::TextureFormat> {
+        match self.depth {
+            DepthMode::Disabled => None,
+            DepthMode::ReadWrite | DepthMode::ReadOnly => depth_format,
+        }
+    }
+}
+
+/// A shader module along with everything needed to build render pipelines from it.
+/// Pipelines are created on demand for each unique set of PipelineOptions and cached.
+pub struct Shader {
+    module: wgpu::ShaderModule,
+    layout: wgpu::PipelineLayout,
+    color_format: wgpu::TextureFormat,
+    depth_format: Option<wgpu::TextureFormat>,
+    vertex_layouts: Vec<wgpu::VertexBufferLayout<'static>>,
+    options: PipelineOptions,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    cache: Option<Arc<wgpu::PipelineCache>>,
+    variants: RefCell<HashMap<PipelineOptions, Arc<wgpu::RenderPipeline>>>,
+}
+
+impl Shader {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        desc: wgpu::ShaderModuleDescriptor,
+        layout: wgpu::PipelineLayout,
+        color_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+        vertex_layouts: &[wgpu::VertexBufferLayout<'static>],
+        options: PipelineOptions,
+        cache: Option<Arc<wgpu::PipelineCache>>,
+    ) -> Self {
+        let module = device.create_shader_module(desc);
+        let pipeline = create_render_pipeline_with_options(
+            device,
+            &layout,
+            color_format,
+            depth_format,
+            vertex_layouts,
+            &module,
+            &options,
+            cache.as_deref(),
+        );
+
+        Self {
+            module,
+            layout,
+            color_format,
+            depth_format,
+            vertex_layouts: vertex_layouts.to_vec(),
+            options,
+            pipeline: Arc::new(pipeline),
+            cache,
+            variants: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Builds a shader from `source` with the same layout, formats and options as this one,
+    /// e.g. to hot reload it. Errors are returned instead of panicking so the caller can
+    /// keep drawing with this shader.
+    pub fn with_source(
+        &self,
+        device: &wgpu::Device,
+        label: &str,
+        source: &str,
+    ) -> Result<Self, ShaderError> {
+        check_wgsl(label, source)?;
+
+        // Catches what naga can't see, e.g. bindings the pipeline layout doesn't have.
+        // Popping the scope can't be waited on in the browser, naga's checks have to do.
+        #[cfg(not(target_arch = "wasm32"))]
+        let scope = device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = Self::new(
+            device,
+            wgpu::ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(source.into()),
+            },
+            self.layout.clone(),
+            self.color_format,
+            self.depth_format,
+            &self.vertex_layouts,
+            self.options,
+            self.cache.clone(),
+        );
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(e) = pollster::block_on(scope.pop()) {
+            return Err(ShaderError {
+                label: String::from(label),
+                location: None,
+                message: e.to_string(),
+                report: format!("{:?}", e),
+            });
+        }
+        Ok(shader)
+    }
+
+    /// The pipeline built with this shader's default options.
+    #[inline]
+    pub fn pipeline(&self) -> Arc<wgpu::RenderPipeline> {
+        self.pipeline.clone()
+    }
+
+    #[inline]
+    pub const fn options(&self) -> PipelineOptions {
+        self.options
+    }
+
+    /// Depth attachment format the pipeline for `options` expects, None if it has no depth state.
+    #[inline]
+    pub fn depth_format(&self, options: PipelineOptions) -> Option<wgpu::TextureFormat> {
+        options.depth_format(self.depth_format)
+    }
+
+    /// Returns the pipeline for the given options, building and caching it on first use.
+    pub fn variant(
+        &self,
+        device: &wgpu::Device,
+        options: PipelineOptions,
+    ) -> Arc<wgpu::RenderPipeline> {
+        if options == self.options {
+            return self.pipeline();
+        }
+
+        self.variants
+            .borrow_mut()
+            .entry(options)
+            .or_insert_with(|| {
+                Arc::new(create_render_pipeline_with_options(
+                    device,
+                    &self.layout,
+                    self.color_format,
+                    self.depth_format,
+                    &self.vertex_layouts,
+                    &self.module,
+                    &options,
+                    self.cache.as_deref(),
+                ))
+            })
+            .clone()
+    }
+
+    /// Same as Shader::variant but only overrides the depth bias of the default options.
+    pub fn with_depth_bias(
+        &self,
+        device: &wgpu::Device,
+        depth_bias: wgpu::DepthBiasState,
+    ) -> Arc<wgpu::RenderPipeline> {
+        self.variant(device, self.options.with_depth_bias(depth_bias))
+    }
+}
@@ -0,0 +1,234 @@
+use std::fmt;
+
+/// Why no surface texture could be acquired this frame, the failure cases of
+/// wgpu::CurrentSurfaceTexture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SurfaceError {
+    /// Skip the frame and try again.
+    Timeout,
+    /// The window is minimized or hidden, skip the frame.
+    Occluded,
+    /// The surface changed size or format, reconfigure it and try again.
+    Outdated,
+    /// The surface has to be recreated.
+    Lost,
+    /// A validation error was caught while acquiring.
+    Validation,
+    /// A pass's color and depth attachments differ in size, usually because the window was
+    /// resized mid frame. Nothing was encoded, resize and try again.
+    AttachmentSize,
+}
+
+impl fmt::Display for SurfaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            Self::Timeout => "timed out acquiring the surface texture",
+            Self::Occluded => "the surface is occluded",
+            Self::Outdated => "the surface is outdated and needs to be reconfigured",
+            Self::Lost => "the surface was lost",
+            Self::Validation => "validation error while acquiring the surface texture",
+            Self::AttachmentSize => "render pass attachments differ in size",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for SurfaceError {}
+
+/// Acquires the next texture of `surface`. Suboptimal textures are still used, the next
+/// resize reconfigures the surface anyway.
+pub fn acquire(surface: &wgpu::Surface) -> Result<wgpu::SurfaceTexture, SurfaceError> {
+    match surface.get_current_texture() {
+        wgpu::CurrentSurfaceTexture::Success(texture)
+        | wgpu::CurrentSurfaceTexture::Suboptimal(texture) => Ok(texture),
+        wgpu::CurrentSurfaceTexture::Timeout => Err(SurfaceError::Timeout),
+        wgpu::CurrentSurfaceTexture::Occluded => Err(SurfaceError::Occluded),
+        wgpu::CurrentSurfaceTexture::Outdated => Err(SurfaceError::Outdated),
+        wgpu::CurrentSurfaceTexture::Lost => Err(SurfaceError::Lost),
+        wgpu::CurrentSurfaceTexture::Validation => Err(SurfaceError::Validation),
+    }
+}
+
+/// Which surface format to pick from the surface's capabilities.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SurfaceFormat {
+    /// First sRGB format, the default since shaders output linear color.
+    #[default]
+    Srgb,
+    /// First non-sRGB format, for apps that do their own encoding.
+    Linear,
+    /// A specific format, falls back to Srgb when the surface doesn't support it.
+    Exact(wgpu::TextureFormat),
+}
+
+/// Surface format, alpha compositing and extra view formats, see EngineConfig::surface.
+/// Everything is checked against the surface's capabilities when the window is created,
+/// unsupported choices log a warning and fall back to a supported one.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SurfaceOptions {
+    pub format: SurfaceFormat,
+    /// Auto lets wgpu pick between Opaque and Inherit.
+    pub alpha_mode: wgpu::CompositeAlphaMode,
+    /// Formats views of the surface texture may use, only the sRGB or non-sRGB
+    /// counterpart of the chosen format is allowed.
+    pub view_formats: Vec<wgpu::TextureFormat>,
+}
+
+/// Result of SurfaceOptions::choose, valid for the capabilities it was chosen from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChosenSurface {
+    pub format: wgpu::TextureFormat,
+    pub alpha_mode: wgpu::CompositeAlphaMode,
+    pub view_formats: Vec<wgpu::TextureFormat>,
+}
+
+impl SurfaceOptions {
+    /// Applies a `surface.*` config key, returns false for keys of other sections.
+    pub fn set(&mut self, key: &str, value: &str) -> anyhow::Result<bool> {
+        let Some(field) = key.strip_prefix("surface.") else {
+            return Ok(false);
+        };
+        match field {
+            "format" => {
+                self.format = match value {
+                    "srgb" => SurfaceFormat::Srgb,
+                    "linear" => SurfaceFormat::Linear,
+                    name => SurfaceFormat::Exact(parse_texture_format(name)?),
+                }
+            }
+            "alpha_mode" => self.alpha_mode = parse_alpha_mode(value)?,
+            "view_formats" => {
+                self.view_formats = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(parse_texture_format)
+                    .collect::<anyhow::Result<_>>()?
+            }
+            _ => anyhow::bail!("SurfaceOptions::set => unknown surface setting {}", field),
+        }
+        Ok(true)
+    }
+
+    /// `key = value` pairs read back by set.
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let format = match self.format {
+            SurfaceFormat::Srgb => String::from("srgb"),
+            SurfaceFormat::Linear => String::from("linear"),
+            SurfaceFormat::Exact(format) => format!("{:?}", format),
+        };
+        let alpha_mode = match self.alpha_mode {
+            wgpu::CompositeAlphaMode::Auto => "auto",
+            wgpu::CompositeAlphaMode::Opaque => "opaque",
+            wgpu::CompositeAlphaMode::PreMultiplied => "pre_multiplied",
+            wgpu::CompositeAlphaMode::PostMultiplied => "post_multiplied",
+            wgpu::CompositeAlphaMode::Inherit => "inherit",
+        };
+        let view_formats: Vec<_> = self
+            .view_formats
+            .iter()
+            .map(|f| format!("{:?}", f))
+            .collect();
+        vec![
+            (String::from("surface.format"), format),
+            (String::from("surface.alpha_mode"), String::from(alpha_mode)),
+            (
+                String::from("surface.view_formats"),
+                view_formats.join(", "),
+            ),
+        ]
+    }
+
+    pub fn choose(&self, caps: &wgpu::SurfaceCapabilities) -> ChosenSurface {
+        let first_srgb = || caps.formats.iter().copied().find(|f| f.is_srgb());
+        let format = match self.format {
+            SurfaceFormat::Srgb => first_srgb(),
+            SurfaceFormat::Linear => caps.formats.iter().copied().find(|f| !f.is_srgb()),
+            SurfaceFormat::Exact(format) if caps.formats.contains(&format) => Some(format),
+            SurfaceFormat::Exact(format) => {
+                log::warn!(
+                    "SurfaceOptions::choose => {:?} is not supported by the surface, supported: {:?}",
+                    format,
+                    caps.formats
+                );
+                first_srgb()
+            }
+        }
+        .unwrap_or(caps.formats[0]);
+
+        let alpha_mode = match self.alpha_mode {
+            wgpu::CompositeAlphaMode::Auto => wgpu::CompositeAlphaMode::Auto,
+            mode if caps.alpha_modes.contains(&mode) => mode,
+            mode => {
+                log::warn!(
+                    "SurfaceOptions::choose => alpha mode {:?} is not supported, supported: {:?}",
+                    mode,
+                    caps.alpha_modes
+                );
+                caps.alpha_modes[0]
+            }
+        };
+
+        let view_formats = self
+            .view_formats
+            .iter()
+            .copied()
+            .filter(|&f| {
+                let ok = f != format && f.remove_srgb_suffix() == format.remove_srgb_suffix();
+                if !ok {
+                    log::warn!(
+                        "SurfaceOptions::choose => view format {:?} is not compatible with {:?}, ignored",
+                        f,
+                        format
+                    );
+                }
+                ok
+            })
+            .collect();
+
+        ChosenSurface {
+            format,
+            alpha_mode,
+            view_formats,
+        }
+    }
+}
+
+/// First alpha mode that composites the surface with what's behind the window, for
+/// transparent windows. Opaque when the surface supports none.
+pub fn transparent_alpha_mode(caps: &wgpu::SurfaceCapabilities) -> wgpu::CompositeAlphaMode {
+    [
+        wgpu::CompositeAlphaMode::PreMultiplied,
+        wgpu::CompositeAlphaMode::PostMultiplied,
+        wgpu::CompositeAlphaMode::Inherit,
+    ]
+    .into_iter()
+    .find(|mode| caps.alpha_modes.contains(mode))
+    .unwrap_or(wgpu::CompositeAlphaMode::Opaque)
+}
+
+/// Formats surfaces commonly support, by their wgpu::TextureFormat name.
+pub fn parse_texture_format(name: &str) -> anyhow::Result<wgpu::TextureFormat> {
+    use wgpu::TextureFormat as F;
+    Ok(match name {
+        "Bgra8Unorm" => F::Bgra8Unorm,
+        "Bgra8UnormSrgb" => F::Bgra8UnormSrgb,
+        "Rgba8Unorm" => F::Rgba8Unorm,
+        "Rgba8UnormSrgb" => F::Rgba8UnormSrgb,
+        "Rgb10a2Unorm" => F::Rgb10a2Unorm,
+        "Rgba16Float" => F::Rgba16Float,
+        _ => anyhow::bail!("parse_texture_format => unknown surface format {}", name),
+    })
+}
+
+pub fn parse_alpha_mode(name: &str) -> anyhow::Result<wgpu::CompositeAlphaMode> {
+    use wgpu::CompositeAlphaMode as A;
+    Ok(match name {
+        "auto" => A::Auto,
+        "opaque" => A::Opaque,
+        "pre_multiplied" => A::PreMultiplied,
+        "post_multiplied" => A::PostMultiplied,
+        "inherit" => A::Inherit,
+        _ => anyhow::bail!("parse_alpha_mode => unknown alpha mode {}", name),
+    })
+}
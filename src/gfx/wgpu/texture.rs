@@ -1,11 +1,41 @@
 use anyhow::*;
 use image::GenericImageView;
 
+use crate::sys::{
+    baked::{BakedTexture, TextureEncoding},
+    math::noise,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TextureType {
     Diffuse,
     Normal,
 }
-#[derive(Debug)]
+/// Side of the error texture's checker cells in pixels, the texture is 4 cells across.
+pub const ERROR_CELL: u32 = 4;
+
+/// Flat tangent space normal, (0, 0, 1) packed into a normal map texel.
+pub const FLAT_NORMAL: [u8; 4] = [128, 128, 255, 255];
+
+/// Image bound in place of textures that failed to load: a magenta and black checkerboard,
+/// or a flat normal for normal maps so the lighting doesn't hide the checkerboard.
+pub fn error_image(ty: TextureType) -> image::RgbaImage {
+    match ty {
+        TextureType::Diffuse => {
+            let size = ERROR_CELL * 4;
+            image::RgbaImage::from_fn(size, size, |x, y| {
+                if (x / ERROR_CELL + y / ERROR_CELL).is_multiple_of(2) {
+                    image::Rgba([255, 0, 255, 255])
+                } else {
+                    image::Rgba([0, 0, 0, 255])
+                }
+            })
+        }
+        TextureType::Normal => image::RgbaImage::from_pixel(1, 1, image::Rgba(FLAT_NORMAL)),
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Texture {
     pub handle: wgpu::Texture,
     pub view: wgpu::TextureView,
@@ -43,7 +73,7 @@ impl Texture {
             address_mode_w: wgpu::AddressMode::ClampToEdge,
             mag_filter: wgpu::FilterMode::Linear,
             min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::MipmapFilterMode::Nearest,
             compare: Some(wgpu::CompareFunction::LessEqual),
             lod_min_clamp: 0.0,
             lod_max_clamp: 100.0,
@@ -56,6 +86,49 @@ impl Texture {
         }
     }
 
+    /// Creates a texture that can be rendered into and then sampled from a later pass.
+    pub fn render_target(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        label: Option<&str>,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::MipmapFilterMode::Nearest,
+            ..Default::default()
+        });
+        Self {
+            handle: texture,
+            view,
+            sampler,
+        }
+    }
+
     pub fn from_bytes(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
@@ -99,14 +172,14 @@ impl Texture {
         });
 
         queue.write_texture(
-            wgpu::ImageCopyTexture {
+            wgpu::TexelCopyTextureInfo {
                 aspect: wgpu::TextureAspect::All,
                 texture: &texture,
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
             },
             &rgba,
-            wgpu::ImageDataLayout {
+            wgpu::TexelCopyBufferLayout {
                 offset: 0,
                 bytes_per_row: Some(4 * dims.0),
                 rows_per_image: Some(dims.1),
@@ -121,7 +194,7 @@ impl Texture {
             address_mode_w: wgpu::AddressMode::ClampToEdge,
             mag_filter: wgpu::FilterMode::Linear,
             min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::MipmapFilterMode::Nearest,
             ..Default::default()
         });
 
@@ -131,4 +204,159 @@ impl Texture {
             sampler,
         })
     }
+
+    /// 1x1 texture of a single color, e.g. the white diffuse of untextured materials.
+    pub fn from_color(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rgba: [u8; 4],
+        ty: TextureType,
+        label: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let img =
+            image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, image::Rgba(rgba)));
+        Self::from_image(device, queue, &img, ty, label)
+    }
+
+    /// Uploads error_image, bound by the asset loaders when a texture fails to load.
+    pub fn error_texture(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        ty: TextureType,
+        label: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let img = image::DynamicImage::ImageRgba8(error_image(ty));
+        Self::from_image(device, queue, &img, ty, label)
+    }
+
+    /// Bakes `f` with noise::bake_image and uploads it as linear data, the value is in
+    /// every channel. Use a seeded Noise inside `f` to get the same texture every run.
+    pub fn from_noise(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        width: u32,
+        height: u32,
+        f: impl Fn(f32, f32) -> f32,
+        label: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let img = image::DynamicImage::ImageLuma8(noise::bake_image(width, height, f));
+        Self::from_image(device, queue, &img, TextureType::Normal, label)
+    }
+
+    /// Uploads a texture baked by the asset importer along with all of its mips.
+    pub fn from_baked(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        baked: &BakedTexture,
+        ty: TextureType,
+        label: Option<&str>,
+    ) -> Self {
+        Self::from_baked_mips(device, queue, baked, 0, ty, label)
+    }
+
+    /// Encoding from_baked_mips uploads the mips from `first_mip` down with. Block compressed
+    /// textures stay compressed if the device supports BC and the top level is made of whole
+    /// blocks, otherwise they are decompressed to RGBA8 first.
+    pub fn upload_encoding(
+        device: &wgpu::Device,
+        baked: &BakedTexture,
+        first_mip: u32,
+    ) -> TextureEncoding {
+        let encoding = baked.encoding;
+        let (width, height) = baked.mip_size(first_mip);
+        let block = encoding.block_size();
+        if !encoding.is_compressed()
+            || (device
+                .features()
+                .contains(wgpu::Features::TEXTURE_COMPRESSION_BC)
+                && width.is_multiple_of(block)
+                && height.is_multiple_of(block))
+        {
+            encoding
+        } else {
+            TextureEncoding::Rgba8
+        }
+    }
+
+    /// Uploads the mips of a baked texture from `first_mip` down, so mip `first_mip` becomes
+    /// the texture's top level. Used by texture streaming to keep only what is needed resident.
+    pub fn from_baked_mips(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        baked: &BakedTexture,
+        first_mip: u32,
+        ty: TextureType,
+        label: Option<&str>,
+    ) -> Self {
+        let first_mip = first_mip.min(baked.mips.len().saturating_sub(1) as u32);
+        let encoding = Self::upload_encoding(device, baked, first_mip);
+        if encoding != baked.encoding {
+            return Self::from_baked_mips(device, queue, &baked.decompress(), first_mip, ty, label);
+        }
+        let format = match encoding {
+            TextureEncoding::Rgba8 => wgpu::TextureFormat::Rgba8Unorm,
+            TextureEncoding::Bc1 => wgpu::TextureFormat::Bc1RgbaUnorm,
+            TextureEncoding::Bc3 => wgpu::TextureFormat::Bc3RgbaUnorm,
+        };
+        let (width, height) = baked.mip_size(first_mip);
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: baked.mips.len() as u32 - first_mip,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: match ty {
+                TextureType::Diffuse => format.add_srgb_suffix(),
+                TextureType::Normal => format,
+            },
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        let block = encoding.block_size();
+        for (level, mip) in baked.mips.iter().enumerate().skip(first_mip as usize) {
+            let (width, height) = baked.mip_size(level as u32);
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &texture,
+                    mip_level: level as u32 - first_mip,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                mip,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(encoding.row_bytes(width)),
+                    rows_per_image: Some(encoding.rows(height)),
+                },
+                // Compressed mips smaller than a block still copy a whole block.
+                wgpu::Extent3d {
+                    width: width.next_multiple_of(block),
+                    height: height.next_multiple_of(block),
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::MipmapFilterMode::Linear,
+            ..Default::default()
+        });
+        Self {
+            handle: texture,
+            view,
+            sampler,
+        }
+    }
 }
@@ -1,37 +1,50 @@
 use std::{cell::RefCell, ops::Range, rc::Rc, sync::Arc, time::Duration};
 
-use actix::{Arbiter, SyncArbiter, System};
 use cgmath::prelude::*;
 use eng::{
-    app::{InputEventStatus, RadApp, Radium},
+    app::InputEventStatus,
     command::RenderCommand,
-    render::{light::draw_light_model, mesh::draw_model_instanced, RenderWindow},
+    render::{light::draw_light_model, mesh::draw_model_instanced},
 };
 use gfx::{
-    camera::{Camera, CameraControl, CameraUniform, PanCamera, Projection},
-    draw::DrawCtx,
-    model::{Material, Mesh, Model},
+    camera::{CameraControl, CameraUniform},
+    model::Model,
     wgpu::{
         buffer::{Instance, InstanceRaw},
-        texture::{Texture, TextureType},
+        surface::{self, SurfaceError},
+        texture::TextureType,
         vertex::Vertex3D,
     },
 };
 use sys::fs::load_model;
 use winit::{
+    application::ApplicationHandler,
     dpi::PhysicalSize,
     event::*,
-    event_loop::{ControlFlow, EventLoop},
-    window::{Window, WindowBuilder},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
+    keyboard::{KeyCode, PhysicalKey},
+    window::{Window, WindowId},
 };
 
 use wgpu::{util::DeviceExt, RenderPass};
 
 use crate::gfx::{light::LightUniform, wgpu::buffer::create_render_pipeline};
 
-mod eng;
-mod gfx;
-mod sys;
+pub mod eng;
+pub mod gfx;
+pub mod prelude;
+pub mod sys;
+
+pub use eng::{
+    app::{EngineConfig, RadApp, Radium},
+    render::RenderWindow,
+};
+pub use gfx::{
+    camera::{Camera, PanCamera, Projection},
+    draw::DrawCtx,
+    quad::QuadBuffer,
+    wgpu::{shader::Shader, texture::Texture},
+};
 
 #[cfg(test)]
 mod tests;
@@ -45,12 +58,12 @@ const INSTANCE_DISPLACEMENT: cgmath::Vector3<f32> = cgmath::Vector3::new(
 
 const INDICES: &[u16] = &[0, 1, 4, 1, 2, 4, 2, 3, 4];
 pub struct GfxState {
-    surface: wgpu::Surface,
+    surface: wgpu::Surface<'static>,
     device: wgpu::Device,
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     size: winit::dpi::PhysicalSize<u32>,
-    window: Window,
+    window: Arc<Window>,
     clear_color: wgpu::Color,
     render_pipeline: Arc<wgpu::RenderPipeline>,
 
@@ -76,36 +89,36 @@ pub struct GfxState {
 impl GfxState {
     pub async fn new(window: Window) -> anyhow::Result<Self> {
         let size = window.inner_size();
+        let window = Arc::new(window);
 
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
-            dx12_shader_compiler: Default::default(),
+            ..wgpu::InstanceDescriptor::new_without_display_handle()
         });
 
-        let surface = unsafe { instance.create_surface(&window)? };
+        let surface = instance.create_surface(window.clone())?;
 
         let adapter = instance
             .request_adapter(&wgpu::RequestAdapterOptions {
                 power_preference: wgpu::PowerPreference::default(),
                 compatible_surface: Some(&surface),
                 force_fallback_adapter: false,
+                apply_limit_buckets: false,
             })
             .await
             .expect("Failed to request compatible adapter");
 
         let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::empty(),
-                    limits: if cfg!(target_arch = "wasm32") {
-                        wgpu::Limits::downlevel_webgl2_defaults()
-                    } else {
-                        wgpu::Limits::default()
-                    },
-                    label: None,
+            .request_device(&wgpu::DeviceDescriptor {
+                label: None,
+                required_features: wgpu::Features::empty(),
+                required_limits: if cfg!(target_arch = "wasm32") {
+                    wgpu::Limits::downlevel_webgl2_defaults()
+                } else {
+                    wgpu::Limits::default()
                 },
-                None,
-            )
+                ..Default::default()
+            })
             .await
             .expect("Failed to request compatible device");
 
@@ -122,11 +135,13 @@ impl GfxState {
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
             format: surface_format,
+            color_space: wgpu::SurfaceColorSpace::Auto,
             width: size.width,
             height: size.height,
             present_mode: surface_caps.present_modes[0],
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
+            desired_maximum_frame_latency: 2,
         };
         surface.configure(&device, &config);
 
@@ -248,11 +263,11 @@ impl GfxState {
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
                 bind_group_layouts: &[
-                    &texture_bind_group_layout,
-                    &cam_bind_group_layout,
-                    &light_bind_group_layout,
+                    Some(&texture_bind_group_layout),
+                    Some(&cam_bind_group_layout),
+                    Some(&light_bind_group_layout),
                 ],
-                push_constant_ranges: &[],
+                immediate_size: 0,
             });
 
         let render_pipeline = {
@@ -312,8 +327,8 @@ impl GfxState {
         let light_render_pipeline = {
             let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Light Pipeline Layout"),
-                bind_group_layouts: &[&cam_bind_group_layout, &light_bind_group_layout],
-                push_constant_ranges: &[],
+                bind_group_layouts: &[Some(&cam_bind_group_layout), Some(&light_bind_group_layout)],
+                immediate_size: 0,
             });
             let shader = wgpu::ShaderModuleDescriptor {
                 label: Some("Light Shader"),
@@ -370,7 +385,7 @@ impl GfxState {
         self.size
     }
 
-    pub const fn window(&self) -> &Window {
+    pub fn window(&self) -> &Window {
         &self.window
     }
 
@@ -434,9 +449,9 @@ impl GfxState {
     pub fn input(&mut self, event: &WindowEvent) -> bool {
         match event {
             WindowEvent::KeyboardInput {
-                input:
-                    KeyboardInput {
-                        virtual_keycode: Some(key),
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key),
                         state,
                         ..
                     },
@@ -458,8 +473,8 @@ impl GfxState {
         }
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let output = self.surface.get_current_texture()?;
+    pub fn render(&mut self) -> Result<(), SurfaceError> {
+        let output = surface::acquire(&self.surface)?;
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
@@ -474,20 +489,24 @@ impl GfxState {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: &view,
+                    depth_slice: None,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Clear(self.clear_color),
-                        store: true,
+                        store: wgpu::StoreOp::Store,
                     },
                 })],
                 depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                     view: &self.depth_texture.view,
                     depth_ops: Some(wgpu::Operations {
                         load: wgpu::LoadOp::Clear(1.0),
-                        store: true,
+                        store: wgpu::StoreOp::Store,
                     }),
                     stencil_ops: None,
                 }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+                multiview_mask: None,
             });
             render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
 
@@ -539,6 +558,7 @@ impl GfxState {
                     RenderCommand::SetStencilReference(reference) => {
                         rp.set_stencil_reference(*reference)
                     }
+                    RenderCommand::SetImmediates(offset, data) => rp.set_immediates(*offset, data),
                     RenderCommand::Draw(vertices, instances) => {
                         rp.draw(vertices.clone(), instances.clone())
                     }
@@ -554,82 +574,134 @@ impl GfxState {
                     RenderCommand::DrawIndexedIndirect(indirect_buffer, indirect_offset) => {
                         rp.draw_indexed_indirect(&indirect_buffer, *indirect_offset)
                     }
+                    RenderCommand::MultiDrawIndirect(indirect_buffer, indirect_offset, count) => {
+                        rp.multi_draw_indirect(indirect_buffer, *indirect_offset, *count)
+                    }
+                    RenderCommand::MultiDrawIndexedIndirect(
+                        indirect_buffer,
+                        indirect_offset,
+                        count,
+                    ) => rp.multi_draw_indexed_indirect(indirect_buffer, *indirect_offset, *count),
+                    RenderCommand::BeginOcclusionQuery(query_index) => {
+                        rp.begin_occlusion_query(*query_index)
+                    }
+                    RenderCommand::EndOcclusionQuery => rp.end_occlusion_query(),
+                    RenderCommand::BeginPipelineStatisticsQuery(query_set, query_index) => {
+                        rp.begin_pipeline_statistics_query(query_set, *query_index)
+                    }
+                    RenderCommand::EndPipelineStatisticsQuery => rp.end_pipeline_statistics_query(),
                     RenderCommand::ExecuteBundles() => todo!(),
                 }
             }
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+        self.queue.present(output);
         Ok(())
     }
 }
 
 pub async fn _run_loop() -> anyhow::Result<()> {
     env_logger::init();
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop)?;
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::Poll);
+
+    let mut handler = GfxHandler {
+        renderer: None,
+        last_dt: std::time::Instant::now(),
+    };
+    event_loop.run_app(&mut handler)?;
+    Ok(())
+}
 
-    let mut renderer = GfxState::new(window).await?;
-    let mut last_dt = std::time::Instant::now();
+struct GfxHandler {
+    renderer: Option<GfxState>,
+    last_dt: std::time::Instant,
+}
 
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Poll;
+impl ApplicationHandler for GfxHandler {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.renderer.is_some() {
+            return;
+        }
+        let renderer = event_loop
+            .create_window(Window::default_attributes())
+            .map_err(anyhow::Error::from)
+            .and_then(|window| pollster::block_on(GfxState::new(window)));
+        match renderer {
+            Ok(renderer) => self.renderer = Some(renderer),
+            Err(e) => {
+                eprintln!("{:?}", e);
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        let Some(renderer) = self.renderer.as_mut() else {
+            return;
+        };
+        if window_id != renderer.window().id() || renderer.input(&event) {
+            return;
+        }
         match event {
-            Event::WindowEvent {
-                ref event,
-                window_id,
-            } if window_id == renderer.window().id() => {
-                if !renderer.input(event) {
-                    match event {
-                        WindowEvent::CloseRequested
-                        | WindowEvent::KeyboardInput {
-                            input:
-                                KeyboardInput {
-                                    state: ElementState::Pressed,
-                                    virtual_keycode: Some(VirtualKeyCode::Escape),
-                                    ..
-                                },
-                            ..
-                        } => {
-                            *control_flow = ControlFlow::Exit;
-                        }
-                        WindowEvent::Resized(physical_size) => {
-                            renderer.resize(*physical_size);
-                        }
-                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                            renderer.resize(**new_inner_size);
-                        }
-                        _ => {}
-                    }
-                }
+            WindowEvent::CloseRequested
+            | WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        physical_key: PhysicalKey::Code(KeyCode::Escape),
+                        ..
+                    },
+                ..
+            } => {
+                event_loop.exit();
             }
-            Event::RedrawRequested(window_id) if window_id == renderer.window().id() => {
+            WindowEvent::Resized(physical_size) => {
+                renderer.resize(physical_size);
+            }
+            WindowEvent::ScaleFactorChanged { .. } => {
+                renderer.resize(renderer.window().inner_size());
+            }
+            WindowEvent::RedrawRequested => {
                 let now = std::time::Instant::now();
-                let dt = now - last_dt;
-                last_dt = now;
+                let dt = now - self.last_dt;
+                self.last_dt = now;
                 renderer.update(dt);
                 match renderer.render() {
                     Ok(_) => {}
-                    Err(wgpu::SurfaceError::Lost) => renderer.resize(renderer.size),
-                    Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
+                    Err(SurfaceError::Lost | SurfaceError::Outdated) => {
+                        renderer.resize(renderer.size)
+                    }
+                    Err(SurfaceError::Timeout | SurfaceError::Occluded) => {}
                     Err(e) => eprintln!("{:?}", e),
                 }
             }
-            Event::MainEventsCleared => {
-                renderer.window().request_redraw();
-            }
-            Event::DeviceEvent {
-                event: DeviceEvent::MouseMotion { delta },
-                ..
-            } => {
-                if renderer.mouse_pressed {
-                    renderer.camera.process_mouse(delta.0, delta.1);
-                }
-            }
             _ => {}
         }
-    });
+    }
+
+    fn device_event(&mut self, _: &ActiveEventLoop, _: DeviceId, event: DeviceEvent) {
+        let Some(renderer) = self.renderer.as_mut() else {
+            return;
+        };
+        if let DeviceEvent::MouseMotion { delta } = event {
+            if renderer.mouse_pressed {
+                renderer.camera.process_mouse(delta.0, delta.1);
+            }
+        }
+    }
+
+    fn about_to_wait(&mut self, _: &ActiveEventLoop) {
+        if let Some(renderer) = self.renderer.as_ref() {
+            renderer.window().request_redraw();
+        }
+    }
 }
 
 struct Renderer {
@@ -723,9 +795,9 @@ impl RadApp for Renderer {
         let camera = window.camera_mut();
         match event {
             WindowEvent::KeyboardInput {
-                input:
-                    KeyboardInput {
-                        virtual_keycode: Some(key),
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key),
                         state,
                         ..
                     },
@@ -748,7 +820,7 @@ impl RadApp for Renderer {
         }
     }
 
-    fn draw_frame(&mut self, ctx: &mut gfx::draw::DrawCtx) -> Result<(), wgpu::SurfaceError> {
+    fn draw_frame(&mut self, ctx: &mut gfx::draw::DrawCtx) -> Result<(), SurfaceError> {
         ctx.set_vertex_buffer(1, self.instance_buffer.clone());
 
         ctx.draw_light_model(&self.obj_model);
@@ -758,6 +830,7 @@ impl RadApp for Renderer {
     }
 }
 
+/// Runs the demo scene, await it inside a tokio runtime since model loading uses tokio::fs.
 pub async fn run_loop() -> anyhow::Result<()> {
     env_logger::init();
 
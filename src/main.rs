@@ -1,12 +1,39 @@
-use actix::prelude::*;
 use anyhow::Result;
-use rad::run_loop;
-
-mod eng;
-mod gfx;
-mod sys;
+use rad::{run_loop, sys};
 
 fn main() -> Result<()> {
-    let sys = System::new();
-    sys.block_on(run_loop())
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.get(1).map(String::as_str) == Some("asset-import") {
+        return asset_import(&args[2..]);
+    }
+
+    // Model loading goes through tokio::fs, so the loop runs inside a runtime.
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(run_loop())
+}
+
+/// radium asset-import [--uncompressed] <source dir> <output pack>
+fn asset_import(args: &[String]) -> Result<()> {
+    let mut settings = sys::import::ImportSettings::default();
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--uncompressed" => settings.compress_textures = false,
+            _ => paths.push(arg),
+        }
+    }
+    let [src, out] = paths[..] else {
+        anyhow::bail!("usage: radium asset-import [--uncompressed] <source dir> <output pack>");
+    };
+    let report = sys::import::import_dir(src.as_ref(), out.as_ref(), &settings)?;
+    println!(
+        "Packed {} textures ({} compressed), {} models, {} fonts and {} raw files into {}",
+        report.textures, report.compressed, report.models, report.fonts, report.raw, out
+    );
+    for (name, reason) in report.skipped.iter() {
+        println!("Skipped {}: {}", name, reason);
+    }
+    Ok(())
 }
@@ -0,0 +1,21 @@
+// One line import for apps, `use rad::prelude::*;`.
+
+pub use crate::{
+    eng::{
+        app::{EngineConfig, HeadlessApp, InputEventStatus, RadApp, Radium},
+        context::EngineContext,
+        render::{RenderCamera, RenderWindow},
+    },
+    gfx::{
+        camera::{Camera, CameraUniform, PanCamera, Projection},
+        draw::DrawCtx,
+        model::{Material, Mesh, Model},
+        quad::{QuadBuffer, QuadMaterial, Sprite},
+        wgpu::{
+            shader::{DepthMode, PipelineOptions, Shader},
+            surface::SurfaceError,
+            texture::Texture,
+            vertex::SpriteVertex,
+        },
+    },
+};
@@ -22,6 +22,28 @@ struct Light {
 @group(2) @binding(0)
 var<uniform> light: Light;
 
+// See gfx::environment::EnvironmentUniform.
+struct Environment {
+  fog_color: vec4<f32>,
+  fog_params: vec4<f32>,
+  sky: array<vec4<f32>, 3>,
+  ambient: vec4<f32>,
+}
+@group(2) @binding(1)
+var<uniform> environment: Environment;
+
+// See gfx::probe::ProbeUniform.
+struct Probe {
+  position_radius: vec4<f32>,
+  params: vec4<f32>,
+}
+@group(2) @binding(2)
+var t_probe: texture_cube<f32>;
+@group(2) @binding(3)
+var s_probe: sampler;
+@group(2) @binding(4)
+var<uniform> probe: Probe;
+
 struct InstanceInput {
   @location(5) model_matrix0: vec4<f32>,
   @location(6) model_matrix1: vec4<f32>,
@@ -40,10 +62,17 @@ struct VertexOutput {
   @location(1) tangent_position: vec3<f32>,
   @location(2) tangent_light_position: vec3<f32>,
   @location(3) tangent_view_position: vec3<f32>,
+  @location(4) world_position: vec3<f32>,
+  @location(5) world_normal: vec3<f32>,
 };
 
 @vertex
 fn vs_main(model: VertexInput, instance: InstanceInput,) -> VertexOutput {
+  return transform_vertex(model, instance);
+}
+
+// The body of vs_main, for other vertex entry points feeding fs_main, see crowd.wgsl.
+fn transform_vertex(model: VertexInput, instance: InstanceInput) -> VertexOutput {
   let model_matrix = mat4x4<f32>(
         instance.model_matrix0,
         instance.model_matrix1,
@@ -69,6 +98,8 @@ fn vs_main(model: VertexInput, instance: InstanceInput,) -> VertexOutput {
   out.tangent_position = tangent_matrix * world_position.xyz;
   out.tangent_view_position = tangent_matrix * camera.view_pos.xyz;
   out.tangent_light_position = tangent_matrix * light.position.xyz;
+  out.world_position = world_position.xyz;
+  out.world_normal = world_normal;
   return out;
 }
 
@@ -84,10 +115,16 @@ var s_normal: sampler;
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
   let object_color: vec4<f32> = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+  let result = shade(in, object_color);
+  return vec4<f32>(apply_fog(result, in.world_position), object_color.a);
+}
+
+// The window light and reflection probe before fog, shared with clustered.wgsl.
+fn shade(in: VertexOutput, object_color: vec4<f32>) -> vec3<f32> {
   let object_normal: vec4<f32> = textureSample(t_normal, s_normal, in.tex_coords);
 
   let ambient_strength = 0.001;
-  let ambient_color = light.color.xyz * ambient_strength;
+  let ambient_color = light.color.xyz * ambient_strength + environment.ambient.xyz;
 
   let tangent_normal = object_normal.xyz * 2.0 - 1.0;
   let light_dir = normalize(in.tangent_light_position - in.tangent_position);
@@ -100,6 +137,98 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
   let specular_strength = pow(max(dot(tangent_normal, half_dir), 0.0), 32.0); 
   let specular_color = specular_strength * light.color;
 
-  let result = (ambient_color + diffuse_color + specular_color.xyz) * object_color.xyz; 
-  return vec4<f32>(result, object_color.a);
+  let reflection = probe_reflection(in.world_position, normalize(in.world_normal));
+  return (ambient_color + diffuse_color + specular_color.xyz) * object_color.xyz + reflection;
+}
+
+// Fresnel weighted reflection from the bound probe, black when there is none. The lookup is
+// projected onto the probe's sphere of influence so nearby surroundings line up.
+fn probe_reflection(world_position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+  let view_dir = normalize(world_position - camera.view_pos.xyz);
+  let r = reflect(view_dir, normal);
+  let offset = world_position - probe.position_radius.xyz;
+  let radius = probe.position_radius.w;
+  let b = dot(offset, r);
+  let c = dot(offset, offset) - radius * radius;
+  let dir = offset + r * (-b + sqrt(max(b * b - c, 0.0)));
+  let fresnel = 0.04 + 0.96 * pow(1.0 - max(dot(-view_dir, normal), 0.0), 5.0);
+  let color = textureSample(t_probe, s_probe, dir).rgb;
+  return color * fresnel * probe.params.x * probe.params.y;
+}
+
+fn fog_factor(distance: f32) -> f32 {
+  let params = environment.fog_params;
+  var f = 0.0;
+  switch i32(environment.fog_color.w) {
+    case 1: { f = (distance - params.x) / max(params.y - params.x, 1e-4); }
+    case 2: { f = 1.0 - exp(-params.x * distance); }
+    case 3: { f = 1.0 - exp(-pow(params.x * distance, 2.0)); }
+    default: {}
+  }
+  return clamp(f, 0.0, 1.0);
+}
+
+fn apply_fog(color: vec3<f32>, world_position: vec3<f32>) -> vec3<f32> {
+  let to_fragment = world_position - camera.view_pos.xyz;
+  let f = fog_factor(length(to_fragment));
+  let fog = mix(
+    environment.fog_color.xyz,
+    sky_color(normalize(to_fragment)),
+    environment.fog_params.z,
+  );
+  return mix(color, fog, f);
+}
+
+fn perez(a: f32, b: f32, c: f32, d: f32, e: f32, cos_theta: f32, gamma: f32) -> f32 {
+  return (1.0 + a * exp(b / cos_theta)) * (1.0 + c * exp(d * gamma) + e * pow(cos(gamma), 2.0));
+}
+
+// Mirrors Sky::color in gfx::environment, keep the two in sync.
+fn sky_color(dir: vec3<f32>) -> vec3<f32> {
+  let sky = environment.sky;
+  if environment.fog_params.w < 0.5 {
+    if dir.y >= 0.0 {
+      return mix(sky[1].xyz, sky[0].xyz, sqrt(dir.y));
+    }
+    return mix(sky[1].xyz, sky[2].xyz, sqrt(-dir.y));
+  }
+
+  let sun = sky[0].xyz;
+  let t = sky[1].x;
+  let exposure = sky[1].y;
+  let cos_theta = max(dir.y, 0.001);
+  let theta_s = acos(clamp(sun.y, 0.0, 1.0));
+  let gamma = acos(clamp(dot(dir, sun), -1.0, 1.0));
+
+  let chi = (4.0 / 9.0 - t / 120.0) * (3.14159265 - 2.0 * theta_s);
+  let zenith_y = max((4.0453 * t - 4.9710) * tan(chi) - 0.2155 * t + 2.4192, 0.0);
+  let s = theta_s;
+  let s2 = s * s;
+  let s3 = s2 * s;
+  let zenith_x = t * t * (0.00166 * s3 - 0.00375 * s2 + 0.00209 * s)
+    + t * (-0.02903 * s3 + 0.06377 * s2 - 0.03202 * s + 0.00394)
+    + (0.11693 * s3 - 0.21196 * s2 + 0.06052 * s + 0.25886);
+  let zenith_yc = t * t * (0.00275 * s3 - 0.00610 * s2 + 0.00317 * s)
+    + t * (-0.04214 * s3 + 0.08970 * s2 - 0.04153 * s + 0.00516)
+    + (0.15346 * s3 - 0.26756 * s2 + 0.06670 * s + 0.26688);
+
+  let cy = array<f32, 5>(0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703);
+  let cx = array<f32, 5>(-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452);
+  let cc = array<f32, 5>(-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529);
+  let luminance = zenith_y * perez(cy[0], cy[1], cy[2], cy[3], cy[4], cos_theta, gamma)
+    / perez(cy[0], cy[1], cy[2], cy[3], cy[4], 1.0, theta_s);
+  let x = zenith_x * perez(cx[0], cx[1], cx[2], cx[3], cx[4], cos_theta, gamma)
+    / perez(cx[0], cx[1], cx[2], cx[3], cx[4], 1.0, theta_s);
+  let y = max(zenith_yc * perez(cc[0], cc[1], cc[2], cc[3], cc[4], cos_theta, gamma)
+    / perez(cc[0], cc[1], cc[2], cc[3], cc[4], 1.0, theta_s), 1e-4);
+
+  let big_y = 1.0 - exp(-exposure * luminance);
+  let big_x = x / y * big_y;
+  let big_z = (1.0 - x - y) / y * big_y;
+  let rgb = vec3<f32>(
+    3.2406 * big_x - 1.5372 * big_y - 0.4986 * big_z,
+    -0.9689 * big_x + 1.8758 * big_y + 0.0415 * big_z,
+    0.0557 * big_x - 0.2040 * big_y + 1.0570 * big_z,
+  );
+  return max(rgb, vec3<f32>(0.0));
 }
@@ -0,0 +1,65 @@
+// Assigns point lights to view space clusters, one invocation per cluster. Prefixed with
+// cluster_common.wgsl.
+
+@group(0) @binding(0) var<uniform> params: ClusterParams;
+@group(0) @binding(1) var<storage, read> lights: array<PointLight>;
+@group(0) @binding(2) var<storage, read_write> counts: array<u32>;
+@group(0) @binding(3) var<storage, read_write> indices: array<u32>;
+
+// View space position of an NDC point on the near plane.
+fn unproject(ndc: vec2<f32>) -> vec3<f32> {
+    let p = params.inv_proj * vec4<f32>(ndc, 0.0, 1.0);
+    return p.xyz / p.w;
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let tiles = params.tiles;
+    let index = id.x;
+    if (index >= tiles.x * tiles.y * tiles.z) {
+        return;
+    }
+    let x = index % tiles.x;
+    let y = (index / tiles.x) % tiles.y;
+    let z = index / (tiles.x * tiles.y);
+
+    // Tile rows run top to bottom like fragment coordinates.
+    let ndc_min = vec2<f32>(
+        -1.0 + 2.0 * f32(x) / f32(tiles.x),
+        1.0 - 2.0 * f32(y + 1u) / f32(tiles.y),
+    );
+    let ndc_max = vec2<f32>(
+        -1.0 + 2.0 * f32(x + 1u) / f32(tiles.x),
+        1.0 - 2.0 * f32(y) / f32(tiles.y),
+    );
+    var corners = array<vec3<f32>, 4>(
+        unproject(ndc_min),
+        unproject(vec2<f32>(ndc_max.x, ndc_min.y)),
+        unproject(vec2<f32>(ndc_min.x, ndc_max.y)),
+        unproject(ndc_max),
+    );
+    let depths = vec2<f32>(cluster_slice_depth(params, z), cluster_slice_depth(params, z + 1u));
+    var lo = vec3<f32>(1e30);
+    var hi = vec3<f32>(-1e30);
+    for (var i = 0u; i < 4u; i++) {
+        for (var j = 0u; j < 2u; j++) {
+            let p = corners[i] * (depths[j] / -corners[i].z);
+            lo = min(lo, p);
+            hi = max(hi, p);
+        }
+    }
+
+    var count = 0u;
+    for (var i = 0u; i < tiles.w && count < MAX_LIGHTS_PER_CLUSTER; i++) {
+        let light = lights[i];
+        let center = (params.view * vec4<f32>(light.position_radius.xyz, 1.0)).xyz;
+        let closest = clamp(center, lo, hi);
+        let d = center - closest;
+        let radius = light.position_radius.w;
+        if (dot(d, d) <= radius * radius) {
+            indices[index * MAX_LIGHTS_PER_CLUSTER + count] = i;
+            count++;
+        }
+    }
+    counts[index] = count;
+}
@@ -0,0 +1,47 @@
+// Clustered light helpers shared by cluster.wgsl and clustered.wgsl, see gfx::cluster.
+
+// Mirrors gfx::cluster::ClusterParams.
+struct ClusterParams {
+    view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    // Tiles along x, y, depth slices and the number of lights.
+    tiles: vec4<u32>,
+    // Screen width, height in pixels and the near, far planes.
+    screen: vec4<f32>,
+}
+
+// Mirrors gfx::cluster::PointLightRaw.
+struct PointLight {
+    position_radius: vec4<f32>,
+    color_intensity: vec4<f32>,
+    // Shadow cube slot (negative without one), depth bias and normal bias.
+    shadow: vec4<f32>,
+}
+
+// Mirrors gfx::cluster::MAX_LIGHTS_PER_CLUSTER.
+const MAX_LIGHTS_PER_CLUSTER: u32 = 64u;
+
+// Slices are spaced exponentially so near clusters stay small, see ClusterGrid::slice.
+fn cluster_slice(params: ClusterParams, depth: f32) -> u32 {
+    let near = params.screen.z;
+    let far = params.screen.w;
+    let slices = f32(params.tiles.z);
+    let slice = floor(log(max(depth, near) / near) * slices / log(far / near));
+    return u32(clamp(slice, 0.0, slices - 1.0));
+}
+
+fn cluster_slice_depth(params: ClusterParams, slice: u32) -> f32 {
+    let near = params.screen.z;
+    let far = params.screen.w;
+    return near * pow(far / near, f32(slice) / f32(params.tiles.z));
+}
+
+fn cluster_index(params: ClusterParams, tile: vec3<u32>) -> u32 {
+    return (tile.z * params.tiles.y + tile.y) * params.tiles.x + tile.x;
+}
+
+// Attenuation reaching zero at the light's radius.
+fn point_light_falloff(distance: f32, radius: f32) -> f32 {
+    let x = clamp(1.0 - pow(distance / radius, 4.0), 0.0, 1.0);
+    return x * x / (distance * distance + 1.0);
+}
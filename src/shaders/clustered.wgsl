@@ -0,0 +1,58 @@
+// Forward shading with clustered point lights on top of the window light, appended to
+// basic.wgsl and cluster_common.wgsl.
+
+@group(3) @binding(0) var<uniform> cluster_params: ClusterParams;
+@group(3) @binding(1) var<storage, read> cluster_lights: array<PointLight>;
+@group(3) @binding(2) var<storage, read> cluster_counts: array<u32>;
+@group(3) @binding(3) var<storage, read> cluster_indices: array<u32>;
+@group(3) @binding(4) var t_point_shadow: texture_depth_cube_array;
+@group(3) @binding(5) var s_point_shadow: sampler_comparison;
+
+// 1 when lit, 0 when a caster is closer to the light. Lights without a shadow slot are always lit.
+fn point_shadow(light: PointLight, world_position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    let slot = i32(light.shadow.x);
+    if (slot < 0) {
+        return 1.0;
+    }
+    let to_fragment = world_position + normal * light.shadow.z - light.position_radius.xyz;
+    let depth = length(to_fragment) / light.position_radius.w - light.shadow.y;
+    return textureSampleCompareLevel(t_point_shadow, s_point_shadow, to_fragment, slot, depth);
+}
+
+// Blinn-Phong from every light of the fragment's cluster. Uses the vertex normal, point lights
+// skip the normal map.
+fn clustered_lighting(frag_coord: vec4<f32>, world_position: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let params = cluster_params;
+    let view_position = params.view * vec4<f32>(world_position, 1.0);
+    let tile_xy = min(
+        vec2<u32>(frag_coord.xy / params.screen.xy * vec2<f32>(params.tiles.xy)),
+        params.tiles.xy - vec2<u32>(1u),
+    );
+    let tile = vec3<u32>(tile_xy, cluster_slice(params, -view_position.z));
+    let cluster = cluster_index(params, tile);
+    let view_dir = normalize(camera.view_pos.xyz - world_position);
+
+    var result = vec3<f32>(0.0);
+    let count = cluster_counts[cluster];
+    for (var i = 0u; i < count; i++) {
+        let light = cluster_lights[cluster_indices[cluster * MAX_LIGHTS_PER_CLUSTER + i]];
+        let to_light = light.position_radius.xyz - world_position;
+        let distance = length(to_light);
+        let light_dir = to_light / max(distance, 1e-4);
+        let half_dir = normalize(view_dir + light_dir);
+        let diffuse = max(dot(normal, light_dir), 0.0);
+        let specular = pow(max(dot(normal, half_dir), 0.0), 32.0);
+        let falloff = point_light_falloff(distance, light.position_radius.w);
+        let shadow = point_shadow(light, world_position, normal);
+        result += light.color_intensity.rgb * light.color_intensity.w * falloff * shadow * (diffuse + specular);
+    }
+    return result;
+}
+
+@fragment
+fn fs_clustered(in: VertexOutput) -> @location(0) vec4<f32> {
+  let object_color: vec4<f32> = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+  let lights = clustered_lighting(in.clip_position, in.world_position, normalize(in.world_normal));
+  let result = shade(in, object_color) + lights * object_color.xyz;
+  return vec4<f32>(apply_fog(result, in.world_position), object_color.a);
+}
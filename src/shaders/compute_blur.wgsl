@@ -0,0 +1,34 @@
+// One direction of a separable gaussian blur, see gfx::compute::GaussianBlur.
+
+struct BlurParams {
+  direction: vec2<i32>,
+  radius: u32,
+  _pad: u32,
+  // Center weight first, packed four to a vec4.
+  weights: array<vec4<f32>, 4>,
+}
+
+@group(0) @binding(0) var<uniform> params: BlurParams;
+@group(0) @binding(1) var src: texture_2d<f32>;
+@group(0) @binding(2) var dst: texture_storage_2d<rgba16float, write>;
+
+fn weight(i: u32) -> f32 {
+  return params.weights[i / 4u][i % 4u];
+}
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+  let size = textureDimensions(dst);
+  if (id.x >= size.x || id.y >= size.y) {
+    return;
+  }
+  let max_coord = vec2<i32>(textureDimensions(src)) - vec2<i32>(1);
+  let coord = vec2<i32>(id.xy);
+  var sum = textureLoad(src, min(coord, max_coord), 0) * weight(0u);
+  for (var i = 1u; i <= params.radius; i++) {
+    let offset = params.direction * i32(i);
+    sum += textureLoad(src, clamp(coord + offset, vec2<i32>(0), max_coord), 0) * weight(i);
+    sum += textureLoad(src, clamp(coord - offset, vec2<i32>(0), max_coord), 0) * weight(i);
+  }
+  textureStore(dst, coord, sum);
+}
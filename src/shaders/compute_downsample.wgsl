@@ -0,0 +1,21 @@
+// 2x2 box filter into a half size level, see gfx::compute::DownsampleChain.
+
+@group(0) @binding(0) var src: texture_2d<f32>;
+@group(0) @binding(1) var dst: texture_storage_2d<rgba16float, write>;
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+  let size = textureDimensions(dst);
+  if (id.x >= size.x || id.y >= size.y) {
+    return;
+  }
+  let max_coord = vec2<i32>(textureDimensions(src)) - vec2<i32>(1);
+  let base = vec2<i32>(id.xy) * 2;
+  var sum = vec4<f32>(0.0);
+  for (var y = 0; y < 2; y++) {
+    for (var x = 0; x < 2; x++) {
+      sum += textureLoad(src, min(base + vec2<i32>(x, y), max_coord), 0);
+    }
+  }
+  textureStore(dst, vec2<i32>(id.xy), sum * 0.25);
+}
@@ -0,0 +1,40 @@
+// 256 bin log luminance histogram, see gfx::compute::LuminanceHistogram. Bin 0 counts
+// black texels, bins 1 to 255 split [min_log_lum, min_log_lum + log_range] evenly.
+
+struct HistogramParams {
+  min_log_lum: f32,
+  inv_log_range: f32,
+  _pad0: f32,
+  _pad1: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: HistogramParams;
+@group(0) @binding(1) var src: texture_2d<f32>;
+@group(0) @binding(2) var<storage, read_write> bins: array<atomic<u32>, 256>;
+
+var<workgroup> local_bins: array<atomic<u32>, 256>;
+
+fn luminance_bin(color: vec3<f32>) -> u32 {
+  let lum = dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+  if (lum < 0.0001) {
+    return 0u;
+  }
+  let t = clamp((log2(lum) - params.min_log_lum) * params.inv_log_range, 0.0, 1.0);
+  return u32(t * 254.0 + 1.0);
+}
+
+@compute @workgroup_size(16, 16)
+fn cs_main(
+  @builtin(global_invocation_id) id: vec3<u32>,
+  @builtin(local_invocation_index) local: u32,
+) {
+  atomicStore(&local_bins[local], 0u);
+  workgroupBarrier();
+  let size = textureDimensions(src);
+  if (id.x < size.x && id.y < size.y) {
+    let color = textureLoad(src, vec2<i32>(id.xy), 0).rgb;
+    atomicAdd(&local_bins[luminance_bin(color)], 1u);
+  }
+  workgroupBarrier();
+  atomicAdd(&bins[local], atomicLoad(&local_bins[local]));
+}
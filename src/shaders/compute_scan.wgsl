@@ -0,0 +1,57 @@
+// Exclusive prefix sum over u32, one level of gfx::compute::PrefixSum. scan_blocks scans
+// each block of 256 in place and writes the block totals to `sums`, add_offsets adds the
+// scanned totals back once the level above has scanned them.
+
+struct ScanParams {
+  count: u32,
+  _pad0: u32,
+  _pad1: u32,
+  _pad2: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: ScanParams;
+@group(0) @binding(1) var<storage, read_write> data: array<u32>;
+@group(0) @binding(2) var<storage, read_write> sums: array<u32>;
+
+const BLOCK: u32 = 256u;
+
+var<workgroup> scratch: array<u32, 256>;
+
+@compute @workgroup_size(256)
+fn scan_blocks(
+  @builtin(global_invocation_id) id: vec3<u32>,
+  @builtin(local_invocation_index) local: u32,
+  @builtin(workgroup_id) group: vec3<u32>,
+) {
+  var value = 0u;
+  if (id.x < params.count) {
+    value = data[id.x];
+  }
+  scratch[local] = value;
+  workgroupBarrier();
+  for (var offset = 1u; offset < BLOCK; offset <<= 1u) {
+    var add = 0u;
+    if (local >= offset) {
+      add = scratch[local - offset];
+    }
+    workgroupBarrier();
+    scratch[local] += add;
+    workgroupBarrier();
+  }
+  if (id.x < params.count) {
+    data[id.x] = scratch[local] - value;
+  }
+  if (local == BLOCK - 1u) {
+    sums[group.x] = scratch[local];
+  }
+}
+
+@compute @workgroup_size(256)
+fn add_offsets(
+  @builtin(global_invocation_id) id: vec3<u32>,
+  @builtin(workgroup_id) group: vec3<u32>,
+) {
+  if (id.x < params.count) {
+    data[id.x] += sums[group.x];
+  }
+}
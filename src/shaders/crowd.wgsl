@@ -0,0 +1,41 @@
+// Vertex animation texture playback for gfx::crowd, appended to basic.wgsl. Each texel holds
+// one vertex's object space position in one frame, rows are frames.
+
+@group(3) @binding(0)
+var t_vertex_animation: texture_2d<f32>;
+
+struct CrowdInstanceInput {
+  @location(5) model_matrix0: vec4<f32>,
+  @location(6) model_matrix1: vec4<f32>,
+  @location(7) model_matrix2: vec4<f32>,
+  @location(8) model_matrix3: vec4<f32>,
+
+  @location(9) normal_matrix0: vec3<f32>,
+  @location(10) normal_matrix1: vec3<f32>,
+  @location(11) normal_matrix2: vec3<f32>,
+
+  // Frame row, next frame row and the blend between them, see gfx::crowd::AnimationFrame.
+  @location(12) animation: vec4<f32>,
+}
+
+@vertex
+fn vs_crowd(
+  model: VertexInput,
+  instance: CrowdInstanceInput,
+  @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+  let a = textureLoad(t_vertex_animation, vec2<u32>(vertex_index, u32(instance.animation.x)), 0);
+  let b = textureLoad(t_vertex_animation, vec2<u32>(vertex_index, u32(instance.animation.y)), 0);
+  var animated = model;
+  animated.position = mix(a.xyz, b.xyz, instance.animation.z);
+  let rigid = InstanceInput(
+    instance.model_matrix0,
+    instance.model_matrix1,
+    instance.model_matrix2,
+    instance.model_matrix3,
+    instance.normal_matrix0,
+    instance.normal_matrix1,
+    instance.normal_matrix2,
+  );
+  return transform_vertex(animated, rigid);
+}
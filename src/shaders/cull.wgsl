@@ -0,0 +1,56 @@
+// Frustum culls InstanceRaw data and compacts the survivors for an indexed indirect draw.
+
+struct CullParams {
+    planes: array<vec4<f32>, 6>,
+    // Bounding sphere of the mesh in model space, xyz center and w radius.
+    sphere: vec4<f32>,
+    count: u32,
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
+}
+
+struct DrawArgs {
+    index_count: u32,
+    instance_count: atomic<u32>,
+    first_index: u32,
+    base_vertex: i32,
+    first_instance: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: CullParams;
+// InstanceRaw is tightly packed, a mat4 model followed by a mat3 normal matrix.
+@group(0) @binding(1) var<storage, read> instances: array<f32>;
+@group(0) @binding(2) var<storage, read_write> visible: array<f32>;
+@group(0) @binding(3) var<storage, read_write> args: DrawArgs;
+
+const STRIDE: u32 = 25u;
+
+fn column(base: u32) -> vec4<f32> {
+    return vec4<f32>(instances[base], instances[base + 1u], instances[base + 2u], instances[base + 3u]);
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if (index >= params.count) {
+        return;
+    }
+    let base = index * STRIDE;
+    let model = mat4x4<f32>(column(base), column(base + 4u), column(base + 8u), column(base + 12u));
+    let center = (model * vec4<f32>(params.sphere.xyz, 1.0)).xyz;
+    let scale = max(length(model[0].xyz), max(length(model[1].xyz), length(model[2].xyz)));
+    let radius = params.sphere.w * scale;
+
+    for (var i = 0u; i < 6u; i++) {
+        let plane = params.planes[i];
+        if (dot(plane.xyz, center) + plane.w < -radius) {
+            return;
+        }
+    }
+
+    let dst = atomicAdd(&args.instance_count, 1u) * STRIDE;
+    for (var i = 0u; i < STRIDE; i++) {
+        visible[dst + i] = instances[base + i];
+    }
+}
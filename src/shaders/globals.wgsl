@@ -0,0 +1,8 @@
+// See gfx::globals::GlobalsUniform, bound at binding 1 of the camera bind group.
+struct Globals {
+    resolution: vec2<f32>,
+    cursor: vec2<f32>,
+    time: f32,
+    delta_time: f32,
+    frame: u32,
+};
@@ -0,0 +1,72 @@
+// x = cell size, y = fade distance, zw unused.
+struct Grid {
+    view_proj: mat4x4<f32>,
+    inv_view_proj: mat4x4<f32>,
+    eye: vec4<f32>,
+    params: vec4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> grid: Grid;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) near: vec3<f32>,
+    @location(1) far: vec3<f32>,
+};
+
+fn unproject(p: vec2<f32>, z: f32) -> vec3<f32> {
+    let world = grid.inv_view_proj * vec4<f32>(p, z, 1.0);
+    return world.xyz / world.w;
+}
+
+// Full screen quad, each pixel carries the world space ray through it.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, -1.0), vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0), vec2<f32>(1.0, 1.0), vec2<f32>(-1.0, 1.0),
+    );
+    let p = corners[index];
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(p, 0.0, 1.0);
+    out.near = unproject(p, 0.0);
+    out.far = unproject(p, 1.0);
+    return out;
+}
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+};
+
+// Intersects the pixel ray with the y = 0 plane and draws anti-aliased cell lines there.
+@fragment
+fn fs_main(in: VertexOutput) -> FragmentOutput {
+    let t = -in.near.y / (in.far.y - in.near.y);
+    let world = in.near + t * (in.far - in.near);
+    let clip = grid.view_proj * vec4<f32>(world, 1.0);
+
+    let coord = world.xz / grid.params.x;
+    let deriv = fwidth(coord);
+    let lines = abs(fract(coord - 0.5) - 0.5) / deriv;
+    let line = min(lines.x, lines.y);
+    // Derivatives need uniform control flow, so only discard once they are taken.
+    if (t <= 0.0) {
+        discard;
+    }
+
+    var color = vec3<f32>(0.5, 0.5, 0.5);
+    // The x axis runs along z == 0, the z axis along x == 0.
+    if (abs(world.z) < deriv.y * grid.params.x) {
+        color = vec3<f32>(0.9, 0.2, 0.2);
+    }
+    if (abs(world.x) < deriv.x * grid.params.x) {
+        color = vec3<f32>(0.25, 0.4, 0.95);
+    }
+    let fade = 1.0 - clamp(distance(world, grid.eye.xyz) / grid.params.y, 0.0, 1.0);
+
+    var out: FragmentOutput;
+    out.color = vec4<f32>(color, (1.0 - min(line, 1.0)) * fade);
+    out.depth = clip.z / clip.w;
+    return out;
+}
@@ -0,0 +1,47 @@
+// Static geometry lit only by its baked lightmap, prefixed with lightmap_common.wgsl.
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(0) @binding(1)
+var s_diffuse: sampler;
+
+struct Camera {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+}
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+@group(2) @binding(0)
+var t_lightmap: texture_2d<f32>;
+@group(2) @binding(1)
+var s_lightmap: sampler;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) lightmap_uv: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) lightmap_uv: vec2<f32>,
+};
+
+// Baked geometry is already in world space.
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(in.position, 1.0);
+    out.tex_coords = in.tex_coords;
+    out.lightmap_uv = in.lightmap_uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let albedo = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    let light = decode_lightmap(textureSample(t_lightmap, s_lightmap, in.lightmap_uv));
+    return vec4<f32>(albedo.rgb * light, albedo.a);
+}
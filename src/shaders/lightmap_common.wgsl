@@ -0,0 +1,15 @@
+// Lightmap helpers shared by lightmap.wgsl and 2D shaders sampling tile lightmaps, see
+// sys::lightmap.
+
+// Mirrors sys::lightmap::LIGHTMAP_RANGE.
+const LIGHTMAP_RANGE: f32 = 2.0;
+
+fn decode_lightmap(texel: vec4<f32>) -> vec3<f32> {
+    return texel.rgb * LIGHTMAP_RANGE;
+}
+
+// Mirrors sys::lightmap::tile_uv, `map_size` is the tile map's size in world units.
+fn lightmap_tile_uv(world: vec2<f32>, map_size: vec2<f32>) -> vec2<f32> {
+    let uv = world / map_size;
+    return vec2<f32>(uv.x, 1.0 - uv.y);
+}
@@ -0,0 +1,8 @@
+// See gfx::material_params::MaterialParamsUniform.
+struct MaterialParams {
+    tint: vec4<f32>,
+    // rgb color, w intensity.
+    emissive: vec4<f32>,
+    dissolve: f32,
+    custom: vec4<f32>,
+};
@@ -0,0 +1,57 @@
+// x = outline width in pixels, yzw unused.
+struct OutlineParams {
+    width: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var t_mask: texture_2d<f32>;
+@group(0) @binding(1)
+var<uniform> params: OutlineParams;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// Full screen triangle, no vertex buffers needed.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    return out;
+}
+
+// Fragment shader
+
+// Dilates the mask: pixels outside a masked object take the color of the
+// nearest covered pixel within the outline width.
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let dims = vec2<i32>(textureDimensions(t_mask));
+    let coord = vec2<i32>(in.clip_position.xy);
+
+    let center = textureLoad(t_mask, coord, 0);
+    if center.a > 0.0 {
+        discard;
+    }
+
+    let radius = i32(params.width.x);
+    var result = vec4<f32>(0.0);
+    for (var y = -radius; y <= radius; y += 1) {
+        for (var x = -radius; x <= radius; x += 1) {
+            if x * x + y * y > radius * radius {
+                continue;
+            }
+            let sample_coord = clamp(coord + vec2<i32>(x, y), vec2<i32>(0), dims - 1);
+            let mask = textureLoad(t_mask, sample_coord, 0);
+            if mask.a > result.a {
+                result = mask;
+            }
+        }
+    }
+
+    if result.a == 0.0 {
+        discard;
+    }
+    return result;
+}
@@ -0,0 +1,72 @@
+// Screen space quads of overlay widgets like the minimap and the tweak panel, see
+// gfx::overlay. Positions are in pixels with the origin at the bottom left of the window.
+// Materials differ only in the texture bound at group 1, solid quads sample a white one.
+// MSDF text materials also bind the font's distance range and draw with fs_msdf.
+
+struct CameraUniform {
+    view_pos: vec4<f32>,
+    view_proj: mat4x4<f32>,
+};
+
+struct Globals {
+    resolution: vec2<f32>,
+    cursor: vec2<f32>,
+    time: f32,
+    delta_time: f32,
+    frame: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+@group(0) @binding(1)
+var<uniform> globals: Globals;
+
+@group(1) @binding(0)
+var t_overlay: texture_2d<f32>;
+@group(1) @binding(1)
+var s_overlay: sampler;
+// x is the distance range of the font in atlas pixels, only bound for fs_msdf.
+@group(1) @binding(2)
+var<uniform> msdf_params: vec4<f32>;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    let ndc = in.position.xy / max(globals.resolution, vec2<f32>(1.0)) * 2.0 - 1.0;
+    out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+    out.tex_coords = in.tex_coords;
+    out.color = in.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_overlay, s_overlay, in.tex_coords) * in.color;
+}
+
+fn median(c: vec3<f32>) -> f32 {
+    return max(min(c.r, c.g), min(max(c.r, c.g), c.b));
+}
+
+@fragment
+fn fs_msdf(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = textureSample(t_overlay, s_overlay, in.tex_coords);
+    // Screen pixels the distance range spans, at least one so small text stays smooth.
+    let unit_range = vec2<f32>(msdf_params.x) / vec2<f32>(textureDimensions(t_overlay, 0));
+    let screen_range = max(0.5 * dot(unit_range, 1.0 / fwidth(in.tex_coords)), 1.0);
+    let distance = screen_range * (median(texel.rgb) - 0.5);
+    let coverage = clamp(distance + 0.5, 0.0, 1.0);
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
@@ -0,0 +1,46 @@
+// Renders shadow casters into one face of a point light's shadow cube. Stores the distance
+// to the light over its radius instead of the projected depth, so every face compares alike.
+
+struct PointShadowFace {
+    view_proj: mat4x4<f32>,
+    // Light position and radius.
+    light: vec4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> face: PointShadowFace;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+}
+
+struct InstanceInput {
+    @location(5) model_matrix0: vec4<f32>,
+    @location(6) model_matrix1: vec4<f32>,
+    @location(7) model_matrix2: vec4<f32>,
+    @location(8) model_matrix3: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+}
+
+@vertex
+fn vs_main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix0,
+        instance.model_matrix1,
+        instance.model_matrix2,
+        instance.model_matrix3,
+    );
+    let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+    var out: VertexOutput;
+    out.clip_position = face.view_proj * world_position;
+    out.world_position = world_position.xyz;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @builtin(frag_depth) f32 {
+    return clamp(length(in.world_position - face.light.xyz) / face.light.w, 0.0, 1.0);
+}
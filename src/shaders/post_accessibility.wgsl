@@ -0,0 +1,32 @@
+// Color vision filter and reduced flash effect, see gfx::post::accessibility.
+// limit_flash comes from post_accessibility_history.wgsl, or is a passthrough on devices
+// where fragment shaders can't write storage textures.
+
+struct AccessibilityParams {
+    // Rows of the color vision matrix, applied to linear RGB.
+    color: array<vec4<f32>, 3>,
+    // Largest increase in log2 luminance allowed this frame.
+    max_change: f32,
+    reduced_flash: u32,
+    // Set on the first frame, the history is written but not read.
+    reset: u32,
+    _pad: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> params: AccessibilityParams;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_input, s_input, in.uv);
+    var rgb = vec3<f32>(
+        dot(params.color[0].xyz, color.rgb),
+        dot(params.color[1].xyz, color.rgb),
+        dot(params.color[2].xyz, color.rgb),
+    );
+    rgb = max(rgb, vec3<f32>(0.0));
+    if params.reduced_flash != 0u {
+        rgb = limit_flash(rgb, vec2<u32>(in.clip_position.xy));
+    }
+    return vec4<f32>(rgb, color.a);
+}
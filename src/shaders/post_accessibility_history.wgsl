@@ -0,0 +1,22 @@
+// Per pixel luminance of the last frame for the reduced flash mode of the accessibility
+// effect. Read from one texture and written to the other, they swap every frame.
+
+@group(1) @binding(1)
+var t_history: texture_2d<f32>;
+@group(1) @binding(2)
+var history_out: texture_storage_2d<r32float, write>;
+
+// Dark pixels count as this bright, so a flash from black isn't held back for seconds.
+const FLASH_FLOOR: f32 = 0.03125;
+
+fn limit_flash(rgb: vec3<f32>, pixel: vec2<u32>) -> vec3<f32> {
+    let luminance = max(dot(rgb, vec3<f32>(0.2126, 0.7152, 0.0722)), 0.0001);
+    var limited = luminance;
+    if params.reset == 0u {
+        let previous = max(textureLoad(t_history, pixel, 0).r, FLASH_FLOOR);
+        // Only brightening is limited, going dark right away can't flash.
+        limited = min(luminance, exp2(log2(previous) + params.max_change));
+    }
+    textureStore(history_out, pixel, vec4<f32>(limited, 0.0, 0.0, 1.0));
+    return rgb * (limited / luminance);
+}
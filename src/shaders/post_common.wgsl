@@ -0,0 +1,37 @@
+// Shared interface for full screen post process effects.
+// Effect shaders are appended to this file and only need to provide fs_main.
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// Result of the previous pass (or the scene).
+@group(0) @binding(0)
+var t_input: texture_2d<f32>;
+@group(0) @binding(1)
+var s_input: sampler;
+
+// Full screen triangle, no vertex buffers needed.
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let cutoff = color <= vec3<f32>(0.0031308);
+    let lower = color * 12.92;
+    let higher = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(higher, lower, cutoff);
+}
+
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    let cutoff = color <= vec3<f32>(0.04045);
+    let lower = color / 12.92;
+    let higher = pow((color + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(higher, lower, cutoff);
+}
@@ -0,0 +1,14 @@
+// Standard interface for user post effects, appended after post_common.wgsl.
+// A custom effect provides fs_main and may declare its own uniform block at
+// @group(1) @binding(1), e.g. `@group(1) @binding(1) var<uniform> params: MyParams;`
+
+struct PostFrame {
+    // Size of the input texture in pixels.
+    resolution: vec2<f32>,
+    // Seconds since the effect was created.
+    time: f32,
+    delta_time: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> frame: PostFrame;
@@ -0,0 +1,154 @@
+// FXAA: finds luma edges, walks along them to their ends and resamples across the edge
+// where the pixel sits on it. Appended to post_common.wgsl.
+
+struct FxaaParams {
+    // x: subpixel blend, y: relative edge threshold, z: absolute edge threshold.
+    params: vec4<f32>,
+};
+@group(1) @binding(0)
+var<uniform> fxaa: FxaaParams;
+
+const FXAA_ITERATIONS: i32 = 12;
+
+fn fxaa_step(i: i32) -> f32 {
+    if i < 5 {
+        return 1.0;
+    } else if i == 5 {
+        return 1.5;
+    } else if i < 10 {
+        return 2.0;
+    } else if i == 10 {
+        return 4.0;
+    }
+    return 8.0;
+}
+
+// Perceptual luma, the input is linear so sqrt stands in for the gamma curve.
+fn luma(color: vec3<f32>) -> f32 {
+    return dot(sqrt(max(color, vec3<f32>(0.0))), vec3<f32>(0.299, 0.587, 0.114));
+}
+
+fn luma_at(uv: vec2<f32>) -> f32 {
+    return luma(textureSampleLevel(t_input, s_input, uv, 0.0).rgb);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_input));
+    let uv = in.uv;
+    let center = textureSampleLevel(t_input, s_input, uv, 0.0);
+
+    let luma_center = luma(center.rgb);
+    let luma_down = luma_at(uv + vec2<f32>(0.0, texel.y));
+    let luma_up = luma_at(uv - vec2<f32>(0.0, texel.y));
+    let luma_left = luma_at(uv - vec2<f32>(texel.x, 0.0));
+    let luma_right = luma_at(uv + vec2<f32>(texel.x, 0.0));
+
+    let luma_min = min(luma_center, min(min(luma_down, luma_up), min(luma_left, luma_right)));
+    let luma_max = max(luma_center, max(max(luma_down, luma_up), max(luma_left, luma_right)));
+    let luma_range = luma_max - luma_min;
+    if luma_range < max(fxaa.params.z, luma_max * fxaa.params.y) {
+        return center;
+    }
+
+    let luma_down_left = luma_at(uv + vec2<f32>(-texel.x, texel.y));
+    let luma_up_right = luma_at(uv + vec2<f32>(texel.x, -texel.y));
+    let luma_up_left = luma_at(uv - texel);
+    let luma_down_right = luma_at(uv + texel);
+
+    let luma_down_up = luma_down + luma_up;
+    let luma_left_right = luma_left + luma_right;
+    let luma_left_corners = luma_down_left + luma_up_left;
+    let luma_down_corners = luma_down_left + luma_down_right;
+    let luma_right_corners = luma_down_right + luma_up_right;
+    let luma_up_corners = luma_up_right + luma_up_left;
+
+    let edge_horizontal = abs(-2.0 * luma_left + luma_left_corners)
+        + abs(-2.0 * luma_center + luma_down_up) * 2.0
+        + abs(-2.0 * luma_right + luma_right_corners);
+    let edge_vertical = abs(-2.0 * luma_up + luma_up_corners)
+        + abs(-2.0 * luma_center + luma_left_right) * 2.0
+        + abs(-2.0 * luma_down + luma_down_corners);
+    let is_horizontal = edge_horizontal >= edge_vertical;
+
+    // Pick the side of the edge with the steepest gradient.
+    let luma1 = select(luma_left, luma_up, is_horizontal);
+    let luma2 = select(luma_right, luma_down, is_horizontal);
+    let gradient1 = luma1 - luma_center;
+    let gradient2 = luma2 - luma_center;
+    let is1_steepest = abs(gradient1) >= abs(gradient2);
+    let gradient_scaled = 0.25 * max(abs(gradient1), abs(gradient2));
+
+    var step_length = select(texel.x, texel.y, is_horizontal);
+    var luma_local_average = 0.5 * (luma2 + luma_center);
+    if is1_steepest {
+        step_length = -step_length;
+        luma_local_average = 0.5 * (luma1 + luma_center);
+    }
+
+    // Start half a texel onto the edge and walk both ways until the luma changes.
+    var current_uv = uv;
+    if is_horizontal {
+        current_uv.y += step_length * 0.5;
+    } else {
+        current_uv.x += step_length * 0.5;
+    }
+    let offset = select(vec2<f32>(0.0, texel.y), vec2<f32>(texel.x, 0.0), is_horizontal);
+    var uv1 = current_uv - offset;
+    var uv2 = current_uv + offset;
+    var luma_end1 = luma_at(uv1) - luma_local_average;
+    var luma_end2 = luma_at(uv2) - luma_local_average;
+    var reached1 = abs(luma_end1) >= gradient_scaled;
+    var reached2 = abs(luma_end2) >= gradient_scaled;
+    if !reached1 {
+        uv1 -= offset;
+    }
+    if !reached2 {
+        uv2 += offset;
+    }
+    for (var i = 2; i < FXAA_ITERATIONS; i++) {
+        if reached1 && reached2 {
+            break;
+        }
+        if !reached1 {
+            luma_end1 = luma_at(uv1) - luma_local_average;
+        }
+        if !reached2 {
+            luma_end2 = luma_at(uv2) - luma_local_average;
+        }
+        reached1 = abs(luma_end1) >= gradient_scaled;
+        reached2 = abs(luma_end2) >= gradient_scaled;
+        if !reached1 {
+            uv1 -= offset * fxaa_step(i);
+        }
+        if !reached2 {
+            uv2 += offset * fxaa_step(i);
+        }
+    }
+
+    let distance1 = select(uv.y - uv1.y, uv.x - uv1.x, is_horizontal);
+    let distance2 = select(uv2.y - uv.y, uv2.x - uv.x, is_horizontal);
+    let is_direction1 = distance1 < distance2;
+    let distance_final = min(distance1, distance2);
+    let edge_thickness = distance1 + distance2;
+    let pixel_offset = -distance_final / edge_thickness + 0.5;
+
+    // Only blend if the pixel is on the side of the edge the end points agree with.
+    let is_luma_center_smaller = luma_center < luma_local_average;
+    let correct_variation = (select(luma_end2, luma_end1, is_direction1) < 0.0) != is_luma_center_smaller;
+    var final_offset = select(0.0, pixel_offset, correct_variation);
+
+    // Subpixel aliasing, e.g. thin lines the edge walk can't catch.
+    let luma_average = (1.0 / 12.0) * (2.0 * (luma_down_up + luma_left_right) + luma_left_corners + luma_right_corners);
+    let subpixel1 = clamp(abs(luma_average - luma_center) / luma_range, 0.0, 1.0);
+    let subpixel2 = (-2.0 * subpixel1 + 3.0) * subpixel1 * subpixel1;
+    final_offset = max(final_offset, subpixel2 * subpixel2 * fxaa.params.x);
+
+    var final_uv = uv;
+    if is_horizontal {
+        final_uv.y += final_offset * step_length;
+    } else {
+        final_uv.x += final_offset * step_length;
+    }
+    return vec4<f32>(textureSampleLevel(t_input, s_input, final_uv, 0.0).rgb, center.a);
+}
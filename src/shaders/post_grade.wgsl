@@ -0,0 +1,51 @@
+// Color grading and palette swap effect.
+
+struct GradeParams {
+    // 0 = passthrough, 1 = lut, 2 = palette swap
+    mode: u32,
+    strength: f32,
+    lut_size: f32,
+    palette_len: u32,
+    tolerance: f32,
+    _pad0: f32,
+    _pad1: f32,
+    _pad2: f32,
+}
+
+@group(1) @binding(0)
+var<uniform> grade: GradeParams;
+@group(1) @binding(1)
+var t_lut: texture_3d<f32>;
+@group(1) @binding(2)
+var s_lut: sampler;
+// Row 0 holds the source colors, row 1 their replacements.
+@group(1) @binding(3)
+var t_palette: texture_2d<f32>;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_input, s_input, in.uv);
+    // LUTs and palettes are authored in sRGB.
+    let srgb = linear_to_srgb(clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0)));
+
+    // Sample at texel centers so the edges of the LUT are not blended with the border.
+    let lut_scale = (grade.lut_size - 1.0) / grade.lut_size;
+    let lut_offset = 0.5 / grade.lut_size;
+    let graded = textureSample(t_lut, s_lut, srgb * lut_scale + lut_offset).rgb;
+
+    var result = color.rgb;
+    if grade.mode == 1u {
+        result = mix(color.rgb, srgb_to_linear(graded), grade.strength);
+    } else if grade.mode == 2u {
+        for (var i = 0u; i < grade.palette_len; i += 1u) {
+            let source = textureLoad(t_palette, vec2<i32>(i32(i), 0), 0);
+            if distance(source.rgb, srgb) <= grade.tolerance {
+                let replacement = textureLoad(t_palette, vec2<i32>(i32(i), 1), 0);
+                result = srgb_to_linear(replacement.rgb);
+                break;
+            }
+        }
+    }
+
+    return vec4<f32>(result, color.a);
+}
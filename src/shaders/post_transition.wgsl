@@ -0,0 +1,66 @@
+// Full screen transitions, blends from a color or captured frame to the live scene.
+
+struct TransitionParams {
+    color: vec4<f32>,
+    // 0 = none, 1 = fade, 2 = wipe, 3 = dissolve
+    mode: u32,
+    // Wipe direction, 0 = left to right, 1 = right to left, 2 = top to bottom, 3 = bottom to top
+    direction: u32,
+    // 1 when blending from params.color instead of t_from
+    from_color: u32,
+    // How much of the scene is revealed, 0..1
+    progress: f32,
+    softness: f32,
+    _pad0: f32,
+    _pad1: f32,
+    _pad2: f32,
+}
+
+@group(1) @binding(0)
+var<uniform> transition: TransitionParams;
+@group(1) @binding(1)
+var t_from: texture_2d<f32>;
+@group(1) @binding(2)
+var s_from: sampler;
+@group(1) @binding(3)
+var t_mask: texture_2d<f32>;
+@group(1) @binding(4)
+var s_mask: sampler;
+
+fn wipe_coord(uv: vec2<f32>) -> f32 {
+    switch transition.direction {
+        case 1u: { return 1.0 - uv.x; }
+        case 2u: { return uv.y; }
+        case 3u: { return 1.0 - uv.y; }
+        default: { return uv.x; }
+    }
+}
+
+// Scene shows where coord is behind the moving edge.
+fn reveal(coord: f32) -> f32 {
+    let s = max(transition.softness, 0.0001);
+    let edge = transition.progress * (1.0 + s);
+    return 1.0 - smoothstep(edge - s, edge, coord);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let scene = textureSample(t_input, s_input, in.uv);
+    let from_frame = textureSample(t_from, s_from, in.uv);
+    let mask = textureSample(t_mask, s_mask, in.uv).r;
+
+    var base = from_frame;
+    if transition.from_color == 1u {
+        base = transition.color;
+    }
+
+    var t = 1.0;
+    switch transition.mode {
+        case 1u: { t = transition.progress; }
+        case 2u: { t = reveal(wipe_coord(in.uv)); }
+        case 3u: { t = reveal(mask); }
+        default: {}
+    }
+
+    return mix(base, scene, t);
+}
@@ -0,0 +1,29 @@
+// Depth only pass rendering shadow casters into one cascade of the shadow map.
+
+struct ShadowCaster {
+    view_proj: mat4x4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> caster: ShadowCaster;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+}
+
+struct InstanceInput {
+    @location(5) model_matrix0: vec4<f32>,
+    @location(6) model_matrix1: vec4<f32>,
+    @location(7) model_matrix2: vec4<f32>,
+    @location(8) model_matrix3: vec4<f32>,
+}
+
+@vertex
+fn vs_main(model: VertexInput, instance: InstanceInput) -> @builtin(position) vec4<f32> {
+    let model_matrix = mat4x4<f32>(
+        instance.model_matrix0,
+        instance.model_matrix1,
+        instance.model_matrix2,
+        instance.model_matrix3,
+    );
+    return caster.view_proj * model_matrix * vec4<f32>(model.position, 1.0);
+}
@@ -0,0 +1,88 @@
+// Forward shading with a directional light and cascaded shadows on top of the window light,
+// appended to basic.wgsl.
+
+// Mirrors gfx::shadow::ShadowUniform.
+struct Shadows {
+    cascades: array<mat4x4<f32>, 4>,
+    // View distance each cascade ends at.
+    splits: vec4<f32>,
+    // Third row of the camera's view matrix, dot with a world position for its view z.
+    view_row: vec4<f32>,
+    // Direction the light travels in and its intensity.
+    light_dir: vec4<f32>,
+    // Light color and the number of cascades.
+    color: vec4<f32>,
+    // One over the map resolution, normal offset, PCF radius in texels and the debug flag.
+    params: vec4<f32>,
+}
+@group(3) @binding(0) var<uniform> shadows: Shadows;
+@group(3) @binding(1) var t_shadow: texture_depth_2d_array;
+@group(3) @binding(2) var s_shadow: sampler_comparison;
+
+// First cascade reaching the fragment, the cascade count past the last one.
+fn shadow_cascade(world_position: vec3<f32>) -> u32 {
+    let depth = -dot(shadows.view_row, vec4<f32>(world_position, 1.0));
+    let count = u32(shadows.color.w);
+    for (var i = 0u; i < count; i++) {
+        if (depth < shadows.splits[i]) {
+            return i;
+        }
+    }
+    return count;
+}
+
+// Fraction of the light reaching the fragment, filtered over a (2r + 1)^2 texel kernel.
+fn shadow_factor(cascade: u32, world_position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    if (cascade >= u32(shadows.color.w)) {
+        return 1.0;
+    }
+    let offset_position = world_position + normal * shadows.params.y;
+    let clip = shadows.cascades[cascade] * vec4<f32>(offset_position, 1.0);
+    let ndc = clip.xyz / clip.w;
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || ndc.z > 1.0) {
+        return 1.0;
+    }
+    let radius = i32(shadows.params.z);
+    var lit = 0.0;
+    var taps = 0.0;
+    for (var y = -radius; y <= radius; y++) {
+        for (var x = -radius; x <= radius; x++) {
+            let tap = uv + vec2<f32>(f32(x), f32(y)) * shadows.params.x;
+            lit += textureSampleCompareLevel(t_shadow, s_shadow, tap, cascade, ndc.z);
+            taps += 1.0;
+        }
+    }
+    return lit / taps;
+}
+
+fn cascade_tint(cascade: u32) -> vec3<f32> {
+    switch cascade {
+        case 0u: { return vec3<f32>(1.0, 0.3, 0.3); }
+        case 1u: { return vec3<f32>(0.3, 1.0, 0.3); }
+        case 2u: { return vec3<f32>(0.3, 0.3, 1.0); }
+        case 3u: { return vec3<f32>(1.0, 1.0, 0.3); }
+        default: { return vec3<f32>(1.0); }
+    }
+}
+
+@fragment
+fn fs_shadowed(in: VertexOutput) -> @location(0) vec4<f32> {
+  let object_color: vec4<f32> = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+  let normal = normalize(in.world_normal);
+  let cascade = shadow_cascade(in.world_position);
+  let shadow = shadow_factor(cascade, in.world_position, normal);
+
+  let light_dir = -normalize(shadows.light_dir.xyz);
+  let view_dir = normalize(camera.view_pos.xyz - in.world_position);
+  let half_dir = normalize(view_dir + light_dir);
+  let diffuse = max(dot(normal, light_dir), 0.0);
+  let specular = select(0.0, pow(max(dot(normal, half_dir), 0.0), 32.0), diffuse > 0.0);
+  let sun = shadows.color.rgb * shadows.light_dir.w * (diffuse + specular) * shadow;
+
+  var result = shade(in, object_color) + sun * object_color.xyz;
+  if (shadows.params.w > 0.5) {
+    result *= cascade_tint(cascade);
+  }
+  return vec4<f32>(apply_fog(result, in.world_position), object_color.a);
+}
@@ -0,0 +1,35 @@
+// Rain drops running down the screen, a custom post effect, see gfx::weather. Each cell of
+// a grid may hold one drop that slides down and shrinks, refracting the frame behind it.
+
+struct DropletParams {
+    // 0 is dry, 1 puts a drop in every cell.
+    strength: f32,
+    // Drop lifetimes per second.
+    speed: f32,
+    // Grid cells across the screen height.
+    scale: f32,
+    _pad: f32,
+};
+@group(1) @binding(1)
+var<uniform> params: DropletParams;
+
+fn droplet_hash(p: vec2<f32>) -> vec2<f32> {
+    let q = vec2<f32>(dot(p, vec2<f32>(127.1, 311.7)), dot(p, vec2<f32>(269.5, 183.3)));
+    return fract(sin(q) * 43758.5453);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let aspect = frame.resolution.x / max(frame.resolution.y, 1.0);
+    let grid = in.uv * vec2<f32>(aspect, 1.0) * params.scale;
+    let cell = floor(grid);
+    let h = droplet_hash(cell);
+    let life = fract(frame.time * params.speed * (0.5 + h.x) + h.y);
+    let center = cell + vec2<f32>(0.25 + 0.5 * h.x, 0.2 + 0.6 * life);
+    let offset = grid - center;
+    let radius = 0.3 * (1.0 - life);
+    let present = step(1.0 - params.strength, fract(h.x * 7.31 + h.y * 3.17));
+    let mask = smoothstep(radius, radius * 0.5, length(offset)) * present;
+    let refract = offset / (vec2<f32>(aspect, 1.0) * params.scale) * mask * 0.8;
+    return textureSample(t_input, s_input, in.uv - refract);
+}
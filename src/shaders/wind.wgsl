@@ -0,0 +1,23 @@
+// Wind for vegetation sway, see gfx::weather::WindUniform. Bind the weather's wind buffer
+// as var<uniform> wind: Wind and offset vertices with wind_sway.
+
+struct Wind {
+    // Normalized direction on the XZ plane.
+    direction: vec2<f32>,
+    speed: f32,
+    // Seconds since the weather was created.
+    time: f32,
+    gust: f32,
+    gust_frequency: f32,
+    _pad: vec2<f32>,
+};
+
+// World space offset for a vertex `height` meters above its plant's root. Stiffer near the
+// root and phase shifted by position so neighbours don't move in lockstep.
+fn wind_sway(wind: Wind, world_position: vec3<f32>, height: f32) -> vec3<f32> {
+    let phase = dot(world_position.xz, vec2<f32>(0.37, 0.21));
+    let gust = wind.gust * sin((wind.time * wind.gust_frequency + phase) * 6.2831853);
+    let flutter = sin(wind.time * 2.3 + phase * 4.0) * 0.1;
+    let bend = (wind.speed * 0.05 + gust * 0.05 + flutter) * height * height;
+    return vec3<f32>(wind.direction.x, 0.0, wind.direction.y) * bend;
+}
@@ -0,0 +1,457 @@
+use std::borrow::Cow;
+
+use anyhow::{bail, ensure};
+use image::{imageops::FilterType, DynamicImage};
+
+use crate::gfx::{font::BmFont, wgpu::vertex::Vertex3D};
+
+use super::{
+    bc,
+    pack::{align_up, write_string, ByteReader},
+};
+
+pub const TEXTURE_MAGIC: [u8; 4] = *b"RTEX";
+/// Version 2 added TextureEncoding, version 1 textures are read as RGBA8.
+pub const TEXTURE_VERSION: u32 = 2;
+pub const MESH_MAGIC: [u8; 4] = *b"RMSH";
+pub const MESH_VERSION: u32 = 1;
+pub const FONT_MAGIC: [u8; 4] = *b"RFNT";
+pub const FONT_VERSION: u32 = 1;
+
+/// How the mips of a BakedTexture are stored.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextureEncoding {
+    #[default]
+    Rgba8 = 0,
+    /// BC1 blocks, 8 bytes per 4x4 pixels, for opaque textures.
+    Bc1 = 1,
+    /// BC3 blocks, 16 bytes per 4x4 pixels, BC1 color plus interpolated alpha.
+    Bc3 = 2,
+}
+
+impl TextureEncoding {
+    fn from_u32(v: u32) -> anyhow::Result<Self> {
+        Ok(match v {
+            0 => Self::Rgba8,
+            1 => Self::Bc1,
+            2 => Self::Bc3,
+            _ => bail!(
+                "TextureEncoding::from_u32 => Unknown texture encoding {}",
+                v
+            ),
+        })
+    }
+
+    #[inline]
+    pub fn is_compressed(self) -> bool {
+        self != Self::Rgba8
+    }
+
+    /// Pixels along each side of a block, 1 for RGBA8.
+    #[inline]
+    pub fn block_size(self) -> u32 {
+        match self {
+            Self::Rgba8 => 1,
+            Self::Bc1 | Self::Bc3 => bc::BLOCK_SIZE,
+        }
+    }
+
+    #[inline]
+    pub fn block_bytes(self) -> u32 {
+        match self {
+            Self::Rgba8 => 4,
+            Self::Bc1 => bc::BC1_BLOCK_BYTES as u32,
+            Self::Bc3 => bc::BC3_BLOCK_BYTES as u32,
+        }
+    }
+
+    /// Bytes in one row of blocks of a `width` pixel wide mip.
+    #[inline]
+    pub fn row_bytes(self, width: u32) -> u32 {
+        width.div_ceil(self.block_size()) * self.block_bytes()
+    }
+
+    /// Rows of blocks in a `height` pixel high mip.
+    #[inline]
+    pub fn rows(self, height: u32) -> u32 {
+        height.div_ceil(self.block_size())
+    }
+
+    pub fn mip_bytes(self, width: u32, height: u32) -> usize {
+        self.row_bytes(width) as usize * self.rows(height) as usize
+    }
+}
+
+/// Image with its full mip chain generated at import time, level 0 first. The importer
+/// block compresses textures (see compress), loaders fall back to decompress on devices
+/// without BC support.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BakedTexture {
+    pub width: u32,
+    pub height: u32,
+    pub encoding: TextureEncoding,
+    pub mips: Vec<Vec<u8>>,
+}
+
+impl BakedTexture {
+    pub fn from_image(img: &DynamicImage) -> Self {
+        let mut level = img.to_rgba8();
+        let (width, height) = level.dimensions();
+        let mut mips = Vec::new();
+        loop {
+            let (w, h) = level.dimensions();
+            let next = (w > 1 || h > 1).then(|| {
+                image::imageops::resize(
+                    &level,
+                    (w / 2).max(1),
+                    (h / 2).max(1),
+                    FilterType::Triangle,
+                )
+            });
+            mips.push(level.into_raw());
+            match next {
+                Some(next) => level = next,
+                None => break,
+            }
+        }
+        Self {
+            width,
+            height,
+            encoding: TextureEncoding::Rgba8,
+            mips,
+        }
+    }
+
+    /// BC1 when every pixel is opaque and BC3 otherwise. None if the texture is already
+    /// compressed or its size isn't a multiple of the 4x4 blocks GPUs require.
+    pub fn compress(&self) -> Option<Self> {
+        if self.encoding.is_compressed()
+            || !self.width.is_multiple_of(bc::BLOCK_SIZE)
+            || !self.height.is_multiple_of(bc::BLOCK_SIZE)
+        {
+            return None;
+        }
+        let opaque = self
+            .mips
+            .iter()
+            .all(|mip| mip.chunks_exact(4).all(|p| p[3] == 255));
+        let mips = (0..self.mips.len() as u32)
+            .map(|level| {
+                let (w, h) = self.mip_size(level);
+                let mip = &self.mips[level as usize];
+                if opaque {
+                    bc::encode_bc1(mip, w, h)
+                } else {
+                    bc::encode_bc3(mip, w, h)
+                }
+            })
+            .collect();
+        Some(Self {
+            encoding: if opaque {
+                TextureEncoding::Bc1
+            } else {
+                TextureEncoding::Bc3
+            },
+            mips,
+            ..*self
+        })
+    }
+
+    /// RGBA8 copy of the texture, for devices without BC support.
+    pub fn decompress(&self) -> Self {
+        let decode: fn(&[u8], u32, u32) -> Vec<u8> = match self.encoding {
+            TextureEncoding::Rgba8 => return self.clone(),
+            TextureEncoding::Bc1 => bc::decode_bc1,
+            TextureEncoding::Bc3 => bc::decode_bc3,
+        };
+        let mips = (0..self.mips.len() as u32)
+            .map(|level| {
+                let (w, h) = self.mip_size(level);
+                decode(&self.mips[level as usize], w, h)
+            })
+            .collect();
+        Self {
+            encoding: TextureEncoding::Rgba8,
+            mips,
+            ..*self
+        }
+    }
+
+    /// Size of mip `level` in pixels.
+    pub fn mip_size(&self, level: u32) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&TEXTURE_MAGIC);
+        out.extend_from_slice(&TEXTURE_VERSION.to_le_bytes());
+        out.extend_from_slice(&self.width.to_le_bytes());
+        out.extend_from_slice(&self.height.to_le_bytes());
+        out.extend_from_slice(&(self.encoding as u32).to_le_bytes());
+        out.extend_from_slice(&(self.mips.len() as u32).to_le_bytes());
+        for mip in self.mips.iter() {
+            out.extend_from_slice(&(mip.len() as u32).to_le_bytes());
+            out.extend_from_slice(mip);
+        }
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> anyhow::Result<Self> {
+        let mut r = ByteReader::new(data);
+        ensure!(
+            r.bytes(4)? == TEXTURE_MAGIC,
+            "BakedTexture::from_bytes => Not a baked texture"
+        );
+        let version = r.u32()?;
+        ensure!(
+            (1..=TEXTURE_VERSION).contains(&version),
+            "BakedTexture::from_bytes => Unsupported version {}",
+            version
+        );
+        let width = r.u32()?;
+        let height = r.u32()?;
+        let encoding = if version >= 2 {
+            TextureEncoding::from_u32(r.u32()?)?
+        } else {
+            TextureEncoding::Rgba8
+        };
+        let count = r.u32()?;
+        let mut s = Self {
+            width,
+            height,
+            encoding,
+            mips: Vec::with_capacity(count as usize),
+        };
+        for level in 0..count {
+            let len = r.u32()? as usize;
+            let (w, h) = s.mip_size(level);
+            ensure!(
+                len == encoding.mip_bytes(w, h),
+                "BakedTexture::from_bytes => Mip {} has the wrong size",
+                level
+            );
+            s.mips.push(r.bytes(len)?.to_vec());
+        }
+        ensure!(
+            !s.mips.is_empty(),
+            "BakedTexture::from_bytes => Texture has no mips"
+        );
+        Ok(s)
+    }
+}
+
+/// Texture names are pack entry names.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BakedMaterial {
+    pub name: String,
+    pub diffuse_texture: String,
+    pub normal_texture: String,
+}
+
+/// Mesh with tangents already computed, ready to be copied into GPU buffers.
+#[derive(Debug, Clone)]
+pub struct BakedMesh {
+    pub name: String,
+    pub material: u32,
+    pub vertices: Vec<Vertex3D>,
+    pub indices: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BakedModel {
+    pub meshes: Vec<BakedMesh>,
+    pub materials: Vec<BakedMaterial>,
+}
+
+impl BakedModel {
+    /// Vertex and index blobs are aligned to 16 bytes from the start of the output.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&MESH_MAGIC);
+        out.extend_from_slice(&MESH_VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.materials.len() as u32).to_le_bytes());
+        for mat in self.materials.iter() {
+            write_string(&mut out, &mat.name);
+            write_string(&mut out, &mat.diffuse_texture);
+            write_string(&mut out, &mat.normal_texture);
+        }
+        out.extend_from_slice(&(self.meshes.len() as u32).to_le_bytes());
+        for mesh in self.meshes.iter() {
+            write_string(&mut out, &mesh.name);
+            out.extend_from_slice(&mesh.material.to_le_bytes());
+            out.extend_from_slice(&(mesh.vertices.len() as u32).to_le_bytes());
+            out.extend_from_slice(&(mesh.indices.len() as u32).to_le_bytes());
+            out.resize(align_up(out.len(), 16), 0);
+            out.extend_from_slice(bytemuck::cast_slice(&mesh.vertices));
+            out.resize(align_up(out.len(), 16), 0);
+            out.extend_from_slice(bytemuck::cast_slice(&mesh.indices));
+        }
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> anyhow::Result<Self> {
+        Ok(BakedModelView::parse(data)?.into_owned())
+    }
+
+    /// Borrows the model as a view, e.g. for fs::upload_baked_meshes.
+    pub fn view(&self) -> BakedModelView<'_> {
+        BakedModelView {
+            meshes: self
+                .meshes
+                .iter()
+                .map(|m| BakedMeshView {
+                    name: m.name.clone(),
+                    material: m.material,
+                    vertices: Cow::Borrowed(&m.vertices),
+                    indices: Cow::Borrowed(&m.indices),
+                })
+                .collect(),
+            materials: self.materials.clone(),
+        }
+    }
+
+    pub fn write(&self, path: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+}
+
+/// Mesh borrowed from baked bytes. Vertex and index data point straight into the source
+/// when it is suitably aligned (see AlignedBytes) and are copied otherwise.
+#[derive(Debug, Clone)]
+pub struct BakedMeshView<'a> {
+    pub name: String,
+    pub material: u32,
+    pub vertices: Cow<'a, [Vertex3D]>,
+    pub indices: Cow<'a, [u32]>,
+}
+
+impl BakedMeshView<'_> {
+    /// True when neither vertices nor indices had to be copied.
+    pub fn is_zero_copy(&self) -> bool {
+        matches!(
+            (&self.vertices, &self.indices),
+            (Cow::Borrowed(_), Cow::Borrowed(_))
+        )
+    }
+
+    pub fn into_owned(self) -> BakedMesh {
+        BakedMesh {
+            name: self.name,
+            material: self.material,
+            vertices: self.vertices.into_owned(),
+            indices: self.indices.into_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BakedModelView<'a> {
+    pub meshes: Vec<BakedMeshView<'a>>,
+    pub materials: Vec<BakedMaterial>,
+}
+
+impl<'a> BakedModelView<'a> {
+    /// Parses the header and material table, checking the format version.
+    pub fn parse(data: &'a [u8]) -> anyhow::Result<Self> {
+        let mut r = ByteReader::new(data);
+        ensure!(
+            r.bytes(4)? == MESH_MAGIC,
+            "BakedModelView::parse => Not a baked mesh"
+        );
+        let version = r.u32()?;
+        ensure!(
+            version == MESH_VERSION,
+            "BakedModelView::parse => Unsupported version {}, expected {}",
+            version,
+            MESH_VERSION
+        );
+
+        let mut model = Self::default();
+        for _ in 0..r.u32()? {
+            model.materials.push(BakedMaterial {
+                name: r.string()?,
+                diffuse_texture: r.string()?,
+                normal_texture: r.string()?,
+            });
+        }
+        for _ in 0..r.u32()? {
+            let name = r.string()?;
+            let material = r.u32()?;
+            let vertex_count = r.u32()? as usize;
+            let index_count = r.u32()? as usize;
+            r.bytes(align_up(r.pos(), 16) - r.pos())?;
+            let vertices = r.bytes(vertex_count * std::mem::size_of::<Vertex3D>())?;
+            r.bytes(align_up(r.pos(), 16) - r.pos())?;
+            let indices = r.bytes(index_count * 4)?;
+            model.meshes.push(BakedMeshView {
+                name,
+                material,
+                vertices: cast_or_copy(vertices),
+                indices: cast_or_copy(indices),
+            });
+        }
+        Ok(model)
+    }
+
+    pub fn into_owned(self) -> BakedModel {
+        BakedModel {
+            meshes: self
+                .meshes
+                .into_iter()
+                .map(BakedMeshView::into_owned)
+                .collect(),
+            materials: self.materials,
+        }
+    }
+}
+
+fn cast_or_copy<T: bytemuck::Pod>(bytes: &[u8]) -> Cow<'_, [T]> {
+    match bytemuck::try_cast_slice(bytes) {
+        Ok(slice) => Cow::Borrowed(slice),
+        Err(_) => Cow::Owned(bytemuck::pod_collect_to_vec(bytes)),
+    }
+}
+
+/// Font whose atlas pages are multi-channel signed distance fields, see sys::msdf. Page
+/// names are pack entry names of the atlas textures.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BakedFont {
+    pub font: BmFont,
+    /// Width of the band around glyph edges the distance field covers, in atlas pixels.
+    pub distance_range: f32,
+}
+
+impl BakedFont {
+    /// The BmFont is stored in the text .fnt format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&FONT_MAGIC);
+        out.extend_from_slice(&FONT_VERSION.to_le_bytes());
+        out.extend_from_slice(&self.distance_range.to_le_bytes());
+        write_string(&mut out, &self.font.to_text());
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> anyhow::Result<Self> {
+        let mut r = ByteReader::new(data);
+        ensure!(
+            r.bytes(4)? == FONT_MAGIC,
+            "BakedFont::from_bytes => Not a baked font"
+        );
+        let version = r.u32()?;
+        ensure!(
+            version == FONT_VERSION,
+            "BakedFont::from_bytes => Unsupported version {}",
+            version
+        );
+        let distance_range = f32::from_bits(r.u32()?);
+        let font = BmFont::parse(&r.string()?)?;
+        Ok(Self {
+            font,
+            distance_range,
+        })
+    }
+}
@@ -0,0 +1,247 @@
+/// Pixels along each side of a compressed block.
+pub const BLOCK_SIZE: u32 = 4;
+pub const BC1_BLOCK_BYTES: usize = 8;
+pub const BC3_BLOCK_BYTES: usize = 16;
+
+type Block = [[u8; 4]; 16];
+
+/// Blocks needed to cover `size` pixels.
+#[inline]
+pub fn block_count(size: u32) -> u32 {
+    size.div_ceil(BLOCK_SIZE)
+}
+
+/// Compresses RGBA8 pixels to BC1 blocks, row by row. Alpha is dropped, so only use it for
+/// opaque images. Blocks past the right and bottom edge repeat the last column and row.
+pub fn encode_bc1(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    encode_blocks(rgba, width, height, BC1_BLOCK_BYTES, encode_color)
+}
+
+/// Compresses RGBA8 pixels to BC3 blocks, BC1 color plus 8 bit interpolated alpha.
+pub fn encode_bc3(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    encode_blocks(rgba, width, height, BC3_BLOCK_BYTES, |block, out| {
+        encode_alpha(block, &mut out[..8]);
+        encode_color(block, &mut out[8..]);
+    })
+}
+
+/// RGBA8 pixels of `width` x `height` BC1 blocks, the inverse of encode_bc1.
+pub fn decode_bc1(blocks: &[u8], width: u32, height: u32) -> Vec<u8> {
+    decode_blocks(blocks, width, height, BC1_BLOCK_BYTES, |data| {
+        decode_color(data, true)
+    })
+}
+
+pub fn decode_bc3(blocks: &[u8], width: u32, height: u32) -> Vec<u8> {
+    decode_blocks(blocks, width, height, BC3_BLOCK_BYTES, |data| {
+        let mut block = decode_color(&data[8..], false);
+        for (texel, alpha) in block.iter_mut().zip(decode_alpha(&data[..8])) {
+            texel[3] = alpha;
+        }
+        block
+    })
+}
+
+fn encode_blocks(
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    block_bytes: usize,
+    encode: impl Fn(&Block, &mut [u8]),
+) -> Vec<u8> {
+    let (blocks_x, blocks_y) = (block_count(width), block_count(height));
+    let mut out = vec![0; (blocks_x * blocks_y) as usize * block_bytes];
+    if width == 0 || height == 0 {
+        return out;
+    }
+    for (i, out) in out.chunks_exact_mut(block_bytes).enumerate() {
+        let (bx, by) = (i as u32 % blocks_x, i as u32 / blocks_x);
+        let mut block = [[0; 4]; 16];
+        for (j, texel) in block.iter_mut().enumerate() {
+            let x = (bx * BLOCK_SIZE + j as u32 % 4).min(width - 1);
+            let y = (by * BLOCK_SIZE + j as u32 / 4).min(height - 1);
+            let p = ((y * width + x) * 4) as usize;
+            texel.copy_from_slice(&rgba[p..p + 4]);
+        }
+        encode(&block, out);
+    }
+    out
+}
+
+fn decode_blocks(
+    blocks: &[u8],
+    width: u32,
+    height: u32,
+    block_bytes: usize,
+    decode: impl Fn(&[u8]) -> Block,
+) -> Vec<u8> {
+    let blocks_x = block_count(width);
+    let mut rgba = vec![0; (width * height * 4) as usize];
+    for (i, data) in blocks.chunks_exact(block_bytes).enumerate() {
+        let (bx, by) = (i as u32 % blocks_x, i as u32 / blocks_x);
+        for (j, texel) in decode(data).iter().enumerate() {
+            let x = bx * BLOCK_SIZE + j as u32 % 4;
+            let y = by * BLOCK_SIZE + j as u32 / 4;
+            if x < width && y < height {
+                let p = ((y * width + x) * 4) as usize;
+                rgba[p..p + 4].copy_from_slice(texel);
+            }
+        }
+    }
+    rgba
+}
+
+/// Endpoints along the block's principal axis, indices to the nearest of the four
+/// palette colors. Always uses the opaque four color mode.
+fn encode_color(block: &Block, out: &mut [u8]) {
+    let colors = block.map(|p| [p[0] as f32, p[1] as f32, p[2] as f32]);
+    let mut mean = [0.0; 3];
+    for c in colors.iter() {
+        for k in 0..3 {
+            mean[k] += c[k] / 16.0;
+        }
+    }
+    let mut cov = [[0.0f32; 3]; 3];
+    for c in colors.iter() {
+        let d = [c[0] - mean[0], c[1] - mean[1], c[2] - mean[2]];
+        for (row, dr) in cov.iter_mut().zip(d) {
+            for (v, dc) in row.iter_mut().zip(d) {
+                *v += dr * dc;
+            }
+        }
+    }
+    // Power iteration from the row of the channel that varies most, a few steps are
+    // plenty for picking endpoints. Starting from gray could be orthogonal to the axis.
+    let widest = (0..3).max_by(|&a, &b| cov[a][a].total_cmp(&cov[b][b])).unwrap_or(0);
+    let mut axis = if cov[widest][widest] > 0.0 {
+        cov[widest]
+    } else {
+        [1.0; 3]
+    };
+    for _ in 0..8 {
+        let next = cov.map(|row| row[0] * axis[0] + row[1] * axis[1] + row[2] * axis[2]);
+        let len = next.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if len < 1e-6 {
+            break;
+        }
+        axis = next.map(|v| v / len);
+    }
+    let project = |c: &[f32; 3]| (0..3).map(|k| (c[k] - mean[k]) * axis[k]).sum::<f32>();
+    let (lo, hi) = colors
+        .iter()
+        .map(project)
+        .fold((f32::MAX, f32::MIN), |(lo, hi), t| (lo.min(t), hi.max(t)));
+    let endpoint = |t: f32| to_565([0, 1, 2].map(|k| mean[k] + axis[k] * t));
+
+    let (mut c0, mut c1) = (endpoint(hi), endpoint(lo));
+    if c0 < c1 {
+        std::mem::swap(&mut c0, &mut c1);
+    }
+    out[0..2].copy_from_slice(&c0.to_le_bytes());
+    out[2..4].copy_from_slice(&c1.to_le_bytes());
+    if c0 == c1 {
+        out[4..8].fill(0);
+        return;
+    }
+    let palette = color_palette(c0, c1, false);
+    let mut indices = 0u32;
+    for (i, texel) in block.iter().enumerate() {
+        let nearest = nearest(&palette, |p| {
+            (0..3).map(|k| (p[k] as i32 - texel[k] as i32).pow(2)).sum()
+        });
+        indices |= (nearest as u32) << (2 * i);
+    }
+    out[4..8].copy_from_slice(&indices.to_le_bytes());
+}
+
+/// Alpha endpoints at the block's min and max, in the eight value mode.
+fn encode_alpha(block: &Block, out: &mut [u8]) {
+    let (lo, hi) = block.iter().fold((u8::MAX, u8::MIN), |(lo, hi), p| {
+        (lo.min(p[3]), hi.max(p[3]))
+    });
+    out[0] = hi;
+    out[1] = lo;
+    out[2..8].fill(0);
+    if hi == lo {
+        return;
+    }
+    let palette = alpha_palette(hi, lo);
+    let mut indices = 0u64;
+    for (i, texel) in block.iter().enumerate() {
+        let nearest = nearest(&palette, |&a| (a as i32 - texel[3] as i32).abs());
+        indices |= (nearest as u64) << (3 * i);
+    }
+    out[2..8].copy_from_slice(&indices.to_le_bytes()[..6]);
+}
+
+fn decode_color(data: &[u8], bc1: bool) -> Block {
+    let c0 = u16::from_le_bytes([data[0], data[1]]);
+    let c1 = u16::from_le_bytes([data[2], data[3]]);
+    let palette = color_palette(c0, c1, bc1);
+    let indices = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+    std::array::from_fn(|i| palette[(indices >> (2 * i) & 3) as usize])
+}
+
+fn decode_alpha(data: &[u8]) -> [u8; 16] {
+    let palette = alpha_palette(data[0], data[1]);
+    let mut bytes = [0; 8];
+    bytes[..6].copy_from_slice(&data[2..8]);
+    let indices = u64::from_le_bytes(bytes);
+    std::array::from_fn(|i| palette[(indices >> (3 * i) & 7) as usize])
+}
+
+/// BC1 blocks with c0 <= c1 use three colors and transparent black, BC3 always four.
+fn color_palette(c0: u16, c1: u16, bc1: bool) -> [[u8; 4]; 4] {
+    let (a, b) = (from_565(c0), from_565(c1));
+    let mix = |wa: u32, wb: u32| {
+        let total = wa + wb;
+        [0, 1, 2].map(|k| ((a[k] as u32 * wa + b[k] as u32 * wb) / total) as u8)
+    };
+    let rgba = |c: [u8; 3]| [c[0], c[1], c[2], 255];
+    if c0 > c1 || !bc1 {
+        [rgba(a), rgba(b), rgba(mix(2, 1)), rgba(mix(1, 2))]
+    } else {
+        [rgba(a), rgba(b), rgba(mix(1, 1)), [0; 4]]
+    }
+}
+
+fn alpha_palette(a0: u8, a1: u8) -> [u8; 8] {
+    let (a, b) = (a0 as u32, a1 as u32);
+    if a0 > a1 {
+        std::array::from_fn(|i| match i {
+            0 => a0,
+            1 => a1,
+            _ => (((8 - i as u32) * a + (i as u32 - 1) * b) / 7) as u8,
+        })
+    } else {
+        std::array::from_fn(|i| match i {
+            0 => a0,
+            1 => a1,
+            6 => 0,
+            7 => 255,
+            _ => (((6 - i as u32) * a + (i as u32 - 1) * b) / 5) as u8,
+        })
+    }
+}
+
+fn nearest<T>(palette: &[T], distance: impl Fn(&T) -> i32) -> usize {
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, p)| distance(p))
+        .map_or(0, |(i, _)| i)
+}
+
+fn to_565(c: [f32; 3]) -> u16 {
+    let q = |v: f32, max: f32| (v.clamp(0.0, 255.0) * max / 255.0).round() as u16;
+    (q(c[0], 31.0) << 11) | (q(c[1], 63.0) << 5) | q(c[2], 31.0)
+}
+
+fn from_565(c: u16) -> [u8; 3] {
+    let (r, g, b) = ((c >> 11) as u8, (c >> 5 & 63) as u8, (c & 31) as u8);
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+    ]
+}
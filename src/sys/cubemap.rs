@@ -0,0 +1,143 @@
+use anyhow::{bail, ensure};
+
+use super::pack::ByteReader;
+
+pub const CUBEMAP_MAGIC: [u8; 4] = *b"RCUB";
+pub const CUBEMAP_VERSION: u32 = 1;
+
+/// How cubemap faces are stored on disk, faces are always RGBA8 once loaded.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CubemapEncoding {
+    #[default]
+    Rgba8 = 0,
+    /// 16 bits per texel without alpha, half the size for a little banding.
+    Rgb565 = 1,
+}
+
+impl CubemapEncoding {
+    fn from_u8(v: u8) -> anyhow::Result<Self> {
+        Ok(match v {
+            0 => Self::Rgba8,
+            1 => Self::Rgb565,
+            _ => bail!("CubemapEncoding::from_u8 => Unknown encoding {}", v),
+        })
+    }
+}
+
+/// Six square RGBA8 faces in wgpu layer order (+X, -X, +Y, -Y, +Z, -Z), rows top first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BakedCubemap {
+    pub size: u32,
+    pub faces: [Vec<u8>; 6],
+}
+
+impl BakedCubemap {
+    /// Every face filled with one color.
+    pub fn solid(size: u32, rgba: [u8; 4]) -> Self {
+        let face = rgba.repeat((size * size) as usize);
+        Self {
+            size,
+            faces: std::array::from_fn(|_| face.clone()),
+        }
+    }
+
+    /// Nearest texel seen looking along `dir`, the same lookup the GPU does.
+    pub fn sample(&self, dir: [f32; 3]) -> [u8; 4] {
+        let (face, [u, v]) = face_uv(dir);
+        let texel = |t: f32| ((t * self.size as f32) as u32).min(self.size.saturating_sub(1));
+        let i = ((texel(v) * self.size + texel(u)) * 4) as usize;
+        let mut rgba = [0; 4];
+        rgba.copy_from_slice(&self.faces[face][i..i + 4]);
+        rgba
+    }
+
+    pub fn to_bytes(&self, encoding: CubemapEncoding) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&CUBEMAP_MAGIC);
+        out.extend_from_slice(&CUBEMAP_VERSION.to_le_bytes());
+        out.extend_from_slice(&self.size.to_le_bytes());
+        out.push(encoding as u8);
+        for face in self.faces.iter() {
+            match encoding {
+                CubemapEncoding::Rgba8 => out.extend_from_slice(face),
+                CubemapEncoding::Rgb565 => {
+                    for texel in face.chunks_exact(4) {
+                        out.extend_from_slice(&encode_rgb565(texel).to_le_bytes());
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> anyhow::Result<Self> {
+        let mut r = ByteReader::new(data);
+        ensure!(
+            r.bytes(4)? == CUBEMAP_MAGIC,
+            "BakedCubemap::from_bytes => Not a baked cubemap"
+        );
+        let version = r.u32()?;
+        ensure!(
+            version == CUBEMAP_VERSION,
+            "BakedCubemap::from_bytes => Unsupported version {}",
+            version
+        );
+        let size = r.u32()?;
+        let encoding = CubemapEncoding::from_u8(r.u8()?)?;
+        let texels = (size * size) as usize;
+        let mut faces: [Vec<u8>; 6] = Default::default();
+        for face in faces.iter_mut() {
+            *face = match encoding {
+                CubemapEncoding::Rgba8 => r.bytes(texels * 4)?.to_vec(),
+                CubemapEncoding::Rgb565 => r
+                    .bytes(texels * 2)?
+                    .chunks_exact(2)
+                    .flat_map(|c| decode_rgb565(u16::from_le_bytes([c[0], c[1]])))
+                    .collect(),
+            };
+        }
+        Ok(Self { size, faces })
+    }
+}
+
+fn encode_rgb565(texel: &[u8]) -> u16 {
+    let [r, g, b] = [(texel[0], 31), (texel[1], 63), (texel[2], 31)]
+        .map(|(c, max)| ((c as u32 * max + 127) / 255) as u16);
+    (r << 11) | (g << 5) | b
+}
+
+fn decode_rgb565(v: u16) -> [u8; 4] {
+    let expand = |c: u16, max: u32| ((c as u32 * 255 + max / 2) / max) as u8;
+    [
+        expand(v >> 11, 31),
+        expand((v >> 5) & 63, 63),
+        expand(v & 31, 31),
+        255,
+    ]
+}
+
+/// Face and uv (origin top left) a cube lookup along `dir` lands on.
+pub fn face_uv(dir: [f32; 3]) -> (usize, [f32; 2]) {
+    let [x, y, z] = dir;
+    let [ax, ay, az] = dir.map(f32::abs);
+    let (face, ma, sc, tc) = if ax >= ay && ax >= az {
+        if x >= 0.0 {
+            (0, ax, -z, -y)
+        } else {
+            (1, ax, z, -y)
+        }
+    } else if ay >= az {
+        if y >= 0.0 {
+            (2, ay, x, z)
+        } else {
+            (3, ay, x, -z)
+        }
+    } else if z >= 0.0 {
+        (4, az, x, -y)
+    } else {
+        (5, az, -x, -y)
+    };
+    let ma = ma.max(f32::EPSILON);
+    (face, [(sc / ma + 1.0) * 0.5, (tc / ma + 1.0) * 0.5])
+}
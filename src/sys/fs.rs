@@ -4,10 +4,13 @@ use std::{
 };
 const TEMP: u32 = 0;
 
+use anyhow::bail;
 use cfg_if::cfg_if;
+use cgmath::Point3;
 use wgpu::util::DeviceExt;
 
 use crate::gfx::{
+    font::BmFont,
     model::{Material, Mesh, Model},
     wgpu::{
         texture::{self, TextureType},
@@ -15,6 +18,14 @@ use crate::gfx::{
     },
 };
 
+use super::{
+    baked::{BakedFont, BakedModelView, BakedTexture},
+    geom::bounds::Aabb3,
+    mem::AlignedBytes,
+    meshopt::optimize_mesh,
+    pack::AssetPack,
+};
+
 #[cfg(target_arch = "wasm32")]
 fn format_url(file_name: &str) -> reqwest::Url {
     let win = web_sys::window().expect("Error loading Browser window");
@@ -75,6 +86,264 @@ pub async fn load_texture(
     texture::Texture::from_bytes(device, queue, &data, ty, Some(filename))
 }
 
+/// Same as load_texture, but a texture that fails to load is replaced by the error
+/// texture with a warning, so the scene still loads and the asset shows up magenta.
+pub async fn load_texture_or_error(
+    filename: &str,
+    ty: TextureType,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> anyhow::Result<texture::Texture> {
+    let result = load_texture(filename, ty, device, queue).await;
+    texture_or_error(result, filename, ty, device, queue)
+}
+
+fn texture_or_error(
+    result: anyhow::Result<texture::Texture>,
+    path: &str,
+    ty: TextureType,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> anyhow::Result<texture::Texture> {
+    result.or_else(|e| {
+        log::warn!(
+            "load_texture => Failed to load '{}': {:#}, using the error texture",
+            path,
+            e
+        );
+        texture::Texture::error_texture(device, queue, ty, Some(path))
+    })
+}
+
+/// Points meshes whose material slot doesn't exist at an error material appended to the
+/// model, warning once with the asset path, so they draw magenta instead of panicking.
+fn fill_missing_materials(
+    model: &mut Model,
+    path: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+) -> anyhow::Result<()> {
+    let slots = model.materials.len();
+    let missing = model.meshes.iter().filter(|m| m.material >= slots).count();
+    if missing == 0 {
+        return Ok(());
+    }
+    log::warn!(
+        "load_model => '{}': {} meshes reference missing material slots, using the error material",
+        path,
+        missing
+    );
+    let diffuse = texture::Texture::error_texture(device, queue, TextureType::Diffuse, Some(path))?;
+    let normal = texture::Texture::error_texture(device, queue, TextureType::Normal, Some(path))?;
+    model.materials.push(Material::new(
+        device,
+        diffuse,
+        normal,
+        layout,
+        Some("Error Material"),
+    ));
+    for mesh in model.meshes.iter_mut().filter(|m| m.material >= slots) {
+        mesh.material = slots;
+    }
+    Ok(())
+}
+
+/// Loads a BMFont .fnt and its page textures, which are looked up next to the .fnt file.
+pub async fn load_bmfont(
+    filename: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> anyhow::Result<(BmFont, Vec<texture::Texture>)> {
+    let font = BmFont::from_bytes(&load_to_bytes(filename).await?)?;
+    let dir = filename.rfind('/').map_or("", |i| &filename[..=i]);
+    let mut pages = Vec::with_capacity(font.pages.len());
+    for page in font.pages.iter() {
+        let path = format!("{}{}", dir, page);
+        pages.push(load_texture_or_error(&path, TextureType::Diffuse, device, queue).await?);
+    }
+    Ok((font, pages))
+}
+
+/// Loads an asset pack written by the asset importer, see sys::import.
+pub async fn load_pack(filename: &str) -> anyhow::Result<AssetPack> {
+    cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            let data = AlignedBytes::from_slice(&load_to_bytes(filename).await?);
+        } else {
+            let path = std::path::Path::new(env!("OUT_DIR"))
+                .join("public")
+                .join(filename);
+            let data = AlignedBytes::read_file(path)?;
+        }
+    }
+    AssetPack::from_aligned(data)
+}
+
+pub fn load_packed_texture(
+    pack: &AssetPack,
+    name: &str,
+    ty: TextureType,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> anyhow::Result<texture::Texture> {
+    let Some(data) = pack.get(name) else {
+        bail!("load_packed_texture => {} not found in pack", name);
+    };
+    let baked = BakedTexture::from_bytes(data)?;
+    Ok(texture::Texture::from_baked(
+        device,
+        queue,
+        &baked,
+        ty,
+        Some(name),
+    ))
+}
+
+/// Loads a font baked by the asset importer and its MSDF atlas pages, uploaded as linear
+/// data. Draw it with OverlayPipeline::msdf_material, see BakedFont.
+pub fn load_packed_font(
+    pack: &AssetPack,
+    name: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> anyhow::Result<(BakedFont, Vec<texture::Texture>)> {
+    let Some(data) = pack.get(name) else {
+        bail!("load_packed_font => {} not found in pack", name);
+    };
+    let font = BakedFont::from_bytes(data)?;
+    let pages = font
+        .font
+        .pages
+        .iter()
+        .map(|page| load_packed_texture(pack, page, TextureType::Normal, device, queue))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok((font, pages))
+}
+
+/// Loads a model baked by the asset importer. Skips obj parsing and tangent generation,
+/// vertex and index data is uploaded straight from the pack.
+pub fn load_packed_model(
+    pack: &AssetPack,
+    name: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+) -> anyhow::Result<Model> {
+    let Some(data) = pack.get(name) else {
+        bail!("load_packed_model => {} not found in pack", name);
+    };
+    let baked = BakedModelView::parse(data)?;
+
+    let mut materials = Vec::with_capacity(baked.materials.len());
+    for m in baked.materials.iter() {
+        let diffuse_texture = texture_or_error(
+            load_packed_texture(
+                pack,
+                &m.diffuse_texture,
+                TextureType::Diffuse,
+                device,
+                queue,
+            ),
+            &m.diffuse_texture,
+            TextureType::Diffuse,
+            device,
+            queue,
+        )?;
+        let normal_texture = texture_or_error(
+            load_packed_texture(pack, &m.normal_texture, TextureType::Normal, device, queue),
+            &m.normal_texture,
+            TextureType::Normal,
+            device,
+            queue,
+        )?;
+        materials.push(Material::new(
+            device,
+            diffuse_texture,
+            normal_texture,
+            layout,
+            Some(&m.name),
+        ));
+    }
+
+    let meshes = upload_baked_meshes(device, &baked);
+    let mut model = Model { meshes, materials };
+    fill_missing_materials(&mut model, name, device, queue, layout)?;
+    Ok(model)
+}
+
+/// Loads a standalone baked mesh file (see BakedModel::write), its textures are loaded
+/// as regular image files by name.
+pub async fn load_baked_model(
+    filename: &str,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+) -> anyhow::Result<Model> {
+    cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            let data = AlignedBytes::from_slice(&load_to_bytes(filename).await?);
+        } else {
+            let path = std::path::Path::new(env!("OUT_DIR"))
+                .join("public")
+                .join(filename);
+            let data = AlignedBytes::read_file(path)?;
+        }
+    }
+    let baked = BakedModelView::parse(data.as_slice())?;
+
+    let mut materials = Vec::with_capacity(baked.materials.len());
+    for m in baked.materials.iter() {
+        let diffuse_texture =
+            load_texture_or_error(&m.diffuse_texture, TextureType::Diffuse, device, queue).await?;
+        let normal_texture =
+            load_texture_or_error(&m.normal_texture, TextureType::Normal, device, queue).await?;
+        materials.push(Material::new(
+            device,
+            diffuse_texture,
+            normal_texture,
+            layout,
+            Some(&m.name),
+        ));
+    }
+
+    let meshes = upload_baked_meshes(device, &baked);
+    let mut model = Model { meshes, materials };
+    fill_missing_materials(&mut model, filename, device, queue, layout)?;
+    Ok(model)
+}
+
+fn vertex_bounds(vertices: &[Vertex3D]) -> Aabb3 {
+    Aabb3::from_points(vertices.iter().map(|v| Point3::from(v.position)))
+}
+
+pub(crate) fn upload_baked_meshes(device: &wgpu::Device, baked: &BakedModelView) -> Vec<Mesh> {
+    baked
+        .meshes
+        .iter()
+        .map(|m| {
+            let vert_buff = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{:?} Vertex Buffer", m.name)),
+                contents: bytemuck::cast_slice(&m.vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+            let index_buff = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("{:?} Index Buffer", m.name)),
+                contents: bytemuck::cast_slice(&m.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+            Mesh {
+                name: m.name.clone(),
+                vert_buff: Arc::new(vert_buff),
+                index_buff: Arc::new(index_buff),
+                num_elements: m.indices.len() as u32,
+                material: m.material as usize,
+                bounds: vertex_bounds(&m.vertices),
+            }
+        })
+        .collect()
+}
+
 pub async fn load_model(
     filename: &str,
     device: &wgpu::Device,
@@ -93,31 +362,49 @@ pub async fn load_model(
             ..Default::default()
         },
         |x| async move {
-            let mat_text = load_to_str(&x)
-                .await
-                .expect("unable to load path for material");
-            tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text)))
+            match load_to_str(&x).await {
+                Ok(mat_text) => tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(mat_text))),
+                Err(e) => {
+                    log::warn!("load_model => Failed to load '{}': {:#}", x, e);
+                    Err(tobj::LoadError::OpenFileFailed)
+                }
+            }
         },
     )
     .await?;
 
+    // Meshes of a model without materials are given the error material below.
+    let obj_materials = obj_materials.unwrap_or_else(|e| {
+        log::warn!(
+            "load_model => '{}' has no usable materials: {}",
+            filename,
+            e
+        );
+        Vec::new()
+    });
     let mut materials = Vec::new();
-    for m in obj_materials? {
-        let diffuse_texture = {
-            let dt = m
-                .diffuse_texture
-                .as_ref()
-                .expect("Failed to find diffuse texture for object materail");
-            load_texture(dt, TextureType::Diffuse, device, queue).await?
+    for m in obj_materials {
+        // Untextured materials get the same white diffuse and flat normal as
+        // RenderWindow::default_material.
+        let diffuse_texture = match m.diffuse_texture.as_ref() {
+            Some(dt) => load_texture_or_error(dt, TextureType::Diffuse, device, queue).await?,
+            None => texture::Texture::from_color(
+                device,
+                queue,
+                [255, 255, 255, 255],
+                TextureType::Diffuse,
+                Some(&m.name),
+            )?,
         };
-
-        // TODO :: Probably want to make Normal textures optional? not sure yet.
-        let normal_texture = {
-            let nt = m
-                .normal_texture
-                .as_ref()
-                .expect("Failed to find normal texture for object material");
-            load_texture(nt, TextureType::Normal, device, queue).await?
+        let normal_texture = match m.normal_texture.as_ref() {
+            Some(nt) => load_texture_or_error(nt, TextureType::Normal, device, queue).await?,
+            None => texture::Texture::from_color(
+                device,
+                queue,
+                texture::FLAT_NORMAL,
+                TextureType::Normal,
+                Some(&m.name),
+            )?,
         };
 
         materials.push(Material::new(
@@ -149,70 +436,16 @@ pub async fn load_model(
                 })
                 .collect::<Vec<_>>();
 
-            let indicies = &m.mesh.indices;
-            let mut triangels_included = vec![0; verticies.len()];
-
-            // Calculate tangents and bitangents using Triangles.
-            // Loop through indicies in chunks of 3
-            for c in indicies.chunks(3) {
-                let v0 = verticies[c[0] as usize];
-                let v1 = verticies[c[1] as usize];
-                let v2 = verticies[c[2] as usize];
-
-                let pos0: cgmath::Vector3<_> = v0.position.into();
-                let pos1: cgmath::Vector3<_> = v1.position.into();
-                let pos2: cgmath::Vector3<_> = v2.position.into();
-
-                let uv0: cgmath::Vector2<_> = v0.tex_coords.into();
-                let uv1: cgmath::Vector2<_> = v1.tex_coords.into();
-                let uv2: cgmath::Vector2<_> = v2.tex_coords.into();
-
-                // Calc edges of triangle
-                let delta_pos1 = pos1 - pos0;
-                let delta_pos2 = pos2 - pos0;
-
-                // Gives us a direction to calc the tangent and bitangent
-                let delta_uv1 = uv1 - uv0;
-                let delta_uv2 = uv2 - uv0;
-
-                // System of Equations solves for tangent and bitangent
-                // delta_pos1 = delta_uv1.x * T + delta_u.y * B
-                // delta_pos2 = delta_uv2.x * T + delta_uv2.y * B
-                let r = 1.0 / (delta_uv1.x * delta_uv2.y - delta_uv1.y * delta_uv2.x);
-                let tangent = (delta_pos1 * delta_uv2.y - delta_pos2 * delta_uv1.y) * r;
-
-                // Flip bitangent to enable right-handed normal
-                // maps with wgpu texture coordinate system.
-                let bitangent = (delta_pos2 * delta_uv1.x - delta_pos1 * delta_uv2.x) * -r;
-
-                // Use the same tangent and bitangent for each vertex in the triangle.
-                verticies[c[0] as usize].tangent =
-                    (tangent + cgmath::Vector3::from(verticies[c[0] as usize].tangent)).into();
-                verticies[c[1] as usize].tangent =
-                    (tangent + cgmath::Vector3::from(verticies[c[1] as usize].tangent)).into();
-                verticies[c[2] as usize].tangent =
-                    (tangent + cgmath::Vector3::from(verticies[c[2] as usize].tangent)).into();
-
-                verticies[c[0] as usize].bitangent =
-                    (bitangent + cgmath::Vector3::from(verticies[c[0] as usize].bitangent)).into();
-                verticies[c[1] as usize].bitangent =
-                    (bitangent + cgmath::Vector3::from(verticies[c[1] as usize].bitangent)).into();
-                verticies[c[2] as usize].bitangent =
-                    (bitangent + cgmath::Vector3::from(verticies[c[2] as usize].bitangent)).into();
-
-                // used to average the tangents and bitangents.
-                triangels_included[c[0] as usize] += 1;
-                triangels_included[c[1] as usize] += 1;
-                triangels_included[c[2] as usize] += 1;
-            }
-
-            // Average the tangents and bitangents.
-            for (i, n) in triangels_included.into_iter().enumerate() {
-                let denom = 1.0 / n as f32;
-                let v = &mut verticies[i];
-                v.tangent = (cgmath::Vector3::from(v.tangent) * denom).into();
-                v.bitangent = (cgmath::Vector3::from(v.bitangent) * denom).into();
-            }
+            let mut indices = m.mesh.indices;
+            let (before, after) = optimize_mesh(&mut verticies, &mut indices, |v| v.position);
+            log::info!(
+                "load_model => {} '{}': {} -> {}",
+                filename,
+                m.name,
+                before,
+                after
+            );
+            compute_tangents(&mut verticies, &indices);
 
             let vert_buff = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some(&format!("{:?} Vertex Buffer", filename)),
@@ -221,7 +454,7 @@ pub async fn load_model(
             });
             let index_buff = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some(&format!("{:?} Index Buffer", filename)),
-                contents: bytemuck::cast_slice(&m.mesh.indices),
+                contents: bytemuck::cast_slice(&indices),
                 usage: wgpu::BufferUsages::INDEX,
             });
             let vert_buff = Arc::new(vert_buff);
@@ -230,11 +463,199 @@ pub async fn load_model(
                 name: filename.to_string(),
                 vert_buff,
                 index_buff,
-                num_elements: m.mesh.indices.len() as u32,
+                num_elements: indices.len() as u32,
                 material: m.mesh.material_id.unwrap_or(0),
+                bounds: vertex_bounds(&verticies),
             }
         })
         .collect::<Vec<_>>();
 
-    Ok(Model { meshes, materials })
+    let mut model = Model { meshes, materials };
+    fill_missing_materials(&mut model, filename, device, queue, layout)?;
+    Ok(model)
+}
+
+/// Computes per vertex tangents and bitangents from the triangles in `indicies`,
+/// averaging them over every triangle a vertex is part of.
+pub fn compute_tangents(verticies: &mut [Vertex3D], indicies: &[u32]) {
+    let mut triangels_included = vec![0; verticies.len()];
+
+    // Calculate tangents and bitangents using Triangles.
+    // Loop through indicies in chunks of 3
+    for c in indicies.chunks(3) {
+        let v0 = verticies[c[0] as usize];
+        let v1 = verticies[c[1] as usize];
+        let v2 = verticies[c[2] as usize];
+
+        let pos0: cgmath::Vector3<_> = v0.position.into();
+        let pos1: cgmath::Vector3<_> = v1.position.into();
+        let pos2: cgmath::Vector3<_> = v2.position.into();
+
+        let uv0: cgmath::Vector2<_> = v0.tex_coords.into();
+        let uv1: cgmath::Vector2<_> = v1.tex_coords.into();
+        let uv2: cgmath::Vector2<_> = v2.tex_coords.into();
+
+        // Calc edges of triangle
+        let delta_pos1 = pos1 - pos0;
+        let delta_pos2 = pos2 - pos0;
+
+        // Gives us a direction to calc the tangent and bitangent
+        let delta_uv1 = uv1 - uv0;
+        let delta_uv2 = uv2 - uv0;
+
+        // System of Equations solves for tangent and bitangent
+        // delta_pos1 = delta_uv1.x * T + delta_u.y * B
+        // delta_pos2 = delta_uv2.x * T + delta_uv2.y * B
+        let r = 1.0 / (delta_uv1.x * delta_uv2.y - delta_uv1.y * delta_uv2.x);
+        let tangent = (delta_pos1 * delta_uv2.y - delta_pos2 * delta_uv1.y) * r;
+
+        // Flip bitangent to enable right-handed normal
+        // maps with wgpu texture coordinate system.
+        let bitangent = (delta_pos2 * delta_uv1.x - delta_pos1 * delta_uv2.x) * -r;
+
+        // Use the same tangent and bitangent for each vertex in the triangle.
+        verticies[c[0] as usize].tangent =
+            (tangent + cgmath::Vector3::from(verticies[c[0] as usize].tangent)).into();
+        verticies[c[1] as usize].tangent =
+            (tangent + cgmath::Vector3::from(verticies[c[1] as usize].tangent)).into();
+        verticies[c[2] as usize].tangent =
+            (tangent + cgmath::Vector3::from(verticies[c[2] as usize].tangent)).into();
+
+        verticies[c[0] as usize].bitangent =
+            (bitangent + cgmath::Vector3::from(verticies[c[0] as usize].bitangent)).into();
+        verticies[c[1] as usize].bitangent =
+            (bitangent + cgmath::Vector3::from(verticies[c[1] as usize].bitangent)).into();
+        verticies[c[2] as usize].bitangent =
+            (bitangent + cgmath::Vector3::from(verticies[c[2] as usize].bitangent)).into();
+
+        // used to average the tangents and bitangents.
+        triangels_included[c[0] as usize] += 1;
+        triangels_included[c[1] as usize] += 1;
+        triangels_included[c[2] as usize] += 1;
+    }
+
+    // Average the tangents and bitangents.
+    for (i, n) in triangels_included.into_iter().enumerate() {
+        let denom = 1.0 / n as f32;
+        let v = &mut verticies[i];
+        v.tangent = (cgmath::Vector3::from(v.tangent) * denom).into();
+        v.bitangent = (cgmath::Vector3::from(v.bitangent) * denom).into();
+    }
+}
+
+/// Per user directories an app keeps its files in, see user_dir.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UserDir {
+    /// Settings, e.g. the EngineConfig file.
+    Config,
+    /// Save games and other files that must not be lost.
+    Data,
+    /// Files that can be rebuilt, e.g. shader and pipeline caches.
+    Cache,
+}
+
+/// Platform directory of `kind` for `app_name`, e.g. ~/.config/<app_name> on Linux,
+/// ~/Library/Application Support/<app_name> on macOS and %APPDATA%\<app_name> on Windows.
+/// The directory is not created, see ensure_dir.
+pub fn user_dir(kind: UserDir, app_name: &str) -> anyhow::Result<std::path::PathBuf> {
+    user_dir_from_env(kind, app_name, std::env::consts::OS, |key| {
+        std::env::var_os(key).filter(|v| !v.is_empty())
+    })
+}
+
+#[inline]
+pub fn config_dir(app_name: &str) -> anyhow::Result<std::path::PathBuf> {
+    user_dir(UserDir::Config, app_name)
+}
+
+#[inline]
+pub fn data_dir(app_name: &str) -> anyhow::Result<std::path::PathBuf> {
+    user_dir(UserDir::Data, app_name)
+}
+
+#[inline]
+pub fn cache_dir(app_name: &str) -> anyhow::Result<std::path::PathBuf> {
+    user_dir(UserDir::Cache, app_name)
+}
+
+/// user_dir with the platform and environment passed in, so every platform can be tested.
+pub fn user_dir_from_env(
+    kind: UserDir,
+    app_name: &str,
+    os: &str,
+    env: impl Fn(&str) -> Option<std::ffi::OsString>,
+) -> anyhow::Result<std::path::PathBuf> {
+    use std::path::PathBuf;
+
+    if app_name.is_empty() || app_name.contains(['/', '\\']) || app_name == ".." {
+        bail!("user_dir => invalid app name {:?}", app_name);
+    }
+    let home = || {
+        env(if os == "windows" {
+            "USERPROFILE"
+        } else {
+            "HOME"
+        })
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow::anyhow!("user_dir => no home directory"))
+    };
+    let base = match (os, kind) {
+        ("windows", UserDir::Config | UserDir::Data) => env("APPDATA")
+            .map(PathBuf::from)
+            .map_or_else(|| home().map(|h| h.join("AppData").join("Roaming")), Ok)?,
+        ("windows", UserDir::Cache) => env("LOCALAPPDATA")
+            .map(PathBuf::from)
+            .map_or_else(|| home().map(|h| h.join("AppData").join("Local")), Ok)?,
+        ("macos", UserDir::Config | UserDir::Data) => {
+            home()?.join("Library").join("Application Support")
+        }
+        ("macos", UserDir::Cache) => home()?.join("Library").join("Caches"),
+        (_, kind) => {
+            // XDG base directories, only absolute overrides are valid.
+            let (var, fallback) = match kind {
+                UserDir::Config => ("XDG_CONFIG_HOME", ".config"),
+                UserDir::Data => ("XDG_DATA_HOME", ".local/share"),
+                UserDir::Cache => ("XDG_CACHE_HOME", ".cache"),
+            };
+            match env(var).map(PathBuf::from).filter(|p| p.is_absolute()) {
+                Some(dir) => dir,
+                None => home()?.join(fallback),
+            }
+        }
+    };
+    Ok(base.join(app_name))
+}
+
+/// Creates `path` and any missing parents, returning it for chaining.
+pub fn ensure_dir(path: impl AsRef<std::path::Path>) -> anyhow::Result<std::path::PathBuf> {
+    let path = path.as_ref();
+    std::fs::create_dir_all(path)
+        .map_err(|e| anyhow::anyhow!("ensure_dir => {}: {}", path.display(), e))?;
+    Ok(path.to_path_buf())
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it over `path`, so a
+/// crash mid write leaves either the old or the new file, never a truncated one.
+pub fn write_atomic(path: impl AsRef<std::path::Path>, contents: &[u8]) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let path = path.as_ref();
+    let Some(file_name) = path.file_name() else {
+        bail!("write_atomic => {} is not a file path", path.display());
+    };
+    let mut temp_name = file_name.to_os_string();
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp = path.with_file_name(temp_name);
+
+    let result = (|| {
+        let mut file = std::fs::File::create(&temp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        std::fs::rename(&temp, path)
+    })();
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&temp);
+        bail!("write_atomic => {}: {}", path.display(), e);
+    }
+    Ok(())
 }
@@ -0,0 +1,94 @@
+use cgmath::{EuclideanSpace, Matrix4, Point3, Transform, Vector3};
+
+/// Axis aligned box in 3D, e.g. the bounds of a mesh in model space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb3 {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Default for Aabb3 {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl Aabb3 {
+    /// Contains nothing, the union with any box is that box.
+    pub const EMPTY: Aabb3 = Aabb3 {
+        min: Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+        max: Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+    };
+
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Self {
+        Self { min, max }
+    }
+
+    /// Smallest box around `points`, EMPTY if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = Point3<f32>>) -> Self {
+        points.into_iter().fold(Self::EMPTY, |b, p| b.expanded(p))
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    #[inline]
+    pub fn center(&self) -> Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+
+    #[inline]
+    pub fn size(&self) -> Vector3<f32> {
+        self.max - self.min
+    }
+
+    pub fn expanded(&self, p: Point3<f32>) -> Aabb3 {
+        Aabb3::new(
+            Point3::new(
+                self.min.x.min(p.x),
+                self.min.y.min(p.y),
+                self.min.z.min(p.z),
+            ),
+            Point3::new(
+                self.max.x.max(p.x),
+                self.max.y.max(p.y),
+                self.max.z.max(p.z),
+            ),
+        )
+    }
+
+    pub fn union(&self, other: &Aabb3) -> Aabb3 {
+        if other.is_empty() {
+            return *self;
+        }
+        self.expanded(other.min).expanded(other.max)
+    }
+
+    #[inline]
+    pub fn contains(&self, p: Point3<f32>) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x)
+            && (self.min.y..=self.max.y).contains(&p.y)
+            && (self.min.z..=self.max.z).contains(&p.z)
+    }
+
+    /// Corners ordered by their bits, bit 0 picks max x, bit 1 max y and bit 2 max z.
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        std::array::from_fn(|i| {
+            Point3::new(
+                if i & 1 == 0 { self.min.x } else { self.max.x },
+                if i & 2 == 0 { self.min.y } else { self.max.y },
+                if i & 4 == 0 { self.min.z } else { self.max.z },
+            )
+        })
+    }
+
+    /// Box around this box after `transform`, e.g. an instance's world bounds.
+    pub fn transformed(&self, transform: &Matrix4<f32>) -> Aabb3 {
+        if self.is_empty() {
+            return *self;
+        }
+        Self::from_points(self.corners().map(|c| transform.transform_point(c)))
+    }
+}
@@ -0,0 +1,562 @@
+pub mod bounds;
+pub mod world;
+
+use cgmath::{InnerSpace, Vector2};
+
+pub type Vec2 = Vector2<f32>;
+
+const EPSILON: f32 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Aabb {
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_center(center: Vec2, half_extents: Vec2) -> Self {
+        Self::new(center - half_extents, center + half_extents)
+    }
+
+    #[inline]
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) * 0.5
+    }
+
+    #[inline]
+    pub fn half_extents(&self) -> Vec2 {
+        (self.max - self.min) * 0.5
+    }
+
+    #[inline]
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x
+            && self.max.x >= other.min.x
+            && self.min.y <= other.max.y
+            && self.max.y >= other.min.y
+    }
+
+    #[inline]
+    pub fn contains(&self, p: Vec2) -> bool {
+        p.x >= self.min.x && p.x <= self.max.x && p.y >= self.min.y && p.y <= self.max.y
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(
+            Vec2::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y)),
+            Vec2::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y)),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Circle {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+/// Box rotated by `rotation` radians around its center.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Obb {
+    pub center: Vec2,
+    pub half_extents: Vec2,
+    pub rotation: f32,
+}
+
+impl Obb {
+    /// Local x and y axes in world space.
+    pub fn axes(&self) -> (Vec2, Vec2) {
+        let (s, c) = self.rotation.sin_cos();
+        (Vec2::new(c, s), Vec2::new(-s, c))
+    }
+
+    pub fn corners(&self) -> [Vec2; 4] {
+        let (ax, ay) = self.axes();
+        let (x, y) = (ax * self.half_extents.x, ay * self.half_extents.y);
+        let c = self.center;
+        [c - x - y, c + x - y, c + x + y, c - x + y]
+    }
+}
+
+/// Segment from `a` to `b` swept by a circle of `radius`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capsule {
+    pub a: Vec2,
+    pub b: Vec2,
+    pub radius: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Aabb(Aabb),
+    Circle(Circle),
+    Obb(Obb),
+    Capsule(Capsule),
+}
+
+impl From<Aabb> for Shape {
+    fn from(v: Aabb) -> Self {
+        Self::Aabb(v)
+    }
+}
+
+impl From<Circle> for Shape {
+    fn from(v: Circle) -> Self {
+        Self::Circle(v)
+    }
+}
+
+impl From<Obb> for Shape {
+    fn from(v: Obb) -> Self {
+        Self::Obb(v)
+    }
+}
+
+impl From<Capsule> for Shape {
+    fn from(v: Capsule) -> Self {
+        Self::Capsule(v)
+    }
+}
+
+/// Separation of two overlapping shapes. Moving the second shape by normal * depth
+/// resolves the overlap, normal points from the first shape towards the second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    pub normal: Vec2,
+    pub depth: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    /// Distance along the ray direction, in units of its length.
+    pub t: f32,
+    pub point: Vec2,
+    pub normal: Vec2,
+}
+
+/// First touch of a swept shape, `toi` is the fraction of the motion travelled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SweepHit {
+    pub toi: f32,
+    /// Surface normal of the obstacle, facing the moving shape.
+    pub normal: Vec2,
+}
+
+impl Shape {
+    pub fn center(&self) -> Vec2 {
+        match self {
+            Self::Aabb(s) => s.center(),
+            Self::Circle(s) => s.center,
+            Self::Obb(s) => s.center,
+            Self::Capsule(s) => (s.a + s.b) * 0.5,
+        }
+    }
+
+    pub fn translated(&self, offset: Vec2) -> Shape {
+        match *self {
+            Self::Aabb(s) => Self::Aabb(Aabb::new(s.min + offset, s.max + offset)),
+            Self::Circle(s) => Self::Circle(Circle {
+                center: s.center + offset,
+                ..s
+            }),
+            Self::Obb(s) => Self::Obb(Obb {
+                center: s.center + offset,
+                ..s
+            }),
+            Self::Capsule(s) => Self::Capsule(Capsule {
+                a: s.a + offset,
+                b: s.b + offset,
+                ..s
+            }),
+        }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        let hull = Hull::from(self);
+        let r = Vec2::new(hull.radius, hull.radius);
+        let pts = hull.points();
+        let mut bounds = Aabb::new(pts[0], pts[0]);
+        for p in &pts[1..] {
+            bounds = bounds.union(&Aabb::new(*p, *p));
+        }
+        Aabb::new(bounds.min - r, bounds.max + r)
+    }
+
+    pub fn contains_point(&self, p: Vec2) -> bool {
+        let point = Shape::Circle(Circle {
+            center: p,
+            radius: 0.0,
+        });
+        self.overlaps(&point)
+    }
+
+    #[inline]
+    pub fn overlaps(&self, other: &Shape) -> bool {
+        self.contact(other).is_some()
+    }
+
+    /// Penetration of two shapes, None if they don't overlap.
+    pub fn contact(&self, other: &Shape) -> Option<Contact> {
+        let (a, b) = (Hull::from(self), Hull::from(other));
+        let radius = a.radius + b.radius;
+
+        if a.len >= 3 || b.len >= 3 {
+            if let Some(contact) = sat(&a, &b) {
+                return Some(Contact {
+                    normal: contact.normal,
+                    depth: contact.depth + radius,
+                });
+            }
+        }
+        let (pa, pb) = closest_points(&a, &b);
+        let delta = pb - pa;
+        let dist = delta.magnitude();
+        if dist >= radius {
+            return None;
+        }
+        let normal = if dist > EPSILON {
+            delta / dist
+        } else {
+            fallback_normal(&a, &b)
+        };
+        Some(Contact {
+            normal,
+            depth: radius - dist,
+        })
+    }
+
+    /// Distance between the surfaces, 0 when overlapping.
+    pub fn distance(&self, other: &Shape) -> f32 {
+        self.separation(other).map_or(0.0, |(dist, _)| dist)
+    }
+
+    /// Distance and direction from this shape towards `other`, None when they overlap.
+    fn separation(&self, other: &Shape) -> Option<(f32, Vec2)> {
+        let (a, b) = (Hull::from(self), Hull::from(other));
+        if (a.len >= 3 || b.len >= 3) && sat(&a, &b).is_some() {
+            return None;
+        }
+        let (pa, pb) = closest_points(&a, &b);
+        let delta = pb - pa;
+        let core = delta.magnitude();
+        let dist = core - a.radius - b.radius;
+        (core > EPSILON && dist >= 0.0).then(|| (dist, delta / core))
+    }
+
+    /// Moves this shape by `motion` and reports the first time it touches `other`. Shapes
+    /// that already overlap hit at toi 0, touching shapes that slide along or move apart
+    /// don't hit at all.
+    pub fn sweep(&self, motion: Vec2, other: &Shape) -> Option<SweepHit> {
+        const MAX_ITERATIONS: usize = 32;
+        const TOLERANCE: f32 = 1e-4;
+
+        let mut t = 0.0;
+        for _ in 0..MAX_ITERATIONS {
+            let moved = self.translated(motion * t);
+            let Some((dist, dir)) = moved.separation(other) else {
+                let normal = other
+                    .contact(&moved)
+                    .map_or(Vec2::new(0.0, 1.0), |c| c.normal);
+                return Some(SweepHit { toi: t, normal });
+            };
+            // Distance is convex in t for convex shapes, so if it isn't shrinking now it
+            // never will, and stepping to where its tangent hits zero can't overshoot.
+            let closing = motion.dot(dir);
+            if closing <= EPSILON {
+                return None;
+            }
+            if dist <= TOLERANCE {
+                return Some(SweepHit {
+                    toi: t,
+                    normal: -dir,
+                });
+            }
+            t += dist / closing;
+            if t > 1.0 {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Casts a ray from `origin` along `dir`, hits beyond `max_t` are ignored. Rays starting
+    /// inside a shape hit at t 0 with the normal facing back along the ray.
+    pub fn raycast(&self, origin: Vec2, dir: Vec2, max_t: f32) -> Option<RayHit> {
+        if self.contains_point(origin) {
+            let normal = if dir.magnitude2() > EPSILON {
+                -dir.normalize()
+            } else {
+                Vec2::new(0.0, 1.0)
+            };
+            return Some(RayHit {
+                t: 0.0,
+                point: origin,
+                normal,
+            });
+        }
+        let hit = match *self {
+            Self::Aabb(s) => ray_box(origin, dir, s.center(), s.half_extents(), 0.0),
+            Self::Obb(s) => ray_box(origin, dir, s.center, s.half_extents, s.rotation),
+            Self::Circle(s) => ray_circle(origin, dir, s.center, s.radius),
+            Self::Capsule(s) => {
+                let axis = s.b - s.a;
+                let side = [
+                    ray_circle(origin, dir, s.a, s.radius),
+                    ray_circle(origin, dir, s.b, s.radius),
+                    ray_box(
+                        origin,
+                        dir,
+                        (s.a + s.b) * 0.5,
+                        Vec2::new(axis.magnitude() * 0.5, s.radius),
+                        axis.y.atan2(axis.x),
+                    ),
+                ];
+                side.into_iter()
+                    .flatten()
+                    .min_by(|a, b| a.t.total_cmp(&b.t))
+            }
+        }?;
+        (hit.t <= max_t).then_some(hit)
+    }
+}
+
+/// Convex core with up to 4 points, inflated by radius. A circle is a point, a capsule a
+/// segment and boxes are polygons with no radius, so every pair goes through one path.
+#[derive(Debug, Clone, Copy)]
+struct Hull {
+    pts: [Vec2; 4],
+    len: usize,
+    radius: f32,
+}
+
+impl From<&Shape> for Hull {
+    fn from(shape: &Shape) -> Self {
+        let zero = Vec2::new(0.0, 0.0);
+        match *shape {
+            Shape::Aabb(s) => Hull {
+                pts: [
+                    s.min,
+                    Vec2::new(s.max.x, s.min.y),
+                    s.max,
+                    Vec2::new(s.min.x, s.max.y),
+                ],
+                len: 4,
+                radius: 0.0,
+            },
+            Shape::Obb(s) => Hull {
+                pts: s.corners(),
+                len: 4,
+                radius: 0.0,
+            },
+            Shape::Circle(s) => Hull {
+                pts: [s.center, zero, zero, zero],
+                len: 1,
+                radius: s.radius,
+            },
+            Shape::Capsule(s) => Hull {
+                pts: [s.a, s.b, zero, zero],
+                len: 2,
+                radius: s.radius,
+            },
+        }
+    }
+}
+
+impl Hull {
+    #[inline]
+    fn points(&self) -> &[Vec2] {
+        &self.pts[..self.len]
+    }
+
+    fn center(&self) -> Vec2 {
+        self.points().iter().fold(Vec2::new(0.0, 0.0), |a, p| a + p) / self.len as f32
+    }
+
+    /// Edges as segments, a point is a single zero length edge.
+    fn edges(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        let n = self.len;
+        let count = if n <= 2 { 1 } else { n };
+        (0..count).map(move |i| (self.pts[i], self.pts[(i + 1) % n]))
+    }
+
+    fn axes(&self) -> impl Iterator<Item = Vec2> + '_ {
+        self.edges().filter_map(|(a, b)| {
+            let e = b - a;
+            (e.magnitude2() > EPSILON).then(|| Vec2::new(-e.y, e.x).normalize())
+        })
+    }
+
+    fn project(&self, axis: Vec2) -> (f32, f32) {
+        self.points()
+            .iter()
+            .map(|p| p.dot(axis))
+            .fold((f32::MAX, f32::MIN), |(lo, hi), d| (lo.min(d), hi.max(d)))
+    }
+}
+
+/// Separating axis test of the cores, the axis of least overlap if they intersect.
+fn sat(a: &Hull, b: &Hull) -> Option<Contact> {
+    let mut best: Option<Contact> = None;
+    for axis in a.axes().chain(b.axes()) {
+        let (amin, amax) = a.project(axis);
+        let (bmin, bmax) = b.project(axis);
+        let overlap = amax.min(bmax) - amin.max(bmin);
+        if overlap < 0.0 {
+            return None;
+        }
+        // Pushing b out the far side can be shorter when one contains the other.
+        let (push_pos, push_neg) = (amax - bmin, bmax - amin);
+        let (depth, normal) = if push_pos <= push_neg {
+            (push_pos, axis)
+        } else {
+            (push_neg, -axis)
+        };
+        if best.is_none_or(|c| depth < c.depth) {
+            best = Some(Contact { normal, depth });
+        }
+    }
+    best
+}
+
+fn fallback_normal(a: &Hull, b: &Hull) -> Vec2 {
+    let d = b.center() - a.center();
+    if d.magnitude2() > EPSILON {
+        d.normalize()
+    } else if let Some(axis) = a.axes().next() {
+        axis
+    } else {
+        Vec2::new(0.0, 1.0)
+    }
+}
+
+/// Closest points between the cores, only meaningful when they don't intersect.
+fn closest_points(a: &Hull, b: &Hull) -> (Vec2, Vec2) {
+    let mut best = (a.pts[0], b.pts[0]);
+    let mut best_dist = f32::MAX;
+    for (a0, a1) in a.edges() {
+        for (b0, b1) in b.edges() {
+            let (pa, pb) = closest_segment_points(a0, a1, b0, b1);
+            let dist = (pb - pa).magnitude2();
+            if dist < best_dist {
+                best_dist = dist;
+                best = (pa, pb);
+            }
+        }
+    }
+    best
+}
+
+pub fn closest_point_on_segment(p: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let ab = b - a;
+    let len2 = ab.magnitude2();
+    if len2 < EPSILON {
+        return a;
+    }
+    a + ab * ((p - a).dot(ab) / len2).clamp(0.0, 1.0)
+}
+
+/// Closest points between segments p1-q1 and p2-q2, see Real-Time Collision Detection 5.1.9.
+pub fn closest_segment_points(p1: Vec2, q1: Vec2, p2: Vec2, q2: Vec2) -> (Vec2, Vec2) {
+    let (d1, d2, r) = (q1 - p1, q2 - p2, p1 - p2);
+    let (a, e, f) = (d1.magnitude2(), d2.magnitude2(), d2.dot(r));
+    if a < EPSILON && e < EPSILON {
+        return (p1, p2);
+    }
+    if a < EPSILON {
+        return (p1, p2 + d2 * (f / e).clamp(0.0, 1.0));
+    }
+    let c = d1.dot(r);
+    if e < EPSILON {
+        return (p1 + d1 * (-c / a).clamp(0.0, 1.0), p2);
+    }
+    let b = d1.dot(d2);
+    let denom = a * e - b * b;
+    let mut s = if denom > EPSILON {
+        ((b * f - c * e) / denom).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let mut t = (b * s + f) / e;
+    if t < 0.0 {
+        t = 0.0;
+        s = (-c / a).clamp(0.0, 1.0);
+    } else if t > 1.0 {
+        t = 1.0;
+        s = ((b - c) / a).clamp(0.0, 1.0);
+    }
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+fn ray_circle(origin: Vec2, dir: Vec2, center: Vec2, radius: f32) -> Option<RayHit> {
+    let m = origin - center;
+    let a = dir.magnitude2();
+    if a < EPSILON {
+        return None;
+    }
+    let b = m.dot(dir);
+    let c = m.magnitude2() - radius * radius;
+    let disc = b * b - a * c;
+    if disc < 0.0 {
+        return None;
+    }
+    let t = (-b - disc.sqrt()) / a;
+    if t < 0.0 {
+        return None;
+    }
+    let point = origin + dir * t;
+    let n = point - center;
+    Some(RayHit {
+        t,
+        point,
+        normal: if n.magnitude2() > EPSILON {
+            n.normalize()
+        } else {
+            -dir.normalize()
+        },
+    })
+}
+
+/// Slab test in the box's local space.
+fn ray_box(origin: Vec2, dir: Vec2, center: Vec2, half: Vec2, rotation: f32) -> Option<RayHit> {
+    let (s, c) = rotation.sin_cos();
+    let (ax, ay) = (Vec2::new(c, s), Vec2::new(-s, c));
+    let rel = origin - center;
+    let (o, d) = ([rel.dot(ax), rel.dot(ay)], [dir.dot(ax), dir.dot(ay)]);
+    let h = [half.x, half.y];
+
+    let (mut t_min, mut t_max) = (0.0f32, f32::MAX);
+    let mut normal = [0.0f32; 2];
+    for i in 0..2 {
+        if d[i].abs() < EPSILON {
+            if o[i].abs() > h[i] {
+                return None;
+            }
+            continue;
+        }
+        let inv = 1.0 / d[i];
+        let (mut t0, mut t1) = ((-h[i] - o[i]) * inv, (h[i] - o[i]) * inv);
+        let mut n = -1.0;
+        if t0 > t1 {
+            std::mem::swap(&mut t0, &mut t1);
+            n = 1.0;
+        }
+        if t0 > t_min {
+            t_min = t0;
+            normal = [0.0; 2];
+            normal[i] = n;
+        }
+        t_max = t_max.min(t1);
+        if t_min > t_max {
+            return None;
+        }
+    }
+    Some(RayHit {
+        t: t_min,
+        point: origin + dir * t_min,
+        normal: ax * normal[0] + ay * normal[1],
+    })
+}
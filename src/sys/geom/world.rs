@@ -0,0 +1,273 @@
+use cgmath::InnerSpace;
+
+use super::{Contact, RayHit, Shape, SweepHit, Vec2};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ColliderId {
+    index: u32,
+    generation: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collider {
+    pub shape: Shape,
+    /// Layers this collider is on.
+    pub layers: u32,
+    /// Layers this collider collides with when it moves.
+    pub mask: u32,
+}
+
+impl Collider {
+    pub fn new(shape: impl Into<Shape>) -> Self {
+        Self {
+            shape: shape.into(),
+            layers: 1,
+            mask: u32::MAX,
+        }
+    }
+
+    pub fn with_layers(mut self, layers: u32, mask: u32) -> Self {
+        self.layers = layers;
+        self.mask = mask;
+        self
+    }
+}
+
+/// Outcome of CollisionWorld::move_and_slide.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlideResult {
+    /// How far the collider actually moved.
+    pub moved: Vec2,
+    pub on_floor: bool,
+    pub on_wall: bool,
+    pub on_ceiling: bool,
+    /// Colliders hit while moving, in order, may repeat.
+    pub hits: Vec<ColliderId>,
+}
+
+impl Default for SlideResult {
+    fn default() -> Self {
+        Self {
+            moved: Vec2::new(0.0, 0.0),
+            on_floor: false,
+            on_wall: false,
+            on_ceiling: false,
+            hits: Vec::new(),
+        }
+    }
+}
+
+/// Flat list of colliders with overlap, ray and sweep queries that check every collider,
+/// which is plenty for a screen worth of level geometry.
+#[derive(Debug, Clone)]
+pub struct CollisionWorld {
+    slots: Vec<(u32, Option<Collider>)>,
+    free: Vec<u32>,
+    /// Points away from the floor, (0, 1) for y up.
+    pub up: Vec2,
+    /// Steepest slope in radians that still counts as floor.
+    pub floor_max_angle: f32,
+    /// Gap kept between a moving collider and what it slides along.
+    pub skin: f32,
+    pub max_slides: usize,
+}
+
+impl Default for CollisionWorld {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            up: Vec2::new(0.0, 1.0),
+            floor_max_angle: std::f32::consts::FRAC_PI_4,
+            skin: 0.01,
+            max_slides: 4,
+        }
+    }
+}
+
+impl CollisionWorld {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, collider: Collider) -> ColliderId {
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.1 = Some(collider);
+                ColliderId {
+                    index,
+                    generation: slot.0,
+                }
+            }
+            None => {
+                self.slots.push((0, Some(collider)));
+                ColliderId {
+                    index: self.slots.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: ColliderId) -> Option<Collider> {
+        let slot = self.slots.get_mut(id.index as usize)?;
+        if slot.0 != id.generation {
+            return None;
+        }
+        let collider = slot.1.take()?;
+        slot.0 = slot.0.wrapping_add(1);
+        self.free.push(id.index);
+        Some(collider)
+    }
+
+    pub fn get(&self, id: ColliderId) -> Option<&Collider> {
+        self.slots
+            .get(id.index as usize)
+            .filter(|slot| slot.0 == id.generation)
+            .and_then(|slot| slot.1.as_ref())
+    }
+
+    pub fn get_mut(&mut self, id: ColliderId) -> Option<&mut Collider> {
+        self.slots
+            .get_mut(id.index as usize)
+            .filter(|slot| slot.0 == id.generation)
+            .and_then(|slot| slot.1.as_mut())
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ColliderId, &Collider)> {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (generation, c))| {
+                c.as_ref().map(|c| {
+                    (
+                        ColliderId {
+                            index: i as u32,
+                            generation: *generation,
+                        },
+                        c,
+                    )
+                })
+            })
+    }
+
+    fn candidates(
+        &self,
+        mask: u32,
+        ignore: Option<ColliderId>,
+    ) -> impl Iterator<Item = (ColliderId, &Collider)> {
+        self.iter()
+            .filter(move |(id, c)| c.layers & mask != 0 && Some(*id) != ignore)
+    }
+
+    /// Colliders on `mask` overlapping `shape`.
+    pub fn overlaps(&self, shape: &Shape, mask: u32) -> Vec<ColliderId> {
+        self.candidates(mask, None)
+            .filter(|(_, c)| c.shape.overlaps(shape))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Closest collider on `mask` hit by the ray.
+    pub fn raycast(
+        &self,
+        origin: Vec2,
+        dir: Vec2,
+        max_t: f32,
+        mask: u32,
+    ) -> Option<(ColliderId, RayHit)> {
+        self.candidates(mask, None)
+            .filter_map(|(id, c)| c.shape.raycast(origin, dir, max_t).map(|hit| (id, hit)))
+            .min_by(|a, b| a.1.t.total_cmp(&b.1.t))
+    }
+
+    /// First collider on `mask` that `shape` touches while moving by `motion`.
+    pub fn sweep(
+        &self,
+        shape: &Shape,
+        motion: Vec2,
+        mask: u32,
+        ignore: Option<ColliderId>,
+    ) -> Option<(ColliderId, SweepHit)> {
+        self.candidates(mask, ignore)
+            .filter_map(|(id, c)| shape.sweep(motion, &c.shape).map(|hit| (id, hit)))
+            .min_by(|a, b| a.1.toi.total_cmp(&b.1.toi))
+    }
+
+    /// Moves collider `id` by `motion`, stopping at obstacles and sliding the rest of the
+    /// motion along their surfaces, then pushes it out of anything it still overlaps.
+    /// Hits are sorted into floor, wall and ceiling using `up` and `floor_max_angle`.
+    pub fn move_and_slide(&mut self, id: ColliderId, motion: Vec2) -> SlideResult {
+        let mut result = SlideResult::default();
+        let Some(&collider) = self.get(id) else {
+            return result;
+        };
+        let floor_cos = self.floor_max_angle.cos();
+        let mut shape = collider.shape;
+        let mut remaining = motion;
+
+        for _ in 0..self.max_slides {
+            let len = remaining.magnitude();
+            if len < 1e-6 {
+                break;
+            }
+            let Some((hit_id, hit)) = self.sweep(&shape, remaining, collider.mask, Some(id)) else {
+                shape = shape.translated(remaining);
+                result.moved += remaining;
+                break;
+            };
+
+            let travel = (hit.toi - self.skin / len).max(0.0);
+            shape = shape.translated(remaining * travel);
+            result.moved += remaining * travel;
+            result.hits.push(hit_id);
+            self.classify(hit.normal, floor_cos, &mut result);
+
+            remaining *= 1.0 - travel;
+            let into = remaining.dot(hit.normal);
+            if into < 0.0 {
+                remaining -= hit.normal * into;
+            }
+        }
+
+        for (other, contact) in self.penetrations(&shape, collider.mask, id) {
+            shape = shape.translated(contact.normal * contact.depth);
+            result.moved += contact.normal * contact.depth;
+            if !result.hits.contains(&other) {
+                result.hits.push(other);
+            }
+            self.classify(contact.normal, floor_cos, &mut result);
+        }
+
+        if let Some(c) = self.get_mut(id) {
+            c.shape = shape;
+        }
+        result
+    }
+
+    fn penetrations(&self, shape: &Shape, mask: u32, id: ColliderId) -> Vec<(ColliderId, Contact)> {
+        self.candidates(mask, Some(id))
+            .filter_map(|(other, c)| c.shape.contact(shape).map(|contact| (other, contact)))
+            .collect()
+    }
+
+    fn classify(&self, normal: Vec2, floor_cos: f32, result: &mut SlideResult) {
+        let d = normal.dot(self.up);
+        if d >= floor_cos {
+            result.on_floor = true;
+        } else if d <= -floor_cos {
+            result.on_ceiling = true;
+        } else {
+            result.on_wall = true;
+        }
+    }
+}
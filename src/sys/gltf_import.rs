@@ -0,0 +1,347 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{bail, Context};
+use base64::Engine;
+use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, SquareMatrix, Vector3, Vector4};
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::gfx::wgpu::{texture::FLAT_NORMAL, vertex::Vertex3D};
+
+use super::{
+    baked::{BakedMaterial, BakedMesh, BakedModel},
+    fs::compute_tangents,
+    import::entry_name,
+    meshopt::optimize_mesh,
+};
+
+/// A glTF model plus the textures that have no file of their own: images embedded in the
+/// glTF and solid colors for materials without a texture. import_dir packs them next to
+/// the model under the names its materials reference, "<model>#<suffix>".
+pub struct GltfImport {
+    pub model: BakedModel,
+    pub textures: Vec<(String, DynamicImage)>,
+}
+
+/// Parses a .gltf or .glb with its buffers, from the GLB blob, data URIs or files next to
+/// it. Every mesh instance in the default scene becomes one BakedMesh per primitive with
+/// the node transform applied. Base color and normal textures become the diffuse and
+/// normal textures, external images are referenced by their pack entry names.
+pub fn import_gltf(root: &Path, path: &Path) -> anyhow::Result<GltfImport> {
+    let gltf::Gltf { document, mut blob } = gltf::Gltf::from_slice(&std::fs::read(path)?)?;
+    let dir = path.parent().unwrap_or(root);
+    let buffers = document
+        .buffers()
+        .map(|b| load_buffer(dir, &b, &mut blob))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut import = Importer {
+        root,
+        dir,
+        entry: entry_name(root, path),
+        buffers,
+        images: HashMap::new(),
+        textures: Vec::new(),
+    };
+
+    let mut materials = document
+        .materials()
+        .map(|m| import.material(&m))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    // Primitives without a material use the spec's default, plain white.
+    let default_material = materials.len() as u32;
+    let mut uses_default = false;
+
+    let mut meshes = Vec::new();
+    let mut instances = Vec::new();
+    match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(scene) => {
+            for node in scene.nodes() {
+                collect_instances(&node, Matrix4::identity(), &mut instances);
+            }
+        }
+        None => instances.extend(document.meshes().map(|m| (m, Matrix4::identity()))),
+    }
+    for (mesh, transform) in instances {
+        let name = mesh
+            .name()
+            .map_or_else(|| format!("mesh{}", mesh.index()), str::to_string);
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                log::warn!(
+                    "import_gltf => '{}': skipping {:?} primitive, only triangles are supported",
+                    name,
+                    primitive.mode()
+                );
+                continue;
+            }
+            let material = match primitive.material().index() {
+                Some(i) => i as u32,
+                None => {
+                    uses_default = true;
+                    default_material
+                }
+            };
+            let (vertices, indices) = import.primitive(&primitive, transform)?;
+            meshes.push(BakedMesh {
+                name: name.clone(),
+                material,
+                vertices,
+                indices,
+            });
+        }
+    }
+    if uses_default {
+        materials.push(BakedMaterial {
+            name: "default".to_string(),
+            diffuse_texture: import.solid("default.diffuse", [255; 4]),
+            normal_texture: import.solid("default.normal", FLAT_NORMAL),
+        });
+    }
+
+    Ok(GltfImport {
+        model: BakedModel { meshes, materials },
+        textures: import.textures,
+    })
+}
+
+struct Importer<'a> {
+    root: &'a Path,
+    dir: &'a Path,
+    /// Entry name of the glTF itself, the prefix of generated texture names.
+    entry: String,
+    buffers: Vec<Vec<u8>>,
+    /// Entry names of images already resolved, by image index.
+    images: HashMap<usize, String>,
+    textures: Vec<(String, DynamicImage)>,
+}
+
+impl Importer<'_> {
+    fn material(&mut self, m: &gltf::Material) -> anyhow::Result<BakedMaterial> {
+        let index = m.index().unwrap_or_default();
+        let name = m
+            .name()
+            .map_or_else(|| format!("material{}", index), str::to_string);
+        let diffuse_texture = match m.pbr_metallic_roughness().base_color_texture() {
+            Some(info) => self.image(&info.texture().source())?,
+            None => {
+                let color = m.pbr_metallic_roughness().base_color_factor();
+                let color = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+                self.solid(&format!("material{}.diffuse", index), color)
+            }
+        };
+        let normal_texture = match m.normal_texture() {
+            Some(info) => self.image(&info.texture().source())?,
+            None => self.solid(&format!("material{}.normal", index), FLAT_NORMAL),
+        };
+        Ok(BakedMaterial {
+            name,
+            diffuse_texture,
+            normal_texture,
+        })
+    }
+
+    /// Entry name of an image, decoding embedded ones into `textures`.
+    fn image(&mut self, image: &gltf::Image) -> anyhow::Result<String> {
+        if let Some(name) = self.images.get(&image.index()) {
+            return Ok(name.clone());
+        }
+        let bytes = match image.source() {
+            gltf::image::Source::Uri { uri, .. } if !uri.starts_with("data:") => {
+                let name = entry_name(self.root, &self.dir.join(percent_decode(uri)));
+                self.images.insert(image.index(), name.clone());
+                return Ok(name);
+            }
+            gltf::image::Source::Uri { uri, .. } => decode_data_uri(uri)?,
+            gltf::image::Source::View { view, .. } => {
+                let buffer = &self.buffers[view.buffer().index()];
+                buffer
+                    .get(view.offset()..view.offset() + view.length())
+                    .context("image view is out of its buffer's bounds")?
+                    .to_vec()
+            }
+        };
+        let decoded = image::load_from_memory(&bytes)
+            .with_context(|| format!("decoding image {}", image.index()))?;
+        let name = format!("{}#image{}", self.entry, image.index());
+        self.images.insert(image.index(), name.clone());
+        self.textures.push((name.clone(), decoded));
+        Ok(name)
+    }
+
+    /// Entry name of a 1x1 texture of `color`.
+    fn solid(&mut self, suffix: &str, color: [u8; 4]) -> String {
+        let name = format!("{}#{}", self.entry, suffix);
+        let image = RgbaImage::from_pixel(1, 1, Rgba(color));
+        self.textures
+            .push((name.clone(), DynamicImage::ImageRgba8(image)));
+        name
+    }
+
+    fn primitive(
+        &self,
+        primitive: &gltf::Primitive,
+        transform: Matrix4<f32>,
+    ) -> anyhow::Result<(Vec<Vertex3D>, Vec<u32>)> {
+        let reader = primitive.reader(|b| self.buffers.get(b.index()).map(Vec::as_slice));
+        let Some(positions) = reader.read_positions() else {
+            bail!(
+                "import_gltf => primitive {} has no positions",
+                primitive.index()
+            );
+        };
+        let normal_matrix = Matrix3::from_cols(
+            transform.x.truncate(),
+            transform.y.truncate(),
+            transform.z.truncate(),
+        );
+        let mirrored = normal_matrix.determinant() < 0.0;
+        let normal_matrix = normal_matrix
+            .invert()
+            .map_or(Matrix3::identity(), |m| m.transpose());
+
+        let mut vertices = positions
+            .map(|p| Vertex3D {
+                position: (transform * Vector4::new(p[0], p[1], p[2], 1.0))
+                    .truncate()
+                    .into(),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        if let Some(uvs) = reader.read_tex_coords(0) {
+            for (v, uv) in vertices.iter_mut().zip(uvs.into_f32()) {
+                v.tex_coords = uv;
+            }
+        }
+        let mut indices = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect::<Vec<_>>(),
+            None => (0..vertices.len() as u32).collect(),
+        };
+        if indices.iter().any(|&i| i as usize >= vertices.len()) {
+            bail!(
+                "import_gltf => primitive {} indexes past its vertices",
+                primitive.index()
+            );
+        }
+        indices.truncate(indices.len() / 3 * 3);
+        // A mirroring transform flips the winding, swap it back so culling still works.
+        if mirrored {
+            for tri in indices.chunks_exact_mut(3) {
+                tri.swap(1, 2);
+            }
+        }
+        match reader.read_normals() {
+            Some(normals) => {
+                for (v, n) in vertices.iter_mut().zip(normals) {
+                    v.normal = (normal_matrix * Vector3::from(n)).normalize().into();
+                }
+            }
+            None => smooth_normals(&mut vertices, &indices),
+        }
+
+        let (before, after) = optimize_mesh(&mut vertices, &mut indices, |v| v.position);
+        log::info!(
+            "import_gltf => '{}' primitive {}: {} -> {}",
+            self.entry,
+            primitive.index(),
+            before,
+            after
+        );
+        compute_tangents(&mut vertices, &indices);
+        Ok((vertices, indices))
+    }
+}
+
+fn collect_instances<'a>(
+    node: &gltf::Node<'a>,
+    parent: Matrix4<f32>,
+    out: &mut Vec<(gltf::Mesh<'a>, Matrix4<f32>)>,
+) {
+    let transform = parent * Matrix4::from(node.transform().matrix());
+    if let Some(mesh) = node.mesh() {
+        out.push((mesh, transform));
+    }
+    for child in node.children() {
+        collect_instances(&child, transform, out);
+    }
+}
+
+/// The spec leaves missing normals to the loader, average the face normals around each
+/// vertex.
+fn smooth_normals(vertices: &mut [Vertex3D], indices: &[u32]) {
+    let mut sums = vec![Vector3::new(0.0f32, 0.0, 0.0); vertices.len()];
+    for tri in indices.chunks_exact(3) {
+        let [a, b, c] =
+            [tri[0], tri[1], tri[2]].map(|i| Vector3::from(vertices[i as usize].position));
+        let face = (b - a).cross(c - a);
+        for &i in tri {
+            sums[i as usize] += face;
+        }
+    }
+    for (v, n) in vertices.iter_mut().zip(sums) {
+        if n.magnitude2() > 0.0 {
+            v.normal = n.normalize().into();
+        }
+    }
+}
+
+fn load_buffer(
+    dir: &Path,
+    buffer: &gltf::Buffer,
+    blob: &mut Option<Vec<u8>>,
+) -> anyhow::Result<Vec<u8>> {
+    let data = match buffer.source() {
+        gltf::buffer::Source::Bin => blob.take().context("GLB has no binary chunk")?,
+        gltf::buffer::Source::Uri(uri) if uri.starts_with("data:") => decode_data_uri(uri)?,
+        gltf::buffer::Source::Uri(uri) => {
+            let path = dir.join(percent_decode(uri));
+            std::fs::read(&path).with_context(|| format!("reading {}", path.display()))?
+        }
+    };
+    if data.len() < buffer.length() {
+        bail!(
+            "buffer {} is {} bytes, expected {}",
+            buffer.index(),
+            data.len(),
+            buffer.length()
+        );
+    }
+    Ok(data)
+}
+
+/// Payload of a base64 data URI, e.g. "data:application/octet-stream;base64,AAAA".
+fn decode_data_uri(uri: &str) -> anyhow::Result<Vec<u8>> {
+    let Some((header, data)) = uri.split_once(',') else {
+        bail!("malformed data URI");
+    };
+    if !header.ends_with(";base64") {
+        bail!("only base64 data URIs are supported");
+    }
+    Ok(base64::engine::general_purpose::STANDARD.decode(data)?)
+}
+
+/// Relative URIs in glTF are percent encoded, e.g. "my%20texture.png".
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
@@ -0,0 +1,283 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use image::DynamicImage;
+
+use crate::gfx::wgpu::vertex::Vertex3D;
+
+use super::{
+    baked::{BakedMaterial, BakedMesh, BakedModel, BakedTexture},
+    fs::compute_tangents,
+    meshopt::optimize_mesh,
+    pack::{AssetKind, PackWriter},
+};
+
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "bmp", "tga"];
+/// Consumed while importing other assets, not packed on their own.
+const SKIPPED_EXTENSIONS: &[&str] = &["mtl"];
+const FONT_EXTENSIONS: &[&str] = &["ttf", "otf"];
+const GLTF_EXTENSIONS: &[&str] = &["gltf", "glb"];
+
+/// What import_dir does to the source files, see the asset-import flags in main.rs.
+#[derive(Debug, Clone)]
+pub struct ImportSettings {
+    /// Block compress textures, see BakedTexture::compress. Textures whose size isn't a
+    /// multiple of 4 stay RGBA8.
+    pub compress_textures: bool,
+}
+
+impl Default for ImportSettings {
+    fn default() -> Self {
+        Self {
+            compress_textures: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    pub textures: usize,
+    /// Textures stored block compressed, the rest are RGBA8.
+    pub compressed: usize,
+    pub models: usize,
+    pub fonts: usize,
+    pub raw: usize,
+    /// Entry names of files that could not be baked, with the reason.
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Bakes every asset under `src` into one asset pack at `out`. Images get a mip chain and
+/// are block compressed, obj and glTF models get precomputed tangents, fonts get an MSDF
+/// atlas, other files are stored as is. Entries are named by their path relative to `src` with '/' separators, e.g.
+/// "cube-diffuse.jpg".
+pub fn import_dir(
+    src: &Path,
+    out: &Path,
+    settings: &ImportSettings,
+) -> anyhow::Result<ImportReport> {
+    let mut files = Vec::new();
+    collect_files(src, &mut files)?;
+    files.sort();
+
+    let mut pack = PackWriter::new();
+    let mut report = ImportReport::default();
+    for path in files {
+        let name = entry_name(src, &path);
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase)
+            .unwrap_or_default();
+
+        // One broken file shouldn't stop the rest of the import.
+        match bake_file(src, &path, &name, &ext, settings, &mut report) {
+            Ok(entries) => {
+                for (name, kind, bytes) in entries {
+                    match kind {
+                        AssetKind::Texture => report.textures += 1,
+                        AssetKind::Mesh => report.models += 1,
+                        AssetKind::Font => report.fonts += 1,
+                        _ => report.raw += 1,
+                    }
+                    pack.add(&name, kind, bytes);
+                }
+            }
+            Err(e) => {
+                log::warn!("import_dir => {} skipped: {:#}", name, e);
+                report.skipped.push((name, format!("{:#}", e)));
+            }
+        }
+    }
+
+    pack.write(out)?;
+    Ok(report)
+}
+
+/// Baked entries for one source file, usually just `name`. glTF models add their
+/// embedded textures, files only read while importing others add nothing.
+fn bake_file(
+    src: &Path,
+    path: &Path,
+    name: &str,
+    ext: &str,
+    settings: &ImportSettings,
+    report: &mut ImportReport,
+) -> anyhow::Result<Vec<(String, AssetKind, Vec<u8>)>> {
+    let (kind, bytes) = if IMAGE_EXTENSIONS.contains(&ext) {
+        let texture = bake_texture(&image::open(path)?, settings, report);
+        (AssetKind::Texture, texture)
+    } else if ext == "obj" {
+        (AssetKind::Mesh, import_obj(src, path)?.to_bytes())
+    } else if GLTF_EXTENSIONS.contains(&ext) {
+        return bake_gltf(src, path, name, settings, report);
+    } else if FONT_EXTENSIONS.contains(&ext) {
+        return bake_font(path, name);
+    } else if SKIPPED_EXTENSIONS.contains(&ext) {
+        return Ok(Vec::new());
+    } else {
+        (AssetKind::Raw, std::fs::read(path)?)
+    };
+    Ok(vec![(name.to_string(), kind, bytes)])
+}
+
+fn bake_texture(
+    img: &DynamicImage,
+    settings: &ImportSettings,
+    report: &mut ImportReport,
+) -> Vec<u8> {
+    let mut texture = BakedTexture::from_image(img);
+    if settings.compress_textures {
+        if let Some(compressed) = texture.compress() {
+            texture = compressed;
+            report.compressed += 1;
+        }
+    }
+    texture.to_bytes()
+}
+
+/// A BakedFont entry plus its MSDF atlas as "<font>#atlas". The atlas stays RGBA8 with a
+/// single mip, block compression and mips both smear the distances between channels.
+#[cfg(feature = "ttf")]
+fn bake_font(path: &Path, name: &str) -> anyhow::Result<Vec<(String, AssetKind, Vec<u8>)>> {
+    use super::{baked::TextureEncoding, msdf};
+
+    let page = format!("{}#atlas", name);
+    let (font, atlas) = msdf::bake_font(
+        &std::fs::read(path)?,
+        &page,
+        msdf::DEFAULT_PX,
+        msdf::DEFAULT_RANGE,
+        msdf::default_chars(),
+    )?;
+    let (width, height) = atlas.dimensions();
+    let atlas = BakedTexture {
+        width,
+        height,
+        encoding: TextureEncoding::Rgba8,
+        mips: vec![atlas.into_raw()],
+    };
+    Ok(vec![
+        (name.to_string(), AssetKind::Font, font.to_bytes()),
+        (page, AssetKind::Texture, atlas.to_bytes()),
+    ])
+}
+
+#[cfg(not(feature = "ttf"))]
+fn bake_font(_: &Path, _: &str) -> anyhow::Result<Vec<(String, AssetKind, Vec<u8>)>> {
+    bail!("font atlas baking needs the ttf feature");
+}
+
+#[cfg(feature = "gltf")]
+fn bake_gltf(
+    src: &Path,
+    path: &Path,
+    name: &str,
+    settings: &ImportSettings,
+    report: &mut ImportReport,
+) -> anyhow::Result<Vec<(String, AssetKind, Vec<u8>)>> {
+    let import = super::gltf_import::import_gltf(src, path)?;
+    let mut entries = vec![(name.to_string(), AssetKind::Mesh, import.model.to_bytes())];
+    for (name, img) in import.textures {
+        let texture = bake_texture(&img, settings, report);
+        entries.push((name, AssetKind::Texture, texture));
+    }
+    Ok(entries)
+}
+
+#[cfg(not(feature = "gltf"))]
+fn bake_gltf(
+    _: &Path,
+    _: &Path,
+    _: &str,
+    _: &ImportSettings,
+    _: &mut ImportReport,
+) -> anyhow::Result<Vec<(String, AssetKind, Vec<u8>)>> {
+    bail!("glTF import needs the gltf feature");
+}
+
+/// Parses an obj and its materials, referencing textures by their pack entry names.
+pub fn import_obj(root: &Path, path: &Path) -> anyhow::Result<BakedModel> {
+    let (models, obj_materials) = tobj::load_obj(
+        path,
+        &tobj::LoadOptions {
+            triangulate: true,
+            single_index: true,
+            ..Default::default()
+        },
+    )?;
+    let dir = path.parent().unwrap_or(root);
+
+    let mut materials = Vec::new();
+    for m in obj_materials? {
+        let (Some(diffuse), Some(normal)) = (&m.diffuse_texture, &m.normal_texture) else {
+            bail!(
+                "import_obj => Material {} needs a diffuse and normal texture",
+                m.name
+            );
+        };
+        materials.push(BakedMaterial {
+            name: m.name.clone(),
+            diffuse_texture: entry_name(root, &dir.join(diffuse)),
+            normal_texture: entry_name(root, &dir.join(normal)),
+        });
+    }
+
+    let meshes = models
+        .into_iter()
+        .map(|m| {
+            let mesh = &m.mesh;
+            let mut vertices = (0..mesh.positions.len() / 3)
+                .map(|i| Vertex3D {
+                    position: [
+                        mesh.positions[i * 3],
+                        mesh.positions[i * 3 + 1],
+                        mesh.positions[i * 3 + 2],
+                    ],
+                    tex_coords: mesh
+                        .texcoords
+                        .get(i * 2..i * 2 + 2)
+                        .map_or([0.0; 2], |uv| [uv[0], uv[1]]),
+                    normal: mesh
+                        .normals
+                        .get(i * 3..i * 3 + 3)
+                        .map_or([0.0; 3], |n| [n[0], n[1], n[2]]),
+                    ..Default::default()
+                })
+                .collect::<Vec<_>>();
+            let material = mesh.material_id.unwrap_or(0) as u32;
+            let mut indices = m.mesh.indices;
+            let (before, after) = optimize_mesh(&mut vertices, &mut indices, |v| v.position);
+            log::info!("import_obj => '{}': {} -> {}", m.name, before, after);
+            compute_tangents(&mut vertices, &indices);
+            BakedMesh {
+                name: m.name,
+                material,
+                vertices,
+                indices,
+            }
+        })
+        .collect();
+
+    Ok(BakedModel { meshes, materials })
+}
+
+/// Path of `path` relative to `root` with '/' separators.
+pub fn entry_name(root: &Path, path: &Path) -> String {
+    let rel = path.strip_prefix(root).unwrap_or(path);
+    rel.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,655 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, ensure};
+use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, Point3, SquareMatrix, Transform, Vector3};
+
+use crate::gfx::{light::LightUniform, wgpu::vertex::Vertex3D};
+
+use super::{
+    baked::{BakedTexture, TextureEncoding},
+    pack::ByteReader,
+    rect_pack::{PackStats, SkylinePacker},
+};
+
+pub const LIGHTMAP_MAGIC: [u8; 4] = *b"RLMP";
+pub const LIGHTMAP_VERSION: u32 = 1;
+/// Texels store light / LIGHTMAP_RANGE so surfaces lit brighter than the light color
+/// survive 8 bit storage. Mirrored in lightmap_common.wgsl.
+pub const LIGHTMAP_RANGE: f32 = 2.0;
+
+/// Empty texels around every chart so bilinear filtering never reads a neighbour's light.
+const CHART_BORDER: u32 = 1;
+const SHADOW_BIAS: f32 = 1e-3;
+/// Charts start out in a lightmap this size, doubling until they fit.
+const MIN_SIZE: u32 = 64;
+
+/// Trades bake time for lightmap resolution, filtering and shadows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BakeQuality {
+    /// Low resolution and no shadows, for iterating on a level.
+    Draft,
+    #[default]
+    Medium,
+    High,
+}
+
+impl BakeQuality {
+    /// Lightmap texels per world unit for 3D surfaces.
+    pub const fn texels_per_unit(self) -> f32 {
+        match self {
+            Self::Draft => 4.0,
+            Self::Medium => 8.0,
+            Self::High => 16.0,
+        }
+    }
+
+    /// Lightmap texels along each side of a tile for 2D maps.
+    pub const fn texels_per_tile(self) -> u32 {
+        match self {
+            Self::Draft => 2,
+            Self::Medium => 4,
+            Self::High => 8,
+        }
+    }
+
+    /// Each texel averages samples x samples points spread over its area.
+    pub const fn samples(self) -> u32 {
+        match self {
+            Self::Draft => 1,
+            Self::Medium => 2,
+            Self::High => 3,
+        }
+    }
+
+    pub const fn shadows(self) -> bool {
+        !matches!(self, Self::Draft)
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+}
+
+pub fn parse_bake_quality(name: &str) -> anyhow::Result<BakeQuality> {
+    Ok(match name {
+        "draft" => BakeQuality::Draft,
+        "medium" => BakeQuality::Medium,
+        "high" => BakeQuality::High,
+        _ => bail!("parse_bake_quality => unknown bake quality {}", name),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BakeSettings {
+    pub quality: BakeQuality,
+    /// Light every texel receives on top of the lights.
+    pub ambient: [f32; 3],
+    /// Largest lightmap edge in texels, bakes whose charts don't fit fail.
+    pub max_size: u32,
+}
+
+impl Default for BakeSettings {
+    fn default() -> Self {
+        Self {
+            quality: BakeQuality::default(),
+            ambient: [0.05; 3],
+            max_size: 2048,
+        }
+    }
+}
+
+impl BakeSettings {
+    pub fn with_quality(mut self, quality: BakeQuality) -> Self {
+        self.quality = quality;
+        self
+    }
+
+    pub fn with_ambient(mut self, ambient: [f32; 3]) -> Self {
+        self.ambient = ambient;
+        self
+    }
+
+    pub fn with_max_size(mut self, max_size: u32) -> Self {
+        self.max_size = max_size;
+        self
+    }
+}
+
+/// Static geometry to bake, e.g. a level's BakedMesh placed in the world.
+#[derive(Debug, Clone, Copy)]
+pub struct BakeSurface<'a> {
+    pub vertices: &'a [Vertex3D],
+    pub indices: &'a [u32],
+    /// Model to world transform of the geometry.
+    pub transform: Matrix4<f32>,
+    /// Whether the surface blocks light, for itself and every other surface.
+    pub casts_shadows: bool,
+}
+
+impl<'a> BakeSurface<'a> {
+    pub fn new(vertices: &'a [Vertex3D], indices: &'a [u32], transform: Matrix4<f32>) -> Self {
+        Self {
+            vertices,
+            indices,
+            transform,
+            casts_shadows: true,
+        }
+    }
+
+    pub fn with_shadows(mut self, casts_shadows: bool) -> Self {
+        self.casts_shadows = casts_shadows;
+        self
+    }
+}
+
+/// Lighting baked into one texture by bake_lightmap or bake_tiles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BakedLightmap {
+    /// Light / LIGHTMAP_RANGE without mips, alpha is 255 wherever a surface was baked.
+    pub texture: BakedTexture,
+    /// Lightmap uv of every index of every surface, in the order the surfaces were given
+    /// to bake_lightmap. Empty for tile lightmaps.
+    pub uvs: Vec<Vec<[f32; 2]>>,
+}
+
+impl BakedLightmap {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&LIGHTMAP_MAGIC);
+        out.extend_from_slice(&LIGHTMAP_VERSION.to_le_bytes());
+        let texture = self.texture.to_bytes();
+        out.extend_from_slice(&(texture.len() as u32).to_le_bytes());
+        out.extend_from_slice(&texture);
+        out.extend_from_slice(&(self.uvs.len() as u32).to_le_bytes());
+        for uvs in self.uvs.iter() {
+            out.extend_from_slice(&(uvs.len() as u32).to_le_bytes());
+            out.extend_from_slice(bytemuck::cast_slice(uvs));
+        }
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> anyhow::Result<Self> {
+        let mut r = ByteReader::new(data);
+        ensure!(
+            r.bytes(4)? == LIGHTMAP_MAGIC,
+            "BakedLightmap::from_bytes => Not a baked lightmap"
+        );
+        let version = r.u32()?;
+        ensure!(
+            version == LIGHTMAP_VERSION,
+            "BakedLightmap::from_bytes => Unsupported version {}",
+            version
+        );
+        let len = r.u32()? as usize;
+        let texture = BakedTexture::from_bytes(r.bytes(len)?)?;
+        let count = r.u32()?;
+        let mut uvs = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let len = r.u32()? as usize;
+            let bytes = r.bytes(len * std::mem::size_of::<[f32; 2]>())?;
+            uvs.push(bytemuck::pod_collect_to_vec(bytes));
+        }
+        Ok(Self { texture, uvs })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Triangle {
+    surface: usize,
+    /// Position of the triangle's first index in the surface's index list.
+    first: usize,
+    positions: [Point3<f32>; 3],
+    normals: [Vector3<f32>; 3],
+    face_normal: Vector3<f32>,
+}
+
+/// Coplanar, connected triangles unwrapped together onto their plane.
+#[derive(Debug, Clone)]
+struct Chart {
+    triangles: Vec<usize>,
+    axes: [Vector3<f32>; 2],
+    /// Lowest projected coordinate, in texels.
+    min: [f32; 2],
+    size: [u32; 2],
+}
+
+impl Chart {
+    fn project(&self, p: Point3<f32>, texels_per_unit: f32) -> [f32; 2] {
+        let p = p.to_homogeneous().truncate();
+        self.axes.map(|axis| p.dot(axis) * texels_per_unit)
+    }
+}
+
+/// Bakes the light reaching `surfaces` from `lights` into one lightmap, unwrapping each
+/// surface into charts of connected coplanar triangles. Lights are lit like in basic.wgsl,
+/// without distance falloff, so baked and realtime lighting match.
+pub fn bake_lightmap(
+    surfaces: &[BakeSurface],
+    lights: &[LightUniform],
+    settings: &BakeSettings,
+) -> anyhow::Result<(BakedLightmap, PackStats)> {
+    let quality = settings.quality;
+    let texels_per_unit = quality.texels_per_unit();
+    let triangles = world_triangles(surfaces);
+    let charts = build_charts(surfaces, &triangles, texels_per_unit);
+
+    let sizes = charts.iter().map(|c| c.size).collect::<Vec<_>>();
+    let mut size = MIN_SIZE.min(settings.max_size);
+    let (placements, stats) = loop {
+        let mut packer = SkylinePacker::new(size, size).with_padding(CHART_BORDER);
+        let placements = packer.pack(&sizes);
+        if placements.iter().all(Option::is_some) {
+            break (
+                placements.into_iter().flatten().collect::<Vec<_>>(),
+                packer.stats(),
+            );
+        }
+        if size >= settings.max_size {
+            bail!(
+                "bake_lightmap => Charts don't fit in a {0}x{0} lightmap, lower the quality or raise max_size",
+                settings.max_size
+            );
+        }
+        size = (size * 2).min(settings.max_size);
+    };
+    let [width, height] = [size, stats.used_height.max(1)];
+
+    let occluders = triangles
+        .iter()
+        .filter(|t| surfaces[t.surface].casts_shadows)
+        .map(|t| t.positions)
+        .collect::<Vec<_>>();
+    let occluders = if quality.shadows() {
+        &occluders[..]
+    } else {
+        &[]
+    };
+
+    let mut uvs = surfaces
+        .iter()
+        .map(|s| vec![[0.0; 2]; s.indices.len()])
+        .collect::<Vec<_>>();
+    let mut texels = vec![None; (width * height) as usize];
+    let samples = quality.samples();
+    for (chart, origin) in charts.iter().zip(placements) {
+        // Texel space coordinates of the chart's corners, texel centers sit at +0.5.
+        let offset = [0, 1].map(|i| (origin[i] + CHART_BORDER) as f32 - chart.min[i]);
+        let corners = chart
+            .triangles
+            .iter()
+            .map(|&t| {
+                let t = &triangles[t];
+                t.positions.map(|p| {
+                    let c = chart.project(p, texels_per_unit);
+                    [c[0] + offset[0], c[1] + offset[1]]
+                })
+            })
+            .collect::<Vec<_>>();
+        for (&t, corners) in chart.triangles.iter().zip(corners.iter()) {
+            let t = &triangles[t];
+            for (i, c) in corners.iter().enumerate() {
+                uvs[t.surface][t.first + i] = [c[0] / width as f32, c[1] / height as f32];
+            }
+        }
+
+        for ty in origin[1]..origin[1] + chart.size[1] {
+            for tx in origin[0]..origin[0] + chart.size[0] {
+                let mut sum = [0.0; 3];
+                let mut hits = 0;
+                for s in 0..samples * samples {
+                    let sample = [
+                        tx as f32 + ((s % samples) as f32 + 0.5) / samples as f32,
+                        ty as f32 + ((s / samples) as f32 + 0.5) / samples as f32,
+                    ];
+                    let hit = chart
+                        .triangles
+                        .iter()
+                        .zip(corners.iter())
+                        .find_map(|(&t, c)| barycentric(c, sample).map(|b| (&triangles[t], b)));
+                    let Some((t, b)) = hit else {
+                        continue;
+                    };
+                    let position = Point3::from_homogeneous(
+                        (0..3).map(|i| t.positions[i].to_homogeneous() * b[i]).sum(),
+                    );
+                    let normal = (0..3).map(|i| t.normals[i] * b[i]).sum::<Vector3<f32>>();
+                    let normal = if normal.magnitude2() > 1e-12 {
+                        normal.normalize()
+                    } else {
+                        t.face_normal
+                    };
+                    let light = light_point(position, normal, lights, occluders);
+                    for (sum, light) in sum.iter_mut().zip(light) {
+                        *sum += light;
+                    }
+                    hits += 1;
+                }
+                if hits > 0 {
+                    let texel = &mut texels[(ty * width + tx) as usize];
+                    *texel = Some(sum.map(|c| c / hits as f32));
+                }
+            }
+        }
+    }
+
+    let lightmap = BakedLightmap {
+        texture: encode(width, height, texels, settings.ambient),
+        uvs,
+    };
+    Ok((lightmap, stats))
+}
+
+/// Bakes a 2D tile map, `solid(x, y)` tiles block light. Tile (0, 0) is the bottom left one
+/// at the world origin. Lights use position.xy, with position.w as the radius their light
+/// fades out over, 0 for no falloff. Sample the result with tile_uv.
+pub fn bake_tiles(
+    size: [u32; 2],
+    tile_size: f32,
+    solid: impl Fn(u32, u32) -> bool,
+    lights: &[LightUniform],
+    settings: &BakeSettings,
+) -> BakedLightmap {
+    let quality = settings.quality;
+    let per_tile = quality.texels_per_tile();
+    let [width, height] = size.map(|s| (s * per_tile).max(1));
+    let texel_size = tile_size / per_tile as f32;
+    let samples = quality.samples();
+
+    let tile_at = |p: [f32; 2]| {
+        let [x, y] = p.map(|c| (c / tile_size).floor());
+        (x >= 0.0 && y >= 0.0 && (x as u32) < size[0] && (y as u32) < size[1])
+            .then_some((x as u32, y as u32))
+    };
+    let blocked = |from: [f32; 2], to: [f32; 2]| {
+        let own = tile_at(from);
+        let d = [to[0] - from[0], to[1] - from[1]];
+        let steps = ((d[0].hypot(d[1]) / (tile_size * 0.25)).ceil() as u32).max(1);
+        (1..steps).any(|i| {
+            let t = i as f32 / steps as f32;
+            match tile_at([from[0] + d[0] * t, from[1] + d[1] * t]) {
+                Some(tile) => Some(tile) != own && solid(tile.0, tile.1),
+                None => false,
+            }
+        })
+    };
+
+    let mut texels = vec![None; (width * height) as usize];
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0.0; 3];
+            for s in 0..samples * samples {
+                let p = [
+                    (x as f32 + ((s % samples) as f32 + 0.5) / samples as f32) * texel_size,
+                    (y as f32 + ((s / samples) as f32 + 0.5) / samples as f32) * texel_size,
+                ];
+                for light in lights {
+                    let to = [light.position[0], light.position[1]];
+                    let distance = (to[0] - p[0]).hypot(to[1] - p[1]);
+                    let radius = light.position[3];
+                    let falloff = if radius > 0.0 {
+                        (1.0 - distance / radius).max(0.0).powi(2)
+                    } else {
+                        1.0
+                    };
+                    if falloff <= 0.0 || (quality.shadows() && blocked(p, to)) {
+                        continue;
+                    }
+                    for (sum, color) in sum.iter_mut().zip(light.color) {
+                        *sum += color * falloff;
+                    }
+                }
+            }
+            // Rows are stored top down, tile rows count up from the bottom.
+            let row = height - 1 - y;
+            texels[(row * width + x) as usize] = Some(sum.map(|c| c / (samples * samples) as f32));
+        }
+    }
+
+    BakedLightmap {
+        texture: encode(width, height, texels, settings.ambient),
+        uvs: Vec::new(),
+    }
+}
+
+/// Lightmap uv of `world` on a tile lightmap from bake_tiles. Mirrored by lightmap_tile_uv
+/// in lightmap_common.wgsl.
+pub fn tile_uv(world: [f32; 2], size: [u32; 2], tile_size: f32) -> [f32; 2] {
+    let [w, h] = size.map(|s| s as f32 * tile_size);
+    [world[0] / w, 1.0 - world[1] / h]
+}
+
+fn world_triangles(surfaces: &[BakeSurface]) -> Vec<Triangle> {
+    let mut triangles = Vec::new();
+    for (surface_index, surface) in surfaces.iter().enumerate() {
+        let m = surface.transform;
+        let normal_matrix = Matrix3::from_cols(m.x.truncate(), m.y.truncate(), m.z.truncate())
+            .invert()
+            .map(|inv| inv.transpose())
+            .unwrap_or_else(Matrix3::identity);
+        for (t, tri) in surface.indices.chunks_exact(3).enumerate() {
+            let vertices = [0, 1, 2].map(|i| surface.vertices[tri[i] as usize]);
+            let positions = vertices.map(|v| m.transform_point(Point3::from(v.position)));
+            let face_normal = (positions[1] - positions[0]).cross(positions[2] - positions[0]);
+            if face_normal.magnitude2() < 1e-12 {
+                continue;
+            }
+            triangles.push(Triangle {
+                surface: surface_index,
+                first: t * 3,
+                positions,
+                normals: vertices.map(|v| normal_matrix * Vector3::from(v.normal)),
+                face_normal: face_normal.normalize(),
+            });
+        }
+    }
+    triangles
+}
+
+/// Groups triangles that share an edge and face the same way, then sizes each group's
+/// projection onto its plane.
+fn build_charts(
+    surfaces: &[BakeSurface],
+    triangles: &[Triangle],
+    texels_per_unit: f32,
+) -> Vec<Chart> {
+    let mut parent = (0..triangles.len()).collect::<Vec<_>>();
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut edges: HashMap<(usize, u32, u32), usize> = HashMap::new();
+    for (i, t) in triangles.iter().enumerate() {
+        let indices = &surfaces[t.surface].indices[t.first..t.first + 3];
+        for e in 0..3 {
+            let (a, b) = (indices[e], indices[(e + 1) % 3]);
+            let key = (t.surface, a.min(b), a.max(b));
+            match edges.get(&key) {
+                Some(&other) if triangles[other].face_normal.dot(t.face_normal) > 0.999 => {
+                    let (ra, rb) = (find(&mut parent, i), find(&mut parent, other));
+                    parent[ra] = rb;
+                }
+                Some(_) => {}
+                None => {
+                    edges.insert(key, i);
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..triangles.len() {
+        groups.entry(find(&mut parent, i)).or_default().push(i);
+    }
+    let mut groups = groups.into_values().collect::<Vec<_>>();
+    groups.sort_by_key(|g| g[0]);
+
+    groups
+        .into_iter()
+        .map(|group| {
+            let n = triangles[group[0]].face_normal;
+            let up = if n.y.abs() < 0.99 {
+                Vector3::unit_y()
+            } else {
+                Vector3::unit_x()
+            };
+            let x = up.cross(n).normalize();
+            let mut chart = Chart {
+                triangles: group,
+                axes: [x, n.cross(x)],
+                min: [f32::MAX; 2],
+                size: [0; 2],
+            };
+            let mut max = [f32::MIN; 2];
+            for &t in chart.triangles.iter() {
+                for p in triangles[t].positions {
+                    let c = chart.project(p, texels_per_unit);
+                    for i in 0..2 {
+                        chart.min[i] = chart.min[i].min(c[i]);
+                        max[i] = max[i].max(c[i]);
+                    }
+                }
+            }
+            chart.size = [0, 1].map(|i| (max[i] - chart.min[i]).ceil() as u32 + 2 * CHART_BORDER);
+            chart
+        })
+        .collect()
+}
+
+/// Barycentric coordinates of `p` in triangle `t`, None if it lies outside.
+fn barycentric(t: &[[f32; 2]; 3], p: [f32; 2]) -> Option<[f32; 3]> {
+    let [a, b, c] = *t;
+    let area = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+    if area.abs() < 1e-12 {
+        return None;
+    }
+    let w1 = ((p[0] - a[0]) * (c[1] - a[1]) - (p[1] - a[1]) * (c[0] - a[0])) / area;
+    let w2 = ((b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0])) / area;
+    let w0 = 1.0 - w1 - w2;
+    const EPS: f32 = -1e-4;
+    (w0 >= EPS && w1 >= EPS && w2 >= EPS).then_some([w0, w1, w2])
+}
+
+fn light_point(
+    position: Point3<f32>,
+    normal: Vector3<f32>,
+    lights: &[LightUniform],
+    occluders: &[[Point3<f32>; 3]],
+) -> [f32; 3] {
+    let mut out = [0.0; 3];
+    let origin = position + normal * SHADOW_BIAS;
+    for light in lights {
+        let [x, y, z, _] = light.position;
+        let to_light = Point3::new(x, y, z) - position;
+        let distance = to_light.magnitude();
+        if distance < 1e-6 {
+            continue;
+        }
+        let dir = to_light / distance;
+        let n_dot_l = normal.dot(dir);
+        if n_dot_l <= 0.0 {
+            continue;
+        }
+        if occluders
+            .iter()
+            .any(|t| ray_triangle(origin, dir, t).is_some_and(|hit| hit < distance))
+        {
+            continue;
+        }
+        for (out, color) in out.iter_mut().zip(light.color) {
+            *out += color * n_dot_l;
+        }
+    }
+    out
+}
+
+/// Möller-Trumbore, distance along `dir` to the triangle.
+fn ray_triangle(origin: Point3<f32>, dir: Vector3<f32>, t: &[Point3<f32>; 3]) -> Option<f32> {
+    let e1 = t[1] - t[0];
+    let e2 = t[2] - t[0];
+    let p = dir.cross(e2);
+    let det = e1.dot(p);
+    if det.abs() < 1e-9 {
+        return None;
+    }
+    let inv = 1.0 / det;
+    let s = origin - t[0];
+    let u = s.dot(p) * inv;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(e1);
+    let v = dir.dot(q) * inv;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let hit = e2.dot(q) * inv;
+    (hit > SHADOW_BIAS).then_some(hit)
+}
+
+/// Adds ambient light, grows baked texels into their empty neighbours so filtering at
+/// chart edges doesn't darken, then quantizes to RGBA8.
+fn encode(
+    width: u32,
+    height: u32,
+    mut texels: Vec<Option<[f32; 3]>>,
+    ambient: [f32; 3],
+) -> BakedTexture {
+    for texel in texels.iter_mut().flatten() {
+        for (c, ambient) in texel.iter_mut().zip(ambient) {
+            *c += ambient;
+        }
+    }
+    let baked = texels.iter().map(Option::is_some).collect::<Vec<_>>();
+    for _ in 0..CHART_BORDER {
+        let source = texels.clone();
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                let i = (y * width as i64 + x) as usize;
+                if source[i].is_some() {
+                    continue;
+                }
+                let mut sum = [0.0; 3];
+                let mut count = 0;
+                for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if nx < 0 || ny < 0 || nx >= width as i64 || ny >= height as i64 {
+                        continue;
+                    }
+                    if let Some(c) = source[(ny * width as i64 + nx) as usize] {
+                        for (sum, c) in sum.iter_mut().zip(c) {
+                            *sum += c;
+                        }
+                        count += 1;
+                    }
+                }
+                if count > 0 {
+                    texels[i] = Some(sum.map(|c| c / count as f32));
+                }
+            }
+        }
+    }
+
+    let mut data = Vec::with_capacity(texels.len() * 4);
+    for (texel, baked) in texels.iter().zip(baked) {
+        let [r, g, b] = texel.unwrap_or([0.0; 3]);
+        let alpha = if baked { 255 } else { 0 };
+        data.extend(
+            [r, g, b].map(|c| ((c / LIGHTMAP_RANGE).clamp(0.0, 1.0) * 255.0).round() as u8),
+        );
+        data.push(alpha);
+    }
+    BakedTexture {
+        width,
+        height,
+        encoding: TextureEncoding::Rgba8,
+        mips: vec![data],
+    }
+}
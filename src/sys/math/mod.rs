@@ -0,0 +1,14 @@
+pub mod noise;
+
+pub use radium_core::math::SAFE_FRAC_PI_2;
+
+const TEMP: u32 = 0;
+/// Remaps OpenGL clip space depth from [-1, 1] to wgpu's [0, 1], z' = 0.5 * z + 0.5 * w.
+/// Matrix4::new takes columns, so the 0.5 offset sits in the last one.
+#[rustfmt::skip]
+pub const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
@@ -0,0 +1,18 @@
+use image::{GrayImage, Luma};
+
+pub use radium_core::math::noise::*;
+
+/// Samples `f` at every pixel center with uv in 0..1 and maps -1..1 to 0..255, see
+/// Texture::from_noise to upload the result.
+pub fn bake_image(width: u32, height: u32, f: impl Fn(f32, f32) -> f32) -> GrayImage {
+    GrayImage::from_fn(width, height, |x, y| {
+        let u = (x as f32 + 0.5) / width as f32;
+        let v = (y as f32 + 0.5) / height as f32;
+        Luma([to_unorm8(f(u, v))])
+    })
+}
+
+/// Like bake_image but `f` already returns 0..1, e.g. ridged noise.
+pub fn bake_image_unorm(width: u32, height: u32, f: impl Fn(f32, f32) -> f32) -> GrayImage {
+    bake_image(width, height, |u, v| f(u, v) * 2.0 - 1.0)
+}
@@ -0,0 +1,290 @@
+use std::{collections::HashMap, fmt};
+
+/// Cache size the triangle order is optimized for.
+const CACHE_SIZE: usize = 32;
+/// Cache size used to measure a mesh, smaller than CACHE_SIZE to model worse hardware.
+const ANALYZE_CACHE_SIZE: usize = 16;
+
+/// Vertex count, triangle count and average cache miss ratio of a mesh.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MeshStats {
+    pub vertices: usize,
+    pub triangles: usize,
+    /// Transformed vertices per triangle, 0.5 is about the best a regular grid gets and 3 the worst.
+    pub acmr: f32,
+}
+
+impl MeshStats {
+    pub fn analyze(vertex_count: usize, indices: &[u32]) -> Self {
+        Self {
+            vertices: vertex_count,
+            triangles: indices.len() / 3,
+            acmr: acmr(indices, ANALYZE_CACHE_SIZE),
+        }
+    }
+}
+
+impl fmt::Display for MeshStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} vertices, {} triangles, acmr {:.3}",
+            self.vertices, self.triangles, self.acmr
+        )
+    }
+}
+
+/// Average cache miss ratio of `indices` on a FIFO cache of `cache_size` vertices.
+pub fn acmr(indices: &[u32], cache_size: usize) -> f32 {
+    let triangles = indices.len() / 3;
+    if triangles == 0 {
+        return 0.0;
+    }
+    let mut cache = std::collections::VecDeque::with_capacity(cache_size);
+    let mut misses = 0;
+    for &index in indices {
+        if !cache.contains(&index) {
+            misses += 1;
+            if cache.len() == cache_size {
+                cache.pop_front();
+            }
+            cache.push_back(index);
+        }
+    }
+    misses as f32 / triangles as f32
+}
+
+/// Merges vertices that are byte for byte identical and drops unreferenced ones.
+pub fn reindex<V: bytemuck::Pod>(vertices: &[V], indices: &mut [u32]) -> Vec<V> {
+    let mut unique = Vec::new();
+    let mut lookup: HashMap<&[u8], u32> = HashMap::new();
+    let mut remap = vec![u32::MAX; vertices.len()];
+    for index in indices.iter_mut() {
+        let old = *index as usize;
+        if remap[old] == u32::MAX {
+            let bytes = bytemuck::bytes_of(&vertices[old]);
+            remap[old] = *lookup.entry(bytes).or_insert_with(|| {
+                unique.push(vertices[old]);
+                unique.len() as u32 - 1
+            });
+        }
+        *index = remap[old];
+    }
+    unique
+}
+
+/// Reorders triangles for the post transform cache with Tom Forsyth's linear speed algorithm.
+pub fn optimize_vertex_cache(indices: &[u32], vertex_count: usize) -> Vec<u32> {
+    let triangle_count = indices.len() / 3;
+    if triangle_count == 0 {
+        return indices.to_vec();
+    }
+
+    // Triangles using each vertex, flattened with per vertex offsets.
+    let mut remaining = vec![0u32; vertex_count];
+    for &index in indices.iter() {
+        remaining[index as usize] += 1;
+    }
+    let mut offsets = vec![0usize; vertex_count + 1];
+    for v in 0..vertex_count {
+        offsets[v + 1] = offsets[v] + remaining[v] as usize;
+    }
+    let mut adjacency = vec![0u32; indices.len()];
+    let mut filled = offsets.clone();
+    for (t, triangle) in indices.chunks_exact(3).enumerate() {
+        for &v in triangle {
+            adjacency[filled[v as usize]] = t as u32;
+            filled[v as usize] += 1;
+        }
+    }
+
+    let mut cache_position = vec![usize::MAX; vertex_count];
+    let mut vertex_score: Vec<f32> = (0..vertex_count)
+        .map(|v| forsyth_score(usize::MAX, remaining[v]))
+        .collect();
+    let mut triangle_score: Vec<f32> = indices
+        .chunks_exact(3)
+        .map(|t| t.iter().map(|&v| vertex_score[v as usize]).sum())
+        .collect();
+    let mut emitted = vec![false; triangle_count];
+    let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE + 3);
+    let mut output = Vec::with_capacity(indices.len());
+    // Next triangle to consider when the cache has nothing left to offer.
+    let mut scan = 0;
+
+    let best_in =
+        |candidates: &mut dyn Iterator<Item = usize>, emitted: &[bool], triangle_score: &[f32]| {
+            candidates
+                .filter(|&t| !emitted[t])
+                .max_by(|&a, &b| triangle_score[a].total_cmp(&triangle_score[b]))
+        };
+    let mut best = best_in(&mut (0..triangle_count), &emitted, &triangle_score);
+
+    while let Some(t) = best {
+        emitted[t] = true;
+        let triangle = &indices[t * 3..t * 3 + 3];
+        output.extend_from_slice(triangle);
+
+        for &v in triangle {
+            let v = v as usize;
+            let range = offsets[v]..offsets[v] + remaining[v] as usize;
+            if let Some(pos) = adjacency[range.clone()].iter().position(|&a| a == t as u32) {
+                adjacency.swap(range.start + pos, range.end - 1);
+                remaining[v] -= 1;
+            }
+        }
+
+        // Most recently used first, vertices pushed past CACHE_SIZE get rescored too.
+        let mut updated: Vec<u32> = triangle.to_vec();
+        for &v in cache.iter() {
+            if !triangle.contains(&v) {
+                updated.push(v);
+            }
+        }
+        for (position, &v) in updated.iter().enumerate() {
+            let v = v as usize;
+            cache_position[v] = if position < CACHE_SIZE {
+                position
+            } else {
+                usize::MAX
+            };
+            let score = forsyth_score(cache_position[v], remaining[v]);
+            let delta = score - vertex_score[v];
+            vertex_score[v] = score;
+            for &a in &adjacency[offsets[v]..offsets[v] + remaining[v] as usize] {
+                triangle_score[a as usize] += delta;
+            }
+        }
+        updated.truncate(CACHE_SIZE);
+        cache = updated;
+
+        let mut candidates = cache.iter().flat_map(|&v| {
+            let v = v as usize;
+            adjacency[offsets[v]..offsets[v] + remaining[v] as usize]
+                .iter()
+                .map(|&a| a as usize)
+        });
+        best = best_in(&mut candidates, &emitted, &triangle_score);
+        if best.is_none() {
+            while scan < triangle_count && emitted[scan] {
+                scan += 1;
+            }
+            best = (scan < triangle_count).then_some(scan);
+        }
+    }
+    output
+}
+
+fn forsyth_score(cache_position: usize, remaining: u32) -> f32 {
+    const CACHE_DECAY_POWER: f32 = 1.5;
+    const LAST_TRIANGLE_SCORE: f32 = 0.75;
+    const VALENCE_BOOST_SCALE: f32 = 2.0;
+    const VALENCE_BOOST_POWER: f32 = 0.5;
+
+    if remaining == 0 {
+        return -1.0;
+    }
+    let cache_score = match cache_position {
+        usize::MAX => 0.0,
+        // The last triangle's vertices get a fixed score so it isn't simply repeated.
+        p if p < 3 => LAST_TRIANGLE_SCORE,
+        p => (1.0 - (p - 3) as f32 / (CACHE_SIZE - 3) as f32).powf(CACHE_DECAY_POWER),
+    };
+    cache_score + VALENCE_BOOST_SCALE * (remaining as f32).powf(-VALENCE_BOOST_POWER)
+}
+
+/// Sorts runs of cache optimized triangles so outward facing ones are drawn first and
+/// occlude the rest. Runs are split where the cache would start over, which keeps most of
+/// the vertex cache efficiency.
+pub fn optimize_overdraw(indices: &[u32], positions: &[[f32; 3]]) -> Vec<u32> {
+    let triangles: Vec<&[u32]> = indices.chunks_exact(3).collect();
+    if triangles.len() < 2 {
+        return indices.to_vec();
+    }
+
+    // A new cluster starts at each triangle whose vertices all miss the cache.
+    let mut clusters: Vec<std::ops::Range<usize>> = Vec::new();
+    let mut cache = std::collections::VecDeque::with_capacity(CACHE_SIZE);
+    for (t, triangle) in triangles.iter().enumerate() {
+        let mut misses = 0;
+        for index in triangle.iter() {
+            if !cache.contains(index) {
+                misses += 1;
+                if cache.len() == CACHE_SIZE {
+                    cache.pop_front();
+                }
+                cache.push_back(*index);
+            }
+        }
+        match clusters.last_mut() {
+            Some(cluster) if misses < 3 => cluster.end = t + 1,
+            _ => clusters.push(t..t + 1),
+        }
+    }
+
+    let position = |i: u32| cgmath::Vector3::from(positions[i as usize]);
+    let mesh_center = indices
+        .iter()
+        .map(|&i| position(i))
+        .sum::<cgmath::Vector3<f32>>()
+        / indices.len() as f32;
+    // Larger means further out and facing away from the center.
+    let sort_key = |cluster: &std::ops::Range<usize>| {
+        use cgmath::InnerSpace;
+        let mut center = cgmath::Vector3::new(0.0, 0.0, 0.0);
+        let mut normal = cgmath::Vector3::new(0.0, 0.0, 0.0);
+        for triangle in &triangles[cluster.clone()] {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(position);
+            // Area weighted, the cross product's length is twice the area.
+            normal += (b - a).cross(c - a);
+            center += (a + b + c) / 3.0;
+        }
+        center /= cluster.len() as f32;
+        if normal.magnitude2() > 0.0 {
+            normal = normal.normalize();
+        }
+        (center - mesh_center).dot(normal)
+    };
+    let mut keyed: Vec<(f32, std::ops::Range<usize>)> =
+        clusters.into_iter().map(|c| (sort_key(&c), c)).collect();
+    keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    keyed
+        .into_iter()
+        .flat_map(|(_, cluster)| triangles[cluster].concat())
+        .collect()
+}
+
+/// Reorders vertices by first use so the vertex fetch reads memory in order.
+/// Unreferenced vertices are dropped.
+pub fn optimize_vertex_fetch<V: Copy>(vertices: &[V], indices: &mut [u32]) -> Vec<V> {
+    let mut remap = vec![u32::MAX; vertices.len()];
+    let mut ordered = Vec::with_capacity(vertices.len());
+    for index in indices.iter_mut() {
+        let old = *index as usize;
+        if remap[old] == u32::MAX {
+            remap[old] = ordered.len() as u32;
+            ordered.push(vertices[old]);
+        }
+        *index = remap[old];
+    }
+    ordered
+}
+
+/// Reindexes and reorders a mesh in place so the post transform cache is hit more often,
+/// outward facing triangles are drawn first and vertices are fetched in order. Returns the
+/// stats before and after.
+pub fn optimize_mesh<V: bytemuck::Pod>(
+    vertices: &mut Vec<V>,
+    indices: &mut Vec<u32>,
+    position: impl Fn(&V) -> [f32; 3],
+) -> (MeshStats, MeshStats) {
+    let before = MeshStats::analyze(vertices.len(), indices);
+    let unique = reindex(vertices, indices);
+    let mut optimized = optimize_vertex_cache(indices, unique.len());
+    let positions: Vec<[f32; 3]> = unique.iter().map(position).collect();
+    optimized = optimize_overdraw(&optimized, &positions);
+    *vertices = optimize_vertex_fetch(&unique, &mut optimized);
+    *indices = optimized;
+    (before, MeshStats::analyze(vertices.len(), indices))
+}
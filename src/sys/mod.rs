@@ -1,6 +1,21 @@
+pub mod baked;
+pub mod bc;
+pub mod cubemap;
 pub mod fs;
+pub mod geom;
+#[cfg(feature = "gltf")]
+pub mod gltf_import;
+pub mod import;
+pub mod lightmap;
 pub mod math;
 pub mod mem;
+pub mod meshopt;
+#[cfg(feature = "ttf")]
+pub mod msdf;
+pub mod pack;
+pub mod rand;
+pub mod rect_pack;
+pub mod triple_buffer;
 
 /// Readonly
 pub mod ro {
@@ -0,0 +1,645 @@
+use std::collections::HashMap;
+
+use ab_glyph::{Font, FontRef, OutlineCurve, PxScale, ScaleFont};
+use anyhow::ensure;
+use cgmath::{InnerSpace, Vector2};
+use image::RgbaImage;
+
+use crate::gfx::font::{BmChar, BmFont};
+
+use super::{baked::BakedFont, rect_pack::SkylinePacker};
+
+/// Em size in pixels the importer bakes font atlases at, see GlyphAtlas::new for how it
+/// maps to font metrics.
+pub const DEFAULT_PX: f32 = 48.0;
+/// Width of the band around glyph edges the distance field covers, in atlas pixels.
+pub const DEFAULT_RANGE: f32 = 4.0;
+
+/// Atlases start out this size, doubling until every glyph fits.
+const MIN_ATLAS_SIZE: u32 = 128;
+const MAX_ATLAS_SIZE: u32 = 4096;
+/// Edges meeting at a sharper angle than this, in radians, are a corner.
+const CORNER_ANGLE: f32 = 3.0;
+/// Pieces each curve is cut into for the inside test and the orientation.
+const FLATTEN_STEPS: usize = 16;
+/// Samples picking the starting point of the nearest point search on curves.
+const SEARCH_STEPS: usize = 8;
+const NEWTON_STEPS: usize = 4;
+
+/// Channels an edge writes its distance to, as bits of red, green and blue.
+pub const RED: u8 = 1;
+pub const GREEN: u8 = 2;
+pub const BLUE: u8 = 4;
+pub const YELLOW: u8 = RED | GREEN;
+pub const MAGENTA: u8 = RED | BLUE;
+pub const CYAN: u8 = GREEN | BLUE;
+pub const WHITE: u8 = RED | GREEN | BLUE;
+
+type Vec2 = Vector2<f32>;
+
+/// Printable ASCII and Latin-1, what the importer bakes when nothing else is asked for.
+pub fn default_chars() -> impl Iterator<Item = char> {
+    (' '..='~').chain('\u{a0}'..='\u{ff}')
+}
+
+/// One piece of a glyph outline, in shape space with y growing upwards.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Segment {
+    Line([Vec2; 2]),
+    Quad([Vec2; 3]),
+    Cubic([Vec2; 4]),
+}
+
+impl Segment {
+    pub fn start(&self) -> Vec2 {
+        match self {
+            Self::Line(p) => p[0],
+            Self::Quad(p) => p[0],
+            Self::Cubic(p) => p[0],
+        }
+    }
+
+    pub fn end(&self) -> Vec2 {
+        match self {
+            Self::Line(p) => p[1],
+            Self::Quad(p) => p[2],
+            Self::Cubic(p) => p[3],
+        }
+    }
+
+    pub fn point(&self, t: f32) -> Vec2 {
+        let s = 1.0 - t;
+        match *self {
+            Self::Line([a, b]) => a * s + b * t,
+            Self::Quad([a, b, c]) => a * (s * s) + b * (2.0 * s * t) + c * (t * t),
+            Self::Cubic([a, b, c, d]) => {
+                a * (s * s * s) + b * (3.0 * s * s * t) + c * (3.0 * s * t * t) + d * (t * t * t)
+            }
+        }
+    }
+
+    /// Tangent at `t`, falling back to the chord where control points coincide with the
+    /// end points and the derivative vanishes.
+    pub fn direction(&self, t: f32) -> Vec2 {
+        let d = self.derivative(t);
+        if d.magnitude2() > 0.0 {
+            d
+        } else {
+            self.end() - self.start()
+        }
+    }
+
+    fn derivative(&self, t: f32) -> Vec2 {
+        let s = 1.0 - t;
+        match *self {
+            Self::Line([a, b]) => b - a,
+            Self::Quad([a, b, c]) => (b - a) * (2.0 * s) + (c - b) * (2.0 * t),
+            Self::Cubic([a, b, c, d]) => {
+                (b - a) * (3.0 * s * s) + (c - b) * (6.0 * s * t) + (d - c) * (3.0 * t * t)
+            }
+        }
+    }
+
+    fn second_derivative(&self, t: f32) -> Vec2 {
+        match *self {
+            Self::Line(_) => Vec2::new(0.0, 0.0),
+            Self::Quad([a, b, c]) => (a - b * 2.0 + c) * 2.0,
+            Self::Cubic([a, b, c, d]) => {
+                (a - b * 2.0 + c) * (6.0 * (1.0 - t)) + (b - c * 2.0 + d) * (6.0 * t)
+            }
+        }
+    }
+
+    /// The two halves at `t`, by de Casteljau.
+    pub fn split(&self, t: f32) -> (Self, Self) {
+        let lerp = |a: Vec2, b: Vec2| a + (b - a) * t;
+        match *self {
+            Self::Line([a, b]) => {
+                let m = lerp(a, b);
+                (Self::Line([a, m]), Self::Line([m, b]))
+            }
+            Self::Quad([a, b, c]) => {
+                let (ab, bc) = (lerp(a, b), lerp(b, c));
+                let m = lerp(ab, bc);
+                (Self::Quad([a, ab, m]), Self::Quad([m, bc, c]))
+            }
+            Self::Cubic([a, b, c, d]) => {
+                let (ab, bc, cd) = (lerp(a, b), lerp(b, c), lerp(c, d));
+                let (abc, bcd) = (lerp(ab, bc), lerp(bc, cd));
+                let m = lerp(abc, bcd);
+                (Self::Cubic([a, ab, abc, m]), Self::Cubic([m, bcd, cd, d]))
+            }
+        }
+    }
+
+    fn thirds(&self) -> [Self; 3] {
+        let (first, rest) = self.split(1.0 / 3.0);
+        let (second, third) = rest.split(0.5);
+        [first, second, third]
+    }
+
+    fn control_points(&self) -> &[Vec2] {
+        match self {
+            Self::Line(p) => p,
+            Self::Quad(p) => p,
+            Self::Cubic(p) => p,
+        }
+    }
+
+    /// Signed distance from `p` to the nearest point on the segment and where that point
+    /// is. Points to the right of the direction of travel are positive.
+    fn distance(&self, p: Vec2) -> (SignedDistance, f32) {
+        let t = match *self {
+            Self::Line([a, b]) => {
+                let ab = b - a;
+                let len2 = ab.magnitude2();
+                if len2 > 0.0 {
+                    ((p - a).dot(ab) / len2).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                }
+            }
+            _ => {
+                let mut t = (0..=SEARCH_STEPS)
+                    .map(|i| i as f32 / SEARCH_STEPS as f32)
+                    .min_by(|&a, &b| {
+                        let da = (self.point(a) - p).magnitude2();
+                        let db = (self.point(b) - p).magnitude2();
+                        da.total_cmp(&db)
+                    })
+                    .unwrap_or(0.0);
+                for _ in 0..NEWTON_STEPS {
+                    let q = self.point(t) - p;
+                    let d1 = self.derivative(t);
+                    let f = q.dot(d1);
+                    let df = d1.dot(d1) + q.dot(self.second_derivative(t));
+                    if df.abs() < 1e-12 {
+                        break;
+                    }
+                    t = (t - f / df).clamp(0.0, 1.0);
+                }
+                t
+            }
+        };
+        let v = p - self.point(t);
+        let dir = self.direction(t);
+        let distance = v.magnitude();
+        let sign = if v.perp_dot(dir) < 0.0 { -1.0 } else { 1.0 };
+        let dot = if distance > 0.0 && dir.magnitude2() > 0.0 {
+            dir.normalize().dot(v / distance).abs()
+        } else {
+            0.0
+        };
+        (
+            SignedDistance {
+                distance: sign * distance,
+                dot,
+            },
+            t,
+        )
+    }
+
+    /// Distance to the tangent line past the end the nearest point sits on, if that is
+    /// closer. Keeps corners sharp where two channels meet.
+    fn pseudo_distance(&self, p: Vec2, nearest: (SignedDistance, f32)) -> f32 {
+        let (sd, t) = nearest;
+        let (q, dir) = if t <= 0.0 {
+            (self.start(), self.direction(0.0))
+        } else if t >= 1.0 {
+            (self.end(), self.direction(1.0))
+        } else {
+            return sd.distance;
+        };
+        if dir.magnitude2() == 0.0 {
+            return sd.distance;
+        }
+        let dir = dir.normalize();
+        let v = p - q;
+        let along = v.dot(dir);
+        if (t <= 0.0 && along < 0.0) || (t >= 1.0 && along > 0.0) {
+            let pseudo = v.perp_dot(dir);
+            if pseudo.abs() <= sd.distance.abs() {
+                return pseudo;
+            }
+        }
+        sd.distance
+    }
+}
+
+/// Distance with a tie breaker for points equally far from two edges sharing an end
+/// point: the edge the point is more perpendicular to wins.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SignedDistance {
+    distance: f32,
+    /// |cos| of the angle between the edge and the direction to the point.
+    dot: f32,
+}
+
+impl SignedDistance {
+    const INFINITE: Self = Self {
+        distance: f32::MAX,
+        dot: 1.0,
+    };
+
+    fn closer_than(&self, other: &Self) -> bool {
+        let (a, b) = (self.distance.abs(), other.distance.abs());
+        a < b || (a == b && self.dot < other.dot)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Edge {
+    pub segment: Segment,
+    /// Channel bits, see Shape::color_edges.
+    pub color: u8,
+}
+
+/// Closed contours of a glyph outline, edges colored for multi-channel distance fields.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Shape {
+    pub contours: Vec<Vec<Edge>>,
+}
+
+impl Shape {
+    /// Contours from closed loops of segments, each ending where it starts. Edges start out
+    /// white, call color_edges before generate.
+    pub fn new(contours: Vec<Vec<Segment>>) -> Self {
+        let contours = contours
+            .into_iter()
+            .map(|segments| {
+                segments
+                    .into_iter()
+                    .map(|segment| Edge {
+                        segment,
+                        color: WHITE,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .filter(|edges| !edges.is_empty())
+            .collect();
+        Self { contours }
+    }
+
+    /// Groups ab_glyph outline curves into contours, a new one starting wherever a curve
+    /// doesn't continue the last. Open contours are closed with a line, zero length lines
+    /// are dropped.
+    pub fn from_outline(curves: &[OutlineCurve]) -> Self {
+        let v = |p: ab_glyph::Point| Vec2::new(p.x, p.y);
+        let mut contours: Vec<Vec<Segment>> = Vec::new();
+        for curve in curves {
+            let segment = match *curve {
+                OutlineCurve::Line(a, b) => Segment::Line([v(a), v(b)]),
+                OutlineCurve::Quad(a, b, c) => Segment::Quad([v(a), v(b), v(c)]),
+                OutlineCurve::Cubic(a, b, c, d) => Segment::Cubic([v(a), v(b), v(c), v(d)]),
+            };
+            if segment
+                .control_points()
+                .iter()
+                .all(|&p| p == segment.start())
+            {
+                continue;
+            }
+            match contours.last_mut() {
+                Some(contour) if contour.last().map(Segment::end) == Some(segment.start()) => {
+                    contour.push(segment)
+                }
+                _ => contours.push(vec![segment]),
+            }
+        }
+        for contour in contours.iter_mut() {
+            let (start, end) = (contour[0].start(), contour[contour.len() - 1].end());
+            if start != end {
+                contour.push(Segment::Line([end, start]));
+            }
+        }
+        Self::new(contours)
+    }
+
+    /// Smallest box around every control point as (min, max), None for empty shapes.
+    pub fn bounds(&self) -> Option<([f32; 2], [f32; 2])> {
+        let mut points = self
+            .contours
+            .iter()
+            .flatten()
+            .flat_map(|e| e.segment.control_points().iter().copied());
+        let first = points.next()?;
+        let (min, max) = points.fold((first, first), |(min, max), p| {
+            (
+                Vec2::new(min.x.min(p.x), min.y.min(p.y)),
+                Vec2::new(max.x.max(p.x), max.y.max(p.y)),
+            )
+        });
+        Some((min.into(), max.into()))
+    }
+
+    /// Colors edges so the two sides of every corner share at most one channel, which is
+    /// what keeps corners sharp. Smooth contours stay white, a contour with a single corner
+    /// is split in three. Follows msdfgen's simple edge coloring.
+    pub fn color_edges(&mut self) {
+        let threshold = CORNER_ANGLE.sin();
+        for contour in self.contours.iter_mut() {
+            let m = contour.len();
+            let corners: Vec<usize> = (0..m)
+                .filter(|&i| {
+                    let prev = contour[(i + m - 1) % m].segment.direction(1.0);
+                    let next = contour[i].segment.direction(0.0);
+                    is_corner(prev, next, threshold)
+                })
+                .collect();
+            match corners.len() {
+                0 => contour.iter_mut().for_each(|e| e.color = WHITE),
+                1 => color_teardrop(contour, corners[0]),
+                count => {
+                    let initial = switch_color(WHITE, 0);
+                    let mut color = initial;
+                    let mut spline = 0;
+                    for i in 0..m {
+                        let index = (corners[0] + i) % m;
+                        if spline + 1 < count && corners[spline + 1] == index {
+                            spline += 1;
+                            let banned = if spline == count - 1 { initial } else { 0 };
+                            color = switch_color(color, banned);
+                        }
+                        contour[index].color = color;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Contours cut into straight pieces, for the inside test.
+    fn flatten(&self) -> Vec<Vec<Vec2>> {
+        self.contours
+            .iter()
+            .map(|contour| {
+                contour
+                    .iter()
+                    .flat_map(|e| match e.segment {
+                        Segment::Line([a, _]) => vec![a],
+                        s => (0..FLATTEN_STEPS)
+                            .map(|i| s.point(i as f32 / FLATTEN_STEPS as f32))
+                            .collect(),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+fn is_corner(a: Vec2, b: Vec2, threshold: f32) -> bool {
+    if a.magnitude2() == 0.0 || b.magnitude2() == 0.0 {
+        return false;
+    }
+    let (a, b) = (a.normalize(), b.normalize());
+    a.dot(b) <= 0.0 || a.perp_dot(b).abs() > threshold
+}
+
+/// Next color after `color` avoiding the channels of `banned`, always two channels so
+/// neighbouring edges share exactly one.
+fn switch_color(color: u8, banned: u8) -> u8 {
+    let combined = color & banned;
+    if combined == RED || combined == GREEN || combined == BLUE {
+        return combined ^ WHITE;
+    }
+    if color == 0 || color == WHITE {
+        return CYAN;
+    }
+    let shifted = color << 1;
+    (shifted | shifted >> 3) & WHITE
+}
+
+/// A contour with one corner needs three colors along it, edges are split in thirds if
+/// there are fewer than three of them.
+fn color_teardrop(contour: &mut Vec<Edge>, corner: usize) {
+    const COLORS: [u8; 3] = [MAGENTA, WHITE, YELLOW];
+    let m = contour.len();
+    if m >= 3 {
+        for i in 0..m {
+            // Maps positions along the contour to -1, 0 and 1 evenly.
+            let third = (3.0 + 2.875 * i as f32 / (m - 1) as f32 - 1.4375 + 0.5) as i32 - 3;
+            contour[(corner + i) % m].color = COLORS[(third + 1) as usize];
+        }
+        return;
+    }
+    let pieces: Vec<Segment> = (0..m)
+        .flat_map(|i| contour[(corner + i) % m].segment.thirds())
+        .collect();
+    let per_color = pieces.len() / 3;
+    *contour = pieces
+        .into_iter()
+        .enumerate()
+        .map(|(i, segment)| Edge {
+            segment,
+            color: COLORS[i / per_color],
+        })
+        .collect();
+}
+
+/// Signed area of the flattened contours, positive when they run counter clockwise.
+fn signed_area(polygons: &[Vec<Vec2>]) -> f32 {
+    polygons
+        .iter()
+        .flat_map(|poly| {
+            (0..poly.len()).map(|i| poly[i].perp_dot(poly[(i + 1) % poly.len()]) * 0.5)
+        })
+        .sum()
+}
+
+/// Non-zero winding number test.
+fn inside(polygons: &[Vec<Vec2>], p: Vec2) -> bool {
+    let mut winding = 0;
+    for poly in polygons {
+        for i in 0..poly.len() {
+            let (a, b) = (poly[i], poly[(i + 1) % poly.len()]);
+            let side = (b - a).perp_dot(p - a);
+            if a.y <= p.y {
+                if b.y > p.y && side > 0.0 {
+                    winding += 1;
+                }
+            } else if b.y <= p.y && side < 0.0 {
+                winding -= 1;
+            }
+        }
+    }
+    winding != 0
+}
+
+#[inline]
+pub fn median(r: f32, g: f32, b: f32) -> f32 {
+    r.min(g).max(r.max(g).min(b))
+}
+
+/// Renders the multi-channel distance field of `shape` into a `width` x `height` image.
+/// Texel (x, y), rows top down, samples shape space at ((x + 0.5, height - y - 0.5) /
+/// `scale`) - `translate`. Channels store distance / `range` + 0.5 with the inside above
+/// 0.5, `range` in texels. Alpha holds the plain signed distance.
+///
+/// Texels whose median lands on the wrong side of the outline, where edges of one color
+/// nearly touch, fall back to the plain distance in every channel.
+pub fn generate(
+    shape: &Shape,
+    width: u32,
+    height: u32,
+    scale: f32,
+    translate: [f32; 2],
+    range: f32,
+) -> RgbaImage {
+    let polygons = shape.flatten();
+    // Edge signs assume clockwise outer contours like TrueType, CFF fonts run the other way.
+    let flip = if signed_area(&polygons) > 0.0 {
+        -1.0
+    } else {
+        1.0
+    };
+    let encode = |d: f32| ((d * scale / range + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let p = Vec2::new(
+            (x as f32 + 0.5) / scale - translate[0],
+            (height as f32 - y as f32 - 0.5) / scale - translate[1],
+        );
+        let mut nearest = [(SignedDistance::INFINITE, None::<(&Segment, f32)>); 3];
+        let mut plain = SignedDistance::INFINITE;
+        for edge in shape.contours.iter().flatten() {
+            let (sd, t) = edge.segment.distance(p);
+            if sd.closer_than(&plain) {
+                plain = sd;
+            }
+            for (channel, best) in nearest.iter_mut().enumerate() {
+                if edge.color & (1 << channel) != 0 && sd.closer_than(&best.0) {
+                    *best = (sd, Some((&edge.segment, t)));
+                }
+            }
+        }
+        let channels = nearest.map(|(sd, edge)| match edge {
+            Some((segment, t)) => flip * segment.pseudo_distance(p, (sd, t)),
+            None => -f32::MAX,
+        });
+
+        let inside = inside(&polygons, p);
+        let plain = if inside {
+            plain.distance.abs()
+        } else {
+            -plain.distance.abs()
+        };
+        let [r, g, b] = if (median(channels[0], channels[1], channels[2]) > 0.0) != inside {
+            [plain; 3]
+        } else {
+            channels
+        };
+        image::Rgba([encode(r), encode(g), encode(b), encode(plain)])
+    })
+}
+
+/// Bakes the glyphs of `chars` the font has into one MSDF atlas page named `page`, with
+/// metrics and kerning for an em of `px` atlas pixels, see GlyphAtlas::new. Missing
+/// glyphs are left out, BmFont::glyph draws them as '?'.
+pub fn bake_font(
+    data: &[u8],
+    page: &str,
+    px: f32,
+    range: f32,
+    chars: impl IntoIterator<Item = char>,
+) -> anyhow::Result<(BakedFont, RgbaImage)> {
+    let font = FontRef::try_from_slice(data)
+        .map_err(|e| anyhow::anyhow!("bake_font => invalid font: {}", e))?;
+    let scaled = font.as_scaled(PxScale::from(px));
+    let scale = scaled.h_scale_factor();
+    let base = scaled.ascent().round() as i32;
+    let pad = (range / 2.0).ceil() as i32 + 1;
+
+    let mut glyphs = Vec::new();
+    for c in chars {
+        let id = font.glyph_id(c);
+        if id.0 == 0 {
+            continue;
+        }
+        let advance = scaled.h_advance(id).round() as i32;
+        let field = font.outline(id).and_then(|outline| {
+            let mut shape = Shape::from_outline(&outline.curves);
+            let (min, max) = shape.bounds()?;
+            shape.color_edges();
+            let left = (min[0] * scale).floor() as i32 - pad;
+            let bottom = (min[1] * scale).floor() as i32 - pad;
+            let right = (max[0] * scale).ceil() as i32 + pad;
+            let top = (max[1] * scale).ceil() as i32 + pad;
+            let translate = [-left as f32 / scale, -bottom as f32 / scale];
+            let (width, height) = ((right - left) as u32, (top - bottom) as u32);
+            let image = generate(&shape, width, height, scale, translate, range);
+            Some((image, [left, base - top]))
+        });
+        glyphs.push((c, id, advance, field));
+    }
+
+    let sizes: Vec<[u32; 2]> = glyphs
+        .iter()
+        .map(|(_, _, _, field)| {
+            field
+                .as_ref()
+                .map_or([0, 0], |(img, _)| [img.width(), img.height()])
+        })
+        .collect();
+    let mut size = MIN_ATLAS_SIZE;
+    let placements = loop {
+        let placements = SkylinePacker::new(size, size).with_padding(1).pack(&sizes);
+        if placements.iter().all(Option::is_some) {
+            break placements;
+        }
+        ensure!(
+            size < MAX_ATLAS_SIZE,
+            "bake_font => glyphs don't fit in a {}px atlas",
+            MAX_ATLAS_SIZE
+        );
+        size *= 2;
+    };
+
+    let mut atlas = RgbaImage::new(size, size);
+    let mut chars = HashMap::new();
+    for ((c, _, advance, field), placement) in glyphs.iter().zip(placements) {
+        let [x, y] = placement.unwrap_or_default();
+        let mut placed = BmChar {
+            x,
+            y,
+            width: 0,
+            height: 0,
+            offset: [0, 0],
+            advance: *advance,
+            page: 0,
+        };
+        if let Some((image, offset)) = field {
+            image::imageops::replace(&mut atlas, image, x as i64, y as i64);
+            placed.width = image.width();
+            placed.height = image.height();
+            placed.offset = *offset;
+        }
+        chars.insert(*c, placed);
+    }
+
+    let mut kerning = HashMap::new();
+    for (a, first, _, _) in glyphs.iter() {
+        for (b, second, _, _) in glyphs.iter() {
+            let kern = scaled.kern(*first, *second).round() as i32;
+            if kern != 0 {
+                kerning.insert((*a, *b), kern);
+            }
+        }
+    }
+
+    let font = BmFont {
+        face: String::new(),
+        size: px.round() as i32,
+        line_height: (scaled.ascent() - scaled.descent() + scaled.line_gap()).ceil() as u32,
+        base: base as u32,
+        page_size: [size, size],
+        pages: vec![page.to_string()],
+        chars,
+        kerning,
+    };
+    Ok((
+        BakedFont {
+            font,
+            distance_range: range,
+        },
+        atlas,
+    ))
+}
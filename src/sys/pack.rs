@@ -0,0 +1,238 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{bail, ensure};
+
+use super::mem::AlignedBytes;
+
+pub const PACK_MAGIC: [u8; 4] = *b"RPAK";
+pub const PACK_VERSION: u32 = 1;
+/// Blobs start on this alignment so they can be viewed in place as vertex/index data.
+pub const BLOB_ALIGN: usize = 16;
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssetKind {
+    Raw = 0,
+    Texture = 1,
+    Mesh = 2,
+    Lightmap = 3,
+    Cubemap = 4,
+    Font = 5,
+}
+
+impl AssetKind {
+    fn from_u8(v: u8) -> anyhow::Result<Self> {
+        Ok(match v {
+            0 => Self::Raw,
+            1 => Self::Texture,
+            2 => Self::Mesh,
+            3 => Self::Lightmap,
+            4 => Self::Cubemap,
+            5 => Self::Font,
+            _ => bail!("AssetKind::from_u8 => Unknown asset kind {}", v),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackEntry {
+    pub name: String,
+    pub kind: AssetKind,
+    pub offset: usize,
+    pub len: usize,
+}
+
+/// Baked assets produced by the asset importer, read whole into 16 byte aligned memory so
+/// blobs can be viewed in place.
+/// Layout: magic, version, entry count, entry table (name, kind, offset, len), then the blobs.
+#[derive(Debug, Clone, Default)]
+pub struct AssetPack {
+    entries: Vec<PackEntry>,
+    lookup: HashMap<String, usize>,
+    data: AlignedBytes,
+}
+
+impl AssetPack {
+    pub fn from_bytes(data: &[u8]) -> anyhow::Result<Self> {
+        Self::from_aligned(AlignedBytes::from_slice(data))
+    }
+
+    pub fn from_aligned(data: AlignedBytes) -> anyhow::Result<Self> {
+        let mut r = ByteReader::new(data.as_slice());
+        ensure!(
+            r.bytes(4)? == PACK_MAGIC,
+            "AssetPack::from_bytes => Not an asset pack"
+        );
+        let version = r.u32()?;
+        ensure!(
+            version == PACK_VERSION,
+            "AssetPack::from_bytes => Unsupported pack version {}, expected {}",
+            version,
+            PACK_VERSION
+        );
+
+        let count = r.u32()? as usize;
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            let name = r.string()?;
+            let kind = AssetKind::from_u8(r.u8()?)?;
+            let offset = r.u64()? as usize;
+            let len = r.u64()? as usize;
+            ensure!(
+                offset.checked_add(len).is_some_and(|end| end <= data.len()),
+                "AssetPack::from_bytes => Entry {} out of bounds",
+                name
+            );
+            entries.push(PackEntry {
+                name,
+                kind,
+                offset,
+                len,
+            });
+        }
+
+        let lookup = entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.name.clone(), i))
+            .collect();
+        Ok(Self {
+            entries,
+            lookup,
+            data,
+        })
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::from_aligned(AlignedBytes::read_file(path)?)
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&PackEntry> {
+        self.lookup.get(name).map(|&i| &self.entries[i])
+    }
+
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        self.entry(name)
+            .map(|e| &self.data.as_slice()[e.offset..e.offset + e.len])
+    }
+
+    pub fn entries(&self) -> &[PackEntry] {
+        &self.entries
+    }
+}
+
+/// Collects blobs and writes them out in the AssetPack layout.
+#[derive(Debug, Default)]
+pub struct PackWriter {
+    blobs: Vec<(String, AssetKind, Vec<u8>)>,
+}
+
+impl PackWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a blob, replacing any previous one with the same name.
+    pub fn add(&mut self, name: &str, kind: AssetKind, data: Vec<u8>) {
+        self.blobs.retain(|(n, _, _)| n != name);
+        self.blobs.push((String::from(name), kind, data));
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.blobs.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.blobs.is_empty()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let table_len: usize = self
+            .blobs
+            .iter()
+            .map(|(name, _, _)| 4 + name.len() + 1 + 8 + 8)
+            .sum();
+        let mut offset = align_up(4 + 4 + 4 + table_len, BLOB_ALIGN);
+
+        let mut out = Vec::with_capacity(offset);
+        out.extend_from_slice(&PACK_MAGIC);
+        out.extend_from_slice(&PACK_VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.blobs.len() as u32).to_le_bytes());
+        for (name, kind, data) in self.blobs.iter() {
+            write_string(&mut out, name);
+            out.push(*kind as u8);
+            out.extend_from_slice(&(offset as u64).to_le_bytes());
+            out.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            offset = align_up(offset + data.len(), BLOB_ALIGN);
+        }
+        for (_, _, data) in self.blobs.iter() {
+            out.resize(align_up(out.len(), BLOB_ALIGN), 0);
+            out.extend_from_slice(data);
+        }
+        out
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+}
+
+#[inline]
+pub(crate) const fn align_up(v: usize, align: usize) -> usize {
+    v.div_ceil(align) * align
+}
+
+pub(crate) fn write_string(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+/// Little endian cursor over baked asset bytes, errors instead of panicking on short input.
+pub(crate) struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    #[inline]
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    pub fn bytes(&mut self, n: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.data.len());
+        let Some(end) = end else {
+            bail!("ByteReader::bytes => Unexpected end of data");
+        };
+        let s = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(s)
+    }
+
+    pub fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    pub fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into()?))
+    }
+
+    pub fn string(&mut self) -> anyhow::Result<String> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8(self.bytes(len)?.to_vec())?)
+    }
+}
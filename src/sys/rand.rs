@@ -0,0 +1,254 @@
+use std::ops::Range;
+
+use cgmath::{Vector2, Vector3};
+
+pub use radium_core::rand::SplitMix64;
+
+/// xoshiro256** generator. Fast and not cryptographic, the same seed always gives the
+/// same sequence on every platform so it is safe to use for replays.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    s: [u64; 4],
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        let mut sm = SplitMix64::new(seed);
+        Self {
+            s: std::array::from_fn(|_| sm.next_u64()),
+        }
+    }
+
+    /// Seeds from the system clock, for things that should differ between runs.
+    pub fn from_time() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Self::new(nanos)
+    }
+
+    /// Internal state, e.g. for hashing in a determinism audit.
+    #[inline]
+    pub fn state(&self) -> [u64; 4] {
+        self.s
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let result = self.s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.s[1] << 17;
+        self.s[2] ^= self.s[0];
+        self.s[3] ^= self.s[1];
+        self.s[1] ^= self.s[2];
+        self.s[0] ^= self.s[3];
+        self.s[2] ^= t;
+        self.s[3] = self.s[3].rotate_left(45);
+        result
+    }
+
+    #[inline]
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Uniform in 0..1.
+    #[inline]
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 * (1.0 / (1u64 << 24) as f32)
+    }
+
+    #[inline]
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Independent generator derived from this one, advancing this one by a single step.
+    pub fn split(&mut self) -> Self {
+        Self::new(self.next_u64())
+    }
+
+    /// Uniform in `range`, returns range.start if it is empty.
+    pub fn range_f32(&mut self, range: Range<f32>) -> f32 {
+        if range.start >= range.end {
+            return range.start;
+        }
+        (range.start + (range.end - range.start) * self.next_f32()).min(range.end.next_down())
+    }
+
+    /// Uniform in `range` without modulo bias, returns range.start if it is empty.
+    pub fn range_i32(&mut self, range: Range<i32>) -> i32 {
+        if range.start >= range.end {
+            return range.start;
+        }
+        let span = (range.end as i64 - range.start as i64) as u64;
+        (range.start as i64 + self.below(span) as i64) as i32
+    }
+
+    pub fn range_usize(&mut self, range: Range<usize>) -> usize {
+        if range.start >= range.end {
+            return range.start;
+        }
+        range.start + self.below((range.end - range.start) as u64) as usize
+    }
+
+    /// Uniform in 0..n using Lemire's multiply and reject.
+    fn below(&mut self, n: u64) -> u64 {
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let m = self.next_u64() as u128 * n as u128;
+            if (m as u64) >= threshold {
+                return (m >> 64) as u64;
+            }
+        }
+    }
+
+    /// True with probability `p`.
+    #[inline]
+    pub fn chance(&mut self, p: f32) -> bool {
+        self.next_f32() < p
+    }
+
+    /// Uniformly distributed point on the unit circle.
+    pub fn on_unit_circle(&mut self) -> Vector2<f32> {
+        let a = self.next_f32() * std::f32::consts::TAU;
+        Vector2::new(a.cos(), a.sin())
+    }
+
+    /// Uniformly distributed point inside the unit circle.
+    pub fn in_unit_circle(&mut self) -> Vector2<f32> {
+        self.on_unit_circle() * self.next_f32().sqrt()
+    }
+
+    /// Uniformly distributed point on the unit sphere.
+    pub fn on_unit_sphere(&mut self) -> Vector3<f32> {
+        let z = self.next_f32() * 2.0 - 1.0;
+        let a = self.next_f32() * std::f32::consts::TAU;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        Vector3::new(r * a.cos(), r * a.sin(), z)
+    }
+
+    /// Uniformly distributed point inside the unit sphere.
+    pub fn in_unit_sphere(&mut self) -> Vector3<f32> {
+        self.on_unit_sphere() * self.next_f32().cbrt()
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        (!items.is_empty()).then(|| &items[self.range_usize(0..items.len())])
+    }
+
+    /// Index picked with probability proportional to its weight. Negative weights count
+    /// as zero, None if no weight is positive.
+    pub fn weighted_index(&mut self, weights: &[f32]) -> Option<usize> {
+        let total: f32 = weights.iter().map(|w| w.max(0.0)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut pick = self.next_f32() * total;
+        for (i, w) in weights.iter().enumerate() {
+            let w = w.max(0.0);
+            if pick < w {
+                return Some(i);
+            }
+            pick -= w;
+        }
+        weights.iter().rposition(|w| *w > 0.0)
+    }
+
+    pub fn weighted_choice<'a, T>(&mut self, items: &'a [(T, f32)]) -> Option<&'a T> {
+        let weights = items.iter().map(|(_, w)| *w).collect::<Vec<_>>();
+        self.weighted_index(&weights).map(|i| &items[i].0)
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.range_usize(0..i + 1));
+        }
+    }
+}
+
+/// Which system a stream belongs to. Gameplay has to stay deterministic for replays, so
+/// effects that may run a different number of times (e.g. skipped when offscreen) draw
+/// from their own stream and can't shift the gameplay sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RngStream {
+    Gameplay,
+    Visual,
+    Audio,
+    Ai,
+    Custom(u32),
+}
+
+impl RngStream {
+    pub(crate) fn id(self) -> u64 {
+        match self {
+            Self::Gameplay => 0,
+            Self::Visual => 1,
+            Self::Audio => 2,
+            Self::Ai => 3,
+            Self::Custom(id) => 0x100 + id as u64,
+        }
+    }
+}
+
+/// One Rng per RngStream, all derived from a single world seed.
+#[derive(Debug, Clone)]
+pub struct RngStreams {
+    seed: u64,
+    streams: Vec<(RngStream, Rng)>,
+}
+
+impl RngStreams {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Generator of `stream`, created on first use. A stream's sequence only depends on the
+    /// world seed and the stream, not on what other streams have drawn.
+    pub fn get(&mut self, stream: RngStream) -> &mut Rng {
+        let i = match self.streams.iter().position(|(s, _)| *s == stream) {
+            Some(i) => i,
+            None => {
+                let seed =
+                    SplitMix64::new(self.seed ^ stream.id().wrapping_mul(0xD6E8_FEB8_6659_FD93))
+                        .next_u64();
+                self.streams.push((stream, Rng::new(seed)));
+                self.streams.len() - 1
+            }
+        };
+        &mut self.streams[i].1
+    }
+
+    /// Streams used so far, in the order they were first used.
+    pub fn streams(&self) -> impl Iterator<Item = (RngStream, &Rng)> {
+        self.streams.iter().map(|(stream, rng)| (*stream, rng))
+    }
+
+    #[inline]
+    pub fn gameplay(&mut self) -> &mut Rng {
+        self.get(RngStream::Gameplay)
+    }
+
+    #[inline]
+    pub fn visual(&mut self) -> &mut Rng {
+        self.get(RngStream::Visual)
+    }
+
+    /// Restarts every stream from the world seed, e.g. when a replay begins.
+    pub fn reset(&mut self, seed: u64) {
+        self.seed = seed;
+        self.streams.clear();
+    }
+}
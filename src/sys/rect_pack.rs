@@ -0,0 +1,204 @@
+use std::fmt;
+
+/// Horizontal span of the skyline at height `y`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Segment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+/// How much of the atlas a SkylinePacker has filled.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PackStats {
+    pub packed: usize,
+    pub rejected: usize,
+    /// Sum of the packed rect areas, without padding.
+    pub used_area: u64,
+    /// Highest row touched, the atlas can be cropped to this height.
+    pub used_height: u32,
+    pub size: [u32; 2],
+}
+
+impl PackStats {
+    /// Fraction of the whole atlas covered by rects.
+    pub fn efficiency(&self) -> f32 {
+        let area = self.size[0] as u64 * self.size[1] as u64;
+        if area == 0 {
+            return 0.0;
+        }
+        self.used_area as f32 / area as f32
+    }
+
+    /// Fraction of the atlas cropped to used_height covered by rects.
+    pub fn cropped_efficiency(&self) -> f32 {
+        let area = self.size[0] as u64 * self.used_height as u64;
+        if area == 0 {
+            return 0.0;
+        }
+        self.used_area as f32 / area as f32
+    }
+}
+
+impl fmt::Display for PackStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} packed, {} rejected, {:.1}% of {}x{} used ({:.1}% cropped to {})",
+            self.packed,
+            self.rejected,
+            self.efficiency() * 100.0,
+            self.size[0],
+            self.size[1],
+            self.cropped_efficiency() * 100.0,
+            self.used_height
+        )
+    }
+}
+
+/// Bottom left skyline rect packer for texture atlases, e.g. glyphs, sprites and lightmap
+/// charts. Placements are top left corners in pixels, rects are never rotated.
+#[derive(Debug, Clone)]
+pub struct SkylinePacker {
+    size: [u32; 2],
+    padding: u32,
+    skyline: Vec<Segment>,
+    stats: PackStats,
+}
+
+impl SkylinePacker {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            size: [width, height],
+            padding: 0,
+            skyline: vec![Segment { x: 0, y: 0, width }],
+            stats: PackStats {
+                packed: 0,
+                rejected: 0,
+                used_area: 0,
+                used_height: 0,
+                size: [width, height],
+            },
+        }
+    }
+
+    /// Empty pixels kept to the right of and below every rect so filtering doesn't bleed
+    /// between neighbours. Padding may be cut off at the atlas edges.
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    #[inline]
+    pub const fn size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    #[inline]
+    pub const fn stats(&self) -> PackStats {
+        self.stats
+    }
+
+    /// Forgets every placement, e.g. before rebuilding a font atlas.
+    pub fn clear(&mut self) {
+        *self = Self::new(self.size[0], self.size[1]).with_padding(self.padding);
+    }
+
+    /// Finds room for a `width` x `height` rect and returns its top left corner, None if
+    /// it doesn't fit anywhere. Empty rects are placed at the origin without using space.
+    pub fn insert(&mut self, width: u32, height: u32) -> Option<[u32; 2]> {
+        if width == 0 || height == 0 {
+            return Some([0, 0]);
+        }
+
+        // Lowest resulting top edge wins, ties go to the narrowest segment to keep wide
+        // segments free for wide rects.
+        let mut best: Option<(usize, u32, (u32, u32))> = None;
+        for i in 0..self.skyline.len() {
+            let Some(y) = self.fit(i, width, height) else {
+                continue;
+            };
+            let key = (y + height, self.skyline[i].width);
+            if best.is_none_or(|(_, _, best_key)| key < best_key) {
+                best = Some((i, y, key));
+            }
+        }
+
+        let Some((i, y, _)) = best else {
+            self.stats.rejected += 1;
+            return None;
+        };
+        let x = self.skyline[i].x;
+        let top = y + (height + self.padding).min(self.size[1] - y);
+        self.raise(i, x, top, (width + self.padding).min(self.size[0] - x));
+
+        self.stats.packed += 1;
+        self.stats.used_area += width as u64 * height as u64;
+        self.stats.used_height = self.stats.used_height.max(y + height);
+        Some([x, y])
+    }
+
+    /// Inserts every rect in `sizes`, tallest first since that wastes less space than
+    /// arbitrary order. Placements are returned in the order of `sizes`.
+    pub fn pack(&mut self, sizes: &[[u32; 2]]) -> Vec<Option<[u32; 2]>> {
+        let mut order = (0..sizes.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| std::cmp::Reverse((sizes[i][1], sizes[i][0])));
+
+        let mut placements = vec![None; sizes.len()];
+        for i in order {
+            placements[i] = self.insert(sizes[i][0], sizes[i][1]);
+        }
+        placements
+    }
+
+    /// Height a rect placed at the start of segment `i` would sit at, None if it would
+    /// leave the atlas.
+    fn fit(&self, i: usize, width: u32, height: u32) -> Option<u32> {
+        let x = self.skyline[i].x;
+        if x + width > self.size[0] {
+            return None;
+        }
+        let mut remaining = (width + self.padding).min(self.size[0] - x);
+        let mut y = 0;
+        for segment in &self.skyline[i..] {
+            if remaining == 0 {
+                break;
+            }
+            y = y.max(segment.y);
+            if y + height > self.size[1] {
+                return None;
+            }
+            remaining = remaining.saturating_sub(segment.width);
+        }
+        Some(y)
+    }
+
+    /// Raises the skyline to `y` over `width` pixels starting at segment `i`.
+    fn raise(&mut self, i: usize, x: u32, y: u32, width: u32) {
+        self.skyline.insert(i, Segment { x, y, width });
+        let end = x + width;
+        while let Some(next) = self.skyline.get_mut(i + 1) {
+            let next_end = next.x + next.width;
+            if next_end <= end {
+                self.skyline.remove(i + 1);
+            } else {
+                if next.x < end {
+                    next.width = next_end - end;
+                    next.x = end;
+                }
+                break;
+            }
+        }
+
+        // Merge neighbours at the same height so the skyline stays short.
+        let mut j = 0;
+        while j + 1 < self.skyline.len() {
+            if self.skyline[j].y == self.skyline[j + 1].y {
+                self.skyline[j].width += self.skyline[j + 1].width;
+                self.skyline.remove(j + 1);
+            } else {
+                j += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    cell::UnsafeCell,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+};
+
+const INDEX_MASK: u8 = 0b011;
+const FRESH: u8 = 0b100;
+
+struct Shared<T> {
+    slots: [UnsafeCell<T>; 3],
+    /// Index of the middle slot, plus FRESH when it holds a snapshot the reader hasn't taken.
+    state: AtomicU8,
+}
+
+// Each slot is owned by exactly one of the writer (back), the state (middle) or the reader
+// (front), ownership only moves through the atomic swaps on `state`.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+/// Creates a triple buffer, every slot starts as a clone of `initial`. Hands snapshots of
+/// e.g. the next frame's draw data from an update thread to a render thread: the writer
+/// fills one slot while the reader holds another and the third is in flight, so neither
+/// side ever waits on the other. The reader always gets the newest published snapshot,
+/// older ones it didn't get to are skipped.
+pub fn triple_buffer<T: Clone + Send>(initial: T) -> (TripleWriter<T>, TripleReader<T>) {
+    let shared = Arc::new(Shared {
+        slots: [
+            UnsafeCell::new(initial.clone()),
+            UnsafeCell::new(initial.clone()),
+            UnsafeCell::new(initial),
+        ],
+        state: AtomicU8::new(1),
+    });
+    (
+        TripleWriter {
+            shared: shared.clone(),
+            back: 0,
+        },
+        TripleReader { shared, front: 2 },
+    )
+}
+
+pub struct TripleWriter<T> {
+    shared: Arc<Shared<T>>,
+    back: u8,
+}
+
+impl<T> TripleWriter<T> {
+    /// The slot being written. It holds whatever snapshot was swapped out on the last
+    /// publish, not the one just published, so overwrite it fully or use publish_from.
+    pub fn back_mut(&mut self) -> &mut T {
+        // Safety: the back slot is only reachable through this writer.
+        unsafe { &mut *self.shared.slots[self.back as usize].get() }
+    }
+
+    /// Makes the back slot the newest snapshot and takes over the previous middle slot.
+    pub fn publish(&mut self) {
+        let prev = self.shared.state.swap(self.back | FRESH, Ordering::AcqRel);
+        self.back = prev & INDEX_MASK;
+    }
+
+    /// Fills the back slot with `f` and publishes it.
+    pub fn publish_with(&mut self, f: impl FnOnce(&mut T)) {
+        f(self.back_mut());
+        self.publish();
+    }
+
+    /// Publishes a copy of `value`, reusing the slot's allocations where T::clone_from does.
+    pub fn publish_from(&mut self, value: &T)
+    where
+        T: Clone,
+    {
+        self.back_mut().clone_from(value);
+        self.publish();
+    }
+}
+
+pub struct TripleReader<T> {
+    shared: Arc<Shared<T>>,
+    front: u8,
+}
+
+impl<T> TripleReader<T> {
+    /// True if a snapshot was published since the last read.
+    pub fn has_new(&self) -> bool {
+        self.shared.state.load(Ordering::Acquire) & FRESH != 0
+    }
+
+    /// Takes the newest published snapshot if there is one, then returns the front slot.
+    pub fn read(&mut self) -> &T {
+        if self.has_new() {
+            let prev = self.shared.state.swap(self.front, Ordering::AcqRel);
+            self.front = prev & INDEX_MASK;
+        }
+        // Safety: the front slot is only reachable through this reader.
+        unsafe { &*self.shared.slots[self.front as usize].get() }
+    }
+}
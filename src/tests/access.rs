@@ -0,0 +1,107 @@
+#[cfg(all(test, feature = "accesskit"))]
+mod tests {
+    use crate::{
+        eng::access::{AccessActionKind, AccessId, AccessNode, AccessRole, AccessTree, Accessible},
+        gfx::tweak::{TweakPanel, TweakValues},
+    };
+
+    fn frame(tree: &mut AccessTree, names: &[&str]) {
+        tree.begin_frame();
+        tree.push(AccessNode::new(
+            AccessId::from_name("title"),
+            AccessRole::Label,
+            "Title",
+        ));
+        for name in names {
+            tree.push(AccessNode::new(
+                AccessId::from_name(name),
+                AccessRole::Button,
+                name,
+            ));
+        }
+        tree.end_frame();
+    }
+
+    #[test]
+    fn focus_cycles_through_focusable_nodes() {
+        let mut tree = AccessTree::new("Game");
+        frame(&mut tree, &["play", "options", "quit"]);
+        assert_eq!(tree.focus(), None);
+
+        tree.focus_prev();
+        assert!(tree.is_focused(AccessId::from_name("quit")));
+        tree.focus_next();
+        assert!(tree.is_focused(AccessId::from_name("play")));
+        tree.focus_next();
+        assert!(tree.is_focused(AccessId::from_name("options")));
+
+        // Focus survives a rebuild by id and is dropped once its widget is gone.
+        frame(&mut tree, &["play", "options"]);
+        assert!(tree.is_focused(AccessId::from_name("options")));
+        frame(&mut tree, &["play"]);
+        assert_eq!(tree.focus(), None);
+
+        tree.act_on_focus(AccessActionKind::Click);
+        assert!(tree.actions().is_empty());
+        tree.focus_next();
+        tree.act_on_focus(AccessActionKind::Click);
+        assert_eq!(tree.actions()[0].target, AccessId::from_name("play"));
+        frame(&mut tree, &["play"]);
+        assert!(tree.actions().is_empty());
+    }
+
+    #[test]
+    fn tree_update_nests_nodes_under_the_window() {
+        let mut tree = AccessTree::new("Game");
+        let menu = AccessId::from_name("menu");
+        tree.begin_frame();
+        tree.push(AccessNode::new(menu, AccessRole::Group, "Menu"));
+        tree.push(
+            AccessNode::new(AccessId::from_name("play"), AccessRole::Button, "Play")
+                .with_parent(menu),
+        );
+        // Orphans aren't reachable from the root, so they are left out.
+        tree.push(
+            AccessNode::new(AccessId::from_name("lost"), AccessRole::Button, "Lost")
+                .with_parent(AccessId::from_name("gone")),
+        );
+        tree.end_frame();
+        tree.focus_next();
+
+        let update = tree.tree_update();
+        assert_eq!(update.nodes.len(), 3);
+        assert_eq!(update.tree.unwrap().root.0, AccessId::ROOT.0);
+        assert_eq!(update.nodes[0].1.children()[0].0, menu.0);
+        assert_eq!(update.focus.0, AccessId::from_name("play").0);
+    }
+
+    #[test]
+    fn tweak_panel_applies_access_actions() {
+        let mut panel = TweakPanel::new(TweakValues::default());
+        let mut tree = AccessTree::new("Game");
+        let (mut bloom, mut fog) = (1.0, false);
+
+        tree.begin_frame();
+        panel.begin([-1.0, -1.0], false);
+        panel.begin_access(&tree);
+        panel.tweak_f32("bloom", &mut bloom, 0.0..2.0);
+        panel.tweak_bool("fog", &mut fog);
+        panel.push_access_nodes(&mut tree, AccessId::ROOT);
+        tree.end_frame();
+        assert_eq!(tree.nodes().len(), 3);
+
+        tree.focus_next();
+        tree.act_on_focus(AccessActionKind::Increment);
+        tree.focus_next();
+        tree.act_on_focus(AccessActionKind::Click);
+
+        tree.begin_frame();
+        panel.begin([-1.0, -1.0], false);
+        panel.begin_access(&tree);
+        assert!(panel.tweak_f32("bloom", &mut bloom, 0.0..2.0));
+        assert!(panel.tweak_bool("fog", &mut fog));
+        assert!((bloom - 1.1).abs() < 1e-5);
+        assert!(fog);
+        assert!(panel.rows()[1].focused);
+    }
+}
@@ -0,0 +1,82 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        eng::app::EngineConfig,
+        gfx::post::accessibility::{AccessibilitySettings, ColorFilterMode, ColorVision},
+    };
+
+    fn apply(m: [[f32; 3]; 3], rgb: [f32; 3]) -> [f32; 3] {
+        m.map(|row| row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2])
+    }
+
+    fn close(a: [f32; 3], b: [f32; 3]) -> bool {
+        a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-3)
+    }
+
+    #[test]
+    fn color_filters_keep_grays() {
+        let normal = AccessibilitySettings::default();
+        assert!(!normal.is_active());
+        assert_eq!(normal.color_matrix(), identity());
+
+        for vision in [
+            ColorVision::Protan,
+            ColorVision::Deutan,
+            ColorVision::Tritan,
+        ] {
+            for filter in [ColorFilterMode::Simulate, ColorFilterMode::Correct] {
+                let settings = AccessibilitySettings {
+                    color_vision: vision,
+                    color_filter: filter,
+                    ..Default::default()
+                };
+                assert!(settings.is_active());
+                let gray = [0.5; 3];
+                assert!(close(apply(settings.color_matrix(), gray), gray));
+            }
+        }
+
+        // Red and green look alike to a deutan viewer, correction pulls them apart.
+        let simulate = AccessibilitySettings {
+            color_vision: ColorVision::Deutan,
+            color_filter: ColorFilterMode::Simulate,
+            ..Default::default()
+        };
+        let red = apply(simulate.color_matrix(), [1.0, 0.0, 0.0]);
+        assert!((red[0] - red[1]).abs() < 0.1);
+
+        let off = AccessibilitySettings {
+            filter_strength: 0.0,
+            ..simulate
+        };
+        assert!(!off.is_active());
+        assert_eq!(off.color_matrix(), identity());
+    }
+
+    fn identity() -> [[f32; 3]; 3] {
+        [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+    }
+
+    #[test]
+    fn settings_round_trip_through_config() {
+        let config = EngineConfig::parse(
+            "accessibility.color_vision = tritan\naccessibility.reduced_flash = true\nui_scale = 1.5",
+        )
+        .unwrap();
+        assert_eq!(config.accessibility.color_vision, ColorVision::Tritan);
+        assert!(config.accessibility.reduced_flash);
+        assert_eq!(config.ui_scale, 1.5);
+        assert_eq!(
+            EngineConfig::parse(&config.to_string())
+                .unwrap()
+                .accessibility,
+            config.accessibility
+        );
+
+        let mut settings = AccessibilitySettings::default();
+        assert!(settings
+            .set("accessibility.color_vision", "purple")
+            .is_err());
+        assert!(!settings.set("ui_scale", "2").unwrap());
+    }
+}
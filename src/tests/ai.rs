@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::eng::ai::{
+        bt::{self, BehaviorTree, Status},
+        fsm::{State, StateMachine},
+    };
+
+    const DT: Duration = Duration::from_millis(100);
+
+    #[derive(Default)]
+    struct Guard {
+        log: Vec<String>,
+        health: i32,
+        enemy_seen: bool,
+    }
+
+    struct Logged(&'static str, Option<&'static str>);
+
+    impl State<&'static str, Guard> for Logged {
+        fn enter(&mut self, ctx: &mut Guard) {
+            ctx.log.push(format!("enter {}", self.0));
+        }
+
+        fn exit(&mut self, ctx: &mut Guard) {
+            ctx.log.push(format!("exit {}", self.0));
+        }
+
+        fn update(&mut self, ctx: &mut Guard, _dt: Duration) -> Option<&'static str> {
+            if ctx.enemy_seen {
+                self.1
+            } else {
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn hierarchical_state_machine() {
+        let mut fsm = StateMachine::new();
+        fsm.add("alive", |g: &mut Guard, _| {
+            (g.health <= 0).then_some("dead")
+        })
+        .add_child("alive", "patrol", Logged("patrol", Some("chase")))
+        .add_child("alive", "chase", Logged("chase", None))
+        .add("dead", Logged("dead", None));
+
+        let mut guard = Guard {
+            health: 10,
+            ..Default::default()
+        };
+        fsm.start(&mut guard, "alive");
+        assert_eq!(fsm.active(), &["alive", "patrol"]);
+
+        guard.enemy_seen = true;
+        fsm.update(&mut guard, DT);
+        assert_eq!(fsm.current(), Some("chase"));
+        assert!(fsm.is_in("alive"));
+
+        // The parent takes over before the child runs.
+        guard.health = 0;
+        fsm.update(&mut guard, DT);
+        assert_eq!(fsm.active(), &["dead"]);
+        assert_eq!(
+            guard.log,
+            [
+                "enter patrol",
+                "exit patrol",
+                "enter chase",
+                "exit chase",
+                "enter dead"
+            ]
+        );
+    }
+
+    #[test]
+    fn behavior_tree() {
+        let mut tree = BehaviorTree::new(bt::selector(vec![
+            bt::sequence(vec![
+                bt::condition(|g: &Guard, _| g.enemy_seen),
+                bt::action(|g: &mut Guard, bb, _| {
+                    *bb.get_mut::<u32>("attacks").unwrap() += 1;
+                    g.log.push(String::from("attack"));
+                    Status::Success
+                }),
+            ]),
+            bt::sequence(vec![
+                bt::wait(Duration::from_millis(250)),
+                bt::action(|g: &mut Guard, _, _| {
+                    g.log.push(String::from("look around"));
+                    Status::Success
+                }),
+            ]),
+        ]));
+        tree.blackboard.set("attacks", 0u32);
+        let mut guard = Guard::default();
+
+        assert_eq!(tree.tick(&mut guard, DT), Status::Running);
+        assert_eq!(tree.tick(&mut guard, DT), Status::Running);
+        assert_eq!(tree.tick(&mut guard, DT), Status::Success);
+        assert_eq!(guard.log, ["look around"]);
+
+        guard.enemy_seen = true;
+        assert_eq!(tree.tick(&mut guard, DT), Status::Success);
+        assert_eq!(tree.blackboard.get::<u32>("attacks"), Some(&1));
+        assert_eq!(tree.blackboard.get::<i32>("attacks"), None);
+    }
+
+    #[test]
+    fn decorators() {
+        let mut count = 0;
+        let mut tree = BehaviorTree::new(bt::cooldown(
+            Duration::from_millis(300),
+            bt::repeat(
+                2,
+                bt::action(|c: &mut i32, _, _| {
+                    *c += 1;
+                    Status::Success
+                }),
+            ),
+        ));
+        assert_eq!(tree.tick(&mut count, DT), Status::Running);
+        assert_eq!(tree.tick(&mut count, DT), Status::Success);
+        assert_eq!(count, 2);
+        assert_eq!(tree.tick(&mut count, DT), Status::Failure);
+        assert_eq!(tree.tick(&mut count, DT), Status::Failure);
+        assert_eq!(tree.tick(&mut count, DT), Status::Running);
+        assert_eq!(count, 3);
+
+        let mut inverted = BehaviorTree::new(bt::invert(bt::condition(|_: &(), _| true)));
+        assert_eq!(inverted.tick(&mut (), DT), Status::Failure);
+    }
+}
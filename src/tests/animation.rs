@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod tests {
+    use std::{rc::Rc, time::Duration};
+
+    use cgmath::Vector3;
+
+    use crate::{
+        eng::animation::{
+            AnimationClip, ClipPlayer, Interpolation, Keyframe, Track, TrackProperty, TrackValue,
+        },
+        gfx::transform::Transform,
+    };
+
+    fn slide(name: &str, to: f32) -> Rc<AnimationClip> {
+        let key = |time, x| Keyframe {
+            time,
+            value: TrackValue::Vec3([x, 0.0, 0.0]),
+        };
+        Rc::new(AnimationClip::new(
+            name,
+            vec![Track::new(
+                "player",
+                TrackProperty::Position,
+                vec![key(0.0, 0.0), key(1.0, to)],
+            )],
+        ))
+    }
+
+    #[test]
+    fn tracks_interpolate_and_hold_ends() {
+        let clip = slide("walk", 2.0);
+        let track = &clip.tracks[0];
+        assert_eq!(track.sample(0.5), Some(TrackValue::Vec3([1.0, 0.0, 0.0])));
+        assert_eq!(track.sample(-1.0), Some(TrackValue::Vec3([0.0, 0.0, 0.0])));
+        assert_eq!(track.sample(5.0), Some(TrackValue::Vec3([2.0, 0.0, 0.0])));
+
+        let step = track.clone().with_interpolation(Interpolation::Step);
+        assert_eq!(step.sample(0.9), Some(TrackValue::Vec3([0.0, 0.0, 0.0])));
+    }
+
+    #[test]
+    fn crossfade_blends_between_clips() {
+        let mut player = ClipPlayer::default();
+        let mut transform = Transform::default();
+        player.play(slide("a", 0.0));
+        player.crossfade(slide("b", 4.0), Duration::from_millis(500));
+
+        // Halfway through the fade: a is at 0, b at 1, weighted 50/50.
+        player.frame_update(Duration::from_millis(250), &mut transform);
+        assert_eq!(transform.position(), Vector3::new(0.5, 0.0, 0.0));
+
+        player.frame_update(Duration::from_millis(750), &mut transform);
+        assert_eq!(transform.position(), Vector3::new(4.0, 0.0, 0.0));
+        assert!(player.is_finished());
+    }
+}
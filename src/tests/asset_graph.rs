@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::eng::asset_graph::{AssetGraph, AssetSize, AssetType};
+
+    #[test]
+    fn unloading_a_scene_frees_only_what_it_alone_reached() {
+        let mut graph = AssetGraph::new();
+        let sampler = graph.insert(
+            "linear",
+            AssetType::Sampler,
+            AssetSize::default(),
+            Rc::new(0),
+        );
+        let brick = graph.insert(
+            "brick.png",
+            AssetType::Texture,
+            AssetSize::gpu(4096),
+            Rc::new(1),
+        );
+        let grass = graph.insert(
+            "grass.png",
+            AssetType::Texture,
+            AssetSize::gpu(2048),
+            Rc::new(2),
+        );
+        let house = graph.insert(
+            "house.obj",
+            AssetType::Model,
+            AssetSize::cpu(1024),
+            Rc::new(3),
+        );
+        let field = graph.insert(
+            "field.obj",
+            AssetType::Model,
+            AssetSize::cpu(512),
+            Rc::new(4),
+        );
+        graph.add_dependency(house, brick).unwrap();
+        graph.add_dependency(field, grass).unwrap();
+        graph.add_dependency(brick, sampler).unwrap();
+        graph.add_dependency(grass, sampler).unwrap();
+        assert!(graph.add_dependency(sampler, house).is_err());
+
+        graph.set_scene_assets("town", &[house]);
+        graph.set_scene_assets("farm", &[field]);
+        assert!(graph.unload_unused().is_empty());
+        assert_eq!(graph.ref_count(sampler), 2);
+
+        graph.unload_scene("farm");
+        let held = graph.get::<i32>(grass).unwrap();
+        let report = graph.unload_unused();
+        let names = report
+            .freed
+            .iter()
+            .map(|a| a.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["field.obj", "grass.png"]);
+        assert!(report.freed[1].still_held);
+        assert_eq!(report.freed_size(), AssetSize::cpu(512));
+        assert_eq!(*held, 2);
+
+        assert!(graph.get::<i32>(sampler).is_some());
+        assert!(graph.get::<f32>(house).is_none());
+        assert_eq!(graph.find("grass.png"), None);
+        assert_eq!(graph.len(), 3);
+    }
+}
@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        time::{Duration, Instant},
+    };
+
+    use crate::eng::{
+        assets::{AssetLoader, LoadPriority},
+        tasks::Tasks,
+    };
+
+    #[test]
+    fn visible_loads_first_and_cancelled_never_upload() {
+        let tasks = Tasks::new(2);
+        let uploaded = RefCell::new(Vec::new());
+        let mut loader = AssetLoader::<RefCell<Vec<&str>>>::new(1);
+
+        loader.load(
+            LoadPriority::Prefetch,
+            || "far",
+            |c, v| c.borrow_mut().push(v),
+        );
+        let dropped = loader.load(
+            LoadPriority::Prefetch,
+            || "gone",
+            |c, v| c.borrow_mut().push(v),
+        );
+        loader.load(
+            LoadPriority::Visible,
+            || "near",
+            |c, v| c.borrow_mut().push(v),
+        );
+        dropped.cancel();
+
+        let start = Instant::now();
+        while !loader.is_empty() {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "loads never finished"
+            );
+            loader.update(&tasks, &uploaded);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(*uploaded.borrow(), ["near", "far"]);
+    }
+}
@@ -0,0 +1,162 @@
+#[cfg(all(test, feature = "audio"))]
+mod tests {
+    use std::time::Duration;
+
+    use cgmath::{Point3, Vector3};
+
+    use crate::eng::{
+        app::EngineConfig,
+        audio::{
+            bus::{Bus, BusSettings},
+            spatial::{Attenuation, Doppler, Listener, Spatial},
+            stream::{MusicOptions, SoundStream},
+            Audio, Mixer, Sound, VoiceParams,
+        },
+    };
+
+    #[test]
+    fn positional_voices_pan_and_attenuate() {
+        let listener = Listener::default();
+        let (l, r) = listener.gains(&Spatial::at(Point3::new(1.0, 0.0, 0.0)));
+        assert!(r > 0.99 && l < 0.01);
+
+        let attenuation = Attenuation::default();
+        assert_eq!(attenuation.gain(0.5), 1.0);
+        assert_eq!(attenuation.gain(4.0), 0.25);
+        assert_eq!(attenuation.gain(200.0), 0.0);
+    }
+
+    #[test]
+    fn approaching_sources_pitch_up() {
+        let listener = Listener::default();
+        let doppler = Doppler::default();
+        let position = Point3::new(0.0, 0.0, -10.0);
+        assert!(doppler.pitch(&listener, position, Vector3::new(0.0, 0.0, 30.0)) > 1.0);
+        assert!(doppler.pitch(&listener, position, Vector3::new(0.0, 0.0, -30.0)) < 1.0);
+    }
+
+    #[test]
+    fn finished_voices_are_removed() {
+        let mut mixer = Mixer::new(4);
+        let sound = Sound::new(vec![0.5; 2], 1, 4).unwrap();
+        let voice = mixer.play(&sound, VoiceParams::default());
+
+        let mut out = [0.0; 8];
+        mixer.mix(&mut out);
+        assert_eq!(&out[..4], &[0.5; 4]);
+        assert_eq!(&out[4..], &[0.0; 4]);
+        assert!(!mixer.is_playing(voice));
+    }
+
+    #[test]
+    fn buses_scale_and_mute_their_voices() {
+        let mut mixer = Mixer::new(4);
+        let sound = Sound::new(vec![1.0; 4], 1, 4).unwrap();
+        mixer.play(&sound, VoiceParams::default().on_bus(Bus::Music));
+        mixer.play(&sound, VoiceParams::default().on_bus(Bus::Sfx));
+        mixer.set_bus_gain(Bus::Music, 0.5);
+        mixer.set_bus_muted(Bus::Sfx, true);
+        mixer.set_bus_gain(Bus::Master, 0.5);
+
+        let mut out = [0.0; 4];
+        mixer.mix(&mut out);
+        assert_eq!(out, [0.25; 4]);
+    }
+
+    #[test]
+    fn bus_effects_run_on_the_callback_thread() {
+        let audio = Audio::new(1000);
+        {
+            let mut mixer = audio.lock();
+            let sound = Sound::new(vec![1.0; 50], 1, 1000).unwrap();
+            mixer.play(&sound, VoiceParams::default());
+            mixer.set_bus(
+                Bus::Sfx,
+                BusSettings {
+                    low_pass: Some(100.0),
+                    reverb_send: 0.5,
+                    ..Default::default()
+                },
+            );
+            mixer.reserve(64);
+        }
+
+        // Pulls 64 frame buffers like an output stream callback would.
+        let callback = {
+            let audio = audio.clone();
+            std::thread::spawn(move || {
+                let mut out = Vec::new();
+                let mut buffer = [0.0; 128];
+                for _ in 0..4 {
+                    audio.lock().mix(&mut buffer);
+                    out.extend(buffer.iter().step_by(2).copied());
+                }
+                out
+            })
+        };
+        let left = callback.join().unwrap();
+        // The low-pass smooths the step in, the first comb echo arrives after 25 frames.
+        assert!(left[0] > 0.0 && left[0] < 0.5);
+        assert!(left[10] > left[0] && left[10] < 1.0);
+        assert!(left[30] > left[10]);
+        // The voice stops at frame 50, the filter and the reverb tail keep ringing.
+        assert!(left[60].abs() > 1e-3);
+        assert!(left[200].abs() > 1e-4);
+    }
+
+    #[test]
+    fn audio_settings_round_trip_through_config() {
+        let mut config = EngineConfig::default();
+        config.audio.bus_mut(Bus::Music).gain = 0.25;
+        config.audio.bus_mut(Bus::Sfx).low_pass = Some(800.0);
+
+        let parsed = EngineConfig::parse(&config.to_string()).unwrap();
+        assert_eq!(parsed, config);
+        assert!(EngineConfig::parse("audio.voice.gain = 1").is_err());
+    }
+
+    #[test]
+    fn music_loops_between_loop_points() {
+        let ramp = Sound::new((0..8).map(|i| i as f32).collect(), 1, 1000).unwrap();
+        let mut mixer = Mixer::new(1000);
+        mixer.music().play(
+            Box::new(SoundStream::new(ramp)),
+            MusicOptions::default().with_loop_points(2, Some(6)),
+        );
+
+        let mut out = [0.0; 24];
+        mixer.mix(&mut out);
+        let left: Vec<f32> = out.iter().step_by(2).copied().collect();
+        assert_eq!(
+            left,
+            [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 2.0, 3.0, 4.0, 5.0, 2.0, 3.0]
+        );
+        assert_eq!(out[1], out[0]);
+
+        mixer.music().paused = true;
+        mixer.mix(&mut out);
+        assert!(out.iter().all(|s| *s == 0.0));
+    }
+
+    #[test]
+    fn music_crossfades_between_tracks() {
+        let constant = |value: f32| {
+            let sound = Sound::new(vec![value; 200], 2, 1000).unwrap();
+            Box::new(SoundStream::new(sound))
+        };
+        let mut mixer = Mixer::new(1000);
+        mixer.music().play(constant(1.0), MusicOptions::default());
+        mixer.music().crossfade(
+            constant(0.5),
+            MusicOptions::default(),
+            Duration::from_millis(10),
+        );
+
+        let mut out = [0.0; 40];
+        mixer.mix(&mut out);
+        assert_eq!(out[0], 1.0);
+        assert!((out[10] - 0.75).abs() < 1e-5);
+        assert_eq!(out[38], 0.5);
+        assert!(mixer.music().is_playing());
+    }
+}
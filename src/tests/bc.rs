@@ -0,0 +1,62 @@
+#[cfg(test)]
+mod tests {
+    use crate::sys::bc::{
+        block_count, decode_bc1, decode_bc3, encode_bc1, encode_bc3, BC1_BLOCK_BYTES,
+        BC3_BLOCK_BYTES,
+    };
+
+    /// Colors on a line through RGB space, which is what a BC1 block can represent.
+    fn gradient(width: u32, height: u32, alpha: impl Fn(u32, u32) -> u8) -> Vec<u8> {
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                let t = (x * 16) as u8;
+                [t, 255 - t, 64, alpha(x, y)]
+            })
+            .collect()
+    }
+
+    fn max_error(a: &[u8], b: &[u8], channels: std::ops::Range<usize>) -> u8 {
+        a.chunks_exact(4)
+            .zip(b.chunks_exact(4))
+            .flat_map(|(a, b)| channels.clone().map(move |c| a[c].abs_diff(b[c])))
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn solid_blocks_are_exact() {
+        // 565 representable color.
+        let rgba = [255, 0, 255, 255].repeat(16);
+        let blocks = encode_bc1(&rgba, 4, 4);
+        assert_eq!(blocks.len(), BC1_BLOCK_BYTES);
+        assert_eq!(decode_bc1(&blocks, 4, 4), rgba);
+    }
+
+    #[test]
+    fn gradients_stay_close() {
+        let rgba = gradient(16, 16, |_, _| 255);
+        let decoded = decode_bc1(&encode_bc1(&rgba, 16, 16), 16, 16);
+        assert!(max_error(&rgba, &decoded, 0..3) <= 8);
+        assert!(decoded.chunks_exact(4).all(|p| p[3] == 255));
+
+        let rgba = gradient(16, 16, |x, y| ((x + y) * 8) as u8);
+        let blocks = encode_bc3(&rgba, 16, 16);
+        assert_eq!(blocks.len(), 16 * BC3_BLOCK_BYTES);
+        let decoded = decode_bc3(&blocks, 16, 16);
+        assert!(max_error(&rgba, &decoded, 0..3) <= 8);
+        assert!(max_error(&rgba, &decoded, 3..4) <= 4);
+    }
+
+    #[test]
+    fn partial_blocks() {
+        assert_eq!((block_count(0), block_count(1), block_count(5)), (0, 1, 2));
+        let rgba = gradient(5, 3, |x, _| if x < 2 { 0 } else { 255 });
+        let blocks = encode_bc3(&rgba, 5, 3);
+        assert_eq!(blocks.len(), 2 * BC3_BLOCK_BYTES);
+        let decoded = decode_bc3(&blocks, 5, 3);
+        assert_eq!(decoded.len(), rgba.len());
+        assert_eq!(max_error(&rgba, &decoded, 3..4), 0);
+        assert!(encode_bc1(&[], 0, 0).is_empty());
+    }
+}
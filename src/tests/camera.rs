@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod tests {
+    use cgmath::{Deg, InnerSpace, Matrix4, Point3, Vector4};
+
+    use crate::{
+        gfx::{
+            camera::{Camera, CameraUniform, Projection},
+            gizmo::Ray,
+        },
+        sys::rand::Rng,
+    };
+
+    fn assert_mat_eq(a: Matrix4<f32>, b: [[f32; 4]; 4]) {
+        let a: [[f32; 4]; 4] = a.into();
+        for (col_a, col_b) in a.iter().zip(b) {
+            for (x, y) in col_a.iter().zip(col_b) {
+                assert!((x - y).abs() < 1e-5, "{:?} != {:?}", a, b);
+            }
+        }
+    }
+
+    /// At (0, 0, 5) looking down -z.
+    fn camera() -> Camera {
+        Camera::new((0.0, 0.0, 5.0), Deg(-90.0), Deg(0.0))
+    }
+
+    /// Square viewport, 90 degree fov, near 1 and far 3.
+    fn projection() -> Projection {
+        Projection::new(100, 100, Deg(90.0), 1.0, 3.0)
+    }
+
+    fn project(view_proj: Matrix4<f32>, p: Point3<f32>, viewport: [f32; 2]) -> [f32; 3] {
+        let clip = view_proj * Vector4::new(p.x, p.y, p.z, 1.0);
+        let ndc = clip.truncate() / clip.w;
+        [
+            (ndc.x + 1.0) * 0.5 * viewport[0],
+            (1.0 - ndc.y) * 0.5 * viewport[1],
+            ndc.z,
+        ]
+    }
+
+    #[test]
+    fn view_matrix_golden() {
+        assert_mat_eq(
+            camera().calc_view_matrix(),
+            [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, -5.0, 1.0],
+            ],
+        );
+        // Yaw 0 looks down +x, so world -z ends up on the camera's left.
+        assert_mat_eq(
+            Camera::new((0.0, 0.0, 0.0), Deg(0.0), Deg(0.0)).calc_view_matrix(),
+            [
+                [0.0, 0.0, -1.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        );
+    }
+
+    #[test]
+    fn projection_golden() {
+        // The OpenGL matrix maps depth to [-1, 1], OPENGL_TO_WGPU remaps it to [0, 1].
+        assert_mat_eq(
+            projection().calc_matrix(),
+            [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, -1.5, -1.0],
+                [0.0, 0.0, -1.5, 0.0],
+            ],
+        );
+
+        let mut wide = projection();
+        wide.resize(200, 100);
+        assert!((wide.calc_matrix().x.x - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn uniform_from_camera() {
+        let uniform = CameraUniform::from_camera(&camera(), &projection());
+        assert_eq!(uniform.view_position(), Point3::new(0.0, 0.0, 5.0));
+        assert_mat_eq(
+            uniform.view_proj(),
+            [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, -1.5, -1.0],
+                [0.0, 0.0, 6.0, 5.0],
+            ],
+        );
+
+        let viewport = [100.0, 100.0];
+        let near = project(uniform.view_proj(), Point3::new(0.0, 0.0, 4.0), viewport);
+        let far = project(uniform.view_proj(), Point3::new(0.0, 0.0, 2.0), viewport);
+        assert!((near[2] - 0.0).abs() < 1e-5 && (far[2] - 1.0).abs() < 1e-5);
+        assert!((near[0] - 50.0).abs() < 1e-3 && (near[1] - 50.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn screen_world_round_trip() {
+        let camera = Camera::new((1.0, 2.0, 3.0), Deg(-120.0), Deg(-20.0));
+        let projection = Projection::new(1280, 720, Deg(60.0), 0.1, 100.0);
+        let view_proj = CameraUniform::from_camera(&camera, &projection).view_proj();
+        let viewport = [1280.0, 720.0];
+
+        let mut rng = Rng::new(7);
+        for _ in 0..64 {
+            let distance = rng.range_f32(0.5..50.0);
+            let offset = rng.in_unit_sphere() * distance * 0.3;
+            let p = camera.position() + camera.forward() * distance + offset;
+            let [x, y, _] = project(view_proj, p, viewport);
+
+            let ray = Ray::from_screen([x, y], viewport, view_proj).unwrap();
+            let to_p = p - ray.origin;
+            let miss = (to_p - ray.dir * to_p.dot(ray.dir)).magnitude();
+            assert!(miss < 1e-3 * distance, "{:?} missed by {}", p, miss);
+        }
+    }
+}
@@ -0,0 +1,31 @@
+#[cfg(all(test, feature = "renderdoc"))]
+mod tests {
+    use crate::eng::capture::GpuCapture;
+
+    #[test]
+    fn errors_capture_the_next_frame_up_to_the_limit() {
+        let mut capture = GpuCapture::disabled();
+        capture.set_auto_capture(true);
+        capture.set_max_auto_captures(2);
+
+        assert!(capture.capture_on_error("surface error Lost"));
+        // Already pending, a second error in the same frame doesn't count.
+        assert!(!capture.capture_on_error("surface error Lost"));
+        assert!(capture.is_pending());
+        // Without RenderDoc nothing is captured, but the request is consumed.
+        assert!(!capture.begin_frame());
+        assert!(!capture.is_pending());
+        capture.end_frame();
+
+        assert!(capture.capture_on_error("surface error Validation"));
+        capture.begin_frame();
+        assert!(!capture.capture_on_error("surface error Validation"));
+        assert_eq!(capture.auto_captures(), 2);
+
+        capture.capture_next_frame();
+        assert!(capture.is_pending());
+        capture.set_auto_capture(false);
+        capture.begin_frame();
+        assert!(!capture.capture_on_error("surface error Lost"));
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use cgmath::{Deg, Matrix4, Point3, Vector3};
+
+    use crate::gfx::{
+        camera::Projection,
+        cluster::{ClusterGrid, ClusterView, PointLight, MAX_LIGHTS_PER_CLUSTER},
+    };
+
+    fn view() -> ClusterView {
+        let projection = Projection::new(1600, 900, Deg(60.0), 0.1, 100.0);
+        ClusterView {
+            view: Matrix4::look_to_rh(
+                Point3::new(0.0, 0.0, 0.0),
+                -Vector3::unit_z(),
+                Vector3::unit_y(),
+            ),
+            proj: projection.calc_matrix(),
+            near: projection.znear(),
+            far: projection.zfar(),
+            screen: [1600, 900],
+        }
+    }
+
+    #[test]
+    fn lights_land_only_in_the_clusters_they_touch() {
+        let grid = ClusterGrid::default();
+        let view = view();
+        assert_eq!(grid.slice(&view, 0.1), 0);
+        assert_eq!(grid.slice(&view, 100.0), 23);
+
+        let near = Point3::new(0.0, 0.0, -10.0);
+        let far = Point3::new(2.0, 1.0, -90.0);
+        let lights = [
+            PointLight::new(near, 1.0),
+            PointLight::new(far, 0.5),
+            // Behind the camera.
+            PointLight::new(Point3::new(0.0, 0.0, 5.0), 1.0),
+        ];
+        let lists = grid.assign(&view, &lights);
+        let near_cluster = grid.cluster_at(&view, near).unwrap();
+        let far_cluster = grid.cluster_at(&view, far).unwrap();
+        assert_eq!(lists.lights(near_cluster), [0]);
+        assert_eq!(lists.lights(far_cluster), [1]);
+        assert!(!lists.indices.is_empty());
+        assert!((0..grid.len()).all(|c| !lists.lights(c).contains(&2)));
+        let touched = (0..grid.len()).filter(|&c| lists.counts[c] > 0).count();
+        assert!(touched < grid.len() / 20);
+        assert_eq!(grid.cluster_at(&view, Point3::new(0.0, 0.0, 5.0)), None);
+
+        let crowd = vec![PointLight::new(near, 0.5); MAX_LIGHTS_PER_CLUSTER + 10];
+        let lists = grid.assign(&view, &crowd);
+        assert_eq!(lists.lights(near_cluster).len(), MAX_LIGHTS_PER_CLUSTER);
+    }
+}
@@ -0,0 +1,48 @@
+#[cfg(test)]
+mod tests {
+    use crate::eng::command::{PassRecorder, RenderCommand};
+
+    fn names(commands: &[RenderCommand]) -> Vec<&'static str> {
+        commands.iter().map(RenderCommand::name).collect()
+    }
+
+    #[test]
+    fn recorder_appends_to_the_pass_queue() {
+        let mut queue = vec![RenderCommand::PushDebugGroup(String::from("Frame"))];
+        let mut recorder = PassRecorder::new(&mut queue);
+        recorder.set_scissor_rect(0, 0, 64, 64);
+        recorder.draw(0..3, 0..1);
+        recorder.draw_indexed(0..6, 0, 0..2);
+        assert_eq!(recorder.commands().len(), 4);
+        drop(recorder);
+        assert_eq!(
+            names(&queue),
+            ["PushDebugGroup", "SetScissorRect", "Draw", "DrawIndexed"]
+        );
+    }
+
+    #[test]
+    fn recorder_closes_only_its_own_debug_groups() {
+        let mut queue = vec![RenderCommand::PushDebugGroup(String::from("Frame"))];
+        {
+            let mut recorder = PassRecorder::new(&mut queue);
+            // The group opened before the recorder isn't its to pop.
+            recorder.pop_debug_group();
+            recorder.push_debug_group("Sprites");
+            recorder.push_debug_group("Batch");
+            recorder.draw(0..6, 0..1);
+            recorder.pop_debug_group();
+        }
+        assert_eq!(
+            names(&queue),
+            [
+                "PushDebugGroup",
+                "PushDebugGroup",
+                "PushDebugGroup",
+                "Draw",
+                "PopDebugGroup",
+                "PopDebugGroup",
+            ]
+        );
+    }
+}
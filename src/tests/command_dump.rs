@@ -0,0 +1,102 @@
+#[cfg(test)]
+mod tests {
+    use crate::eng::command_dump::{diff_lines, CommandDump, DiffLine, PassDump};
+
+    fn pass(label: &str, commands: &[&str]) -> PassDump {
+        PassDump {
+            kind: String::from("render"),
+            label: String::from(label),
+            target: String::from("surface"),
+            op: String::from("clear 0, 0, 0, 1, no depth"),
+            commands: commands.iter().map(|c| String::from(*c)).collect(),
+        }
+    }
+
+    #[test]
+    fn dumps_round_trip_through_json() {
+        let dump = CommandDump {
+            frame: 42,
+            passes: vec![
+                pass(
+                    "Main",
+                    &["SetPipeline(pipeline 'Sprites \"2D\"')", "Draw(0..6, 0..1)"],
+                ),
+                pass("Empty", &[]),
+            ],
+        };
+        assert_eq!(CommandDump::from_json(&dump.to_json()).unwrap(), dump);
+        assert_eq!(
+            CommandDump::from_json(&CommandDump::default().to_json()).unwrap(),
+            CommandDump::default()
+        );
+        assert!(CommandDump::from_json("{\"frame\": 1, \"passes\": [").is_err());
+    }
+
+    #[test]
+    fn diff_matches_passes_and_commands() {
+        let working = CommandDump {
+            frame: 1,
+            passes: vec![
+                pass("Shadows", &["SetPipeline(pipeline#0)", "Draw(0..3, 0..1)"]),
+                pass(
+                    "Main",
+                    &[
+                        "SetPipeline(pipeline#1)",
+                        "SetBindGroup(0, bind_group#0)",
+                        "SetVertexBuffer(0, buffer#0 [96 B])",
+                        "Draw(0..6, 0..1)",
+                    ],
+                ),
+            ],
+        };
+        let broken = CommandDump {
+            frame: 2,
+            passes: vec![pass(
+                "Main",
+                &[
+                    "SetPipeline(pipeline#1)",
+                    "SetVertexBuffer(0, buffer#0 [96 B])",
+                    "Draw(0..6, 0..1)",
+                ],
+            )],
+        };
+        assert!(working.diff(&working).is_empty());
+
+        let diff = working.diff(&broken);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.passes.len(), 2);
+        assert!(diff.passes[0]
+            .lines
+            .iter()
+            .all(|line| matches!(line, DiffLine::Removed(_))));
+        assert_eq!(
+            diff.passes[1]
+                .lines
+                .iter()
+                .filter(|line| line.is_change())
+                .collect::<Vec<_>>(),
+            [&DiffLine::Removed(String::from(
+                "SetBindGroup(0, bind_group#0)"
+            ))]
+        );
+        assert!(diff.to_string().contains("- SetBindGroup(0, bind_group#0)"));
+    }
+
+    #[test]
+    fn diff_lines_keeps_common_lines() {
+        let lines = |s: &str| s.split(' ').map(String::from).collect::<Vec<_>>();
+        let diff = diff_lines(&lines("a b c d e"), &lines("a x c e f"));
+        assert_eq!(
+            diff,
+            [
+                DiffLine::Same(String::from("a")),
+                DiffLine::Removed(String::from("b")),
+                DiffLine::Added(String::from("x")),
+                DiffLine::Same(String::from("c")),
+                DiffLine::Removed(String::from("d")),
+                DiffLine::Same(String::from("e")),
+                DiffLine::Added(String::from("f")),
+            ]
+        );
+    }
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use crate::gfx::{
+        compute::{
+            average_log_luminance, blur_radius, downsample_sizes, gaussian_weights, histogram_bins,
+            luminance_bin, scan_levels, HISTOGRAM_BINS, MAX_BLUR_RADIUS,
+        },
+        wgpu::shader::check_wgsl,
+    };
+
+    #[test]
+    fn kernels_are_valid_wgsl() {
+        check_wgsl(
+            "compute_blur.wgsl",
+            include_str!("../shaders/compute_blur.wgsl"),
+        )
+        .unwrap();
+        check_wgsl(
+            "compute_downsample.wgsl",
+            include_str!("../shaders/compute_downsample.wgsl"),
+        )
+        .unwrap();
+        check_wgsl(
+            "compute_histogram.wgsl",
+            include_str!("../shaders/compute_histogram.wgsl"),
+        )
+        .unwrap();
+        check_wgsl(
+            "compute_scan.wgsl",
+            include_str!("../shaders/compute_scan.wgsl"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn gaussian_weights_are_normalized_and_falling() {
+        let weights = gaussian_weights(2.0, blur_radius(2.0));
+        assert_eq!(weights.len(), 7);
+        let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+        assert!((total - 1.0).abs() < 1e-5);
+        assert!(weights.windows(2).all(|w| w[0] > w[1]));
+        assert_eq!(blur_radius(100.0), MAX_BLUR_RADIUS);
+        assert_eq!(gaussian_weights(1.0, 0), vec![1.0]);
+    }
+
+    #[test]
+    fn downsample_halves_until_one_texel() {
+        assert_eq!(downsample_sizes(10, 3, 8), vec![(5, 1), (2, 1), (1, 1)]);
+        assert_eq!(downsample_sizes(64, 64, 2), vec![(32, 32), (16, 16)]);
+        assert!(downsample_sizes(1, 1, 4).is_empty());
+    }
+
+    #[test]
+    fn luminance_bins_cover_the_log_range() {
+        assert_eq!(luminance_bin([0.0; 3], -8.0, 4.0), 0);
+        assert_eq!(luminance_bin([2f32.powi(-10); 3], -8.0, 4.0), 1);
+        assert_eq!(luminance_bin([100.0; 3], -8.0, 4.0), 255);
+        let mid = luminance_bin([2f32.powi(-2); 3], -8.0, 4.0);
+        assert!((127..=128).contains(&mid));
+
+        let mut bins = vec![0; HISTOGRAM_BINS];
+        bins[0] = 50;
+        bins[mid] = 10;
+        assert!((average_log_luminance(&bins, -8.0, 4.0) + 2.0).abs() < 0.05);
+        assert_eq!(average_log_luminance(&[3, 0, 0], -8.0, 4.0), -8.0);
+    }
+
+    #[test]
+    fn histogram_bytes_decode_to_counts() {
+        let bytes: Vec<u8> = [1u32, 2, 300]
+            .iter()
+            .flat_map(|n| n.to_ne_bytes())
+            .collect();
+        assert_eq!(histogram_bins(&bytes), vec![1, 2, 300]);
+    }
+
+    #[test]
+    fn scan_levels_reduce_to_one_block() {
+        assert_eq!(scan_levels(0), vec![1]);
+        assert_eq!(scan_levels(256), vec![256]);
+        assert_eq!(scan_levels(1000), vec![1000, 4]);
+        assert_eq!(scan_levels(70_000), vec![70_000, 274, 2]);
+    }
+}
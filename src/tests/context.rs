@@ -0,0 +1,45 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::eng::context::EngineContext;
+
+    #[test]
+    fn time_scale() {
+        let engine = EngineContext::new();
+        engine.set_time_scale(0.5);
+        assert_eq!(
+            engine.begin_frame(Duration::from_millis(20)),
+            Some(Duration::from_millis(10))
+        );
+        engine.set_time_scale(-1.0);
+        assert_eq!(engine.time_scale(), 0.0);
+    }
+
+    #[test]
+    fn pause_and_step() {
+        let engine = EngineContext::new();
+        engine.set_paused(true);
+        assert_eq!(engine.begin_frame(Duration::from_millis(16)), None);
+
+        engine.step();
+        assert_eq!(
+            engine.begin_frame(Duration::from_millis(16)),
+            Some(engine.fixed_timestep())
+        );
+        assert_eq!(engine.begin_frame(Duration::from_millis(16)), None);
+        assert_eq!(engine.frame_count(), 3);
+    }
+
+    #[test]
+    fn fixed_steps() {
+        let engine = EngineContext::new();
+        engine.set_fixed_timestep(Duration::from_millis(10));
+        assert_eq!(engine.fixed_steps(Duration::from_millis(25)), 2);
+        assert_eq!(engine.fixed_steps(Duration::from_millis(5)), 1);
+        assert_eq!(
+            engine.fixed_steps(Duration::from_secs(10)),
+            EngineContext::MAX_FIXED_STEPS
+        );
+    }
+}
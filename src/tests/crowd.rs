@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod tests {
+    use crate::gfx::crowd::{AnimationFrame, CrowdLod, VertexAnimationData};
+
+    #[test]
+    fn clips_are_laid_out_row_after_row_and_loop_or_hold() {
+        let mut data = VertexAnimationData::new(2);
+        let frames = |n: usize| vec![vec![[0.0; 3]; 2]; n];
+        let idle = data.add_clip("idle", 10.0, true, &frames(4)).unwrap();
+        let fall = data.add_clip("fall", 10.0, false, &frames(3)).unwrap();
+        assert_eq!((idle, fall), (0, 1));
+        assert_eq!(data.frame_count(), 7);
+        assert_eq!(data.clip("fall"), Some(1));
+        assert!(data.add_clip("bad", 10.0, true, &[vec![[0.0; 3]]]).is_err());
+
+        let idle = &data.clips()[0];
+        let frame = idle.sample(0.35);
+        assert_eq!((frame.row, frame.next_row), (3, 0));
+        assert!((frame.blend - 0.5).abs() < 1e-4);
+        assert_eq!(idle.sample(0.4).row, 0);
+
+        let fall = &data.clips()[1];
+        assert_eq!(fall.sample(0.15).row, 5);
+        assert_eq!(
+            fall.sample(10.0),
+            AnimationFrame {
+                row: 6,
+                next_row: 6,
+                blend: 0.0
+            }
+        );
+    }
+
+    #[test]
+    fn far_instances_update_less_often_and_are_staggered() {
+        let lod = CrowdLod::new(&[(50.0, 2), (10.0, 1)]);
+        assert_eq!(lod.interval(5.0), 1);
+        assert_eq!(lod.interval(30.0), 2);
+        assert_eq!(lod.interval(500.0), 2);
+        assert_eq!(CrowdLod::full().interval(500.0), 1);
+
+        let due = |index| (0..8).filter(|&tick| lod.is_due(30.0, tick, index)).count();
+        assert_eq!(due(0), 4);
+        assert!(lod.is_due(30.0, 0, 0) && !lod.is_due(30.0, 0, 1));
+        assert!((0..8).all(|tick| lod.is_due(5.0, tick, 3)));
+    }
+}
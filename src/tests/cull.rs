@@ -0,0 +1,27 @@
+#[cfg(test)]
+mod tests {
+    use cgmath::{perspective, Deg, Matrix4, Point3, Vector3};
+
+    use crate::{gfx::cull::Frustum, sys::math::OPENGL_TO_WGPU_MATRIX};
+
+    fn frustum() -> Frustum {
+        let proj = OPENGL_TO_WGPU_MATRIX * perspective(Deg(90.0), 1.0, 0.1, 100.0);
+        let view = Matrix4::look_to_rh(
+            Point3::new(0.0, 0.0, 0.0),
+            -Vector3::unit_z(),
+            Vector3::unit_y(),
+        );
+        Frustum::from_view_proj(proj * view)
+    }
+
+    #[test]
+    fn spheres_are_culled_against_every_plane() {
+        let frustum = frustum();
+        assert!(frustum.intersects_sphere(Point3::new(0.0, 0.0, -10.0), 1.0));
+        assert!(!frustum.intersects_sphere(Point3::new(0.0, 0.0, 10.0), 1.0));
+        // Outside the 90 degree cone unless the radius reaches back in.
+        assert!(!frustum.intersects_sphere(Point3::new(-20.0, 0.0, -10.0), 1.0));
+        assert!(frustum.intersects_sphere(Point3::new(-20.0, 0.0, -10.0), 8.0));
+        assert!(!frustum.intersects_sphere(Point3::new(0.0, 20.0, -10.0), 1.0));
+    }
+}
@@ -0,0 +1,32 @@
+#[cfg(test)]
+mod tests {
+    use crate::gfx::post::custom::validate_custom_effect;
+
+    const WAVE: &str = r#"
+struct WaveParams { amplitude: f32, frequency: f32, _pad: vec2<f32> };
+@group(1) @binding(1)
+var<uniform> params: WaveParams;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let offset = sin(in.uv.y * params.frequency + frame.time) * params.amplitude / frame.resolution.x;
+    return textureSample(t_input, s_input, in.uv + vec2<f32>(offset, 0.0));
+}
+"#;
+
+    #[test]
+    fn custom_effect_reflects_params_and_rejects_bad_interfaces() {
+        assert_eq!(validate_custom_effect(WAVE).unwrap().params_size, 16);
+
+        let no_params = "@fragment fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> { return textureSample(t_input, s_input, in.uv); }";
+        assert_eq!(validate_custom_effect(no_params).unwrap().params_size, 0);
+
+        assert!(validate_custom_effect("fn helper() {}").is_err());
+        assert!(validate_custom_effect("@fragment fn fs_main( -> {").is_err());
+        let extra_binding = format!(
+            "@group(2) @binding(0) var<uniform> extra: vec4<f32>;\n{}",
+            no_params.replace("in.uv)", "in.uv) * extra")
+        );
+        assert!(validate_custom_effect(&extra_binding).is_err());
+    }
+}
@@ -0,0 +1,35 @@
+#[cfg(test)]
+mod tests {
+    use crate::gfx::debug_name::{DebugId, DebugName};
+
+    struct Named(&'static str);
+
+    impl DebugName for Named {
+        fn debug_kind(&self) -> &'static str {
+            "Mesh"
+        }
+
+        fn debug_name(&self) -> &str {
+            self.0
+        }
+    }
+
+    #[test]
+    fn ids_are_stable_and_labels_name_their_kind() {
+        // FNV-1a of "Mesh" then "crate", fixed so captures of old builds still line up.
+        let id = Named("crate").debug_id();
+        assert_eq!(id, DebugId::from_name("Mesh").with("crate"));
+        assert_eq!(id.to_string(), "afb7fe18");
+        assert_eq!(Named("crate").debug_label(), format!("Mesh crate #{}", id));
+        assert_eq!(
+            Named("").debug_label(),
+            format!("Mesh <unnamed> #{}", Named("").debug_id())
+        );
+
+        assert_ne!(
+            DebugId::from_name("ab").with("c"),
+            DebugId::from_name("a").with("bc")
+        );
+        assert_ne!(Named("crate").debug_id(), Named("barrel").debug_id());
+    }
+}
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cgmath::{Matrix4, Point3, SquareMatrix, Vector3};
+
+    use crate::gfx::{
+        decal::{clip_decal, Decal, DecalTarget, Decals},
+        wgpu::vertex::Vertex3D,
+    };
+
+    /// Two triangles covering the XZ plane from -10 to 10, facing up.
+    fn floor() -> (Vec<Vertex3D>, Vec<u32>) {
+        let vertex = |x: f32, z: f32| Vertex3D {
+            position: [x, 0.0, z],
+            ..Default::default()
+        };
+        let vertices = vec![
+            vertex(-10.0, -10.0),
+            vertex(-10.0, 10.0),
+            vertex(10.0, 10.0),
+            vertex(10.0, -10.0),
+        ];
+        (vertices, vec![0, 1, 2, 0, 2, 3])
+    }
+
+    #[test]
+    fn decal_is_clipped_to_its_box() {
+        let (vertices, indices) = floor();
+        let target = DecalTarget {
+            vertices: &vertices,
+            indices: &indices,
+            transform: Matrix4::identity(),
+        };
+        let decal = Decal::new(
+            Point3::new(1.0, 0.0, 2.0),
+            Vector3::unit_y(),
+            Vector3::new(2.0, 2.0, 1.0),
+        );
+        let clipped = clip_decal(&decal, &target);
+        assert!(!clipped.is_empty() && clipped.len().is_multiple_of(3));
+        for v in &clipped {
+            assert!((0.0 - 1e-4..=2.0 + 1e-4).contains(&v.position[0]));
+            assert!((1.0 - 1e-4..=3.0 + 1e-4).contains(&v.position[2]));
+            assert!(v.position[1].abs() < 1e-4);
+            assert!(v.uv.iter().all(|uv| (-1e-4..=1.0 + 1e-4).contains(uv)));
+        }
+
+        // Projected from below, the floor faces away.
+        let below = Decal {
+            normal: -Vector3::unit_y(),
+            ..decal
+        };
+        assert!(clip_decal(&below, &target).is_empty());
+    }
+
+    #[test]
+    fn budget_and_fade() {
+        let (vertices, indices) = floor();
+        let targets = [DecalTarget {
+            vertices: &vertices,
+            indices: &indices,
+            transform: Matrix4::identity(),
+        }];
+        let mut decals = Decals::new(2);
+        let decal = Decal::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Vector3::unit_y(),
+            Vector3::new(1.0, 1.0, 1.0),
+        )
+        .with_lifetime(Duration::from_secs(2))
+        .with_fade_out(Duration::from_secs(1));
+        for _ in 0..3 {
+            assert!(decals.spawn(&decal, &targets));
+        }
+        assert_eq!(decals.len(), 2);
+
+        decals.frame_update(Duration::from_millis(1500));
+        assert!(decals
+            .vertices()
+            .iter()
+            .all(|v| (v.color[3] - 0.5).abs() < 1e-4));
+        decals.frame_update(Duration::from_millis(500));
+        assert!(decals.is_empty());
+    }
+}
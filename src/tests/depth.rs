@@ -0,0 +1,26 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        eng::command::validate_depth,
+        gfx::wgpu::{
+            shader::{DepthMode, PipelineOptions},
+            texture::Texture,
+        },
+    };
+
+    #[test]
+    fn disabled_depth_only_matches_passes_without_depth() {
+        let depth = Some(Texture::DEPTH_FORMAT);
+        let ui = PipelineOptions::default().with_depth(DepthMode::Disabled);
+        let scene = PipelineOptions::default().with_depth(DepthMode::ReadOnly);
+
+        assert_eq!(ui.depth_format(depth), None);
+        assert_eq!(scene.depth_format(depth), depth);
+
+        assert!(validate_depth(None, ui.depth_format(depth)).is_ok());
+        assert!(validate_depth(depth, scene.depth_format(depth)).is_ok());
+        assert!(validate_depth(depth, ui.depth_format(depth)).is_err());
+        assert!(validate_depth(None, scene.depth_format(depth)).is_err());
+        assert!(validate_depth(depth, Some(wgpu::TextureFormat::Depth24Plus)).is_err());
+    }
+}
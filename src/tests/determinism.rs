@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector3;
+
+    use crate::{
+        eng::{
+            context::EngineContext,
+            determinism::{first_divergence, DeterminismAudit, StateHasher},
+        },
+        gfx::transform::Transform,
+        sys::rand::RngStreams,
+    };
+
+    fn run(engine: &EngineContext, ticks: u64, nudge_at: Option<u64>) {
+        let mut rng = RngStreams::new(7);
+        let mut transform = Transform::default();
+        for tick in 0..ticks {
+            let step = rng.gameplay().range_f32(0.0..1.0);
+            let mut position = transform.position() + Vector3::new(step, 0.0, 0.0);
+            if nudge_at == Some(tick) {
+                position.y += 0.001;
+            }
+            transform.set_position(position);
+            engine.audit_tick(|t| {
+                t.section("rng", &rng).section("transforms", &transform);
+            });
+        }
+    }
+
+    #[test]
+    fn replay_reports_first_divergent_tick() {
+        let engine = EngineContext::new();
+        run(&engine, 10, None);
+        assert!(!engine.is_auditing());
+
+        engine.set_determinism_audit(Some(DeterminismAudit::new()));
+        run(&engine, 10, None);
+        let live = engine.set_determinism_audit(None).unwrap();
+        let hashes = DeterminismAudit::hashes_from_bytes(&live.to_bytes()).unwrap();
+        assert_eq!(hashes, live.hashes());
+
+        engine.set_determinism_audit(Some(DeterminismAudit::new().with_reference(hashes)));
+        run(&engine, 10, Some(6));
+        let replay = engine.set_determinism_audit(None).unwrap();
+        let divergence = replay.divergence().unwrap();
+        assert_eq!(divergence.tick, 6);
+        assert_eq!(divergence.sections, ["transforms"]);
+        assert_eq!(
+            first_divergence(live.hashes(), replay.hashes()).as_ref(),
+            Some(divergence)
+        );
+
+        // -0.0 and 0.0 hash the same.
+        let (mut a, mut b) = (StateHasher::new(), StateHasher::new());
+        a.write_f32(0.0);
+        b.write_f32(-0.0);
+        assert_eq!(a.finish(), b.finish());
+    }
+}
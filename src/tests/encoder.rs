@@ -0,0 +1,21 @@
+#[cfg(test)]
+mod tests {
+    use crate::eng::encoder::{EncoderPool, SubmissionStats};
+
+    #[test]
+    fn stats_roll_over_each_frame() {
+        let pool = EncoderPool::new();
+        pool.record_pass(3);
+        pool.record_pass(4);
+        assert_eq!(pool.stats().passes, 2);
+        assert_eq!(pool.stats().commands, 7);
+
+        pool.end_frame();
+        assert_eq!(pool.stats(), SubmissionStats::default());
+        assert_eq!(pool.last_frame_stats().commands, 7);
+        assert_eq!(pool.pending(), 0);
+
+        pool.set_max_passes_per_submission(Some(0));
+        assert_eq!(pool.max_passes_per_submission(), Some(1));
+    }
+}
@@ -0,0 +1,48 @@
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector3;
+
+    use crate::gfx::environment::{Fog, Sky};
+
+    #[test]
+    fn fog_factor_by_mode() {
+        let linear = Fog::Linear {
+            start: 10.0,
+            end: 20.0,
+        };
+        assert_eq!(linear.factor(5.0), 0.0);
+        assert_eq!(linear.factor(15.0), 0.5);
+        assert_eq!(linear.factor(30.0), 1.0);
+        assert_eq!(Fog::None.factor(1000.0), 0.0);
+
+        let exp = Fog::Exponential { density: 0.1 };
+        let exp2 = Fog::ExponentialSquared { density: 0.1 };
+        assert!((exp.factor(10.0) - (1.0 - (-1.0f32).exp())).abs() < 1e-5);
+        // Squared stays clearer up close and catches up at distance.
+        assert!(exp2.factor(5.0) < exp.factor(5.0));
+        assert!(exp2.factor(40.0) > 0.99);
+    }
+
+    #[test]
+    fn sky_colors() {
+        let gradient = Sky::default();
+        let Sky::Gradient {
+            zenith, horizon, ..
+        } = gradient
+        else {
+            unreachable!()
+        };
+        assert_eq!(gradient.color(Vector3::unit_y()), zenith);
+        assert_eq!(gradient.color(Vector3::unit_x()), horizon);
+
+        let preetham = Sky::Preetham {
+            sun_direction: [0.0, 0.5, 1.0],
+            turbidity: 3.0,
+            exposure: 0.1,
+        };
+        let up = preetham.color(Vector3::unit_y());
+        assert!(up.iter().all(|c| c.is_finite() && *c >= 0.0));
+        // A clear day sky is bluer than it is red.
+        assert!(up[2] > up[0]);
+    }
+}
@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod tests {
+    use crate::gfx::font::BmFont;
+
+    const FNT: &str = r#"info face="Pixel Sans" size=-8 bold=0 italic=0
+common lineHeight=10 base=8 scaleW=64 scaleH=32 pages=2 packed=0
+page id=0 file="pixel_0.png"
+page id=1 file="pixel_1.png"
+chars count=3
+char id=65 x=0 y=0 width=6 height=8 xoffset=0 yoffset=1 xadvance=7 page=0 chnl=15
+char id=86 x=8 y=0 width=6 height=8 xoffset=1 yoffset=1 xadvance=7 page=1 chnl=15
+char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=4 page=0 chnl=15
+kernings count=1
+kerning first=65 second=86 amount=-2
+"#;
+
+    #[test]
+    fn text_and_binary_fnt_lay_out_with_kerning_across_pages() {
+        let font = BmFont::parse(FNT).unwrap();
+        assert_eq!(font.face, "Pixel Sans");
+        assert_eq!(font.pages, ["pixel_0.png", "pixel_1.png"]);
+        assert_eq!(font.kerning('A', 'V'), -2);
+
+        let quads = font.layout("AV A\nV", [10.0, 100.0], 2.0);
+        assert_eq!(quads.len(), 4);
+        assert_eq!(quads[0].page, 0);
+        assert_eq!(quads[0].position, [10.0, 82.0]);
+        assert_eq!(quads[0].size, [12.0, 16.0]);
+        // Kerning pulls V back 2 pixels, its own offset pushes it 1 forward.
+        assert_eq!(quads[1].page, 1);
+        assert_eq!(quads[1].position, [10.0 + 6.0 * 2.0, 82.0]);
+        assert_eq!(quads[1].uv.min, [8.0 / 64.0, 0.0]);
+        assert_eq!(quads[1].uv.max, [14.0 / 64.0, 8.0 / 32.0]);
+        assert_eq!(quads[2].position, [10.0 + 16.0 * 2.0, 82.0]);
+        assert_eq!(quads[3].position, [12.0, 62.0]);
+        assert_eq!(font.measure("AV A\nV"), [23, 20]);
+
+        let mut binary = b"BMF\x03".to_vec();
+        let mut block = |ty: u8, data: &[u8]| {
+            binary.push(ty);
+            binary.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            binary.extend_from_slice(data);
+        };
+        let mut info = vec![0; 14];
+        info[..2].copy_from_slice(&(-8i16).to_le_bytes());
+        info.extend_from_slice(b"Pixel Sans\0");
+        block(1, &info);
+        block(2, &[10, 0, 8, 0, 64, 0, 32, 0, 2, 0, 0, 0, 0, 0, 0]);
+        block(3, b"pixel_0.png\0pixel_1.png\0");
+        let mut chars = Vec::new();
+        for (id, x, xoffset, page) in [(65u32, 0u16, 0i16, 0u8), (86, 8, 1, 1), (32, 0, 0, 0)] {
+            let (w, h, yoffset) = if id == 32 {
+                (0u16, 0u16, 0i16)
+            } else {
+                (6, 8, 1)
+            };
+            chars.extend_from_slice(&id.to_le_bytes());
+            for v in [x, 0, w, h] {
+                chars.extend_from_slice(&v.to_le_bytes());
+            }
+            let advance: i16 = if id == 32 { 4 } else { 7 };
+            for v in [xoffset, yoffset, advance] {
+                chars.extend_from_slice(&v.to_le_bytes());
+            }
+            chars.extend_from_slice(&[page, 15]);
+        }
+        block(4, &chars);
+        let mut kerning = Vec::new();
+        kerning.extend_from_slice(&65u32.to_le_bytes());
+        kerning.extend_from_slice(&86u32.to_le_bytes());
+        kerning.extend_from_slice(&(-2i16).to_le_bytes());
+        block(5, &kerning);
+
+        assert_eq!(BmFont::from_bytes(&binary).unwrap(), font);
+        assert!(BmFont::parse("info face=x size=8").is_err());
+    }
+
+    #[test]
+    fn text_fnt_round_trips() {
+        let font = BmFont::parse(FNT).unwrap();
+        let text = font.to_text();
+        assert!(text.contains("kerning first=65 second=86 amount=-2"));
+        assert_eq!(BmFont::parse(&text).unwrap(), font);
+    }
+}
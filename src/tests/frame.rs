@@ -0,0 +1,36 @@
+#[cfg(test)]
+mod tests {
+    use crate::eng::frame::{FramePacing, FrameRing};
+
+    #[test]
+    fn slots_cycle_through_frames_in_flight() {
+        let pacing = FramePacing::new(3);
+        let slots: Vec<usize> = (0..5)
+            .map(|_| {
+                assert!(pacing.advance().is_none());
+                pacing.slot()
+            })
+            .collect();
+        assert_eq!(slots, [0, 1, 2, 0, 1]);
+        assert_eq!(pacing.frame_index(), 5);
+
+        pacing.set_frames_in_flight(1);
+        pacing.advance();
+        assert_eq!(pacing.slot(), 0);
+        assert_eq!(FramePacing::new(8).frames_in_flight(), 3);
+    }
+
+    #[test]
+    fn ring_follows_slot() {
+        let pacing = FramePacing::new(2);
+        let mut ring = FrameRing::for_pacing(&pacing, |slot| slot * 10);
+        assert_eq!(ring.len(), 2);
+
+        pacing.advance();
+        *ring.current_mut(&pacing) += 1;
+        pacing.advance();
+        assert_eq!(*ring.current(&pacing), 10);
+        pacing.advance();
+        assert_eq!(*ring.current(&pacing), 1);
+    }
+}
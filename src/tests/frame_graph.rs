@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod tests {
+    use crate::eng::frame_graph::{FrameGraph, FrameGraphExport, GraphPass, GraphResource};
+
+    fn resource(name: &str, kind: &str, size: &str, format: &str) -> GraphResource {
+        GraphResource {
+            name: String::from(name),
+            kind: String::from(kind),
+            size: String::from(size),
+            format: String::from(format),
+        }
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| String::from(*n)).collect()
+    }
+
+    fn graph() -> FrameGraph {
+        FrameGraph {
+            passes: vec![
+                GraphPass {
+                    kind: String::from("compute"),
+                    label: String::from("Cull"),
+                    op: String::new(),
+                    reads: Vec::new(),
+                    writes: Vec::new(),
+                    bindings: names(&["bind_group 'Cull'"]),
+                },
+                GraphPass {
+                    kind: String::from("render"),
+                    label: String::from("Main \"3D\""),
+                    op: String::from("clear"),
+                    reads: names(&["buffer#0"]),
+                    writes: names(&["surface", "depth#0"]),
+                    bindings: names(&["bind_group 'Cull'"]),
+                },
+            ],
+            resources: vec![
+                resource("bind_group 'Cull'", "bind_group", "", ""),
+                resource("buffer#0", "buffer", "64 B", ""),
+                resource("surface", "surface", "1280x720", "Bgra8UnormSrgb"),
+                resource("depth#0", "depth", "1280x720", "Depth32Float"),
+            ],
+        }
+    }
+
+    #[test]
+    fn dot_has_nodes_and_edges() {
+        let dot = graph().to_dot();
+        assert!(dot.starts_with("digraph frame {"));
+        assert!(dot.contains("pass1 [shape=box"));
+        assert!(dot.contains(r#"label="1: Main \"3D\"\nrender clear""#));
+        assert!(dot.contains(r#"label="0: Cull\ncompute""#));
+        assert!(dot.contains(r#"label="surface\n1280x720 Bgra8UnormSrgb""#));
+        assert!(dot.contains(r#"label="bind_group 'Cull'""#));
+        assert!(dot.contains("res1 -> pass1;"));
+        assert!(dot.contains("pass1 -> res2 [penwidth=2];"));
+        assert!(dot.contains("pass1 -> res3 [penwidth=2];"));
+        assert!(dot.contains("res0 -> pass0 [style=dashed, dir=none];"));
+        assert!(dot.trim_end().ends_with('}'));
+    }
+
+    #[test]
+    fn json_lists_passes_and_resources() {
+        let json = graph().to_json();
+        assert!(json.contains(r#""label": "Main \"3D\"""#));
+        assert!(json.contains(r#""writes": ["surface", "depth#0"]"#));
+        assert!(json.contains(r#""name": "buffer#0", "kind": "buffer", "size": "64 B""#));
+        assert_eq!(
+            FrameGraph::default().to_json(),
+            "{\n  \"passes\": [],\n  \"resources\": []\n}\n"
+        );
+    }
+
+    #[test]
+    fn export_only_writes_changes() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("radium_frame_graph_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let export = FrameGraphExport::new();
+        assert!(!export.update(graph())?);
+
+        export.set_path(Some(dir.join("frame")));
+        assert!(export.update(graph())?);
+        assert!(!export.update(graph())?);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("frame.dot"))?,
+            graph().to_dot()
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("frame.json"))?,
+            graph().to_json()
+        );
+
+        let mut changed = graph();
+        changed.passes.pop();
+        assert!(export.update(changed.clone())?);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("frame.json"))?,
+            changed.to_json()
+        );
+
+        export.set_path(Some(dir.join("frame")));
+        assert!(export.update(changed)?);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
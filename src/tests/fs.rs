@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsString, path::PathBuf};
+
+    use crate::sys::fs::{user_dir_from_env, write_atomic, UserDir};
+
+    #[test]
+    fn user_dirs_per_platform() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |key: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| OsString::from(v))
+            }
+        };
+        let linux = env(&[("HOME", "/home/me"), ("XDG_CACHE_HOME", "/tmp/cache")]);
+        assert_eq!(
+            user_dir_from_env(UserDir::Config, "game", "linux", linux).unwrap(),
+            PathBuf::from("/home/me/.config/game")
+        );
+        assert_eq!(
+            user_dir_from_env(UserDir::Cache, "game", "linux", linux).unwrap(),
+            PathBuf::from("/tmp/cache/game")
+        );
+        assert_eq!(
+            user_dir_from_env(UserDir::Cache, "game", "macos", linux).unwrap(),
+            PathBuf::from("/home/me/Library/Caches/game")
+        );
+        let windows = env(&[("APPDATA", "C:/Roaming"), ("USERPROFILE", "C:/Me")]);
+        assert_eq!(
+            user_dir_from_env(UserDir::Data, "game", "windows", windows).unwrap(),
+            PathBuf::from("C:/Roaming").join("game")
+        );
+        assert!(user_dir_from_env(UserDir::Data, "../x", "linux", linux).is_err());
+        assert!(user_dir_from_env(UserDir::Data, "game", "linux", env(&[])).is_err());
+    }
+
+    #[test]
+    fn write_atomic_replaces_file() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("radium_fs_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("settings.cfg");
+        write_atomic(&path, b"old")?;
+        write_atomic(&path, b"new")?;
+        assert_eq!(std::fs::read(&path)?, b"new");
+        assert_eq!(std::fs::read_dir(&dir)?.count(), 1);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
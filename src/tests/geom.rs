@@ -0,0 +1,130 @@
+#[cfg(test)]
+mod tests {
+    use cgmath::InnerSpace;
+
+    use crate::sys::geom::{
+        bounds::Aabb3,
+        world::{Collider, CollisionWorld},
+        Aabb, Capsule, Circle, Obb, Shape, Vec2,
+    };
+
+    fn v(x: f32, y: f32) -> Vec2 {
+        Vec2::new(x, y)
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn overlaps_and_contacts() {
+        let boxed: Shape = Aabb::new(v(0.0, 0.0), v(2.0, 2.0)).into();
+        let circle: Shape = Circle {
+            center: v(2.5, 1.0),
+            radius: 1.0,
+        }
+        .into();
+        let c = boxed.contact(&circle).unwrap();
+        assert!(close(c.depth, 0.5) && close(c.normal.x, 1.0));
+
+        let far: Shape = Circle {
+            center: v(4.0, 1.0),
+            radius: 1.0,
+        }
+        .into();
+        assert!(!boxed.overlaps(&far));
+        assert!(close(boxed.distance(&far), 1.0));
+
+        let rotated: Shape = Obb {
+            center: v(3.3, 1.0),
+            half_extents: v(1.0, 1.0),
+            rotation: std::f32::consts::FRAC_PI_4,
+        }
+        .into();
+        // The corner of the diamond reaches back to x = 3.3 - sqrt(2).
+        assert!(boxed.overlaps(&rotated));
+
+        let capsule: Shape = Capsule {
+            a: v(-1.0, 3.0),
+            b: v(3.0, 3.0),
+            radius: 0.5,
+        }
+        .into();
+        assert!(!boxed.overlaps(&capsule));
+        assert!(boxed.overlaps(&capsule.translated(v(0.0, -0.6))));
+        assert!(capsule.contains_point(v(3.2, 3.2)));
+    }
+
+    #[test]
+    fn sweep_and_raycast() {
+        let wall: Shape = Aabb::new(v(5.0, -5.0), v(6.0, 5.0)).into();
+        let ball: Shape = Circle {
+            center: v(0.0, 0.0),
+            radius: 1.0,
+        }
+        .into();
+        let hit = ball.sweep(v(10.0, 0.0), &wall).unwrap();
+        assert!(close(hit.toi, 0.4), "{}", hit.toi);
+        assert!(close(hit.normal.x, -1.0));
+        assert!(ball.sweep(v(0.0, 10.0), &wall).is_none());
+
+        let ray = wall.raycast(v(0.0, 1.0), v(1.0, 0.0), 100.0).unwrap();
+        assert!(close(ray.t, 5.0) && close(ray.normal.x, -1.0));
+        assert!(wall.raycast(v(0.0, 1.0), v(1.0, 0.0), 4.0).is_none());
+        let ray = ball.raycast(v(0.0, 5.0), v(0.0, -2.0), 10.0).unwrap();
+        assert!(close(ray.t, 2.0) && close(ray.normal.y, 1.0));
+    }
+
+    #[test]
+    fn move_and_slide() {
+        let mut world = CollisionWorld::new();
+        let floor = world.insert(Collider::new(Aabb::new(v(-10.0, -1.0), v(10.0, 0.0))));
+        world.insert(Collider::new(Aabb::new(v(5.0, 0.0), v(6.0, 10.0))));
+        let player = world.insert(Collider::new(Aabb::from_center(v(0.0, 1.0), v(0.5, 0.5))));
+
+        // Falling diagonally lands on the floor and keeps the sideways motion.
+        let r = world.move_and_slide(player, v(1.0, -2.0));
+        assert!(r.on_floor && !r.on_wall);
+        assert_eq!(r.hits, vec![floor]);
+        let center = world.get(player).unwrap().shape.center();
+        assert!(close(center.x, 1.0) && center.y >= 0.5 && center.y < 0.52);
+
+        // Walking into the wall stops at it.
+        let r = world.move_and_slide(player, v(10.0, -0.1));
+        assert!(r.on_wall);
+        let center = world.get(player).unwrap().shape.center();
+        assert!(center.x > 4.45 && center.x <= 4.5, "{}", center.x);
+
+        let removed = world.remove(floor).unwrap();
+        assert!(world.get(floor).is_none());
+        let again = world.insert(removed);
+        assert_ne!(again, floor);
+        assert_eq!(world.len(), 3);
+        assert!(world
+            .raycast(v(4.4, 5.0), v(0.0, -1.0), 10.0, u32::MAX)
+            .is_some_and(|(id, _)| id == player));
+        assert!(r.moved.magnitude() > 3.0);
+    }
+
+    #[test]
+    fn aabb3_from_points_union_and_transform() {
+        use cgmath::{Matrix4, Point3, Vector3};
+
+        assert!(Aabb3::from_points([]).is_empty());
+        let a = Aabb3::from_points([Point3::new(1.0, -1.0, 0.0), Point3::new(-1.0, 2.0, 3.0)]);
+        assert_eq!(a.min, Point3::new(-1.0, -1.0, 0.0));
+        assert_eq!(a.max, Point3::new(1.0, 2.0, 3.0));
+        assert!(a.contains(Point3::new(0.0, 0.0, 1.0)));
+        assert_eq!(a.union(&Aabb3::EMPTY), a);
+
+        let b = Aabb3::new(Point3::new(4.0, 0.0, 0.0), Point3::new(5.0, 1.0, 1.0));
+        assert_eq!(a.union(&b).max, Point3::new(5.0, 2.0, 3.0));
+
+        let moved = b.transformed(&Matrix4::from_translation(Vector3::new(0.0, 10.0, 0.0)));
+        assert_eq!(moved.min, Point3::new(4.0, 10.0, 0.0));
+        // A quarter turn about z swaps the x and y extents.
+        let turned = b.transformed(&Matrix4::from_angle_z(cgmath::Deg(90.0)));
+        assert!(close(turned.min.x, -1.0) && close(turned.max.x, 0.0));
+        assert!(close(turned.min.y, 4.0) && close(turned.max.y, 5.0));
+    }
+}
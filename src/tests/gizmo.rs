@@ -0,0 +1,45 @@
+#[cfg(test)]
+mod tests {
+    use cgmath::{perspective, Deg, InnerSpace, Matrix4, Point3, Vector3};
+
+    use crate::gfx::{
+        gizmo::{Gizmo, GizmoAxis, GizmoMode, Ray},
+        transform::Transform,
+    };
+
+    const VIEWPORT: [f32; 2] = [800.0, 600.0];
+
+    fn view_proj(eye: Point3<f32>) -> Matrix4<f32> {
+        perspective(Deg(60.0), VIEWPORT[0] / VIEWPORT[1], 0.1, 100.0)
+            * Matrix4::look_at_rh(eye, Point3::new(0.0, 0.0, 0.0), Vector3::unit_y())
+    }
+
+    #[test]
+    fn center_ray_points_at_target() {
+        let eye = Point3::new(0.0, 0.0, 10.0);
+        let ray = Ray::from_screen([400.0, 300.0], VIEWPORT, view_proj(eye)).unwrap();
+        assert!((ray.dir - -Vector3::unit_z()).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn drag_translates_along_hovered_axis() {
+        let eye = Point3::new(0.0, 0.0, 10.0);
+        let mut gizmo = Gizmo::new(GizmoMode::Translate);
+        let mut target = Transform::default();
+        let length = gizmo.handle_length(eye, target.position());
+
+        let grab = Ray::new(eye, Point3::new(length * 0.5, 0.0, 0.0) - eye);
+        assert!(gizmo.update(&grab, eye, false, &mut target));
+        assert_eq!(gizmo.hovered(), Some(GizmoAxis::X));
+        gizmo.update(&grab, eye, true, &mut target);
+        assert_eq!(gizmo.active(), Some(GizmoAxis::X));
+
+        let moved = Ray::new(eye, Point3::new(length * 0.5 + 2.0, 0.0, 0.0) - eye);
+        gizmo.update(&moved, eye, true, &mut target);
+        let p = target.position();
+        assert!((p.x - 2.0).abs() < 1e-3 && p.y == 0.0 && p.z == 0.0);
+
+        gizmo.update(&moved, eye, false, &mut target);
+        assert!(!gizmo.is_dragging());
+    }
+}
@@ -0,0 +1,22 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::gfx::globals::FrameGlobals;
+
+    #[test]
+    fn frame_globals_advance() {
+        let mut globals = FrameGlobals::new(800, 600);
+        globals.cursor = [10.0, 20.0];
+        globals.advance(Duration::from_millis(250));
+        globals.advance(Duration::from_millis(500));
+
+        let uniform = globals.uniform();
+        assert_eq!(uniform.resolution, [800.0, 600.0]);
+        assert_eq!(uniform.cursor, [10.0, 20.0]);
+        assert_eq!(uniform.time, 0.75);
+        assert_eq!(uniform.delta_time, 0.5);
+        assert_eq!(uniform.frame, 2);
+        assert_eq!(std::mem::size_of_val(&uniform), 32);
+    }
+}
@@ -0,0 +1,182 @@
+#[cfg(all(test, feature = "gltf"))]
+mod tests {
+    use std::io::Cursor;
+
+    use base64::Engine;
+    use image::{ImageFormat, Rgba, RgbaImage};
+
+    use crate::sys::{
+        baked::BakedModel,
+        gltf_import::import_gltf,
+        import::{import_dir, ImportSettings},
+        pack::AssetPack,
+    };
+
+    /// One triangle in the xy plane and its u16 indices, padded to 4 bytes.
+    fn triangle_buffer() -> Vec<u8> {
+        let positions: [f32; 9] = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0];
+        let mut buffer = bytemuck::cast_slice::<_, u8>(&positions).to_vec();
+        buffer.extend(bytemuck::cast_slice::<_, u8>(&[0u16, 1, 2, 0]));
+        buffer
+    }
+
+    /// The triangle under a node translated by x + 1 whose parent scales x by `scale_x`,
+    /// red by factor, with an embedded normal map. `buffer_uri` None uses the GLB blob.
+    fn triangle_json(buffer_uri: Option<String>, scale_x: f32) -> String {
+        let mut png = Vec::new();
+        RgbaImage::from_pixel(4, 4, Rgba([128, 128, 255, 255]))
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        let png = base64::engine::general_purpose::STANDARD.encode(png);
+        let uri = buffer_uri.map_or(String::new(), |uri| format!(r#""uri": "{}","#, uri));
+        format!(
+            r#"{{
+                "asset": {{ "version": "2.0" }},
+                "scene": 0,
+                "scenes": [{{ "nodes": [0] }}],
+                "nodes": [
+                    {{ "scale": [{scale_x}, 1, 1], "children": [1] }},
+                    {{ "mesh": 0, "translation": [1, 0, 0] }}
+                ],
+                "meshes": [{{
+                    "name": "tri",
+                    "primitives": [{{ "attributes": {{ "POSITION": 0 }}, "indices": 1, "material": 0 }}]
+                }}],
+                "materials": [{{
+                    "name": "red",
+                    "pbrMetallicRoughness": {{ "baseColorFactor": [1, 0, 0, 1] }},
+                    "normalTexture": {{ "index": 0 }}
+                }}],
+                "textures": [{{ "source": 0 }}],
+                "images": [{{ "uri": "data:image/png;base64,{png}" }}],
+                "buffers": [{{ {uri} "byteLength": 44 }}],
+                "bufferViews": [
+                    {{ "buffer": 0, "byteOffset": 0, "byteLength": 36 }},
+                    {{ "buffer": 0, "byteOffset": 36, "byteLength": 6 }}
+                ],
+                "accessors": [
+                    {{ "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                       "min": [0, 0, 0], "max": [1, 1, 0] }},
+                    {{ "bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR" }}
+                ]
+            }}"#
+        )
+    }
+
+    fn glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let length = 12 + 8 + json.len() + 8 + bin.len();
+        let mut out = Vec::with_capacity(length);
+        out.extend(b"glTF");
+        out.extend(2u32.to_le_bytes());
+        out.extend((length as u32).to_le_bytes());
+        out.extend((json.len() as u32).to_le_bytes());
+        out.extend(b"JSON");
+        out.extend(json);
+        out.extend((bin.len() as u32).to_le_bytes());
+        out.extend(b"BIN\0");
+        out.extend(bin);
+        out
+    }
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("radium_{}_{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn positions(model: &BakedModel) -> Vec<[f32; 3]> {
+        let mut positions: Vec<_> = model.meshes[0]
+            .vertices
+            .iter()
+            .map(|v| v.position)
+            .collect();
+        positions.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        positions
+    }
+
+    #[test]
+    fn data_uri_gltf_applies_node_transforms() -> anyhow::Result<()> {
+        let dir = temp_dir("gltf_data_uri");
+        let buffer = base64::engine::general_purpose::STANDARD.encode(triangle_buffer());
+        let uri = format!("data:application/octet-stream;base64,{}", buffer);
+        std::fs::write(dir.join("tri.gltf"), triangle_json(Some(uri), 1.0))?;
+
+        let import = import_gltf(&dir, &dir.join("tri.gltf"))?;
+        let model = &import.model;
+        assert_eq!(model.meshes.len(), 1);
+        assert_eq!(model.meshes[0].name, "tri");
+        assert_eq!(model.meshes[0].indices.len(), 3);
+        assert_eq!(
+            positions(model),
+            [[1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [2.0, 0.0, 0.0]]
+        );
+        // No normals in the file, they are generated from the triangle.
+        for v in model.meshes[0].vertices.iter() {
+            assert_eq!(v.normal, [0.0, 0.0, 1.0]);
+        }
+
+        let material = &model.materials[0];
+        assert_eq!(material.name, "red");
+        assert_eq!(material.diffuse_texture, "tri.gltf#material0.diffuse");
+        assert_eq!(material.normal_texture, "tri.gltf#image0");
+        let names: Vec<_> = import.textures.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["tri.gltf#material0.diffuse", "tri.gltf#image0"]);
+        assert_eq!(
+            import.textures[0].1.to_rgba8().get_pixel(0, 0),
+            &Rgba([255, 0, 0, 255])
+        );
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn glb_blob_and_mirrored_nodes() -> anyhow::Result<()> {
+        let dir = temp_dir("gltf_glb");
+        let path = dir.join("tri.glb");
+        std::fs::write(&path, glb(&triangle_json(None, -1.0), &triangle_buffer()))?;
+
+        let model = import_gltf(&dir, &path)?.model;
+        assert_eq!(
+            positions(&model),
+            [[-2.0, 0.0, 0.0], [-1.0, 0.0, 0.0], [-1.0, 1.0, 0.0]]
+        );
+        // Mirroring flips the winding back, the face still points at +z.
+        for v in model.meshes[0].vertices.iter() {
+            assert_eq!(v.normal, [0.0, 0.0, 1.0]);
+        }
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn import_dir_packs_gltf_textures() -> anyhow::Result<()> {
+        let dir = temp_dir("gltf_import_dir");
+        let src = dir.join("src");
+        std::fs::create_dir_all(src.join("models"))?;
+        std::fs::write(src.join("models/tri.bin"), triangle_buffer())?;
+        std::fs::write(
+            src.join("models/tri.gltf"),
+            triangle_json(Some("tri.bin".to_string()), 1.0),
+        )?;
+        std::fs::write(src.join("models/broken.glb"), b"glTF")?;
+
+        let out = dir.join("assets.pak");
+        let report = import_dir(&src, &out, &ImportSettings::default())?;
+        assert_eq!((report.models, report.textures, report.raw), (1, 2, 1));
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].0, "models/broken.glb");
+
+        let pack = AssetPack::from_bytes(&std::fs::read(&out)?)?;
+        let model = BakedModel::from_bytes(pack.get("models/tri.gltf").unwrap())?;
+        for name in [
+            &model.materials[0].diffuse_texture,
+            &model.materials[0].normal_texture,
+        ] {
+            assert!(pack.get(name).is_some(), "{} not packed", name);
+        }
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
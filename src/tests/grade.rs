@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod tests {
+    use crate::gfx::post::grade::Lut;
+
+    #[test]
+    fn cube_identity() -> anyhow::Result<()> {
+        let src = "\
+# comment
+TITLE \"identity\"
+LUT_3D_SIZE 2
+0 0 0
+1 0 0
+0 1 0
+1 1 0
+0 0 1
+1 0 1
+0 1 1
+1 1 1
+";
+        let lut = Lut::from_cube_str(src)?;
+        assert_eq!(lut, Lut::identity(2));
+        Ok(())
+    }
+
+    #[test]
+    fn cube_wrong_entry_count() {
+        let src = "LUT_3D_SIZE 2\n0 0 0\n1 1 1\n";
+        assert!(Lut::from_cube_str(src).is_err());
+    }
+
+    #[test]
+    fn strip_layout() -> anyhow::Result<()> {
+        let size = 4;
+        let identity = Lut::identity(size);
+        let img = image::RgbaImage::from_fn(size * size, size, |x, y| {
+            let (r, g, b) = (x % size, y, x / size);
+            image::Rgba(identity.data[(b * size * size + g * size + r) as usize])
+        });
+        let lut = Lut::from_strip(&image::DynamicImage::ImageRgba8(img))?;
+        assert_eq!(lut, identity);
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        eng::app::EngineConfig,
+        gfx::wgpu::{
+            immediate::{immediate_wgsl, uniform_stride, ImmediateMode},
+            shader::check_wgsl,
+        },
+    };
+
+    const SOURCE: &str = "struct DrawData {
+    tint: vec4<f32>,
+    object: u32,
+}
+
+var<immediate> draw: DrawData;
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return draw.tint * f32(draw.object);
+}
+";
+
+    #[test]
+    fn native_only_with_the_feature_and_room() {
+        let limits = wgpu::Limits {
+            max_immediate_size: 128,
+            ..wgpu::Limits::default()
+        };
+        let features = wgpu::Features::IMMEDIATES;
+        assert_eq!(
+            ImmediateMode::choose(features, &limits, 32, 3),
+            ImmediateMode::Native
+        );
+        assert_eq!(
+            ImmediateMode::choose(features, &limits, 256, 3),
+            ImmediateMode::Uniform(3)
+        );
+        assert_eq!(
+            ImmediateMode::choose(wgpu::Features::empty(), &limits, 32, 3),
+            ImmediateMode::Uniform(3)
+        );
+    }
+
+    #[test]
+    fn fallback_rewrites_the_declaration() {
+        assert_eq!(immediate_wgsl(SOURCE, ImmediateMode::Native), SOURCE);
+        let fallback = immediate_wgsl(SOURCE, ImmediateMode::Uniform(2));
+        assert!(fallback.contains("@group(2) @binding(0) var<uniform> draw: DrawData;"));
+        check_wgsl("native", SOURCE).unwrap();
+        check_wgsl("fallback", &fallback).unwrap();
+    }
+
+    #[test]
+    fn fallback_slots_respect_the_offset_alignment() {
+        assert_eq!(uniform_stride(20, 256), 256);
+        assert_eq!(uniform_stride(300, 256), 512);
+        assert_eq!(uniform_stride(0, 0), 4);
+    }
+
+    #[test]
+    fn immediates_can_be_turned_off_in_the_config() {
+        assert!(EngineConfig::default().immediates);
+        let config = EngineConfig::parse("immediates = false").unwrap();
+        assert!(!config.immediates);
+        assert_eq!(EngineConfig::parse(&config.to_string()).unwrap(), config);
+    }
+}
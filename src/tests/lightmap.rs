@@ -0,0 +1,132 @@
+#[cfg(test)]
+mod tests {
+    use cgmath::{Matrix4, SquareMatrix};
+
+    use crate::{
+        eng::app::EngineConfig,
+        gfx::{light::LightUniform, lightmap::lightmap_vertices, wgpu::vertex::Vertex3D},
+        sys::{
+            lightmap::{
+                bake_lightmap, bake_tiles, tile_uv, BakeQuality, BakeSettings, BakeSurface,
+                BakedLightmap,
+            },
+            pack::{AssetKind, AssetPack, PackWriter},
+        },
+    };
+
+    /// Upward facing square of `half` size at height `y`, as (vertices, indices).
+    fn quad(half: f32, y: f32) -> (Vec<Vertex3D>, Vec<u32>) {
+        let vertices = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0]]
+            .map(|[x, z]| Vertex3D {
+                position: [x * half, y, z * half],
+                tex_coords: [x * 0.5 + 0.5, z * 0.5 + 0.5],
+                normal: [0.0, 1.0, 0.0],
+                ..Default::default()
+            })
+            .to_vec();
+        (vertices, vec![0, 3, 2, 0, 2, 1])
+    }
+
+    /// Red channel of the texel under the floor point that is `t` of the way from its
+    /// (-1, -1) corner to its (1, 1) corner.
+    fn floor_texel(lightmap: &BakedLightmap, t: f32) -> u8 {
+        let uvs = &lightmap.uvs[0];
+        // Index 0 is the (-1, -1) corner and index 2 the (1, 1) corner.
+        let uv = [0, 1].map(|i| uvs[0][i] + (uvs[2][i] - uvs[0][i]) * t);
+        let texture = &lightmap.texture;
+        let x = (uv[0] * texture.width as f32) as u32;
+        let y = (uv[1] * texture.height as f32) as u32;
+        texture.mips[0][((y * texture.width + x) * 4) as usize]
+    }
+
+    fn light(position: [f32; 4]) -> LightUniform {
+        LightUniform {
+            position,
+            color: [1.0, 1.0, 1.0, 0.0],
+        }
+    }
+
+    #[test]
+    fn occluder_shadows_the_floor() {
+        let (floor_vertices, floor_indices) = quad(1.0, 0.0);
+        let (blocker_vertices, blocker_indices) = quad(0.25, 1.0);
+        let surfaces = [
+            BakeSurface::new(&floor_vertices, &floor_indices, Matrix4::identity()),
+            BakeSurface::new(&blocker_vertices, &blocker_indices, Matrix4::identity()),
+        ];
+        let lights = [light([0.0, 3.0, 0.0, 0.0])];
+        let settings = BakeSettings::default().with_ambient([0.0; 3]);
+
+        let (lightmap, stats) = bake_lightmap(&surfaces, &lights, &settings).unwrap();
+        assert_eq!(stats.packed, 2);
+        assert_eq!(lightmap.uvs[0].len(), 6);
+        assert!(lightmap
+            .uvs
+            .iter()
+            .flatten()
+            .flatten()
+            .all(|c| (0.0..=1.0).contains(c)));
+        assert_eq!(floor_texel(&lightmap, 0.5), 0);
+        assert!(floor_texel(&lightmap, 0.1) > 100);
+
+        // Draft skips shadows, so the floor is brightest right under the light.
+        let draft = settings.with_quality(BakeQuality::Draft);
+        let (lightmap, _) = bake_lightmap(&surfaces, &lights, &draft).unwrap();
+        assert!(floor_texel(&lightmap, 0.5) > floor_texel(&lightmap, 0.1));
+
+        let vertices = lightmap_vertices(&surfaces[0], &lightmap.uvs[0]);
+        assert_eq!(vertices.len(), 6);
+        assert_eq!(vertices[2].position, [1.0, 0.0, 1.0]);
+        assert_eq!(vertices[2].lightmap_uv, lightmap.uvs[0][2]);
+
+        // The 2x2 floor needs 16 texels plus borders per side at medium quality.
+        assert!(bake_lightmap(&surfaces, &lights, &settings.with_max_size(8)).is_err());
+    }
+
+    #[test]
+    fn lightmaps_round_trip_through_packs() {
+        let (vertices, indices) = quad(1.0, 0.0);
+        let surfaces = [BakeSurface::new(&vertices, &indices, Matrix4::identity())];
+        let (lightmap, _) = bake_lightmap(
+            &surfaces,
+            &[light([0.0, 1.0, 0.0, 0.0])],
+            &BakeSettings::default(),
+        )
+        .unwrap();
+
+        let mut writer = PackWriter::new();
+        writer.add("level.lightmap", AssetKind::Lightmap, lightmap.to_bytes());
+        let pack = AssetPack::from_bytes(&writer.to_bytes()).unwrap();
+        assert_eq!(
+            pack.entry("level.lightmap").unwrap().kind,
+            AssetKind::Lightmap
+        );
+        let read = BakedLightmap::from_bytes(pack.get("level.lightmap").unwrap()).unwrap();
+        assert_eq!(read, lightmap);
+        assert!(BakedLightmap::from_bytes(&lightmap.texture.to_bytes()).is_err());
+    }
+
+    #[test]
+    fn walls_block_tile_light() {
+        // A 4x1 corridor with a wall in tile 2 and the light in the middle of tile 0.
+        let settings = BakeSettings::default().with_ambient([0.0; 3]);
+        let lights = [light([0.5, 0.5, 0.0, 0.0])];
+        let lightmap = bake_tiles([4, 1], 1.0, |x, _| x == 2, &lights, &settings);
+        let texture = &lightmap.texture;
+        assert_eq!((texture.width, texture.height), (16, 4));
+
+        let red = |world: [f32; 2]| {
+            let uv = tile_uv(world, [4, 1], 1.0);
+            let x = (uv[0] * texture.width as f32) as u32;
+            let y = (uv[1] * texture.height as f32) as u32;
+            texture.mips[0][((y * texture.width + x) * 4) as usize]
+        };
+        assert_eq!(red([1.5, 0.5]), 128);
+        assert_eq!(red([3.5, 0.5]), 0);
+
+        let config = EngineConfig::parse("lightmap_quality = high").unwrap();
+        assert_eq!(config.lightmap_quality, BakeQuality::High);
+        assert!(EngineConfig::parse("lightmap_quality = ultra").is_err());
+        assert_eq!(EngineConfig::parse(&config.to_string()).unwrap(), config);
+    }
+}
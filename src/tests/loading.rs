@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc, time::Duration};
+
+    use crate::{
+        eng::{
+            loading::{LoadProgress, LoadingScreen},
+            state::{GameState, StateStack, Trans},
+        },
+        gfx::{draw::DrawCtx, wgpu::surface::SurfaceError},
+    };
+
+    struct Game(Rc<Cell<bool>>);
+
+    impl GameState for Game {
+        fn on_enter(&mut self) {
+            self.0.set(true);
+        }
+        fn draw_frame(&mut self, _ctx: &mut DrawCtx) -> Result<(), SurfaceError> {
+            Ok(())
+        }
+        fn frame_update(&mut self, _dt: Duration) -> Trans {
+            Trans::None
+        }
+    }
+
+    #[test]
+    fn progress() {
+        let progress = LoadProgress::new();
+        assert!(progress.is_done());
+        assert_eq!(progress.fraction(), 1.0);
+
+        progress.begin(4);
+        progress.complete(1);
+        assert_eq!(progress.fraction(), 0.25);
+        assert!(!progress.is_done());
+
+        let value = progress.spawn(|| 5).join().unwrap();
+        assert_eq!(value, 5);
+        assert_eq!(progress.total(), 5);
+        assert_eq!(progress.completed(), 2);
+    }
+
+    #[test]
+    fn switches_when_done() {
+        let progress = LoadProgress::new();
+        progress.begin(1);
+
+        let entered = Rc::new(Cell::new(false));
+        let game = Game(entered.clone());
+        let screen = LoadingScreen::new(progress.clone(), |_, _| Ok(()), move || Box::new(game))
+            .with_min_duration(Duration::from_millis(30));
+        let mut stack = StateStack::new(Box::new(screen));
+
+        stack.update(Duration::from_millis(20));
+        assert!(!entered.get());
+
+        progress.complete(1);
+        stack.update(Duration::from_millis(5));
+        assert!(!entered.get(), "minimum duration not reached yet");
+
+        stack.update(Duration::from_millis(5));
+        assert!(entered.get());
+        assert_eq!(stack.len(), 1);
+    }
+}
@@ -0,0 +1,39 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::gfx::material_params::{
+        MaterialAnimator, MaterialParam, MaterialParamStore, MaterialParamsUniform, ParamCurve,
+    };
+
+    #[test]
+    fn animator_marks_only_changed_materials() {
+        let mut store = MaterialParamStore::default();
+        let dissolving = store.add(MaterialParamsUniform::default());
+        let idle = store.add(MaterialParamsUniform::default());
+        assert_eq!(store.take_dirty().len(), 2);
+
+        let mut animator = MaterialAnimator::new();
+        animator.play(
+            dissolving,
+            MaterialParam::Dissolve,
+            ParamCurve::scalar(&[(0.0, 0.0), (1.0, 1.0)]),
+        );
+        animator.play(
+            idle,
+            MaterialParam::EmissiveIntensity,
+            ParamCurve::scalar(&[(0.0, 1.0), (1.0, 1.0)]),
+        );
+
+        animator.frame_update(Duration::from_millis(500), &mut store);
+        assert_eq!(store.take_dirty(), vec![dissolving]);
+        assert_eq!(store.values(dissolving).unwrap().dissolve, 0.5);
+
+        animator.frame_update(Duration::from_millis(600), &mut store);
+        assert_eq!(store.values(dissolving).unwrap().dissolve, 1.0);
+        assert!(animator.is_empty());
+
+        let pulse = ParamCurve::scalar(&[(0.0, 0.0), (1.0, 2.0)]).looping();
+        assert_eq!(pulse.sample(1.25), Some([0.5, 0.0, 0.0, 0.0]));
+    }
+}
@@ -0,0 +1,27 @@
+#[cfg(test)]
+mod tests {
+    use crate::eng::command::{merge_draws, RenderCommand};
+
+    #[test]
+    fn adjacent_draws_collapse() {
+        let mut commands = vec![
+            RenderCommand::DrawIndexed(0..6, 0, 0..1),
+            RenderCommand::DrawIndexed(6..12, 0, 0..1),
+            RenderCommand::DrawIndexed(12..18, 0, 0..1),
+            // Gap in the index range, starts a new draw.
+            RenderCommand::DrawIndexed(24..30, 0, 0..1),
+            RenderCommand::DrawIndexed(30..36, 0, 0..2),
+            RenderCommand::PushDebugGroup(String::from("ui")),
+            RenderCommand::Draw(0..3, 0..1),
+            RenderCommand::Draw(3..6, 0..1),
+            RenderCommand::PopDebugGroup,
+        ];
+
+        assert_eq!(merge_draws(&mut commands), 3);
+        assert_eq!(commands.len(), 6);
+        assert!(matches!(&commands[0], RenderCommand::DrawIndexed(r, 0, _) if *r == (0..18)));
+        assert!(matches!(&commands[1], RenderCommand::DrawIndexed(r, 0, _) if *r == (24..30)));
+        assert!(matches!(&commands[2], RenderCommand::DrawIndexed(_, _, i) if *i == (0..2)));
+        assert!(matches!(&commands[4], RenderCommand::Draw(r, _) if *r == (0..6)));
+    }
+}
@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod tests {
+    use crate::sys::{
+        meshopt::{optimize_mesh, optimize_vertex_cache, reindex, MeshStats},
+        rand::Rng,
+    };
+
+    /// Grid of n x n quads with its triangles shuffled, every vertex is duplicated.
+    fn shuffled_grid(n: u32) -> (Vec<[f32; 3]>, Vec<u32>) {
+        let index = |x: u32, y: u32| y * (n + 1) + x;
+        let mut triangles = Vec::new();
+        for y in 0..n {
+            for x in 0..n {
+                triangles.push([index(x, y), index(x + 1, y), index(x + 1, y + 1)]);
+                triangles.push([index(x, y), index(x + 1, y + 1), index(x, y + 1)]);
+            }
+        }
+        Rng::new(7).shuffle(&mut triangles);
+        let positions: Vec<[f32; 3]> = (0..(n + 1) * (n + 1))
+            .map(|i| [(i % (n + 1)) as f32, (i / (n + 1)) as f32, 0.0])
+            .collect();
+        let vertex_count = positions.len() as u32;
+        let mut vertices = positions.clone();
+        vertices.extend_from_slice(&positions);
+        // Odd triangles use the duplicated copies.
+        let indices = triangles
+            .iter()
+            .enumerate()
+            .flat_map(|(t, tri)| tri.map(|i| i + (t as u32 % 2) * vertex_count))
+            .collect();
+        (vertices, indices)
+    }
+
+    fn triangle_set(vertices: &[[f32; 3]], indices: &[u32]) -> Vec<[[i32; 3]; 3]> {
+        let mut set: Vec<_> = indices
+            .chunks_exact(3)
+            .map(|t| {
+                let mut tri = [t[0], t[1], t[2]].map(|i| vertices[i as usize].map(|c| c as i32));
+                // Rotations of the same triangle compare equal.
+                let min = (0..3).min_by_key(|&i| tri[i]).unwrap();
+                tri.rotate_left(min);
+                tri
+            })
+            .collect();
+        set.sort();
+        set
+    }
+
+    #[test]
+    fn reindex_merges_identical_vertices() {
+        let (vertices, mut indices) = shuffled_grid(4);
+        let unique = reindex(&vertices, &mut indices);
+        assert_eq!(unique.len(), 25);
+        assert!(indices.iter().all(|&i| (i as usize) < unique.len()));
+    }
+
+    #[test]
+    fn vertex_cache_order_lowers_acmr() {
+        let (vertices, mut indices) = shuffled_grid(16);
+        let unique = reindex(&vertices, &mut indices);
+        let optimized = optimize_vertex_cache(&indices, unique.len());
+        let before = MeshStats::analyze(unique.len(), &indices);
+        let after = MeshStats::analyze(unique.len(), &optimized);
+        assert!(after.acmr < before.acmr * 0.6, "{} -> {}", before, after);
+    }
+
+    #[test]
+    fn optimize_mesh_keeps_every_triangle() {
+        let (mut vertices, mut indices) = shuffled_grid(8);
+        let expected = triangle_set(&vertices, &indices);
+        let (before, after) = optimize_mesh(&mut vertices, &mut indices, |v| *v);
+        assert_eq!(triangle_set(&vertices, &indices), expected);
+        assert_eq!(before.vertices, 162);
+        assert_eq!(after.vertices, 81);
+        assert_eq!(after.triangles, before.triangles);
+        // Vertices are in first use order.
+        let mut next = 0;
+        for &i in &indices {
+            assert!(i <= next);
+            next = next.max(i + 1);
+        }
+    }
+}
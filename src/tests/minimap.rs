@@ -0,0 +1,62 @@
+#[cfg(test)]
+mod tests {
+    use cgmath::{Point3, Rad, Vector3};
+
+    use crate::gfx::minimap::{minimap_view_proj, MinimapLayers, MinimapMarker, MinimapView};
+
+    fn view(heading: f32) -> MinimapView {
+        MinimapView {
+            view_proj: minimap_view_proj(
+                Point3::new(5.0, 2.0, 5.0),
+                Rad(heading),
+                10.0,
+                50.0,
+                50.0,
+            ),
+            layers: MinimapLayers::TERRAIN.union(MinimapLayers::ACTORS),
+        }
+    }
+
+    fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+        (a[0] - b[0]).abs() < 1e-4 && (a[1] - b[1]).abs() < 1e-4
+    }
+
+    #[test]
+    fn north_up_and_rotated_views_place_points() {
+        let north = view(0.0);
+        assert!(close(north.to_map(Point3::new(5.0, 0.0, 5.0)), [0.5, 0.5]));
+        assert!(close(north.to_map(Point3::new(5.0, 0.0, -5.0)), [0.5, 1.0]));
+        assert!(close(
+            north.to_map(Point3::new(15.0, 30.0, 5.0)),
+            [1.0, 0.5]
+        ));
+        assert!((north.map_angle(-Vector3::unit_x()) - std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+        assert!(north.map_angle(-Vector3::unit_z()).abs() < 1e-5);
+
+        // Facing -X puts -X at the top.
+        let west = view(std::f32::consts::FRAC_PI_2);
+        assert!(close(west.to_map(Point3::new(-5.0, 0.0, 5.0)), [0.5, 1.0]));
+        assert!(west.map_angle(-Vector3::unit_x()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn layers_filter_and_markers_clamp_to_edge() {
+        let view = view(0.0);
+        assert!(view.includes(MinimapLayers::ACTORS));
+        assert!(!view.includes(MinimapLayers::STRUCTURES));
+        assert!(!view.includes(MinimapLayers::NONE));
+
+        let far = Point3::new(45.0, 0.0, 5.0);
+        assert_eq!(
+            MinimapMarker::new(far).place(&view, [10.0, 10.0], [100.0, 100.0]),
+            None
+        );
+        let (position, rotation) = MinimapMarker::new(far)
+            .clamped()
+            .with_direction(-Vector3::unit_z())
+            .place(&view, [10.0, 10.0], [100.0, 100.0])
+            .unwrap();
+        assert!(close(position, [110.0, 60.0]));
+        assert!(rotation.abs() < 1e-5);
+    }
+}
@@ -1 +1,78 @@
+pub mod access;
+pub mod accessibility;
+pub mod ai;
+pub mod animation;
+pub mod asset_graph;
+pub mod assets;
+pub mod audio;
+pub mod bc;
+pub mod camera;
+pub mod capture;
+pub mod cluster;
+pub mod command;
+pub mod command_dump;
+pub mod compute;
+pub mod context;
+pub mod crowd;
+pub mod cull;
+pub mod custom_post;
+pub mod debug_name;
+pub mod decal;
+pub mod depth;
+pub mod determinism;
+pub mod encoder;
+pub mod environment;
+pub mod font;
+pub mod frame;
+pub mod frame_graph;
+pub mod fs;
+pub mod geom;
+pub mod gltf_import;
+pub mod globals;
+pub mod gizmo;
+pub mod grade;
+pub mod immediate;
+pub mod lightmap;
+pub mod loading;
+pub mod material_params;
 pub mod mem;
+pub mod merge;
+pub mod minimap;
+pub mod meshopt;
+pub mod msdf;
+pub mod mouse;
+pub mod music;
+pub mod noise;
+pub mod pack;
+pub mod parallax;
+pub mod path;
+pub mod point_shadow;
+pub mod prefab;
+pub mod probe;
+pub mod quad;
+pub mod rand;
+pub mod readback;
+pub mod rect_pack;
+pub mod rich_text;
+pub mod scale;
+pub mod scatter;
+pub mod scene;
+pub mod script;
+pub mod shader;
+pub mod shadow;
+pub mod shortcut;
+pub mod state;
+pub mod streaming;
+pub mod surface;
+pub mod tasks;
+pub mod text;
+pub mod text_edit;
+pub mod texture;
+pub mod time_of_day;
+pub mod triple_buffer;
+pub mod tweak;
+pub mod validate;
+pub mod video;
+pub mod warmup;
+pub mod watchdog;
+pub mod weather;
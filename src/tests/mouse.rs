@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::eng::input::mouse::{ClickSettings, MouseButton, MouseEvent, MouseInput};
+
+    fn clicks(mouse: &MouseInput) -> Vec<u32> {
+        mouse
+            .events()
+            .iter()
+            .filter_map(|e| match e {
+                MouseEvent::Click { count, .. } => Some(*count),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn double_clicks_need_time_and_distance() {
+        let mut mouse = MouseInput::new();
+        let t = Instant::now();
+        let click = |mouse: &mut MouseInput, at: Duration| {
+            mouse.handle_press(MouseButton::Left, t + at);
+            mouse.handle_release(MouseButton::Left);
+        };
+
+        mouse.handle_cursor([10.0, 10.0]);
+        click(&mut mouse, Duration::ZERO);
+        click(&mut mouse, Duration::from_millis(300));
+        assert_eq!(clicks(&mouse), [1, 2]);
+        assert_eq!(
+            mouse
+                .events()
+                .iter()
+                .filter(|e| matches!(e, MouseEvent::DoubleClick { .. }))
+                .count(),
+            1
+        );
+        assert!(mouse.just_pressed(MouseButton::Left));
+        mouse.end_frame();
+        assert!(mouse.events().is_empty());
+        assert!(!mouse.just_pressed(MouseButton::Left));
+
+        // Too late, then too far away.
+        click(&mut mouse, Duration::from_millis(900));
+        mouse.handle_cursor([30.0, 10.0]);
+        click(&mut mouse, Duration::from_millis(1000));
+        assert_eq!(clicks(&mouse), [1, 1]);
+
+        // Buttons count their clicks separately.
+        mouse.handle_press(MouseButton::Right, t + Duration::from_millis(1100));
+        assert_eq!(mouse.click_count(MouseButton::Right), 1);
+        assert!(mouse.is_down(MouseButton::Right));
+        assert!(!mouse.is_down(MouseButton::Left));
+    }
+
+    #[test]
+    fn moving_past_the_threshold_drags() {
+        let mut mouse = MouseInput::new().with_settings(ClickSettings {
+            drag_threshold: 5.0,
+            ..Default::default()
+        });
+        mouse.handle_press(MouseButton::Middle, Instant::now());
+        mouse.handle_cursor([3.0, 4.0]);
+        assert!(!mouse.is_dragging(MouseButton::Middle));
+        mouse.handle_cursor([4.0, 4.0]);
+        assert_eq!(mouse.drag_origin(MouseButton::Middle), Some([0.0, 0.0]));
+        mouse.handle_cursor([6.0, 4.0]);
+        assert_eq!(mouse.delta(), [6.0, 4.0]);
+        mouse.handle_release(MouseButton::Middle);
+
+        let events = mouse.events();
+        assert!(matches!(
+            events[1],
+            MouseEvent::DragStart {
+                origin: [0.0, 0.0],
+                ..
+            }
+        ));
+        assert!(matches!(
+            events[3],
+            MouseEvent::Drag {
+                delta: [2.0, 0.0],
+                ..
+            }
+        ));
+        assert!(matches!(
+            events.last(),
+            Some(MouseEvent::DragEnd {
+                position: [6.0, 4.0],
+                ..
+            })
+        ));
+        assert!(clicks(&mouse).is_empty());
+        assert_eq!(mouse.click_count(MouseButton::Middle), 0);
+    }
+}
@@ -0,0 +1,127 @@
+#[cfg(all(test, feature = "ttf"))]
+mod tests {
+    use cgmath::Vector2;
+    use image::RgbaImage;
+
+    use crate::sys::{
+        import::{import_dir, ImportSettings},
+        msdf::{self, generate, median, Segment, Shape, MAGENTA, WHITE, YELLOW},
+    };
+
+    fn v(x: f32, y: f32) -> Vector2<f32> {
+        Vector2::new(x, y)
+    }
+
+    /// Square from (1, 1) to (3, 3), clockwise like TrueType outer contours.
+    fn square(clockwise: bool) -> Shape {
+        let mut corners = [v(1.0, 1.0), v(1.0, 3.0), v(3.0, 3.0), v(3.0, 1.0)];
+        if !clockwise {
+            corners.reverse();
+        }
+        let edges = (0..4)
+            .map(|i| Segment::Line([corners[i], corners[(i + 1) % 4]]))
+            .collect();
+        let mut shape = Shape::new(vec![edges]);
+        shape.color_edges();
+        shape
+    }
+
+    /// Bilinearly filtered median at `p` in texels, like the GPU sampler.
+    fn sample(field: &RgbaImage, p: [f32; 2]) -> f32 {
+        let (x, y) = (p[0] - 0.5, p[1] - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let texel = |dx: f32, dy: f32| {
+            let px = (x0 + dx).clamp(0.0, field.width() as f32 - 1.0) as u32;
+            let py = (y0 + dy).clamp(0.0, field.height() as f32 - 1.0) as u32;
+            field.get_pixel(px, py).0.map(|c| c as f32 / 255.0)
+        };
+        let [a, b, c, d] = [
+            texel(0.0, 0.0),
+            texel(1.0, 0.0),
+            texel(0.0, 1.0),
+            texel(1.0, 1.0),
+        ];
+        let channel = |k: usize| {
+            let top = a[k] + (b[k] - a[k]) * fx;
+            let bottom = c[k] + (d[k] - c[k]) * fx;
+            top + (bottom - top) * fy
+        };
+        median(channel(0), channel(1), channel(2))
+    }
+
+    #[test]
+    fn corners_get_distinct_colors() {
+        let shape = square(true);
+        let colors: Vec<u8> = shape.contours[0].iter().map(|e| e.color).collect();
+        for i in 0..4 {
+            let (a, b) = (colors[i], colors[(i + 1) % 4]);
+            assert_ne!(a, WHITE);
+            assert_eq!((a & b).count_ones(), 1, "{:?}", colors);
+        }
+
+        // Tangent continuous contours have no corners and stay white.
+        let k = 0.5523;
+        let circle = [
+            [v(1.0, 0.0), v(1.0, k), v(k, 1.0), v(0.0, 1.0)],
+            [v(0.0, 1.0), v(-k, 1.0), v(-1.0, k), v(-1.0, 0.0)],
+            [v(-1.0, 0.0), v(-1.0, -k), v(-k, -1.0), v(0.0, -1.0)],
+            [v(0.0, -1.0), v(k, -1.0), v(1.0, -k), v(1.0, 0.0)],
+        ];
+        let mut shape = Shape::new(vec![circle.into_iter().map(Segment::Cubic).collect()]);
+        shape.color_edges();
+        assert!(shape.contours[0].iter().all(|e| e.color == WHITE));
+
+        // A single corner splits the contour in three differently colored pieces.
+        let loop_ = Segment::Cubic([v(0.0, 0.0), v(2.0, 2.0), v(2.0, -2.0), v(0.0, 0.0)]);
+        let mut shape = Shape::new(vec![vec![loop_]]);
+        shape.color_edges();
+        let colors: Vec<u8> = shape.contours[0].iter().map(|e| e.color).collect();
+        assert_eq!(colors, [MAGENTA, WHITE, YELLOW]);
+        assert_eq!(shape.contours[0][2].segment.end(), v(0.0, 0.0));
+    }
+
+    #[test]
+    fn filtered_field_keeps_square_corners() {
+        // 4 texels per unit, the square covers texels 4 to 12 with 4 texels of margin.
+        let scale = 4.0;
+        for clockwise in [true, false] {
+            let field = generate(&square(clockwise), 16, 16, scale, [0.0, 0.0], 4.0);
+            // Check every quarter texel away from the outline, corners included. A plain
+            // distance field rounds the corners off under bilinear filtering.
+            for i in 0..64 {
+                for j in 0..64 {
+                    let p = [i as f32 / 4.0 + 0.125, j as f32 / 4.0 + 0.125];
+                    let inside = (4.0..12.0).contains(&p[0]) && (4.0..12.0).contains(&p[1]);
+                    let edge = [p[0] - 4.0, 12.0 - p[0], p[1] - 4.0, 12.0 - p[1]]
+                        .map(f32::abs)
+                        .into_iter()
+                        .fold(f32::MAX, f32::min);
+                    if edge < 0.25 {
+                        continue;
+                    }
+                    assert_eq!(sample(&field, p) > 0.5, inside, "{:?} {}", p, clockwise);
+                }
+            }
+            // Alpha holds the plain distance, 0.5 texels from the edge is 1/8 of the range.
+            assert_eq!(field.get_pixel(4, 8)[3], 159);
+            assert_eq!(field.get_pixel(0, 0)[3], 0);
+        }
+    }
+
+    #[test]
+    fn fonts_need_valid_data() {
+        let err =
+            msdf::bake_font(b"not a font", "atlas", 32.0, 4.0, msdf::default_chars()).unwrap_err();
+        assert!(err.to_string().starts_with("bake_font => invalid font"));
+
+        let dir = std::env::temp_dir().join(format!("radium_msdf_{}", std::process::id()));
+        let src = dir.join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("broken.ttf"), b"not a font").unwrap();
+        let report = import_dir(&src, &dir.join("assets.pak"), &ImportSettings::default()).unwrap();
+        assert_eq!(report.fonts, 0);
+        assert_eq!(report.skipped[0].0, "broken.ttf");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+#[cfg(all(test, feature = "music"))]
+mod tests {
+    use crate::eng::audio::{
+        stream::{DecoderSource, MusicOptions, StreamSource},
+        Mixer,
+    };
+
+    /// 16 bit mono PCM WAV of `samples` at 1kHz.
+    fn wav(samples: &[i16]) -> Vec<u8> {
+        let data_len = samples.len() as u32 * 2;
+        let mut out = Vec::new();
+        out.extend(b"RIFF");
+        out.extend((36 + data_len).to_le_bytes());
+        out.extend(b"WAVEfmt ");
+        out.extend(16u32.to_le_bytes());
+        out.extend(1u16.to_le_bytes());
+        out.extend(1u16.to_le_bytes());
+        out.extend(1000u32.to_le_bytes());
+        out.extend(2000u32.to_le_bytes());
+        out.extend(2u16.to_le_bytes());
+        out.extend(16u16.to_le_bytes());
+        out.extend(b"data");
+        out.extend(data_len.to_le_bytes());
+        for s in samples {
+            out.extend(s.to_le_bytes());
+        }
+        out
+    }
+
+    fn ramp_file(name: &str) -> std::path::PathBuf {
+        let samples: Vec<i16> = (0..2000).map(|i| i * 16).collect();
+        let path = std::env::temp_dir().join(format!("radium_{}_{}.wav", name, std::process::id()));
+        std::fs::write(&path, wav(&samples)).unwrap();
+        path
+    }
+
+    fn sample(i: u64) -> f32 {
+        (i * 16) as f32 / 32768.0
+    }
+
+    #[test]
+    fn decoder_reads_and_seeks() -> anyhow::Result<()> {
+        let path = ramp_file("music_decode");
+        let mut source = DecoderSource::open(&path)?;
+        assert_eq!((source.channels(), source.sample_rate()), (1, 1000));
+
+        let mut out = [0.0; 300];
+        let mut frames = 0;
+        loop {
+            let read = source.read(&mut out)?;
+            if read == 0 {
+                break;
+            }
+            for (i, s) in out[..read].iter().enumerate() {
+                assert_eq!(*s, sample(frames + i as u64));
+            }
+            frames += read as u64;
+        }
+        assert_eq!(frames, 2000);
+
+        source.seek(1234)?;
+        assert_eq!(source.read(&mut out[..2])?, 2);
+        assert_eq!(out[..2], [sample(1234), sample(1235)]);
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn decoded_music_loops_in_the_mixer() -> anyhow::Result<()> {
+        let path = ramp_file("music_loop");
+        let mut mixer = Mixer::new(1000);
+        mixer.music().play(
+            Box::new(DecoderSource::open(&path)?),
+            MusicOptions::default().with_loop_points(1500, Some(1600)),
+        );
+
+        // 1700 frames, the last 100 wrap back to the loop start.
+        let mut out = vec![0.0; 3400];
+        mixer.mix(&mut out);
+        assert_eq!(out[2 * 1599], sample(1599));
+        assert_eq!(out[2 * 1600], sample(1500));
+        assert_eq!(out[2 * 1699], sample(1599));
+        assert!(mixer.music().is_playing());
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+#[cfg(test)]
+mod tests {
+    use crate::sys::math::noise::{bake_image, Fractal, Noise, NoiseKind};
+
+    fn grid() -> impl Iterator<Item = (f32, f32, f32)> {
+        (0..2000).map(|i| {
+            let i = i as f32;
+            (i * 0.137, i * 0.291 - 40.0, i * 0.073 + 3.0)
+        })
+    }
+
+    #[test]
+    fn seeded_and_deterministic() {
+        let a = Noise::new(7);
+        let b = Noise::new(7);
+        let c = Noise::new(8);
+        let mut differs = false;
+        for (x, y, z) in grid() {
+            assert_eq!(a.perlin3(x, y, z), b.perlin3(x, y, z));
+            assert_eq!(a.simplex2(x, y), b.simplex2(x, y));
+            differs |= a.perlin2(x, y) != c.perlin2(x, y);
+        }
+        assert!(differs);
+    }
+
+    #[test]
+    fn ranges() {
+        let n = Noise::new(42);
+        let fractal = Fractal {
+            kind: NoiseKind::Simplex,
+            ..Default::default()
+        };
+        for (x, y, z) in grid() {
+            for v in [
+                n.perlin2(x, y),
+                n.perlin3(x, y, z),
+                n.simplex2(x, y),
+                n.simplex3(x, y, z),
+                n.fbm2(&fractal, x, y),
+                n.fbm3(&Fractal::default(), x, y, z),
+            ] {
+                assert!((-1.01..=1.01).contains(&v), "{}", v);
+            }
+            let r = n.ridged2(&fractal, x, y);
+            assert!((0.0..=1.0).contains(&r), "{}", r);
+        }
+        // Lattice points are always zero for gradient noise.
+        assert_eq!(n.perlin2(3.0, -5.0), 0.0);
+    }
+
+    #[test]
+    fn bake() {
+        let n = Noise::new(1);
+        let img = bake_image(16, 8, |u, v| n.perlin2(u * 4.0, v * 4.0));
+        assert_eq!(img.dimensions(), (16, 8));
+        assert!(img.pixels().any(|p| p.0[0] != img.get_pixel(0, 0).0[0]));
+    }
+}
@@ -0,0 +1,157 @@
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    use crate::{
+        gfx::{font::BmFont, wgpu::vertex::Vertex3D},
+        sys::{
+            baked::{
+                BakedFont, BakedMaterial, BakedMesh, BakedModel, BakedModelView, BakedTexture,
+                TextureEncoding,
+            },
+            import::{import_dir, ImportSettings},
+            pack::{AssetKind, AssetPack, PackWriter, BLOB_ALIGN},
+        },
+    };
+
+    #[test]
+    fn pack_round_trip() {
+        let mut writer = PackWriter::new();
+        writer.add("a.bin", AssetKind::Raw, vec![1, 2, 3]);
+        writer.add("b/c.bin", AssetKind::Raw, vec![4; 20]);
+        writer.add("a.bin", AssetKind::Raw, vec![5]);
+
+        let pack = AssetPack::from_bytes(&writer.to_bytes()).unwrap();
+        assert_eq!(pack.entries().len(), 2);
+        assert_eq!(pack.get("a.bin"), Some(&[5u8][..]));
+        assert_eq!(pack.get("b/c.bin").unwrap().len(), 20);
+        assert!(pack.entries().iter().all(|e| e.offset % BLOB_ALIGN == 0));
+        assert!(pack.get("missing").is_none());
+
+        assert!(AssetPack::from_bytes(b"nope").is_err());
+    }
+
+    #[test]
+    fn baked_texture_has_full_mip_chain() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::new(8, 2));
+        let baked = BakedTexture::from_image(&img);
+        assert_eq!(baked.mips.len(), 4);
+        assert_eq!(baked.mip_size(3), (1, 1));
+        assert_eq!(baked.mips[1].len(), 4 * 4);
+
+        let decoded = BakedTexture::from_bytes(&baked.to_bytes()).unwrap();
+        assert_eq!(decoded, baked);
+
+        // Version 1 textures have no encoding and are RGBA8.
+        let mut v1 = baked.to_bytes();
+        v1.splice(4..8, 1u32.to_le_bytes());
+        v1.drain(16..20);
+        assert_eq!(BakedTexture::from_bytes(&v1).unwrap(), baked);
+    }
+
+    #[test]
+    fn baked_texture_compression() {
+        let opaque = DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 4, Rgba([0, 255, 0, 255])));
+        let baked = BakedTexture::from_image(&opaque);
+        let bc1 = baked.compress().unwrap();
+        assert_eq!(bc1.encoding, TextureEncoding::Bc1);
+        // 8x4, 4x2, 2x1 and 1x1 each round up to whole 4x4 blocks.
+        let sizes: Vec<_> = bc1.mips.iter().map(Vec::len).collect();
+        assert_eq!(sizes, [16, 8, 8, 8]);
+        assert!(bc1.compress().is_none());
+        assert_eq!(BakedTexture::from_bytes(&bc1.to_bytes()).unwrap(), bc1);
+        assert_eq!(bc1.decompress(), baked);
+
+        let clear = DynamicImage::ImageRgba8(RgbaImage::new(4, 4));
+        let bc3 = BakedTexture::from_image(&clear).compress().unwrap();
+        assert_eq!(bc3.encoding, TextureEncoding::Bc3);
+        assert_eq!(bc3.decompress().mips[0], vec![0; 4 * 4 * 4]);
+
+        let odd = DynamicImage::ImageRgba8(RgbaImage::new(6, 4));
+        assert!(BakedTexture::from_image(&odd).compress().is_none());
+    }
+
+    #[test]
+    fn baked_model_round_trip() {
+        let vertex = Vertex3D {
+            position: [1.0, 2.0, 3.0],
+            ..Default::default()
+        };
+        let model = BakedModel {
+            meshes: vec![BakedMesh {
+                name: String::from("cube"),
+                material: 0,
+                vertices: vec![vertex; 3],
+                indices: vec![0, 1, 2],
+            }],
+            materials: vec![BakedMaterial {
+                name: String::from("mat"),
+                diffuse_texture: String::from("d.png"),
+                normal_texture: String::from("n.png"),
+            }],
+        };
+
+        let decoded = BakedModel::from_bytes(&model.to_bytes()).unwrap();
+        assert_eq!(decoded.materials, model.materials);
+        assert_eq!(decoded.meshes[0].indices, [0, 1, 2]);
+        assert_eq!(decoded.meshes[0].vertices[2].position, [1.0, 2.0, 3.0]);
+
+        // Aligned storage lets the mesh data be used in place.
+        let mut writer = PackWriter::new();
+        writer.add("cube.obj", AssetKind::Mesh, model.to_bytes());
+        let pack = AssetPack::from_bytes(&writer.to_bytes()).unwrap();
+        let view = BakedModelView::parse(pack.get("cube.obj").unwrap()).unwrap();
+        assert!(view.meshes[0].is_zero_copy());
+        assert_eq!(view.meshes[0].vertices.len(), 3);
+
+        let mut bytes = model.to_bytes();
+        bytes[4] = 99;
+        assert!(BakedModelView::parse(&bytes).is_err());
+    }
+
+    #[test]
+    fn baked_font_round_trip() {
+        let font = BakedFont {
+            font: BmFont::parse(
+                "common lineHeight=12 base=9 scaleW=128 scaleH=128\n\
+                 page id=0 file=\"fonts/sans.ttf#atlas\"\n\
+                 char id=65 x=1 y=2 width=10 height=12 xoffset=-3 yoffset=-1 xadvance=8",
+            )
+            .unwrap(),
+            distance_range: 4.0,
+        };
+        let decoded = BakedFont::from_bytes(&font.to_bytes()).unwrap();
+        assert_eq!(decoded, font);
+        assert_eq!(decoded.font.pages, ["fonts/sans.ttf#atlas"]);
+
+        let mut bytes = font.to_bytes();
+        bytes[4] = 99;
+        assert!(BakedFont::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn import_dir_skips_broken_files() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join(format!("radium_import_{}", std::process::id()));
+        let src = dir.join("src");
+        std::fs::create_dir_all(&src)?;
+        std::fs::write(src.join("broken.png"), b"not a png")?;
+        std::fs::write(src.join("notes.txt"), b"kept")?;
+        for name in ["ok.png", "ok.bmp", "ok.tga"] {
+            RgbaImage::new(4, 4).save(src.join(name))?;
+        }
+
+        let out = dir.join("assets.pak");
+        let report = import_dir(&src, &out, &ImportSettings::default())?;
+        assert_eq!((report.textures, report.compressed, report.raw), (3, 3, 1));
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].0, "broken.png");
+        assert!(!report.skipped[0].1.is_empty());
+
+        let pack = AssetPack::from_bytes(&std::fs::read(&out)?)?;
+        assert!(pack.get("ok.tga").is_some());
+        assert_eq!(pack.get("notes.txt"), Some(&b"kept"[..]));
+        assert!(pack.get("broken.png").is_none());
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
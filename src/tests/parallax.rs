@@ -0,0 +1,46 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::gfx::{
+        parallax::{ParallaxBackground, ParallaxLayer},
+        quad::MaterialSlot,
+    };
+
+    #[test]
+    fn layers_tile_across_the_view() {
+        let mut background = ParallaxBackground::new();
+        background.add_layer(ParallaxLayer::new(MaterialSlot(1), [10.0, 10.0]));
+        let sprites = background.sprites([0.0, 0.0], [25.0, 10.0]);
+        let xs: Vec<f32> = sprites.iter().map(|s| s.position[0]).collect();
+        assert_eq!(xs, [-20.0, -10.0, 0.0, 10.0]);
+        assert!(sprites.iter().all(|s| s.position[1] == 0.0));
+        assert!(sprites
+            .iter()
+            .all(|s| s.layer == ParallaxBackground::BASE_LAYER));
+    }
+
+    #[test]
+    fn scroll_factor_and_auto_scroll_move_layers() {
+        let mut background = ParallaxBackground::new();
+        background.add_layer(
+            ParallaxLayer::new(MaterialSlot(1), [10.0, 10.0])
+                .with_scroll_factor([0.5, 1.0])
+                .with_repeat(false, false),
+        );
+        background.add_layer(
+            ParallaxLayer::new(MaterialSlot(2), [10.0, 10.0])
+                .with_scroll_factor([0.0, 0.0])
+                .with_auto_scroll([4.0, 0.0])
+                .with_repeat(false, false),
+        );
+        background.frame_update(Duration::from_millis(500));
+
+        let sprites = background.sprites([8.0, 0.0], [10.0, 10.0]);
+        // Half speed: the camera moved 8, the layer 4.
+        assert_eq!(sprites[0].position, [4.0, 0.0]);
+        // Pinned to the camera plus 2 units of drift.
+        assert_eq!(sprites[1].position, [10.0, 0.0]);
+        assert!(sprites[0].layer < sprites[1].layer);
+    }
+}
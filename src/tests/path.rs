@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        eng::ai::path::{Diagonal, GridMap, RegionGraph},
+        sys::geom::Vec2,
+    };
+
+    const LAYER: &str = "\
+.....
+.###.
+...#.
+.#...
+.....";
+
+    fn map() -> GridMap {
+        let tiles = LAYER.lines().flat_map(str::chars).collect::<Vec<_>>();
+        GridMap::from_layer(5, 5, &tiles, |&t| (t == '.').then_some(1.0))
+    }
+
+    #[test]
+    fn grid() {
+        let map = map();
+        let path = map.find_path((0, 2), (4, 2), Diagonal::Never).unwrap();
+        assert_eq!(path.first(), Some(&(0, 2)));
+        assert_eq!(path.last(), Some(&(4, 2)));
+        assert_eq!(path.len(), 7);
+        for w in path.windows(2) {
+            let (dx, dy) = (w[1].0 - w[0].0, w[1].1 - w[0].1);
+            assert_eq!(dx.abs() + dy.abs(), 1);
+            assert!(map.is_walkable(w[1].0, w[1].1));
+        }
+
+        let diag = map.find_path((0, 4), (4, 0), Diagonal::Always).unwrap();
+        let manhattan = map.find_path((0, 4), (4, 0), Diagonal::Never).unwrap();
+        assert!(diag.len() < manhattan.len());
+        assert!(map.find_path((0, 0), (1, 1), Diagonal::Never).is_none());
+
+        let smooth = map.smooth_path(&path);
+        assert!(smooth.len() < path.len());
+        assert_eq!(smooth.first(), path.first());
+        assert_eq!(smooth.last(), path.last());
+    }
+
+    #[test]
+    fn weighted_tiles_are_avoided() {
+        let mut map = GridMap::new(5, 3);
+        for x in 1..4 {
+            map.set_cost(x, 1, Some(10.0));
+        }
+        let path = map.find_path((0, 1), (4, 1), Diagonal::Never).unwrap();
+        assert!(path.iter().all(|&(x, y)| y != 1 || x == 0 || x == 4));
+    }
+
+    #[test]
+    fn regions() {
+        let v = Vec2::new;
+        // An L shaped corridor made of three squares.
+        let graph = RegionGraph::new(vec![
+            vec![v(0.0, 0.0), v(1.0, 0.0), v(1.0, 1.0), v(0.0, 1.0)],
+            vec![v(1.0, 0.0), v(2.0, 0.0), v(2.0, 1.0), v(1.0, 1.0)],
+            vec![v(1.0, 1.0), v(2.0, 1.0), v(2.0, 2.0), v(1.0, 2.0)],
+        ]);
+        assert_eq!(graph.region_at(v(1.5, 1.5)), Some(2));
+        assert_eq!(graph.region_at(v(0.5, 1.5)), None);
+
+        let path = graph.find_path(v(0.2, 0.8), v(1.2, 1.8)).unwrap();
+        // Pulled tight around the inner corner at (1, 1).
+        assert_eq!(path, vec![v(0.2, 0.8), v(1.0, 1.0), v(1.2, 1.8)]);
+        let straight = graph.find_path(v(0.5, 0.5), v(1.5, 0.5)).unwrap();
+        assert_eq!(straight, vec![v(0.5, 0.5), v(1.5, 0.5)]);
+    }
+}
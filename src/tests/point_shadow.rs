@@ -0,0 +1,51 @@
+#[cfg(test)]
+mod tests {
+    use cgmath::{Point3, Vector3, Vector4};
+
+    use crate::gfx::{
+        cluster::PointLight,
+        point_shadow::{cube_face_view_proj, select_shadow_lights},
+    };
+
+    #[test]
+    fn only_the_nearest_casting_lights_get_shadows() {
+        let lights = [
+            PointLight::new(Point3::new(10.0, 0.0, 0.0), 5.0),
+            PointLight::new(Point3::new(1.0, 0.0, 0.0), 5.0).with_casts_shadows(false),
+            PointLight::new(Point3::new(3.0, 0.0, 0.0), 5.0),
+            PointLight::new(Point3::new(0.0, 2.0, 0.0), 5.0),
+            PointLight::new(Point3::new(0.0, 0.0, 1.0), 0.0),
+        ];
+        let selected = select_shadow_lights(&lights, Point3::new(0.0, 0.0, 0.0), 2);
+        assert_eq!(selected, vec![3, 2]);
+        assert!(select_shadow_lights(&lights, Point3::new(0.0, 0.0, 0.0), 0).is_empty());
+    }
+
+    #[test]
+    fn faces_follow_the_cube_sampling_convention() {
+        // Major axis and the directions texture u and v grow towards on each face.
+        let faces = [
+            (Vector3::unit_x(), -Vector3::unit_z(), -Vector3::unit_y()),
+            (-Vector3::unit_x(), Vector3::unit_z(), -Vector3::unit_y()),
+            (Vector3::unit_y(), Vector3::unit_x(), Vector3::unit_z()),
+            (-Vector3::unit_y(), Vector3::unit_x(), -Vector3::unit_z()),
+            (Vector3::unit_z(), Vector3::unit_x(), -Vector3::unit_y()),
+            (-Vector3::unit_z(), -Vector3::unit_x(), -Vector3::unit_y()),
+        ];
+        let light = Point3::new(1.0, 2.0, 3.0);
+        for (face, (axis, u, v)) in faces.into_iter().enumerate() {
+            let view_proj = cube_face_view_proj(light, face, 0.05, 10.0);
+            let ndc = |offset: Vector3<f32>| {
+                let p = view_proj * Vector4::new(light.x, light.y, light.z, 1.0)
+                    + view_proj * (offset).extend(0.0);
+                p.truncate() / p.w
+            };
+            let center = ndc(axis * 2.0);
+            assert!(center.x.abs() < 1e-5 && center.y.abs() < 1e-5);
+            assert!((0.0..=1.0).contains(&center.z));
+            // Texture rows run top to bottom, so v grows downwards in NDC.
+            assert!(ndc(axis * 2.0 + u * 0.5).x > 0.1, "face {}", face);
+            assert!(ndc(axis * 2.0 + v * 0.5).y < -0.1, "face {}", face);
+        }
+    }
+}
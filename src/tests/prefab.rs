@@ -0,0 +1,53 @@
+#[cfg(all(test, feature = "scene"))]
+mod tests {
+    use cgmath::Vector3;
+
+    use crate::eng::{
+        prefab::{Prefab, PrefabNode, PrefabOverrides},
+        scene::{PropertyValue, Scene},
+    };
+
+    fn lamp(height: f32) -> Prefab {
+        Prefab::new(
+            "lamp",
+            vec![
+                PrefabNode::new("post"),
+                PrefabNode::new("bulb")
+                    .with_parent(0)
+                    .with_position([0.0, height, 0.0])
+                    .with_property("tint", PropertyValue::Vec3([1.0; 3])),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn instances_keep_overrides_across_reload() {
+        let mut scene = Scene::default();
+        let id = scene.instantiate(
+            &lamp(2.0),
+            PrefabOverrides::default()
+                .with_position(Vector3::new(5.0, 0.0, 0.0))
+                .with_tint([1.0, 0.0, 0.0]),
+        );
+        let bulb = &scene.entities[1];
+        assert_eq!(bulb.transform.position(), Vector3::new(5.0, 2.0, 0.0));
+        assert_eq!(bulb.properties[0].1, PropertyValue::Vec3([1.0, 0.0, 0.0]));
+
+        assert_eq!(scene.reload_prefab(&lamp(3.0)), 1);
+        let bulb: Vec<_> = scene.instance_entities(id).collect();
+        assert_eq!(bulb.len(), 2);
+        let bulb = &scene.entities[bulb[1]];
+        assert_eq!(bulb.transform.position(), Vector3::new(5.0, 3.0, 0.0));
+        assert_eq!(bulb.properties[0].1, PropertyValue::Vec3([1.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn children_must_follow_their_parent() {
+        let nodes = vec![
+            PrefabNode::new("child").with_parent(1),
+            PrefabNode::new("root"),
+        ];
+        assert!(Prefab::new("broken", nodes).is_err());
+    }
+}
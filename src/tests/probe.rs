@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests {
+    use cgmath::{Point3, Vector4};
+
+    use crate::{
+        gfx::probe::{select_probe, ReflectionProbe},
+        sys::{
+            cubemap::{face_uv, BakedCubemap, CubemapEncoding},
+            pack::{AssetKind, AssetPack, PackWriter},
+        },
+    };
+
+    #[test]
+    fn nested_probes_prefer_the_closer_center() {
+        let probes = [
+            ReflectionProbe::new(Point3::new(0.0, 0.0, 0.0), 10.0),
+            ReflectionProbe::new(Point3::new(4.0, 0.0, 0.0), 2.0),
+        ];
+        assert_eq!(select_probe(&probes, Point3::new(4.5, 0.0, 0.0)), Some(1));
+        // Inside both, but relatively closer to the big probe's center.
+        assert_eq!(select_probe(&probes, Point3::new(3.0, 1.5, 0.0)), Some(0));
+        assert_eq!(select_probe(&probes, Point3::new(20.0, 0.0, 0.0)), None);
+        assert_eq!(select_probe(&[], Point3::new(0.0, 0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn faces_land_where_the_cube_lookup_reads() {
+        let probe = ReflectionProbe::new(Point3::new(1.0, 2.0, 3.0), 5.0);
+        let dirs = [
+            [1.0, 0.3, -0.2],
+            [-1.0, -0.4, 0.6],
+            [0.2, 1.0, 0.7],
+            [-0.5, -1.0, 0.1],
+            [0.6, -0.2, 1.0],
+            [-0.3, 0.5, -1.0],
+        ];
+        for dir in dirs {
+            let (face, [u, v]) = face_uv(dir);
+            let point = Vector4::new(1.0 + dir[0], 2.0 + dir[1], 3.0 + dir[2], 1.0);
+            let clip = probe.face_view_proj(face) * point;
+            let ndc = clip.truncate() / clip.w;
+            // Rendered rows are flipped on readback, so ndc y up maps to v up.
+            assert!((u - (ndc.x + 1.0) * 0.5).abs() < 1e-5, "{:?}", dir);
+            assert!((v - (ndc.y + 1.0) * 0.5).abs() < 1e-5, "{:?}", dir);
+            assert!((0.0..=1.0).contains(&ndc.z));
+        }
+    }
+
+    #[test]
+    fn cubemaps_round_trip_compressed() {
+        let mut cubemap = BakedCubemap::solid(2, [0, 0, 0, 255]);
+        for (i, face) in cubemap.faces.iter_mut().enumerate() {
+            face[..4].copy_from_slice(&[i as u8 * 40, 255, 17, 255]);
+        }
+        assert_eq!(cubemap.sample([0.0, 0.0, -1.0]), [0, 0, 0, 255]);
+        assert_eq!(cubemap.sample([-1.0, 0.9, -0.9]), [40, 255, 17, 255]);
+
+        let raw = cubemap.to_bytes(CubemapEncoding::Rgba8);
+        let compressed = cubemap.to_bytes(CubemapEncoding::Rgb565);
+        assert!(compressed.len() < raw.len());
+        assert_eq!(BakedCubemap::from_bytes(&raw).unwrap(), cubemap);
+
+        let mut writer = PackWriter::new();
+        writer.add("room.probe", AssetKind::Cubemap, compressed);
+        let pack = AssetPack::from_bytes(&writer.to_bytes()).unwrap();
+        assert_eq!(pack.entry("room.probe").unwrap().kind, AssetKind::Cubemap);
+        let read = BakedCubemap::from_bytes(pack.get("room.probe").unwrap()).unwrap();
+        for (a, b) in read
+            .faces
+            .iter()
+            .flatten()
+            .zip(cubemap.faces.iter().flatten())
+        {
+            assert!(a.abs_diff(*b) <= 4);
+        }
+        assert!(BakedCubemap::from_bytes(&raw[..raw.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,183 @@
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use crate::gfx::{
+        quad::{
+            dirty_quads, material_batches, quad_indices, MaterialSlot, QuadBatch, Sprite, UvRect,
+        },
+        wgpu::vertex::SpriteVertex,
+    };
+
+    fn approx(a: [f32; 3], b: [f32; 3]) -> bool {
+        a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5)
+    }
+
+    #[test]
+    fn pivot_and_rotation() {
+        let v = Sprite::new([10.0, 10.0], [2.0, 2.0])
+            .with_rotation(FRAC_PI_2)
+            .vertices();
+        // Bottom left corner (-1, -1) from the center rotates to (1, -1).
+        assert!(approx(v[0].position, [11.0, 9.0, 0.0]));
+
+        let v = Sprite::new([0.0, 0.0], [2.0, 1.0])
+            .with_pivot([0.0, 0.0])
+            .with_layer(3, 0.5)
+            .vertices();
+        assert!(approx(v[2].position, [2.0, 1.0, 0.5]));
+    }
+
+    #[test]
+    fn flip_uvs() {
+        let uv = UvRect::from_grid(1, 0, 2, 2);
+        assert_eq!(uv, UvRect::new([0.5, 0.0], [1.0, 0.5]));
+
+        let v = Sprite::new([0.0, 0.0], [1.0, 1.0]).with_uv(uv).vertices();
+        assert_eq!(v[0].tex_coords, [0.5, 0.5]);
+
+        let v = Sprite::new([0.0, 0.0], [1.0, 1.0])
+            .with_uv(uv)
+            .flipped(true, true)
+            .vertices();
+        assert_eq!(v[0].tex_coords, [1.0, 0.0]);
+    }
+
+    /// Positions and uvs of every corner, compared exactly so any change in the corner
+    /// order or uv mapping shows up.
+    fn snapshot(sprite: Sprite) -> [([f32; 3], [f32; 2]); 4] {
+        sprite.vertices().map(|v| (v.position, v.tex_coords))
+    }
+
+    #[test]
+    fn default_sprite_snapshot() {
+        let sprite = Sprite::new([4.0, 2.0], [2.0, 4.0]).with_color([0.5, 0.25, 1.0, 0.75]);
+        assert_eq!(
+            snapshot(sprite),
+            [
+                ([3.0, 0.0, 0.0], [0.0, 1.0]),
+                ([5.0, 0.0, 0.0], [1.0, 1.0]),
+                ([5.0, 4.0, 0.0], [1.0, 0.0]),
+                ([3.0, 4.0, 0.0], [0.0, 0.0]),
+            ]
+        );
+        assert!(sprite
+            .vertices()
+            .iter()
+            .all(|v| v.color == [0.5, 0.25, 1.0, 0.75]));
+    }
+
+    #[test]
+    fn zero_size_collapses_to_position() {
+        let v = Sprite::new([7.0, -3.0], [0.0, 0.0])
+            .with_rotation(1.0)
+            .with_layer(0, 0.25)
+            .vertices();
+        assert!(v.iter().all(|v| v.position == [7.0, -3.0, 0.25]));
+        // The uvs still cover the rect so a sprite scaled up from zero samples correctly.
+        assert_eq!(v[0].tex_coords, [0.0, 1.0]);
+        assert_eq!(v[2].tex_coords, [1.0, 0.0]);
+    }
+
+    #[test]
+    fn single_axis_flips() {
+        let base = Sprite::new([0.0, 0.0], [1.0, 1.0])
+            .with_pivot([0.0, 0.0])
+            .with_uv(UvRect::new([0.25, 0.5], [0.75, 1.0]));
+        let positions = snapshot(base).map(|(p, _)| p);
+
+        let flip_x = snapshot(base.flipped(true, false));
+        assert_eq!(flip_x.map(|(p, _)| p), positions);
+        assert_eq!(
+            flip_x.map(|(_, uv)| uv),
+            [[0.75, 1.0], [0.25, 1.0], [0.25, 0.5], [0.75, 0.5]]
+        );
+
+        let flip_y = snapshot(base.flipped(false, true));
+        assert_eq!(flip_y.map(|(p, _)| p), positions);
+        assert_eq!(
+            flip_y.map(|(_, uv)| uv),
+            [[0.25, 0.5], [0.75, 0.5], [0.75, 1.0], [0.25, 1.0]]
+        );
+    }
+
+    #[test]
+    fn grid_edges() {
+        assert_eq!(UvRect::from_grid(0, 0, 1, 1), UvRect::FULL);
+        assert_eq!(
+            UvRect::from_grid(3, 1, 4, 2),
+            UvRect::new([0.75, 0.5], [1.0, 1.0])
+        );
+        // Zero columns or rows are treated as one instead of dividing by zero.
+        assert_eq!(UvRect::from_grid(0, 0, 0, 0), UvRect::FULL);
+    }
+
+    #[test]
+    fn indices() {
+        assert!(quad_indices(0).is_empty());
+        assert_eq!(quad_indices(2), [0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7]);
+
+        // Both triangles of a unit quad wind counter clockwise.
+        let v = Sprite::new([0.0, 0.0], [1.0, 1.0]).vertices();
+        for tri in quad_indices(1).chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| v[tri[i] as usize].position);
+            let cross = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+            assert!(cross > 0.0);
+        }
+    }
+
+    #[test]
+    fn batches_split_on_material_changes() {
+        let dissolve = MaterialSlot(1);
+        let slots = [
+            MaterialSlot::DEFAULT,
+            MaterialSlot::DEFAULT,
+            dissolve,
+            MaterialSlot::DEFAULT,
+        ];
+        assert_eq!(
+            material_batches(slots),
+            [
+                QuadBatch {
+                    material: MaterialSlot::DEFAULT,
+                    quads: 0..2
+                },
+                QuadBatch {
+                    material: dissolve,
+                    quads: 2..3
+                },
+                QuadBatch {
+                    material: MaterialSlot::DEFAULT,
+                    quads: 3..4
+                },
+            ]
+        );
+        assert_eq!(
+            Sprite::new([0.0, 0.0], [1.0, 1.0])
+                .with_material(dissolve)
+                .material,
+            dissolve
+        );
+    }
+
+    fn row(xs: &[f32]) -> Vec<SpriteVertex> {
+        xs.iter()
+            .flat_map(|&x| Sprite::new([x, 0.0], [1.0, 1.0]).vertices())
+            .collect()
+    }
+
+    #[test]
+    fn only_changed_quads_are_dirty() {
+        let old = row(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert!(dirty_quads(&old, &old, 8).is_empty());
+        assert_eq!(dirty_quads(&[], &old, 8), vec![0..6]);
+
+        let new = row(&[0.0, 9.0, 2.0, 3.0, 4.0, 9.0, 6.0]);
+        // Quad 6 is past the end of the old buffer.
+        assert_eq!(dirty_quads(&old, &new, 1), [1..2, 5..7]);
+        // Close ranges are merged across the unchanged quads between them.
+        assert_eq!(dirty_quads(&old, &new, 8), vec![1..7]);
+        // Shrinking leaves nothing to write, the draw only covers the new length.
+        assert!(dirty_quads(&old, &old[..8], 8).is_empty());
+    }
+}
@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use cgmath::InnerSpace;
+
+    use crate::sys::rand::{Rng, RngStream, RngStreams};
+
+    #[test]
+    fn deterministic() {
+        let mut a = Rng::new(1234);
+        let mut b = Rng::new(1234);
+        let xs = (0..64).map(|_| a.next_u64()).collect::<Vec<_>>();
+        let ys = (0..64).map(|_| b.next_u64()).collect::<Vec<_>>();
+        assert_eq!(xs, ys);
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn ranges_and_sampling() {
+        let mut rng = Rng::new(9);
+        for _ in 0..1000 {
+            assert!((-3..5).contains(&rng.range_i32(-3..5)));
+            let f = rng.range_f32(2.0..2.5);
+            assert!((2.0..2.5).contains(&f));
+            assert!((rng.on_unit_circle().magnitude() - 1.0).abs() < 1e-4);
+            assert!(rng.in_unit_sphere().magnitude() <= 1.0 + 1e-4);
+        }
+        assert_eq!(rng.range_i32(4..4), 4);
+        assert_eq!(rng.choose::<u8>(&[]), None);
+
+        assert_eq!(rng.weighted_index(&[0.0, -1.0]), None);
+        for _ in 0..100 {
+            assert_eq!(rng.weighted_index(&[0.0, 3.0, 0.0]), Some(1));
+        }
+        let hits = (0..10_000)
+            .filter(|_| *rng.weighted_choice(&[('a', 1.0), ('b', 3.0)]).unwrap() == 'b')
+            .count();
+        assert!((7000..8000).contains(&hits), "{}", hits);
+
+        let mut items = (0..32).collect::<Vec<_>>();
+        rng.shuffle(&mut items);
+        items.sort();
+        assert_eq!(items, (0..32).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn streams_are_independent() {
+        let mut a = RngStreams::new(77);
+        let mut b = RngStreams::new(77);
+        // Drawing visual randomness must not change the gameplay sequence.
+        for _ in 0..10 {
+            a.visual().next_u64();
+        }
+        assert_eq!(a.gameplay().next_u64(), b.gameplay().next_u64());
+        assert_ne!(
+            a.get(RngStream::Custom(0)).next_u64(),
+            a.get(RngStream::Custom(1)).next_u64()
+        );
+
+        let first = b.gameplay().next_u64();
+        b.reset(77);
+        b.gameplay().next_u64();
+        assert_eq!(b.gameplay().next_u64(), first);
+    }
+}
@@ -0,0 +1,33 @@
+#[cfg(test)]
+mod tests {
+    use crate::eng::readback::{staging_size, TexelLayout, MIN_STAGING_SIZE};
+
+    #[test]
+    fn staging_sizes_are_pooled_powers_of_two() {
+        assert_eq!(staging_size(0), MIN_STAGING_SIZE);
+        assert_eq!(staging_size(8), MIN_STAGING_SIZE);
+        assert_eq!(staging_size(MIN_STAGING_SIZE), MIN_STAGING_SIZE);
+        assert_eq!(staging_size(MIN_STAGING_SIZE + 1), MIN_STAGING_SIZE * 2);
+        assert_eq!(staging_size(1000), 1024);
+        assert_eq!(staging_size(1 << 20), 1 << 20);
+    }
+
+    #[test]
+    fn texel_rows_are_padded_and_unpadded() {
+        let layout = TexelLayout::new(3, 2, 4);
+        assert_eq!(layout.row_len, 12);
+        assert_eq!(layout.padded_row_len, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        assert_eq!(layout.size(), 2 * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as u64);
+
+        let mut padded = vec![0xff; layout.size() as usize];
+        for row in 0..2 {
+            for i in 0..12 {
+                padded[row * layout.padded_row_len as usize + i] = (row * 12 + i) as u8;
+            }
+        }
+        assert_eq!(layout.unpad(&padded), (0..24).collect::<Vec<u8>>());
+
+        let aligned = TexelLayout::new(64, 1, 4);
+        assert_eq!(aligned.padded_row_len, aligned.row_len);
+    }
+}
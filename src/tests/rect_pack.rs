@@ -0,0 +1,62 @@
+#[cfg(test)]
+mod tests {
+    use crate::sys::{rand::Rng, rect_pack::SkylinePacker};
+
+    fn overlaps(a: ([u32; 2], [u32; 2]), b: ([u32; 2], [u32; 2])) -> bool {
+        let ([ax, ay], [aw, ah]) = a;
+        let ([bx, by], [bw, bh]) = b;
+        ax < bx + bw && bx < ax + aw && ay < by + bh && by < ay + ah
+    }
+
+    #[test]
+    fn exact_fit() {
+        let mut packer = SkylinePacker::new(64, 64);
+        let placements = packer.pack(&[[32, 32]; 4]);
+        assert!(placements.iter().all(Option::is_some));
+        assert_eq!(packer.stats().efficiency(), 1.0);
+        assert_eq!(packer.insert(1, 1), None);
+        assert_eq!(packer.stats().rejected, 1);
+
+        packer.clear();
+        assert_eq!(packer.insert(64, 64), Some([0, 0]));
+        assert_eq!(packer.insert(65, 1), None);
+    }
+
+    #[test]
+    fn random_rects_never_overlap() {
+        let mut rng = Rng::new(42);
+        let sizes = (0..300)
+            .map(|_| [rng.range_i32(1..40) as u32, rng.range_i32(1..40) as u32])
+            .collect::<Vec<_>>();
+        let padding = 2;
+        let mut packer = SkylinePacker::new(512, 512).with_padding(padding);
+        let placements = packer.pack(&sizes);
+
+        let placed = placements
+            .iter()
+            .zip(&sizes)
+            .filter_map(|(p, size)| p.map(|p| (p, *size)))
+            .collect::<Vec<_>>();
+        assert_eq!(placed.len(), packer.stats().packed);
+        let pad = |(p, size): ([u32; 2], [u32; 2])| (p, size.map(|s| s + padding));
+        for (i, &(p, size)) in placed.iter().enumerate() {
+            assert!(p[0] + size[0] <= 512 && p[1] + size[1] <= 512);
+            for &other in &placed[i + 1..] {
+                assert!(
+                    !overlaps(pad((p, size)), other),
+                    "{:?} overlaps {:?}",
+                    p,
+                    other
+                );
+                assert!(
+                    !overlaps(pad(other), (p, size)),
+                    "{:?} overlaps {:?}",
+                    p,
+                    other
+                );
+            }
+        }
+        // Tallest first packing of small rects should leave little of the used rows empty.
+        assert!(packer.stats().cropped_efficiency() > 0.6);
+    }
+}
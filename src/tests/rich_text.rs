@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use crate::gfx::{
+        font::{BitmapFont, BmFont},
+        quad::MaterialSlot,
+        rich_text::{
+            parse_markup, FontFamily, FontStyle, HAlign, TextEffect, TextLayout, TextSpan, VAlign,
+        },
+    };
+
+    const FNT: &str = r#"info face="Mono" size=8
+common lineHeight=10 base=8 scaleW=64 scaleH=64 pages=1
+page id=0 file="mono.png"
+char id=65 x=0 y=0 width=6 height=8 xoffset=0 yoffset=0 xadvance=7 page=0
+char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=4 page=0
+"#;
+
+    fn family() -> FontFamily {
+        let font = BmFont::parse(FNT).unwrap();
+        FontFamily::new(BitmapFont::new(font.clone(), vec![MaterialSlot(1)]).unwrap())
+            .with_bold(BitmapFont::new(font, vec![MaterialSlot(2)]).unwrap())
+    }
+
+    #[test]
+    fn wraps_at_spaces_and_aligns_lines_in_the_box() {
+        let layout = TextLayout::new()
+            .with_max_width(25.0)
+            .with_height(40.0)
+            .with_align(HAlign::Center, VAlign::Bottom)
+            .with_line_spacing(1.5);
+        let spans = [TextSpan::new("AAA AAA")];
+        let text = layout.layout(&family(), &spans, [0.0, 100.0]);
+        assert_eq!(text.lines, [0..3, 4..7]);
+        assert_eq!(text.size, [21.0, 25.0]);
+        assert_eq!(text.glyphs.len(), 6);
+        assert_eq!(text.glyphs[0].quad.position, [2.0, 77.0]);
+        assert_eq!(text.glyphs[3].quad.position, [2.0, 62.0]);
+        assert_eq!(text.glyphs[3].index, 4);
+
+        // A word wider than the line breaks mid word.
+        let spans = [TextSpan::new("AAAAA")];
+        let text = TextLayout::new()
+            .with_max_width(15.0)
+            .layout(&family(), &spans, [0.0; 2]);
+        assert_eq!(text.lines, [0..2, 2..4, 4..5]);
+    }
+
+    #[test]
+    fn markup_styles_spans_and_picks_font_variants() {
+        let spans = parse_markup("[b]A[/b] [color=#ff000080][wave]A[/wave][/color] [[").unwrap();
+        assert_eq!(spans.len(), 4);
+        assert_eq!(spans[0].style.font, FontStyle::Bold);
+        assert_eq!(spans[2].style.color, Some([1.0, 0.0, 0.0, 128.0 / 255.0]));
+        assert_eq!(spans[2].style.effect, TextEffect::WAVE);
+        assert_eq!(spans[3].text, " [");
+        assert!(parse_markup("[b]A[/i]").is_err());
+        assert!(parse_markup("[b]A").is_err());
+
+        let text = TextLayout::new().layout(&family(), &spans, [0.0; 2]);
+        let materials = text.glyphs.iter().map(|g| g.material).collect::<Vec<_>>();
+        assert_eq!(materials, [MaterialSlot(2), MaterialSlot(1)]);
+        assert_eq!(text.glyphs[1].effect.offset(0, 0.0), [0.0, 0.0]);
+    }
+}
@@ -0,0 +1,45 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        eng::app::EngineConfig,
+        gfx::scale::{DisplayScale, ScaleMode, VirtualResolution},
+    };
+
+    #[test]
+    fn logical_and_physical_round_trip() {
+        let scale = DisplayScale::new(2.0).with_ui_scale(1.25);
+        assert_eq!(scale.to_physical([10.0, 20.0]), [20.0, 40.0]);
+        assert_eq!(scale.to_logical([20.0, 40.0]), [10.0, 20.0]);
+        assert_eq!(scale.ui_size(16.0), 40.0);
+        assert_eq!(scale.to_ui([40.0, 5.0]), [16.0, 2.0]);
+        assert_eq!(DisplayScale::new(1.5).text_px(13.0), 20.0);
+        assert_eq!(DisplayScale::new(1.0).text_px(0.1), 1.0);
+
+        let config = EngineConfig::parse("ui_scale = 1.5").unwrap();
+        assert_eq!(config.ui_scale, 1.5);
+        assert_eq!(EngineConfig::parse(&config.to_string()).unwrap(), config);
+    }
+
+    #[test]
+    fn virtual_resolution_letterboxes() {
+        let fit = VirtualResolution::new(320, 180);
+        let window = [1000, 1000];
+        assert_eq!(fit.viewport(window), [0.0, 218.0, 1000.0, 562.5]);
+        assert_eq!(fit.to_virtual([500.0, 100.0], window), None);
+        assert_eq!(fit.to_virtual([0.0, 218.0], window), Some([0.0, 0.0]));
+        let p = fit.to_physical([160.0, 90.0], window);
+        assert_eq!(fit.to_virtual(p, window), Some([160.0, 90.0]));
+
+        let integer = fit.with_mode(ScaleMode::Integer);
+        assert_eq!(integer.viewport(window), [20.0, 230.0, 960.0, 540.0]);
+        // Never shrinks below one window pixel per virtual pixel.
+        assert_eq!(integer.scale([100, 100]), [1.0, 1.0]);
+
+        let stretch = fit.with_mode(ScaleMode::Stretch);
+        assert_eq!(stretch.viewport([640, 720]), [0.0, 0.0, 640.0, 720.0]);
+        assert_eq!(
+            stretch.to_virtual([320.0, 360.0], [640, 720]),
+            Some([160.0, 90.0])
+        );
+    }
+}
@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use cgmath::{Deg, InnerSpace, Matrix4, Point3, Vector3};
+
+    use crate::gfx::{
+        cull::Frustum,
+        scatter::{
+            DensityMap, HeightField, MeshSurface, ScatterChunks, ScatterFade, ScatterLayer,
+            ScatterSurface,
+        },
+    };
+    use crate::sys::rand::Rng;
+
+    fn flat(size: usize) -> HeightField {
+        HeightField::new(size, size, vec![0.0; size * size], [0.0, 0.0], 1.0)
+    }
+
+    #[test]
+    fn scattering_is_seeded_and_follows_the_density_map() {
+        // Left half bare, right half full.
+        let map = DensityMap {
+            width: 2,
+            height: 1,
+            values: vec![0.0, 1.0],
+            origin: [0.0, 0.0],
+            size: [32.0, 32.0],
+        };
+        let layer = ScatterLayer::new(2.0).with_density_map(map);
+        let field = flat(33);
+        let a = layer.scatter(&field, 7);
+        assert_eq!(a, layer.scatter(&field, 7));
+        assert_ne!(a, layer.scatter(&field, 8));
+        assert!(!a.is_empty());
+        // Bilinear density rises left to right, so the left quarter stays nearly empty.
+        let left = a.iter().filter(|i| i.position.x < 8.0).count();
+        let right = a.iter().filter(|i| i.position.x > 24.0).count();
+        assert!(left * 4 < right, "{} vs {}", left, right);
+    }
+
+    #[test]
+    fn steep_ground_is_left_bare() {
+        // A 45 degree ramp along x.
+        let heights = (0..16 * 16).map(|i| (i % 16) as f32).collect();
+        let ramp = HeightField::new(16, 16, heights, [0.0, 0.0], 1.0);
+        let normal = ramp.normal(5.0, 5.0);
+        assert!((normal.y - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-4);
+        assert_eq!(ramp.height(3.5, 2.0), Some(3.5));
+        assert!(ScatterLayer::new(1.0)
+            .with_max_slope(Deg(30.0))
+            .scatter(&ramp, 1)
+            .is_empty());
+        assert!(!ScatterLayer::new(1.0)
+            .with_max_slope(Deg(50.0))
+            .scatter(&ramp, 1)
+            .is_empty());
+    }
+
+    #[test]
+    fn mesh_points_land_on_the_triangles() {
+        let positions = [
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(4.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 4.0),
+        ];
+        let surface = MeshSurface::new(&positions, &[0, 2, 1]);
+        assert!((surface.area() - 8.0).abs() < 1e-5);
+        let mut rng = Rng::new(3);
+        for _ in 0..100 {
+            let p = surface.sample(&mut rng).unwrap();
+            assert!((p.position.y - 1.0).abs() < 1e-5);
+            assert!(p.position.x + p.position.z <= 4.0 + 1e-4);
+            assert!((p.normal - Vector3::unit_y()).magnitude2() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn chunks_are_contiguous_and_culled_by_distance() {
+        let instances = ScatterLayer::new(1.0).scatter(&flat(65), 11);
+        let count = instances.len();
+        let chunks = ScatterChunks::new(instances, 16.0, 0.5);
+        assert_eq!(chunks.chunks.len(), 16);
+        let mut next = 0;
+        for chunk in &chunks.chunks {
+            assert_eq!(chunk.instances.start, next);
+            next = chunk.instances.end;
+            for i in chunk.instances.clone() {
+                assert!(chunk.bounds.contains(chunks.instances[i as usize].position));
+            }
+        }
+        assert_eq!(next as usize, count);
+
+        // Looking down from above the first chunk, everything is inside the frustum.
+        let everything = Frustum::from_view_proj(Matrix4::from_scale(1e-3));
+        let viewer = Point3::new(8.0, 0.0, 8.0);
+        let near = chunks.visible(&everything, viewer, ScatterFade::new(5.0, 10.0));
+        assert!(near.len() < 16 && near.contains(&0));
+        let all = chunks.visible(&everything, viewer, ScatterFade::new(50.0, 100.0));
+        assert_eq!(all.len(), 16);
+
+        let fade = ScatterFade::new(10.0, 20.0);
+        assert_eq!(fade.factor(5.0), 1.0);
+        assert_eq!(fade.factor(25.0), 0.0);
+        assert!((fade.factor(15.0) - 0.5).abs() < 1e-5);
+    }
+}
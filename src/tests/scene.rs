@@ -0,0 +1,147 @@
+#[cfg(all(test, feature = "scene"))]
+mod tests {
+    use cgmath::{One, Point3, Quaternion, Vector3};
+
+    use crate::{
+        eng::scene::{PropertyValue, Scene, SceneEntity},
+        gfx::{
+            environment::{Environment, Fog},
+            gizmo::Ray,
+            quad::Sprite,
+            transform::Transform,
+            weather::WeatherSettings,
+        },
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Health(u32);
+
+    fn scene() -> Scene {
+        let crate_at = |x: f32| {
+            Transform::new(
+                Vector3::new(x, 0.0, 0.0),
+                Quaternion::one(),
+                Vector3::new(1.0, 1.0, 1.0),
+            )
+        };
+        Scene {
+            entities: vec![
+                SceneEntity::new("near", crate_at(0.0))
+                    .with_property("solid", PropertyValue::Bool(true))
+                    .with_tag("crate")
+                    .with_component(Sprite::new([0.0, 0.0], [1.0, 1.0]))
+                    .with_component(Health(10)),
+                SceneEntity::new("far", crate_at(5.0))
+                    .with_tag("crate")
+                    .with_tag("breakable")
+                    .with_component(Health(3)),
+            ],
+            environment: Environment::default()
+                .with_fog(Fog::Exponential { density: 0.05 }, [0.5, 0.6, 0.7]),
+            weather: WeatherSettings::snow(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn scene_round_trips_through_ron() {
+        let source = scene().to_ron().unwrap();
+        let loaded = Scene::from_ron(&source).unwrap();
+
+        assert_eq!(loaded.entities.len(), 2);
+        assert_eq!(loaded.entities[1].name, "far");
+        assert_eq!(loaded.entities[1].transform.position().x, 5.0);
+        assert_eq!(
+            loaded.entities[0].properties,
+            vec![("solid".to_string(), PropertyValue::Bool(true))]
+        );
+        assert_eq!(loaded.environment, scene().environment);
+        assert_eq!(loaded.weather, WeatherSettings::snow());
+        assert!(loaded.entities[1].has_tag("breakable"));
+        assert!(loaded.entities[0].components.is_empty());
+    }
+
+    #[test]
+    fn world_transforms_follow_parents() {
+        let mut scene = scene();
+        scene
+            .entities
+            .insert(0, SceneEntity::new("lid", Transform::default()));
+        scene.entities[0]
+            .transform
+            .set_position(Vector3::new(0.0, 1.0, 0.0));
+        scene.set_parent(0, Some(2)).unwrap();
+        assert!(scene.set_parent(2, Some(0)).is_err());
+
+        assert_eq!(scene.update_transforms(), 3);
+        assert_eq!(
+            scene.entities[0].world_position(),
+            Vector3::new(5.0, 1.0, 0.0)
+        );
+        assert_eq!(scene.update_transforms(), 0);
+
+        scene.entities[2]
+            .transform
+            .set_position(Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(scene.update_transforms(), 2);
+        assert!(scene.entities[0].world_changed());
+        assert!(!scene.entities[1].world_changed());
+        assert_eq!(
+            scene.entities[0].world_position(),
+            Vector3::new(2.0, 1.0, 0.0)
+        );
+
+        let loaded = Scene::from_ron(&scene.to_ron().unwrap()).unwrap();
+        assert_eq!(loaded.entities[0].parent(), Some(2));
+        assert_eq!(
+            loaded.entities[0].world_position(),
+            Vector3::new(2.0, 1.0, 0.0)
+        );
+
+        scene.retain_entities(|e| e.name != "near");
+        assert_eq!(scene.entities[0].parent(), Some(1));
+        assert_eq!(scene.children(1).collect::<Vec<_>>(), vec![0]);
+    }
+
+    #[test]
+    fn query_filters_by_tag_and_components() {
+        let mut scene = scene();
+        assert_eq!(scene.tagged("crate").count(), 2);
+        assert_eq!(
+            scene
+                .tagged("breakable")
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>(),
+            vec![1]
+        );
+
+        let both: Vec<_> = scene.query::<(&Sprite, &Health)>().collect();
+        assert_eq!(both.len(), 1);
+        assert_eq!(both[0].0, 0);
+        assert_eq!((both[0].2).1, &Health(10));
+
+        for (_, transform, health) in scene.query_mut::<Health>() {
+            health.0 -= 1;
+            transform.set_position(transform.position() + Vector3::unit_y());
+        }
+        let healths: Vec<_> = scene.query::<&Health>().map(|(_, _, h)| h.0).collect();
+        assert_eq!(healths, vec![9, 2]);
+        assert_eq!(scene.entities[1].transform.position().y, 1.0);
+
+        assert_eq!(
+            scene.entities[0].components.remove::<Health>(),
+            Some(Health(9))
+        );
+        assert_eq!(scene.query::<&Health>().count(), 1);
+    }
+
+    #[test]
+    fn pick_returns_closest_entity() {
+        let scene = scene();
+        let along_x = Ray::new(Point3::new(10.0, 0.0, 0.0), -Vector3::unit_x());
+        assert_eq!(scene.pick(&along_x), Some(1));
+
+        let miss = Ray::new(Point3::new(0.0, 10.0, 0.0), Vector3::unit_x());
+        assert_eq!(scene.pick(&miss), None);
+    }
+}
@@ -0,0 +1,80 @@
+#[cfg(all(test, feature = "scripting"))]
+mod tests {
+    use crate::eng::script::{ScriptCommand, ScriptHost};
+
+    const INTRO: &str = r#"
+        move_camera([0, 2, 10], [0, 0, 0]);
+        spawn_entity("guard", [1.5, 0, -2]);
+        wait(1);
+        say("Guard", "Halt!");
+        play_sound("alarm", 0.5);
+        if !has_flag("met_guard") { set_flag("met_guard", true); }
+        move_camera([0, 2, 5], [0, 0, 0], 2.0);
+
+        fn answer(choice) {
+            if choice == 0 { say("Guard", "Go on then."); }
+            flag("met_guard")
+        }
+    "#;
+
+    #[test]
+    fn cutscene_waits_on_time_and_text_then_dialogue_calls_back() {
+        let mut host = ScriptHost::new();
+        host.load("intro", INTRO).unwrap();
+        host.play("intro").unwrap();
+        assert_eq!(host.flag("met_guard").unwrap().as_bool(), Ok(true));
+
+        let first = host.update(0.5);
+        assert_eq!(first.len(), 2);
+        assert_eq!(
+            first[1],
+            ScriptCommand::Spawn {
+                name: String::from("guard"),
+                position: [1.5, 0.0, -2.0],
+            }
+        );
+        let text = host.update(0.6);
+        assert!(
+            matches!(&text[..], [ScriptCommand::ShowText { speaker: Some(s), .. }] if s == "Guard")
+        );
+        assert!(host.update(5.0).is_empty());
+
+        host.advance();
+        let rest = host.update(0.0);
+        assert_eq!(rest.len(), 2);
+        assert!(!host.is_playing());
+
+        let met = host.call("intro", "answer", (0_i64,)).unwrap();
+        assert_eq!(met.as_bool(), Ok(true));
+        assert!(host.is_playing());
+        assert_eq!(host.skip(), []);
+
+        host.load("night", "set_time_of_day(22, 3.0);").unwrap();
+        host.play("night").unwrap();
+        assert_eq!(
+            host.update(0.0),
+            [ScriptCommand::SetTimeOfDay {
+                hours: 22.0,
+                duration: 3.0,
+            }]
+        );
+
+        host.load(
+            "settings",
+            "set_config(\"accessibility.reduced_flash\", true);",
+        )
+        .unwrap();
+        host.play("settings").unwrap();
+        assert_eq!(
+            host.update(0.0),
+            [ScriptCommand::SetConfig {
+                key: String::from("accessibility.reduced_flash"),
+                value: String::from("true"),
+            }]
+        );
+
+        assert!(host.load("broken", "spawn_entity(").is_err());
+        host.load("loop", "loop {}").unwrap();
+        assert!(host.play("loop").is_err());
+    }
+}
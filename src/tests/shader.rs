@@ -0,0 +1,30 @@
+#[cfg(test)]
+mod tests {
+    use crate::gfx::wgpu::shader::{check_wgsl, UNLIT_WGSL};
+
+    #[test]
+    fn builtin_shaders_compile() {
+        check_wgsl("unlit.wgsl", UNLIT_WGSL).unwrap();
+        check_wgsl("basic.wgsl", include_str!("../shaders/basic.wgsl")).unwrap();
+        check_wgsl("overlay.wgsl", include_str!("../shaders/overlay.wgsl")).unwrap();
+    }
+
+    #[test]
+    fn parse_error_has_line_numbers() {
+        let source =
+            "@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n  return vec4<f32>(1.0) +;\n}\n";
+        let e = check_wgsl("broken.wgsl", source).unwrap_err();
+        let (line, column) = e.location.unwrap();
+        assert_eq!(line, 3);
+        assert!(column > 1);
+        assert!(e.to_string().starts_with("broken.wgsl:3:"));
+        assert!(e.report.contains("broken.wgsl"));
+    }
+
+    #[test]
+    fn validation_error_has_line_numbers() {
+        let source = "@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n  let x: f32 = 1u;\n  return vec4<f32>(x);\n}\n";
+        let e = check_wgsl("typed.wgsl", source).unwrap_err();
+        assert_eq!(e.location.map(|l| l.0), Some(3));
+    }
+}
@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod tests {
+    use cgmath::{Deg, Point3, Transform, Vector3, Vector4};
+
+    use crate::gfx::{
+        camera::{Camera, Projection},
+        shadow::{cascade_splits, fit_cascade},
+    };
+
+    #[test]
+    fn splits_run_from_near_to_far_and_favour_the_camera() {
+        let splits = cascade_splits(0.1, 100.0, 4, 0.75);
+        assert_eq!(splits.len(), 5);
+        assert!((splits[0] - 0.1).abs() < 1e-5 && (splits[4] - 100.0).abs() < 1e-3);
+        assert!(splits.windows(2).all(|w| w[0] < w[1]));
+        let uniform = cascade_splits(0.1, 100.0, 4, 0.0);
+        assert!(splits[1] < uniform[1]);
+    }
+
+    #[test]
+    fn cascades_are_texel_snapped_and_keep_their_size_as_the_camera_turns() {
+        let projection = Projection::new(1600, 900, Deg(60.0), 0.1, 100.0);
+        let light = Vector3::new(-0.4, -1.0, 0.3);
+        let fit = |camera: Camera| {
+            fit_cascade(
+                camera.calc_view_matrix(),
+                projection.calc_matrix(),
+                (0.1, 12.0),
+                light,
+                1024,
+                50.0,
+            )
+        };
+
+        let a = fit(Camera::new((0.0, 2.0, 0.0), Deg(-90.0), Deg(-10.0)));
+        let b = fit(Camera::new((0.013, 2.0, 0.007), Deg(-90.0), Deg(-10.0)));
+        let c = fit(Camera::new((0.0, 2.0, 0.0), Deg(-35.0), Deg(-10.0)));
+        assert_eq!(a.texel_size, c.texel_size);
+        for cascade in [a, b, c] {
+            let origin = cascade.view_proj * Vector4::new(0.0, 0.0, 0.0, 1.0);
+            let texel = origin.truncate().truncate() * 512.0;
+            assert!((texel.x - texel.x.round()).abs() < 1e-2);
+            assert!((texel.y - texel.y.round()).abs() < 1e-2);
+        }
+
+        // A point 5 units in front of the camera lands inside the cascade.
+        let p = a.view_proj.transform_point(Point3::new(0.0, 1.2, -5.0));
+        assert!(p.x.abs() < 1.0 && p.y.abs() < 1.0 && (0.0..1.0).contains(&p.z));
+    }
+}
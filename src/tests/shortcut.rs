@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod tests {
+    use winit::keyboard::{KeyCode, ModifiersState};
+
+    use crate::eng::{
+        context::EngineContext,
+        input::shortcut::{parse_chord, Chord, ShortcutContext, Shortcuts},
+    };
+
+    #[test]
+    fn chords_parse_and_print() {
+        let save = parse_chord("ctrl + s").unwrap();
+        assert_eq!(save, Chord::new(KeyCode::KeyS).ctrl());
+        assert_eq!(save.to_string(), "Ctrl+S");
+        let chord = Chord::new(KeyCode::Backquote).shift().alt().logo();
+        assert_eq!(parse_chord(&chord.to_string()).unwrap(), chord);
+        assert_eq!(parse_chord("F11").unwrap(), Chord::new(KeyCode::F11));
+        assert!(parse_chord("Hyper+S").is_err());
+        assert!(parse_chord("Ctrl+").is_err());
+    }
+
+    #[test]
+    fn priority_and_context_pick_the_action() {
+        let mut shortcuts = Shortcuts::new();
+        let w = Chord::new(KeyCode::KeyW);
+        shortcuts.register("game.forward", w, ShortcutContext::Gameplay, 0);
+        shortcuts.register("editor.translate", w, ShortcutContext::Editor, 10);
+        shortcuts.register("console.toggle", w, ShortcutContext::Console, 20);
+        assert_eq!(shortcuts.resolve(w), Some("game.forward"));
+
+        shortcuts.set_active(ShortcutContext::Editor, true);
+        assert_eq!(shortcuts.resolve(w), Some("editor.translate"));
+        // Global can't be switched off.
+        shortcuts.set_active(ShortcutContext::Global, false);
+        assert!(shortcuts.is_active(ShortcutContext::Global));
+
+        // Modifiers have to match exactly.
+        shortcuts.register("save", w.ctrl(), ShortcutContext::Global, 0);
+        shortcuts.set_modifiers(ModifiersState::CONTROL | ModifiersState::SHIFT);
+        assert_eq!(shortcuts.handle_key(KeyCode::KeyW, false), None);
+        shortcuts.set_modifiers(ModifiersState::CONTROL);
+        let hit = shortcuts.handle_key(KeyCode::KeyW, false).unwrap();
+        assert_eq!(hit.action, "save");
+        assert!(shortcuts.handle_key(KeyCode::KeyW, true).unwrap().repeat);
+        assert_eq!(shortcuts.triggered(), ["save"]);
+        shortcuts.end_frame();
+        assert!(!shortcuts.was_triggered("save"));
+
+        assert!(shortcuts.rebind("save", Chord::new(KeyCode::F2)));
+        assert_eq!(shortcuts.chord("save"), Some(Chord::new(KeyCode::F2)));
+        assert!(shortcuts.unregister("save"));
+        assert!(!shortcuts.rebind("save", w));
+
+        // Registering an action again replaces its binding.
+        let engine = EngineContext::new();
+        let mut shortcuts = engine.shortcuts().borrow_mut();
+        shortcuts.register("a", w, ShortcutContext::Global, 0);
+        shortcuts.register("a", w.shift(), ShortcutContext::Global, 0);
+        assert_eq!(shortcuts.shortcuts().len(), 1);
+    }
+}
@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc, time::Duration};
+
+    use crate::{
+        eng::state::{GameState, StateStack, Trans},
+        gfx::{draw::DrawCtx, wgpu::surface::SurfaceError},
+    };
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    struct TestState {
+        name: &'static str,
+        log: Log,
+        next: Option<Trans>,
+    }
+
+    impl TestState {
+        fn boxed(name: &'static str, log: &Log, next: Option<Trans>) -> Box<dyn GameState> {
+            Box::new(Self {
+                name,
+                log: log.clone(),
+                next,
+            })
+        }
+
+        fn record(&self, event: &str) {
+            self.log
+                .borrow_mut()
+                .push(format!("{}:{}", self.name, event));
+        }
+    }
+
+    impl GameState for TestState {
+        fn on_enter(&mut self) {
+            self.record("enter");
+        }
+        fn on_exit(&mut self) {
+            self.record("exit");
+        }
+        fn on_pause(&mut self) {
+            self.record("pause");
+        }
+        fn on_resume(&mut self) {
+            self.record("resume");
+        }
+        fn draw_frame(&mut self, _ctx: &mut DrawCtx) -> Result<(), SurfaceError> {
+            Ok(())
+        }
+        fn frame_update(&mut self, _dt: Duration) -> Trans {
+            self.record("update");
+            self.next.take().unwrap_or(Trans::None)
+        }
+    }
+
+    #[test]
+    fn push_pop() {
+        let log = Log::default();
+        let pause = TestState::boxed("pause", &log, Some(Trans::Pop));
+        let mut stack = StateStack::new(TestState::boxed("game", &log, Some(Trans::Push(pause))));
+
+        stack.update(Duration::ZERO);
+        assert_eq!(stack.len(), 2);
+        stack.update(Duration::ZERO);
+        assert_eq!(stack.len(), 1);
+
+        assert_eq!(
+            *log.borrow(),
+            [
+                "game:enter",
+                "game:update",
+                "game:pause",
+                "pause:enter",
+                "pause:update",
+                "pause:exit",
+                "game:resume",
+            ]
+        );
+    }
+
+    #[test]
+    fn replace_and_switch() {
+        let log = Log::default();
+        let mut stack = StateStack::new(TestState::boxed("menu", &log, None));
+        stack.replace(TestState::boxed("game", &log, None));
+        stack.push(TestState::boxed("pause", &log, None));
+        stack.switch(TestState::boxed("menu2", &log, None));
+
+        assert_eq!(stack.len(), 1);
+        assert_eq!(
+            *log.borrow(),
+            [
+                "menu:enter",
+                "menu:exit",
+                "game:enter",
+                "game:pause",
+                "pause:enter",
+                "pause:exit",
+                "game:exit",
+                "menu2:enter",
+            ]
+        );
+    }
+}
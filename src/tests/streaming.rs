@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use crate::gfx::streaming::{
+        mip_for_screen_size, plan_residency, MipResidency, StreamingSettings,
+    };
+
+    fn texture(last_used: u64, wanted: u32) -> MipResidency {
+        // 1024x1024 with 11 mips, the floor at 64 pixels is mip 4.
+        MipResidency {
+            wanted,
+            last_used,
+            ..MipResidency::new(1024, 1024, 11, 64)
+        }
+    }
+
+    #[test]
+    fn mip_follows_screen_size() {
+        assert_eq!(mip_for_screen_size(1024, 2048.0), 0);
+        assert_eq!(mip_for_screen_size(1024, 1024.0), 0);
+        assert_eq!(mip_for_screen_size(1024, 256.0), 2);
+        assert_eq!(mip_for_screen_size(1024, 0.0), u32::MAX);
+        assert_eq!(MipResidency::new(1024, 1024, 11, 64).floor, 4);
+        assert_eq!(MipResidency::new(32, 32, 6, 64).floor, 0);
+    }
+
+    #[test]
+    fn budget_evicts_least_recently_used_first() {
+        let settings = StreamingSettings {
+            budget: u64::MAX,
+            evict_after: 10,
+            max_uploads_per_update: 8,
+            ..Default::default()
+        };
+        let textures = [texture(100, 0), texture(95, 0), texture(50, 0)];
+        // Not requested for longer than evict_after, back to the floor.
+        assert_eq!(plan_residency(&textures, &settings, 100), vec![0, 0, 4]);
+
+        let full = textures[0].bytes(0);
+        let settings = StreamingSettings {
+            budget: full + textures[1].bytes(1) + textures[2].bytes(4),
+            ..settings
+        };
+        assert_eq!(plan_residency(&textures, &settings, 100), vec![0, 1, 4]);
+    }
+
+    #[test]
+    fn uploads_are_throttled_to_most_recent() {
+        let settings = StreamingSettings {
+            max_uploads_per_update: 1,
+            ..Default::default()
+        };
+        let textures = [texture(9, 0), texture(10, 2)];
+        assert_eq!(plan_residency(&textures, &settings, 10), vec![4, 2]);
+    }
+}
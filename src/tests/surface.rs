@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use wgpu::{CompositeAlphaMode, TextureFormat};
+
+    use crate::{
+        eng::app::EngineConfig,
+        gfx::wgpu::surface::{transparent_alpha_mode, SurfaceFormat, SurfaceOptions},
+    };
+
+    #[test]
+    fn surface_options_parse_and_fall_back_to_capabilities() {
+        let config = EngineConfig::parse(
+            "surface.format = Bgra8Unorm\n\
+             surface.alpha_mode = pre_multiplied\n\
+             surface.view_formats = Bgra8UnormSrgb, Rgba8Unorm",
+        )
+        .unwrap();
+        assert_eq!(
+            config.surface.format,
+            SurfaceFormat::Exact(TextureFormat::Bgra8Unorm)
+        );
+        assert_eq!(EngineConfig::parse(&config.to_string()).unwrap(), config);
+        assert!(EngineConfig::parse("surface.format = R8Unorm").is_err());
+
+        let caps = wgpu::SurfaceCapabilities {
+            formats: vec![TextureFormat::Bgra8UnormSrgb, TextureFormat::Bgra8Unorm],
+            alpha_modes: vec![
+                CompositeAlphaMode::Opaque,
+                CompositeAlphaMode::PreMultiplied,
+            ],
+            ..Default::default()
+        };
+        let chosen = config.surface.choose(&caps);
+        assert_eq!(chosen.format, TextureFormat::Bgra8Unorm);
+        assert_eq!(chosen.alpha_mode, CompositeAlphaMode::PreMultiplied);
+        // Rgba8Unorm isn't a view of a Bgra8 surface.
+        assert_eq!(chosen.view_formats, [TextureFormat::Bgra8UnormSrgb]);
+
+        let unsupported = SurfaceOptions {
+            format: SurfaceFormat::Exact(TextureFormat::Rgba16Float),
+            alpha_mode: CompositeAlphaMode::PostMultiplied,
+            view_formats: Vec::new(),
+        };
+        let chosen = unsupported.choose(&caps);
+        assert_eq!(chosen.format, TextureFormat::Bgra8UnormSrgb);
+        assert_eq!(chosen.alpha_mode, CompositeAlphaMode::Opaque);
+    }
+
+    #[test]
+    fn overlay_window_options() {
+        let config = EngineConfig::parse(
+            "window.transparent = true
+             window.always_on_top = true
+             window.decorations = false",
+        )
+        .unwrap();
+        assert!(config.window.transparent && config.window.always_on_top);
+        assert!(!config.window.decorations && !config.window.click_through);
+        assert_eq!(EngineConfig::parse(&config.to_string()).unwrap(), config);
+
+        let mut caps = wgpu::SurfaceCapabilities {
+            alpha_modes: vec![
+                CompositeAlphaMode::Opaque,
+                CompositeAlphaMode::PostMultiplied,
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            transparent_alpha_mode(&caps),
+            CompositeAlphaMode::PostMultiplied
+        );
+        caps.alpha_modes = vec![CompositeAlphaMode::Opaque];
+        assert_eq!(transparent_alpha_mode(&caps), CompositeAlphaMode::Opaque);
+    }
+}
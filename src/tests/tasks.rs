@@ -0,0 +1,83 @@
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc, time::Duration};
+
+    use crate::eng::{context::EngineContext, tasks::Tasks};
+
+    #[test]
+    fn handle() {
+        let tasks = Tasks::new(1);
+        let mut handle = tasks.spawn(async { 21 * 2 });
+        let mut blocking = tasks.spawn_blocking(|| String::from("decoded"));
+
+        let (mut result, mut decoded) = (None, None);
+        for _ in 0..200 {
+            result = result.or_else(|| handle.try_take());
+            decoded = decoded.or_else(|| blocking.try_take());
+            if result.is_some() && decoded.is_some() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(result, Some(42));
+        assert!(handle.is_finished());
+        assert_eq!(handle.try_take(), None);
+        assert_eq!(decoded, Some(String::from("decoded")));
+    }
+
+    #[test]
+    fn callbacks_run_on_poll() {
+        let engine = EngineContext::new();
+        let got = Rc::new(Cell::new(0));
+        let got_cb = got.clone();
+        engine
+            .tasks()
+            .spawn_then(async { 7 }, move |v| got_cb.set(v));
+        assert_eq!(engine.tasks().pending(), 1);
+
+        for _ in 0..200 {
+            engine.begin_frame(Duration::from_millis(16));
+            if engine.tasks().pending() == 0 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(got.get(), 7);
+        assert_eq!(engine.tasks().pending(), 0);
+
+        // A panicking task drops its callback instead of leaving it pending forever.
+        engine
+            .tasks()
+            .spawn_then(async { panic!("task failed") }, |_: ()| unreachable!());
+        for _ in 0..200 {
+            engine.tasks().poll();
+            if engine.tasks().pending() == 0 {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(engine.tasks().pending(), 0);
+    }
+
+    #[test]
+    fn spawn_inside_main_runtime() {
+        // The engine runs inside main's current thread runtime, spawning from there must
+        // neither panic nor depend on that runtime making progress.
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let got = runtime.block_on(async {
+            let tasks = Tasks::new(1);
+            let mut handle = tasks.spawn(async { 5 });
+            for _ in 0..200 {
+                if let Some(v) = handle.try_take() {
+                    return Some(v);
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+            None
+        });
+        assert_eq!(got, Some(5));
+    }
+}
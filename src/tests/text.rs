@@ -0,0 +1,25 @@
+#[cfg(all(test, feature = "ttf"))]
+mod tests {
+    use crate::gfx::text::{union_rect, GlyphAtlas};
+
+    #[test]
+    fn dirty_regions_grow_to_cover_every_glyph() {
+        assert_eq!(union_rect(None, [4, 2, 8, 8]), [4, 2, 8, 8]);
+        assert_eq!(
+            union_rect(Some([4, 2, 8, 8]), [0, 20, 2, 3]),
+            [0, 2, 12, 21]
+        );
+        assert_eq!(
+            union_rect(Some([0, 0, 16, 16]), [2, 2, 4, 4]),
+            [0, 0, 16, 16]
+        );
+    }
+
+    #[test]
+    fn invalid_font_data_is_an_error() {
+        let err = GlyphAtlas::new(b"not a font".to_vec(), 16.0, 64).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("GlyphAtlas::new => invalid font"));
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use winit::keyboard::KeyCode;
+
+    use crate::eng::input::text::{LocalClipboard, TextEditState};
+
+    #[test]
+    fn typing_and_deleting() {
+        let mut clipboard = LocalClipboard::default();
+        let mut edit = TextEditState::new();
+        for c in "héllo".chars() {
+            edit.handle_char(c);
+        }
+        assert_eq!(edit.text(), "héllo");
+        assert_eq!(edit.cursor_char(), 5);
+
+        edit.handle_key(KeyCode::ArrowLeft, &mut clipboard);
+        edit.handle_key(KeyCode::Backspace, &mut clipboard);
+        assert_eq!(edit.text(), "hélo");
+
+        edit.handle_key(KeyCode::Home, &mut clipboard);
+        edit.handle_key(KeyCode::Delete, &mut clipboard);
+        assert_eq!(edit.text(), "élo");
+        assert!(!edit.handle_char('\u{8}'));
+    }
+
+    #[test]
+    fn selection_and_clipboard() {
+        let mut clipboard = LocalClipboard::default();
+        let mut edit = TextEditState::with_text("hello world");
+        edit.set_cursor(0, false);
+        edit.set_cursor(5, true);
+        assert_eq!(edit.selected_text(), Some("hello"));
+
+        edit.cut(&mut clipboard);
+        assert_eq!(edit.text(), " world");
+
+        edit.set_cursor(edit.text().len(), false);
+        edit.paste(&mut clipboard);
+        assert_eq!(edit.text(), " worldhello");
+
+        edit.select_all();
+        edit.insert("x");
+        assert_eq!(edit.text(), "x");
+    }
+
+    #[test]
+    fn max_len() {
+        let mut edit = TextEditState::new().with_max_len(3);
+        edit.insert("abcdef");
+        assert_eq!(edit.text(), "abc");
+        assert!(edit.handle_char('d'));
+        assert_eq!(edit.text(), "abc");
+    }
+}
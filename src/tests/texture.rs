@@ -0,0 +1,26 @@
+#[cfg(test)]
+mod tests {
+    use crate::gfx::wgpu::texture::{error_image, TextureType, ERROR_CELL, FLAT_NORMAL};
+
+    #[test]
+    fn error_image_is_a_checkerboard() {
+        let img = error_image(TextureType::Diffuse);
+        assert_eq!(img.dimensions(), (ERROR_CELL * 4, ERROR_CELL * 4));
+        let magenta = image::Rgba([255, 0, 255, 255]);
+        let black = image::Rgba([0, 0, 0, 255]);
+        assert_eq!(*img.get_pixel(0, 0), magenta);
+        assert_eq!(*img.get_pixel(ERROR_CELL - 1, ERROR_CELL - 1), magenta);
+        assert_eq!(*img.get_pixel(ERROR_CELL, 0), black);
+        assert_eq!(*img.get_pixel(0, ERROR_CELL), black);
+        assert_eq!(*img.get_pixel(ERROR_CELL, ERROR_CELL), magenta);
+        let magentas = img.pixels().filter(|p| **p == magenta).count();
+        assert_eq!(magentas * 2, img.pixels().count());
+    }
+
+    #[test]
+    fn error_normal_is_flat() {
+        let img = error_image(TextureType::Normal);
+        assert_eq!(img.dimensions(), (1, 1));
+        assert_eq!(img.get_pixel(0, 0).0, FLAT_NORMAL);
+    }
+}
@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cgmath::{Deg, InnerSpace};
+
+    use crate::gfx::{
+        environment::{Environment, Fog, Sky},
+        time_of_day::{TimeOfDay, TimeOfDayKey},
+    };
+
+    #[test]
+    fn sun_rises_peaks_and_sets_with_moon_at_night() {
+        let day = TimeOfDay::new(0.0, 24.0).with_sun_path(Deg(0.0), Deg(90.0));
+        assert!(day.sun_direction(6.0).y.abs() < 1e-5);
+        assert!((day.sun_direction(12.0).y - 1.0).abs() < 1e-5);
+        assert!(day.sun_direction(18.0).y.abs() < 1e-5);
+        assert!(day.sun_direction(0.0).y < -0.99);
+
+        let noon = day.sample(12.0);
+        assert!(noon.light.direction.y < -0.99);
+        assert_eq!(noon.key, TimeOfDayKey::noon());
+        // Moonlight still shines down.
+        let night = day.sample(0.0);
+        assert!(night.light.direction.y < -0.99);
+        assert_eq!(night.light.intensity, TimeOfDayKey::night().sun_intensity);
+
+        let between = day.sample(9.0).key;
+        let (dawn, noon) = (TimeOfDayKey::dawn(), TimeOfDayKey::noon());
+        assert!(
+            (between.sun_intensity - (dawn.sun_intensity + noon.sun_intensity) * 0.5).abs() < 1e-5
+        );
+        // Wraps from dusk back to night.
+        let late = day.sample(21.0).key;
+        let dusk = TimeOfDayKey::dusk();
+        assert!(late.sun_intensity < dusk.sun_intensity);
+        assert!(late.sun_intensity > TimeOfDayKey::night().sun_intensity);
+    }
+
+    #[test]
+    fn clock_fires_hooks_and_transitions() {
+        let mut day = TimeOfDay::new(5.0, 24.0)
+            .with_hook(6.0, "sunrise")
+            .with_hook(23.0, "midnight_bell");
+        assert!(day.update(Duration::from_millis(500)).is_empty());
+        assert_eq!(day.update(Duration::from_millis(600)), vec!["sunrise"]);
+        assert!((day.hour() - 6.1).abs() < 1e-4);
+
+        day.set_paused(true);
+        assert!(day.update(Duration::from_secs(5)).is_empty());
+        assert!((day.hour() - 6.1).abs() < 1e-4);
+
+        day.transition_to(6.0, 2.0);
+        assert_eq!(
+            day.update(Duration::from_millis(1500)),
+            vec!["midnight_bell"]
+        );
+        day.update(Duration::from_secs(2));
+        assert!((day.hour() - 6.0).abs() < 1e-4);
+
+        day.set_hour(-1.0);
+        assert_eq!(day.hour(), 23.0);
+    }
+
+    #[test]
+    fn apply_sets_sky_ambient_and_fog() {
+        let day = TimeOfDay::new(18.0, 60.0).with_fog(true);
+        let env = day.apply(&Environment::default());
+        let dusk = TimeOfDayKey::dusk();
+        assert_eq!(env.ambient, dusk.ambient);
+        assert_eq!(
+            env.fog,
+            Fog::Exponential {
+                density: dusk.fog_density
+            }
+        );
+        assert!(matches!(env.sky, Sky::Gradient { horizon, .. } if horizon == dusk.horizon));
+
+        let preetham = Environment::default().with_sky(Sky::Preetham {
+            sun_direction: [0.0, 1.0, 0.0],
+            turbidity: 3.0,
+            exposure: 0.5,
+        });
+        let env = TimeOfDay::new(9.0, 60.0).apply(&preetham);
+        let Sky::Preetham {
+            sun_direction,
+            turbidity,
+            ..
+        } = env.sky
+        else {
+            panic!("sky changed kind");
+        };
+        assert_eq!(turbidity, 3.0);
+        assert!((cgmath::Vector3::from(sun_direction) - day.sun_direction(9.0)).magnitude() < 1e-5);
+        assert_eq!(TimeOfDay::new(12.0, 60.0).apply(&preetham).fog, Fog::None);
+    }
+}
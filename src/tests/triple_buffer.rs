@@ -0,0 +1,30 @@
+#[cfg(test)]
+mod tests {
+    use crate::sys::triple_buffer::triple_buffer;
+
+    #[test]
+    fn reader_sees_newest_snapshot_without_tearing() {
+        let (mut writer, mut reader) = triple_buffer(vec![0u32; 4]);
+        assert!(!reader.has_new());
+        writer.publish_from(&vec![1; 4]);
+        writer.publish_from(&vec![2; 4]);
+        assert!(reader.has_new());
+        assert_eq!(reader.read(), &[2; 4]);
+        assert_eq!(reader.read(), &[2; 4]);
+
+        let writes = 20_000u32;
+        let producer = std::thread::spawn(move || {
+            for frame in 3..writes {
+                writer.publish_with(|packet| packet.iter_mut().for_each(|v| *v = frame));
+            }
+        });
+        let mut last = 2;
+        while last != writes - 1 {
+            let packet = reader.read();
+            assert!(packet.iter().all(|v| *v == packet[0]), "torn snapshot");
+            assert!(packet[0] >= last, "went back in time");
+            last = packet[0];
+        }
+        producer.join().unwrap();
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        eng::app::EngineConfig,
+        gfx::tweak::{TweakPanel, TweakValue, TweakWidget},
+    };
+
+    #[test]
+    fn tweaks_round_trip_through_config() {
+        let config = EngineConfig::parse("tweak.bloom = 1.5\ntweak.fog = false\n").unwrap();
+        assert_eq!(config.tweaks.get("bloom"), Some(TweakValue::Float(1.5)));
+        assert_eq!(config.tweaks.get("fog"), Some(TweakValue::Bool(false)));
+        assert_eq!(
+            EngineConfig::parse(&config.to_string()).unwrap().tweaks,
+            config.tweaks
+        );
+    }
+
+    #[test]
+    fn panel_restores_and_drags_values() {
+        let config = EngineConfig::parse("tweak.bloom = 1.5").unwrap();
+        let mut panel = TweakPanel::new(config.tweaks).with_position([0.0, 0.0]);
+        let (mut bloom, mut fog) = (0.0, true);
+
+        panel.begin([0.0, 0.0], false);
+        assert!(panel.tweak_f32("bloom", &mut bloom, 0.0..2.0));
+        assert!(!panel.tweak_bool("fog", &mut fog));
+        assert_eq!(bloom, 1.5);
+        assert_eq!(
+            panel.rows()[0].widget,
+            TweakWidget::Slider { fraction: 0.75 }
+        );
+
+        // Press on the start of the slider track, then drag past its end.
+        let track = panel.rows()[0].rect;
+        panel.begin([track[0], track[1] + 1.0], true);
+        panel.tweak_f32("bloom", &mut bloom, 0.0..2.0);
+        panel.tweak_bool("fog", &mut fog);
+        assert_eq!(bloom, 0.0);
+        assert!(panel.wants_mouse());
+
+        panel.begin([track[0] + track[2] + 50.0, 300.0], true);
+        panel.tweak_f32("bloom", &mut bloom, 0.0..2.0);
+        assert_eq!(bloom, 2.0);
+
+        // Clicking the toggle row flips it.
+        panel.begin([5.0, panel.row_height + 1.0], false);
+        panel.begin([5.0, panel.row_height + 1.0], true);
+        panel.tweak_f32("bloom", &mut bloom, 0.0..2.0);
+        assert!(panel.tweak_bool("fog", &mut fog));
+        assert!(!fog);
+        assert_eq!(panel.values().get("fog"), Some(TweakValue::Bool(false)));
+        assert_eq!(panel.values().get("bloom"), Some(TweakValue::Float(2.0)));
+    }
+}
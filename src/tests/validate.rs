@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+    use crate::eng::{
+        command::RenderCommand,
+        validate::{validate_commands, PassLimits, PipelineShapes},
+    };
+
+    #[test]
+    fn reports_offending_commands_by_index() {
+        let shapes = PipelineShapes::new();
+        let limits = PassLimits {
+            attachment_size: Some([800, 600]),
+            max_bind_groups: 4,
+            max_immediate_size: 0,
+            shapes: &shapes,
+        };
+        let commands = [
+            RenderCommand::PushDebugGroup(String::from("Sprites")),
+            RenderCommand::SetScissorRect(0, 0, 800, 600),
+            RenderCommand::SetScissorRect(700, 0, 200, 600),
+            RenderCommand::Draw(0..6, 0..1),
+            RenderCommand::PopDebugGroup,
+            RenderCommand::PopDebugGroup,
+        ];
+
+        let errors = validate_commands("Main", &commands, limits);
+        let found: Vec<_> = errors.iter().map(|e| (e.index, e.command)).collect();
+        assert_eq!(
+            found,
+            [(2, "SetScissorRect"), (3, "Draw"), (5, "PopDebugGroup")]
+        );
+        assert!(errors[1]
+            .to_string()
+            .starts_with("RenderPass 'Main' command 3 (Draw)"));
+
+        // Without a known attachment size scissors aren't checked.
+        let errors = validate_commands(
+            "Offscreen",
+            &[
+                RenderCommand::SetScissorRect(700, 0, 200, 600),
+                RenderCommand::PushDebugGroup(String::from("Open")),
+            ],
+            PassLimits {
+                attachment_size: None,
+                ..limits
+            },
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].index, 1);
+        assert!(validate_commands("Empty", &[], limits).is_empty());
+
+        // Immediates need 4 byte alignment and room under the device limit.
+        let commands = [
+            RenderCommand::SetImmediates(0, vec![0; 16]),
+            RenderCommand::SetImmediates(2, vec![0; 4]),
+            RenderCommand::SetImmediates(64, vec![0; 8]),
+        ];
+        let limits = PassLimits {
+            max_immediate_size: 64,
+            ..limits
+        };
+        let errors = validate_commands("Immediates", &commands, limits);
+        let found: Vec<_> = errors.iter().map(|e| e.index).collect();
+        assert_eq!(found, [1, 2]);
+    }
+}
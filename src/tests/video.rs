@@ -0,0 +1,107 @@
+#[cfg(all(test, feature = "video"))]
+mod tests {
+    use std::time::Duration;
+
+    use image::{codecs::gif::GifEncoder, Delay, Frame, Rgba, RgbaImage};
+
+    use crate::gfx::video::{AnimatedImage, VideoPlayback, VideoSource};
+
+    /// Three 2x2 frames shown for 100ms each, colored 0, 1 and 2 in the red channel.
+    fn gif() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            for i in 0..3u8 {
+                let image = RgbaImage::from_pixel(2, 2, Rgba([i * 100, 0, 0, 255]));
+                let delay = Delay::from_numer_denom_ms(100, 1);
+                encoder
+                    .encode_frame(Frame::from_parts(image, 0, 0, delay))
+                    .unwrap();
+            }
+        }
+        bytes
+    }
+
+    fn red(playback: &VideoPlayback) -> u8 {
+        playback.current().unwrap().image.get_pixel(0, 0)[0]
+    }
+
+    #[test]
+    fn animated_images_decode_frame_by_frame() {
+        let mut video = AnimatedImage::from_bytes(gif()).unwrap();
+        assert_eq!(video.size(), (2, 2));
+        let frame = video.next_frame().unwrap().unwrap();
+        assert_eq!(frame.duration, Duration::from_millis(100));
+        assert_eq!(std::iter::from_fn(|| video.next_frame().unwrap()).count(), 2);
+
+        video.rewind().unwrap();
+        assert!(video.next_frame().unwrap().is_some());
+    }
+
+    #[test]
+    fn playback_follows_frame_delays() {
+        let source = AnimatedImage::from_bytes(gif()).unwrap();
+        let mut playback = VideoPlayback::new(Box::new(source));
+        assert!(playback.advance(Duration::ZERO).unwrap().is_some());
+        assert_eq!(red(&playback), 0);
+
+        assert!(playback.advance(Duration::from_millis(50)).unwrap().is_none());
+        // Skips straight to the third frame.
+        assert!(playback.advance(Duration::from_millis(160)).unwrap().is_some());
+        assert_eq!(red(&playback), 200);
+
+        playback.advance(Duration::from_millis(100)).unwrap();
+        assert!(playback.is_finished());
+        assert_eq!(red(&playback), 200);
+
+        playback.restart().unwrap();
+        playback.looping = true;
+        playback.advance(Duration::ZERO).unwrap();
+        playback.advance(Duration::from_millis(250)).unwrap();
+        playback.advance(Duration::from_millis(50)).unwrap();
+        assert!(!playback.is_finished());
+        assert_eq!(red(&playback), 0);
+    }
+
+    #[test]
+    fn long_hitches_drop_the_backlog() {
+        let source = AnimatedImage::from_bytes(gif()).unwrap();
+        let mut playback = VideoPlayback::new(Box::new(source));
+        playback.looping = true;
+        playback.advance(Duration::ZERO).unwrap();
+
+        // Ten seconds behind, only MAX_CATCH_UP frames are decoded and the rest dropped.
+        assert!(playback.advance(Duration::from_secs(10)).unwrap().is_some());
+        assert_eq!(red(&playback), 200);
+        // The newest frame then gets its full duration.
+        assert!(playback.advance(Duration::from_millis(90)).unwrap().is_none());
+        assert!(playback.advance(Duration::from_millis(10)).unwrap().is_some());
+        assert_eq!(red(&playback), 0);
+    }
+
+    #[cfg(feature = "ffmpeg")]
+    #[test]
+    fn ffmpeg_decodes_and_rewinds() {
+        use crate::gfx::video::FfmpegVideo;
+
+        // FFmpeg demuxes GIF too, which saves checking in a video file.
+        let path =
+            std::env::temp_dir().join(format!("radium_ffmpeg_{}.gif", std::process::id()));
+        std::fs::write(&path, gif()).unwrap();
+        let mut video = FfmpegVideo::open(&path).unwrap();
+        assert_eq!(video.size(), (2, 2));
+
+        let reds = |video: &mut FfmpegVideo| {
+            std::iter::from_fn(|| video.next_frame().unwrap())
+                .map(|f| {
+                    assert_eq!(f.duration, Duration::from_millis(100));
+                    f.image.get_pixel(0, 0)[0]
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(reds(&mut video), [0, 100, 200]);
+        video.rewind().unwrap();
+        assert_eq!(reds(&mut video), [0, 100, 200]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
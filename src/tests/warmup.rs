@@ -0,0 +1,31 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::eng::{loading::LoadProgress, warmup::WarmUp};
+
+    #[test]
+    fn warm_up_runs_within_budget_and_reports() {
+        let progress = LoadProgress::new();
+        let mut warm_up = WarmUp::<()>::new(&progress)
+            .with_budget(Duration::ZERO)
+            .with_step("pipelines", |_| Ok(3))
+            .with_step("broken", |_| anyhow::bail!("no device"))
+            .with_step("textures", |_| Ok(2));
+        assert_eq!(progress.total(), 3);
+
+        // A zero budget still runs one step per call.
+        assert!(!warm_up.run(&()));
+        assert_eq!(progress.completed(), 1);
+        assert!(!warm_up.run(&()));
+        assert!(warm_up.run(&()));
+        assert!(progress.is_done());
+
+        let report = warm_up.report();
+        let names: Vec<_> = report.entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["pipelines", "broken", "textures"]);
+        assert_eq!(report.entries[0].items, 3);
+        assert_eq!(report.failed().count(), 1);
+        assert!(report.to_string().contains("failed: no device"));
+    }
+}
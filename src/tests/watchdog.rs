@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::eng::watchdog::FrameWatchdog;
+
+    #[test]
+    fn long_frames_dump_scopes_counters_and_commands() {
+        let watchdog = FrameWatchdog::new(Duration::from_secs(60)).with_max_commands(2);
+        assert!(!watchdog.is_armed());
+        watchdog.begin_frame();
+        watchdog.record_command("stale");
+        assert!(!watchdog.is_armed());
+        assert!(watchdog.end_frame().is_none());
+
+        watchdog.set_threshold(Duration::from_nanos(1));
+        watchdog.begin_frame();
+        {
+            let _update = watchdog.scope("update");
+            let _ai = watchdog.scope("ai");
+        }
+        watchdog.set_counter("pending loads", 3);
+        watchdog.set_counter("pending loads", 5);
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(watchdog.is_armed());
+        watchdog.record_commands(["a", "b", "c"].map(String::from));
+
+        let dump = watchdog.end_frame().expect("frame over threshold");
+        assert_eq!(dump.frame, 1);
+        let scopes: Vec<_> = dump.scopes.iter().map(|s| (s.name, s.depth)).collect();
+        assert_eq!(scopes, [("ai", 1), ("update", 0)]);
+        assert_eq!(dump.counters, [("pending loads", 5)]);
+        assert_eq!(dump.commands, ["b", "c"]);
+        assert!(dump.to_string().contains("pending loads = 5"));
+        assert_eq!(watchdog.dumps(), 1);
+
+        // Without begin_frame there is nothing to time.
+        assert!(watchdog.end_frame().is_none());
+    }
+}
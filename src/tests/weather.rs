@@ -0,0 +1,76 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cgmath::Point3;
+
+    use crate::gfx::weather::{WeatherKind, WeatherSettings, WeatherState, Wind};
+
+    const STEP: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn rain_fills_budget_and_toggles_off() {
+        let viewer = Point3::new(0.0, 2.0, 0.0);
+        let mut state = WeatherState::new(WeatherSettings::rain().with_intensity(0.5), 200, 7)
+            .with_bounds(10.0, 20.0);
+        state.update(STEP, viewer);
+        assert_eq!(state.particles().len(), 100);
+        assert!(state
+            .particles()
+            .iter()
+            .all(|p| p.kind == WeatherKind::Rain));
+        assert!(state.droplets() > 0.0);
+        assert_eq!(state.accumulation(), 0.0);
+
+        for p in state.particles() {
+            assert!((p.position.x - viewer.x).abs() <= 10.0);
+            assert!(p.position.y >= viewer.y - 10.0 - 1.0);
+        }
+
+        assert!(!state.toggle());
+        for _ in 0..100 {
+            state.update(STEP, viewer);
+        }
+        assert!(state.particles().is_empty());
+        assert_eq!(state.droplets(), 0.0);
+    }
+
+    #[test]
+    fn snow_accumulates_and_melts() {
+        let mut settings = WeatherSettings::snow().with_intensity(1.0);
+        settings.accumulation_time = 1.0;
+        let mut state = WeatherState::new(settings, 10, 1);
+        for _ in 0..5 {
+            state.update(STEP, Point3::new(0.0, 0.0, 0.0));
+        }
+        assert!((state.accumulation() - 0.5).abs() < 1e-4);
+
+        state.set_settings(WeatherSettings {
+            accumulation_time: 1.0,
+            ..WeatherSettings::clear()
+        });
+        for _ in 0..20 {
+            state.update(STEP, Point3::new(0.0, 0.0, 0.0));
+        }
+        assert_eq!(state.accumulation(), 0.0);
+    }
+
+    #[test]
+    fn wind_pushes_particles_and_never_reverses() {
+        let wind = Wind::new([0.0, 2.0], 4.0).with_gust(10.0, 1.0);
+        for i in 0..20 {
+            let v = wind.velocity(i as f32 * 0.05);
+            assert!(v.z >= 0.0 && v.x.abs() < 1e-6);
+        }
+        let uniform = wind.uniform(3.0);
+        assert_eq!(uniform.direction, [0.0, 1.0]);
+        assert_eq!(uniform.time, 3.0);
+
+        let settings = WeatherSettings::snow().with_wind(Wind::new([1.0, 0.0], 5.0));
+        let mut state = WeatherState::new(settings, 50, 3);
+        state.update(STEP, Point3::new(0.0, 0.0, 0.0));
+        state.update(STEP, Point3::new(0.0, 0.0, 0.0));
+        let mean = state.particles().iter().map(|p| p.velocity.x).sum::<f32>() / 50.0;
+        assert!(mean > 3.0);
+    }
+}